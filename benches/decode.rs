@@ -0,0 +1,106 @@
+//! Compares decoding integers with per-byte indexing and shifts against
+//! the `from_be_bytes` reads the crate uses now, and a Vec<f32> decoded
+//! element by element against the bulk slice path.
+//! Run with `cargo bench --bench decode`.
+use bin_buffer::*;
+use std::hint::black_box;
+use std::time::{ Duration, Instant };
+
+const COUNT: usize = 1_000_000;
+const ROUNDS: usize = 10;
+
+/// The ReadBuffer layout and decoders as they were before from_be_bytes.
+struct ShiftBuffer{
+    buffer: Buffer,
+    iter: usize,
+}
+
+fn shift_u64(buf: &mut ShiftBuffer) -> Option<u64>{
+    if buf.iter + 8 > buf.buffer.len() { return Option::None; }
+    let mut val: u64 = 0;
+    val += u64::from(buf.buffer[buf.iter]) << 56;
+    val += u64::from(buf.buffer[buf.iter + 1]) << 48;
+    val += u64::from(buf.buffer[buf.iter + 2]) << 40;
+    val += u64::from(buf.buffer[buf.iter + 3]) << 32;
+    val += u64::from(buf.buffer[buf.iter + 4]) << 24;
+    val += u64::from(buf.buffer[buf.iter + 5]) << 16;
+    val += u64::from(buf.buffer[buf.iter + 6]) << 8;
+    val += u64::from(buf.buffer[buf.iter + 7]);
+    buf.iter += 8;
+    Option::Some(val)
+}
+
+fn shift_u32(buf: &mut ShiftBuffer) -> Option<u32>{
+    if buf.iter + 4 > buf.buffer.len() { return Option::None; }
+    let mut val: u32 = 0;
+    val += u32::from(buf.buffer[buf.iter]) << 24;
+    val += u32::from(buf.buffer[buf.iter + 1]) << 16;
+    val += u32::from(buf.buffer[buf.iter + 2]) << 8;
+    val += u32::from(buf.buffer[buf.iter + 3]);
+    buf.iter += 4;
+    Option::Some(val)
+}
+
+fn time<S, F: FnMut(S) -> u64>(mut setup: impl FnMut() -> S, mut f: F) -> Duration{
+    let mut best = Duration::from_secs(u64::MAX);
+    for _ in 0..ROUNDS{
+        let input = setup();
+        let start = Instant::now();
+        black_box(f(input));
+        let elapsed = start.elapsed();
+        if elapsed < best { best = elapsed; }
+    }
+    best
+}
+
+fn compare(name: &str, old: Duration, new: Duration){
+    println!("{:<5} before: {:>10?}  after: {:>10?}  speedup: {:.2}x",
+        name, old, new, old.as_secs_f64() / new.as_secs_f64());
+}
+
+fn main(){
+    let mut encoded = Vec::new();
+    for i in 0..COUNT as u64 { i.into_buffer(&mut encoded); }
+    let old = time(|| ShiftBuffer{ buffer: encoded.clone(), iter: 0 }, |mut buffer|{
+        let buffer = black_box(&mut buffer);
+        let mut sum = 0u64;
+        while let Some(x) = shift_u64(buffer) { sum = sum.wrapping_add(x); }
+        sum
+    });
+    let new = time(|| ReadBuffer::from_raw(encoded.clone()), |mut buffer|{
+        let buffer = black_box(&mut buffer);
+        let mut sum = 0u64;
+        while let Some(x) = u64::from_buffer(buffer) { sum = sum.wrapping_add(x); }
+        sum
+    });
+    compare("u64", old, new);
+    let mut encoded = Vec::new();
+    for i in 0..COUNT as u32 { i.into_buffer(&mut encoded); }
+    let old = time(|| ShiftBuffer{ buffer: encoded.clone(), iter: 0 }, |mut buffer|{
+        let buffer = black_box(&mut buffer);
+        let mut sum = 0u64;
+        while let Some(x) = shift_u32(buffer) { sum += u64::from(x); }
+        sum
+    });
+    let new = time(|| ReadBuffer::from_raw(encoded.clone()), |mut buffer|{
+        let buffer = black_box(&mut buffer);
+        let mut sum = 0u64;
+        while let Some(x) = u32::from_buffer(buffer) { sum += u64::from(x); }
+        sum
+    });
+    compare("u32", old, new);
+    let mut encoded = Vec::new();
+    (0..COUNT).map(|i| i as f32).collect::<Vec<_>>().into_buffer(&mut encoded);
+    let old = time(|| ReadBuffer::from_raw(encoded.clone()), |mut buffer|{
+        let buffer = black_box(&mut buffer);
+        let len = u64::from_buffer(buffer).unwrap();
+        let mut vec = Vec::new();
+        for _ in 0..len { vec.push(f32::from_buffer(buffer).unwrap()); }
+        vec.len() as u64
+    });
+    let new = time(|| ReadBuffer::from_raw(encoded.clone()), |mut buffer|{
+        let buffer = black_box(&mut buffer);
+        Vec::<f32>::from_buffer(buffer).unwrap().len() as u64
+    });
+    compare("[f32]", old, new);
+}
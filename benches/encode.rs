@@ -0,0 +1,71 @@
+//! Compares encoding primitives with the old byte-by-byte `push` against
+//! the `to_be_bytes` + `extend_from_slice` writes the crate uses now,
+//! and a Vec<f32> written element by element against the bulk slice path.
+//! Run with `cargo bench --bench encode`.
+use bin_buffer::*;
+use std::hint::black_box;
+use std::time::{ Duration, Instant };
+
+const COUNT: usize = 1_000_000;
+const ROUNDS: usize = 10;
+
+fn push_u64(x: u64, vec: &mut Buffer){
+    vec.push(((x >> 56) & 0xff) as u8);
+    vec.push(((x >> 48) & 0xff) as u8);
+    vec.push(((x >> 40) & 0xff) as u8);
+    vec.push(((x >> 32) & 0xff) as u8);
+    vec.push(((x >> 24) & 0xff) as u8);
+    vec.push(((x >> 16) & 0xff) as u8);
+    vec.push(((x >> 8) & 0xff) as u8);
+    vec.push((x & 0xff) as u8);
+}
+
+fn push_u32(x: u32, vec: &mut Buffer){
+    vec.push(((x >> 24) & 0xff) as u8);
+    vec.push(((x >> 16) & 0xff) as u8);
+    vec.push(((x >> 8) & 0xff) as u8);
+    vec.push((x & 0xff) as u8);
+}
+
+fn push_f64(x: f64, vec: &mut Buffer){
+    for b in x.to_be_bytes().iter(){
+        vec.push(*b);
+    }
+}
+
+fn time<F: FnMut(&mut Buffer)>(mut f: F) -> Duration{
+    let mut best = Duration::from_secs(u64::MAX);
+    for _ in 0..ROUNDS{
+        let mut buffer = Vec::new();
+        let start = Instant::now();
+        f(&mut buffer);
+        let elapsed = start.elapsed();
+        black_box(&buffer);
+        if elapsed < best { best = elapsed; }
+    }
+    best
+}
+
+fn compare(name: &str, old: Duration, new: Duration){
+    println!("{:<5} before: {:>10?}  after: {:>10?}  speedup: {:.2}x",
+        name, old, new, old.as_secs_f64() / new.as_secs_f64());
+}
+
+fn main(){
+    let old = time(|b| for i in 0..COUNT as u64 { push_u64(black_box(i), b); });
+    let new = time(|b| for i in 0..COUNT as u64 { black_box(i).into_buffer(b); });
+    compare("u64", old, new);
+    let old = time(|b| for i in 0..COUNT as u32 { push_u32(black_box(i), b); });
+    let new = time(|b| for i in 0..COUNT as u32 { black_box(i).into_buffer(b); });
+    compare("u32", old, new);
+    let old = time(|b| for i in 0..COUNT { push_f64(black_box(i as f64), b); });
+    let new = time(|b| for i in 0..COUNT { black_box(i as f64).into_buffer(b); });
+    compare("f64", old, new);
+    let floats: Vec<f32> = (0..COUNT).map(|i| i as f32).collect();
+    let old = time(|b|{
+        (floats.len() as u64).into_buffer(b);
+        for x in &floats { for byte in x.to_be_bytes().iter() { b.push(*byte); } }
+    });
+    let new = time(|b| floats.copy_into_buffer(b));
+    compare("[f32]", old, new);
+}
@@ -0,0 +1,471 @@
+#![warn(missing_docs)]
+
+//! `#[derive(Bufferable)]` for bin_buffer, used through the `derive` feature of bin_buffer.
+//! Structs get the layout of bufferable_struct! and enums the one of bufferable_enum!,
+//! so derived and hand listed types can read each other's data.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{ parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Expr, Fields,
+    GenericArgument, Ident, Lit, LitInt, Member, PathArguments, Token, Type, WherePredicate };
+
+/// Implement Bufferable, writing fields in the order they are declared.
+/// Enum variants are written as a u32 discriminant followed by their fields. The
+/// discriminant is the one given with `#[bufferable(tag = N)]`, or `= N`, or one more than
+/// that of the variant before, starting at 0.
+/// Options, all in `#[bufferable(...)]`:
+/// - `bound(T: Trait, ...)` on the type: use these where clauses instead of a Bufferable
+///   bound on every type parameter.
+/// - `with = Encoding` on a field: write it with that fields::FieldEncoding,
+///   like `as Encoding` in bufferable_struct!.
+/// - `optional` on an `Option<T>` field of a struct: pack it behind the presence bitmap, after
+///   the other fields, like the `optional { ... }` group of bufferable_struct!.
+/// - `other` on the last variant of an enum, declared as `Other(u32, ByteVec)`: unknown
+///   discriminants are read into it, see bufferable_enum!.
+/// - `tagged` on a struct, with `tag = N` on every field: write it as a tagged::TaggedWriter
+///   message. Readers skip tags they do not know and default the fields that are missing,
+///   fields at their default are not written. The fields have to be Default and PartialEq.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// #[derive(Bufferable, Debug, PartialEq)]
+/// struct Player{ name: String, #[bufferable(with = fields::Varint)] score: u64 }
+/// #[derive(Bufferable, Debug, PartialEq)]
+/// enum Event{ Join(Player), Leave{ id: u32 }, Tick }
+/// let event = Event::Join(Player{ name: String::from("a"), score: 300 });
+/// let bytes = encode(&event);
+/// assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, b'a', 0xac, 0x02], bytes);
+/// assert_eq!(Some(event), decode(&bytes));
+/// assert_eq!(encode(&(2u32)), encode(&Event::Tick));
+/// ```
+#[proc_macro_derive(Bufferable, attributes(bufferable))]
+pub fn derive_bufferable(input: TokenStream) -> TokenStream{
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input){
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+// One field of a struct or variant.
+struct Field{
+    member: Member,
+    ty: Type,
+    enc: Type,
+    with: bool,
+    optional: bool,
+    tag: Option<u32>,
+    span: proc_macro2::Span,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error>{
+    let mut bound = Option::None;
+    let mut tagged = false;
+    for attr in bufferable_attrs(&input.attrs){
+        attr.parse_nested_meta(|meta|{
+            if meta.path.is_ident("bound"){
+                let content;
+                syn::parenthesized!(content in meta.input);
+                bound = Option::Some(Punctuated::<WherePredicate, Token![,]>::parse_terminated(&content)?);
+                Ok(())
+            } else if meta.path.is_ident("tagged"){
+                tagged = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected bound(...) or tagged"))
+            }
+        })?;
+    }
+    let mut generics = input.generics.clone();
+    {
+        let predicates = &mut generics.make_where_clause().predicates;
+        match bound{
+            Option::Some(bound) => predicates.extend(bound),
+            Option::None => for param in input.generics.type_params(){
+                let ident = &param.ident;
+                predicates.push(parse_quote!(#ident: ::bin_buffer::Bufferable));
+            },
+        }
+    }
+    let (impl_gen, ty_gen, where_clause) = generics.split_for_impl();
+    let name = &input.ident;
+    let body = match &input.data{
+        Data::Struct(data) if tagged => expand_tagged(&data.fields)?,
+        Data::Struct(data) => expand_struct(&data.fields)?,
+        Data::Enum(_) if tagged => return Err(Error::new(input.span(), "tagged is only for structs")),
+        Data::Enum(data) => expand_enum(name, &data.variants)?,
+        Data::Union(_) => return Err(Error::new(input.span(), "Bufferable can not be derived for unions")),
+    };
+    Ok(quote!{
+        impl #impl_gen ::bin_buffer::Bufferable for #name #ty_gen #where_clause{
+            #body
+        }
+    })
+}
+
+fn bufferable_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute>{
+    attrs.iter().filter(|attr| attr.path().is_ident("bufferable"))
+}
+
+fn fields_of(fields: &Fields) -> Result<Vec<Field>, Error>{
+    let mut out = Vec::new();
+    for (i, field) in fields.iter().enumerate(){
+        let member = match &field.ident{
+            Option::Some(ident) => Member::Named(ident.clone()),
+            Option::None => Member::Unnamed(i.into()),
+        };
+        let mut enc: Type = parse_quote!(::bin_buffer::fields::Plain);
+        let mut with = false;
+        let mut optional = false;
+        let mut tag = Option::None;
+        for attr in bufferable_attrs(&field.attrs){
+            attr.parse_nested_meta(|meta|{
+                if meta.path.is_ident("with"){
+                    enc = meta.value()?.parse()?;
+                    with = true;
+                    Ok(())
+                } else if meta.path.is_ident("optional"){
+                    optional = true;
+                    Ok(())
+                } else if meta.path.is_ident("tag"){
+                    tag = Option::Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected with = Encoding, optional or tag = N"))
+                }
+            })?;
+        }
+        let ty = if optional{
+            option_inner(&field.ty)
+                .ok_or_else(|| Error::new(field.ty.span(), "optional fields have to be an Option"))?
+        } else {
+            field.ty.clone()
+        };
+        out.push(Field{ member, ty, enc, with, optional, tag, span: field.span() });
+    }
+    Ok(out)
+}
+
+// T of Option<T>.
+fn option_inner(ty: &Type) -> Option<Type>{
+    let Type::Path(path) = ty else { return Option::None; };
+    let last = path.path.segments.last()?;
+    if last.ident != "Option"{
+        return Option::None;
+    }
+    let PathArguments::AngleBracketed(args) = &last.arguments else { return Option::None; };
+    match args.args.first()?{
+        GenericArgument::Type(ty) => Option::Some(ty.clone()),
+        _ => Option::None,
+    }
+}
+
+fn expand_struct(fields: &Fields) -> Result<TokenStream2, Error>{
+    let fields = fields_of(fields)?;
+    if let Option::Some(f) = fields.iter().find(|f| f.tag.is_some()){
+        return Err(Error::new(f.span, "tag = N on a field is for tagged structs"));
+    }
+    let (opt, req): (Vec<&Field>, Vec<&Field>) = fields.iter().partition(|f| f.optional);
+    let opt_count = opt.len();
+    let (rm, rty, renc) = (req.iter().map(|f| &f.member), req.iter().map(|f| &f.ty), req.iter().map(|f| &f.enc));
+    let (om, oty, oenc) = (opt.iter().map(|f| &f.member), opt.iter().map(|f| &f.ty), opt.iter().map(|f| &f.enc));
+    let (rm, rty, renc): (Vec<_>, Vec<_>, Vec<_>) = (rm.collect(), rty.collect(), renc.collect());
+    let (om, oty, oenc): (Vec<_>, Vec<_>, Vec<_>) = (om.collect(), oty.collect(), oenc.collect());
+    Ok(quote!{
+        const MIN_BUFFER_LEN: usize = ::bin_buffer::fields::Presence::byte_len(#opt_count)
+            #( + <#renc as ::bin_buffer::fields::FieldEncoding<#rty>>::MIN_LEN )*;
+
+        #[allow(unused_variables)]
+        fn into_buffer<S: ::bin_buffer::Sink + ?Sized>(self, vec: &mut S){
+            ::bin_buffer::fields::Presence::write(&[ #( self.#om.is_some() ),* ], vec);
+            #( <#renc as ::bin_buffer::fields::FieldEncoding<#rty>>::write_owned(self.#rm, vec); )*
+            #( if let Option::Some(x) = self.#om{
+                <#oenc as ::bin_buffer::fields::FieldEncoding<#oty>>::write_owned(x, vec);
+            } )*
+        }
+
+        #[allow(unused_variables)]
+        fn copy_into_buffer<S: ::bin_buffer::Sink + ?Sized>(&self, vec: &mut S){
+            ::bin_buffer::fields::Presence::write(&[ #( self.#om.is_some() ),* ], vec);
+            #( <#renc as ::bin_buffer::fields::FieldEncoding<#rty>>::write(&self.#rm, vec); )*
+            #( if let Option::Some(x) = &self.#om{
+                <#oenc as ::bin_buffer::fields::FieldEncoding<#oty>>::write(x, vec);
+            } )*
+        }
+
+        #[allow(unused_variables, unused_mut)]
+        fn from_buffer(buf: &mut ::bin_buffer::ReadBuffer) -> Option<Self>{
+            let mut presence = ::bin_buffer::fields::Presence::read(buf, #opt_count).ok()?;
+            Option::Some(Self{
+                #( #rm: <#renc as ::bin_buffer::fields::FieldEncoding<#rty>>::read(buf)?, )*
+                #( #om: if presence.next()?{
+                    Option::Some(<#oenc as ::bin_buffer::fields::FieldEncoding<#oty>>::read(buf)?)
+                } else {
+                    Option::None
+                }, )*
+            })
+        }
+
+        #[allow(unused_variables, unused_mut)]
+        fn validate(buf: &mut ::bin_buffer::ReadBuffer) -> Result<(), ::bin_buffer::BufferError>{
+            let mut presence = ::bin_buffer::fields::Presence::read(buf, #opt_count)?;
+            #( <#renc as ::bin_buffer::fields::FieldEncoding<#rty>>::validate(buf)?; )*
+            #( if presence.next() == Option::Some(true){
+                <#oenc as ::bin_buffer::fields::FieldEncoding<#oty>>::validate(buf)?;
+            } )*
+            Ok(())
+        }
+
+        fn buffer_len(&self) -> usize{
+            ::bin_buffer::fields::Presence::byte_len(#opt_count)
+                #( + <#renc as ::bin_buffer::fields::FieldEncoding<#rty>>::len(&self.#rm) )*
+                #( + self.#om.as_ref().map_or(0, <#oenc as ::bin_buffer::fields::FieldEncoding<#oty>>::len) )*
+        }
+    })
+}
+
+fn expand_tagged(fields: &Fields) -> Result<TokenStream2, Error>{
+    let fields = fields_of(fields)?;
+    let mut tags = Vec::new();
+    for f in &fields{
+        if f.optional{
+            return Err(Error::new(f.span, "optional is not for tagged structs, a missing tag already reads as the default"));
+        }
+        if f.with{
+            return Err(Error::new(f.span, "with = Encoding is not for tagged structs"));
+        }
+        let tag = f.tag.ok_or_else(|| Error::new(f.span, "every field of a tagged struct needs tag = N"))?;
+        if tags.contains(&tag){
+            return Err(Error::new(f.span, format!("tag {} is used twice", tag)));
+        }
+        tags.push(tag);
+    }
+    let members: Vec<_> = fields.iter().map(|f| &f.member).collect();
+    Ok(quote!{
+        const MIN_BUFFER_LEN: usize = 8;
+
+        fn into_buffer<S: ::bin_buffer::Sink + ?Sized>(self, vec: &mut S){
+            ::bin_buffer::Bufferable::copy_into_buffer(&self, vec);
+        }
+
+        fn copy_into_buffer<S: ::bin_buffer::Sink + ?Sized>(&self, vec: &mut S){
+            let mut writer = ::bin_buffer::tagged::TaggedWriter::new();
+            #( writer.field_or_skip(#tags, &self.#members); )*
+            writer.finish(vec);
+        }
+
+        #[allow(unused_variables)]
+        fn from_buffer(buf: &mut ::bin_buffer::ReadBuffer) -> Option<Self>{
+            let fields = <::bin_buffer::tagged::TaggedFields as ::bin_buffer::Bufferable>::from_buffer(buf)?;
+            Option::Some(Self{
+                #( #members: fields.get_or_default(#tags)?, )*
+            })
+        }
+
+        fn buffer_len(&self) -> usize{
+            8 #( + ::bin_buffer::tagged::TaggedWriter::field_or_skip_len(&self.#members) )*
+        }
+    })
+}
+
+// A variant with its discriminant, the names its fields are bound to and the code for them.
+struct Variant{
+    ident: Ident,
+    tag: u32,
+    fields: Vec<Field>,
+    binds: Vec<Ident>,
+}
+
+fn expand_enum(name: &Ident, variants: &Punctuated<syn::Variant, Token![,]>) -> Result<TokenStream2, Error>{
+    let mut known = Vec::new();
+    let mut other = Option::None;
+    let mut next = 0u32;
+    for (i, variant) in variants.iter().enumerate(){
+        let mut tag = Option::None;
+        let mut is_other = false;
+        for attr in bufferable_attrs(&variant.attrs){
+            attr.parse_nested_meta(|meta|{
+                if meta.path.is_ident("tag"){
+                    tag = Option::Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+                    Ok(())
+                } else if meta.path.is_ident("other"){
+                    is_other = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected tag = N or other"))
+                }
+            })?;
+        }
+        if is_other{
+            if i + 1 != variants.len() || variant.fields.len() != 2 || !matches!(variant.fields, Fields::Unnamed(_)){
+                return Err(Error::new(variant.span(), "the other variant comes last and is declared as Other(u32, ByteVec)"));
+            }
+            other = Option::Some(variant.ident.clone());
+            continue;
+        }
+        if tag.is_none(){
+            if let Option::Some((_, Expr::Lit(lit))) = &variant.discriminant{
+                if let Lit::Int(int) = &lit.lit{
+                    tag = Option::Some(int.base10_parse::<u32>()?);
+                }
+            }
+        }
+        let tag = tag.unwrap_or(next);
+        if known.iter().any(|v: &Variant| v.tag == tag){
+            return Err(Error::new(variant.span(), format!("discriminant {} is used twice", tag)));
+        }
+        next = tag.wrapping_add(1);
+        let fields = fields_of(&variant.fields)?;
+        if let Option::Some(f) = fields.iter().find(|f| f.optional){
+            return Err(Error::new(f.ty.span(), "optional is only for struct fields"));
+        }
+        if let Option::Some(f) = fields.iter().find(|f| f.tag.is_some()){
+            return Err(Error::new(f.span, "tag = N on a field is for tagged structs"));
+        }
+        let binds = (0..fields.len()).map(|i| quote::format_ident!("__field{}", i)).collect();
+        known.push(Variant{ ident: variant.ident.clone(), tag, fields, binds });
+    }
+    let pats: Vec<_> = known.iter().map(|v|{
+        let ident = &v.ident;
+        let members = v.fields.iter().map(|f| &f.member);
+        let binds = &v.binds;
+        quote!(#name::#ident{ #( #members: #binds ),* })
+    }).collect();
+    let tags: Vec<_> = known.iter().map(|v| v.tag).collect();
+    let write_owned: Vec<_> = known.iter().map(|v|{
+        let (ty, enc, binds) = (v.fields.iter().map(|f| &f.ty), v.fields.iter().map(|f| &f.enc), &v.binds);
+        quote!( #( <#enc as ::bin_buffer::fields::FieldEncoding<#ty>>::write_owned(#binds, vec); )* )
+    }).collect();
+    let write: Vec<_> = known.iter().map(|v|{
+        let (ty, enc, binds) = (v.fields.iter().map(|f| &f.ty), v.fields.iter().map(|f| &f.enc), &v.binds);
+        quote!( #( <#enc as ::bin_buffer::fields::FieldEncoding<#ty>>::write(#binds, vec); )* )
+    }).collect();
+    let read: Vec<_> = known.iter().map(|v|{
+        let ident = &v.ident;
+        let (ty, enc, members) = (v.fields.iter().map(|f| &f.ty), v.fields.iter().map(|f| &f.enc), v.fields.iter().map(|f| &f.member));
+        quote!( #name::#ident{ #( #members: <#enc as ::bin_buffer::fields::FieldEncoding<#ty>>::read(buf)?, )* } )
+    }).collect();
+    let validate: Vec<_> = known.iter().map(|v|{
+        let (ty, enc) = (v.fields.iter().map(|f| &f.ty), v.fields.iter().map(|f| &f.enc));
+        quote!( #( <#enc as ::bin_buffer::fields::FieldEncoding<#ty>>::validate(buf)?; )* )
+    }).collect();
+    let len: Vec<_> = known.iter().map(|v|{
+        let (ty, enc, binds) = (v.fields.iter().map(|f| &f.ty), v.fields.iter().map(|f| &f.enc), &v.binds);
+        quote!( #( + <#enc as ::bin_buffer::fields::FieldEncoding<#ty>>::len(#binds) )* )
+    }).collect();
+    let Option::Some(other) = other else {
+        return Ok(quote!{
+            const MIN_BUFFER_LEN: usize = ::bin_buffer::enums::VARIANT_LEN;
+
+            #[allow(unused_variables)]
+            fn into_buffer<S: ::bin_buffer::Sink + ?Sized>(self, vec: &mut S){
+                match self{
+                    #( #pats => {
+                        ::bin_buffer::enums::write_variant(#tags, vec);
+                        #write_owned
+                    }, )*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn copy_into_buffer<S: ::bin_buffer::Sink + ?Sized>(&self, vec: &mut S){
+                match self{
+                    #( #pats => {
+                        ::bin_buffer::enums::write_variant(#tags, vec);
+                        #write
+                    }, )*
+                }
+            }
+
+            fn from_buffer(buf: &mut ::bin_buffer::ReadBuffer) -> Option<Self>{
+                let variant = <u32 as ::bin_buffer::Bufferable>::from_buffer(buf)?;
+                #( if variant == #tags{
+                    return Option::Some(#read);
+                } )*
+                Option::None
+            }
+
+            fn validate(buf: &mut ::bin_buffer::ReadBuffer) -> Result<(), ::bin_buffer::BufferError>{
+                let variant = buf.read_checked::<u32>()?;
+                #( if variant == #tags{
+                    #validate
+                    return Ok(());
+                } )*
+                Err(::bin_buffer::BufferError::InvalidDiscriminant)
+            }
+
+            #[allow(unused_variables)]
+            fn buffer_len(&self) -> usize{
+                match self{
+                    #( #pats => ::bin_buffer::enums::VARIANT_LEN #len, )*
+                }
+            }
+        });
+    };
+    Ok(quote!{
+        const MIN_BUFFER_LEN: usize = ::bin_buffer::enums::VARIANT_LEN + 8;
+
+        #[allow(unused_variables)]
+        fn into_buffer<S: ::bin_buffer::Sink + ?Sized>(self, vec: &mut S){
+            match self{
+                #( #pats => {
+                    ::bin_buffer::enums::write_variant(#tags, vec);
+                    ::bin_buffer::enums::write_payload(vec, |vec|{ #write_owned });
+                }, )*
+                #name::#other(tag, payload) => {
+                    ::bin_buffer::enums::write_variant(tag, vec);
+                    ::bin_buffer::Bufferable::into_buffer(payload, vec);
+                },
+            }
+        }
+
+        #[allow(unused_variables)]
+        fn copy_into_buffer<S: ::bin_buffer::Sink + ?Sized>(&self, vec: &mut S){
+            match self{
+                #( #pats => {
+                    ::bin_buffer::enums::write_variant(#tags, vec);
+                    ::bin_buffer::enums::write_payload(vec, |vec|{ #write });
+                }, )*
+                #name::#other(tag, payload) => {
+                    ::bin_buffer::enums::write_variant(*tag, vec);
+                    ::bin_buffer::Bufferable::copy_into_buffer(payload, vec);
+                },
+            }
+        }
+
+        fn from_buffer(buf: &mut ::bin_buffer::ReadBuffer) -> Option<Self>{
+            let variant = <u32 as ::bin_buffer::Bufferable>::from_buffer(buf)?;
+            let end = ::bin_buffer::enums::read_payload_end(buf)?;
+            #( if variant == #tags{
+                let x = #read;
+                ::bin_buffer::enums::finish_payload(buf, end)?;
+                return Option::Some(x);
+            } )*
+            let len = end - buf.consumed();
+            buf.use_bytes(len)?;
+            Option::Some(#name::#other(variant, ::bin_buffer::ByteVec(buf.read_bytes(len)?.to_vec())))
+        }
+
+        fn validate(buf: &mut ::bin_buffer::ReadBuffer) -> Result<(), ::bin_buffer::BufferError>{
+            let variant = buf.read_checked::<u32>()?;
+            let end = ::bin_buffer::enums::check_payload_end(buf)?;
+            #( if variant == #tags{
+                #validate
+                return ::bin_buffer::enums::finish_payload(buf, end).ok_or(::bin_buffer::BufferError::Invalid);
+            } )*
+            let len = end - buf.consumed();
+            buf.use_bytes(len).ok_or(::bin_buffer::BufferError::LengthOverflow)?;
+            buf.skip(len)
+        }
+
+        #[allow(unused_variables)]
+        fn buffer_len(&self) -> usize{
+            match self{
+                #( #pats => ::bin_buffer::enums::VARIANT_LEN + 8 #len, )*
+                #name::#other(_, payload) => ::bin_buffer::enums::VARIANT_LEN + ::bin_buffer::Bufferable::buffer_len(payload),
+            }
+        }
+    })
+}
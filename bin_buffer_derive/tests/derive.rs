@@ -0,0 +1,161 @@
+use bin_buffer::*;
+
+#[derive(Bufferable, Debug, PartialEq)]
+struct Grid<T>{ width: u16, cells: Vec<T> }
+
+#[derive(Bufferable, Debug, PartialEq)]
+struct Point(f32, f32);
+
+#[derive(Bufferable, Debug, PartialEq)]
+struct Unit;
+
+#[derive(Bufferable, Debug, PartialEq)]
+struct Patch{
+    #[bufferable(optional)]
+    name: Option<String>,
+    id: u32,
+    #[bufferable(optional, with = fields::Varint)]
+    size: Option<u64>,
+    #[bufferable(with = fields::Prefixed<u16>)]
+    note: String,
+}
+
+// M is only a marker, it is never written
+#[derive(Bufferable, Debug, PartialEq)]
+#[bufferable(bound())]
+struct Id<M>{ raw: u32, #[bufferable(with = Skip)] marker: std::marker::PhantomData<M> }
+
+struct Skip;
+impl<M> fields::FieldEncoding<std::marker::PhantomData<M>> for Skip{
+    const MIN_LEN: usize = 0;
+    fn write<S: Sink + ?Sized>(_: &std::marker::PhantomData<M>, _: &mut S){}
+    fn read(_: &mut ReadBuffer) -> Option<std::marker::PhantomData<M>>{ Some(std::marker::PhantomData) }
+    fn len(_: &std::marker::PhantomData<M>) -> usize{ 0 }
+}
+
+#[derive(Bufferable, Debug, PartialEq)]
+enum Shape{ Empty, Circle(f32), Rect{ w: f32, h: f32 } }
+
+#[derive(Bufferable, Debug, PartialEq)]
+#[repr(u32)]
+enum Code{ A = 5, B, #[bufferable(tag = 100)] C(u8) }
+
+#[derive(Debug, PartialEq)]
+enum Listed{ Empty, Circle(f32), Rect(f32, f32) }
+bufferable_enum!{ Listed{ Empty = 0, Circle(r: f32) = 1, Rect(w: f32, h: f32) = 2 } }
+
+#[derive(Bufferable, Debug, PartialEq)]
+enum Old{ Ping(u8), #[bufferable(other)] Other(u32, ByteVec) }
+#[derive(Bufferable, Debug, PartialEq)]
+enum New{ Ping(u8, u8), Pong(String), #[bufferable(other)] Other(u32, ByteVec) }
+
+// two versions of a tagged struct: the new one dropped tag 2 and added tags 3 and 4
+#[derive(Bufferable, Debug, PartialEq)]
+#[bufferable(tagged)]
+struct ConfigV1{
+    #[bufferable(tag = 1)]
+    name: String,
+    #[bufferable(tag = 2)]
+    legacy: u32,
+}
+
+#[derive(Bufferable, Debug, PartialEq)]
+#[bufferable(tagged)]
+struct ConfigV2{
+    #[bufferable(tag = 4)]
+    volume: f32,
+    #[bufferable(tag = 1)]
+    name: String,
+    #[bufferable(tag = 3)]
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_derive_struct(){
+    let grid = Grid{ width: 2, cells: vec![(1u8, 2u8), (3, 4)] };
+    let bytes = encode(&grid);
+    assert_eq!(encode(&(2u16, vec![(1u8, 2u8), (3, 4)])), bytes);
+    assert_eq!(grid.buffer_len(), bytes.len());
+    assert_eq!(10, Grid::<u8>::MIN_BUFFER_LEN);
+    assert_eq!(Some(grid), decode(&bytes));
+    assert_eq!(Err(BufferError::UnexpectedEof{ needed: 1, available: 0 }), validate::<Grid<(u8, u8)>>(&mut ReadBuffer::from_raw(bytes[..12].to_vec())));
+    assert_eq!(encode(&(1.0f32, 2.0f32)), encode(&Point(1.0, 2.0)));
+    assert_eq!(Some(Point(1.0, 2.0)), decode(&encode(&(1.0f32, 2.0f32))));
+    assert_eq!(0, encode(&Unit).len());
+    assert_eq!(Some(Unit), decode(&[]));
+    assert_eq!(None, decode::<Vec<Unit>>(&[0xff; 8]));
+    struct NotBufferable;
+    let id = Id::<NotBufferable>{ raw: 5, marker: std::marker::PhantomData };
+    assert_eq!(encode(&5u32), encode(&id));
+}
+
+#[test]
+fn test_derive_optional(){
+    // the bitmap, then id and note, then the optional fields that are there
+    let patch = Patch{ name: None, id: 1, size: Some(300), note: String::from("n") };
+    let bytes = encode(&patch);
+    assert_eq!(vec![0b0100_0000, 0, 0, 0, 1, 0, 1, b'n', 0xac, 0x02], bytes);
+    assert_eq!(patch.buffer_len(), bytes.len());
+    assert_eq!(Some(patch), decode(&bytes));
+    let full = Patch{ name: Some(String::from("x")), id: 2, size: None, note: String::new() };
+    assert_eq!(Some(full), decode(&encode(&Patch{ name: Some(String::from("x")), id: 2, size: None, note: String::new() })));
+    assert_eq!(None, decode::<Patch>(&[0b0010_0000, 0, 0, 0, 1, 0, 0]));
+}
+
+#[test]
+fn test_derive_enum(){
+    // the same layout as bufferable_enum!
+    let shapes = vec![Shape::Rect{ w: 1.0, h: 2.0 }, Shape::Empty, Shape::Circle(0.5)];
+    let listed = vec![Listed::Rect(1.0, 2.0), Listed::Empty, Listed::Circle(0.5)];
+    let bytes = encode(&shapes);
+    assert_eq!(encode(&listed), bytes);
+    assert_eq!(shapes.buffer_len(), bytes.len());
+    assert_eq!(Some(shapes), decode(&bytes));
+    assert_eq!(None, decode::<Shape>(&[0, 0, 0, 3]));
+    assert_eq!(Err(BufferError::InvalidDiscriminant), validate::<Shape>(&mut ReadBuffer::from_raw(vec![0, 0, 0, 3])));
+    assert_eq!(encode(&5u32), encode(&Code::A));
+    assert_eq!(encode(&6u32), encode(&Code::B));
+    assert_eq!(encode(&(100u32, 7u8)), encode(&Code::C(7)));
+    assert_eq!(Some(Code::C(7)), decode(&encode(&(100u32, 7u8))));
+}
+
+#[test]
+fn test_derive_tagged(){
+    let v1 = ConfigV1{ name: String::from("a"), legacy: 7 };
+    let mut writer = tagged::TaggedWriter::new();
+    writer.field(1, &String::from("a")).field(2, &7u32);
+    let mut bytes = Vec::new();
+    writer.finish(&mut bytes);
+    assert_eq!(bytes, encode(&v1));
+    assert_eq!(v1.buffer_len(), bytes.len());
+    // new from old: tag 2 is skipped, tags 3 and 4 get their default
+    let v2: ConfigV2 = decode(&bytes).unwrap();
+    assert_eq!(ConfigV2{ volume: 0.0, name: String::from("a"), tags: Vec::new() }, v2);
+    // old from new: tags 3 and 4 are skipped, tag 2 gets its default
+    let v2 = ConfigV2{ volume: 0.5, name: String::from("b"), tags: vec![String::from("x")] };
+    let bytes = encode(&v2);
+    assert_eq!(v2.buffer_len(), bytes.len());
+    assert_eq!(Some(ConfigV1{ name: String::from("b"), legacy: 0 }), decode(&bytes));
+    assert_eq!(Some(v2), decode(&bytes));
+    // fields at their default are not written
+    assert_eq!(encode(&(0u64)), encode(&ConfigV1{ name: String::new(), legacy: 0 }));
+    // a known tag that does not hold its type
+    let mut writer = tagged::TaggedWriter::new();
+    writer.field(2, &String::from("not a u32"));
+    let mut bytes = Vec::new();
+    writer.finish(&mut bytes);
+    assert_eq!(None, decode::<ConfigV1>(&bytes));
+    assert_eq!(Some(ConfigV2{ volume: 0.0, name: String::new(), tags: Vec::new() }), decode(&bytes));
+}
+
+#[test]
+fn test_derive_other(){
+    let bytes = encode(&vec![New::Ping(1, 2), New::Pong(String::from("p"))]);
+    let old: Vec<Old> = decode(&bytes).unwrap();
+    assert_eq!(Old::Ping(1), old[0]);
+    assert_eq!(Old::Other(1, ByteVec(encode(&String::from("p")))), old[1]);
+    // the old version writes what it did not know back unchanged
+    assert_eq!(encode(&New::Pong(String::from("p"))), encode(&old[1]));
+    assert_eq!(Some(New::Pong(String::from("p"))), decode(&encode(&old[1])));
+    assert_eq!(Ok(()), validate::<Vec<Old>>(&mut ReadBuffer::from_raw(bytes)));
+}
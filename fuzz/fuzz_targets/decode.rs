@@ -0,0 +1,41 @@
+//! Decode every implemented type from arbitrary bytes.
+//! Decoding may fail, but it must never panic or allocate without bound.
+#![no_main]
+use bin_buffer::*;
+use libfuzzer_sys::fuzz_target;
+
+fn decode_all<T: Bufferable>(data: &[u8]){
+    let mut buffer = ReadBuffer::from_raw(data.to_vec());
+    while T::from_buffer(&mut buffer).is_some() {}
+}
+
+fuzz_target!(|data: &[u8]|{
+    decode_all::<u8>(data);
+    decode_all::<u16>(data);
+    decode_all::<u32>(data);
+    decode_all::<u64>(data);
+    decode_all::<usize>(data);
+    decode_all::<f32>(data);
+    decode_all::<f64>(data);
+    decode_all::<String>(data);
+    decode_all::<ByteVec>(data);
+    decode_all::<Vec<u8>>(data);
+    decode_all::<Vec<u32>>(data);
+    decode_all::<Vec<f64>>(data);
+    decode_all::<Vec<String>>(data);
+    decode_all::<Vec<Vec<u16>>>(data);
+    decode_all::<LossyString>(data);
+    decode_all::<SparseVec<u8>>(data);
+    decode_all::<SparseVec<String>>(data);
+    decode_all::<std::collections::HashMap<String,u32>>(data);
+    decode_all::<std::collections::HashSet<u64>>(data);
+    decode_all::<(u8,String)>(data);
+    decode_all::<(u16,Vec<u8>,f32)>(data);
+    decode_all::<(String,u64,Vec<String>,f64)>(data);
+    let mut buffer = ReadBuffer::from_raw(data.to_vec());
+    let _ = buffer.read_str();
+    let _ = buffer.read_blob();
+    if let Some(seq) = buffer.read_seq::<String>(){
+        for _ in seq {}
+    }
+});
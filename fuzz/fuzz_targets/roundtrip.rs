@@ -0,0 +1,24 @@
+//! Anything decoded from arbitrary bytes must encode back to the bytes it
+//! was read from.
+#![no_main]
+use bin_buffer::*;
+use libfuzzer_sys::fuzz_target;
+
+fn roundtrip<T: Bufferable>(data: &[u8]){
+    let mut buffer = ReadBuffer::from_raw(data.to_vec());
+    let before = buffer.remaining();
+    if let Some(x) = T::from_buffer(&mut buffer){
+        let used = before - buffer.remaining();
+        let mut out = Vec::new();
+        x.copy_into_buffer(&mut out);
+        assert_eq!(&data[..used], &out[..]);
+        assert_eq!(used, x.buffer_len());
+    }
+}
+
+fuzz_target!(|data: &[u8]|{
+    roundtrip::<String>(data);
+    roundtrip::<Vec<u16>>(data);
+    roundtrip::<Vec<String>>(data);
+    roundtrip::<(u8,Vec<u64>,String)>(data);
+});
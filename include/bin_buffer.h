@@ -0,0 +1,193 @@
+#ifndef BIN_BUFFER_H
+#define BIN_BUFFER_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Buffer from which we can read.
+ * It owns its bytes, except for forks which borrow them from the ReadBuffer they came from.
+ */
+typedef struct BinReadBuffer BinReadBuffer;
+
+typedef struct Vec_u8 Vec_u8;
+
+/**
+ * Buffer: a Vector of bytes
+ */
+typedef struct Vec_u8 BinBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create an empty buffer to write to.
+ */
+BinBuffer *bin_buffer_new(void);
+
+/**
+ * Free a buffer made with bin_buffer_new. Does nothing with null.
+ * # Safety
+ * buf must be null or made by bin_buffer_new and not freed yet.
+ */
+void bin_buffer_free(BinBuffer *buf);
+
+/**
+ * Pointer to the bytes written so far. Invalid after the next write to or free of buf.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+const uint8_t *bin_buffer_data(const BinBuffer *buf);
+
+/**
+ * How many bytes have been written.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+uintptr_t bin_buffer_len(const BinBuffer *buf);
+
+/**
+ * Write a String: len bytes of UTF-8 at data.
+ * Returns false, writing nothing, if they are not valid UTF-8.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new and data must point to len readable bytes.
+ */
+bool bin_buffer_push_str(BinBuffer *buf, const uint8_t *data, uintptr_t len);
+
+/**
+ * Write a ByteVec: len bytes at data.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new and data must point to len readable bytes.
+ */
+void bin_buffer_push_bytes(BinBuffer *buf, const uint8_t *data, uintptr_t len);
+
+/**
+ * Create a reader over a copy of len bytes at data.
+ * # Safety
+ * data must point to len readable bytes.
+ */
+struct BinReadBuffer *bin_read_buffer_new(const uint8_t *data, uintptr_t len);
+
+/**
+ * Free a reader made with bin_read_buffer_new. Does nothing with null.
+ * # Safety
+ * buf must be null or made by bin_read_buffer_new and not freed yet.
+ */
+void bin_read_buffer_free(struct BinReadBuffer *buf);
+
+/**
+ * How many bytes are left to read.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new.
+ */
+uintptr_t bin_read_buffer_remaining(const struct BinReadBuffer *buf);
+
+/**
+ * Read a String without copying it. On success *data and *len describe its UTF-8 bytes,
+ * which stay valid until buf is freed. They are not null terminated.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new, data and len must be writable.
+ */
+bool bin_read_str(struct BinReadBuffer *buf, const uint8_t **data, uintptr_t *len);
+
+/**
+ * Read a ByteVec without copying it, like bin_read_str.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new, data and len must be writable.
+ */
+bool bin_read_bytes(struct BinReadBuffer *buf, const uint8_t **data, uintptr_t *len);
+
+/**
+ * Write a u8.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+void bin_buffer_push_u8(BinBuffer *buf, uint8_t x);
+
+/**
+ * Read a u8 into *out.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new and out must be writable.
+ */
+bool bin_read_u8(struct BinReadBuffer *buf, uint8_t *out);
+
+/**
+ * Write a u16.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+void bin_buffer_push_u16(BinBuffer *buf, uint16_t x);
+
+/**
+ * Read a u16 into *out.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new and out must be writable.
+ */
+bool bin_read_u16(struct BinReadBuffer *buf, uint16_t *out);
+
+/**
+ * Write a u32.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+void bin_buffer_push_u32(BinBuffer *buf, uint32_t x);
+
+/**
+ * Read a u32 into *out.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new and out must be writable.
+ */
+bool bin_read_u32(struct BinReadBuffer *buf, uint32_t *out);
+
+/**
+ * Write a u64.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+void bin_buffer_push_u64(BinBuffer *buf, uint64_t x);
+
+/**
+ * Read a u64 into *out.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new and out must be writable.
+ */
+bool bin_read_u64(struct BinReadBuffer *buf, uint64_t *out);
+
+/**
+ * Write a f32.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+void bin_buffer_push_f32(BinBuffer *buf, float x);
+
+/**
+ * Read a f32 into *out.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new and out must be writable.
+ */
+bool bin_read_f32(struct BinReadBuffer *buf, float *out);
+
+/**
+ * Write a f64.
+ * # Safety
+ * buf must be a live buffer made by bin_buffer_new.
+ */
+void bin_buffer_push_f64(BinBuffer *buf, double x);
+
+/**
+ * Read a f64 into *out.
+ * # Safety
+ * buf must be a live reader made by bin_read_buffer_new and out must be writable.
+ */
+bool bin_read_f64(struct BinReadBuffer *buf, double *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BIN_BUFFER_H */
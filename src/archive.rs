@@ -0,0 +1,260 @@
+//! Many named blobs in one file, instead of dozens of small files next to each other.
+//! An EntryArchive uses the layout of a SectionWriter with String keys: the entries one
+//! after another, then the table of names, offsets and lengths, then the offset of that table.
+//! So a SectionReader::<String> reads archives too.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::archive::*;
+//! let mut archive = EntryArchive::new();
+//! assert!(archive.append("level1", &vec![1u8, 2, 3]));
+//! assert!(archive.append_bytes("readme.txt", b"hello"));
+//! assert!(!archive.append_bytes("readme.txt", b"again"));
+//! let archive = EntryArchive::from_bytes(archive.to_bytes()).unwrap();
+//! assert_eq!(vec!["level1", "readme.txt"], archive.names().collect::<Vec<_>>());
+//! assert_eq!(Some(&b"hello"[..]), archive.get("readme.txt"));
+//! assert_eq!(Some(vec![1u8, 2, 3]), archive.read("level1"));
+//! ```
+#[cfg(feature = "fs")]
+use std::io::{ Read, Seek, SeekFrom, Write };
+#[cfg(feature = "fs")]
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use crate::merkle::MerkleTree;
+use crate::sections::SectionReader;
+use crate::store::BufferStore;
+
+/// Named entries, each holding its own bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntryArchive{
+    bytes: Buffer,
+    table: Vec<(String, u64, u64)>,
+}
+
+impl EntryArchive{
+    /// An archive without entries.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Read an archive written by to_bytes. None if the table is missing or broken.
+    pub fn from_bytes(mut bytes: Buffer) -> Option<Self>{
+        let reader = SectionReader::<String>::new(&bytes)?;
+        let table: Vec<_> = reader.table().iter()
+            .map(|(name, pos, len)| (name.clone(), *pos as u64, *len as u64)).collect();
+        let end = reader.table().iter().map(|(_, pos, len)| pos + len).max().unwrap_or(0);
+        let table_pos = u64::from_buffer(&mut ReadBuffer::borrowed(&bytes[bytes.len() - 8..], Default::default()))? as usize;
+        // entries have to end where the table starts, so appending keeps every offset valid
+        if end != table_pos{
+            return Option::None;
+        }
+        for (i, (name, _, _)) in table.iter().enumerate(){
+            if table[..i].iter().any(|(n, _, _)| n == name){
+                return Option::None;
+            }
+        }
+        bytes.truncate(table_pos);
+        Option::Some(Self{ bytes, table })
+    }
+    /// The entries followed by the table.
+    pub fn to_bytes(&self) -> Buffer{
+        let mut bytes = self.bytes.clone();
+        self.table.copy_into_buffer(&mut bytes);
+        (self.bytes.len() as u64).into_buffer(&mut bytes);
+        bytes
+    }
+    /// Number of entries.
+    pub fn len(&self) -> usize{
+        self.table.len()
+    }
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> bool{
+        self.table.is_empty()
+    }
+    /// The names, in the order the entries were appended.
+    pub fn names(&self) -> impl Iterator<Item = &str>{
+        self.table.iter().map(|(name, _, _)| name.as_str())
+    }
+    /// Whether there is an entry called name.
+    pub fn contains(&self, name: &str) -> bool{
+        self.table.iter().any(|(n, _, _)| n == name)
+    }
+    /// The bytes of entry name.
+    pub fn get(&self, name: &str) -> Option<&[u8]>{
+        let (_, pos, len) = self.table.iter().find(|(n, _, _)| n == name)?;
+        Option::Some(&self.bytes[*pos as usize..(*pos + *len) as usize])
+    }
+    /// Where the bytes of entry name are in to_bytes, as offset and length.
+    /// With merkle_tree a reader that fetches only those bytes can check them.
+    pub fn entry_range(&self, name: &str) -> Option<(u64, u64)>{
+        self.table.iter().find(|(n, _, _)| n == name).map(|(_, pos, len)| (*pos, *len))
+    }
+    /// A Merkle tree over to_bytes in chunks of chunk_size, to keep next to the archive
+    /// or to send along with its root.
+    pub fn merkle_tree(&self, chunk_size: usize) -> MerkleTree{
+        MerkleTree::build(&self.to_bytes(), chunk_size)
+    }
+    /// Read entry name as one T. None if it is missing,
+    /// does not decode or has bytes left over.
+    pub fn read<T: Bufferable>(&self, name: &str) -> Option<T>{
+        let mut buf = ReadBuffer::borrowed(self.get(name)?, Default::default());
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// Add an entry holding bytes. False, adding nothing, if name is already used.
+    pub fn append_bytes(&mut self, name: &str, bytes: &[u8]) -> bool{
+        if self.contains(name){
+            return false;
+        }
+        self.table.push((String::from(name), self.bytes.len() as u64, bytes.len() as u64));
+        self.bytes.extend_from_slice(bytes);
+        true
+    }
+    /// Add an entry holding x. False, adding nothing, if name is already used.
+    pub fn append<T: Bufferable>(&mut self, name: &str, x: &T) -> bool{
+        if self.contains(name){
+            return false;
+        }
+        let pos = self.bytes.len();
+        x.copy_into_buffer(&mut self.bytes);
+        self.table.push((String::from(name), pos as u64, (self.bytes.len() - pos) as u64));
+        true
+    }
+    /// Read an archive kept under key in a store.
+    pub fn load_from<S: BufferStore + ?Sized>(store: &S, key: &str) -> Option<Self>{
+        Self::from_bytes(store.get(key)?)
+    }
+    /// Keep the archive under key in a store, replacing what was there.
+    pub fn save_to<S: BufferStore + ?Sized>(&self, store: &mut S, key: &str) -> bool{
+        store.put(key, &self.to_bytes())
+    }
+    /// Read an archive from a file.
+    #[cfg(feature = "fs")]
+    pub fn open(path: &std::path::Path) -> Option<Self>{
+        Self::from_bytes(crate::buffer_read_file(path)?)
+    }
+    /// Write the archive to a file, replacing what was there.
+    #[cfg(feature = "fs")]
+    pub fn save(&self, path: &std::path::Path) -> bool{
+        crate::buffer_write_file(path, &self.to_bytes())
+    }
+}
+
+/// Add an entry to the archive in a file without reading the other entries:
+/// the new entry overwrites the table, after which the table is written again.
+/// Creates the archive if the file does not exist or is empty.
+/// False if the file is not an archive or name is already in it.
+/// # Example
+/// ```
+/// use bin_buffer::archive::*;
+/// let path = std::env::temp_dir().join("bin_buffer_archive_doc.bin");
+/// let _ = std::fs::remove_file(&path);
+/// assert!(append_to_file(&path, "a", b"first"));
+/// assert!(append_to_file(&path, "b", b"second"));
+/// assert!(!append_to_file(&path, "a", b"third"));
+/// let archive = EntryArchive::open(&path).unwrap();
+/// assert_eq!(Some(&b"second"[..]), archive.get("b"));
+/// ```
+#[cfg(feature = "fs")]
+pub fn append_to_file(path: &std::path::Path, name: &str, bytes: &[u8]) -> bool{
+    crate::instrument::traced("append archive entry", &path.display(), || append_entry(path, name, bytes), |_| bytes.len())
+}
+
+#[cfg(feature = "fs")]
+fn append_entry(path: &std::path::Path, name: &str, bytes: &[u8]) -> bool{
+    let mut file = if let Ok(f) =
+        std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path) { f }
+    else { return false; };
+    let mut table = Vec::<(String, u64, u64)>::new();
+    let mut table_pos = 0u64;
+    let file_len = if let Ok(m) = file.metadata() { m.len() } else { return false; };
+    if file_len > 0{
+        // only the table and the trailer are read
+        let mut trailer = [0u8; 8];
+        if file_len < 8 || file.seek(SeekFrom::End(-8)).is_err() || file.read_exact(&mut trailer).is_err(){
+            return false;
+        }
+        table_pos = u64::from_be_bytes(trailer);
+        let table_len = if let Some(l) = (file_len - 8).checked_sub(table_pos) { l } else { return false; };
+        let mut raw = vec![0; if let Ok(l) = usize::try_from(table_len) { l } else { return false; }];
+        if file.seek(SeekFrom::Start(table_pos)).is_err() || file.read_exact(&mut raw).is_err(){
+            return false;
+        }
+        let mut buf = ReadBuffer::borrowed(&raw, Default::default());
+        table = if let Some(t) = Vec::from_buffer(&mut buf) { t } else { return false; };
+        if buf.remaining() != 0 || table.iter().any(|(n, _, _)| n == name){
+            return false;
+        }
+    }
+    table.push((String::from(name), table_pos, bytes.len() as u64));
+    let mut tail = bytes.to_vec();
+    table.into_buffer(&mut tail);
+    (table_pos + bytes.len() as u64).into_buffer(&mut tail);
+    if file.seek(SeekFrom::Start(table_pos)).is_err() || file.write_all(&tail).is_err(){
+        return false;
+    }
+    file.set_len(table_pos + tail.len() as u64).is_ok()
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::archive::*;
+
+    #[test]
+    fn test_archive(){
+        let mut archive = EntryArchive::new();
+        assert!(archive.append("config", &(1u16, String::from("x"))));
+        assert!(archive.append_bytes("empty", &[]));
+        assert!(archive.append_bytes("raw", &[9, 8, 7]));
+        assert!(!archive.append("config", &0u8));
+        assert_eq!(3, archive.len());
+        let bytes = archive.to_bytes();
+        // the same layout as sections
+        let reader = SectionReader::<String>::new(&bytes).unwrap();
+        assert_eq!(Some(vec![9u8, 8, 7]), reader.section(&String::from("raw")).map(|b| b.as_slice().to_vec()));
+        let mut back = EntryArchive::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(archive, back);
+        assert_eq!(Some((1u16, String::from("x"))), back.read("config"));
+        assert_eq!(Some(&[][..]), back.get("empty"));
+        assert_eq!(None, back.get("missing"));
+        assert_eq!(None, back.read::<u8>("raw"));
+        assert!(back.append_bytes("later", &[1]));
+        let back = EntryArchive::from_bytes(back.to_bytes()).unwrap();
+        assert_eq!(Some(&[1][..]), back.get("later"));
+        assert_eq!(None, EntryArchive::from_bytes(bytes[1..].to_vec()));
+        let mut store = crate::store::MemoryStore::new();
+        assert!(back.save_to(&mut store, "archive"));
+        assert_eq!(Some(back.clone()), EntryArchive::load_from(&store, "archive"));
+        // checking one entry fetched on its own
+        let bytes = back.to_bytes();
+        let tree = back.merkle_tree(4);
+        let (pos, len) = back.entry_range("raw").unwrap();
+        assert_eq!(&[9, 8, 7][..], &bytes[pos as usize..(pos + len) as usize]);
+        let (start, end) = tree.covering(pos, len);
+        assert!(tree.verify_range(start, &bytes[start as usize..end as usize]));
+        assert_eq!(None, back.entry_range("missing"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_archive_file(){
+        let mut archive = EntryArchive::new();
+        archive.append("config", &(1u16, String::from("x")));
+        archive.append_bytes("empty", &[]);
+        archive.append_bytes("raw", &[9, 8, 7]);
+        // appended to in place
+        let path = std::env::temp_dir().join("bin_buffer_archive_test.bin");
+        assert!(archive.save(&path));
+        assert!(append_to_file(&path, "appended", &[4, 5]));
+        assert!(!append_to_file(&path, "raw", &[4, 5]));
+        let opened = EntryArchive::open(&path).unwrap();
+        assert_eq!(vec!["config", "empty", "raw", "appended"], opened.names().collect::<Vec<_>>());
+        assert_eq!(Some(&[4, 5][..]), opened.get("appended"));
+        assert_eq!(archive.get("raw"), opened.get("raw"));
+        std::fs::write(&path, [1, 2, 3]).unwrap();
+        assert!(!append_to_file(&path, "x", &[]));
+        let _ = std::fs::remove_file(&path);
+    }
+}
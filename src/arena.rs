@@ -0,0 +1,102 @@
+//! Decoding into a bumpalo arena, enabled with the `bumpalo` feature.
+//! Strings and vectors decoded this way take their memory from the arena
+//! instead of the global allocator, so decoding many small values costs a
+//! pointer bump each and everything is freed at once with the arena.
+use crate::{ Bufferable, ReadBuffer };
+use bumpalo::Bump;
+use bumpalo::collections::{ String as BumpString, Vec as BumpVec };
+
+/// Object that can be read from a ReadBuffer into an arena.
+/// Every Bufferable type can, owning its memory as usual.
+pub trait FromBufferIn<'b>: Sized{
+    /// Read object from buffer, allocating from the arena.
+    fn from_buffer_in(buf: &mut ReadBuffer, bump: &'b Bump) -> Option<Self>;
+}
+
+impl<'b, T: Bufferable> FromBufferIn<'b> for T{
+    fn from_buffer_in(buf: &mut ReadBuffer, _: &'b Bump) -> Option<Self>{
+        T::from_buffer(buf)
+    }
+}
+/// Reads a String as a str stored in the arena.
+impl<'b> FromBufferIn<'b> for &'b str{
+    fn from_buffer_in(buf: &mut ReadBuffer, bump: &'b Bump) -> Option<Self>{
+        let string: &'b str = bump.alloc_str(buf.read_str()?);
+        buf.use_bytes(string.len())?;
+        Option::Some(string)
+    }
+}
+/// Reads a Vec<u8> or ByteVec as bytes stored in the arena.
+impl<'b> FromBufferIn<'b> for &'b [u8]{
+    fn from_buffer_in(buf: &mut ReadBuffer, bump: &'b Bump) -> Option<Self>{
+        let bytes: &'b [u8] = bump.alloc_slice_copy(buf.read_blob()?);
+        buf.use_bytes(bytes.len())?;
+        Option::Some(bytes)
+    }
+}
+/// Reads a String into an arena String.
+impl<'b> FromBufferIn<'b> for BumpString<'b>{
+    fn from_buffer_in(buf: &mut ReadBuffer, bump: &'b Bump) -> Option<Self>{
+        let string = BumpString::from_str_in(buf.read_str()?, bump);
+        buf.use_bytes(string.len())?;
+        Option::Some(string)
+    }
+}
+/// Reads a Vec into an arena Vec, with elements also read into the arena.
+impl<'b, T: FromBufferIn<'b>> FromBufferIn<'b> for BumpVec<'b, T>{
+    fn from_buffer_in(buf: &mut ReadBuffer, bump: &'b Bump) -> Option<Self>{
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            let mut vec = BumpVec::new_in(bump);
+            for _ in 0..len{
+                vec.push(T::from_buffer_in(buf, bump)?);
+            }
+            Option::Some(vec)
+        })
+    }
+}
+
+impl ReadBuffer<'_>{
+    /// Read object from buffer, allocating its strings and vectors from the arena.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// use bumpalo::Bump;
+    /// let mut buffer = Vec::new();
+    /// vec![String::from("a"), String::from("bc")].into_buffer(&mut buffer);
+    /// let bump = Bump::new();
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// let x = buffer.read_in::<bumpalo::collections::Vec<&str>>(&bump).unwrap();
+    /// assert_eq!(&["a", "bc"][..], &x[..]);
+    /// ```
+    pub fn read_in<'b, T: FromBufferIn<'b>>(&mut self, bump: &'b Bump) -> Option<T>{
+        T::from_buffer_in(self, bump)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use bumpalo::Bump;
+    use bumpalo::collections::{ String as BumpString, Vec as BumpVec };
+    #[test]
+    fn test_read_in(){
+        let x = vec![vec![String::from("one"), String::from("two")], vec![]];
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        vec![1u8,2].into_buffer(&mut buffer);
+        String::from("three").into_buffer(&mut buffer);
+        7u32.into_buffer(&mut buffer);
+        let bump = Bump::new();
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        let y = buffer.read_in::<BumpVec<BumpVec<&str>>>(&bump).unwrap();
+        assert_eq!(2, y.len());
+        assert_eq!(&["one", "two"][..], &y[0][..]);
+        assert!(y[1].is_empty());
+        assert_eq!(Some(&[1u8,2][..]), buffer.read_in::<&[u8]>(&bump));
+        assert_eq!("three", buffer.read_in::<BumpString>(&bump).unwrap().as_str());
+        assert_eq!(Some(7), buffer.read_in::<u32>(&bump));
+        assert_eq!(None, buffer.read_in::<&str>(&bump));
+    }
+}
@@ -0,0 +1,114 @@
+//! Bufferable for ndarray arrays, enabled with the `ndarray` feature.
+use crate::{ Bufferable, ReadBuffer, Sink };
+use ndarray::{ Array, Dimension };
+
+/// Implements Bufferable for owned arrays of any dimension, such as Array2<T> and ArrayD<T>.
+/// Written as the number of axes and the length of each axis as u64,
+/// then the elements in row-major order without a length.
+/// Arrays in standard layout go through the bulk path of T.
+/// Reading fails if the number of axes does not fit D, or an axis or the
+/// element count is over DecodeLimits::max_elements.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use ndarray::{ array, Array2, ArrayD };
+/// let x = array![[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]];
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// assert_eq!(8 + 2 * 8 + 6 * 4, buffer.len());
+/// let mut read = ReadBuffer::from_raw(buffer.clone());
+/// assert_eq!(Some(x.clone()), Array2::<f32>::from_buffer(&mut read));
+/// let mut read = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(x.into_dyn()), ArrayD::<f32>::from_buffer(&mut read));
+/// ```
+impl<T: Bufferable + Clone, D: Dimension> Bufferable for Array<T, D>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.reserve(self.buffer_len());
+        (self.ndim() as u64).into_buffer(vec);
+        for len in self.shape(){
+            (*len as u64).into_buffer(vec);
+        }
+        match self.as_slice(){
+            Some(slice) => T::copy_slice_into_buffer(slice, vec),
+            None => for x in self.iter(){
+                x.copy_into_buffer(vec);
+            },
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let ndim = buf.read_len()?;
+            if D::NDIM.is_some_and(|n| n != ndim){
+                return Option::None;
+            }
+            let mut shape = D::zeros(ndim);
+            let mut len = 1usize;
+            for axis in shape.slice_mut(){
+                *axis = buf.read_len()?;
+                len = len.checked_mul(*axis)?;
+            }
+            if len > buf.limits().max_elements{
+                return Option::None;
+            }
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            let data = T::vec_from_buffer(len, buf)?;
+            Array::from_shape_vec(shape, data).ok()
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        let data = match self.as_slice(){
+            Some(slice) => T::slice_buffer_len(slice),
+            None => self.iter().map(T::buffer_len).sum(),
+        };
+        8 + 8 * self.ndim() + data
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use ndarray::{ Array, Array2, Array3, ArrayD, IxDyn };
+
+    #[test]
+    fn test_ndarray(){
+        let x = Array::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 100 + j * 10 + k) as u16);
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        assert_eq!(x.buffer_len(), buffer.len());
+        assert_eq!(&[0, 0, 0, 0, 0, 0, 0, 3], &buffer[..8]);
+        // row-major, last axis fastest
+        assert_eq!(&[0, 0, 0, 1, 0, 2], &buffer[32..38]);
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!(Some(x.clone()), Array3::<u16>::from_buffer(&mut read));
+        // a transposed view is written in logical order
+        let t = x.clone().reversed_axes();
+        assert!(t.as_slice().is_none());
+        let mut tbuf = Vec::new();
+        t.copy_into_buffer(&mut tbuf);
+        assert_eq!(t.buffer_len(), tbuf.len());
+        assert_eq!(Some(t.as_standard_layout().to_owned()), Array3::<u16>::from_buffer(&mut ReadBuffer::from_raw(tbuf)));
+        // wrong number of axes, dynamic arrays and strings
+        assert_eq!(None, Array2::<u16>::from_buffer(&mut ReadBuffer::from_raw(buffer.clone())));
+        assert_eq!(Some(x.into_dyn()), ArrayD::<u16>::from_buffer(&mut ReadBuffer::from_raw(buffer)));
+        let s = ArrayD::from_shape_vec(IxDyn(&[2]), vec![String::from("a"), String::from("bc")]).unwrap();
+        let mut buffer = Vec::new();
+        s.copy_into_buffer(&mut buffer);
+        assert_eq!(Some(s), ArrayD::<String>::from_buffer(&mut ReadBuffer::from_raw(buffer)));
+        // huge shapes are rejected before allocating
+        let mut buffer = Vec::new();
+        vec![2u64, u64::MAX / 2, 4].into_buffer(&mut buffer);
+        assert_eq!(None, Array2::<u8>::from_buffer(&mut ReadBuffer::from_raw(buffer)));
+        let limits = DecodeLimits{ max_elements: 10, ..DecodeLimits::default() };
+        let mut buffer = Vec::new();
+        Array2::<u8>::zeros((4, 4)).copy_into_buffer(&mut buffer);
+        assert_eq!(None, Array2::<u8>::from_buffer(&mut ReadBuffer::from_raw(buffer).with_limits(limits)));
+    }
+}
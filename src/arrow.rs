@@ -0,0 +1,552 @@
+//! Converting between Columns buffers and the Arrow IPC stream format, so columnar exports
+//! can be read by Arrow tools like pyarrow, Polars and DataFusion without going through CSV.
+//! A Columns buffer does not say what its columns are, so they are given as fields with a
+//! schema each. The schemas can be U8, U16, U32, U64, F32, F64, Str and Bytes, which become
+//! the Arrow types UInt8 to UInt64, Float32, Float64, Utf8 and Binary, without nulls.
+//! The stream is a schema message, one record batch and the end of stream marker,
+//! with the metadata as FlatBuffers and the buffers little endian, 8 byte aligned.
+//! Reading takes streams with any number of batches, and Arrow files, which hold a stream,
+//! but not dictionaries, compression, nulls or other types.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::arrow::*;
+//! use bin_buffer::columnar::Columns;
+//! use bin_buffer::schema::Schema;
+//! let rows = Columns(vec![(1u32, String::from("a")), (2, String::from("b"))]);
+//! let fields = vec![ArrowField::new("id", Schema::U32), ArrowField::new("name", Schema::Str)];
+//! let stream = columns_to_arrow(&fields, &encode(&rows)).unwrap();
+//! let (read_fields, bytes) = arrow_to_columns(&stream).unwrap();
+//! assert_eq!(fields, read_fields);
+//! assert_eq!(Some(rows), decode(&bytes));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use crate::schema::Schema;
+
+/// A named column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArrowField{
+    /// Name of the column.
+    pub name: String,
+    /// Type of its values.
+    pub schema: Schema,
+}
+
+impl ArrowField{
+    /// A field called name of values with schema.
+    pub fn new(name: &str, schema: Schema) -> Self{
+        Self{ name: String::from(name), schema }
+    }
+}
+
+// The Arrow type of a schema: the Type union tag and its table.
+fn arrow_type(schema: &Schema) -> Option<(u8, Table)>{
+    let int = |bits: i32| (TYPE_INT, Table(vec![(0, Val::I32(bits)), (1, Val::Bool(false))]));
+    let float = |precision: i16| (TYPE_FLOAT, Table(vec![(0, Val::I16(precision))]));
+    Option::Some(match schema{
+        Schema::U8 => int(8),
+        Schema::U16 => int(16),
+        Schema::U32 => int(32),
+        Schema::U64 => int(64),
+        Schema::F32 => float(1),
+        Schema::F64 => float(2),
+        Schema::Str => (TYPE_UTF8, Table(Vec::new())),
+        Schema::Bytes => (TYPE_BINARY, Table(Vec::new())),
+        _ => return Option::None,
+    })
+}
+
+// Bytes per value of the number schemas.
+fn width(schema: &Schema) -> Option<usize>{
+    match schema{
+        Schema::U8 => Option::Some(1),
+        Schema::U16 => Option::Some(2),
+        Schema::U32 | Schema::F32 => Option::Some(4),
+        Schema::U64 | Schema::F64 => Option::Some(8),
+        _ => Option::None,
+    }
+}
+
+const TYPE_INT: u8 = 2;
+const TYPE_FLOAT: u8 = 3;
+const TYPE_BINARY: u8 = 4;
+const TYPE_UTF8: u8 = 5;
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+const METADATA_V5: i16 = 4;
+const CONTINUATION: [u8; 4] = [0xff; 4];
+
+// FlatBuffers are written front to back: a table, then what its offsets point at.
+enum Val{
+    Bool(bool),
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Table(Table),
+    Str(String),
+    Tables(Vec<Table>),
+    // structs of 8 byte aligned fields, as their bytes
+    Structs(usize, Buffer),
+}
+
+struct Table(Vec<(u16, Val)>);
+
+impl Val{
+    fn inline_len(&self) -> usize{
+        match self{
+            Val::Bool(_) | Val::U8(_) => 1,
+            Val::I16(_) => 2,
+            Val::I64(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+fn pad_to(buf: &mut Buffer, align: usize, ahead: usize){
+    while !(buf.len() + ahead).is_multiple_of(align){
+        buf.push(0);
+    }
+}
+
+fn patch_offset(buf: &mut [u8], at: usize, to: usize){
+    buf[at..at + 4].copy_from_slice(&((to - at) as u32).to_le_bytes());
+}
+
+// Write table with its vtable in front, then its children, and give where the table starts.
+fn write_table(buf: &mut Buffer, table: &Table) -> usize{
+    let mut fields: Vec<&(u16, Val)> = table.0.iter().collect();
+    fields.sort_by_key(|(_, v)| std::cmp::Reverse(v.inline_len()));
+    let slots = table.0.iter().map(|(s, _)| *s as usize + 1).max().unwrap_or(0);
+    let vtable_len = 4 + 2 * slots;
+    // the table starts 4 bytes before an 8 byte boundary, so its 8 byte fields are aligned
+    pad_to(buf, 8, vtable_len + 4);
+    let vtable = buf.len();
+    buf.resize(vtable + vtable_len, 0);
+    let start = buf.len();
+    buf.extend_from_slice(&((start - vtable) as i32).to_le_bytes());
+    let mut children = Vec::new();
+    for (slot, val) in fields{
+        let pos = buf.len();
+        buf[vtable + 4 + 2 * *slot as usize..][..2].copy_from_slice(&((pos - start) as u16).to_le_bytes());
+        match val{
+            Val::Bool(x) => buf.push(*x as u8),
+            Val::U8(x) => buf.push(*x),
+            Val::I16(x) => buf.extend_from_slice(&x.to_le_bytes()),
+            Val::I32(x) => buf.extend_from_slice(&x.to_le_bytes()),
+            Val::I64(x) => buf.extend_from_slice(&x.to_le_bytes()),
+            _ => {
+                buf.extend_from_slice(&[0; 4]);
+                children.push((pos, val));
+            },
+        }
+    }
+    let inline_len = buf.len() - start;
+    buf[vtable..vtable + 2].copy_from_slice(&(vtable_len as u16).to_le_bytes());
+    buf[vtable + 2..vtable + 4].copy_from_slice(&(inline_len as u16).to_le_bytes());
+    for (at, val) in children{
+        let to = match val{
+            Val::Table(t) => write_table(buf, t),
+            Val::Str(s) => {
+                pad_to(buf, 4, 0);
+                let to = buf.len();
+                buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                buf.extend_from_slice(s.as_bytes());
+                buf.push(0);
+                to
+            },
+            Val::Tables(tables) => {
+                pad_to(buf, 4, 0);
+                let to = buf.len();
+                buf.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+                let first = buf.len();
+                buf.resize(first + 4 * tables.len(), 0);
+                for (i, t) in tables.iter().enumerate(){
+                    let table = write_table(buf, t);
+                    patch_offset(buf, first + 4 * i, table);
+                }
+                to
+            },
+            Val::Structs(count, bytes) => {
+                pad_to(buf, 8, 4);
+                let to = buf.len();
+                buf.extend_from_slice(&(*count as u32).to_le_bytes());
+                buf.extend_from_slice(bytes);
+                to
+            },
+            _ => unreachable!(),
+        };
+        patch_offset(buf, at, to);
+    }
+    start
+}
+
+fn flatbuffer(root: &Table) -> Buffer{
+    let mut buf = vec![0; 4];
+    let start = write_table(&mut buf, root);
+    patch_offset(&mut buf, 0, start);
+    buf
+}
+
+// Write one message: the continuation marker, the length of the metadata and the metadata
+// padded so the body starts at a multiple of 8, then the body.
+fn write_message(out: &mut Buffer, header_type: u8, header: Table, body: &[u8]){
+    let message = Table(vec![
+        (0, Val::I16(METADATA_V5)),
+        (1, Val::U8(header_type)),
+        (2, Val::Table(header)),
+        (3, Val::I64(body.len() as i64)),
+    ]);
+    let mut metadata = flatbuffer(&message);
+    pad_to(&mut metadata, 8, 0);
+    out.extend_from_slice(&CONTINUATION);
+    out.extend_from_slice(&(metadata.len() as i32).to_le_bytes());
+    out.extend_from_slice(&metadata);
+    out.extend_from_slice(body);
+}
+
+/// Turn a buffer written by Columns, with the columns described by fields,
+/// into an Arrow IPC stream. None if a schema has no Arrow type here, the buffer does not
+/// hold these columns, or a string or bytes column is too big for 32 bit offsets.
+pub fn columns_to_arrow(fields: &[ArrowField], bytes: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+    let rows = usize::try_from(u64::from_buffer(&mut buf)?).ok()?;
+    let mut body = Vec::new();
+    let mut nodes = Vec::new();
+    let mut buffers = Vec::new();
+    let mut add_buffer = |body: &mut Buffer, data: &[u8]|{
+        for x in [body.len() as i64, data.len() as i64].iter(){
+            buffers.extend_from_slice(&x.to_le_bytes());
+        }
+        body.extend_from_slice(data);
+        pad_to(body, 8, 0);
+    };
+    let mut schema_fields = Vec::new();
+    for field in fields{
+        let (type_type, type_table) = arrow_type(&field.schema)?;
+        schema_fields.push(Table(vec![
+            (0, Val::Str(field.name.clone())),
+            (1, Val::Bool(false)),
+            (2, Val::U8(type_type)),
+            (3, Val::Table(type_table)),
+            (5, Val::Tables(Vec::new())),
+        ]));
+        for x in [rows as i64, 0].iter(){
+            nodes.extend_from_slice(&x.to_le_bytes());
+        }
+        // no nulls, so an empty validity buffer
+        add_buffer(&mut body, &[]);
+        match width(&field.schema){
+            Option::Some(w) => {
+                let column = buf.read_bytes(rows.checked_mul(w)?)?;
+                let mut data = column.to_vec();
+                for value in data.chunks_exact_mut(w){
+                    value.reverse();
+                }
+                add_buffer(&mut body, &data);
+            },
+            Option::None => {
+                let mut offsets = Vec::with_capacity(4 * (rows + 1));
+                let mut data = Vec::new();
+                offsets.extend_from_slice(&0i32.to_le_bytes());
+                for _ in 0..rows{
+                    let value = buf.read_blob()?;
+                    if field.schema == Schema::Str{
+                        std::str::from_utf8(value).ok()?;
+                    }
+                    data.extend_from_slice(value);
+                    offsets.extend_from_slice(&i32::try_from(data.len()).ok()?.to_le_bytes());
+                }
+                add_buffer(&mut body, &offsets);
+                add_buffer(&mut body, &data);
+            },
+        }
+    }
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    let mut out = Vec::new();
+    write_message(&mut out, HEADER_SCHEMA, Table(vec![(0, Val::I16(0)), (1, Val::Tables(schema_fields))]), &[]);
+    let batch = Table(vec![
+        (0, Val::I64(rows as i64)),
+        (1, Val::Structs(fields.len(), nodes)),
+        (2, Val::Structs(buffers.len() / 16, buffers)),
+    ]);
+    write_message(&mut out, HEADER_RECORD_BATCH, batch, &body);
+    out.extend_from_slice(&CONTINUATION);
+    out.extend_from_slice(&[0; 4]);
+    Option::Some(out)
+}
+
+// Reading FlatBuffers, every access checked against the end of the buffer.
+#[derive(Clone, Copy)]
+struct Tbl<'a>{
+    buf: &'a [u8],
+    pos: usize,
+}
+
+fn le<const N: usize>(buf: &[u8], pos: usize) -> Option<[u8; N]>{
+    <[u8; N]>::try_from(buf.get(pos..pos.checked_add(N)?)?).ok()
+}
+
+fn follow(buf: &[u8], at: usize) -> Option<usize>{
+    at.checked_add(u32::from_le_bytes(le(buf, at)?) as usize)
+}
+
+impl<'a> Tbl<'a>{
+    fn root(buf: &'a [u8]) -> Option<Self>{
+        Option::Some(Self{ buf, pos: follow(buf, 0)? })
+    }
+    fn field(&self, slot: usize) -> Option<usize>{
+        let vtable = usize::try_from(self.pos as i64 - i32::from_le_bytes(le(self.buf, self.pos)?) as i64).ok()?;
+        let vtable_len = u16::from_le_bytes(le(self.buf, vtable)?) as usize;
+        if 4 + 2 * slot + 2 > vtable_len{
+            return Option::None;
+        }
+        match u16::from_le_bytes(le(self.buf, vtable + 4 + 2 * slot)?){
+            0 => Option::None,
+            off => Option::Some(self.pos + off as usize),
+        }
+    }
+    fn u8(&self, slot: usize) -> Option<u8>{
+        match self.field(slot){
+            Option::Some(at) => self.buf.get(at).copied(),
+            Option::None => Option::Some(0),
+        }
+    }
+    fn i16(&self, slot: usize) -> Option<i16>{
+        match self.field(slot){
+            Option::Some(at) => Option::Some(i16::from_le_bytes(le(self.buf, at)?)),
+            Option::None => Option::Some(0),
+        }
+    }
+    fn i32(&self, slot: usize) -> Option<i32>{
+        match self.field(slot){
+            Option::Some(at) => Option::Some(i32::from_le_bytes(le(self.buf, at)?)),
+            Option::None => Option::Some(0),
+        }
+    }
+    fn i64(&self, slot: usize) -> Option<i64>{
+        match self.field(slot){
+            Option::Some(at) => Option::Some(i64::from_le_bytes(le(self.buf, at)?)),
+            Option::None => Option::Some(0),
+        }
+    }
+    fn table(&self, slot: usize) -> Option<Tbl<'a>>{
+        Option::Some(Tbl{ buf: self.buf, pos: follow(self.buf, self.field(slot)?)? })
+    }
+    // where the elements of a vector start and how many there are, empty if it is missing
+    fn vector(&self, slot: usize) -> Option<(usize, usize)>{
+        let at = match self.field(slot){
+            Option::Some(at) => follow(self.buf, at)?,
+            Option::None => return Option::Some((0, 0)),
+        };
+        Option::Some((at + 4, u32::from_le_bytes(le(self.buf, at)?) as usize))
+    }
+    fn string(&self, slot: usize) -> Option<&'a str>{
+        let at = follow(self.buf, self.field(slot)?)?;
+        let len = u32::from_le_bytes(le(self.buf, at)?) as usize;
+        std::str::from_utf8(self.buf.get(at + 4..(at + 4).checked_add(len)?)?).ok()
+    }
+    fn tables(&self, slot: usize) -> Option<Vec<Tbl<'a>>>{
+        let (start, len) = self.vector(slot)?;
+        (0..len).map(|i| Option::Some(Tbl{ buf: self.buf, pos: follow(self.buf, start + 4 * i)? })).collect()
+    }
+    // a vector of structs of two i64
+    fn pairs(&self, slot: usize) -> Option<Vec<(i64, i64)>>{
+        let (start, len) = self.vector(slot)?;
+        (0..len).map(|i| Option::Some((i64::from_le_bytes(le(self.buf, start + 16 * i)?), i64::from_le_bytes(le(self.buf, start + 16 * i + 8)?)))).collect()
+    }
+}
+
+fn read_field(field: Tbl) -> Option<ArrowField>{
+    let kind = field.table(3)?;
+    let schema = match field.u8(2)?{
+        TYPE_INT if kind.u8(1)? == 0 => match kind.i32(0)?{
+            8 => Schema::U8,
+            16 => Schema::U16,
+            32 => Schema::U32,
+            64 => Schema::U64,
+            _ => return Option::None,
+        },
+        TYPE_FLOAT => match kind.i16(0)?{
+            1 => Schema::F32,
+            2 => Schema::F64,
+            _ => return Option::None,
+        },
+        TYPE_UTF8 => Schema::Str,
+        TYPE_BINARY => Schema::Bytes,
+        _ => return Option::None,
+    };
+    if field.field(4).is_some() || field.vector(5)?.1 != 0{
+        return Option::None;
+    }
+    Option::Some(ArrowField{ name: String::from(field.string(0).unwrap_or("")), schema })
+}
+
+/// Read an Arrow IPC stream or file into the fields of its schema and a buffer as Columns
+/// writes it, with the rows of all record batches. None if it is not a stream of the types,
+/// and without the nulls, dictionaries and compression, that columns_to_arrow writes.
+pub fn arrow_to_columns(stream: &[u8]) -> Option<(Vec<ArrowField>, Buffer)>{
+    let mut pos = if stream.starts_with(b"ARROW1") { 8 } else { 0 };
+    let mut fields: Option<Vec<ArrowField>> = Option::None;
+    let mut columns: Vec<Buffer> = Vec::new();
+    let mut rows = 0usize;
+    loop{
+        let mut len = i32::from_le_bytes(le(stream, pos)?);
+        pos += 4;
+        if len == -1{
+            len = i32::from_le_bytes(le(stream, pos)?);
+            pos += 4;
+        }
+        if len == 0{
+            break;
+        }
+        let metadata = stream.get(pos..pos.checked_add(usize::try_from(len).ok()?)?)?;
+        pos += metadata.len();
+        let message = Tbl::root(metadata)?;
+        let body_len = usize::try_from(message.i64(3)?).ok()?;
+        let body = stream.get(pos..pos.checked_add(body_len)?)?;
+        pos += body_len;
+        let header = message.table(2)?;
+        match (message.u8(1)?, &fields){
+            (HEADER_SCHEMA, Option::None) => {
+                if header.i16(0)? != 0{
+                    return Option::None;
+                }
+                let read: Option<Vec<ArrowField>> = header.tables(1)?.into_iter().map(read_field).collect();
+                let read = read?;
+                columns = vec![Vec::new(); read.len()];
+                fields = Option::Some(read);
+            },
+            (HEADER_RECORD_BATCH, Option::Some(fields)) => {
+                if header.field(3).is_some(){
+                    return Option::None;
+                }
+                let len = usize::try_from(header.i64(0)?).ok()?;
+                let nodes = header.pairs(1)?;
+                let buffers = header.pairs(2)?;
+                let buffer = |i: usize| -> Option<&[u8]>{
+                    let (offset, len) = *buffers.get(i)?;
+                    let offset = usize::try_from(offset).ok()?;
+                    body.get(offset..offset.checked_add(usize::try_from(len).ok()?)?)
+                };
+                if nodes.len() != fields.len(){
+                    return Option::None;
+                }
+                let mut b = 0;
+                for (i, field) in fields.iter().enumerate(){
+                    if nodes[i] != (len as i64, 0){
+                        return Option::None;
+                    }
+                    // the validity buffer, unused without nulls
+                    buffer(b)?;
+                    b += 1;
+                    match width(&field.schema){
+                        Option::Some(w) => {
+                            let data = buffer(b)?.get(..len.checked_mul(w)?)?;
+                            b += 1;
+                            for value in data.chunks_exact(w){
+                                columns[i].extend(value.iter().rev());
+                            }
+                        },
+                        Option::None => {
+                            let offsets = buffer(b)?;
+                            let data = buffer(b + 1)?;
+                            b += 2;
+                            let offset = |j: usize| -> Option<usize>{ usize::try_from(i32::from_le_bytes(le(offsets, 4 * j)?)).ok() };
+                            for j in 0..len{
+                                let value = data.get(offset(j)?..offset(j + 1)?)?;
+                                if field.schema == Schema::Str{
+                                    std::str::from_utf8(value).ok()?;
+                                }
+                                (value.len() as u64).into_buffer(&mut columns[i]);
+                                columns[i].extend_from_slice(value);
+                            }
+                        },
+                    }
+                }
+                rows = rows.checked_add(len)?;
+            },
+            _ => return Option::None,
+        }
+    }
+    let fields = fields?;
+    let mut out = Vec::with_capacity(8 + columns.iter().map(|c| c.len()).sum::<usize>());
+    (rows as u64).into_buffer(&mut out);
+    for column in columns{
+        out.extend_from_slice(&column);
+    }
+    Option::Some((fields, out))
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::arrow::*;
+    use crate::columnar::Columns;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Row{ a: u8, b: u16, c: u32, d: u64, e: f32, f: f64, g: String, h: ByteVec }
+
+    columnar!{ Row{ a: u8, b: u16, c: u32, d: u64, e: f32, f: f64, g: String, h: ByteVec } }
+
+    #[test]
+    fn test_arrow(){
+        let rows = Columns(vec![
+            Row{ a: 1, b: 2, c: 3, d: 4, e: 0.5, f: 1.5, g: String::from("x"), h: ByteVec(vec![1, 2]) },
+            Row{ a: 5, b: 6, c: 7, d: 8, e: -0.5, f: -1.5, g: String::new(), h: ByteVec(Vec::new()) },
+            Row{ a: 9, b: 10, c: 11, d: 12, e: 2.5, f: 3.5, g: String::from("long text"), h: ByteVec(vec![3]) },
+        ]);
+        let fields: Vec<ArrowField> = [Schema::U8, Schema::U16, Schema::U32, Schema::U64, Schema::F32, Schema::F64, Schema::Str, Schema::Bytes]
+            .iter().enumerate().map(|(i, s)| ArrowField::new(&format!("c{}", i), s.clone())).collect();
+        let bytes = encode(&rows);
+        let stream = columns_to_arrow(&fields, &bytes).unwrap();
+        assert_eq!(CONTINUATION, stream[..4]);
+        assert_eq!([0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0], stream[stream.len() - 8..]);
+        // the schema message
+        let len = i32::from_le_bytes(le(&stream, 4).unwrap()) as usize;
+        assert_eq!(0, len % 8);
+        let message = Tbl::root(&stream[8..8 + len]).unwrap();
+        assert_eq!((Some(METADATA_V5), Some(HEADER_SCHEMA), Some(0)), (message.i16(0), message.u8(1), message.i64(3)));
+        let schema = message.table(2).unwrap().tables(1).unwrap();
+        assert_eq!(Some("c6"), schema[6].string(0));
+        assert_eq!(Some(TYPE_UTF8), schema[6].u8(2));
+        assert_eq!(Some(32), schema[2].table(3).unwrap().i32(0));
+        // the batch: the u16 column holds 2, 6 and 10 little endian
+        let batch_at = 8 + len;
+        let batch_len = i32::from_le_bytes(le(&stream, batch_at + 4).unwrap()) as usize;
+        let batch = Tbl::root(&stream[batch_at + 8..batch_at + 8 + batch_len]).unwrap();
+        let batch = batch.table(2).unwrap();
+        assert_eq!(Some(3), batch.i64(0));
+        let buffers = batch.pairs(2).unwrap();
+        assert_eq!(2 * 6 + 3 * 2, buffers.len());
+        assert!(buffers.iter().all(|(offset, _)| offset % 8 == 0));
+        let body = &stream[batch_at + 8 + batch_len..];
+        let (offset, len) = buffers[3];
+        assert_eq!(vec![2, 0, 6, 0, 10, 0], body[offset as usize..(offset + len) as usize].to_vec());
+        let (read_fields, read) = arrow_to_columns(&stream).unwrap();
+        assert_eq!(fields, read_fields);
+        assert_eq!(bytes, read);
+        // as an Arrow file, and with two batches
+        let mut file = b"ARROW1\0\0".to_vec();
+        file.extend_from_slice(&stream);
+        assert_eq!(Some((fields.clone(), bytes.clone())), arrow_to_columns(&file));
+        let mut twice = stream[..stream.len() - 8].to_vec();
+        twice.extend_from_slice(&stream[batch_at..]);
+        let (_, read) = arrow_to_columns(&twice).unwrap();
+        let mut doubled = rows.clone();
+        doubled.extend(rows.0.clone());
+        assert_eq!(encode(&doubled), read);
+        // no rows, schemas without an Arrow type and buffers that do not fit
+        let empty = columns_to_arrow(&fields[..2], &encode(&Columns(Vec::<(u8, u16)>::new()))).unwrap();
+        assert_eq!(Some((fields[..2].to_vec(), vec![0; 8])), arrow_to_columns(&empty));
+        assert_eq!(None, columns_to_arrow(&[ArrowField::new("v", Schema::Vec(Box::new(Schema::U8)))], &encode(&Columns(Vec::<(u8, u8)>::new()))));
+        assert_eq!(None, columns_to_arrow(&fields, &bytes[..bytes.len() - 1]));
+        assert_eq!(None, columns_to_arrow(&fields[..7], &bytes));
+        for cut in [0, 8, 100, stream.len() - 9].iter(){
+            assert_eq!(None, arrow_to_columns(&stream[..*cut]));
+        }
+    }
+}
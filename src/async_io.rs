@@ -0,0 +1,288 @@
+//! Buffers, values and frames over tokio's AsyncRead and AsyncWrite, enabled with the `async` feature.
+//! buffer_read_file_async and buffer_write_file_async are buffer_read_file and buffer_write_file
+//! without blocking the runtime. AsyncBufferWriter and AsyncBufferReader are BufferWriter and
+//! BufferReader for async streams, with the same layout as a Buffer. write_frame, read_frame,
+//! send_over and recv_from put every value in its own frame, a u64 length and then the bytes,
+//! the same frames as net, so an async server can talk to a blocking client.
+//! # Example
+//! ```
+//! use bin_buffer::async_io::*;
+//! tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async{
+//!     let (mut client, mut server) = tokio::io::duplex(64);
+//!     let sending = async{
+//!         send_over(&(1u32, String::from("hi")), &mut client).await.unwrap();
+//!         send_over(&2u8, &mut client).await.unwrap();
+//!     };
+//!     let receiving = async{
+//!         let x = recv_from::<(u32, String), _>(&mut server).await.unwrap();
+//!         (x, recv_from::<u8, _>(&mut server).await.unwrap())
+//!     };
+//!     let (_, received) = tokio::join!(sending, receiving);
+//!     assert_eq!(((1, String::from("hi")), 2), received);
+//! });
+//! ```
+use std::io;
+use std::convert::TryFrom;
+use tokio::io::{ AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt };
+use crate::{ Buffer, Bufferable, BufferError, DecodeLimits, ReadBuffer };
+use crate::net::DEFAULT_MAX_FRAME_LEN;
+
+/// Bytes AsyncBufferWriter gathers before writing them and AsyncBufferReader asks for at least.
+const STREAM_BUFFER_LEN: usize = 64 << 10;
+
+/// Writes a buffer to a file, see buffer_write_file, on tokio's blocking pool.
+#[cfg(feature = "fs")]
+pub async fn buffer_write_file_async(path: &std::path::Path, vec: &[u8]) -> bool{
+    tokio::fs::write(path, vec).await.is_ok()
+}
+
+/// Reads a buffer from a file, see buffer_read_file, on tokio's blocking pool.
+#[cfg(feature = "fs")]
+pub async fn buffer_read_file_async(path: &std::path::Path) -> Option<Buffer>{
+    tokio::fs::read(path).await.ok()
+}
+
+/// Write bytes as one frame.
+pub async fn write_frame<W: AsyncWrite + Unpin + ?Sized>(bytes: &[u8], w: &mut W) -> io::Result<()>{
+    w.write_all(&(bytes.len() as u64).to_be_bytes()).await?;
+    w.write_all(bytes).await?;
+    w.flush().await
+}
+
+/// Read one frame. Fails with InvalidData if it is longer than max_len
+/// and with UnexpectedEof if the stream ends inside it.
+pub async fn read_frame<R: AsyncRead + Unpin + ?Sized>(r: &mut R, max_len: usize) -> io::Result<Buffer>{
+    let mut len = [0u8; 8];
+    r.read_exact(&mut len).await?;
+    let len = usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX);
+    if len > max_len{
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+    let mut vec = vec![0u8; len];
+    r.read_exact(&mut vec).await?;
+    Ok(vec)
+}
+
+/// Encode x and write it as one frame.
+pub async fn send_over<T: Bufferable, W: AsyncWrite + Unpin + ?Sized>(x: &T, w: &mut W) -> io::Result<()>{
+    write_frame(&crate::encode(x), w).await
+}
+
+/// Read one frame of at most DEFAULT_MAX_FRAME_LEN bytes and decode it.
+/// Fails with InvalidData if the frame does not hold exactly one T.
+pub async fn recv_from<T: Bufferable, R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> io::Result<T>{
+    recv_from_with_max(r, DEFAULT_MAX_FRAME_LEN).await
+}
+
+/// recv_from with a custom largest frame length.
+pub async fn recv_from_with_max<T: Bufferable, R: AsyncRead + Unpin + ?Sized>(r: &mut R, max_len: usize) -> io::Result<T>{
+    crate::net::decode_frame(read_frame(r, max_len).await?)
+}
+
+/// Writes values to an async file or socket, with the same layout as a Buffer, see BufferWriter.
+/// Values are gathered in a buffer of 64 KiB by default and written once it is full,
+/// so call flush or finish to write the last of them.
+pub struct AsyncBufferWriter<W: AsyncWrite + Unpin>{
+    writer: W,
+    buffer: Buffer,
+    capacity: usize,
+    written: u64,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncBufferWriter<W>{
+    /// Write to writer, gathering 64 KiB at a time.
+    pub fn new(writer: W) -> Self{
+        Self::with_capacity(STREAM_BUFFER_LEN, writer)
+    }
+    /// Write to writer, gathering capacity bytes at a time.
+    pub fn with_capacity(capacity: usize, writer: W) -> Self{
+        Self{ writer, buffer: Vec::with_capacity(capacity), capacity, written: 0 }
+    }
+    /// Encode x to the writer. If the write fails, x and the values gathered before it are lost.
+    pub async fn write<T: Bufferable>(&mut self, x: &T) -> io::Result<&mut Self>{
+        let len = self.buffer.len();
+        x.copy_into_buffer(&mut self.buffer);
+        self.written += (self.buffer.len() - len) as u64;
+        if self.buffer.len() >= self.capacity{
+            self.write_buffer().await?;
+        }
+        Ok(self)
+    }
+    /// Bytes of the values written so far, including those not handed to the writer yet.
+    pub fn written(&self) -> u64{
+        self.written
+    }
+    /// The writer.
+    pub fn get_ref(&self) -> &W{
+        &self.writer
+    }
+    /// Write what is gathered and flush the writer.
+    pub async fn flush(&mut self) -> io::Result<()>{
+        self.write_buffer().await?;
+        self.writer.flush().await
+    }
+    /// Flush and give back the writer.
+    pub async fn finish(mut self) -> io::Result<W>{
+        self.flush().await?;
+        Ok(self.writer)
+    }
+
+    async fn write_buffer(&mut self) -> io::Result<()>{
+        let res = self.writer.write_all(&self.buffer).await;
+        self.buffer.clear();
+        res
+    }
+}
+
+/// Reads values from an async file or socket, as written by AsyncBufferWriter or found in a
+/// Buffer, see BufferReader. It never waits for more than one read at a time, so it works on
+/// sockets where the other side waits for an answer.
+pub struct AsyncBufferReader<R: AsyncRead + Unpin>{
+    reader: R,
+    buffer: Buffer,
+    pos: usize,
+    end: bool,
+    limits: DecodeLimits,
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufferReader<R>{
+    /// Read from reader.
+    pub fn new(reader: R) -> Self{
+        Self{ reader, buffer: Vec::new(), pos: 0, end: false, limits: DecodeLimits::default() }
+    }
+    /// Decode values with limits.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self{
+        self.limits = limits;
+        self
+    }
+    /// Read the next value. Fails with UnexpectedEof if the reader ends before it does
+    /// and with InvalidData if the bytes are not a T.
+    pub async fn read<T: Bufferable>(&mut self) -> io::Result<T>{
+        loop{
+            let bytes = &self.buffer[self.pos..];
+            let mut buf = ReadBuffer::borrowed(bytes, self.limits);
+            if let Option::Some(x) = T::from_buffer(&mut buf){
+                self.pos += buf.consumed();
+                return Ok(x);
+            }
+            let error = match T::validate(&mut ReadBuffer::borrowed(bytes, self.limits)){
+                Ok(()) => BufferError::Invalid,
+                Err(e) => e,
+            };
+            match error{
+                BufferError::UnexpectedEof{ .. } | BufferError::Invalid if !self.end => self.fill().await?,
+                BufferError::UnexpectedEof{ .. } => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, error)),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+            }
+        }
+    }
+    /// Whether every byte has been read, reading more to find out if needed.
+    pub async fn is_at_end(&mut self) -> io::Result<bool>{
+        while self.pos == self.buffer.len() && !self.end{
+            self.fill().await?;
+        }
+        Ok(self.pos == self.buffer.len())
+    }
+    /// The reader.
+    pub fn get_ref(&self) -> &R{
+        &self.reader
+    }
+
+    // One read, with room for as much again as is kept, at least 64 KiB.
+    async fn fill(&mut self) -> io::Result<()>{
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        let len = self.buffer.len();
+        self.buffer.resize(len + len.max(STREAM_BUFFER_LEN), 0);
+        let read = loop{
+            match self.reader.read(&mut self.buffer[len..]).await{
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buffer.truncate(len);
+                    return Err(e);
+                },
+            }
+        };
+        self.buffer.truncate(len + read);
+        self.end = read == 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::async_io::*;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output{
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(f)
+    }
+
+    #[test]
+    fn test_async_stream(){
+        block_on(async{
+            let values = vec![(String::from("a"), vec![1u32; 1000]), (String::from("b"), Vec::new())];
+            // a pipe smaller than the values, so both sides have to wait on each other
+            let (client, server) = tokio::io::duplex(100);
+            let writing = async{
+                let mut writer = AsyncBufferWriter::with_capacity(16, client);
+                for x in &values{
+                    writer.write(x).await.unwrap();
+                }
+                assert_eq!(encoded_size_of(&values[0]) as u64 + encoded_size_of(&values[1]) as u64, writer.written());
+                drop(writer.finish().await.unwrap());
+            };
+            let reading = async{
+                let mut reader = AsyncBufferReader::new(server);
+                let a = reader.read::<(String, Vec<u32>)>().await.unwrap();
+                let b = reader.read::<(String, Vec<u32>)>().await.unwrap();
+                assert!(reader.is_at_end().await.unwrap());
+                assert_eq!(std::io::ErrorKind::UnexpectedEof, reader.read::<u8>().await.unwrap_err().kind());
+                vec![a, b]
+            };
+            let (_, read) = tokio::join!(writing, reading);
+            assert_eq!(values, read);
+            let mut reader = AsyncBufferReader::new(&[0, 0, 0, 0, 0, 0, 0, 1, 0xff, 0][..]);
+            assert_eq!(std::io::ErrorKind::InvalidData, reader.read::<String>().await.unwrap_err().kind());
+        });
+    }
+
+    #[test]
+    fn test_async_frames(){
+        block_on(async{
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            // a blocking client talking to the async server
+            let client = std::thread::spawn(move ||{
+                let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                net::send_over(&vec![1u16, 2, 3], &mut stream).unwrap();
+                net::recv_from::<String, _>(&mut stream).unwrap()
+            });
+            let (mut stream, _) = listener.accept().await.unwrap();
+            assert_eq!(vec![1u16, 2, 3], recv_from::<Vec<u16>, _>(&mut stream).await.unwrap());
+            send_over(&String::from("ok"), &mut stream).await.unwrap();
+            assert_eq!(String::from("ok"), client.join().unwrap());
+            let mut frames = Vec::new();
+            write_frame(&[1, 2, 3], &mut frames).await.unwrap();
+            assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 3, 1, 2, 3], frames);
+            assert_eq!(std::io::ErrorKind::InvalidData, read_frame(&mut &frames[..], 2).await.unwrap_err().kind());
+            assert_eq!(std::io::ErrorKind::UnexpectedEof, read_frame(&mut &frames[..5], 10).await.unwrap_err().kind());
+            assert_eq!(std::io::ErrorKind::InvalidData, recv_from::<u8, _>(&mut &frames[..]).await.unwrap_err().kind());
+        });
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_async_files(){
+        block_on(async{
+            let path = std::env::temp_dir().join("bin_buffer_async_test.bin");
+            let bytes = encode(&(7u32, String::from("async")));
+            assert!(buffer_write_file_async(&path, &bytes).await);
+            assert_eq!(Some(bytes.clone()), buffer_read_file_async(&path).await);
+            assert_eq!(Some(bytes), buffer_read_file(&path));
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(None, buffer_read_file_async(&path).await);
+        });
+    }
+}
@@ -0,0 +1,174 @@
+//! Looks inside buffer files: binbuf hex, info, sections, verify and decode, see usage.
+use std::path::Path;
+use bin_buffer::*;
+use bin_buffer::schema::{ read_self_describing, Schema, Value };
+use bin_buffer::sections::SectionReader;
+
+const USAGE: &str = "usage: binbuf <command> <file> [args]
+commands:
+  hex <file> [offset] [len]   hexdump the file, or len bytes from offset
+  info <file>                 size, CRC-32 and what the file looks like
+  sections <file>             list the sections of a sections file or archive
+  verify <file> [--repair | --salvage <to>]
+                              check an archive or journal, cutting off or copying
+                              what is left of a journal with a broken tail
+  decode <file> <type>...     decode the types one after the other;
+                              types: u8 u16 u32 u64 f32 f64 string bytes vec<type>,
+                              with a * after the last one it is read until the end";
+
+fn fail(text: &str) -> !{
+    eprintln!("{}", text);
+    std::process::exit(1);
+}
+
+fn parse_type(name: &str) -> Option<Schema>{
+    Option::Some(match name{
+        "u8" => Schema::U8,
+        "u16" => Schema::U16,
+        "u32" => Schema::U32,
+        "u64" | "usize" => Schema::U64,
+        "f32" => Schema::F32,
+        "f64" => Schema::F64,
+        "string" | "str" => Schema::Str,
+        "bytes" => Schema::Bytes,
+        _ => {
+            let inner = name.strip_prefix("vec<")?.strip_suffix('>')?;
+            Schema::Vec(Box::new(parse_type(inner)?))
+        },
+    })
+}
+
+fn show(value: &Value) -> String{
+    match value{
+        Value::UInt(x) => x.to_string(),
+        Value::Float(x) => x.to_string(),
+        Value::Str(s) => format!("{:?}", s),
+        Value::Bytes(b) => format!("{} bytes {}", b.len(), hex_line(&b[..b.len().min(16)])),
+        Value::Seq(items) => format!("[{}]", items.iter().map(show).collect::<Vec<_>>().join(", ")),
+        Value::Map(entries) => format!("{{{}}}", entries.iter().map(|(k, v)| format!("{}: {}", show(k), show(v))).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+fn hex_line(bytes: &[u8]) -> String{
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+fn hexdump(bytes: &[u8], offset: usize){
+    for (i, row) in bytes.chunks(16).enumerate(){
+        let text: String = row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        println!("{:08x}  {:<47}  |{}|", offset + i * 16, hex_line(row), text);
+    }
+}
+
+fn info(bytes: &[u8]){
+    println!("size: {} bytes", bytes.len());
+    println!("crc32: {:08x}", journal::crc32(bytes));
+    let mut buf = ReadBuffer::from_raw(bytes.to_vec());
+    match read_self_describing(&mut buf){
+        Option::Some((schema, _)) if buf.remaining() == 0 => println!("self describing, schema: {:?}", schema),
+        _ => {},
+    }
+    if let Option::Some(reader) = SectionReader::<String>::new(bytes){
+        println!("sections file or archive with {} sections", reader.len());
+    }
+    if bytes.len() >= 4{
+        println!("as a versioned value: version {}", u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+}
+
+fn sections(bytes: &[u8]){
+    let reader = SectionReader::<String>::new(bytes).unwrap_or_else(|| fail("not a sections file or archive"));
+    for key in reader.keys(){
+        let section = reader.section(key).unwrap();
+        println!("{:>12} bytes  {}", section.remaining(), key);
+    }
+}
+
+fn decode_types(bytes: &[u8], types: &[String]){
+    let mut schemas = Vec::new();
+    let mut repeat = false;
+    for (i, name) in types.iter().enumerate(){
+        let name = match name.strip_suffix('*'){
+            Option::Some(name) if i + 1 == types.len() => { repeat = true; name },
+            _ => name.as_str(),
+        };
+        schemas.push(parse_type(name).unwrap_or_else(|| fail(&format!("unknown type {}\n{}", name, USAGE))));
+    }
+    let mut buf = ReadBuffer::from_raw(bytes.to_vec());
+    let mut next = 0;
+    loop{
+        let pos = bytes.len() - buf.remaining();
+        if next == schemas.len(){
+            if !repeat || buf.remaining() == 0{
+                break;
+            }
+            next = schemas.len() - 1;
+        }
+        match schemas[next].decode(&mut buf){
+            Option::Some(value) => println!("{:08x}  {:?}  {}", pos, schemas[next], show(&value)),
+            Option::None => fail(&format!("{:08x}  could not decode {:?}", pos, schemas[next])),
+        }
+        next += 1;
+    }
+    if buf.remaining() != 0{
+        println!("{} bytes left over", buf.remaining());
+    }
+}
+
+fn verify(path: &Path, bytes: &[u8], args: &[String]){
+    if let Option::Some(archive) = archive::EntryArchive::from_bytes(bytes.to_vec()){
+        println!("intact archive with {} entries", archive.len());
+        return;
+    }
+    let report = journal::verify(path).unwrap_or_else(|e| fail(&e.to_string()));
+    if report.is_intact(){
+        println!("intact journal with {} records", report.records);
+        return;
+    }
+    println!("journal with {} records that check out in the first {} of {} bytes", report.records, report.valid_len, report.file_len);
+    match args.first().map(|a| a.as_str()){
+        Option::None => std::process::exit(1),
+        Option::Some("--repair") if args.len() == 1 => {
+            journal::repair(path).unwrap_or_else(|e| fail(&e.to_string()));
+            println!("cut off {} bytes", report.file_len - report.valid_len);
+        },
+        Option::Some("--salvage") if args.len() == 2 => {
+            journal::salvage(path, Path::new(&args[1])).unwrap_or_else(|e| fail(&e.to_string()));
+            println!("wrote the {} records to {}", report.records, args[1]);
+        },
+        _ => fail(USAGE),
+    }
+}
+
+fn number(arg: Option<&String>, default: usize) -> usize{
+    match arg{
+        Option::None => default,
+        Option::Some(x) => {
+            let parsed = match x.strip_prefix("0x"){
+                Option::Some(hex) => usize::from_str_radix(hex, 16),
+                Option::None => x.parse(),
+            };
+            parsed.unwrap_or_else(|_| fail(&format!("not a number: {}", x)))
+        },
+    }
+}
+
+fn main(){
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2{
+        fail(USAGE);
+    }
+    let bytes = buffer_read_file(Path::new(&args[1])).unwrap_or_else(|| fail(&format!("could not read {}", args[1])));
+    match args[0].as_str(){
+        "hex" => {
+            let offset = number(args.get(2), 0).min(bytes.len());
+            let len = number(args.get(3), bytes.len() - offset).min(bytes.len() - offset);
+            hexdump(&bytes[offset..offset + len], offset);
+        },
+        "info" => info(&bytes),
+        "sections" => sections(&bytes),
+        "verify" => verify(Path::new(&args[1]), &bytes, &args[2..]),
+        "decode" if args.len() > 2 => decode_types(&bytes, &args[2..]),
+        _ => fail(USAGE),
+    }
+}
@@ -0,0 +1,16 @@
+//! Writes the test vectors of bin_buffer::vectors to the directory given as the first argument.
+use std::path::Path;
+
+fn main(){
+    let dir = match std::env::args().nth(1){
+        Some(dir) => dir,
+        None => {
+            eprintln!("usage: test_vectors <dir>");
+            std::process::exit(2);
+        },
+    };
+    if let Err(e) = bin_buffer::vectors::write_dir(Path::new(&dir)){
+        eprintln!("could not write test vectors to {}: {}", dir, e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{ Bufferable, BufferError, ReadBuffer, Sink };
+
+/// A Vec of bools that is written as 8 bools per byte.
+/// The number of bools comes first (u64), then the bytes, the first bool in the highest bit
+/// of the first byte. The bits after the last bool are 0, reading fails when they are not.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = BitVec(vec![true, false, true, true, false, false, false, false, true]);
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// assert_eq!(vec![0,0,0,0,0,0,0,9, 0b1011_0000, 0b1000_0000], buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(x), BitVec::from_buffer(&mut buffer));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BitVec(pub Vec<bool>);
+
+impl std::ops::Deref for BitVec{
+    type Target = Vec<bool>;
+    fn deref(&self) -> &Vec<bool>{
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for BitVec{
+    fn deref_mut(&mut self) -> &mut Vec<bool>{
+        &mut self.0
+    }
+}
+
+impl From<Vec<bool>> for BitVec{
+    fn from(vec: Vec<bool>) -> Self{
+        Self(vec)
+    }
+}
+
+impl From<BitVec> for Vec<bool>{
+    fn from(bits: BitVec) -> Self{
+        bits.0
+    }
+}
+
+fn byte_len(bits: usize) -> usize{
+    bits.div_ceil(8)
+}
+
+// The bytes of len bools, None if bits after the last one are set.
+fn check_padding(len: usize, bytes: &[u8]) -> Option<()>{
+    let used = len % 8;
+    if used != 0 && bytes[bytes.len() - 1] & (0xff >> used) != 0{
+        return Option::None;
+    }
+    Option::Some(())
+}
+
+impl Bufferable for BitVec{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (self.0.len() as u64).into_buffer(vec);
+        let bytes: Vec<u8> = self.0.chunks(8).map(|chunk|{
+            chunk.iter().enumerate().fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << (7 - i)))
+        }).collect();
+        vec.write_bytes(&bytes);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let len = buf.read_len()?;
+        buf.use_bytes(len)?;
+        let bytes = buf.read_bytes(byte_len(len))?;
+        check_padding(len, bytes)?;
+        Option::Some(Self((0..len).map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0).collect()))
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + byte_len(self.0.len())
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let max = buf.limits.max_elements;
+        let len = buf.check_prefix(max)?;
+        buf.check_use_bytes(len)?;
+        let bytes = buf.check_bytes(byte_len(len))?;
+        check_padding(len, bytes).ok_or(BufferError::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    #[test]
+    fn test_bit_vec(){
+        for len in [0usize, 1, 7, 8, 9, 100].iter(){
+            let x = BitVec((0..*len).map(|i| i % 3 == 0).collect());
+            let bytes = encode(&x);
+            assert_eq!(x.buffer_len(), bytes.len());
+            assert_eq!(8 + len.div_ceil(8), bytes.len());
+            assert_eq!(Some(x), decode(&bytes));
+            assert_eq!(Ok(()), validate::<BitVec>(&mut ReadBuffer::from_raw(bytes)));
+        }
+        // a set bit after the last bool
+        let bad = vec![0, 0, 0, 0, 0, 0, 0, 3, 0b1110_0001];
+        assert_eq!(None, decode::<BitVec>(&bad));
+        assert_eq!(Err(BufferError::Invalid), validate::<BitVec>(&mut ReadBuffer::from_raw(bad)));
+        assert_eq!(None, decode::<BitVec>(&[0, 0, 0, 0, 0, 0, 0, 9, 0]));
+    }
+}
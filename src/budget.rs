@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+
+/// A budget of bytes that decoding may allocate, shared by every ReadBuffer it is given to.
+/// DecodeLimits::max_total_bytes caps a single ReadBuffer, a tracker caps a whole request:
+/// its forks, its takes and any other buffers decoded for it, from any thread.
+/// Strings and collections count what they take in memory, as ReadBuffer::use_bytes does.
+/// Once the budget is used up every decode with the tracker fails,
+/// and ReadBuffer::read_checked gives BufferError::BudgetExceeded.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let tracker = AllocTracker::new(100);
+/// let mut buffer = Vec::new();
+/// String::from("0123456789").into_buffer(&mut buffer);
+/// vec![0u32; 30].into_buffer(&mut buffer);
+/// let mut read = ReadBuffer::from_raw(buffer).with_tracker(tracker.clone());
+/// assert_eq!(Ok(String::from("0123456789")), read.read_checked::<String>());
+/// assert_eq!(Err(BufferError::BudgetExceeded), read.read_checked::<Vec<u32>>());
+/// assert_eq!(10, tracker.used());
+/// assert!(tracker.exceeded());
+/// ```
+#[derive(Clone, Debug)]
+pub struct AllocTracker{
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner{
+    budget: usize,
+    used: AtomicUsize,
+    exceeded: AtomicBool,
+}
+
+impl AllocTracker{
+    /// A tracker that allows budget bytes.
+    pub fn new(budget: usize) -> Self{
+        Self{ inner: Arc::new(Inner{ budget, used: AtomicUsize::new(0), exceeded: AtomicBool::new(false) }) }
+    }
+    /// The bytes it allows.
+    pub fn budget(&self) -> usize{
+        self.inner.budget
+    }
+    /// The bytes counted so far.
+    pub fn used(&self) -> usize{
+        self.inner.used.load(Ordering::Relaxed)
+    }
+    /// The bytes that are left.
+    pub fn remaining(&self) -> usize{
+        self.budget() - self.used()
+    }
+    /// Whether a decode asked for more than was left.
+    pub fn exceeded(&self) -> bool{
+        self.inner.exceeded.load(Ordering::Relaxed)
+    }
+    /// Start counting from 0 again, for the next request.
+    pub fn reset(&self){
+        self.inner.used.store(0, Ordering::Relaxed);
+        self.inner.exceeded.store(false, Ordering::Relaxed);
+    }
+    // Count bytes, false and marked exceeded if they do not fit.
+    pub(crate) fn charge(&self, bytes: usize) -> bool{
+        let res = self.inner.used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used|{
+            used.checked_add(bytes).filter(|total| *total <= self.inner.budget)
+        });
+        if res.is_err(){
+            self.inner.exceeded.store(true, Ordering::Relaxed);
+        }
+        res.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    #[test]
+    fn test_tracker(){
+        let tracker = AllocTracker::new(200);
+        let mut buffer = Vec::new();
+        vec![String::from("abcd"); 4].into_buffer(&mut buffer);
+        // each request decodes from its own buffer, all counted together
+        let mut first = ReadBuffer::from_raw(buffer.clone()).with_tracker(tracker.clone());
+        assert!(first.read_checked::<Vec<String>>().is_ok());
+        let used = tracker.used();
+        assert!(used > 100 && used <= 200);
+        let mut second = ReadBuffer::from_raw(buffer.clone()).with_tracker(tracker.clone());
+        assert_eq!(Err(BufferError::BudgetExceeded), second.read_checked::<Vec<String>>());
+        assert_eq!(0, second.consumed());
+        // forks and takes count too
+        tracker.reset();
+        let mut third = ReadBuffer::from_raw(buffer.clone()).with_tracker(tracker.clone());
+        let mut part = ReadBuffer::take(&mut third, buffer.len()).unwrap();
+        assert!(Vec::<String>::from_buffer(&mut part).is_some());
+        assert_eq!(used, tracker.used());
+        assert!(!tracker.exceeded());
+        // validating looks at the budget without using it
+        let small = AllocTracker::new(8);
+        let mut read = ReadBuffer::from_raw(buffer).with_tracker(small.clone());
+        assert_eq!(Err(BufferError::BudgetExceeded), read.skip_value::<Vec<String>>());
+        assert_eq!(0, small.used());
+    }
+}
@@ -0,0 +1,186 @@
+//! CBOR encoding of Bufferable types, enabled with the `cbor` feature.
+//! Values go through their Schema, so every Described type can be written as CBOR and read back.
+//! Tuples become arrays, integers use the shortest head and floats are always 64 bit.
+//! Reading accepts any float size, but no negative integers, tags, simple values
+//! or indefinite lengths, as those do not fit a Schema.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::cbor::*;
+//! let x = (10u32, String::from("a"));
+//! let bytes = to_cbor(&x);
+//! assert_eq!(vec![0x82, 0x0a, 0x61, b'a'], bytes);
+//! assert_eq!(Some(x), from_cbor(&bytes));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, ReadBuffer, Sink };
+use crate::schema::{ Described, Value };
+
+fn write_head<S: Sink + ?Sized>(major: u8, x: u64, vec: &mut S){
+    let major = major << 5;
+    if x < 24 { vec.write_bytes(&[major | x as u8]); }
+    else if x <= 0xff { vec.write_bytes(&[major | 24, x as u8]); }
+    else if x <= 0xffff { vec.write_bytes(&[major | 25]); vec.write_bytes(&(x as u16).to_be_bytes()); }
+    else if x <= 0xffff_ffff { vec.write_bytes(&[major | 26]); vec.write_bytes(&(x as u32).to_be_bytes()); }
+    else { vec.write_bytes(&[major | 27]); vec.write_bytes(&x.to_be_bytes()); }
+}
+/// Write value as CBOR.
+pub fn write_value<S: Sink + ?Sized>(value: &Value, vec: &mut S){
+    match value{
+        Value::UInt(x) => write_head(0, *x, vec),
+        Value::Bytes(x) => {
+            write_head(2, x.len() as u64, vec);
+            vec.write_bytes(x);
+        },
+        Value::Str(x) => {
+            write_head(3, x.len() as u64, vec);
+            vec.write_bytes(x.as_bytes());
+        },
+        Value::Seq(xs) => {
+            write_head(4, xs.len() as u64, vec);
+            for x in xs{
+                write_value(x, vec);
+            }
+        },
+        Value::Map(xs) => {
+            write_head(5, xs.len() as u64, vec);
+            for (k, v) in xs{
+                write_value(k, vec);
+                write_value(v, vec);
+            }
+        },
+        Value::Float(x) => {
+            vec.write_bytes(&[0xfb]);
+            vec.write_bytes(&x.to_be_bytes());
+        },
+    }
+}
+
+fn take<const N: usize>(buf: &mut ReadBuffer) -> Option<[u8; N]>{
+    <[u8; N]>::try_from(buf.read_bytes(N)?).ok()
+}
+
+fn read_arg(buf: &mut ReadBuffer, info: u8) -> Option<u64>{
+    match info{
+        0..=23 => Option::Some(u64::from(info)),
+        24 => Option::Some(u64::from(take::<1>(buf)?[0])),
+        25 => Option::Some(u64::from(u16::from_be_bytes(take(buf)?))),
+        26 => Option::Some(u64::from(u32::from_be_bytes(take(buf)?))),
+        27 => Option::Some(u64::from_be_bytes(take(buf)?)),
+        _ => Option::None,
+    }
+}
+
+fn read_len(buf: &mut ReadBuffer, info: u8) -> Option<usize>{
+    let len = usize::try_from(read_arg(buf, info)?).ok()?;
+    if len > buf.limits().max_elements{
+        return Option::None;
+    }
+    Option::Some(len)
+}
+// IEEE half precision, which CBOR uses for small floats.
+fn half_to_f64(bits: u16) -> f64{
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = i32::from((bits >> 10) & 0x1f);
+    let mant = f64::from(bits & 0x3ff);
+    sign * match exp{
+        0 => mant * 2f64.powi(-24),
+        31 => if mant == 0.0 { f64::INFINITY } else { f64::NAN },
+        _ => (1.0 + mant / 1024.0) * 2f64.powi(exp - 15),
+    }
+}
+/// Read a CBOR value.
+pub fn read_value(buf: &mut ReadBuffer) -> Option<Value>{
+    let head = take::<1>(buf)?[0];
+    let info = head & 0x1f;
+    match head >> 5{
+        0 => read_arg(buf, info).map(Value::UInt),
+        2 => {
+            let len = read_len(buf, info)?;
+            Option::Some(Value::Bytes(buf.read_bytes(len)?.to_vec()))
+        },
+        3 => {
+            let len = read_len(buf, info)?;
+            let bytes = buf.read_bytes(len)?;
+            std::str::from_utf8(bytes).ok().map(|s| Value::Str(s.to_owned()))
+        },
+        4 => {
+            let len = read_len(buf, info)?;
+            buf.nested(|buf|{
+                let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+                for _ in 0..len{
+                    vec.push(read_value(buf)?);
+                }
+                Option::Some(Value::Seq(vec))
+            })
+        },
+        5 => {
+            let len = read_len(buf, info)?;
+            buf.nested(|buf|{
+                let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+                for _ in 0..len{
+                    vec.push((read_value(buf)?, read_value(buf)?));
+                }
+                Option::Some(Value::Map(vec))
+            })
+        },
+        7 => match info{
+            25 => Option::Some(Value::Float(half_to_f64(u16::from_be_bytes(take(buf)?)))),
+            26 => Option::Some(Value::Float(f64::from(f32::from_be_bytes(take(buf)?)))),
+            27 => Option::Some(Value::Float(f64::from_be_bytes(take(buf)?))),
+            _ => Option::None,
+        },
+        _ => Option::None,
+    }
+}
+/// Encode x as CBOR.
+pub fn to_cbor<T: Described>(x: &T) -> Buffer{
+    let mut buffer = Vec::with_capacity(x.buffer_len());
+    x.copy_into_buffer(&mut buffer);
+    let value = T::schema().decode(&mut ReadBuffer::from_raw(buffer))
+        .expect("a value decodes with its own schema");
+    let mut vec = Vec::new();
+    write_value(&value, &mut vec);
+    vec
+}
+/// Decode a T from CBOR. None if it is not valid CBOR, does not fit T, or bytes are left over.
+pub fn from_cbor<T: Described>(bytes: &[u8]) -> Option<T>{
+    let mut buf = ReadBuffer::from_raw(bytes.to_vec());
+    let value = read_value(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    let mut buffer = Vec::new();
+    if !T::schema().encode(&value, &mut buffer){
+        return Option::None;
+    }
+    T::from_buffer(&mut ReadBuffer::from_raw(buffer))
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::cbor::*;
+
+    #[test]
+    fn test_cbor(){
+        // examples from RFC 8949 appendix A
+        let read = |bytes: &[u8]| read_value(&mut ReadBuffer::from_raw(bytes.to_vec()));
+        assert_eq!(Some(schema::Value::UInt(1000000)), read(&[0x1a, 0x00, 0x0f, 0x42, 0x40]));
+        assert_eq!(Some(schema::Value::Float(1.5)), read(&[0xf9, 0x3e, 0x00]));
+        assert_eq!(Some(schema::Value::Float(100000.0)), read(&[0xfa, 0x47, 0xc3, 0x50, 0x00]));
+        assert_eq!(Some(schema::Value::Float(-4.0)), read(&[0xf9, 0xc4, 0x00]));
+        assert_eq!(Some(schema::Value::Float(5.960464477539063e-8)), read(&[0xf9, 0x00, 0x01]));
+        assert_eq!(None, read(&[0x20])); // -1
+        assert_eq!(None, read(&[0x9f, 0xff])); // indefinite array
+        assert_eq!(vec![0x19, 0x03, 0xe8], to_cbor(&1000u16));
+        assert_eq!(vec![0x1b, 0, 0, 0, 0xe8, 0xd4, 0xa5, 0x10, 0], to_cbor(&1000000000000u64));
+        assert_eq!(vec![0x64, 0x49, 0x45, 0x54, 0x46], to_cbor(&String::from("IETF")));
+        // round trips
+        let mut map = HashMap::new();
+        map.insert(String::from("a"), vec![ByteVec(vec![1, 2])]);
+        let x = (map, 0.25f32, vec![(1u8, 70000u32)]);
+        assert_eq!(Some(x.clone()), from_cbor(&to_cbor(&x)));
+        assert_eq!(None, from_cbor::<u8>(&to_cbor(&300u16)));
+    }
+}
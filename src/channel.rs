@@ -0,0 +1,270 @@
+//! Sending values between threads in their encoded form, over a std::sync::mpsc channel.
+//! The bytes that go over the channel are the same as anywhere else, so the traffic can be
+//! recorded with Sender::record_into and played again later with replay.
+//! Buffers are taken from a pool that the Receiver gives them back to after decoding,
+//! so a busy channel does not allocate for every value.
+//! # Example
+//! ```
+//! use bin_buffer::channel::*;
+//! let (send, recv) = channel::<(u32, String)>();
+//! let worker = std::thread::spawn(move ||{
+//!     send.send(&(1, String::from("one"))).unwrap();
+//!     send.send(&(2, String::from("two"))).unwrap();
+//! });
+//! assert_eq!((1, String::from("one")), recv.recv().unwrap());
+//! assert_eq!((2, String::from("two")), recv.recv().unwrap());
+//! worker.join().unwrap();
+//! assert!(recv.recv().is_err());
+//! ```
+use std::io::{ self, Read, Write };
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::sync::{ mpsc, Arc, Mutex };
+use std::time::Duration;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use crate::net::{ write_frame, DEFAULT_MAX_FRAME_LEN };
+
+/// Most buffers a pool keeps for reuse.
+pub const POOL_SIZE: usize = 64;
+
+#[derive(Clone, Default)]
+struct Pool{
+    buffers: Arc<Mutex<Vec<Buffer>>>,
+}
+
+impl Pool{
+    fn take(&self) -> Buffer{
+        self.buffers.lock().ok().and_then(|mut b| b.pop()).unwrap_or_default()
+    }
+    fn give(&self, mut buffer: Buffer){
+        buffer.clear();
+        if let Ok(mut buffers) = self.buffers.lock(){
+            if buffers.len() < POOL_SIZE{
+                buffers.push(buffer);
+            }
+        }
+    }
+}
+
+enum Inner{
+    Unbounded(mpsc::Sender<Buffer>),
+    Bounded(mpsc::SyncSender<Buffer>),
+}
+
+impl Clone for Inner{
+    fn clone(&self) -> Self{
+        match self{
+            Inner::Unbounded(s) => Inner::Unbounded(s.clone()),
+            Inner::Bounded(s) => Inner::Bounded(s.clone()),
+        }
+    }
+}
+
+type Recorder = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// The sending half, can be cloned to send from more threads.
+pub struct Sender<T>{
+    inner: Inner,
+    pool: Pool,
+    recorder: Option<Recorder>,
+    marker: PhantomData<fn(&T)>,
+}
+
+impl<T> Clone for Sender<T>{
+    fn clone(&self) -> Self{
+        Self{ inner: self.inner.clone(), pool: self.pool.clone(), recorder: self.recorder.clone(), marker: PhantomData }
+    }
+}
+
+impl<T: Bufferable> Sender<T>{
+    /// Encode x and send it. Fails with BrokenPipe if the Receiver is gone,
+    /// or with the error of the recorder if writing to it failed.
+    pub fn send(&self, x: &T) -> io::Result<()>{
+        let mut buffer = self.pool.take();
+        buffer.reserve(x.buffer_len());
+        x.copy_into_buffer(&mut buffer);
+        self.send_buffer(buffer)
+    }
+    /// Send bytes that are already encoded, for example a recorded value.
+    /// They are checked when they are received.
+    pub fn send_bytes(&self, bytes: &[u8]) -> io::Result<()>{
+        let mut buffer = self.pool.take();
+        buffer.extend_from_slice(bytes);
+        self.send_buffer(buffer)
+    }
+    /// Also write everything sent from now on to w, every value as a frame like net::send_over does.
+    /// Clones made after this write to the same w.
+    pub fn record_into<W: Write + Send + 'static>(mut self, w: W) -> Self{
+        self.recorder = Option::Some(Arc::new(Mutex::new(Box::new(w))));
+        self
+    }
+    fn send_buffer(&self, buffer: Buffer) -> io::Result<()>{
+        if let Option::Some(recorder) = &self.recorder{
+            let mut w = recorder.lock().map_err(|_| io::Error::other("recorder poisoned"))?;
+            write_frame(&buffer, &mut *w)?;
+        }
+        let sent = match &self.inner{
+            Inner::Unbounded(s) => s.send(buffer).map_err(|e| e.0),
+            Inner::Bounded(s) => s.send(buffer).map_err(|e| e.0),
+        };
+        sent.map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "receiver is gone"))
+    }
+}
+
+/// The receiving half.
+pub struct Receiver<T>{
+    inner: mpsc::Receiver<Buffer>,
+    pool: Pool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Bufferable> Receiver<T>{
+    /// Wait for the next value. Fails with BrokenPipe if all Senders are gone and nothing is left,
+    /// and with InvalidData if the bytes are not exactly one T.
+    pub fn recv(&self) -> io::Result<T>{
+        match self.inner.recv(){
+            Ok(buffer) => self.decode(buffer),
+            Err(_) => Err(gone()),
+        }
+    }
+    /// The next value if there is one waiting, None if not.
+    pub fn try_recv(&self) -> io::Result<Option<T>>{
+        match self.inner.try_recv(){
+            Ok(buffer) => self.decode(buffer).map(Option::Some),
+            Err(mpsc::TryRecvError::Empty) => Ok(Option::None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(gone()),
+        }
+    }
+    /// recv that fails with TimedOut if nothing came within timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<T>{
+        match self.inner.recv_timeout(timeout){
+            Ok(buffer) => self.decode(buffer),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, "nothing received")),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(gone()),
+        }
+    }
+    /// The values as they come, until all Senders are gone or a value does not decode.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_{
+        std::iter::from_fn(move || self.recv().ok())
+    }
+    fn decode(&self, buffer: Buffer) -> io::Result<T>{
+        let mut buf = ReadBuffer::borrowed(&buffer, Default::default());
+        let x = T::from_buffer(&mut buf);
+        let left = buf.remaining();
+        self.pool.give(buffer);
+        match x{
+            Option::Some(x) if left == 0 => Ok(x),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "received bytes are not one value")),
+        }
+    }
+}
+
+fn gone() -> io::Error{
+    io::Error::new(io::ErrorKind::BrokenPipe, "all senders are gone")
+}
+
+fn halves<T>(inner: Inner, recv: mpsc::Receiver<Buffer>) -> (Sender<T>, Receiver<T>){
+    let pool = Pool::default();
+    (Sender{ inner, pool: pool.clone(), recorder: Option::None, marker: PhantomData },
+        Receiver{ inner: recv, pool, marker: PhantomData })
+}
+
+/// A channel that holds any number of values waiting to be received.
+pub fn channel<T: Bufferable>() -> (Sender<T>, Receiver<T>){
+    let (send, recv) = mpsc::channel();
+    halves(Inner::Unbounded(send), recv)
+}
+
+/// A channel that holds at most bound values, send waits until there is room.
+pub fn sync_channel<T: Bufferable>(bound: usize) -> (Sender<T>, Receiver<T>){
+    let (send, recv) = mpsc::sync_channel(bound);
+    halves(Inner::Bounded(send), recv)
+}
+
+// The next frame, None if r ends right before it.
+fn next_frame<R: Read + ?Sized>(r: &mut R) -> io::Result<Option<Buffer>>{
+    let mut len = [0u8; 8];
+    let mut got = 0;
+    while got < len.len(){
+        match r.read(&mut len[got..]){
+            Ok(0) if got == 0 => return Ok(Option::None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => got += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    let len = usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX);
+    if len > DEFAULT_MAX_FRAME_LEN{
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+    let mut frame = vec![0u8; len];
+    r.read_exact(&mut frame)?;
+    Ok(Option::Some(frame))
+}
+
+/// Send every value recorded in r, as written by Sender::record_into, again over send.
+/// Returns how many there were. Fails with UnexpectedEof if r ends inside a frame.
+/// # Example
+/// ```
+/// use bin_buffer::channel::*;
+/// let record = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+/// # struct Shared(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+/// # impl std::io::Write for Shared{
+/// #     fn write(&mut self, b: &[u8]) -> std::io::Result<usize>{ self.0.lock().unwrap().write(b) }
+/// #     fn flush(&mut self) -> std::io::Result<()>{ Ok(()) }
+/// # }
+/// let (send, recv) = channel::<u16>();
+/// let send = send.record_into(Shared(record.clone()));
+/// send.send(&7).unwrap();
+/// send.send(&8).unwrap();
+/// assert_eq!(vec![7, 8], vec![recv.recv().unwrap(), recv.recv().unwrap()]);
+/// let recorded = record.lock().unwrap().clone();
+/// let (send, recv) = channel::<u16>();
+/// assert_eq!(2, replay(&mut &recorded[..], &send).unwrap());
+/// drop(send);
+/// assert_eq!(vec![7, 8], recv.iter().collect::<Vec<_>>());
+/// ```
+pub fn replay<T: Bufferable, R: Read + ?Sized>(r: &mut R, send: &Sender<T>) -> io::Result<usize>{
+    let mut count = 0;
+    while let Option::Some(frame) = next_frame(r)?{
+        send.send_bytes(&frame)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::channel::*;
+
+    #[test]
+    fn test_channel(){
+        let (send, recv) = sync_channel::<Vec<String>>(1);
+        let other = send.clone();
+        let worker = std::thread::spawn(move ||{
+            for i in 0..10{
+                other.send(&vec![i.to_string(); i]).unwrap();
+            }
+        });
+        for i in 0..10{
+            assert_eq!(vec![i.to_string(); i], recv.recv().unwrap());
+        }
+        worker.join().unwrap();
+        assert!(recv.pool.buffers.lock().unwrap().len() <= 3);
+        assert_eq!(None, recv.try_recv().unwrap());
+        assert_eq!(io::ErrorKind::TimedOut, recv.recv_timeout(Duration::from_millis(1)).unwrap_err().kind());
+        send.send_bytes(&[0, 0]).unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, recv.recv().unwrap_err().kind());
+        drop(send);
+        assert_eq!(io::ErrorKind::BrokenPipe, recv.recv().unwrap_err().kind());
+        let (send, recv) = channel::<u8>();
+        drop(recv);
+        assert_eq!(io::ErrorKind::BrokenPipe, send.send(&1).unwrap_err().kind());
+        let (send, _recv) = channel::<u8>();
+        let mut torn: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 2, 1];
+        assert_eq!(io::ErrorKind::UnexpectedEof, replay(&mut torn, &send).unwrap_err().kind());
+        let mut empty: &[u8] = &[];
+        assert_eq!(0, replay(&mut empty, &send).unwrap());
+    }
+}
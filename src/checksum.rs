@@ -0,0 +1,637 @@
+//! Checksums for finding damaged records, used by the journal, the flash log and the save slots.
+//! CRC-32 is the usual one, CRC-16 saves two bytes per record where every byte counts.
+//! They need no file system, so they are there on every target.
+//! HashingSink works one out while a value is encoded, with a CRC, XXH64 or, with the
+//! `blake3` feature, BLAKE3. content_hash and content_digest hash a value without a buffer.
+use std::hash::Hasher;
+use crate::{ Bufferable, Endian, Sink };
+
+/// CRC-32 of bytes, the IEEE one used by zip and PNG.
+/// # Example
+/// ```
+/// use bin_buffer::checksum::*;
+/// assert_eq!(0xcbf43926, crc32(b"123456789"));
+/// ```
+pub fn crc32(bytes: &[u8]) -> u32{
+    let mut hash = Crc32Hasher::new();
+    hash.update(bytes);
+    hash.digest()
+}
+
+/// CRC-16/CCITT-FALSE of bytes: polynomial 0x1021, starting at 0xffff.
+/// Two bytes instead of four, for records so small that a CRC-32 would be a big part of them.
+/// # Example
+/// ```
+/// use bin_buffer::checksum::*;
+/// assert_eq!(0x29b1, crc16(b"123456789"));
+/// ```
+pub fn crc16(bytes: &[u8]) -> u16{
+    let mut hash = Crc16Hasher::new();
+    hash.update(bytes);
+    hash.digest()
+}
+
+/// A hash that takes its input in pieces, so it can be worked out while encoding, see HashingSink.
+/// With the `blake3` feature blake3::Hasher is one as well.
+pub trait RunningHash{
+    /// The hash itself.
+    type Digest;
+    /// Hash the next bytes.
+    fn update(&mut self, bytes: &[u8]);
+    /// The hash of all bytes so far. More can still be added after.
+    fn digest(&self) -> Self::Digest;
+}
+
+/// CRC-32 worked out piece by piece, see crc32.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crc32Hasher(u32);
+
+impl Crc32Hasher{
+    /// The CRC-32 of nothing yet.
+    pub fn new() -> Self{
+        Self(!0)
+    }
+}
+
+impl Default for Crc32Hasher{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl RunningHash for Crc32Hasher{
+    type Digest = u32;
+
+    fn update(&mut self, bytes: &[u8]){
+        let mut crc = self.0;
+        for &byte in bytes{
+            crc ^= byte as u32;
+            for _ in 0..8{
+                crc = (crc >> 1) ^ (0xedb8_8320 & 0u32.wrapping_sub(crc & 1));
+            }
+        }
+        self.0 = crc;
+    }
+
+    fn digest(&self) -> u32{
+        !self.0
+    }
+}
+
+/// CRC-16 worked out piece by piece, see crc16.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crc16Hasher(u16);
+
+impl Crc16Hasher{
+    /// The CRC-16 of nothing yet.
+    pub fn new() -> Self{
+        Self(0xffff)
+    }
+}
+
+impl Default for Crc16Hasher{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl RunningHash for Crc16Hasher{
+    type Digest = u16;
+
+    fn update(&mut self, bytes: &[u8]){
+        let mut crc = self.0;
+        for &byte in bytes{
+            crc ^= (byte as u16) << 8;
+            for _ in 0..8{
+                crc = (crc << 1) ^ (0x1021 & 0u16.wrapping_sub(crc >> 15));
+            }
+        }
+        self.0 = crc;
+    }
+
+    fn digest(&self) -> u16{
+        self.0
+    }
+}
+
+const XXH_P1: u64 = 0x9e37_79b1_85eb_ca87;
+const XXH_P2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const XXH_P3: u64 = 0x1656_67b1_9e37_79f9;
+const XXH_P4: u64 = 0x85eb_ca77_c2b2_ae63;
+const XXH_P5: u64 = 0x27d4_eb2f_1656_67c5;
+
+fn xxh_round(acc: u64, input: u64) -> u64{
+    acc.wrapping_add(input.wrapping_mul(XXH_P2)).rotate_left(31).wrapping_mul(XXH_P1)
+}
+
+fn xxh_merge(acc: u64, v: u64) -> u64{
+    (acc ^ xxh_round(0, v)).wrapping_mul(XXH_P1).wrapping_add(XXH_P4)
+}
+
+fn le_u64(bytes: &[u8]) -> u64{
+    let mut x = [0u8; 8];
+    x.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(x)
+}
+
+/// XXH64, a fast non cryptographic 64 bit hash, for checking large outputs
+/// where a CRC-32 is the slow part.
+/// # Example
+/// ```
+/// use bin_buffer::checksum::*;
+/// let mut hash = XxHash64::new();
+/// hash.update(b"a");
+/// assert_eq!(0xd24ec4f1a98c6e5b, hash.digest());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XxHash64{
+    seed: u64,
+    acc: [u64; 4],
+    block: [u8; 32],
+    filled: usize,
+    total: u64,
+}
+
+impl XxHash64{
+    /// XXH64 with seed 0.
+    pub fn new() -> Self{
+        Self::with_seed(0)
+    }
+    /// XXH64 with another seed.
+    pub fn with_seed(seed: u64) -> Self{
+        Self{
+            seed,
+            acc: [
+                seed.wrapping_add(XXH_P1).wrapping_add(XXH_P2),
+                seed.wrapping_add(XXH_P2),
+                seed,
+                seed.wrapping_sub(XXH_P1),
+            ],
+            block: [0; 32],
+            filled: 0,
+            total: 0,
+        }
+    }
+
+    fn stripe(&mut self, block: &[u8]){
+        for (i, acc) in self.acc.iter_mut().enumerate(){
+            *acc = xxh_round(*acc, le_u64(&block[i * 8..]));
+        }
+    }
+}
+
+impl Default for XxHash64{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl RunningHash for XxHash64{
+    type Digest = u64;
+
+    fn update(&mut self, mut bytes: &[u8]){
+        self.total += bytes.len() as u64;
+        if self.filled > 0{
+            let take = (32 - self.filled).min(bytes.len());
+            self.block[self.filled..self.filled + take].copy_from_slice(&bytes[..take]);
+            self.filled += take;
+            bytes = &bytes[take..];
+            if self.filled < 32{
+                return;
+            }
+            let block = self.block;
+            self.stripe(&block);
+            self.filled = 0;
+        }
+        while bytes.len() >= 32{
+            self.stripe(&bytes[..32]);
+            bytes = &bytes[32..];
+        }
+        self.block[..bytes.len()].copy_from_slice(bytes);
+        self.filled = bytes.len();
+    }
+
+    fn digest(&self) -> u64{
+        let [v1, v2, v3, v4] = self.acc;
+        let mut h = if self.total >= 32{
+            let h = v1.rotate_left(1).wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12)).wrapping_add(v4.rotate_left(18));
+            self.acc.iter().fold(h, |h, &v| xxh_merge(h, v))
+        } else {
+            self.seed.wrapping_add(XXH_P5)
+        };
+        h = h.wrapping_add(self.total);
+        let mut rest = &self.block[..self.filled];
+        while rest.len() >= 8{
+            h ^= xxh_round(0, le_u64(rest));
+            h = h.rotate_left(27).wrapping_mul(XXH_P1).wrapping_add(XXH_P4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4{
+            let x = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            h ^= u64::from(x).wrapping_mul(XXH_P1);
+            h = h.rotate_left(23).wrapping_mul(XXH_P2).wrapping_add(XXH_P3);
+            rest = &rest[4..];
+        }
+        for &byte in rest{
+            h ^= u64::from(byte).wrapping_mul(XXH_P5);
+            h = h.rotate_left(11).wrapping_mul(XXH_P1);
+        }
+        h ^= h >> 33;
+        h = h.wrapping_mul(XXH_P2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(XXH_P3);
+        h ^ (h >> 32)
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl RunningHash for blake3::Hasher{
+    type Digest = [u8; 32];
+
+    fn update(&mut self, bytes: &[u8]){
+        blake3::Hasher::update(self, bytes);
+    }
+
+    fn digest(&self) -> [u8; 32]{
+        *self.finalize().as_bytes()
+    }
+}
+
+/// Sink that hashes everything written to it on its way to the Sink it wraps,
+/// so framing code gets the checksum of a large output without a second pass over it.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::checksum::*;
+/// let mut sink = HashingSink::new(Vec::new(), Crc32Hasher::new());
+/// sink.put(&vec![1u32, 2, 3]).put(&String::from("end"));
+/// let (bytes, crc) = sink.finish();
+/// assert_eq!(crc32(&bytes), crc);
+/// ```
+pub struct HashingSink<S, H>{
+    sink: S,
+    hash: H,
+}
+
+impl<S: Sink, H: RunningHash> HashingSink<S, H>{
+    /// Hash what goes to sink with hash.
+    pub fn new(sink: S, hash: H) -> Self{
+        Self{ sink, hash }
+    }
+    /// The hash of everything written so far.
+    pub fn digest(&self) -> H::Digest{
+        self.hash.digest()
+    }
+    /// The sink.
+    pub fn get_ref(&self) -> &S{
+        &self.sink
+    }
+    /// The sink and the hash of everything written to it.
+    pub fn finish(self) -> (S, H::Digest){
+        let digest = self.hash.digest();
+        (self.sink, digest)
+    }
+}
+
+/// Has no Vec to give, so bulk writes go through write_bytes and get hashed too.
+impl<S: Sink, H: RunningHash> Sink for HashingSink<S, H>{
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.hash.update(bytes);
+        self.sink.write_bytes(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize){
+        self.sink.reserve(additional);
+    }
+
+    fn endian(&self) -> Endian{
+        self.sink.endian()
+    }
+}
+
+// Feeds everything written to a hash and keeps nothing.
+struct HashOnly<F: FnMut(&[u8])>(F);
+
+impl<F: FnMut(&[u8])> Sink for HashOnly<F>{
+    fn write_bytes(&mut self, bytes: &[u8]){
+        (self.0)(bytes);
+    }
+}
+
+/// Hash the encoding of x with a std Hasher, without building the buffer.
+/// The encoding is canonical, maps and sets are sorted, so equal values give equal hashes,
+/// except for floats: 0.0 and -0.0 differ, use CanonicalF64 where that matters.
+/// Use a Hasher with fixed keys, not RandomState, for hashes that are kept or shared.
+/// # Example
+/// ```
+/// use bin_buffer::checksum::*;
+/// use std::collections::hash_map::DefaultHasher;
+/// let a = content_hash(&(String::from("mesh"), vec![1u32, 2]), DefaultHasher::new());
+/// let b = content_hash(&(String::from("mesh"), vec![1u32, 2]), DefaultHasher::new());
+/// let c = content_hash(&(String::from("mesh"), vec![1u32, 3]), DefaultHasher::new());
+/// assert_eq!(a, b);
+/// assert_ne!(a, c);
+/// ```
+pub fn content_hash<T: Bufferable, H: Hasher>(x: &T, mut hasher: H) -> u64{
+    x.copy_into_buffer(&mut HashOnly(|bytes: &[u8]| hasher.write(bytes)));
+    hasher.finish()
+}
+
+/// Hash the encoding of x with a RunningHash, without building the buffer.
+/// It is the hash of encode(x), so it can be checked against the bytes later.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::checksum::*;
+/// let x = vec![String::from("a"), String::from("b")];
+/// assert_eq!(crc32(&encode(&x)), content_digest(&x, Crc32Hasher::new()));
+/// ```
+pub fn content_digest<T: Bufferable, H: RunningHash>(x: &T, mut hash: H) -> H::Digest{
+    x.copy_into_buffer(&mut HashOnly(|bytes: &[u8]| hash.update(bytes)));
+    hash.digest()
+}
+
+/// Sink that appends the CRC-32 of everything written to it as a 4 byte big endian trailer
+/// when finished, so damaged bytes are caught when they are read back with verify_crc32_trailer.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::checksum::*;
+/// let mut sink = ChecksumWriter::new(Vec::new());
+/// sink.put(&String::from("level 3"));
+/// let mut bytes = sink.finish();
+/// assert_eq!(Ok(&encode(&String::from("level 3"))[..]), verify_crc32_trailer(&bytes));
+/// bytes[9] ^= 1;
+/// assert!(matches!(verify_crc32_trailer(&bytes), Err(ChecksumError::Mismatch{ .. })));
+/// ```
+pub struct ChecksumWriter<S>{
+    inner: HashingSink<S, Crc32Hasher>,
+}
+
+impl<S: Sink> ChecksumWriter<S>{
+    /// Checksum what goes to sink.
+    pub fn new(sink: S) -> Self{
+        Self{ inner: HashingSink::new(sink, Crc32Hasher::new()) }
+    }
+    /// The sink, without the trailer yet.
+    pub fn get_ref(&self) -> &S{
+        self.inner.get_ref()
+    }
+    /// Write the trailer and give back the sink.
+    pub fn finish(self) -> S{
+        let (mut sink, crc) = self.inner.finish();
+        sink.write_bytes(&crc.to_be_bytes());
+        sink
+    }
+}
+
+impl<S: Sink> Sink for ChecksumWriter<S>{
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.inner.write_bytes(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize){
+        self.inner.reserve(additional + 4);
+    }
+
+    fn endian(&self) -> Endian{
+        self.inner.endian()
+    }
+}
+
+/// Why bytes with a CRC-32 trailer do not check out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumError{
+    /// There are fewer bytes than the trailer takes.
+    TooShort,
+    /// The bytes are damaged: the trailer holds stored, but they hash to computed.
+    Mismatch{
+        /// CRC-32 in the trailer.
+        stored: u32,
+        /// CRC-32 of the bytes before it.
+        computed: u32,
+    },
+}
+
+impl std::fmt::Display for ChecksumError{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            Self::TooShort => write!(f, "too short for a checksum"),
+            Self::Mismatch{ stored, computed } =>
+                write!(f, "checksum mismatch, stored {:08x} but computed {:08x}", stored, computed),
+        }
+    }
+}
+
+impl std::error::Error for ChecksumError{}
+
+/// Damaged bytes are invalid data.
+impl From<ChecksumError> for std::io::Error{
+    fn from(e: ChecksumError) -> Self{
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Check the CRC-32 trailer written by ChecksumWriter and give the bytes before it.
+pub fn verify_crc32_trailer(bytes: &[u8]) -> Result<&[u8], ChecksumError>{
+    if bytes.len() < 4{
+        return Err(ChecksumError::TooShort);
+    }
+    let (body, trailer) = bytes.split_at(bytes.len() - 4);
+    let stored = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let computed = crc32(body);
+    if stored != computed{
+        return Err(ChecksumError::Mismatch{ stored, computed });
+    }
+    Ok(body)
+}
+
+/// Which checksum a record carries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Checksum{
+    /// CRC-32, see crc32.
+    Crc32,
+    /// CRC-16, see crc16.
+    Crc16,
+}
+
+impl Checksum{
+    /// Bytes the checksum takes.
+    pub fn width(self) -> usize{
+        match self{
+            Checksum::Crc32 => 4,
+            Checksum::Crc16 => 2,
+        }
+    }
+    /// The checksum of bytes, big endian.
+    pub fn of(self, bytes: &[u8]) -> Vec<u8>{
+        match self{
+            Checksum::Crc32 => crc32(bytes).to_be_bytes().to_vec(),
+            Checksum::Crc16 => crc16(bytes).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 of the parts one after the other, for when a CRC is not enough because
+/// someone might change the bytes on purpose.
+/// # Example
+/// ```
+/// use bin_buffer::checksum::*;
+/// let hash = sha256(&[b"ab", b"c"]);
+/// assert_eq!([0xba, 0x78, 0x16, 0xbf], hash[..4]);
+/// ```
+pub fn sha256(parts: &[&[u8]]) -> [u8; 32]{
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let len: usize = parts.iter().map(|p| p.len()).sum();
+    let mut tail = Vec::with_capacity(72);
+    tail.push(0x80);
+    tail.resize((55usize.wrapping_sub(len) % 64) + 1, 0);
+    tail.extend_from_slice(&((len as u64) * 8).to_be_bytes());
+    let mut block = [0u8; 64];
+    let mut filled = 0;
+    for byte in parts.iter().flat_map(|p| p.iter()).chain(tail.iter()){
+        block[filled] = *byte;
+        filled += 1;
+        if filled == 64{
+            sha256_block(&mut h, &block);
+            filled = 0;
+        }
+    }
+    let mut out = [0u8; 32];
+    for (i, x) in h.iter().enumerate(){
+        out[i * 4..i * 4 + 4].copy_from_slice(&x.to_be_bytes());
+    }
+    out
+}
+
+fn sha256_block(h: &mut [u32; 8], block: &[u8; 64]){
+    let mut w = [0u32; 64];
+    for i in 0..16{
+        w[i] = u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]]);
+    }
+    for i in 16..64{
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+    }
+    let mut v = *h;
+    for i in 0..64{
+        let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[i]).wrapping_add(w[i]);
+        let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let t2 = s0.wrapping_add(maj);
+        v = [t1.wrapping_add(t2), v[0], v[1], v[2], v[3].wrapping_add(t1), v[4], v[5], v[6]];
+    }
+    for (x, y) in h.iter_mut().zip(v.iter()){
+        *x = x.wrapping_add(*y);
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::checksum::*;
+
+    #[test]
+    fn test_checksums(){
+        assert_eq!(0x29b1, crc16(b"123456789"));
+        assert_eq!(vec![0x29, 0xb1], Checksum::Crc16.of(b"123456789"));
+        let hex = |h: [u8; 32]| h.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", hex(sha256(&[])));
+        let long = vec![b'a'; 1000];
+        assert_eq!("41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3", hex(sha256(&[&long])));
+        assert_eq!(sha256(&[&long[..55], &long[55..56]]), sha256(&[&long[..56]]));
+    }
+
+    #[test]
+    fn test_checksum_writer(){
+        let mut sink = ChecksumWriter::new(Vec::new());
+        crate::Sink::put(&mut sink, &vec![1u16, 2, 3]);
+        assert_eq!(14, sink.get_ref().len());
+        let bytes = sink.finish();
+        assert_eq!(18, bytes.len());
+        assert_eq!(crc32(&bytes[..14]).to_be_bytes(), bytes[14..]);
+        assert_eq!(Ok(&bytes[..14]), verify_crc32_trailer(&bytes));
+        assert_eq!(Err(ChecksumError::TooShort), verify_crc32_trailer(&bytes[..3]));
+        let mut damaged = bytes.clone();
+        damaged[13] = 9;
+        assert_eq!(Err(ChecksumError::Mismatch{ stored: crc32(&bytes[..14]), computed: crc32(&damaged[..14]) }),
+            verify_crc32_trailer(&damaged));
+        assert_eq!(std::io::ErrorKind::InvalidData, std::io::Error::from(ChecksumError::TooShort).kind());
+    }
+
+    #[test]
+    fn test_running_hashes(){
+        let xxh = |bytes: &[u8]|{
+            let mut hash = XxHash64::new();
+            hash.update(bytes);
+            hash.digest()
+        };
+        assert_eq!(0xef46db3751d8e999, xxh(b""));
+        assert_eq!(0x44bc2cf5ad770999, xxh(b"abc"));
+        assert_eq!(0xfbcea83c8a378bf1, xxh(b"Nobody inspects the spammish repetition"));
+        // the same in pieces of every size
+        let x = (vec![7u64; 100], String::from("tail"));
+        let bytes = crate::encode(&x);
+        for size in [1, 5, 31, 32, 33, 1000]{
+            let mut hash = XxHash64::new();
+            let mut crc = Crc16Hasher::new();
+            for piece in bytes.chunks(size){
+                hash.update(piece);
+                crc.update(piece);
+            }
+            assert_eq!(xxh(&bytes), hash.digest());
+            assert_eq!(crc16(&bytes), crc.digest());
+        }
+        let mut sink = HashingSink::new(crate::SizeCounter::new(), XxHash64::new());
+        crate::Bufferable::copy_into_buffer(&x, &mut sink);
+        assert_eq!(bytes.len(), sink.get_ref().len());
+        assert_eq!(xxh(&bytes), sink.digest());
+    }
+
+    #[test]
+    fn test_content_hash(){
+        use std::collections::HashMap;
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasher;
+        // maps hash the same whatever their order in memory
+        let a: HashMap<String, u32> = (0..50).map(|i| (i.to_string(), i)).collect();
+        let b: HashMap<String, u32> = (0..50).rev().map(|i| (i.to_string(), i)).collect();
+        assert_eq!(content_hash(&a, DefaultHasher::new()), content_hash(&b, DefaultHasher::new()));
+        let bytes = crate::encode(&a);
+        let mut hash = XxHash64::new();
+        hash.update(&bytes);
+        assert_eq!(hash.digest(), content_digest(&a, XxHash64::new()));
+        assert_eq!(crc16(&bytes), content_digest(&b, Crc16Hasher::new()));
+        // feeding the bytes in one go gives the same hash
+        let mut whole = DefaultHasher::new();
+        whole.write(&crate::encode(&(7u16, String::from("x"))));
+        assert_eq!(whole.finish(), content_hash(&(7u16, String::from("x")), DefaultHasher::new()));
+        let state = std::collections::hash_map::RandomState::new();
+        assert_ne!(content_hash(&1u8, state.build_hasher()), content_hash(&2u8, state.build_hasher()));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3(){
+        let mut sink = HashingSink::new(Vec::new(), blake3::Hasher::new());
+        crate::Bufferable::copy_into_buffer(&vec![1u16, 2, 3], &mut sink);
+        let (bytes, digest) = sink.finish();
+        assert_eq!(*blake3::hash(&bytes).as_bytes(), digest);
+    }
+}
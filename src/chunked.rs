@@ -0,0 +1,342 @@
+//! Encoding into fixed size chunks instead of one Buffer.
+//! Only a single chunk is held at a time, so a large object can be streamed
+//! out with O(chunk) memory, for example into a socket's own send buffers.
+//! StreamWriter does the same into an io::Write, pacing the chunks so a background save
+//! spreads its IO over time. ChainedReadBuffer goes the other way, decoding from a list of segments.
+use std::io::{ self, Write };
+use std::time::{ Duration, Instant };
+use crate::{ Buffer, Bufferable, DecodeLimits, ReadBuffer, Sink };
+
+/// Sink that hands its output to a callback in chunks of a fixed size.
+/// Every chunk is exactly chunk_size bytes, except the last one given by finish.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::chunked::ChunkSink;
+/// let mut chunks = Vec::new();
+/// let mut sink = ChunkSink::new(4, |chunk: &[u8]| chunks.push(chunk.to_vec()));
+/// 1u32.into_buffer(&mut sink);
+/// 2u16.into_buffer(&mut sink);
+/// sink.finish();
+/// assert_eq!(vec![vec![0,0,0,1],vec![0,2]], chunks);
+/// ```
+pub struct ChunkSink<F: FnMut(&[u8])>{
+    chunk: Buffer,
+    size: usize,
+    f: F,
+}
+
+impl<F: FnMut(&[u8])> ChunkSink<F>{
+    /// Create a ChunkSink that calls f with every full chunk of chunk_size bytes.
+    /// Panics if chunk_size is 0.
+    pub fn new(chunk_size: usize, f: F) -> Self{
+        assert!(chunk_size > 0, "chunk size must not be 0");
+        Self{
+            chunk: Vec::with_capacity(chunk_size),
+            size: chunk_size,
+            f,
+        }
+    }
+    /// Hand over the last, possibly shorter, chunk if there is one.
+    pub fn finish(mut self){
+        if !self.chunk.is_empty(){
+            (self.f)(&self.chunk);
+        }
+    }
+}
+
+impl<F: FnMut(&[u8])> Sink for ChunkSink<F>{
+    fn write_bytes(&mut self, mut bytes: &[u8]){
+        if !self.chunk.is_empty(){
+            let take = (self.size - self.chunk.len()).min(bytes.len());
+            self.chunk.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+            if self.chunk.len() < self.size { return; }
+            (self.f)(&self.chunk);
+            self.chunk.clear();
+        }
+        // Whole chunks can go out straight from the input.
+        while bytes.len() >= self.size{
+            (self.f)(&bytes[..self.size]);
+            bytes = &bytes[self.size..];
+        }
+        self.chunk.extend_from_slice(bytes);
+    }
+}
+
+/// Encode x and hand the output to f in chunks of chunk_size bytes.
+/// The chunks put together are the same bytes copy_into_buffer would write.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = vec![7u64; 1000];
+/// let mut sent = Vec::new();
+/// chunked::encode_chunked(&x, 64 * 1024, |chunk| sent.extend_from_slice(chunk));
+/// let mut buffer = ReadBuffer::from_raw(sent);
+/// assert_eq!(Some(x), Vec::<u64>::from_buffer(&mut buffer));
+/// ```
+pub fn encode_chunked<T: Bufferable, F: FnMut(&[u8])>(x: &T, chunk_size: usize, f: F){
+    let mut sink = ChunkSink::new(chunk_size, f);
+    x.copy_into_buffer(&mut sink);
+    sink.finish();
+}
+
+/// Sink that writes to an io::Write in chunks of a fixed size, calling a pacing callback
+/// with the length of every chunk after it is written. The callback can sleep or yield, so a game
+/// saving in the background spreads the IO over many frames instead of causing a hitch.
+/// The first error of the writer is kept and returned by finish, nothing is written after it.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::chunked::StreamWriter;
+/// use std::sync::{ Arc, Mutex };
+/// let flushes = Arc::new(Mutex::new(Vec::new()));
+/// let seen = flushes.clone();
+/// let mut writer = StreamWriter::new(Vec::new(), 4096)
+///     .with_pacing(move |len| seen.lock().unwrap().push(len));
+/// vec![0u8; 10000].copy_into_buffer(&mut writer);
+/// let file = writer.finish().unwrap();
+/// assert_eq!(8 + 10000, file.len());
+/// assert_eq!(vec![4096, 4096, 1816], *flushes.lock().unwrap());
+/// ```
+pub struct StreamWriter<W: Write>{
+    writer: W,
+    chunk: Buffer,
+    size: usize,
+    written: u64,
+    pace: Option<Box<dyn FnMut(usize) + Send>>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> StreamWriter<W>{
+    /// Create a StreamWriter that writes to writer in chunks of chunk_size bytes, without pacing.
+    /// Panics if chunk_size is 0.
+    pub fn new(writer: W, chunk_size: usize) -> Self{
+        assert!(chunk_size > 0, "chunk size must not be 0");
+        Self{
+            writer,
+            chunk: Vec::with_capacity(chunk_size),
+            size: chunk_size,
+            written: 0,
+            pace: Option::None,
+            error: Option::None,
+        }
+    }
+    /// Call pace with the length of every chunk after it is written.
+    pub fn with_pacing<P: FnMut(usize) + Send + 'static>(mut self, pace: P) -> Self{
+        self.pace = Option::Some(Box::new(pace));
+        self
+    }
+    /// Sleep after chunks as needed to write no more than bytes_per_second on average.
+    /// Panics if bytes_per_second is 0.
+    pub fn with_rate(self, bytes_per_second: u64) -> Self{
+        assert!(bytes_per_second > 0, "rate must not be 0");
+        let start = Instant::now();
+        let mut total = 0u64;
+        self.with_pacing(move |len|{
+            total += len as u64;
+            let due = Duration::from_secs_f64(total as f64 / bytes_per_second as f64);
+            if let Option::Some(wait) = due.checked_sub(start.elapsed()){
+                std::thread::sleep(wait);
+            }
+        })
+    }
+    /// Bytes handed to the writer so far.
+    pub fn written(&self) -> u64{
+        self.written
+    }
+    /// Write the last, possibly shorter, chunk, flush the writer and give it back,
+    /// or the first error it gave.
+    pub fn finish(mut self) -> io::Result<W>{
+        if !self.chunk.is_empty(){
+            let chunk = std::mem::take(&mut self.chunk);
+            self.flush_chunk(&chunk);
+        }
+        if let Option::Some(e) = self.error{
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn flush_chunk(&mut self, chunk: &[u8]){
+        if self.error.is_some(){
+            return;
+        }
+        if let Err(e) = self.writer.write_all(chunk){
+            self.error = Option::Some(e);
+            return;
+        }
+        self.written += chunk.len() as u64;
+        if let Option::Some(pace) = &mut self.pace{
+            pace(chunk.len());
+        }
+    }
+}
+
+impl<W: Write> Sink for StreamWriter<W>{
+    fn write_bytes(&mut self, mut bytes: &[u8]){
+        while !bytes.is_empty(){
+            let take = (self.size - self.chunk.len()).min(bytes.len());
+            self.chunk.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+            if self.chunk.len() == self.size{
+                let chunk = std::mem::take(&mut self.chunk);
+                self.flush_chunk(&chunk);
+                self.chunk = chunk;
+                self.chunk.clear();
+            }
+        }
+    }
+}
+
+/// Reads values from a sequence of byte segments, like reassembled packets or the pieces of a rope,
+/// as if they were one buffer, without putting them together first.
+/// A value inside a segment is read from that segment directly. Only a value that runs over the
+/// end of one is decoded from a copy of the segments it spans.
+/// Made with ReadBuffer::chain. The limits count for every value on its own.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let bytes = encode(&(1u32, String::from("split"), 2u16));
+/// let (a, b) = bytes.split_at(10);
+/// let mut chain = ReadBuffer::chain(vec![a, b]);
+/// assert_eq!(Some(1u32), chain.read());
+/// assert_eq!(Some(String::from("split")), chain.read());
+/// assert_eq!(Some(2u16), chain.read());
+/// assert_eq!(0, chain.remaining());
+/// ```
+pub struct ChainedReadBuffer<'a>{
+    segments: Vec<&'a [u8]>,
+    segment: usize,
+    pos: usize,
+    limits: DecodeLimits,
+}
+
+impl<'a> ChainedReadBuffer<'a>{
+    /// A ChainedReadBuffer over the segments, in order.
+    pub fn new<I: IntoIterator<Item = &'a [u8]>>(segments: I) -> Self{
+        Self{
+            segments: segments.into_iter().filter(|s| !s.is_empty()).collect(),
+            segment: 0,
+            pos: 0,
+            limits: DecodeLimits::default(),
+        }
+    }
+    /// Set the limits every value has to stay within.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self{
+        self.limits = limits;
+        self
+    }
+    /// How many bytes are left to read over all segments.
+    pub fn remaining(&self) -> usize{
+        self.segments[self.segment.min(self.segments.len())..].iter().map(|s| s.len()).sum::<usize>() - self.pos
+    }
+    /// Read the next value. None if it does not decode, which leaves the position where it was.
+    /// A value that does not decode is only known not to after trying it on all segments that are left.
+    pub fn read<T: Bufferable>(&mut self) -> Option<T>{
+        let first = *self.segments.get(self.segment)?;
+        let mut buf = ReadBuffer::borrowed(&first[self.pos..], self.limits);
+        if let Option::Some(x) = T::from_buffer(&mut buf){
+            let used = buf.consumed();
+            self.advance(used);
+            return Option::Some(x);
+        }
+        let mut joined = first[self.pos..].to_vec();
+        for next in &self.segments[self.segment + 1..]{
+            joined.extend_from_slice(next);
+            let mut buf = ReadBuffer::borrowed(&joined, self.limits);
+            if let Option::Some(x) = T::from_buffer(&mut buf){
+                let used = buf.consumed();
+                self.advance(used);
+                return Option::Some(x);
+            }
+        }
+        Option::None
+    }
+    // Move used bytes ahead, into the next segments if needed.
+    fn advance(&mut self, mut used: usize){
+        while let Option::Some(segment) = self.segments.get(self.segment){
+            let left = segment.len() - self.pos;
+            if used < left{
+                self.pos += used;
+                return;
+            }
+            used -= left;
+            self.segment += 1;
+            self.pos = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::chunked::*;
+    #[test]
+    fn test_encode_chunked(){
+        let x = (vec![String::from("abc"); 50],ByteVec(vec![9; 333]),vec![1.5f32; 77]);
+        let mut whole = Vec::new();
+        x.copy_into_buffer(&mut whole);
+        for size in [1,7,64,1000,10000]{
+            let mut chunks: Vec<Vec<u8>> = Vec::new();
+            encode_chunked(&x, size, |chunk| chunks.push(chunk.to_vec()));
+            let (last, full) = chunks.split_last().unwrap();
+            assert!(full.iter().all(|c| c.len() == size));
+            assert!(!last.is_empty() && last.len() <= size);
+            assert_eq!(whole, chunks.concat());
+        }
+    }
+
+    struct Failing(usize);
+    impl std::io::Write for Failing{
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>{
+            if self.0 == 0{
+                return Err(std::io::Error::other("full"));
+            }
+            self.0 -= 1;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()>{ Ok(()) }
+    }
+
+    #[test]
+    fn test_stream_writer(){
+        let x = (vec![String::from("abc"); 50], ByteVec(vec![9; 333]));
+        let whole = encode(&x);
+        let mut writer = StreamWriter::new(Vec::new(), 100);
+        x.copy_into_buffer(&mut writer);
+        assert_eq!(whole.len() / 100 * 100, writer.written() as usize);
+        assert_eq!(whole, writer.finish().unwrap());
+        // paced to 100 kB/s, 3 kB take at least 30 ms
+        let start = std::time::Instant::now();
+        let mut writer = StreamWriter::new(Vec::new(), 1000).with_rate(100_000);
+        vec![1u8; 2992].copy_into_buffer(&mut writer);
+        assert_eq!(3000, writer.finish().unwrap().len());
+        assert!(start.elapsed() >= std::time::Duration::from_millis(30));
+        // the first error comes out of finish and stops the writing
+        let mut writer = StreamWriter::new(Failing(2), 10);
+        vec![0u8; 100].copy_into_buffer(&mut writer);
+        assert_eq!(20, writer.written());
+        assert_eq!("full", writer.finish().err().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_chained(){
+        let x = (vec![String::from("abc"); 20], ByteVec(vec![9; 33]), 7u64);
+        let whole = encode(&x);
+        for size in [1, 3, 16, 1000]{
+            let mut chain = ReadBuffer::chain(whole.chunks(size));
+            assert_eq!(Some(x.clone()), chain.read());
+            assert_eq!(0, chain.remaining());
+            assert_eq!(None, chain.read::<u8>());
+        }
+        let mut chain = ReadBuffer::chain(vec![&[0u8, 1][..], &[], &[2, 3, 4]]);
+        assert_eq!(Some(1u16), chain.read());
+        // a u32 does not fit in what is left, and the position stays
+        assert_eq!(None, chain.read::<u32>());
+        assert_eq!(3, chain.remaining());
+        assert_eq!(Some(0x0203), chain.read::<u16>());
+        assert_eq!(Some(4u8), chain.read());
+    }
+}
@@ -0,0 +1,677 @@
+//! A small schema language for messages, and a generator that turns it into Rust.
+//! One schema file can be the single source of truth for every program that reads the
+//! messages: parse gives the definitions to generators for other languages, and
+//! generate_rust writes structs and enums with their Bufferable impls, best called
+//! from build.rs through write_rust.
+//! ```text
+//! // comments run to the end of the line
+//! struct Player{
+//!     name: string,
+//!     score: u32,
+//!     position: (f32, f32),
+//!     inventory: vec<Item>,
+//!     stats: map<string, f64>,
+//! }
+//! enum Item{
+//!     Empty = 0,
+//!     Sword(u8) = 1,
+//!     Potion(string, u32) = 2,
+//! }
+//! ```
+//! The basic types are u8, u16, u32, u64, f32, f64, string and bytes (a ByteVec).
+//! vec<T>, map<K, V> (a HashMap) and tuples of 2 to 4 types can be nested, and structs
+//! and enums defined in the same file can be used by name, before or after their definition.
+//! Fields are written in order, enums with bufferable_enum.
+//! # Example
+//! ```
+//! use bin_buffer::codegen::*;
+//! let rust = generate_rust("struct Point{ x: f32, y: f32 }").unwrap();
+//! assert!(rust.contains("pub struct Point{"));
+//! assert!(rust.contains("pub x: f32,"));
+//! let error = generate_rust("struct Point{\n x: f33 }").unwrap_err();
+//! assert_eq!((2, "unknown type f33"), (error.line, error.message.as_str()));
+//! ```
+//! In build.rs:
+//! ```no_run
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("messages.rs");
+//! bin_buffer::codegen::write_rust(std::path::Path::new("messages.schema"), &out).unwrap();
+//! ```
+//! and next to the code that uses them `include!(concat!(env!("OUT_DIR"), "/messages.rs"));`.
+use std::fmt::Write;
+
+/// Type of a field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldType{
+    /// A u8.
+    U8,
+    /// A u16.
+    U16,
+    /// A u32.
+    U32,
+    /// A u64.
+    U64,
+    /// A f32.
+    F32,
+    /// A f64.
+    F64,
+    /// A String.
+    Str,
+    /// A ByteVec.
+    Bytes,
+    /// A Vec of the inner type.
+    Vec(Box<FieldType>),
+    /// A HashMap from the first type to the second.
+    Map(Box<FieldType>, Box<FieldType>),
+    /// A tuple.
+    Tuple(Vec<FieldType>),
+    /// A struct or enum of the schema.
+    Named(String),
+}
+
+impl FieldType{
+    /// The Rust type, with crate_path in front of the types of this crate.
+    pub fn rust(&self, crate_path: &str) -> String{
+        match self{
+            FieldType::U8 => String::from("u8"),
+            FieldType::U16 => String::from("u16"),
+            FieldType::U32 => String::from("u32"),
+            FieldType::U64 => String::from("u64"),
+            FieldType::F32 => String::from("f32"),
+            FieldType::F64 => String::from("f64"),
+            FieldType::Str => String::from("String"),
+            FieldType::Bytes => format!("{}::ByteVec", crate_path),
+            FieldType::Vec(inner) => format!("Vec<{}>", inner.rust(crate_path)),
+            FieldType::Map(k, v) => format!("std::collections::HashMap<{}, {}>", k.rust(crate_path), v.rust(crate_path)),
+            FieldType::Tuple(types) => format!("({})", types.iter().map(|t| t.rust(crate_path)).collect::<Vec<_>>().join(", ")),
+            FieldType::Named(name) => name.clone(),
+        }
+    }
+    /// Bytes every value takes, None if that depends on the value or the type is named.
+    pub fn fixed_len(&self) -> Option<usize>{
+        match self{
+            FieldType::U8 => Option::Some(1),
+            FieldType::U16 => Option::Some(2),
+            FieldType::U32 | FieldType::F32 => Option::Some(4),
+            FieldType::U64 | FieldType::F64 => Option::Some(8),
+            FieldType::Tuple(types) => types.iter().map(|t| t.fixed_len()).sum(),
+            _ => Option::None,
+        }
+    }
+}
+
+/// A struct, its fields written in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructDef{
+    /// Name of the struct.
+    pub name: String,
+    /// Names and types of the fields.
+    pub fields: Vec<(String, FieldType)>,
+}
+
+/// A variant of an enum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantDef{
+    /// Name of the variant.
+    pub name: String,
+    /// Types of the tuple fields, empty for a unit variant.
+    pub fields: Vec<FieldType>,
+    /// The discriminant.
+    pub tag: u32,
+}
+
+/// An enum, written as bufferable_enum does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumDef{
+    /// Name of the enum.
+    pub name: String,
+    /// The variants.
+    pub variants: Vec<VariantDef>,
+}
+
+/// A definition in a schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Definition{
+    /// A struct.
+    Struct(StructDef),
+    /// An enum.
+    Enum(EnumDef),
+}
+
+impl Definition{
+    /// Name of the struct or enum.
+    pub fn name(&self) -> &str{
+        match self{
+            Definition::Struct(s) => &s.name,
+            Definition::Enum(e) => &e.name,
+        }
+    }
+}
+
+/// What is wrong with a schema, and on which line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError{
+    /// Line of the problem, starting at 1.
+    pub line: usize,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError{}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token{
+    Ident(String),
+    Number(u64),
+    Punct(char),
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, ParseError>{
+    let mut tokens = Vec::new();
+    for (i, line) in src.lines().enumerate(){
+        let line_no = i + 1;
+        let code = match line.find("//"){
+            Option::Some(c) => &line[..c],
+            Option::None => line,
+        };
+        let mut chars = code.char_indices().peekable();
+        while let Option::Some((start, c)) = chars.next(){
+            if c.is_whitespace(){
+                continue;
+            }
+            if c.is_ascii_alphanumeric() || c == '_'{
+                let mut end = start + c.len_utf8();
+                while let Option::Some(&(j, d)) = chars.peek(){
+                    if !(d.is_ascii_alphanumeric() || d == '_'){
+                        break;
+                    }
+                    end = j + d.len_utf8();
+                    chars.next();
+                }
+                let word = &code[start..end];
+                let token = if c.is_ascii_digit(){
+                    Token::Number(word.parse().map_err(|_| ParseError{ line: line_no, message: format!("bad number {}", word) })?)
+                } else {
+                    Token::Ident(String::from(word))
+                };
+                tokens.push((token, line_no));
+            } else if "{}()<>,:=".contains(c){
+                tokens.push((Token::Punct(c), line_no));
+            } else {
+                return Err(ParseError{ line: line_no, message: format!("unexpected {}", c) });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser{
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    // names used as types, with their line
+    used: Vec<(String, usize)>,
+}
+
+impl Parser{
+    fn line(&self) -> usize{
+        self.tokens.get(self.pos).or_else(|| self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+    fn error<T>(&self, message: String) -> Result<T, ParseError>{
+        Err(ParseError{ line: self.line(), message })
+    }
+    fn peek(&self) -> Option<&Token>{
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+    fn punct(&mut self, c: char) -> Result<(), ParseError>{
+        if self.peek() == Option::Some(&Token::Punct(c)){
+            self.pos += 1;
+            return Ok(());
+        }
+        self.error(format!("expected {}", c))
+    }
+    fn eat(&mut self, c: char) -> bool{
+        self.punct(c).is_ok()
+    }
+    fn ident(&mut self) -> Result<String, ParseError>{
+        if let Option::Some(Token::Ident(name)) = self.peek(){
+            let name = name.clone();
+            self.pos += 1;
+            return Ok(name);
+        }
+        self.error(String::from("expected a name"))
+    }
+    fn field_type(&mut self) -> Result<FieldType, ParseError>{
+        if self.eat('('){
+            let mut types = Vec::new();
+            while !self.eat(')'){
+                types.push(self.field_type()?);
+                if !self.eat(','){
+                    self.punct(')')?;
+                    break;
+                }
+            }
+            if types.len() < 2 || types.len() > 4{
+                return self.error(String::from("tuples have 2 to 4 types"));
+            }
+            return Ok(FieldType::Tuple(types));
+        }
+        let line = self.line();
+        let name = self.ident()?;
+        Ok(match name.as_str(){
+            "u8" => FieldType::U8,
+            "u16" => FieldType::U16,
+            "u32" => FieldType::U32,
+            "u64" => FieldType::U64,
+            "f32" => FieldType::F32,
+            "f64" => FieldType::F64,
+            "string" => FieldType::Str,
+            "bytes" => FieldType::Bytes,
+            "vec" => {
+                self.punct('<')?;
+                let inner = self.field_type()?;
+                self.punct('>')?;
+                FieldType::Vec(Box::new(inner))
+            },
+            "map" => {
+                self.punct('<')?;
+                let k = self.field_type()?;
+                self.punct(',')?;
+                let v = self.field_type()?;
+                self.punct('>')?;
+                FieldType::Map(Box::new(k), Box::new(v))
+            },
+            _ => {
+                self.used.push((name.clone(), line));
+                FieldType::Named(name)
+            },
+        })
+    }
+    // Items separated by commas up to the closing brace.
+    fn list<T, F: FnMut(&mut Self) -> Result<T, ParseError>>(&mut self, mut item: F) -> Result<Vec<T>, ParseError>{
+        self.punct('{')?;
+        let mut items = Vec::new();
+        while !self.eat('}'){
+            items.push(item(self)?);
+            if !self.eat(','){
+                self.punct('}')?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+    fn definition(&mut self) -> Result<Definition, ParseError>{
+        match self.ident()?.as_str(){
+            "struct" => {
+                let name = self.ident()?;
+                let fields = self.list(|p|{
+                    let field = p.ident()?;
+                    p.punct(':')?;
+                    Ok((field, p.field_type()?))
+                })?;
+                Ok(Definition::Struct(StructDef{ name, fields }))
+            },
+            "enum" => {
+                let name = self.ident()?;
+                let variants = self.list(|p|{
+                    let name = p.ident()?;
+                    let mut fields = Vec::new();
+                    if p.eat('('){
+                        while !p.eat(')'){
+                            fields.push(p.field_type()?);
+                            if !p.eat(','){
+                                p.punct(')')?;
+                                break;
+                            }
+                        }
+                    }
+                    p.punct('=')?;
+                    match p.peek(){
+                        Option::Some(Token::Number(n)) if *n <= u32::MAX as u64 => {
+                            let tag = *n as u32;
+                            p.pos += 1;
+                            Ok(VariantDef{ name, fields, tag })
+                        },
+                        _ => p.error(String::from("expected a discriminant")),
+                    }
+                })?;
+                Ok(Definition::Enum(EnumDef{ name, variants }))
+            },
+            other => {
+                self.pos -= 1;
+                self.error(format!("expected struct or enum, not {}", other))
+            },
+        }
+    }
+}
+
+const KEYWORDS: [&str; 10] = ["u8", "u16", "u32", "u64", "f32", "f64", "string", "bytes", "vec", "map"];
+
+/// Parse a schema. Fails on syntax errors, unknown types, names that are defined twice,
+/// fields and variants that are listed twice and duplicate discriminants.
+pub fn parse(src: &str) -> Result<Vec<Definition>, ParseError>{
+    let mut parser = Parser{ tokens: tokenize(src)?, pos: 0, used: Vec::new() };
+    let mut defs = Vec::new();
+    while parser.peek().is_some(){
+        let line = parser.line();
+        let def = parser.definition()?;
+        let error = |message: String| Err(ParseError{ line, message });
+        if KEYWORDS.contains(&def.name()) || defs.iter().any(|d: &Definition| d.name() == def.name()){
+            return error(format!("{} is defined twice or is a basic type", def.name()));
+        }
+        match &def{
+            Definition::Struct(s) => for (i, (field, _)) in s.fields.iter().enumerate(){
+                if s.fields[..i].iter().any(|(f, _)| f == field){
+                    return error(format!("field {} of {} is listed twice", field, s.name));
+                }
+            },
+            Definition::Enum(e) => for (i, v) in e.variants.iter().enumerate(){
+                if e.variants[..i].iter().any(|w| w.name == v.name || w.tag == v.tag){
+                    return error(format!("variant {} of {} has the name or discriminant of another", v.name, e.name));
+                }
+            },
+        }
+        defs.push(def);
+    }
+    // every name has to be defined somewhere in the file
+    if let Option::Some((name, line)) = parser.used.iter().find(|(n, _)| !defs.iter().any(|d| d.name() == n)){
+        return Err(ParseError{ line: *line, message: format!("unknown type {}", name) });
+    }
+    Ok(defs)
+}
+
+/// Rust source for a schema, using bin_buffer from the crate root as ::bin_buffer.
+pub fn generate_rust(src: &str) -> Result<String, ParseError>{
+    Generator::new().generate(src)
+}
+
+/// Rust source for a schema, with crate_path as the path to this crate, like "crate" from inside it.
+pub fn generate_rust_with_path(src: &str, crate_path: &str) -> Result<String, ParseError>{
+    Generator::new().crate_path(crate_path).generate(src)
+}
+
+/// Generates Rust with options, generate_rust uses the defaults.
+/// # Example
+/// ```
+/// use bin_buffer::codegen::*;
+/// let rust = Generator::new().descriptors(true).generate("struct Point{ x: f32, y: f32 }").unwrap();
+/// assert!(rust.contains("impl ::bin_buffer::describe::Describe for Point{"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Generator{
+    crate_path: String,
+    descriptors: bool,
+}
+
+impl Default for Generator{
+    fn default() -> Self{
+        Self{ crate_path: String::from("::bin_buffer"), descriptors: false }
+    }
+}
+
+impl Generator{
+    /// A generator using ::bin_buffer and writing no descriptors.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// The path to this crate in the generated code, like "crate" from inside it.
+    pub fn crate_path(mut self, crate_path: &str) -> Self{
+        self.crate_path = String::from(crate_path);
+        self
+    }
+    /// Also implement describe::Describe for every struct and enum.
+    pub fn descriptors(mut self, descriptors: bool) -> Self{
+        self.descriptors = descriptors;
+        self
+    }
+    fn struct_descriptor(&self, s: &StructDef, out: &mut String){
+        let c = &self.crate_path;
+        let _ = writeln!(out, "\nimpl {c}::describe::Describe for {}{{\n    const DESCRIPTOR: {c}::describe::TypeDescriptor = {c}::describe::TypeDescriptor{{", s.name, c = c);
+        let _ = writeln!(out, "        name: \"{}\",\n        kind: {}::describe::Kind::Struct(&[", s.name, c);
+        let mut offset = Option::Some(0);
+        for (field, t) in &s.fields{
+            let len = t.fixed_len();
+            let _ = writeln!(out, "            {}::describe::FieldDescriptor{{ name: \"{}\", type_name: \"{}\", offset: Option::{:?}, len: Option::{:?} }},",
+                c, field, t.rust(c), offset, len);
+            offset = offset.and_then(|o| Option::Some(o + len?));
+        }
+        let _ = writeln!(out, "        ]),\n    }};\n}}");
+    }
+    fn enum_descriptor(&self, e: &EnumDef, out: &mut String){
+        let c = &self.crate_path;
+        let _ = writeln!(out, "\nimpl {c}::describe::Describe for {}{{\n    const DESCRIPTOR: {c}::describe::TypeDescriptor = {c}::describe::TypeDescriptor{{", e.name, c = c);
+        let _ = writeln!(out, "        name: \"{}\",\n        kind: {}::describe::Kind::Enum(&[", e.name, c);
+        for v in &e.variants{
+            let types: Vec<String> = v.fields.iter().map(|t| format!("\"{}\"", t.rust(c))).collect();
+            let _ = writeln!(out, "            {}::describe::VariantDescriptor{{ name: \"{}\", tag: {}, fields: &[{}] }},", c, v.name, v.tag, types.join(", "));
+        }
+        let _ = writeln!(out, "        ]),\n    }};\n}}");
+    }
+    /// Rust source for a schema.
+    pub fn generate(&self, src: &str) -> Result<String, ParseError>{
+        let defs = parse(src)?;
+        let c = self.crate_path.as_str();
+        let mut out = String::from("// Generated by bin_buffer::codegen, do not edit.\n");
+        for def in &defs{
+            out.push('\n');
+            match def{
+                Definition::Struct(s) => {
+                    let _ = writeln!(out, "#[derive(Clone, Debug, PartialEq)]\npub struct {}{{", s.name);
+                    for (field, t) in &s.fields{
+                        let _ = writeln!(out, "    pub {}: {},", field, t.rust(c));
+                    }
+                    let _ = writeln!(out, "}}\n\nimpl {}::Bufferable for {}{{", c, s.name);
+                    let _ = writeln!(out, "    #[allow(unused_variables)]\n    fn into_buffer<S: {}::Sink + ?Sized>(self, vec: &mut S){{", c);
+                    for (field, _) in &s.fields{
+                        let _ = writeln!(out, "        {}::Bufferable::into_buffer(self.{}, vec);", c, field);
+                    }
+                    let _ = writeln!(out, "    }}\n\n    #[allow(unused_variables)]\n    fn copy_into_buffer<S: {}::Sink + ?Sized>(&self, vec: &mut S){{", c);
+                    for (field, _) in &s.fields{
+                        let _ = writeln!(out, "        {}::Bufferable::copy_into_buffer(&self.{}, vec);", c, field);
+                    }
+                    let _ = writeln!(out, "    }}\n\n    #[allow(unused_variables)]\n    fn from_buffer(buf: &mut {}::ReadBuffer) -> Option<Self>{{\n        Option::Some(Self{{", c);
+                    for (field, _) in &s.fields{
+                        let _ = writeln!(out, "            {}: {}::Bufferable::from_buffer(buf)?,", field, c);
+                    }
+                    let lens: Vec<String> = s.fields.iter()
+                        .map(|(field, _)| format!("{}::Bufferable::buffer_len(&self.{})", c, field)).collect();
+                    let lens = if lens.is_empty() { String::from("0") } else { lens.join("\n            + ") };
+                    let _ = writeln!(out, "        }})\n    }}\n\n    fn buffer_len(&self) -> usize{{\n        {}\n    }}\n}}", lens);
+                    if self.descriptors{
+                        self.struct_descriptor(s, &mut out);
+                    }
+                },
+                Definition::Enum(e) => {
+                    let _ = writeln!(out, "#[derive(Clone, Debug, PartialEq)]\npub enum {}{{", e.name);
+                    for v in &e.variants{
+                        if v.fields.is_empty(){
+                            let _ = writeln!(out, "    {},", v.name);
+                        } else {
+                            let types: Vec<String> = v.fields.iter().map(|t| t.rust(c)).collect();
+                            let _ = writeln!(out, "    {}({}),", v.name, types.join(", "));
+                        }
+                    }
+                    let _ = writeln!(out, "}}\n\n{}::bufferable_enum!{{ {}{{", c, e.name);
+                    for v in &e.variants{
+                        if v.fields.is_empty(){
+                            let _ = writeln!(out, "    {} = {},", v.name, v.tag);
+                        } else {
+                            let fields: Vec<String> = v.fields.iter().enumerate().map(|(i, t)| format!("f{}: {}", i, t.rust(c))).collect();
+                            let _ = writeln!(out, "    {}({}) = {},", v.name, fields.join(", "), v.tag);
+                        }
+                    }
+                    let _ = writeln!(out, "}} }}");
+                    if self.descriptors{
+                        self.enum_descriptor(e, &mut out);
+                    }
+                },
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Generate Rust for the schema file input and write it to output, for build.rs.
+/// Tells cargo to run build.rs again when the schema changes.
+/// Parse errors fail with InvalidData.
+#[cfg(feature = "fs")]
+pub fn write_rust(input: &std::path::Path, output: &std::path::Path) -> std::io::Result<()>{
+    println!("cargo:rerun-if-changed={}", input.display());
+    let src = std::fs::read_to_string(input)?;
+    let rust = generate_rust(&src).map_err(|e|
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", input.display(), e)))?;
+    std::fs::write(output, rust)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::codegen::*;
+
+    const SCHEMA: &str = "
+        // a player
+        struct Player{
+            name: string,
+            score: u32,
+            position: (f32, f32),
+            inventory: vec<Item>,
+            stats: map<string, vec<u8>>,
+        }
+        enum Item{ Empty = 0, Sword(u8) = 1, Potion(string, u32) = 7 }
+        struct Nothing{}
+    ";
+
+    // What generate_rust_with_path(SCHEMA, "crate") writes, checked below.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Player{
+        pub name: String,
+        pub score: u32,
+        pub position: (f32, f32),
+        pub inventory: Vec<Item>,
+        pub stats: std::collections::HashMap<String, Vec<u8>>,
+    }
+
+    impl crate::Bufferable for Player{
+        #[allow(unused_variables)]
+        fn into_buffer<S: crate::Sink + ?Sized>(self, vec: &mut S){
+            crate::Bufferable::into_buffer(self.name, vec);
+            crate::Bufferable::into_buffer(self.score, vec);
+            crate::Bufferable::into_buffer(self.position, vec);
+            crate::Bufferable::into_buffer(self.inventory, vec);
+            crate::Bufferable::into_buffer(self.stats, vec);
+        }
+
+        #[allow(unused_variables)]
+        fn copy_into_buffer<S: crate::Sink + ?Sized>(&self, vec: &mut S){
+            crate::Bufferable::copy_into_buffer(&self.name, vec);
+            crate::Bufferable::copy_into_buffer(&self.score, vec);
+            crate::Bufferable::copy_into_buffer(&self.position, vec);
+            crate::Bufferable::copy_into_buffer(&self.inventory, vec);
+            crate::Bufferable::copy_into_buffer(&self.stats, vec);
+        }
+
+        #[allow(unused_variables)]
+        fn from_buffer(buf: &mut crate::ReadBuffer) -> Option<Self>{
+            Option::Some(Self{
+                name: crate::Bufferable::from_buffer(buf)?,
+                score: crate::Bufferable::from_buffer(buf)?,
+                position: crate::Bufferable::from_buffer(buf)?,
+                inventory: crate::Bufferable::from_buffer(buf)?,
+                stats: crate::Bufferable::from_buffer(buf)?,
+            })
+        }
+
+        fn buffer_len(&self) -> usize{
+            crate::Bufferable::buffer_len(&self.name)
+                + crate::Bufferable::buffer_len(&self.score)
+                + crate::Bufferable::buffer_len(&self.position)
+                + crate::Bufferable::buffer_len(&self.inventory)
+                + crate::Bufferable::buffer_len(&self.stats)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Item{
+        Empty,
+        Sword(u8),
+        Potion(String, u32),
+    }
+
+    crate::bufferable_enum!{ Item{
+        Empty = 0,
+        Sword(f0: u8) = 1,
+        Potion(f0: String, f1: u32) = 7,
+    } }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Nothing{
+    }
+
+    impl crate::Bufferable for Nothing{
+        #[allow(unused_variables)]
+        fn into_buffer<S: crate::Sink + ?Sized>(self, vec: &mut S){
+        }
+
+        #[allow(unused_variables)]
+        fn copy_into_buffer<S: crate::Sink + ?Sized>(&self, vec: &mut S){
+        }
+
+        #[allow(unused_variables)]
+        fn from_buffer(buf: &mut crate::ReadBuffer) -> Option<Self>{
+            Option::Some(Self{
+            })
+        }
+
+        fn buffer_len(&self) -> usize{
+            0
+        }
+    }
+
+    #[test]
+    fn test_codegen(){
+        let rust = generate_rust_with_path(SCHEMA, "crate").unwrap();
+        let this = include_str!("codegen.rs");
+        let start = this.find("    #[derive(Clone, Debug, PartialEq)]\n    pub struct Player").unwrap();
+        let end = this.find("    #[test]\n    fn test_codegen").unwrap();
+        let expected: String = this[start..end].lines().map(|l| format!("{}\n", l.strip_prefix("    ").unwrap_or(l))).collect();
+        assert_eq!(format!("// Generated by bin_buffer::codegen, do not edit.\n\n{}\n", expected.trim_end()), rust);
+        let player = Player{
+            name: String::from("ann"),
+            score: 3,
+            position: (1.0, 2.0),
+            inventory: vec![Item::Sword(4), Item::Potion(String::from("heal"), 2), Item::Empty],
+            stats: vec![(String::from("xp"), vec![1])].into_iter().collect(),
+        };
+        let bytes = encode(&player);
+        assert_eq!(player.buffer_len(), bytes.len());
+        assert_eq!(Some(player), decode(&bytes));
+        assert_eq!(Some(Nothing{}), decode(&[]));
+        // descriptors
+        let rust = Generator::new().crate_path("crate").descriptors(true).generate(SCHEMA).unwrap();
+        assert!(rust.contains("impl crate::describe::Describe for Player{"));
+        assert!(rust.contains("FieldDescriptor{ name: \"name\", type_name: \"String\", offset: Option::Some(0), len: Option::None },"));
+        assert!(rust.contains("FieldDescriptor{ name: \"position\", type_name: \"(f32, f32)\", offset: Option::None, len: Option::Some(8) },"));
+        assert!(rust.contains("FieldDescriptor{ name: \"stats\", type_name: \"std::collections::HashMap<String, Vec<u8>>\", offset: Option::None, len: Option::None },"));
+        assert!(rust.contains("VariantDescriptor{ name: \"Potion\", tag: 7, fields: &[\"String\", \"u32\"] },"));
+        let defs = parse(SCHEMA).unwrap();
+        assert_eq!(vec!["Player", "Item", "Nothing"], defs.iter().map(|d| d.name()).collect::<Vec<_>>());
+        for (src, line) in [
+            ("struct A{ x: B }", 1),
+            ("struct A{}\nenum A{}", 2),
+            ("struct A{ x: u8, x: u8 }", 1),
+            ("enum A{ X = 1, Y = 1 }", 1),
+            ("enum A{ X }", 1),
+            ("struct A{ x: (u8) }", 1),
+            ("struct A{ x: u8\n y: u8 }", 2),
+            ("message A{}", 1),
+            ("struct A{ x: u8; }", 1),
+            ("struct u8{}", 1),
+        ].iter(){
+            assert_eq!(*line, parse(src).unwrap_err().line, "{}", src);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Columnar layout for a Vec of records: all values of the first field, then all of the
+//! second and so on, instead of one whole record after the other.
+//! Similar values end up next to each other, which compresses a lot better, and columns
+//! of numbers go through the bulk paths of their type.
+//! Wrap the Vec in Columns to get this layout. The records have to implement Columnar,
+//! which the columnar macro does for structs with named fields, and which tuples already do.
+//! A column is written like a Vec without its length, the number of records comes first.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::columnar::*;
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct Sample{ time: u32, value: f32 }
+//! columnar!{ Sample{ time: u32, value: f32 } }
+//! let samples = Columns(vec![Sample{ time: 1, value: 0.5 }, Sample{ time: 2, value: 1.5 }]);
+//! let mut buffer = Vec::new();
+//! samples.copy_into_buffer(&mut buffer);
+//! // the times come before the values
+//! assert_eq!(vec![0,0,0,0,0,0,0,2, 0,0,0,1, 0,0,0,2, 0x3f,0,0,0, 0x3f,0xc0,0,0], buffer);
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! assert_eq!(Some(samples), Columns::<Sample>::from_buffer(&mut buffer));
+//! ```
+use crate::{ Bufferable, ReadBuffer, Sink };
+
+/// Records that can be written as columns.
+pub trait Columnar: Sized{
+    /// Write every field of rows as a column, in field order.
+    fn write_columns<S: Sink + ?Sized>(rows: &[Self], vec: &mut S);
+    /// Read len records written by write_columns.
+    fn read_columns(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>;
+    /// How many bytes write_columns will write, 0 if unknown.
+    fn columns_len(rows: &[Self]) -> usize{
+        let _ = rows;
+        0
+    }
+}
+
+/// Write one column: the field picked by get of every row.
+pub fn write_column<R, T: Bufferable + Clone, S: Sink + ?Sized, F: Fn(&R) -> &T>(rows: &[R], get: F, vec: &mut S){
+    let column: Vec<T> = rows.iter().map(|row| get(row).clone()).collect();
+    T::copy_slice_into_buffer(&column, vec);
+}
+
+/// Read one column of len values.
+pub fn read_column<T: Bufferable>(len: usize, buf: &mut ReadBuffer) -> Option<std::vec::IntoIter<T>>{
+    Option::Some(T::vec_from_buffer(len, buf)?.into_iter())
+}
+
+/// A Vec of records that is written as columns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Columns<T>(pub Vec<T>);
+
+impl<T> std::ops::Deref for Columns<T>{
+    type Target = Vec<T>;
+    fn deref(&self) -> &Vec<T>{
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for Columns<T>{
+    fn deref_mut(&mut self) -> &mut Vec<T>{
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for Columns<T>{
+    fn from(vec: Vec<T>) -> Self{
+        Self(vec)
+    }
+}
+
+impl<T> From<Columns<T>> for Vec<T>{
+    fn from(columns: Columns<T>) -> Self{
+        columns.0
+    }
+}
+
+impl<T: Columnar> Bufferable for Columns<T>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.reserve(self.buffer_len());
+        (self.0.len() as u64).into_buffer(vec);
+        T::write_columns(&self.0, vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            Option::Some(Self(T::read_columns(len, buf)?))
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + T::columns_len(&self.0)
+    }
+}
+
+macro_rules! tuple_columnar{
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: Bufferable + Clone),*> Columnar for ($($t,)*){
+            fn write_columns<S: Sink + ?Sized>(rows: &[Self], vec: &mut S){
+                $( write_column(rows, |row| &row.$i, vec); )*
+            }
+
+            #[allow(non_snake_case)]
+            fn read_columns(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+                $( let mut $t = read_column::<$t>(len, buf)?; )*
+                Option::Some((0..len).map(|_| ($($t.next().unwrap(),)*)).collect())
+            }
+
+            fn columns_len(rows: &[Self]) -> usize{
+                0 $( + rows.iter().map(|row| row.$i.buffer_len()).sum::<usize>() )*
+            }
+        }
+    };
+}
+
+tuple_columnar!(A 0, B 1);
+tuple_columnar!(A 0, B 1, C 2);
+tuple_columnar!(A 0, B 1, C 2, D 3);
+
+/// Implement Columnar for a struct with named fields, see the columnar module.
+/// Every field is listed with its type, the types have to be Bufferable and Clone.
+#[macro_export]
+macro_rules! columnar{
+    ($name:ident{ $( $field:ident : $ty:ty ),* $(,)? }) => {
+        impl $crate::columnar::Columnar for $name{
+            fn write_columns<S: $crate::Sink + ?Sized>(rows: &[Self], vec: &mut S){
+                $( $crate::columnar::write_column(rows, |row: &$name| &row.$field, vec); )*
+            }
+
+            fn read_columns(len: usize, buf: &mut $crate::ReadBuffer) -> Option<Vec<Self>>{
+                $( let mut $field = $crate::columnar::read_column::<$ty>(len, buf)?; )*
+                Option::Some((0..len).map(|_| $name{ $( $field: $field.next().unwrap() ),* }).collect())
+            }
+
+            fn columns_len(rows: &[Self]) -> usize{
+                0 $( + rows.iter().map(|row| $crate::Bufferable::buffer_len(&row.$field)).sum::<usize>() )*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::columnar::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Event{
+        id: u64,
+        name: String,
+        tags: Vec<u8>,
+    }
+
+    columnar!{ Event{ id: u64, name: String, tags: Vec<u8>, } }
+
+    #[test]
+    fn test_columnar(){
+        let events: Vec<Event> = (0..100).map(|i| Event{ id: i, name: format!("e{}", i), tags: vec![i as u8; (i % 3) as usize] }).collect();
+        let columns = Columns(events.clone());
+        let mut buffer = Vec::new();
+        columns.copy_into_buffer(&mut buffer);
+        assert_eq!(columns.buffer_len(), buffer.len());
+        // the ids come first, as one block
+        let mut ids = Vec::new();
+        (100u64).into_buffer(&mut ids);
+        u64::copy_slice_into_buffer(&(0..100).collect::<Vec<u64>>(), &mut ids);
+        assert_eq!(&ids[..], &buffer[..ids.len()]);
+        // as many bytes as writing the records one by one
+        let rows: usize = events.iter().map(|e| 8 + e.name.buffer_len() + e.tags.buffer_len()).sum();
+        assert_eq!(8 + rows, buffer.len());
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!(Some(columns), Columns::<Event>::from_buffer(&mut read));
+        assert_eq!(0, read.remaining());
+        assert_eq!(None, Columns::<Event>::from_buffer(&mut ReadBuffer::from_raw(buffer[..buffer.len() - 1].to_vec())));
+        // tuples
+        let pairs = Columns(vec![(1u8, 2.0f32), (3, 4.0)]);
+        let mut buffer = Vec::new();
+        pairs.copy_into_buffer(&mut buffer);
+        assert_eq!(vec![0,0,0,0,0,0,0,2, 1, 3, 0x40,0,0,0, 0x40,0x80,0,0], buffer);
+        assert_eq!(Some(pairs), Columns::<(u8, f32)>::from_buffer(&mut ReadBuffer::from_raw(buffer)));
+        let empty = Columns(Vec::<(u8, u8, String)>::new());
+        assert_eq!(Some(empty.clone()), decode(&encode(&empty)));
+    }
+}
@@ -0,0 +1,601 @@
+//! Reading and writing the bincode and postcard layouts, to move existing data to this crate bit by bit.
+//! Bincode is the fixed int little endian layout of bincode 1 with its default options,
+//! postcard is the layout of postcard 1, with varints for integers above u8 and for lengths.
+//! Config builds a layout from a byte order, a length width and whether to use varints,
+//! for picking one layout for a whole code base.
+//! Only the types this crate supports are covered, implement Compat for your own
+//! structs by writing and reading their fields in order, as serde would.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::compat::*;
+//! let x = (7u32, String::from("hi"));
+//! let old = to_vec::<Bincode, _>(&x);
+//! assert_eq!(vec![7,0,0,0, 2,0,0,0,0,0,0,0, b'h',b'i'], old);
+//! let y: (u32,String) = from_slice::<Bincode, _>(&old).unwrap();
+//! assert_eq!(x, y);
+//! assert_eq!(vec![7, 2, b'h', b'i'], to_vec::<Postcard, _>(&x));
+//! type Wire = Config<false, 2, false>;
+//! assert_eq!(vec![7,0,0,0, 2,0, b'h',b'i'], to_vec::<Wire, _>(&x));
+//! ```
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::{ BuildHasher, Hash };
+use crate::{ varint, Buffer, ByteVec, ReadBuffer, Sink };
+
+/// How integers and lengths are laid out.
+pub trait Format{
+    /// Write a u16.
+    fn write_u16<S: Sink + ?Sized>(x: u16, vec: &mut S);
+    /// Write a u32.
+    fn write_u32<S: Sink + ?Sized>(x: u32, vec: &mut S);
+    /// Write a u64.
+    fn write_u64<S: Sink + ?Sized>(x: u64, vec: &mut S);
+    /// Read a u16.
+    fn read_u16(buf: &mut ReadBuffer) -> Option<u16>;
+    /// Read a u32.
+    fn read_u32(buf: &mut ReadBuffer) -> Option<u32>;
+    /// Read a u64.
+    fn read_u64(buf: &mut ReadBuffer) -> Option<u64>;
+    /// Write the length of a string or collection.
+    fn write_len<S: Sink + ?Sized>(len: usize, vec: &mut S){
+        Self::write_u64(len as u64, vec);
+    }
+    /// Read the length of a string or collection.
+    fn read_len(buf: &mut ReadBuffer) -> Option<usize>{
+        usize::try_from(Self::read_u64(buf)?).ok()
+    }
+    /// Write an f32, little endian by default.
+    fn write_f32<S: Sink + ?Sized>(x: f32, vec: &mut S){
+        vec.write_bytes(&x.to_le_bytes());
+    }
+    /// Write an f64, little endian by default.
+    fn write_f64<S: Sink + ?Sized>(x: f64, vec: &mut S){
+        vec.write_bytes(&x.to_le_bytes());
+    }
+    /// Read an f32.
+    fn read_f32(buf: &mut ReadBuffer) -> Option<f32>{
+        Option::Some(f32::from_le_bytes(<[u8; 4]>::try_from(buf.read_bytes(4)?).ok()?))
+    }
+    /// Read an f64.
+    fn read_f64(buf: &mut ReadBuffer) -> Option<f64>{
+        Option::Some(f64::from_le_bytes(<[u8; 8]>::try_from(buf.read_bytes(8)?).ok()?))
+    }
+    /// Whether signed integers above i8 are zigzag encoded and written as a u64, as varint
+    /// layouts do. Otherwise their two's complement bits are written as the unsigned type.
+    const ZIGZAG: bool = false;
+    /// Write a char, as its UTF-8 bytes without a length by default, like bincode.
+    fn write_char<S: Sink + ?Sized>(x: char, vec: &mut S){
+        vec.write_bytes(x.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    /// Read a char.
+    fn read_char(buf: &mut ReadBuffer) -> Option<char>{
+        let first = buf.read_bytes(1)?[0];
+        let len = match first{
+            0..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Option::None,
+        };
+        let mut utf8 = [first, 0, 0, 0];
+        utf8[1..len].copy_from_slice(buf.read_bytes(len - 1)?);
+        std::str::from_utf8(&utf8[..len]).ok()?.chars().next()
+    }
+}
+
+/// The bincode 1 layout: little endian fixed size integers, u64 lengths.
+pub struct Bincode;
+
+impl Format for Bincode{
+    fn write_u16<S: Sink + ?Sized>(x: u16, vec: &mut S){
+        vec.write_bytes(&x.to_le_bytes());
+    }
+    fn write_u32<S: Sink + ?Sized>(x: u32, vec: &mut S){
+        vec.write_bytes(&x.to_le_bytes());
+    }
+    fn write_u64<S: Sink + ?Sized>(x: u64, vec: &mut S){
+        vec.write_bytes(&x.to_le_bytes());
+    }
+    fn read_u16(buf: &mut ReadBuffer) -> Option<u16>{
+        Option::Some(u16::from_le_bytes(<[u8; 2]>::try_from(buf.read_bytes(2)?).ok()?))
+    }
+    fn read_u32(buf: &mut ReadBuffer) -> Option<u32>{
+        Option::Some(u32::from_le_bytes(<[u8; 4]>::try_from(buf.read_bytes(4)?).ok()?))
+    }
+    fn read_u64(buf: &mut ReadBuffer) -> Option<u64>{
+        Option::Some(u64::from_le_bytes(<[u8; 8]>::try_from(buf.read_bytes(8)?).ok()?))
+    }
+}
+
+/// The postcard 1 layout: varints for integers above u8 and for lengths.
+pub struct Postcard;
+
+/// Chars are written as a string of one char.
+impl Format for Postcard{
+    const ZIGZAG: bool = true;
+    fn write_u16<S: Sink + ?Sized>(x: u16, vec: &mut S){
+        varint::write_u64(u64::from(x), vec);
+    }
+    fn write_u32<S: Sink + ?Sized>(x: u32, vec: &mut S){
+        varint::write_u64(u64::from(x), vec);
+    }
+    fn write_u64<S: Sink + ?Sized>(x: u64, vec: &mut S){
+        varint::write_u64(x, vec);
+    }
+    fn read_u16(buf: &mut ReadBuffer) -> Option<u16>{
+        u16::try_from(varint::read_u64(buf)?).ok()
+    }
+    fn read_u32(buf: &mut ReadBuffer) -> Option<u32>{
+        u32::try_from(varint::read_u64(buf)?).ok()
+    }
+    fn read_u64(buf: &mut ReadBuffer) -> Option<u64>{
+        varint::read_u64(buf)
+    }
+    fn write_char<S: Sink + ?Sized>(x: char, vec: &mut S){
+        let mut utf8 = [0; 4];
+        let utf8 = x.encode_utf8(&mut utf8);
+        Self::write_len(utf8.len(), vec);
+        vec.write_bytes(utf8.as_bytes());
+    }
+    fn read_char(buf: &mut ReadBuffer) -> Option<char>{
+        let len = Self::read_len(buf)?;
+        if len > 4{
+            return Option::None;
+        }
+        let mut chars = std::str::from_utf8(buf.read_bytes(len)?).ok()?.chars();
+        match (chars.next(), chars.next()){
+            (Option::Some(c), Option::None) => Option::Some(c),
+            _ => Option::None,
+        }
+    }
+}
+
+/// A Format made of the choices an organization standardizes on, so it can name its layout
+/// once, like `type Wire = Config<false, 4, false>;`, and write everything with
+/// `to_vec::<Wire, _>` instead of picking an encoding for every field.
+/// Integers and floats are big endian if BIG_ENDIAN and little endian otherwise,
+/// lengths take LEN_BYTES bytes, which has to be 1, 2, 4 or 8.
+/// With VARINTS integers above u8 and lengths are varints instead, signed ones zigzag encoded.
+/// Chars are written as their scalar value in a u32, like Bufferable does.
+/// Writing panics for a length that does not fit in LEN_BYTES bytes.
+pub struct Config<const BIG_ENDIAN: bool, const LEN_BYTES: usize, const VARINTS: bool>;
+
+/// The layout of Bufferable itself: big endian, u64 lengths.
+pub type Native = Config<true, 8, false>;
+
+impl<const BIG_ENDIAN: bool, const LEN_BYTES: usize, const VARINTS: bool> Config<BIG_ENDIAN, LEN_BYTES, VARINTS>{
+    const VALID_LEN_BYTES: () = assert!(matches!(LEN_BYTES, 1 | 2 | 4 | 8), "LEN_BYTES has to be 1, 2, 4 or 8");
+
+    // The lowest bytes of x in the chosen byte order.
+    fn write_fixed<S: Sink + ?Sized>(x: u64, bytes: usize, vec: &mut S){
+        if BIG_ENDIAN{
+            vec.write_bytes(&x.to_be_bytes()[8 - bytes..]);
+        } else {
+            vec.write_bytes(&x.to_le_bytes()[..bytes]);
+        }
+    }
+
+    fn read_fixed(buf: &mut ReadBuffer, bytes: usize) -> Option<u64>{
+        let mut full = [0u8; 8];
+        if BIG_ENDIAN{
+            full[8 - bytes..].copy_from_slice(buf.read_bytes(bytes)?);
+            Option::Some(u64::from_be_bytes(full))
+        } else {
+            full[..bytes].copy_from_slice(buf.read_bytes(bytes)?);
+            Option::Some(u64::from_le_bytes(full))
+        }
+    }
+
+    fn write_uint<S: Sink + ?Sized>(x: u64, bytes: usize, vec: &mut S){
+        if VARINTS{
+            varint::write_u64(x, vec);
+        } else {
+            Self::write_fixed(x, bytes, vec);
+        }
+    }
+
+    fn read_uint(buf: &mut ReadBuffer, bytes: usize) -> Option<u64>{
+        if VARINTS{
+            varint::read_u64(buf)
+        } else {
+            Self::read_fixed(buf, bytes)
+        }
+    }
+}
+
+impl<const BIG_ENDIAN: bool, const LEN_BYTES: usize, const VARINTS: bool> Format for Config<BIG_ENDIAN, LEN_BYTES, VARINTS>{
+    const ZIGZAG: bool = VARINTS;
+    fn write_u16<S: Sink + ?Sized>(x: u16, vec: &mut S){
+        Self::write_uint(u64::from(x), 2, vec);
+    }
+    fn write_u32<S: Sink + ?Sized>(x: u32, vec: &mut S){
+        Self::write_uint(u64::from(x), 4, vec);
+    }
+    fn write_u64<S: Sink + ?Sized>(x: u64, vec: &mut S){
+        Self::write_uint(x, 8, vec);
+    }
+    fn read_u16(buf: &mut ReadBuffer) -> Option<u16>{
+        u16::try_from(Self::read_uint(buf, 2)?).ok()
+    }
+    fn read_u32(buf: &mut ReadBuffer) -> Option<u32>{
+        u32::try_from(Self::read_uint(buf, 4)?).ok()
+    }
+    fn read_u64(buf: &mut ReadBuffer) -> Option<u64>{
+        Self::read_uint(buf, 8)
+    }
+    fn write_len<S: Sink + ?Sized>(len: usize, vec: &mut S){
+        let () = Self::VALID_LEN_BYTES;
+        let len = len as u64;
+        assert!(LEN_BYTES == 8 || len >> (8 * LEN_BYTES) == 0, "length does not fit the prefix");
+        Self::write_uint(len, LEN_BYTES, vec);
+    }
+    fn read_len(buf: &mut ReadBuffer) -> Option<usize>{
+        let () = Self::VALID_LEN_BYTES;
+        usize::try_from(Self::read_uint(buf, LEN_BYTES)?).ok()
+    }
+    fn write_f32<S: Sink + ?Sized>(x: f32, vec: &mut S){
+        Self::write_fixed(u64::from(x.to_bits()), 4, vec);
+    }
+    fn write_f64<S: Sink + ?Sized>(x: f64, vec: &mut S){
+        Self::write_fixed(x.to_bits(), 8, vec);
+    }
+    fn read_f32(buf: &mut ReadBuffer) -> Option<f32>{
+        Option::Some(f32::from_bits(u32::try_from(Self::read_fixed(buf, 4)?).ok()?))
+    }
+    fn read_f64(buf: &mut ReadBuffer) -> Option<f64>{
+        Option::Some(f64::from_bits(Self::read_fixed(buf, 8)?))
+    }
+    fn write_char<S: Sink + ?Sized>(x: char, vec: &mut S){
+        Self::write_u32(x as u32, vec);
+    }
+    fn read_char(buf: &mut ReadBuffer) -> Option<char>{
+        char::from_u32(Self::read_u32(buf)?)
+    }
+}
+
+/// Object that can be written and read in a foreign Format.
+pub trait Compat: Sized{
+    /// Write object in format F.
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S);
+    /// Read object in format F.
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>;
+}
+
+/// Encode x in format F.
+pub fn to_vec<F: Format, T: Compat>(x: &T) -> Buffer{
+    let mut vec = Vec::new();
+    x.write_as::<F, _>(&mut vec);
+    vec
+}
+/// Decode a T in format F, None if it fails or bytes are left over.
+pub fn from_slice<F: Format, T: Compat>(bytes: &[u8]) -> Option<T>{
+    let mut buf = ReadBuffer::from_raw(bytes.to_vec());
+    let x = T::read_as::<F>(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    Option::Some(x)
+}
+
+// Read a length and check it against the limits of the buffer,
+// counting item_size bytes of memory for every element.
+fn read_len<F: Format>(buf: &mut ReadBuffer, item_size: usize) -> Option<usize>{
+    let len = F::read_len(buf)?;
+    if len > buf.limits().max_elements{
+        return Option::None;
+    }
+    buf.use_bytes(len.checked_mul(item_size)?)?;
+    Option::Some(len)
+}
+
+impl Compat for u8{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(&[*self]);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some(buf.read_bytes(1)?[0])
+    }
+}
+
+impl Compat for u16{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_u16(*self, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        F::read_u16(buf)
+    }
+}
+
+impl Compat for u32{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_u32(*self, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        F::read_u32(buf)
+    }
+}
+
+impl Compat for u64{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_u64(*self, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        F::read_u64(buf)
+    }
+}
+/// Written as a u64, like both formats do.
+impl Compat for usize{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_u64(*self as u64, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        usize::try_from(F::read_u64(buf)?).ok()
+    }
+}
+/// Written as a u64 or zigzag encoded like an i64.
+impl Compat for isize{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self as i64).write_as::<F, _>(vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        isize::try_from(i64::read_as::<F>(buf)?).ok()
+    }
+}
+
+impl Compat for i8{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(&[*self as u8]);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some(buf.read_bytes(1)?[0] as i8)
+    }
+}
+/// Implements Compat for a signed integer, written as the unsigned $u or zigzag encoded.
+macro_rules! signed_compat_impl{
+    ($t:ty, $u:ty, $write:ident, $read:ident) => {
+        impl Compat for $t{
+            fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+                if F::ZIGZAG{
+                    F::write_u64(varint::zigzag(i64::from(*self)), vec);
+                } else {
+                    F::$write(*self as $u, vec);
+                }
+            }
+            fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+                if F::ZIGZAG{
+                    <$t>::try_from(varint::unzigzag(F::read_u64(buf)?)).ok()
+                } else {
+                    F::$read(buf).map(|x| x as $t)
+                }
+            }
+        }
+    };
+}
+signed_compat_impl!(i16, u16, write_u16, read_u16);
+signed_compat_impl!(i32, u32, write_u32, read_u32);
+signed_compat_impl!(i64, u64, write_u64, read_u64);
+/// One byte, 0 or 1, in every format.
+impl Compat for bool{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(&[*self as u8]);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        match buf.read_bytes(1)?[0]{
+            0 => Option::Some(false),
+            1 => Option::Some(true),
+            _ => Option::None,
+        }
+    }
+}
+
+impl Compat for char{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_char(*self, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        F::read_char(buf)
+    }
+}
+/// Floats are little endian in both formats, Config uses its byte order.
+impl Compat for f32{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_f32(*self, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        F::read_f32(buf)
+    }
+}
+
+impl Compat for f64{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_f64(*self, vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        F::read_f64(buf)
+    }
+}
+
+impl Compat for String{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_len(self.len(), vec);
+        vec.write_bytes(self.as_bytes());
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        let len = F::read_len(buf)?;
+        if len > buf.limits().max_string_len{
+            return Option::None;
+        }
+        buf.use_bytes(len)?;
+        let bytes = buf.read_bytes(len)?;
+        std::str::from_utf8(bytes).ok().map(str::to_owned)
+    }
+}
+/// Like a Vec<u8>, or a serde_bytes byte buffer, which have the same layout.
+impl Compat for ByteVec{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_len(self.len(), vec);
+        vec.write_bytes(self);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        let len = read_len::<F>(buf, 1)?;
+        Option::Some(ByteVec(buf.read_bytes(len)?.to_vec()))
+    }
+}
+
+impl<T: Compat> Compat for Vec<T>{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        F::write_len(self.len(), vec);
+        for x in self{
+            x.write_as::<F, _>(vec);
+        }
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let len = read_len::<F>(buf, std::mem::size_of::<T>())?;
+            let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+            for _ in 0..len{
+                vec.push(T::read_as::<F>(buf)?);
+            }
+            Option::Some(vec)
+        })
+    }
+}
+/// Entries are written sorted by their encoded key, like the Bufferable impl.
+impl<K, V, H> Compat for HashMap<K,V,H>
+    where
+        K: Compat + Eq + Hash,
+        V: Compat,
+        H: BuildHasher + Default,
+{
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        let mut entries: Vec<(Buffer, &V)> = self.iter().map(|(k, v)| (to_vec::<F, K>(k), v)).collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        F::write_len(self.len(), vec);
+        for (k, v) in entries{
+            vec.write_bytes(&k);
+            v.write_as::<F, _>(vec);
+        }
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let len = read_len::<F>(buf, std::mem::size_of::<(K,V)>())?;
+            let mut map = HashMap::with_capacity_and_hasher(len.min(buf.remaining()), H::default());
+            for _ in 0..len{
+                let k = K::read_as::<F>(buf)?;
+                let v = V::read_as::<F>(buf)?;
+                if map.insert(k, v).is_some(){
+                    return Option::None;
+                }
+            }
+            Option::Some(map)
+        })
+    }
+}
+
+impl<U: Compat, V: Compat> Compat for (U,V){
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        self.0.write_as::<F, _>(vec);
+        self.1.write_as::<F, _>(vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some((U::read_as::<F>(buf)?, V::read_as::<F>(buf)?))
+    }
+}
+
+impl<U: Compat, V: Compat, W: Compat> Compat for (U,V,W){
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        self.0.write_as::<F, _>(vec);
+        self.1.write_as::<F, _>(vec);
+        self.2.write_as::<F, _>(vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some((U::read_as::<F>(buf)?, V::read_as::<F>(buf)?, W::read_as::<F>(buf)?))
+    }
+}
+
+impl<U: Compat, V: Compat, W: Compat, X: Compat> Compat for (U,V,W,X){
+    fn write_as<F: Format, S: Sink + ?Sized>(&self, vec: &mut S){
+        self.0.write_as::<F, _>(vec);
+        self.1.write_as::<F, _>(vec);
+        self.2.write_as::<F, _>(vec);
+        self.3.write_as::<F, _>(vec);
+    }
+    fn read_as<F: Format>(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some((U::read_as::<F>(buf)?, V::read_as::<F>(buf)?, W::read_as::<F>(buf)?, X::read_as::<F>(buf)?))
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::compat::*;
+
+    #[test]
+    fn test_bincode(){
+        // bytes from bincode 1.3 serialize
+        let x = (258u16, 0.5f32, vec![1u64, 2], String::from("ab"));
+        let bytes = vec![2,1, 0,0,0,63, 2,0,0,0,0,0,0,0, 1,0,0,0,0,0,0,0, 2,0,0,0,0,0,0,0,
+            2,0,0,0,0,0,0,0, 97,98];
+        assert_eq!(bytes, to_vec::<Bincode, _>(&x));
+        assert_eq!(Some(x), from_slice::<Bincode, _>(&bytes));
+        assert_eq!(None, from_slice::<Bincode, (u16,f32)>(&bytes));
+        let x = ((-1i8, -2i16, -3i32, -4i64), (true, 'ß', 'a', -5isize));
+        let bytes = vec![0xff, 0xfe,0xff, 0xfd,0xff,0xff,0xff, 0xfc,0xff,0xff,0xff,0xff,0xff,0xff,0xff,
+            1, 0xc3,0x9f, b'a', 0xfb,0xff,0xff,0xff,0xff,0xff,0xff,0xff];
+        assert_eq!(bytes, to_vec::<Bincode, _>(&x));
+        assert_eq!(Some(x), from_slice::<Bincode, _>(&bytes));
+        assert_eq!(None, from_slice::<Bincode, bool>(&[2]));
+        assert_eq!(None, from_slice::<Bincode, char>(&[0xc3]));
+        assert_eq!(None, from_slice::<Bincode, char>(&[0x9f]));
+        assert_eq!(Some('😀'), from_slice::<Bincode, char>(&to_vec::<Bincode, _>(&'😀')));
+    }
+
+    #[test]
+    fn test_config(){
+        let x = (258u16, 0.5f32, vec![1u64, 2], String::from("ab"));
+        assert_eq!(encode(&x), to_vec::<Native, _>(&x));
+        assert_eq!(to_vec::<Bincode, _>(&x), to_vec::<Config<false, 8, false>, _>(&x));
+        let bytes = to_vec::<Config<true, 1, false>, _>(&x);
+        assert_eq!(vec![1,2, 63,0,0,0, 2, 0,0,0,0,0,0,0,1, 0,0,0,0,0,0,0,2, 2, 97,98], bytes);
+        assert_eq!(Some(x.clone()), from_slice::<Config<true, 1, false>, _>(&bytes));
+        // varints for integers and lengths, floats keep the byte order
+        let bytes = to_vec::<Config<true, 4, true>, _>(&x);
+        assert_eq!(vec![130,2, 63,0,0,0, 2, 1, 2, 2, 97,98], bytes);
+        assert_eq!(Some(x), from_slice::<Config<true, 4, true>, _>(&bytes));
+        assert!(std::panic::catch_unwind(|| to_vec::<Config<true, 1, false>, _>(&vec![0u8; 256])).is_err());
+        // every primitive has the Bufferable layout in Native
+        let x = ((i8::MIN, i16::MIN, -70000i32, i64::MIN), (false, 'ß', u64::MAX, -2isize));
+        assert_eq!(encode(&x), to_vec::<Native, _>(&x));
+        assert_eq!(Some(x), from_slice::<Native, _>(&encode(&x)));
+        // with varints signed integers are zigzag encoded and chars are a varint too
+        let x = (-2i16, 300i32, 'ß');
+        assert_eq!(vec![3, 0xd8,4, 0xdf,1], to_vec::<Config<true, 8, true>, _>(&x));
+        assert_eq!(Some(x), from_slice::<Config<true, 8, true>, _>(&[3, 0xd8,4, 0xdf,1]));
+    }
+
+    #[test]
+    fn test_postcard(){
+        // bytes from postcard 1 to_allocvec
+        let x = (300u32, 1.0f64, vec![ByteVec(vec![9]), ByteVec(vec![])], u64::MAX);
+        let bytes = vec![172,2, 0,0,0,0,0,0,240,63, 2, 1,9, 0,
+            255,255,255,255,255,255,255,255,255,1];
+        assert_eq!(bytes, to_vec::<Postcard, _>(&x));
+        assert_eq!(Some(x), from_slice::<Postcard, _>(&bytes));
+        // 70000 does not fit a u16
+        assert_eq!(None, from_slice::<Postcard, u16>(&to_vec::<Postcard, _>(&70000u32)));
+        let mut map = std::collections::HashMap::new();
+        map.insert(String::from("b"), 2u8);
+        map.insert(String::from("a"), 1u8);
+        let bytes = to_vec::<Postcard, _>(&map);
+        assert_eq!(vec![2, 1,b'a',1, 1,b'b',2], bytes);
+        assert_eq!(Some(map), from_slice::<Postcard, _>(&bytes));
+        // signed integers zigzag encoded, chars as a string
+        let x = ((-1i8, -2i16, 300i32, i64::MIN), (true, 'ß'));
+        let bytes = vec![0xff, 3, 0xd8,4, 255,255,255,255,255,255,255,255,255,1, 1, 2,0xc3,0x9f];
+        assert_eq!(bytes, to_vec::<Postcard, _>(&x));
+        assert_eq!(Some(x), from_slice::<Postcard, _>(&bytes));
+        assert_eq!(None, from_slice::<Postcard, i16>(&to_vec::<Postcard, _>(&70000i32)));
+        assert_eq!(None, from_slice::<Postcard, char>(&[2, b'a', b'b']));
+    }
+}
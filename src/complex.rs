@@ -0,0 +1,100 @@
+//! Bufferable for num_complex::Complex, enabled with the `num-complex` feature.
+use crate::{ Bufferable, BufferError, ReadBuffer, Sink };
+use num_complex::Complex;
+
+/// Implements Bufferable for Complex<T>, written as re then im.
+/// Vectors of them go through the bulk path of T.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use num_complex::Complex;
+/// let x = vec![Complex::new(1.0f32, -1.0), Complex::new(0.5, 2.0)];
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// assert_eq!(8 + 16, buffer.len());
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(x), Vec::<Complex<f32>>::from_buffer(&mut buffer));
+/// ```
+impl<T: Bufferable + Clone> Bufferable for Complex<T>{
+    const MIN_BUFFER_LEN: usize = 2 * T::MIN_BUFFER_LEN;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.re.into_buffer(vec);
+        self.im.into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.re.copy_into_buffer(vec);
+        self.im.copy_into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        // Complex is repr(C) with two fields of the same type, so a slice of them
+        // is a slice of twice as many T: re, im, re, im...
+        let parts = unsafe{
+            std::slice::from_raw_parts(slice.as_ptr() as *const T, slice.len() * 2)
+        };
+        T::copy_slice_into_buffer(parts, vec);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        let parts = T::vec_from_buffer(len.checked_mul(2)?, buf)?;
+        Option::Some(parts.chunks_exact(2).map(|p| Complex::new(p[0].clone(), p[1].clone())).collect())
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let re = T::from_buffer(buf)?;
+        let im = T::from_buffer(buf)?;
+        Option::Some(Complex::new(re, im))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        T::validate(buf)?;
+        T::validate(buf)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        T::validate_slice(len.checked_mul(2).ok_or(BufferError::LengthOverflow)?, buf)
+    }
+
+    fn buffer_len(&self) -> usize{
+        self.re.buffer_len() + self.im.buffer_len()
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        let parts = unsafe{
+            std::slice::from_raw_parts(slice.as_ptr() as *const T, slice.len() * 2)
+        };
+        T::slice_buffer_len(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use num_complex::Complex;
+
+    #[test]
+    fn test_complex(){
+        let x = Complex::new(1.5f64, -2.0);
+        let mut buffer = Vec::new();
+        x.into_buffer(&mut buffer);
+        let mut expected = Vec::new();
+        (1.5f64, -2.0f64).into_buffer(&mut expected);
+        assert_eq!(expected, buffer);
+        let samples: Vec<Complex<f32>> = (0..1000).map(|i| Complex::new(i as f32, -(i as f32) / 2.0)).collect();
+        let mut buffer = Vec::new();
+        samples.copy_into_buffer(&mut buffer);
+        assert_eq!(samples.buffer_len(), buffer.len());
+        // the same bytes element by element
+        let mut slow = Vec::new();
+        (samples.len() as u64).into_buffer(&mut slow);
+        for s in &samples{
+            s.copy_into_buffer(&mut slow);
+        }
+        assert_eq!(slow, buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(samples), Vec::<Complex<f32>>::from_buffer(&mut buffer));
+        assert_eq!(None, Complex::<f64>::from_buffer(&mut ReadBuffer::from_raw(vec![0; 12])));
+    }
+}
@@ -0,0 +1,271 @@
+//! Compression of whole buffers, enabled with the `compression` feature.
+//! For data with long repeats, like world chunks full of the same tiles, where the Huffman
+//! coding of entropy does not get far because it only looks at single bytes.
+//! The compressed form says how it was made: the Algorithm (u8), the length of the
+//! uncompressed bytes (u64) and then the compressed bytes, so decompress_buffer needs
+//! nothing else to undo it. Lz4 is the LZ4 block format, Stored keeps the bytes as they are
+//! and is what compress_buffer falls back to when LZ4 does not make them smaller.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::compress::*;
+//! let tiles = vec![3u16; 4096];
+//! let bytes = compress_buffer(&encode(&tiles));
+//! assert!(bytes.len() < 100);
+//! assert_eq!(Some(Algorithm::Lz4), algorithm_of(&bytes));
+//! assert_eq!(Some(tiles), decode(&decompress_buffer(&bytes).unwrap()));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+
+/// How bytes were compressed, the first byte of the compressed form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm{
+    /// Not compressed.
+    Stored = 0,
+    /// The LZ4 block format.
+    Lz4 = 1,
+}
+
+impl TryFrom<u8> for Algorithm{
+    type Error = u8;
+
+    fn try_from(id: u8) -> Result<Self, u8>{
+        match id{
+            0 => Ok(Algorithm::Stored),
+            1 => Ok(Algorithm::Lz4),
+            _ => Err(id),
+        }
+    }
+}
+
+/// Bytes in the compressed form before the compressed bytes.
+pub const HEADER_LEN: usize = 9;
+
+const MIN_MATCH: usize = 4;
+// A match has to start at least this far from the end, and the last bytes are always literals.
+const MATCH_START_LIMIT: usize = 12;
+const LAST_LITERALS: usize = 5;
+const HASH_BITS: u32 = 12;
+
+fn read_u32(bytes: &[u8], at: usize) -> u32{
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn hash(seq: u32) -> usize{
+    (seq.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+// The part of a length that does not fit in the 4 bits of the token.
+fn write_len_rest(out: &mut Buffer, mut len: usize){
+    while len >= 255{
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+fn read_len_rest(block: &[u8], i: &mut usize) -> Option<usize>{
+    let mut len = 0usize;
+    loop{
+        let b = *block.get(*i)?;
+        *i += 1;
+        len = len.checked_add(b as usize)?;
+        if b != 255{
+            return Option::Some(len);
+        }
+    }
+}
+
+// A sequence of literals and, when there is one, a match of len bytes offset bytes back.
+fn write_sequence(out: &mut Buffer, literals: &[u8], m: Option<(usize, usize)>){
+    let lit = literals.len().min(15) as u8;
+    let ml = m.map_or(0, |(_, len)| (len - MIN_MATCH).min(15) as u8);
+    out.push(lit << 4 | ml);
+    if literals.len() >= 15{
+        write_len_rest(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Option::Some((offset, len)) = m{
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if len - MIN_MATCH >= 15{
+            write_len_rest(out, len - MIN_MATCH - 15);
+        }
+    }
+}
+
+/// Compress bytes to an LZ4 block, without the header of compress_buffer.
+pub fn lz4_compress(bytes: &[u8]) -> Buffer{
+    let mut out = Vec::with_capacity(bytes.len() / 2 + 16);
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut i = 0;
+    if bytes.len() > MATCH_START_LIMIT{
+        let limit = bytes.len() - MATCH_START_LIMIT;
+        let end_limit = bytes.len() - LAST_LITERALS;
+        while i < limit{
+            let seq = read_u32(bytes, i);
+            let h = hash(seq);
+            // positions are kept one up, so 0 is an empty slot
+            let candidate = table[h];
+            table[h] = i + 1;
+            if candidate > 0 && i - (candidate - 1) <= 0xffff && read_u32(bytes, candidate - 1) == seq{
+                let start = candidate - 1;
+                let mut len = MIN_MATCH;
+                while i + len < end_limit && bytes[start + len] == bytes[i + len]{
+                    len += 1;
+                }
+                write_sequence(&mut out, &bytes[anchor..i], Option::Some((i - start, len)));
+                i += len;
+                anchor = i;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    write_sequence(&mut out, &bytes[anchor..], Option::None);
+    out
+}
+
+/// Decompress an LZ4 block that holds exactly len bytes.
+/// None if the block is broken or does not hold len bytes.
+pub fn lz4_decompress(block: &[u8], len: usize) -> Option<Buffer>{
+    // a block can not grow more than 255 times, so a lying len does not allocate much
+    let mut out = Vec::with_capacity(len.min(block.len().saturating_mul(255)));
+    let mut i = 0;
+    loop{
+        let token = *block.get(i)?;
+        i += 1;
+        let mut lit = (token >> 4) as usize;
+        if lit == 15{
+            lit += read_len_rest(block, &mut i)?;
+        }
+        let literals = block.get(i..i.checked_add(lit)?)?;
+        if out.len() + lit > len{
+            return Option::None;
+        }
+        out.extend_from_slice(literals);
+        i += lit;
+        if i == block.len(){
+            break;
+        }
+        let offset = u16::from_le_bytes([*block.get(i)?, *block.get(i + 1)?]) as usize;
+        i += 2;
+        if offset == 0 || offset > out.len(){
+            return Option::None;
+        }
+        let mut mlen = (token & 15) as usize + MIN_MATCH;
+        if token & 15 == 15{
+            mlen += read_len_rest(block, &mut i)?;
+        }
+        if out.len() + mlen > len{
+            return Option::None;
+        }
+        let start = out.len() - offset;
+        if offset >= mlen{
+            out.extend_from_within(start..start + mlen);
+        } else {
+            // the match overlaps what it writes, like a run of one byte
+            for k in start..start + mlen{
+                out.push(out[k]);
+            }
+        }
+    }
+    if out.len() != len{
+        return Option::None;
+    }
+    Option::Some(out)
+}
+
+/// Compress bytes with algorithm, behind the header that says how.
+pub fn compress_buffer_with(bytes: &[u8], algorithm: Algorithm) -> Buffer{
+    let body = match algorithm{
+        Algorithm::Stored => bytes.to_vec(),
+        Algorithm::Lz4 => lz4_compress(bytes),
+    };
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len());
+    (algorithm as u8).into_buffer(&mut out);
+    (bytes.len() as u64).into_buffer(&mut out);
+    out.extend_from_slice(&body);
+    out
+}
+
+/// Compress bytes with LZ4, or store them if that does not make them smaller.
+pub fn compress_buffer(bytes: &[u8]) -> Buffer{
+    let lz4 = compress_buffer_with(bytes, Algorithm::Lz4);
+    if lz4.len() < HEADER_LEN + bytes.len(){
+        lz4
+    } else {
+        compress_buffer_with(bytes, Algorithm::Stored)
+    }
+}
+
+/// The algorithm the header of compressed bytes names, None if it names none known.
+pub fn algorithm_of(bytes: &[u8]) -> Option<Algorithm>{
+    Algorithm::try_from(*bytes.first()?).ok()
+}
+
+/// Undo compress_buffer or compress_buffer_with, using the algorithm the header names.
+/// None if the algorithm is not known or the bytes are broken.
+pub fn decompress_buffer(bytes: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::from_slice(bytes);
+    let algorithm = Algorithm::try_from(u8::from_buffer(&mut buf)?).ok()?;
+    let len = usize::try_from(u64::from_buffer(&mut buf)?).ok()?;
+    let body = &bytes[HEADER_LEN..];
+    match algorithm{
+        Algorithm::Stored => Option::Some(body.to_vec()).filter(|b| b.len() == len),
+        Algorithm::Lz4 => lz4_decompress(body, len),
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::compress::*;
+
+    #[test]
+    fn test_compress(){
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            b"short".to_vec(),
+            vec![7; 100000],
+            (0..5000u32).flat_map(|i| (i % 97).to_be_bytes()).collect(),
+            encode(&vec![String::from("grass"); 300]),
+            // does not compress
+            (0..3000).scan(0x2545f491u32, |x, _|{
+                *x ^= *x << 13;
+                *x ^= *x >> 17;
+                *x ^= *x << 5;
+                Some((*x >> 24) as u8)
+            }).collect(),
+        ];
+        for bytes in &inputs{
+            let block = lz4_compress(bytes);
+            assert_eq!(Some(bytes.clone()), lz4_decompress(&block, bytes.len()));
+            let packed = compress_buffer(bytes);
+            assert_eq!(Some(bytes.clone()), decompress_buffer(&packed));
+            assert!(packed.len() <= HEADER_LEN + bytes.len());
+            let stored = compress_buffer_with(bytes, Algorithm::Stored);
+            assert_eq!(Some(Algorithm::Stored), algorithm_of(&stored));
+            assert_eq!(Some(bytes.clone()), decompress_buffer(&stored));
+        }
+        assert!(compress_buffer(&inputs[2]).len() < 500);
+        assert_eq!(Some(Algorithm::Stored), algorithm_of(&compress_buffer(&inputs[5])));
+        // a block made by hand: literal 'a', then 9 more from 1 back, then 5 literals
+        let block = [0x15, b'a', 1, 0, 0x50, b'b', b'c', b'd', b'e', b'f'];
+        assert_eq!(Some(b"aaaaaaaaaabcdef".to_vec()), lz4_decompress(&block, 15));
+        assert_eq!(None, lz4_decompress(&block, 14));
+        assert_eq!(None, lz4_decompress(&block, 16));
+        assert_eq!(None, lz4_decompress(&[0x10, b'a', 2, 0, 0x00], 5));
+        assert_eq!(None, lz4_decompress(&block[..8], 15));
+        // unknown algorithm, cut off header and a wrong length
+        let mut packed = compress_buffer(&inputs[3]);
+        assert_eq!(None, algorithm_of(&[9]));
+        assert_eq!(None, decompress_buffer(&packed[..5]));
+        packed[0] = 9;
+        assert_eq!(None, decompress_buffer(&packed));
+        let mut stored = compress_buffer_with(b"abc", Algorithm::Stored);
+        stored.pop();
+        assert_eq!(None, decompress_buffer(&stored));
+    }
+}
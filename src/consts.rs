@@ -0,0 +1,133 @@
+//! Encoding fixed data at compile time, for protocol preambles and assets that are baked
+//! into the program. ConstBuffer has const fns for the numbers, strings and byte strings,
+//! writing the same bytes as their Bufferable impls. A tuple is its items one after the other.
+//! const_buffer! works out the length first and gives a &'static [u8].
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::consts::*;
+//! static HELLO: &[u8] = const_buffer![u16(1), str("hi"), bytes(b"\x01\x02"), f32(0.5)];
+//! let mut buffer = Vec::new();
+//! (1u16, String::from("hi")).into_buffer(&mut buffer);
+//! ByteVec(vec![1, 2]).into_buffer(&mut buffer);
+//! 0.5f32.into_buffer(&mut buffer);
+//! assert_eq!(&buffer[..], HELLO);
+//! const MAGIC: [u8; 6] = ConstBuffer::<6>::new().raw(b"BB").u32(7).finish();
+//! assert_eq!([b'B', b'B', 0, 0, 0, 7], MAGIC);
+//! ```
+
+/// Encoder usable in const context, writing into an array of N bytes.
+/// Writes past the end are dropped but still counted, so the same calls on a
+/// ConstBuffer<0> tell how big N has to be.
+#[derive(Clone, Copy, Debug)]
+pub struct ConstBuffer<const N: usize>{
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ConstBuffer<N>{
+    /// An empty buffer.
+    pub const fn new() -> Self{
+        Self{ bytes: [0; N], len: 0 }
+    }
+    /// Bytes written so far, including those that did not fit.
+    pub const fn len(&self) -> usize{
+        self.len
+    }
+    /// Whether nothing was written.
+    pub const fn is_empty(&self) -> bool{
+        self.len == 0
+    }
+    /// Write bytes as they are, without a length.
+    pub const fn raw(mut self, bytes: &[u8]) -> Self{
+        let mut i = 0;
+        while i < bytes.len(){
+            if self.len < N{
+                self.bytes[self.len] = bytes[i];
+            }
+            self.len += 1;
+            i += 1;
+        }
+        self
+    }
+    /// Write a u8.
+    pub const fn u8(self, x: u8) -> Self{
+        self.raw(&[x])
+    }
+    /// Write a u16.
+    pub const fn u16(self, x: u16) -> Self{
+        self.raw(&x.to_be_bytes())
+    }
+    /// Write a u32.
+    pub const fn u32(self, x: u32) -> Self{
+        self.raw(&x.to_be_bytes())
+    }
+    /// Write a u64.
+    pub const fn u64(self, x: u64) -> Self{
+        self.raw(&x.to_be_bytes())
+    }
+    /// Write a usize, as a u64 like its Bufferable impl.
+    pub const fn usize(self, x: usize) -> Self{
+        self.u64(x as u64)
+    }
+    /// Write a f32.
+    pub const fn f32(self, x: f32) -> Self{
+        self.raw(&x.to_be_bytes())
+    }
+    /// Write a f64.
+    pub const fn f64(self, x: f64) -> Self{
+        self.raw(&x.to_be_bytes())
+    }
+    /// Write bytes with their length in front, like a ByteVec or Vec<u8>.
+    pub const fn bytes(self, bytes: &[u8]) -> Self{
+        self.u64(bytes.len() as u64).raw(bytes)
+    }
+    /// Write a string, like a String.
+    pub const fn str(self, string: &str) -> Self{
+        self.bytes(string.as_bytes())
+    }
+    /// The array. Fails, at compile time in a const, if not exactly N bytes were written.
+    pub const fn finish(self) -> [u8; N]{
+        assert!(self.len == N, "ConstBuffer: written length is not N");
+        self.bytes
+    }
+}
+
+impl<const N: usize> Default for ConstBuffer<N>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+/// Encode values at compile time into a &'static [u8], see the consts module.
+/// Every item is a ConstBuffer method with its argument, like `u32(7)` or `str("hi")`.
+#[macro_export]
+macro_rules! const_buffer{
+    ($( $kind:ident ( $value:expr ) ),* $(,)?) => {{
+        const LEN: usize = $crate::consts::ConstBuffer::<0>::new() $( .$kind($value) )* .len();
+        const BYTES: [u8; LEN] = $crate::consts::ConstBuffer::<LEN>::new() $( .$kind($value) )* .finish();
+        &BYTES
+    }};
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::consts::*;
+
+    const PREAMBLE: &[u8] = const_buffer![u8(0xb1), u64(u64::MAX), usize(3), f64(-1.5), str(""), raw(b"end")];
+
+    #[test]
+    fn test_consts(){
+        let mut buffer = Vec::new();
+        (0xb1u8, u64::MAX, 3usize).into_buffer(&mut buffer);
+        (-1.5f64, String::new()).into_buffer(&mut buffer);
+        buffer.extend_from_slice(b"end");
+        assert_eq!(&buffer[..], PREAMBLE);
+        let empty: &'static [u8] = const_buffer![];
+        assert!(empty.is_empty());
+        let measured = ConstBuffer::<2>::new().u32(1);
+        assert_eq!(4, measured.len());
+        assert!(std::panic::catch_unwind(|| measured.finish()).is_err());
+    }
+}
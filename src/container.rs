@@ -0,0 +1,269 @@
+//! Buffers and files that say what they are: a magic number and a format version in front
+//! of the payload, so old or foreign files are caught before their bytes are decoded and
+//! callers can branch on the version to read older layouts.
+//! The header is the magic (4 bytes) and the version (u32), 8 bytes in all.
+//! Sections inside a payload have their length in front, so a reader that does not know
+//! a section, or only the fields at its start, skips the rest of it. New fields can then be
+//! added at the end of a section without breaking older readers.
+//! A section is written to any Sink: it is gathered in a WriteBuffer, with
+//! WriteBuffer::reserve_len_prefix and finalize, and goes to the Sink when it ends.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::container::*;
+//! const MAGIC: [u8; 4] = *b"SAVE";
+//! let mut bytes = Vec::new();
+//! Header{ magic: MAGIC, version: 2 }.into_buffer(&mut bytes);
+//! let mut section = begin_section(&mut bytes);
+//! 3u32.into_buffer(&mut section);
+//! String::from("added in version 2").into_buffer(&mut section);
+//! end_section(section);
+//! 9u8.into_buffer(&mut bytes);
+//! let (version, mut buf) = decode_header(&bytes, MAGIC).unwrap();
+//! assert_eq!(2, version);
+//! // a reader that only knows the first field of the section
+//! let mut section = read_section(&mut buf).unwrap();
+//! assert_eq!(Some(3), u32::from_buffer(&mut section));
+//! assert_eq!(Some(9), u8::from_buffer(&mut buf));
+//! assert_eq!(Err(HeaderError::Magic(MAGIC)), decode_header(&bytes, *b"CONF").map(|(v, _)| v));
+//! ```
+use std::io;
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, Endian, LenPrefix, ReadBuffer, Sink, WriteBuffer };
+
+/// Bytes a header takes.
+pub const HEADER_LEN: usize = 8;
+
+/// What a buffer or file holds: the magic number of its format and the version of that format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Header{
+    /// Tells the format apart from anything else.
+    pub magic: [u8; 4],
+    /// Version of the layout of the payload.
+    pub version: u32,
+}
+
+impl Bufferable for Header{
+    const MIN_BUFFER_LEN: usize = HEADER_LEN;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(&self.magic);
+        self.version.into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let magic = <[u8; 4]>::try_from(buf.read_bytes(4)?).ok()?;
+        Option::Some(Self{ magic, version: u32::from_buffer(buf)? })
+    }
+
+    fn buffer_len(&self) -> usize{
+        HEADER_LEN
+    }
+}
+
+/// Why a header does not check out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderError{
+    /// There are fewer bytes than a header takes.
+    TooShort,
+    /// The bytes start with this instead of the expected magic, so they are of another format.
+    Magic([u8; 4]),
+}
+
+impl std::fmt::Display for HeaderError{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            Self::TooShort => write!(f, "too short for a header"),
+            Self::Magic(magic) => write!(f, "not the expected format, got magic {:?}", magic),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError{}
+
+/// A short file ends too soon, another magic is invalid data.
+impl From<HeaderError> for io::Error{
+    fn from(e: HeaderError) -> Self{
+        let kind = match e{
+            HeaderError::TooShort => io::ErrorKind::UnexpectedEof,
+            HeaderError::Magic(_) => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// A Buffer with the header for magic and version and then payload.
+pub fn encode_versioned<T: Bufferable>(magic: [u8; 4], version: u32, payload: &T) -> Buffer{
+    let mut buffer = Vec::with_capacity(HEADER_LEN + payload.buffer_len());
+    Header{ magic, version }.into_buffer(&mut buffer);
+    payload.copy_into_buffer(&mut buffer);
+    buffer
+}
+
+/// Check that bytes start with a header for magic. Gives its version and a ReadBuffer
+/// over the payload after it, for reading the layout of that version.
+pub fn decode_header(bytes: &[u8], magic: [u8; 4]) -> Result<(u32, ReadBuffer<'_>), HeaderError>{
+    let mut buf = ReadBuffer::from_slice(bytes);
+    let header = Header::from_buffer(&mut buf).ok_or(HeaderError::TooShort)?;
+    if header.magic != magic{
+        return Err(HeaderError::Magic(header.magic));
+    }
+    Ok((header.version, buf))
+}
+
+/// Write the header for magic and version and then payload to the file at path,
+/// replacing what was there.
+#[cfg(feature = "fs")]
+pub fn write_versioned<T: Bufferable>(path: &std::path::Path, magic: [u8; 4], version: u32, payload: &T) -> io::Result<()>{
+    std::fs::write(path, encode_versioned(magic, version, payload))
+}
+
+/// Read the file at path and check its header against magic. Gives the version and a
+/// ReadBuffer positioned at the payload, so the caller can decode the layout of that version.
+/// Fails with InvalidData for a file of another format and with UnexpectedEof for one
+/// too short to have a header.
+#[cfg(feature = "fs")]
+pub fn read_versioned(path: &std::path::Path, magic: [u8; 4]) -> io::Result<(u32, ReadBuffer<'static>)>{
+    let bytes = std::fs::read(path)?;
+    let (version, _) = decode_header(&bytes, magic)?;
+    let mut buf = ReadBuffer::from_raw(bytes);
+    buf.skip(HEADER_LEN).map_err(|e| io::Error::new(io::ErrorKind::UnexpectedEof, e))?;
+    Ok((version, buf))
+}
+
+/// A section being written, made by begin_section. It is a Sink: everything written to it
+/// is in the section, in the byte order of the Sink it goes to.
+#[must_use = "the section is only written by end_section"]
+pub struct Section<'a, S: Sink + ?Sized>{
+    vec: &'a mut S,
+    buffer: WriteBuffer,
+    len: LenPrefix,
+}
+
+impl<S: Sink + ?Sized> Sink for Section<'_, S>{
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.buffer.write_bytes(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize){
+        self.buffer.reserve(additional);
+    }
+
+    fn endian(&self) -> Endian{
+        self.vec.endian()
+    }
+}
+
+/// Start a section that goes to vec.
+pub fn begin_section<S: Sink + ?Sized>(vec: &mut S) -> Section<'_, S>{
+    let mut buffer = WriteBuffer::new();
+    let len = buffer.reserve_len_prefix();
+    Section{ vec, buffer, len }
+}
+
+/// End section by filling in its length and writing it to the Sink it began on.
+pub fn end_section<S: Sink + ?Sized>(section: Section<'_, S>){
+    let Section{ vec, mut buffer, len } = section;
+    // nothing else can clear the buffer, so the placeholder is there
+    let filled = buffer.finalize(len);
+    debug_assert!(filled);
+    let (len, bytes) = buffer.as_slice().split_at(8);
+    match vec.endian(){
+        Endian::Big => vec.write_bytes(len),
+        // finalize writes the length big endian
+        Endian::Little => {
+            let mut len = <[u8; 8]>::try_from(len).unwrap();
+            len.reverse();
+            vec.write_bytes(&len);
+        },
+    }
+    vec.write_bytes(bytes);
+}
+
+/// Write a section with f, begin_section and end_section around it.
+pub fn write_section<S: Sink + ?Sized, F: FnOnce(&mut Section<'_, S>)>(vec: &mut S, f: F){
+    let mut section = begin_section(vec);
+    f(&mut section);
+    end_section(section);
+}
+
+/// Read a section as a ReadBuffer over only its bytes, and move buf past all of it,
+/// also the part that is not read from the section.
+pub fn read_section<'b>(buf: &'b mut ReadBuffer) -> Option<ReadBuffer<'b>>{
+    let len = buf.read_len()?;
+    buf.take(len)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::container::*;
+
+    #[test]
+    fn test_container(){
+        // version 1 wrote a section with a name, version 2 added a level to it
+        let mut v2 = Vec::new();
+        write_section(&mut v2, |buf|{
+            String::from("hero").into_buffer(buf);
+            7u16.into_buffer(buf);
+        });
+        write_section(&mut v2, |buf| 1.5f32.into_buffer(buf));
+        let bytes = encode_versioned(*b"GAME", 2, &ByteVec(v2.clone()));
+        assert_eq!(b"GAME", &bytes[..4]);
+        assert_eq!(HEADER_LEN + 8 + v2.len(), bytes.len());
+        let (version, mut buf) = decode_header(&bytes, *b"GAME").unwrap();
+        assert_eq!(2, version);
+        assert_eq!(Some(v2.len()), buf.read_len());
+        // an old reader skips what it does not know
+        let mut first = read_section(&mut buf).unwrap();
+        assert_eq!(Some(String::from("hero")), String::from_buffer(&mut first));
+        let mut second = read_section(&mut buf).unwrap();
+        assert_eq!(Some(1.5), f32::from_buffer(&mut second));
+        assert_eq!(0, buf.remaining());
+        assert_eq!(Err(HeaderError::TooShort), decode_header(&bytes[..7], *b"GAME").map(|(v, _)| v));
+        assert_eq!(Err(HeaderError::Magic(*b"GAME")), decode_header(&bytes, *b"SAVE").map(|(v, _)| v));
+        // a section that says it is longer than what is left
+        let mut short = Vec::new();
+        write_section(&mut short, |buf| buf.write_bytes(&[1, 2, 3]));
+        short.pop();
+        assert!(read_section(&mut ReadBuffer::from_raw(short)).is_none());
+        // any Sink, sections in sections, and the byte order of the Sink
+        let mut buffer = WriteBuffer::new();
+        write_section(&mut buffer, |outer|{
+            1u8.into_buffer(outer);
+            write_section(outer, |inner| 2u16.into_buffer(inner));
+        });
+        assert_eq!(&[0,0,0,0,0,0,0,11, 1, 0,0,0,0,0,0,0,2, 0,2], buffer.as_slice());
+        let mut little = EndianSink::new(Vec::new(), Endian::Little);
+        write_section(&mut little, |buf|{
+            1u8.into_buffer(buf);
+            write_section(buf, |buf| 2u16.into_buffer(buf));
+        });
+        let little = little.into_inner();
+        assert_eq!(vec![11,0,0,0,0,0,0,0, 1, 2,0,0,0,0,0,0,0, 2,0], little);
+        let mut buf = ReadBuffer::from_raw(little).with_endian(Endian::Little);
+        let mut outer = read_section(&mut buf).unwrap();
+        assert_eq!(Some(1), u8::from_buffer(&mut outer));
+        let mut inner = read_section(&mut outer).unwrap();
+        assert_eq!(Some(2), u16::from_buffer(&mut inner));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_versioned_file(){
+        let path = std::env::temp_dir().join("bin_buffer_container_test.bin");
+        write_versioned(&path, *b"CONF", 5, &(1u8, String::from("x"))).unwrap();
+        let (version, mut buf) = read_versioned(&path, *b"CONF").unwrap();
+        assert_eq!(5, version);
+        assert_eq!(Some((1u8, String::from("x"))), Bufferable::from_buffer(&mut buf));
+        let err = read_versioned(&path, *b"SAVE").err().unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+        std::fs::write(&path, b"CO").unwrap();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, read_versioned(&path, *b"CONF").err().unwrap().kind());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
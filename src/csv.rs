@@ -0,0 +1,239 @@
+//! Converting a Vec of records to and from CSV, to look at them in a spreadsheet
+//! or to bring CSV data from older tools into buffers.
+//! The records need a description, see the describe module, with fields of the basic types:
+//! the unsigned integers, the floats, String and ByteVec. ByteVec cells are written as hex.
+//! The first line holds the field names. Reading matches the columns to the fields by name,
+//! so they can be in any order, and columns that are not a field are left out.
+//! Cells with a comma, a quote or a line break are quoted, with quotes doubled.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::describe::*;
+//! use bin_buffer::csv::*;
+//! #[derive(Debug, PartialEq)]
+//! struct Row{ id: u32, name: String }
+//! impl Describe for Row{
+//!     const DESCRIPTOR: TypeDescriptor = TypeDescriptor{
+//!         name: "Row",
+//!         kind: Kind::Struct(&[
+//!             FieldDescriptor{ name: "id", type_name: "u32", offset: Some(0), len: Some(4) },
+//!             FieldDescriptor{ name: "name", type_name: "String", offset: Some(4), len: None },
+//!         ]),
+//!     };
+//! }
+//! impl Bufferable for Row{
+//!     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.copy_into_buffer(vec); }
+//!     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+//!         self.id.into_buffer(vec);
+//!         self.name.copy_into_buffer(vec);
+//!     }
+//!     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+//!         Some(Row{ id: u32::from_buffer(buf)?, name: String::from_buffer(buf)? })
+//!     }
+//! }
+//! let rows = vec![Row{ id: 1, name: String::from("a, b") }, Row{ id: 2, name: String::from("c") }];
+//! let text = to_csv(&rows).unwrap();
+//! assert_eq!("id,name\n1,\"a, b\"\n2,c\n", text);
+//! assert_eq!(Some(rows), from_csv::<Row>(&text));
+//! ```
+use crate::{ Bufferable, ReadBuffer };
+use crate::describe::{ Describe, FieldDescriptor };
+use crate::schema::{ Schema, Value };
+
+// The schema of a field that fits in one cell.
+fn cell_schema(field: &FieldDescriptor) -> Option<Schema>{
+    Option::Some(match field.type_name{
+        "u8" => Schema::U8,
+        "u16" => Schema::U16,
+        "u32" => Schema::U32,
+        "u64" | "usize" => Schema::U64,
+        "f32" | "CanonicalF32" => Schema::F32,
+        "f64" | "CanonicalF64" => Schema::F64,
+        "String" | "LossyString" => Schema::Str,
+        "ByteVec" => Schema::Bytes,
+        _ => return Option::None,
+    })
+}
+
+fn schemas<T: Describe>() -> Option<Vec<Schema>>{
+    T::DESCRIPTOR.fields().iter().map(cell_schema).collect()
+}
+
+fn write_cell(text: &str, out: &mut String){
+    if text.contains([',', '"', '\n', '\r']){
+        out.push('"');
+        out.push_str(&text.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(text);
+    }
+}
+
+fn format_value(schema: &Schema, value: &Value) -> Option<String>{
+    Option::Some(match (schema, value){
+        (Schema::F32, Value::Float(x)) => (*x as f32).to_string(),
+        (_, Value::UInt(x)) => x.to_string(),
+        (_, Value::Float(x)) => x.to_string(),
+        (_, Value::Str(x)) => x.clone(),
+        (_, Value::Bytes(x)) => x.iter().map(|b| format!("{:02x}", b)).collect(),
+        _ => return Option::None,
+    })
+}
+
+fn parse_value(schema: &Schema, cell: &str) -> Option<Value>{
+    Option::Some(match schema{
+        Schema::U8 | Schema::U16 | Schema::U32 | Schema::U64 => Value::UInt(cell.trim().parse().ok()?),
+        Schema::F32 | Schema::F64 => Value::Float(cell.trim().parse().ok()?),
+        Schema::Str => Value::Str(cell.to_string()),
+        Schema::Bytes => {
+            let cell = cell.trim();
+            if !cell.len().is_multiple_of(2) || !cell.is_ascii() { return Option::None; }
+            Value::Bytes((0..cell.len()).step_by(2)
+                .map(|i| u8::from_str_radix(&cell[i..i + 2], 16).ok())
+                .collect::<Option<Vec<_>>>()?)
+        },
+        _ => return Option::None,
+    })
+}
+
+/// Write records as CSV, a line with the field names and then a line per record.
+/// None if T is not a struct of fields of the basic types, or a record does not read back.
+pub fn to_csv<T: Describe + Bufferable>(records: &[T]) -> Option<String>{
+    let fields = T::DESCRIPTOR.fields();
+    let schemas = schemas::<T>()?;
+    if schemas.is_empty() { return Option::None; }
+    let mut out = String::new();
+    for (i, f) in fields.iter().enumerate(){
+        if i > 0 { out.push(','); }
+        write_cell(f.name, &mut out);
+    }
+    out.push('\n');
+    let mut buffer = Vec::new();
+    for record in records{
+        buffer.clear();
+        record.copy_into_buffer(&mut buffer);
+        let mut buf = ReadBuffer::from_raw(std::mem::take(&mut buffer));
+        for (i, schema) in schemas.iter().enumerate(){
+            if i > 0 { out.push(','); }
+            write_cell(&format_value(schema, &schema.decode(&mut buf)?)?, &mut out);
+        }
+        out.push('\n');
+        buffer = buf.into_raw();
+    }
+    Option::Some(out)
+}
+
+/// Split CSV text into rows of cells, undoing the quoting. Lines may end in "\n" or "\r\n".
+/// None if a quoted cell is not closed.
+pub fn parse_rows(text: &str) -> Option<Vec<Vec<String>>>{
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next(){
+        match c{
+            '"' if quoted => {
+                if chars.peek() == Some(&'"'){
+                    chars.next();
+                    cell.push('"');
+                } else {
+                    quoted = false;
+                }
+            },
+            '"' if cell.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut cell)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {},
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            },
+            c => cell.push(c),
+        }
+    }
+    if quoted { return Option::None; }
+    if !cell.is_empty() || !row.is_empty(){
+        row.push(cell);
+        rows.push(row);
+    }
+    Option::Some(rows)
+}
+
+/// Read records from CSV written by to_csv, or by anything else with a line of field names first.
+/// Empty lines are skipped.
+/// None if a field has no column, a cell does not parse as its type or a record does not decode.
+pub fn from_csv<T: Describe + Bufferable>(text: &str) -> Option<Vec<T>>{
+    let fields = T::DESCRIPTOR.fields();
+    let schemas = schemas::<T>()?;
+    let mut rows = parse_rows(text)?.into_iter();
+    let header = rows.next()?;
+    let columns = fields.iter()
+        .map(|f| header.iter().position(|h| h.trim() == f.name))
+        .collect::<Option<Vec<_>>>()?;
+    let mut records = Vec::new();
+    let mut buffer = Vec::new();
+    for row in rows{
+        if row.len() == 1 && row[0].is_empty() { continue; }
+        buffer.clear();
+        for (schema, &column) in schemas.iter().zip(&columns){
+            let value = parse_value(schema, row.get(column)?)?;
+            if !schema.encode(&value, &mut buffer) { return Option::None; }
+        }
+        let mut buf = ReadBuffer::from_raw(std::mem::take(&mut buffer));
+        records.push(T::from_buffer(&mut buf)?);
+        buffer = buf.into_raw();
+    }
+    Option::Some(records)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::csv::*;
+    use crate::describe::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Reading{ sensor: u16, value: f32, raw: ByteVec }
+
+    impl Describe for Reading{
+        const DESCRIPTOR: TypeDescriptor = TypeDescriptor{
+            name: "Reading",
+            kind: Kind::Struct(&[
+                FieldDescriptor{ name: "sensor", type_name: "u16", offset: Some(0), len: Some(2) },
+                FieldDescriptor{ name: "value", type_name: "f32", offset: Some(2), len: Some(4) },
+                FieldDescriptor{ name: "raw", type_name: "ByteVec", offset: Some(6), len: None },
+            ]),
+        };
+    }
+
+    impl Bufferable for Reading{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.copy_into_buffer(vec); }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+            self.sensor.into_buffer(vec);
+            self.value.into_buffer(vec);
+            self.raw.copy_into_buffer(vec);
+        }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+            Some(Reading{ sensor: u16::from_buffer(buf)?, value: f32::from_buffer(buf)?, raw: ByteVec::from_buffer(buf)? })
+        }
+    }
+
+    #[test]
+    fn test_csv(){
+        let rows = vec![
+            Reading{ sensor: 3, value: 0.1, raw: ByteVec(vec![0xde, 0xad]) },
+            Reading{ sensor: 65535, value: -2.5, raw: ByteVec(vec![]) },
+        ];
+        let text = to_csv(&rows).unwrap();
+        assert_eq!("sensor,value,raw\n3,0.1,dead\n65535,-2.5,\n", text);
+        assert_eq!(Some(rows), from_csv::<Reading>(&text));
+        let legacy = "raw,extra,sensor,value\r\n01,\"x, \"\"y\"\"\",7,1e3\r\n\r\n";
+        let read = from_csv::<Reading>(legacy).unwrap();
+        assert_eq!(vec![Reading{ sensor: 7, value: 1000.0, raw: ByteVec(vec![1]) }], read);
+        assert_eq!(None, from_csv::<Reading>("sensor,value\n1,2\n"));
+        assert_eq!(None, from_csv::<Reading>("sensor,value,raw\n70000,1,\n"));
+        assert_eq!(None, from_csv::<Reading>("sensor,value,raw\n1,1,abc\n"));
+        assert_eq!(None, parse_rows("a,\"b\n"));
+        assert_eq!(Some(vec![vec![String::from("a\nb"), String::from("")]]), parse_rows("\"a\nb\","));
+    }
+}
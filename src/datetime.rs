@@ -0,0 +1,159 @@
+//! Bufferable for the types of the time crate, enabled with the `time` feature.
+//! A Date is its Julian day (i32), a Time the nanoseconds since midnight (u64),
+//! a PrimitiveDateTime the two of them and an OffsetDateTime the nanoseconds since
+//! the Unix epoch (i128) and its UTC offset in seconds (i32). Signed numbers are big endian
+//! two's complement. Reading fails for values out of the range of the type.
+use std::convert::TryFrom;
+use crate::{ Bufferable, ReadBuffer, Sink };
+use time::{ Date, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset };
+
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+
+fn read_array<const N: usize>(buf: &mut ReadBuffer) -> Option<[u8; N]>{
+    <[u8; N]>::try_from(buf.read_bytes(N)?).ok()
+}
+
+/// Implements Bufferable for Date, written as its Julian day.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use time::{ Date, Month };
+/// let x = Date::from_calendar_date(2024, Month::February, 29).unwrap();
+/// assert_eq!(4, encode(&x).len());
+/// assert_eq!(Some(x), decode(&encode(&x)));
+/// ```
+impl Bufferable for Date{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        vec.write_bytes(&self.to_julian_day().to_be_bytes());
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        Date::from_julian_day(i32::from_be_bytes(read_array(buf)?)).ok()
+    }
+
+    fn buffer_len(&self) -> usize{
+        4
+    }
+}
+
+/// Implements Bufferable for Time, written as the nanoseconds since midnight.
+impl Bufferable for Time{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        let (h, m, s, nano) = self.as_hms_nano();
+        let nanos = ((u64::from(h) * 60 + u64::from(m)) * 60 + u64::from(s)) * 1_000_000_000 + u64::from(nano);
+        nanos.into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let nanos = u64::from_buffer(buf)?;
+        if nanos >= NANOS_PER_DAY{
+            return Option::None;
+        }
+        let secs = nanos / 1_000_000_000;
+        Time::from_hms_nano((secs / 3600) as u8, (secs / 60 % 60) as u8, (secs % 60) as u8,
+            (nanos % 1_000_000_000) as u32).ok()
+    }
+
+    fn buffer_len(&self) -> usize{
+        8
+    }
+}
+
+/// Implements Bufferable for PrimitiveDateTime, written as its date and then its time.
+impl Bufferable for PrimitiveDateTime{
+    const MIN_BUFFER_LEN: usize = 12;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.date().into_buffer(vec);
+        self.time().into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let date = Date::from_buffer(buf)?;
+        let time = Time::from_buffer(buf)?;
+        Option::Some(PrimitiveDateTime::new(date, time))
+    }
+
+    fn buffer_len(&self) -> usize{
+        12
+    }
+}
+
+/// Implements Bufferable for OffsetDateTime, written as the nanoseconds since the Unix epoch
+/// and the UTC offset, so the same moment in another offset writes other bytes.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use time::{ OffsetDateTime, UtcOffset };
+/// let x = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()
+///     .to_offset(UtcOffset::from_hms(2, 0, 0).unwrap());
+/// assert_eq!(20, encode(&x).len());
+/// let y: OffsetDateTime = decode(&encode(&x)).unwrap();
+/// assert_eq!(x, y);
+/// assert_eq!(x.offset(), y.offset());
+/// ```
+impl Bufferable for OffsetDateTime{
+    const MIN_BUFFER_LEN: usize = 20;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        vec.write_bytes(&self.unix_timestamp_nanos().to_be_bytes());
+        vec.write_bytes(&self.offset().whole_seconds().to_be_bytes());
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let nanos = i128::from_be_bytes(read_array(buf)?);
+        let offset = UtcOffset::from_whole_seconds(i32::from_be_bytes(read_array(buf)?)).ok()?;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()?.checked_to_offset(offset)
+    }
+
+    fn buffer_len(&self) -> usize{
+        20
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use time::{ Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset };
+
+    #[test]
+    fn test_time(){
+        let date = Date::from_calendar_date(-44, Month::March, 15).unwrap();
+        let time = Time::from_hms_nano(23, 59, 59, 999_999_999).unwrap();
+        let x = (date, time, PrimitiveDateTime::new(date, time), vec![Date::MIN, Date::MAX]);
+        let bytes = encode(&x);
+        assert_eq!(4 + 8 + 12 + 8 + 4 + 4, bytes.len());
+        assert_eq!(Some(x), decode(&bytes));
+        assert_eq!(encode(&(86_399_999_999_999u64)), encode(&time));
+        let moment = OffsetDateTime::UNIX_EPOCH.to_offset(UtcOffset::from_hms(-5, -30, 0).unwrap());
+        let bytes = encode(&moment);
+        assert_eq!([0; 16], bytes[..16]);
+        assert_eq!(Some(moment), decode(&bytes));
+        // out of range
+        assert_eq!(None, decode::<Time>(&encode(&86_400_000_000_000u64)));
+        assert_eq!(None, decode::<Date>(&i32::MAX.to_be_bytes()));
+        let mut bad = bytes.clone();
+        bad[16..].copy_from_slice(&100_000i32.to_be_bytes());
+        assert_eq!(None, decode::<OffsetDateTime>(&bad));
+    }
+}
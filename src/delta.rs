@@ -0,0 +1,256 @@
+//! Deltas between two snapshots of the same type, for sending state every tick
+//! when most of it stays the same from one tick to the next.
+//! Unlike a patch from the patch module, which finds moved data, a delta only compares the
+//! bytes at the same positions, which is what changes in a snapshot look like and is
+//! much cheaper to make. A delta is the old and the new length as varints, followed by runs
+//! of changed bytes: the number of unchanged bytes since the last run, the length of the run
+//! and its bytes, all lengths as varints. Bytes past the end of old count as changed.
+//! DeltaEncoder and DeltaDecoder keep the last snapshot on both sides, the two have to see
+//! the same deltas in the same order.
+//! # Example
+//! ```
+//! use bin_buffer::delta::*;
+//! let mut server = DeltaEncoder::new();
+//! let mut client = DeltaDecoder::new();
+//! let mut state = (vec![0u32; 100], String::from("lobby"));
+//! let first = server.encode(&state);
+//! assert_eq!(Some(state.clone()), client.apply(&first));
+//! state.0[42] = 7;
+//! let next = server.encode(&state);
+//! assert!(next.len() < 10);
+//! assert_eq!(Some(state), client.apply(&next));
+//! ```
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use crate::describe::TypeDescriptor;
+use crate::varint;
+
+// Unchanged bytes between runs that are cheaper to send along than to start a new run for.
+const MERGE_GAP: usize = 2;
+
+/// The delta that turns old into new.
+pub fn make_delta(old: &[u8], new: &[u8]) -> Buffer{
+    let mut delta = Vec::new();
+    varint::write_u64(old.len() as u64, &mut delta);
+    varint::write_u64(new.len() as u64, &mut delta);
+    let same = |i: usize| i < old.len() && old[i] == new[i];
+    let mut end = 0;
+    let mut pos = 0;
+    while pos < new.len(){
+        if same(pos){
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        let mut run_end = pos + 1;
+        // grow the run over gaps of at most MERGE_GAP unchanged bytes
+        loop{
+            while run_end < new.len() && !same(run_end){
+                run_end += 1;
+            }
+            let next = (run_end..new.len().min(run_end + MERGE_GAP + 1)).find(|&i| !same(i));
+            match next{
+                Option::Some(i) => run_end = i + 1,
+                Option::None => break,
+            }
+        }
+        varint::write_u64((start - end) as u64, &mut delta);
+        varint::write_u64((run_end - start) as u64, &mut delta);
+        delta.extend_from_slice(&new[start..run_end]);
+        end = run_end;
+        pos = run_end;
+    }
+    delta
+}
+
+/// Apply a delta made by make_delta to old. None if the delta is broken
+/// or was made for a buffer of another length.
+pub fn apply_delta(old: &[u8], delta: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::borrowed(delta, Default::default());
+    let old_len = usize::try_from(varint::read_u64(&mut buf)?).ok()?;
+    let new_len = usize::try_from(varint::read_u64(&mut buf)?).ok()?;
+    if old_len != old.len() || new_len > old.len().saturating_add(delta.len()){
+        return Option::None;
+    }
+    let mut new = old.to_vec();
+    new.resize(new_len, 0);
+    let mut pos = 0usize;
+    while buf.remaining() > 0{
+        let gap = usize::try_from(varint::read_u64(&mut buf)?).ok()?;
+        let len = usize::try_from(varint::read_u64(&mut buf)?).ok()?;
+        let start = pos.checked_add(gap)?;
+        let run = buf.read_bytes(len)?;
+        new.get_mut(start..start.checked_add(len)?)?.copy_from_slice(run);
+        pos = start + len;
+    }
+    // bytes past the end of old were all sent
+    if new_len > old_len && pos < new_len{
+        return Option::None;
+    }
+    Option::Some(new)
+}
+
+/// The names of the fields of a struct whose bytes differ between two encodings of it.
+/// Fields at a fixed offset with a fixed length are compared directly,
+/// the others are listed if anything from where the fixed ones end differs.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::delta::*;
+/// use bin_buffer::describe::*;
+/// const POINT: TypeDescriptor = TypeDescriptor{ name: "Point", kind: Kind::Struct(&[
+///     FieldDescriptor{ name: "x", type_name: "u16", offset: Some(0), len: Some(2) },
+///     FieldDescriptor{ name: "y", type_name: "u16", offset: Some(2), len: Some(2) },
+/// ]) };
+/// assert_eq!(vec!["y"], changed_fields(&POINT, &encode(&(1u16, 2u16)), &encode(&(1u16, 3u16))));
+/// ```
+pub fn changed_fields(descriptor: &TypeDescriptor, old: &[u8], new: &[u8]) -> Vec<&'static str>{
+    let mut changed = Vec::new();
+    let mut fixed_end = 0;
+    let mut rest = Vec::new();
+    for field in descriptor.fields(){
+        match (field.offset, field.len){
+            (Option::Some(offset), Option::Some(len)) => {
+                fixed_end = fixed_end.max(offset + len);
+                if old.get(offset..offset + len) != new.get(offset..offset + len){
+                    changed.push(field.name);
+                }
+            },
+            _ => rest.push(field.name),
+        }
+    }
+    if old.get(fixed_end..) != new.get(fixed_end..){
+        changed.extend(rest);
+    }
+    changed
+}
+
+/// Makes deltas of every snapshot against the one before it.
+pub struct DeltaEncoder<T>{
+    last: Buffer,
+    marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Bufferable> DeltaEncoder<T>{
+    /// An encoder whose first delta is against an empty buffer, so holds the whole snapshot.
+    pub fn new() -> Self{
+        Self{ last: Vec::new(), marker: PhantomData }
+    }
+    /// The delta from the last snapshot to x.
+    pub fn encode(&mut self, x: &T) -> Buffer{
+        let mut bytes = Vec::with_capacity(x.buffer_len().max(self.last.len()));
+        x.copy_into_buffer(&mut bytes);
+        let delta = make_delta(&self.last, &bytes);
+        self.last = bytes;
+        delta
+    }
+    /// Start over, for when the other side lost track: the next delta holds the whole snapshot.
+    pub fn reset(&mut self){
+        self.last.clear();
+    }
+    /// The encoding of the last snapshot.
+    pub fn last(&self) -> &[u8]{
+        &self.last
+    }
+}
+
+impl<T: Bufferable> Default for DeltaEncoder<T>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+/// Applies the deltas of a DeltaEncoder.
+pub struct DeltaDecoder<T>{
+    last: Buffer,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Bufferable> DeltaDecoder<T>{
+    /// A decoder that starts from an empty buffer, as DeltaEncoder does.
+    pub fn new() -> Self{
+        Self{ last: Vec::new(), marker: PhantomData }
+    }
+    /// Apply delta to the last snapshot and decode the result.
+    /// None if the delta does not fit the last snapshot or the result is not exactly one T,
+    /// the last snapshot is then left as it was.
+    pub fn apply(&mut self, delta: &[u8]) -> Option<T>{
+        let bytes = apply_delta(&self.last, delta)?;
+        let mut buf = ReadBuffer::borrowed(&bytes, Default::default());
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        self.last = bytes;
+        Option::Some(x)
+    }
+    /// Start over, as DeltaEncoder::reset.
+    pub fn reset(&mut self){
+        self.last.clear();
+    }
+}
+
+impl<T: Bufferable> Default for DeltaDecoder<T>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::delta::*;
+
+    #[test]
+    fn test_delta(){
+        let old: Vec<u8> = (0..=255).collect();
+        let mut new = old.clone();
+        new[10] = 0;
+        new[12] = 0;
+        new[200] = 0;
+        let delta = make_delta(&old, &new);
+        // 2 lengths, then runs 10..13 and 200
+        assert_eq!(2 + 2 + (1 + 1 + 3) + (2 + 1 + 1), delta.len());
+        assert_eq!(Some(new.clone()), apply_delta(&old, &delta));
+        assert_eq!(4, make_delta(&old, &old).len());
+        // growing, shrinking and from nothing
+        let mut longer = new.clone();
+        longer.extend_from_slice(b"more");
+        assert_eq!(Some(longer.clone()), apply_delta(&new, &make_delta(&new, &longer)));
+        assert_eq!(Some(old[..5].to_vec()), apply_delta(&old, &make_delta(&old, &old[..5])));
+        assert_eq!(Some(old.clone()), apply_delta(&[], &make_delta(&[], &old)));
+        // the wrong base, a cut off delta and a run past the end
+        assert_eq!(None, apply_delta(&old[1..], &delta));
+        assert_eq!(None, apply_delta(&old, &delta[..delta.len() - 1]));
+        assert_eq!(None, apply_delta(&[1], &[1, 1, 1, 1, 9]));
+        // snapshots
+        let mut enc = DeltaEncoder::<Vec<u16>>::new();
+        let mut dec = DeltaDecoder::<Vec<u16>>::new();
+        let mut x = vec![1u16; 10];
+        assert_eq!(Some(x.clone()), dec.apply(&enc.encode(&x)));
+        x.push(2);
+        assert_eq!(Some(x.clone()), dec.apply(&enc.encode(&x)));
+        let same = enc.encode(&x);
+        assert_eq!(None, DeltaDecoder::<u8>::new().apply(&same));
+        assert_eq!(Some(x.clone()), dec.apply(&same));
+        enc.reset();
+        dec.reset();
+        let full = enc.encode(&x);
+        assert!(full.len() > 8 + 2 * 11);
+        assert_eq!(Some(x), dec.apply(&full));
+    }
+
+    #[test]
+    fn test_changed_fields(){
+        use crate::describe::*;
+        const PLAYER: TypeDescriptor = TypeDescriptor{ name: "Player", kind: Kind::Struct(&[
+            FieldDescriptor{ name: "hp", type_name: "u32", offset: Some(0), len: Some(4) },
+            FieldDescriptor{ name: "name", type_name: "String", offset: Some(4), len: None },
+            FieldDescriptor{ name: "score", type_name: "u64", offset: None, len: Some(8) },
+        ]) };
+        let old = crate::encode(&(10u32, String::from("ann"), 5u64));
+        assert!(changed_fields(&PLAYER, &old, &old).is_empty());
+        assert_eq!(vec!["hp"], changed_fields(&PLAYER, &old, &crate::encode(&(9u32, String::from("ann"), 5u64))));
+        assert_eq!(vec!["name", "score"], changed_fields(&PLAYER, &old, &crate::encode(&(10u32, String::from("ann"), 6u64))));
+    }
+}
@@ -0,0 +1,143 @@
+//! Descriptions of types that can be looked at while the program runs: the fields of a
+//! struct or the variants of an enum, with the types and where the fields are in the encoding.
+//! Generic tools like dumpers, editors and schema diffs can work from these instead of
+//! metadata kept by hand. codegen writes them when asked to, see Generator::descriptors.
+//! # Example
+//! ```
+//! use bin_buffer::describe::*;
+//! struct Pixel{ x: u16, y: u16, label: String, alpha: u8 }
+//! impl Describe for Pixel{
+//!     const DESCRIPTOR: TypeDescriptor = TypeDescriptor{
+//!         name: "Pixel",
+//!         kind: Kind::Struct(&[
+//!             FieldDescriptor{ name: "x", type_name: "u16", offset: Some(0), len: Some(2) },
+//!             FieldDescriptor{ name: "y", type_name: "u16", offset: Some(2), len: Some(2) },
+//!             FieldDescriptor{ name: "label", type_name: "String", offset: Some(4), len: None },
+//!             FieldDescriptor{ name: "alpha", type_name: "u8", offset: None, len: Some(1) },
+//!         ]),
+//!     };
+//! }
+//! let d = Pixel::DESCRIPTOR;
+//! assert_eq!(Some(2), d.field("y").unwrap().offset);
+//! assert_eq!(None, d.fixed_len());
+//! ```
+
+/// A field of a struct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldDescriptor{
+    /// Name of the field.
+    pub name: &'static str,
+    /// Its Rust type, as written in the source.
+    pub type_name: &'static str,
+    /// Where the field starts in the encoding of the struct,
+    /// None if a field in front of it does not always take the same number of bytes.
+    pub offset: Option<usize>,
+    /// Bytes the field takes, None if that depends on the value.
+    pub len: Option<usize>,
+}
+
+/// A variant of an enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VariantDescriptor{
+    /// Name of the variant.
+    pub name: &'static str,
+    /// Its discriminant.
+    pub tag: u32,
+    /// The Rust types of its tuple fields, empty for a unit variant.
+    pub fields: &'static [&'static str],
+}
+
+/// Whether a type is a struct or an enum, with its fields or variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind{
+    /// A struct, its fields in the order they are written.
+    Struct(&'static [FieldDescriptor]),
+    /// An enum, written as bufferable_enum does.
+    Enum(&'static [VariantDescriptor]),
+}
+
+/// Description of a type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeDescriptor{
+    /// Name of the type.
+    pub name: &'static str,
+    /// Its fields or variants.
+    pub kind: Kind,
+}
+
+impl TypeDescriptor{
+    /// The fields of a struct, empty for an enum.
+    pub fn fields(&self) -> &'static [FieldDescriptor]{
+        match self.kind{
+            Kind::Struct(fields) => fields,
+            Kind::Enum(_) => &[],
+        }
+    }
+    /// The variants of an enum, empty for a struct.
+    pub fn variants(&self) -> &'static [VariantDescriptor]{
+        match self.kind{
+            Kind::Struct(_) => &[],
+            Kind::Enum(variants) => variants,
+        }
+    }
+    /// The field called name.
+    pub fn field(&self, name: &str) -> Option<&'static FieldDescriptor>{
+        self.fields().iter().find(|f| f.name == name)
+    }
+    /// The variant with discriminant tag.
+    pub fn variant(&self, tag: u32) -> Option<&'static VariantDescriptor>{
+        self.variants().iter().find(|v| v.tag == tag)
+    }
+    /// Bytes every value of a struct takes, None for enums and if any field can vary.
+    pub fn fixed_len(&self) -> Option<usize>{
+        match self.kind{
+            Kind::Struct(fields) => fields.iter().map(|f| f.len).sum(),
+            Kind::Enum(_) => Option::None,
+        }
+    }
+}
+
+/// A type with a description.
+pub trait Describe{
+    /// The description.
+    const DESCRIPTOR: TypeDescriptor;
+}
+
+/// The description of T.
+pub fn descriptor_of<T: Describe>() -> TypeDescriptor{
+    T::DESCRIPTOR
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::describe::*;
+
+    enum Shape{}
+
+    impl Describe for Shape{
+        const DESCRIPTOR: TypeDescriptor = TypeDescriptor{
+            name: "Shape",
+            kind: Kind::Enum(&[
+                VariantDescriptor{ name: "Empty", tag: 0, fields: &[] },
+                VariantDescriptor{ name: "Rect", tag: 2, fields: &["f32", "f32"] },
+            ]),
+        };
+    }
+
+    #[test]
+    fn test_describe(){
+        let d = descriptor_of::<Shape>();
+        assert_eq!("Shape", d.name);
+        assert_eq!(Some("Rect"), d.variant(2).map(|v| v.name));
+        assert_eq!(None, d.variant(1));
+        assert!(d.fields().is_empty());
+        assert_eq!(None, d.fixed_len());
+        let point = TypeDescriptor{ name: "Point", kind: Kind::Struct(&[
+            FieldDescriptor{ name: "x", type_name: "f64", offset: Some(0), len: Some(8) },
+            FieldDescriptor{ name: "y", type_name: "f64", offset: Some(8), len: Some(8) },
+        ]) };
+        assert_eq!(Some(16), point.fixed_len());
+        assert!(point.variants().is_empty());
+        assert_eq!(None, point.field("z"));
+    }
+}
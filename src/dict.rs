@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use crate::{ Bufferable, ReadBuffer, Sink };
+
+/// A Vec that is written as a dictionary of its distinct values and an index into it per element.
+/// The dictionary comes first, as a Vec<T> in the order the values first show up, then the
+/// number of elements (u64) and their indices. The indices are u8 when the dictionary has
+/// at most 256 values, u16 when it has at most 65536 and u32 otherwise.
+/// Good for columns with only a few different values, like names of categories.
+/// Every element read is a clone of its dictionary entry, and the memory of each counts
+/// against DecodeLimits::max_total_bytes.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let colours: Vec<String> = ["red", "green", "red", "red", "green"].iter().map(|s| s.to_string()).collect();
+/// let x = DictEncoded(colours);
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// assert_eq!(8 + (8 + 3) + (8 + 5) + 8 + 5, buffer.len());
+/// assert_eq!(&[0, 1, 0, 0, 1], &buffer[buffer.len() - 5..]);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(x), DictEncoded::<String>::from_buffer(&mut buffer));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DictEncoded<T>(pub Vec<T>);
+
+impl<T> std::ops::Deref for DictEncoded<T>{
+    type Target = Vec<T>;
+    fn deref(&self) -> &Vec<T>{
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for DictEncoded<T>{
+    fn deref_mut(&mut self) -> &mut Vec<T>{
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for DictEncoded<T>{
+    fn from(vec: Vec<T>) -> Self{
+        Self(vec)
+    }
+}
+
+impl<T> From<DictEncoded<T>> for Vec<T>{
+    fn from(vec: DictEncoded<T>) -> Self{
+        vec.0
+    }
+}
+
+// Bytes per index for a dictionary of len values.
+fn index_width(len: usize) -> usize{
+    if len <= 1 << 8 { 1 } else if len <= 1 << 16 { 2 } else { 4 }
+}
+
+impl<T: Bufferable + Eq + Hash + Clone> DictEncoded<T>{
+    /// The distinct values in the order they first show up, and the index of every element.
+    pub fn dictionary(&self) -> (Vec<&T>, Vec<u32>){
+        let mut dict = Vec::new();
+        let mut indices: HashMap<&T, u32> = HashMap::new();
+        let index = self.0.iter().map(|x|{
+            *indices.entry(x).or_insert_with(||{
+                dict.push(x);
+                (dict.len() - 1) as u32
+            })
+        }).collect();
+        (dict, index)
+    }
+}
+
+impl<T: Bufferable + Eq + Hash + Clone> Bufferable for DictEncoded<T>{
+    const MIN_BUFFER_LEN: usize = 16;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        let (dict, index) = self.dictionary();
+        (dict.len() as u64).into_buffer(vec);
+        for x in &dict{
+            x.copy_into_buffer(vec);
+        }
+        (index.len() as u64).into_buffer(vec);
+        match index_width(dict.len()){
+            1 => u8::copy_slice_into_buffer(&index.iter().map(|i| *i as u8).collect::<Vec<_>>(), vec),
+            2 => u16::copy_slice_into_buffer(&index.iter().map(|i| *i as u16).collect::<Vec<_>>(), vec),
+            _ => u32::copy_slice_into_buffer(&index, vec),
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let dict = Vec::<T>::from_buffer(buf)?;
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            let index: Vec<u32> = match index_width(dict.len()){
+                1 => u8::vec_from_buffer(len, buf)?.into_iter().map(u32::from).collect(),
+                2 => u16::vec_from_buffer(len, buf)?.into_iter().map(u32::from).collect(),
+                _ => u32::vec_from_buffer(len, buf)?,
+            };
+            // every clone copies the memory of its entry, so a few indices into one big entry
+            // count for all the copies they make
+            let sizes: Vec<usize> = dict.iter().map(Bufferable::buffer_len).collect();
+            let mut vec = Vec::with_capacity(index.len());
+            for i in index{
+                buf.use_bytes(*sizes.get(i as usize)?)?;
+                vec.push(dict[i as usize].clone());
+            }
+            Option::Some(Self(vec))
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        let (dict, index) = self.dictionary();
+        16 + dict.iter().map(|x| x.buffer_len()).sum::<usize>() + index.len() * index_width(dict.len())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    #[test]
+    fn test_dict_encoded(){
+        for distinct in [1u32, 2, 256, 257, 70_000].iter(){
+            let x = DictEncoded((0..100_000u32).map(|i| i % distinct).collect::<Vec<_>>());
+            let bytes = encode(&x);
+            assert_eq!(x.buffer_len(), bytes.len());
+            assert_eq!(Some(x), decode(&bytes));
+        }
+        let x = DictEncoded(vec![String::from("b"), String::from("a"), String::from("b")]);
+        let (dict, index) = x.dictionary();
+        assert_eq!(vec!["b", "a"], dict);
+        assert_eq!(vec![0, 1, 0], index);
+        assert_eq!(Some(DictEncoded(Vec::<u8>::new())), decode(&encode(&DictEncoded(Vec::<u8>::new()))));
+        // an index past the dictionary
+        assert_eq!(None, decode::<DictEncoded<u8>>(&[0,0,0,0,0,0,0,1, 7, 0,0,0,0,0,0,0,2, 0, 1]));
+        assert_eq!(Some(DictEncoded(vec![7u8, 7])), decode(&[0,0,0,0,0,0,0,1, 7, 0,0,0,0,0,0,0,2, 0, 0]));
+        // many indices into one big entry count every copy against the limits
+        let mut bytes = Vec::new();
+        vec![String::from_utf8(vec![b'x'; 1000]).unwrap()].into_buffer(&mut bytes);
+        vec![0u8; 100].into_buffer(&mut bytes);
+        let limits = DecodeLimits{ max_total_bytes: 50_000, ..DecodeLimits::default() };
+        assert_eq!(None, DictEncoded::<String>::from_buffer(&mut ReadBuffer::from_slice(&bytes).with_limits(limits)));
+        let limits = DecodeLimits{ max_total_bytes: 200_000, ..DecodeLimits::default() };
+        assert_eq!(100, DictEncoded::<String>::from_buffer(&mut ReadBuffer::from_slice(&bytes).with_limits(limits)).unwrap().len());
+    }
+}
@@ -0,0 +1,319 @@
+//! Huffman coding of bytes, enabled with the `entropy` feature.
+//! For data with few distinct byte values, like flags, small numbers and enum tags,
+//! without pulling in a general compressor. Wrap a field in Entropy to code its bytes,
+//! or code whole sections with encode_bytes and decode_bytes.
+//! The coded form is the number of bytes (u64), then if that is not 0 the number of
+//! byte values used (u16), every used value with the length of its code (u8 each, lowest
+//! value first) and the bits as a blob (u64 length and the bytes), first bit in the highest bit.
+//! Entropy writes the numbers and the value it codes in the byte order of the Sink.
+//! The codes are canonical Huffman codes, at most MAX_CODE_LEN bits long.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::entropy::*;
+//! let flags: Vec<u8> = (0..1000).map(|i| if i % 10 == 0 { 1 } else { 0 }).collect();
+//! let x = Entropy(flags.clone());
+//! let mut buffer = Vec::new();
+//! x.copy_into_buffer(&mut buffer);
+//! assert!(buffer.len() < 200);
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! assert_eq!(Some(x), Entropy::<Vec<u8>>::from_buffer(&mut buffer));
+//! ```
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, EndianSink, ReadBuffer, Sink };
+
+/// Longest code, in bits.
+pub const MAX_CODE_LEN: u8 = 24;
+
+// Code length of every byte value, 0 for values that do not occur.
+fn code_lengths(bytes: &[u8]) -> [u8; 256]{
+    let mut freq = [0u64; 256];
+    for &b in bytes{
+        freq[b as usize] += 1;
+    }
+    loop{
+        let mut lens = [0u8; 256];
+        let mut groups: Vec<Vec<u8>> = Vec::new();
+        let mut heap = BinaryHeap::new();
+        for (b, &f) in freq.iter().enumerate(){
+            if f > 0{
+                heap.push((Reverse(f), Reverse(groups.len())));
+                groups.push(vec![b as u8]);
+            }
+        }
+        if groups.len() == 1{
+            lens[groups[0][0] as usize] = 1;
+            return lens;
+        }
+        // merge the two lightest groups, every byte in them gets a bit longer
+        while heap.len() > 1{
+            let (Reverse(fa), Reverse(a)) = heap.pop().unwrap();
+            let (Reverse(fb), Reverse(b)) = heap.pop().unwrap();
+            let mut merged = std::mem::take(&mut groups[a]);
+            merged.append(&mut groups[b]);
+            for &byte in &merged{
+                lens[byte as usize] += 1;
+            }
+            heap.push((Reverse(fa + fb), Reverse(groups.len())));
+            groups.push(merged);
+        }
+        if lens.iter().all(|&l| l <= MAX_CODE_LEN){
+            return lens;
+        }
+        // flatten the counts until the tree is shallow enough
+        for f in freq.iter_mut().filter(|f| **f > 0){
+            *f = *f / 2 + 1;
+        }
+    }
+}
+
+// The used byte values in canonical order, by code length and then by value.
+fn canonical_order(lens: &[u8; 256]) -> Vec<u8>{
+    let mut order: Vec<u8> = (0..=255u8).filter(|&b| lens[b as usize] > 0).collect();
+    order.sort_by_key(|&b| (lens[b as usize], b));
+    order
+}
+
+/// Huffman code bytes.
+/// # Example
+/// ```
+/// use bin_buffer::entropy::*;
+/// let bytes = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaabbbbbbbbc".to_vec();
+/// let coded = encode_bytes(&bytes);
+/// assert!(coded.len() < bytes.len());
+/// assert_eq!(Some(bytes), decode_bytes(&coded));
+/// ```
+pub fn encode_bytes(bytes: &[u8]) -> Buffer{
+    let mut coded = Vec::new();
+    write_coded(bytes, &mut coded);
+    coded
+}
+
+// encode_bytes to the end of a Sink, with the numbers in its byte order.
+fn write_coded<S: Sink + ?Sized>(bytes: &[u8], coded: &mut S){
+    (bytes.len() as u64).into_buffer(coded);
+    if bytes.is_empty(){
+        return;
+    }
+    let lens = code_lengths(bytes);
+    let mut codes = [0u32; 256];
+    let mut code = 0u32;
+    let mut prev = 0;
+    for b in canonical_order(&lens){
+        let len = lens[b as usize];
+        code <<= len - prev;
+        codes[b as usize] = code;
+        code += 1;
+        prev = len;
+    }
+    let used: Vec<u8> = (0..=255u8).filter(|&b| lens[b as usize] > 0).collect();
+    (used.len() as u16).into_buffer(coded);
+    for &b in &used{
+        b.into_buffer(coded);
+        lens[b as usize].into_buffer(coded);
+    }
+    let mut bits = Vec::new();
+    let mut acc = 0u64;
+    let mut filled = 0;
+    for &b in bytes{
+        acc = (acc << lens[b as usize]) | codes[b as usize] as u64;
+        filled += lens[b as usize];
+        while filled >= 8{
+            filled -= 8;
+            bits.push((acc >> filled) as u8);
+        }
+    }
+    if filled > 0{
+        bits.push((acc << (8 - filled)) as u8);
+    }
+    (bits.len() as u64).into_buffer(coded);
+    coded.write_bytes(&bits);
+}
+
+// Read bytes coded by encode_bytes.
+fn read_coded(buf: &mut ReadBuffer) -> Option<Buffer>{
+    let n = usize::try_from(u64::from_buffer(buf)?).ok()?;
+    if n == 0{
+        return Option::Some(Vec::new());
+    }
+    let used = u16::from_buffer(buf)? as usize;
+    if used == 0 || used > 256{
+        return Option::None;
+    }
+    let mut lens = [0u8; 256];
+    let mut prev = Option::None;
+    let mut kraft = 0u64;
+    for _ in 0..used{
+        let b = u8::from_buffer(buf)?;
+        let len = u8::from_buffer(buf)?;
+        if prev.is_some_and(|p| p >= b) || len == 0 || len > MAX_CODE_LEN{
+            return Option::None;
+        }
+        kraft += 1 << (MAX_CODE_LEN - len);
+        lens[b as usize] = len;
+        prev = Option::Some(b);
+    }
+    // more codes than fit in the lengths
+    if kraft > 1 << MAX_CODE_LEN{
+        return Option::None;
+    }
+    let bits_len = buf.read_len()?;
+    // every byte takes at least one bit
+    if n / 8 > bits_len{
+        return Option::None;
+    }
+    buf.use_bytes(n)?;
+    let bits = buf.read_bytes(bits_len)?;
+    let order = canonical_order(&lens);
+    // first code and index in order of every length
+    let mut first = [0u32; MAX_CODE_LEN as usize + 2];
+    let mut start = [0usize; MAX_CODE_LEN as usize + 2];
+    let mut count = [0usize; MAX_CODE_LEN as usize + 2];
+    for &b in &order{
+        count[lens[b as usize] as usize] += 1;
+    }
+    let mut code = 0u32;
+    let mut index = 0;
+    for len in 1..=MAX_CODE_LEN as usize{
+        code <<= 1;
+        first[len] = code;
+        start[len] = index;
+        code += count[len] as u32;
+        index += count[len];
+    }
+    let mut out = Vec::with_capacity(n);
+    let mut bit = 0;
+    while out.len() < n{
+        let mut code = 0u32;
+        let mut len = 0;
+        loop{
+            let byte = *bits.get(bit / 8)?;
+            code = (code << 1) | ((byte >> (7 - bit % 8)) & 1) as u32;
+            bit += 1;
+            len += 1;
+            if len > MAX_CODE_LEN as usize{
+                return Option::None;
+            }
+            if code - first[len] < count[len] as u32{
+                out.push(order[start[len] + (code - first[len]) as usize]);
+                break;
+            }
+        }
+    }
+    // no bytes after the last code and the padding bits are 0
+    if bit.div_ceil(8) != bits.len() || (bit % 8 != 0 && bits[bits.len() - 1] & (0xff >> (bit % 8)) != 0){
+        return Option::None;
+    }
+    Option::Some(out)
+}
+
+/// Decode bytes coded by encode_bytes. None if they are broken or have bytes left over.
+pub fn decode_bytes(coded: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::borrowed(coded, Default::default());
+    let bytes = read_coded(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    Option::Some(bytes)
+}
+
+/// A value that is written Huffman coded: its bytes go through encode_bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Entropy<T>(pub T);
+
+impl<T> std::ops::Deref for Entropy<T>{
+    type Target = T;
+    fn deref(&self) -> &T{
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for Entropy<T>{
+    fn deref_mut(&mut self) -> &mut T{
+        &mut self.0
+    }
+}
+
+impl<T: Bufferable> Bufferable for Entropy<T>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        let mut bytes = EndianSink::new(Vec::with_capacity(self.0.buffer_len()), vec.endian());
+        self.0.copy_into_buffer(&mut bytes);
+        write_coded(&bytes.into_inner(), vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let bytes = read_coded(buf)?;
+            let mut inner = ReadBuffer::borrowed(&bytes, buf.limits).with_endian(buf.endian);
+            let x = T::from_buffer(&mut inner)?;
+            if inner.remaining() != 0{
+                return Option::None;
+            }
+            Option::Some(Self(x))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::entropy::*;
+
+    #[test]
+    fn test_entropy(){
+        let mut seed = 7u32;
+        let skewed: Vec<u8> = (0..20_000).map(|_|{
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16).trailing_zeros() as u8
+        }).collect();
+        let coded = encode_bytes(&skewed);
+        assert!(coded.len() < skewed.len() / 3, "{}", coded.len());
+        assert_eq!(Some(skewed.clone()), decode_bytes(&coded));
+        // every byte value, one value, nothing
+        let all: Vec<u8> = (0..=255u8).cycle().take(3000).collect();
+        assert_eq!(Some(all.clone()), decode_bytes(&encode_bytes(&all)));
+        assert_eq!(Some(vec![5; 17]), decode_bytes(&encode_bytes(&[5; 17])));
+        assert_eq!(vec![0; 8], encode_bytes(&[]));
+        assert_eq!(Some(Vec::new()), decode_bytes(&[0; 8]));
+        // counts like fibonacci numbers make a deep tree that has to be flattened
+        let mut fib = Vec::new();
+        let (mut a, mut b) = (1usize, 1usize);
+        for byte in 0..30u8{
+            fib.extend(std::iter::repeat_n(byte, a));
+            let c = a + b;
+            a = b;
+            b = c;
+        }
+        assert_eq!(Some(fib.clone()), decode_bytes(&encode_bytes(&fib)));
+        // broken codes
+        assert_eq!(None, decode_bytes(&coded[..coded.len() - 1]));
+        let mut extra = coded.clone();
+        extra.push(0);
+        assert_eq!(None, decode_bytes(&extra));
+        // two values with one bit codes and a third one does not fit
+        assert_eq!(None, decode_bytes(&[0,0,0,0,0,0,0,1, 0,3, 0,1, 1,1, 2,1, 0,0,0,0,0,0,0,1, 0]));
+        assert_eq!(None, decode_bytes(&[0,0,0,0,0,0,0,1, 0,1, 0,1, 0,0,0,0,0,0,0,1, 0b0100_0000]));
+        assert_eq!(Some(vec![0]), decode_bytes(&[0,0,0,0,0,0,0,1, 0,1, 0,1, 0,0,0,0,0,0,0,1, 0]));
+        // the wrapper
+        let x = Entropy((String::from("aaaaaaaaaaaaaaaab"), vec![0u32; 100]));
+        let bytes = encode(&x);
+        assert!(bytes.len() < 100);
+        assert_eq!(Some(x.clone()), decode(&bytes));
+        // little endian, the numbers of the coded form and the value both
+        let mut little = EndianSink::new(Vec::new(), Endian::Little);
+        x.copy_into_buffer(&mut little);
+        let little = little.into_inner();
+        assert_ne!(bytes, little);
+        let mut buffer = ReadBuffer::from_raw(little).with_endian(Endian::Little);
+        assert_eq!(Some(x), Entropy::from_buffer(&mut buffer));
+        assert_eq!(0, buffer.remaining());
+    }
+}
@@ -0,0 +1,328 @@
+//! Writing enums by hand: a u32 discriminant followed by the fields of the variant.
+//! write_variant and read_variant do the discriminant, with read_variant checking that it
+//! is in range. The bufferable_enum macro writes the whole Bufferable impl for enums whose
+//! variants are units or tuples, so every hand written enum uses the same layout.
+//! An enum with a `#[bufferable(other)]` variant writes the length of the fields (u64) after
+//! the discriminant, so a version that does not know a variant reads it as the other variant,
+//! holding the discriminant and the field bytes, instead of failing the whole file.
+//! Fields added to the end of a variant later are skipped the same way.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! #[derive(Debug, PartialEq)]
+//! enum Shape{ Empty, Circle(f32), Rect(f32, f32) }
+//! bufferable_enum!{ Shape{
+//!     Empty = 0,
+//!     Circle(r: f32) = 1,
+//!     Rect(w: f32, h: f32) = 2,
+//! } }
+//! let mut buffer = Vec::new();
+//! Shape::Rect(1.0, 2.0).into_buffer(&mut buffer);
+//! Shape::Empty.into_buffer(&mut buffer);
+//! assert_eq!(vec![0,0,0,2, 0x3f,0x80,0,0, 0x40,0,0,0, 0,0,0,0], buffer);
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! assert_eq!(Some(Shape::Rect(1.0, 2.0)), Shape::from_buffer(&mut buffer));
+//! assert_eq!(Some(Shape::Empty), Shape::from_buffer(&mut buffer));
+//! // no variant 3
+//! assert_eq!(None, Shape::from_buffer(&mut ReadBuffer::from_raw(vec![0,0,0,3])));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, BufferError, Bufferable, ReadBuffer, Sink };
+
+/// Bytes the discriminant takes.
+pub const VARIANT_LEN: usize = 4;
+
+/// Write the discriminant of a variant.
+pub fn write_variant<S: Sink + ?Sized>(variant: u32, vec: &mut S){
+    variant.into_buffer(vec);
+}
+/// Read a discriminant, None if the buffer ends or it is not below count.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::enums::*;
+/// let mut buffer = ReadBuffer::from_raw(vec![0,0,0,1, 0,0,0,2]);
+/// assert_eq!(Some(1), read_variant(&mut buffer, 2));
+/// assert_eq!(None, read_variant(&mut buffer, 2));
+/// ```
+pub fn read_variant(buf: &mut ReadBuffer, count: u32) -> Option<u32>{
+    let variant = u32::from_buffer(buf)?;
+    if variant >= count{
+        return Option::None;
+    }
+    Option::Some(variant)
+}
+
+/// Write what f writes behind its length, for enums with an other variant.
+pub fn write_payload<S: Sink + ?Sized, F: FnOnce(&mut Buffer)>(vec: &mut S, f: F){
+    let mut payload = Vec::new();
+    f(&mut payload);
+    (payload.len() as u64).into_buffer(vec);
+    vec.write_bytes(&payload);
+}
+/// Read the length of a payload written by write_payload and give the position it ends at,
+/// None if the buffer is shorter.
+pub fn read_payload_end(buf: &mut ReadBuffer) -> Option<usize>{
+    check_payload_end(buf).ok()
+}
+/// read_payload_end for validating, UnexpectedEof if the buffer is shorter.
+pub fn check_payload_end(buf: &mut ReadBuffer) -> Result<usize, BufferError>{
+    let len = u64::from_buffer(buf).ok_or_else(|| buf.eof(8))?;
+    let available = buf.remaining();
+    let needed = usize::try_from(len).map_err(|_| BufferError::LengthOverflow)?;
+    if needed > available{
+        return Err(BufferError::UnexpectedEof{ needed, available });
+    }
+    Ok(buf.consumed() + needed)
+}
+/// Move past what is left of a payload ending at end, None if more than the payload was read.
+pub fn finish_payload(buf: &mut ReadBuffer, end: usize) -> Option<()>{
+    let left = end.checked_sub(buf.consumed())?;
+    buf.skip(left).ok()
+}
+
+/// Implement Bufferable for an enum with unit and tuple variants, see the enums module.
+/// Every variant is listed with its discriminant, tuple fields get a name and a type.
+/// Reading an unlisted discriminant gives None, unless the last variant is marked
+/// `#[bufferable(other)]`. That variant is declared as `Other(u32, ByteVec)` and gets the
+/// discriminant and the field bytes of variants it does not know, writing them back as they were.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// #[derive(Debug, PartialEq)]
+/// enum Old{ Empty, Circle(f32), Unknown(u32, ByteVec) }
+/// bufferable_enum!{ Old{
+///     Empty = 0,
+///     Circle(r: f32) = 1,
+///     #[bufferable(other)] Unknown,
+/// } }
+/// #[derive(Debug, PartialEq)]
+/// enum New{ Empty, Circle(f32), Rect(f32, f32), Unknown(u32, ByteVec) }
+/// bufferable_enum!{ New{
+///     Empty = 0,
+///     Circle(r: f32) = 1,
+///     Rect(w: f32, h: f32) = 2,
+///     #[bufferable(other)] Unknown,
+/// } }
+/// let bytes = encode(&vec![New::Rect(1.0, 2.0), New::Circle(3.0)]);
+/// let old = decode::<Vec<Old>>(&bytes).unwrap();
+/// assert_eq!(Old::Circle(3.0), old[1]);
+/// assert!(matches!(old[0], Old::Unknown(2, _)));
+/// assert_eq!(bytes, encode(&old));
+/// ```
+#[macro_export]
+macro_rules! bufferable_enum{
+    ($name:ident{ $( $variant:ident $( ( $( $field:ident : $ty:ty ),* ) )? = $tag:literal, )*
+        #[bufferable(other)] $other:ident $(,)? }) => {
+        impl $crate::Bufferable for $name{
+            const MIN_BUFFER_LEN: usize = $crate::enums::VARIANT_LEN + 8;
+
+            #[allow(unused_variables)]
+            fn into_buffer<S: $crate::Sink + ?Sized>(self, vec: &mut S){
+                match self{
+                    $( $name::$variant $( ( $( $field ),* ) )? => {
+                        $crate::enums::write_variant($tag, vec);
+                        $crate::enums::write_payload(vec, |payload|{
+                            $( $( $crate::Bufferable::into_buffer($field, payload); )* )?
+                        });
+                    }, )*
+                    $name::$other(tag, payload) => {
+                        $crate::enums::write_variant(tag, vec);
+                        $crate::Bufferable::into_buffer(payload, vec);
+                    },
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn copy_into_buffer<S: $crate::Sink + ?Sized>(&self, vec: &mut S){
+                match self{
+                    $( $name::$variant $( ( $( $field ),* ) )? => {
+                        $crate::enums::write_variant($tag, vec);
+                        $crate::enums::write_payload(vec, |payload|{
+                            $( $( $crate::Bufferable::copy_into_buffer($field, payload); )* )?
+                        });
+                    }, )*
+                    $name::$other(tag, payload) => {
+                        $crate::enums::write_variant(*tag, vec);
+                        $crate::Bufferable::copy_into_buffer(payload, vec);
+                    },
+                }
+            }
+
+            fn from_buffer(buf: &mut $crate::ReadBuffer) -> Option<Self>{
+                let variant = <u32 as $crate::Bufferable>::from_buffer(buf)?;
+                let end = $crate::enums::read_payload_end(buf)?;
+                $( if variant == $tag{
+                    let x = $name::$variant $( ( $( <$ty as $crate::Bufferable>::from_buffer(buf)? ),* ) )?;
+                    $crate::enums::finish_payload(buf, end)?;
+                    return Option::Some(x);
+                } )*
+                let len = end - buf.consumed();
+                buf.use_bytes(len)?;
+                Option::Some($name::$other(variant, $crate::ByteVec(buf.read_bytes(len)?.to_vec())))
+            }
+
+            fn validate(buf: &mut $crate::ReadBuffer) -> Result<(), $crate::BufferError>{
+                let variant = buf.read_checked::<u32>()?;
+                let end = $crate::enums::check_payload_end(buf)?;
+                $( if variant == $tag{
+                    $( $( <$ty as $crate::Bufferable>::validate(buf)?; )* )?
+                    return $crate::enums::finish_payload(buf, end).ok_or($crate::BufferError::Invalid);
+                } )*
+                let len = end - buf.consumed();
+                buf.use_bytes(len).ok_or($crate::BufferError::LengthOverflow)?;
+                buf.skip(len)
+            }
+
+            #[allow(unused_variables)]
+            fn buffer_len(&self) -> usize{
+                match self{
+                    $( $name::$variant $( ( $( $field ),* ) )? => {
+                        $crate::enums::VARIANT_LEN + 8 $( $( + $crate::Bufferable::buffer_len($field) )* )?
+                    }, )*
+                    $name::$other(_, payload) => $crate::enums::VARIANT_LEN + $crate::Bufferable::buffer_len(payload),
+                }
+            }
+        }
+    };
+    ($name:ident{ $( $variant:ident $( ( $( $field:ident : $ty:ty ),* ) )? = $tag:literal ),* $(,)? }) => {
+        impl $crate::Bufferable for $name{
+            const MIN_BUFFER_LEN: usize = $crate::enums::VARIANT_LEN;
+
+            #[allow(unused_variables)]
+            fn into_buffer<S: $crate::Sink + ?Sized>(self, vec: &mut S){
+                match self{
+                    $( $name::$variant $( ( $( $field ),* ) )? => {
+                        $crate::enums::write_variant($tag, vec);
+                        $( $( $crate::Bufferable::into_buffer($field, vec); )* )?
+                    }, )*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn copy_into_buffer<S: $crate::Sink + ?Sized>(&self, vec: &mut S){
+                match self{
+                    $( $name::$variant $( ( $( $field ),* ) )? => {
+                        $crate::enums::write_variant($tag, vec);
+                        $( $( $crate::Bufferable::copy_into_buffer($field, vec); )* )?
+                    }, )*
+                }
+            }
+
+            fn from_buffer(buf: &mut $crate::ReadBuffer) -> Option<Self>{
+                let variant = <u32 as $crate::Bufferable>::from_buffer(buf)?;
+                $( if variant == $tag{
+                    return Option::Some($name::$variant $( ( $( <$ty as $crate::Bufferable>::from_buffer(buf)? ),* ) )?);
+                } )*
+                Option::None
+            }
+
+            fn validate(buf: &mut $crate::ReadBuffer) -> Result<(), $crate::BufferError>{
+                let variant = buf.read_checked::<u32>()?;
+                $( if variant == $tag{
+                    $( $( <$ty as $crate::Bufferable>::validate(buf)?; )* )?
+                    return Ok(());
+                } )*
+                Err($crate::BufferError::InvalidDiscriminant)
+            }
+
+            #[allow(unused_variables)]
+            fn buffer_len(&self) -> usize{
+                match self{
+                    $( $name::$variant $( ( $( $field ),* ) )? => {
+                        $crate::enums::VARIANT_LEN $( $( + $crate::Bufferable::buffer_len($field) )* )?
+                    }, )*
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::enums::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Message{
+        Ping,
+        Text(String),
+        Move(u8, f64, Vec<u16>),
+        Quit,
+    }
+
+    bufferable_enum!{ Message{
+        Ping = 0,
+        Text(s: String) = 1,
+        Move(id: u8, speed: f64, path: Vec<u16>) = 5,
+        Quit = 6
+    } }
+
+    #[test]
+    fn test_enums(){
+        let xs = vec![Message::Ping, Message::Text(String::from("hi")),
+            Message::Move(3, 0.5, vec![1, 2]), Message::Quit];
+        let mut buffer = Vec::new();
+        xs.copy_into_buffer(&mut buffer);
+        assert_eq!(xs.buffer_len(), buffer.len());
+        xs[2].clone().into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(xs.clone()), Vec::<Message>::from_buffer(&mut buffer));
+        assert_eq!(Some(xs[2].clone()), Message::from_buffer(&mut buffer));
+        assert_eq!(4 + 1 + 8 + 8 + 4, xs[2].buffer_len());
+        // gaps between discriminants are rejected too
+        for tag in [2u32, 4, 7, u32::MAX].iter(){
+            let mut buffer = Vec::new();
+            write_variant(*tag, &mut buffer);
+            assert_eq!(None, Message::from_buffer(&mut ReadBuffer::from_raw(buffer.clone())));
+            assert_eq!(Err(BufferError::InvalidDiscriminant), validate::<Message>(&mut ReadBuffer::from_raw(buffer)));
+        }
+        assert_eq!(None, read_variant(&mut ReadBuffer::from_raw(vec![0, 0, 0, 7]), 7));
+        assert_eq!(Some(6), read_variant(&mut ReadBuffer::from_raw(vec![0, 0, 0, 6]), 7));
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event{
+        Start,
+        Score(u32),
+        Other(u32, ByteVec),
+    }
+
+    bufferable_enum!{ Event{
+        Start = 0,
+        Score(points: u32) = 1,
+        #[bufferable(other)] Other,
+    } }
+
+    #[test]
+    fn test_enum_other(){
+        let xs = vec![Event::Start, Event::Score(10)];
+        let bytes = encode(&xs);
+        assert_eq!(8 + 12 + 16, bytes.len());
+        assert_eq!(xs.buffer_len(), bytes.len());
+        assert_eq!(Some(xs), decode(&bytes));
+        // a newer version sends variant 2, and 1 with a field more
+        let mut newer = Vec::new();
+        write_variant(2, &mut newer);
+        write_payload(&mut newer, |p| { String::from("new").into_buffer(p); });
+        write_variant(1, &mut newer);
+        write_payload(&mut newer, |p| { 7u32.into_buffer(p); 1u8.into_buffer(p); });
+        let mut read = ReadBuffer::from_raw(newer.clone());
+        assert_eq!(Ok(()), validate::<Event>(&mut read));
+        assert_eq!(Ok(()), validate::<Event>(&mut read));
+        let mut read = ReadBuffer::from_raw(newer.clone());
+        let other = Event::from_buffer(&mut read).unwrap();
+        assert_eq!(Event::Other(2, ByteVec(encode(&String::from("new")))), other);
+        assert_eq!(Some(Event::Score(7)), Event::from_buffer(&mut read));
+        assert_eq!(0, read.remaining());
+        // written back as it came
+        assert_eq!(newer[..other.buffer_len()], encode(&other)[..]);
+        // fields that run past their length
+        let mut bad = Vec::new();
+        write_variant(1, &mut bad);
+        2u64.into_buffer(&mut bad);
+        7u32.into_buffer(&mut bad);
+        assert_eq!(None, decode::<Event>(&bad));
+        assert_eq!(Err(BufferError::Invalid), validate::<Event>(&mut ReadBuffer::from_raw(bad)));
+    }
+}
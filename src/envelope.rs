@@ -0,0 +1,274 @@
+//! A standard header for the messages of a protocol: what type the message is, which
+//! version of that type, flags for the protocol to use as it likes and the payload length.
+//! An Envelope is written as the type id (u32), the version (u32), the flags (u16),
+//! the payload length (u64) and the payload.
+//! Message types give their id with Registered and their version with Migrate, so an
+//! envelope of an older version is read through the migrations of the type.
+//! An EnvelopeRegistry picks the handler for a message by its type id.
+//! Message types added at runtime, with a registry::Codecs, go to the handler given to with_codecs.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::envelope::*;
+//! use bin_buffer::registry::Registered;
+//! #[derive(Debug, PartialEq)]
+//! struct Ping(u32);
+//! impl Bufferable for Ping{
+//!     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.0.into_buffer(vec); }
+//!     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ self.0.copy_into_buffer(vec); }
+//!     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{ Some(Ping(u32::from_buffer(buf)?)) }
+//! }
+//! impl Registered for Ping{ const TYPE_ID: u32 = 1; }
+//! impl Migrate for Ping{ const VERSION: u32 = 1; }
+//!
+//! let bytes = encode(&Envelope::wrap(&Ping(5), 0));
+//! assert_eq!(4 + 4 + 2 + 8 + 4, bytes.len());
+//! let mut registry = EnvelopeRegistry::<String>::new();
+//! registry.register(|ping: Ping| format!("ping {}", ping.0));
+//! let mut read = ReadBuffer::from_raw(bytes);
+//! assert_eq!(Some(String::from("ping 5")), registry.decode(&mut read));
+//! ```
+use std::collections::HashMap;
+use crate::{ Buffer, Bufferable, ByteVec, Migrate, ReadBuffer, Sink };
+use crate::registry::{ Codecs, Registered };
+use crate::schema::Value;
+
+/// Bytes in front of the payload.
+pub const HEADER_LEN: usize = 18;
+
+/// A message with its header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Envelope{
+    /// What type the payload is.
+    pub type_id: u32,
+    /// Which version of that type.
+    pub version: u32,
+    /// Free for the protocol to use.
+    pub flags: u16,
+    /// The encoded message.
+    pub payload: Buffer,
+}
+
+impl Envelope{
+    /// An envelope holding x, with its type id and current version.
+    pub fn wrap<T: Migrate + Registered>(x: &T, flags: u16) -> Self{
+        let mut payload = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut payload);
+        Self{ type_id: T::TYPE_ID, version: T::VERSION, flags, payload }
+    }
+    /// Whether the payload is a T, of any version.
+    pub fn is<T: Registered>(&self) -> bool{
+        self.type_id == T::TYPE_ID
+    }
+    /// Whether the flags in mask are all set.
+    pub fn has_flags(&self, mask: u16) -> bool{
+        self.flags & mask == mask
+    }
+    /// The payload as a T, going through T::migrations for older versions.
+    /// None if it is another type, a version without a migration,
+    /// or does not decode to exactly the payload.
+    pub fn open<T: Migrate + Registered>(&self) -> Option<T>{
+        if !self.is::<T>(){
+            return Option::None;
+        }
+        let mut buf = ReadBuffer::borrowed(&self.payload, Default::default());
+        let x = if self.version == T::VERSION{
+            T::from_buffer(&mut buf)?
+        } else {
+            T::migrations().read(self.version, &mut buf)?
+        };
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// The payload as a Value, decoded by the codec for the type id.
+    /// None if there is no codec for it or it does not decode to exactly the payload.
+    pub fn open_with(&self, codecs: &Codecs) -> Option<Value>{
+        let mut buf = ReadBuffer::borrowed(&self.payload, Default::default());
+        let x = codecs.decode(self.type_id, &mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+}
+
+impl Bufferable for Envelope{
+    const MIN_BUFFER_LEN: usize = HEADER_LEN;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.reserve(HEADER_LEN + self.payload.len());
+        self.type_id.into_buffer(vec);
+        self.version.into_buffer(vec);
+        self.flags.into_buffer(vec);
+        (self.payload.len() as u64).into_buffer(vec);
+        vec.write_bytes(&self.payload);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let type_id = u32::from_buffer(buf)?;
+        let version = u32::from_buffer(buf)?;
+        let flags = u16::from_buffer(buf)?;
+        let payload = ByteVec::from_buffer(buf)?.0;
+        Option::Some(Self{ type_id, version, flags, payload })
+    }
+
+    fn buffer_len(&self) -> usize{
+        HEADER_LEN + self.payload.len()
+    }
+}
+
+type Handler<R> = Box<dyn Fn(&Envelope) -> Option<R>>;
+type CodecHandler<R> = Box<dyn Fn(&Envelope, Value) -> R>;
+
+/// Handlers for message types by their id, each turning its message into an R.
+pub struct EnvelopeRegistry<R>{
+    handlers: HashMap<u32, Handler<R>>,
+    codecs: Option<(Codecs, CodecHandler<R>)>,
+}
+
+impl<R> Default for EnvelopeRegistry<R>{
+    fn default() -> Self{
+        Self{ handlers: HashMap::new(), codecs: Option::None }
+    }
+}
+
+impl<R: 'static> EnvelopeRegistry<R>{
+    /// A registry without handlers.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Handle messages of type T with handle.
+    /// Panics if T::TYPE_ID already has a handler.
+    pub fn register<T, F>(&mut self, handle: F)
+        where T: Migrate + Registered, F: Fn(T) -> R + 'static
+    {
+        let handler: Handler<R> = Box::new(move |envelope| envelope.open::<T>().map(&handle));
+        assert!(self.handlers.insert(T::TYPE_ID, handler).is_none(),
+            "type id {} is registered twice", T::TYPE_ID);
+    }
+    /// Hand messages of the types in codecs that have no handler of their own to handle,
+    /// decoded to a Value by their codec.
+    pub fn with_codecs<F>(mut self, codecs: Codecs, handle: F) -> Self
+        where F: Fn(&Envelope, Value) -> R + 'static
+    {
+        self.codecs = Option::Some((codecs, Box::new(handle)));
+        self
+    }
+    /// Whether there is a handler or codec for type id.
+    pub fn contains(&self, type_id: u32) -> bool{
+        self.handlers.contains_key(&type_id)
+            || self.codecs.as_ref().is_some_and(|(codecs, _)| codecs.contains(type_id))
+    }
+    /// Give the message in envelope to the handler of its type.
+    /// None if there is no handler or the message does not open.
+    pub fn dispatch(&self, envelope: &Envelope) -> Option<R>{
+        if let Option::Some(handler) = self.handlers.get(&envelope.type_id){
+            return handler(envelope);
+        }
+        let (codecs, handle) = self.codecs.as_ref()?;
+        Option::Some(handle(envelope, envelope.open_with(codecs)?))
+    }
+    /// Read an envelope and dispatch it.
+    pub fn decode(&self, buf: &mut ReadBuffer) -> Option<R>{
+        self.dispatch(&Envelope::from_buffer(buf)?)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::envelope::*;
+    use crate::registry::{ Codecs, Registered };
+use crate::schema::Value;
+
+    #[derive(Debug, PartialEq)]
+    enum Msg{ Chat(String), Move(u16, u16) }
+
+    #[derive(Debug, PartialEq)]
+    struct Chat{ from: String, text: String }
+    #[derive(Debug, PartialEq)]
+    struct Move(u16, u16);
+
+    impl Bufferable for Chat{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.copy_into_buffer(vec); }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+            self.from.copy_into_buffer(vec);
+            self.text.copy_into_buffer(vec);
+        }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+            Some(Chat{ from: String::from_buffer(buf)?, text: String::from_buffer(buf)? })
+        }
+    }
+    impl Bufferable for Move{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.copy_into_buffer(vec); }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ (self.0, self.1).into_buffer(vec); }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+            let (x, y) = <(u16, u16)>::from_buffer(buf)?;
+            Some(Move(x, y))
+        }
+    }
+    impl Registered for Chat{ const TYPE_ID: u32 = 7; }
+    impl Registered for Move{ const TYPE_ID: u32 = 8; }
+    // version 1 of Chat had only the text
+    impl Migrate for Chat{
+        const VERSION: u32 = 2;
+        fn migrations() -> Migrations<Self>{
+            Migrations::new().upgrade_from(1, |text: String| Chat{ from: String::new(), text })
+        }
+    }
+    impl Migrate for Move{ const VERSION: u32 = 1; }
+
+    #[test]
+    fn test_envelope(){
+        let mut registry = EnvelopeRegistry::new();
+        registry.register(|c: Chat| Msg::Chat(c.text));
+        registry.register(|m: Move| Msg::Move(m.0, m.1));
+        assert!(registry.contains(7) && !registry.contains(9));
+        let mut bytes = Vec::new();
+        Envelope::wrap(&Chat{ from: String::from("a"), text: String::from("hi") }, 3).into_buffer(&mut bytes);
+        Envelope::wrap(&Move(1, 2), 0).into_buffer(&mut bytes);
+        Envelope{ type_id: 7, version: 1, flags: 0, payload: encode(&String::from("old")) }.into_buffer(&mut bytes);
+        assert_eq!(&[0, 0, 0, 7, 0, 0, 0, 2, 0, 3], &bytes[..10]);
+        let mut read = ReadBuffer::from_raw(bytes.clone());
+        let first = Envelope::from_buffer(&mut read).unwrap();
+        assert!(first.has_flags(1) && !first.has_flags(4));
+        assert!(first.is::<Chat>() && !first.is::<Move>());
+        assert_eq!(None, first.open::<Move>());
+        assert_eq!(Some(Msg::Chat(String::from("hi"))), registry.dispatch(&first));
+        assert_eq!(Some(Msg::Move(1, 2)), registry.decode(&mut read));
+        assert_eq!(Some(Msg::Chat(String::from("old"))), registry.decode(&mut read));
+        assert_eq!(None, registry.decode(&mut read));
+        // unknown type, unknown version and a payload with bytes left over
+        let unknown = Envelope{ type_id: 9, version: 1, flags: 0, payload: Vec::new() };
+        assert_eq!(None, registry.dispatch(&unknown));
+        let future = Envelope{ type_id: 8, version: 2, flags: 0, payload: encode(&(1u16, 2u16)) };
+        assert_eq!(None, registry.dispatch(&future));
+        let long = Envelope{ type_id: 8, version: 1, flags: 0, payload: encode(&(1u16, 2u16, 3u8)) };
+        assert_eq!(None, registry.dispatch(&long));
+        assert_eq!(HEADER_LEN + 4, Envelope::wrap(&Move(0, 0), 0).buffer_len());
+    }
+
+    #[test]
+    fn test_envelope_codecs(){
+        // a plugin adds type 20, a string, a codec for 8 does not replace the compiled in handler
+        let mut codecs = Codecs::new();
+        codecs.register(20, |buf| String::from_buffer(buf).map(Value::Str), |_, _| false);
+        codecs.register(8, |_| None, |_, _| false);
+        let mut registry = EnvelopeRegistry::new()
+            .with_codecs(codecs, |envelope, value| format!("{} {:?}", envelope.version, value));
+        registry.register(|m: Move| format!("move {}", m.0));
+        assert!(registry.contains(20) && registry.contains(8) && !registry.contains(7));
+        let plugin = Envelope{ type_id: 20, version: 3, flags: 0, payload: encode(&String::from("x")) };
+        assert_eq!(Some(String::from("3 Str(\"x\")")), registry.dispatch(&plugin));
+        assert_eq!(Some(String::from("move 4")), registry.dispatch(&Envelope::wrap(&Move(4, 0), 0)));
+        let long = Envelope{ payload: encode(&(String::from("x"), 1u8)), ..plugin };
+        assert_eq!(None, registry.dispatch(&long));
+        assert_eq!(None, registry.dispatch(&Envelope{ type_id: 7, ..long }));
+    }
+}
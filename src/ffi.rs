@@ -0,0 +1,254 @@
+//! C functions for writing and reading buffers, enabled with the `ffi` feature.
+//! include/bin_buffer.h declares them for C and C++, cbindgen.toml regenerates it.
+//! Build a library to link against with
+//! `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`).
+//!
+//! A writer is a Buffer made with bin_buffer_new and freed with bin_buffer_free.
+//! A reader is a ReadBuffer made with bin_read_buffer_new and freed with bin_read_buffer_free.
+//! Reading functions return false, and leave out untouched, when the value is not there.
+use crate::{ Buffer, Bufferable, ReadBuffer };
+
+/// Create an empty buffer to write to.
+#[no_mangle]
+pub extern "C" fn bin_buffer_new() -> *mut Buffer{
+    Box::into_raw(Box::new(Vec::new()))
+}
+/// Free a buffer made with bin_buffer_new. Does nothing with null.
+/// # Safety
+/// buf must be null or made by bin_buffer_new and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_free(buf: *mut Buffer){
+    if !buf.is_null(){
+        drop(Box::from_raw(buf));
+    }
+}
+/// Pointer to the bytes written so far. Invalid after the next write to or free of buf.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_data(buf: *const Buffer) -> *const u8{
+    (*buf).as_ptr()
+}
+/// How many bytes have been written.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_len(buf: *const Buffer) -> usize{
+    (*buf).len()
+}
+/// Write a String: len bytes of UTF-8 at data.
+/// Returns false, writing nothing, if they are not valid UTF-8.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new and data must point to len readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_str(buf: *mut Buffer, data: *const u8, len: usize) -> bool{
+    match std::str::from_utf8(bytes(data, len)){
+        Ok(string) => {
+            (string.len() as u64).into_buffer(&mut *buf);
+            (*buf).extend_from_slice(string.as_bytes());
+            true
+        },
+        Err(_) => false,
+    }
+}
+/// Write a ByteVec: len bytes at data.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new and data must point to len readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_bytes(buf: *mut Buffer, data: *const u8, len: usize){
+    (len as u64).into_buffer(&mut *buf);
+    (*buf).extend_from_slice(bytes(data, len));
+}
+/// Create a reader over a copy of len bytes at data.
+/// # Safety
+/// data must point to len readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_buffer_new(data: *const u8, len: usize) -> *mut ReadBuffer<'static>{
+    Box::into_raw(Box::new(ReadBuffer::from_raw(bytes(data, len).to_vec())))
+}
+/// Free a reader made with bin_read_buffer_new. Does nothing with null.
+/// # Safety
+/// buf must be null or made by bin_read_buffer_new and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_buffer_free(buf: *mut ReadBuffer){
+    if !buf.is_null(){
+        drop(Box::from_raw(buf));
+    }
+}
+/// How many bytes are left to read.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_buffer_remaining(buf: *const ReadBuffer) -> usize{
+    (*buf).remaining()
+}
+/// Read a String without copying it. On success *data and *len describe its UTF-8 bytes,
+/// which stay valid until buf is freed. They are not null terminated.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new, data and len must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_str(buf: *mut ReadBuffer, data: *mut *const u8, len: *mut usize) -> bool{
+    match (*buf).read_str(){
+        Some(string) => {
+            *data = string.as_ptr();
+            *len = string.len();
+            true
+        },
+        None => false,
+    }
+}
+/// Read a ByteVec without copying it, like bin_read_str.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new, data and len must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_bytes(buf: *mut ReadBuffer, data: *mut *const u8, len: *mut usize) -> bool{
+    match (*buf).read_blob(){
+        Some(blob) => {
+            *data = blob.as_ptr();
+            *len = blob.len();
+            true
+        },
+        None => false,
+    }
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8]{
+    if len == 0 { &[] } else { std::slice::from_raw_parts(data, len) }
+}
+
+unsafe fn read_into<T: Bufferable>(buf: *mut ReadBuffer, out: *mut T) -> bool{
+    match T::from_buffer(&mut *buf){
+        Some(x) => {
+            *out = x;
+            true
+        },
+        None => false,
+    }
+}
+
+/// Write a u8.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_u8(buf: *mut Buffer, x: u8){
+    x.into_buffer(&mut *buf);
+}
+/// Read a u8 into *out.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new and out must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_u8(buf: *mut ReadBuffer, out: *mut u8) -> bool{
+    read_into(buf, out)
+}
+/// Write a u16.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_u16(buf: *mut Buffer, x: u16){
+    x.into_buffer(&mut *buf);
+}
+/// Read a u16 into *out.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new and out must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_u16(buf: *mut ReadBuffer, out: *mut u16) -> bool{
+    read_into(buf, out)
+}
+/// Write a u32.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_u32(buf: *mut Buffer, x: u32){
+    x.into_buffer(&mut *buf);
+}
+/// Read a u32 into *out.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new and out must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_u32(buf: *mut ReadBuffer, out: *mut u32) -> bool{
+    read_into(buf, out)
+}
+/// Write a u64.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_u64(buf: *mut Buffer, x: u64){
+    x.into_buffer(&mut *buf);
+}
+/// Read a u64 into *out.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new and out must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_u64(buf: *mut ReadBuffer, out: *mut u64) -> bool{
+    read_into(buf, out)
+}
+/// Write a f32.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_f32(buf: *mut Buffer, x: f32){
+    x.into_buffer(&mut *buf);
+}
+/// Read a f32 into *out.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new and out must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_f32(buf: *mut ReadBuffer, out: *mut f32) -> bool{
+    read_into(buf, out)
+}
+/// Write a f64.
+/// # Safety
+/// buf must be a live buffer made by bin_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn bin_buffer_push_f64(buf: *mut Buffer, x: f64){
+    x.into_buffer(&mut *buf);
+}
+/// Read a f64 into *out.
+/// # Safety
+/// buf must be a live reader made by bin_read_buffer_new and out must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn bin_read_f64(buf: *mut ReadBuffer, out: *mut f64) -> bool{
+    read_into(buf, out)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::ffi::*;
+
+    #[test]
+    fn test_ffi(){
+        unsafe{
+            let buf = bin_buffer_new();
+            bin_buffer_push_u32(buf, 7);
+            bin_buffer_push_f64(buf, 0.5);
+            let s = "hi";
+            assert!(bin_buffer_push_str(buf, s.as_ptr(), s.len()));
+            assert!(!bin_buffer_push_str(buf, [0xffu8].as_ptr(), 1));
+            bin_buffer_push_bytes(buf, std::ptr::null(), 0);
+            let written = std::slice::from_raw_parts(bin_buffer_data(buf), bin_buffer_len(buf)).to_vec();
+            bin_buffer_free(buf);
+
+            let mut expected = Vec::new();
+            (7u32, 0.5f64, String::from("hi"), ByteVec(vec![])).into_buffer(&mut expected);
+            assert_eq!(expected, written);
+
+            let read = bin_read_buffer_new(written.as_ptr(), written.len());
+            let mut x = 0u32;
+            let mut y = 0.0f64;
+            assert!(bin_read_u32(read, &mut x));
+            assert!(bin_read_f64(read, &mut y));
+            assert_eq!((7, 0.5), (x, y));
+            let mut data = std::ptr::null();
+            let mut len = 0;
+            assert!(bin_read_str(read, &mut data, &mut len));
+            assert_eq!(b"hi", std::slice::from_raw_parts(data, len));
+            assert!(bin_read_bytes(read, &mut data, &mut len));
+            assert_eq!(0, len);
+            assert_eq!(0, bin_read_buffer_remaining(read));
+            assert!(!bin_read_u8(read, &mut 0));
+            bin_read_buffer_free(read);
+            bin_read_buffer_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,612 @@
+//! Other ways to write a single field, for formats that do not write everything the way
+//! Bufferable does. bufferable_struct takes one per field after `as`:
+//! `id: u32 as Varint` writes a varint, `delta: i32 as Zigzag` a zigzag encoded one,
+//! `size: u64 as LittleEndian` little endian bytes,
+//! `name: String as Prefixed<u16>` a u16 length instead of a u64 one, `Prefixed<varint::VarInt>` a varint one, and
+//! `x: f64 as Cast<f32>` an f32. Fields without one are written as usual.
+//! Strings can also be `NullTerminated`, `FixedWidth<N>` or `Utf16`, as legacy records mix them.
+//! F32Of, and F16Of with the `half` feature, store a float at lower precision outside a struct.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::fields::*;
+//! #[derive(Debug, PartialEq)]
+//! struct Legacy{ id: u32, name: String, size: u64, x: f64 }
+//! bufferable_struct!{ Legacy{
+//!     id: u32 as Varint,
+//!     name: String as Prefixed<u16>,
+//!     size: u64 as LittleEndian,
+//!     x: f64 as Cast<f32>,
+//! } }
+//! let legacy = Legacy{ id: 300, name: String::from("ab"), size: 1, x: 0.5 };
+//! let bytes = encode(&legacy);
+//! assert_eq!(vec![0xac, 0x02, 0, 2, b'a', b'b', 1, 0, 0, 0, 0, 0, 0, 0, 0x3f, 0, 0, 0], bytes);
+//! assert_eq!(Some(legacy), decode(&bytes));
+//! ```
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use crate::{ BufferError, Bufferable, ByteVec, FixedStr, ReadBuffer, Sink, WideString };
+use crate::varint;
+
+/// A way to write and read a T.
+pub trait FieldEncoding<T>{
+    /// Fewest bytes a T takes.
+    const MIN_LEN: usize;
+    /// Write x.
+    fn write<S: Sink + ?Sized>(x: &T, vec: &mut S);
+    /// Read a T, None if it does not decode.
+    fn read(buf: &mut ReadBuffer) -> Option<T>;
+    /// Bytes x takes.
+    fn len(x: &T) -> usize;
+    /// Write x, taking it. Calls write by default.
+    fn write_owned<S: Sink + ?Sized>(x: T, vec: &mut S){
+        Self::write(&x, vec);
+    }
+    /// Check that a T comes next. Reads one by default.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        Self::read(buf).map(drop).ok_or(BufferError::Invalid)
+    }
+}
+
+/// The Bufferable encoding, what fields without an encoding use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Plain;
+
+impl<T: Bufferable> FieldEncoding<T> for Plain{
+    const MIN_LEN: usize = T::MIN_BUFFER_LEN;
+
+    fn write<S: Sink + ?Sized>(x: &T, vec: &mut S){
+        x.copy_into_buffer(vec);
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<T>{
+        T::from_buffer(buf)
+    }
+
+    fn len(x: &T) -> usize{
+        x.buffer_len()
+    }
+
+    fn write_owned<S: Sink + ?Sized>(x: T, vec: &mut S){
+        x.into_buffer(vec);
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        T::validate(buf)
+    }
+}
+
+/// Unsigned integers as LEB128 varints, see the varint module.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Varint;
+
+/// Signed integers zigzag encoded as LEB128 varints, so small negative values stay small too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Zigzag;
+
+/// Numbers as little endian bytes instead of big endian.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LittleEndian;
+
+macro_rules! varint_encoding{
+    ($($t:ty),*) => {
+        $(
+            impl FieldEncoding<$t> for Varint{
+                const MIN_LEN: usize = 1;
+
+                fn write<S: Sink + ?Sized>(x: &$t, vec: &mut S){
+                    varint::write_u64(*x as u64, vec);
+                }
+
+                fn read(buf: &mut ReadBuffer) -> Option<$t>{
+                    <$t>::try_from(varint::read_u64(buf)?).ok()
+                }
+
+                fn len(x: &$t) -> usize{
+                    varint::len_u64(*x as u64)
+                }
+            }
+        )*
+    };
+}
+
+varint_encoding!(u8, u16, u32, u64, usize);
+
+macro_rules! zigzag_encoding{
+    ($($t:ty),*) => {
+        $(
+            impl FieldEncoding<$t> for Zigzag{
+                const MIN_LEN: usize = 1;
+
+                fn write<S: Sink + ?Sized>(x: &$t, vec: &mut S){
+                    varint::write_u64(varint::zigzag(*x as i64), vec);
+                }
+
+                fn read(buf: &mut ReadBuffer) -> Option<$t>{
+                    <$t>::try_from(varint::unzigzag(varint::read_u64(buf)?)).ok()
+                }
+
+                fn len(x: &$t) -> usize{
+                    varint::len_u64(varint::zigzag(*x as i64))
+                }
+            }
+        )*
+    };
+}
+
+zigzag_encoding!(i8, i16, i32, i64, isize);
+
+macro_rules! little_endian_encoding{
+    ($($t:ty),*) => {
+        $(
+            impl FieldEncoding<$t> for LittleEndian{
+                const MIN_LEN: usize = std::mem::size_of::<$t>();
+
+                fn write<S: Sink + ?Sized>(x: &$t, vec: &mut S){
+                    vec.write_bytes(&x.to_le_bytes());
+                }
+
+                fn read(buf: &mut ReadBuffer) -> Option<$t>{
+                    let bytes = buf.read_bytes(std::mem::size_of::<$t>())?;
+                    Option::Some(<$t>::from_le_bytes(<[u8; std::mem::size_of::<$t>()]>::try_from(bytes).ok()?))
+                }
+
+                fn len(_: &$t) -> usize{
+                    std::mem::size_of::<$t>()
+                }
+            }
+        )*
+    };
+}
+
+little_endian_encoding!(u16, u32, u64, f32, f64);
+
+/// A number type that can be the length in front of a Prefixed value.
+pub trait PrefixLen: Bufferable{
+    /// The length as this type, None if it does not fit.
+    fn from_len(len: usize) -> Option<Self>;
+    /// The length this is.
+    fn to_len(self) -> Option<usize>;
+}
+
+macro_rules! prefix_len{
+    ($($t:ty),*) => {
+        $(
+            impl PrefixLen for $t{
+                fn from_len(len: usize) -> Option<Self>{
+                    <$t>::try_from(len).ok()
+                }
+
+                fn to_len(self) -> Option<usize>{
+                    usize::try_from(self).ok()
+                }
+            }
+        )*
+    };
+}
+
+prefix_len!(u8, u16, u32, u64);
+
+impl PrefixLen for varint::VarInt{
+    fn from_len(len: usize) -> Option<Self>{
+        Option::Some(varint::VarInt(len as u64))
+    }
+
+    fn to_len(self) -> Option<usize>{
+        usize::try_from(self.0).ok()
+    }
+}
+
+/// Strings, byte vectors and vectors with a length prefix of type P instead of a u64.
+/// Writing panics if the length does not fit in a P.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Prefixed<P>(PhantomData<P>);
+
+fn write_len<P: PrefixLen, S: Sink + ?Sized>(len: usize, vec: &mut S){
+    P::from_len(len).expect("length does not fit the prefix").into_buffer(vec);
+}
+
+fn prefix_len<P: PrefixLen>(len: usize) -> usize{
+    P::from_len(len).map_or(P::MIN_BUFFER_LEN, |p| p.buffer_len())
+}
+
+impl<P: PrefixLen> FieldEncoding<String> for Prefixed<P>{
+    const MIN_LEN: usize = P::MIN_BUFFER_LEN;
+
+    fn write<S: Sink + ?Sized>(x: &String, vec: &mut S){
+        write_len::<P, S>(x.len(), vec);
+        vec.write_bytes(x.as_bytes());
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<String>{
+        let len = P::from_buffer(buf)?.to_len()?;
+        if len > buf.limits.max_string_len{
+            return Option::None;
+        }
+        let string = std::str::from_utf8(buf.read_bytes(len)?).ok()?.to_owned();
+        buf.use_bytes(len)?;
+        Option::Some(string)
+    }
+
+    fn len(x: &String) -> usize{
+        prefix_len::<P>(x.len()) + x.len()
+    }
+}
+
+impl<P: PrefixLen> FieldEncoding<ByteVec> for Prefixed<P>{
+    const MIN_LEN: usize = P::MIN_BUFFER_LEN;
+
+    fn write<S: Sink + ?Sized>(x: &ByteVec, vec: &mut S){
+        write_len::<P, S>(x.0.len(), vec);
+        vec.write_bytes(&x.0);
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<ByteVec>{
+        let len = P::from_buffer(buf)?.to_len()?;
+        if len > buf.limits.max_elements{
+            return Option::None;
+        }
+        let bytes = buf.read_bytes(len)?.to_vec();
+        buf.use_bytes(len)?;
+        Option::Some(ByteVec(bytes))
+    }
+
+    fn len(x: &ByteVec) -> usize{
+        prefix_len::<P>(x.0.len()) + x.0.len()
+    }
+}
+
+impl<P: PrefixLen, T: Bufferable> FieldEncoding<Vec<T>> for Prefixed<P>{
+    const MIN_LEN: usize = P::MIN_BUFFER_LEN;
+
+    fn write<S: Sink + ?Sized>(x: &Vec<T>, vec: &mut S){
+        write_len::<P, S>(x.len(), vec);
+        T::copy_slice_into_buffer(x, vec);
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<Vec<T>>{
+        let len = P::from_buffer(buf)?.to_len()?;
+        if len > buf.limits.max_elements{
+            return Option::None;
+        }
+        buf.nested(|buf|{
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            T::vec_from_buffer(len, buf)
+        })
+    }
+
+    fn len(x: &Vec<T>) -> usize{
+        prefix_len::<P>(x.len()) + T::slice_buffer_len(x)
+    }
+}
+
+/// A String as its UTF-8 bytes and a 0, without a length, like a CString.
+/// Writing panics for a string that has a 0 in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NullTerminated;
+
+impl FieldEncoding<String> for NullTerminated{
+    const MIN_LEN: usize = 1;
+
+    fn write<S: Sink + ?Sized>(x: &String, vec: &mut S){
+        assert!(!x.contains('\0'), "null terminated string has a 0 in it");
+        vec.write_bytes(x.as_bytes());
+        vec.write_bytes(&[0]);
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<String>{
+        let string = buf.read_cstr()?.to_str().ok()?.to_owned();
+        buf.use_bytes(string.len())?;
+        Option::Some(string)
+    }
+
+    fn len(x: &String) -> usize{
+        x.len() + 1
+    }
+}
+
+/// A String as exactly N bytes padded with zeros, like a FixedStr.
+/// Writing panics for a string that does not fit or has a 0 in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FixedWidth<const N: usize>;
+
+impl<const N: usize> FieldEncoding<String> for FixedWidth<N>{
+    const MIN_LEN: usize = N;
+
+    fn write<S: Sink + ?Sized>(x: &String, vec: &mut S){
+        FixedStr::<N>::try_new(x).expect("string does not fit the fixed width").into_buffer(vec);
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<String>{
+        FixedStr::<N>::from_buffer(buf).map(String::from)
+    }
+
+    fn len(_: &String) -> usize{
+        N
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        FixedStr::<N>::validate(buf)
+    }
+}
+
+/// A String as UTF-16 little endian after a u64 with the number of code units, like a WideString.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Utf16;
+
+impl FieldEncoding<String> for Utf16{
+    const MIN_LEN: usize = 8;
+
+    fn write<S: Sink + ?Sized>(x: &String, vec: &mut S){
+        (x.encode_utf16().count() as u64).into_buffer(vec);
+        for unit in x.encode_utf16(){
+            vec.write_bytes(&unit.to_le_bytes());
+        }
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<String>{
+        WideString::from_buffer(buf).map(String::from)
+    }
+
+    fn len(x: &String) -> usize{
+        8 + 2 * x.encode_utf16().count()
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        WideString::validate(buf)
+    }
+}
+
+/// A type that can be written as a W: a float as another float, an integer as a wider or
+/// narrower one.
+pub trait Castable<W>: Sized{
+    /// Self as a W. Panics for an integer that does not fit.
+    fn cast(&self) -> W;
+    /// A W back as Self, None for an integer that does not fit.
+    fn uncast(w: W) -> Option<Self>;
+}
+
+impl Castable<f32> for f64{
+    fn cast(&self) -> f32{
+        *self as f32
+    }
+
+    fn uncast(w: f32) -> Option<Self>{
+        Option::Some(f64::from(w))
+    }
+}
+
+impl Castable<f64> for f32{
+    fn cast(&self) -> f64{
+        f64::from(*self)
+    }
+
+    fn uncast(w: f64) -> Option<Self>{
+        Option::Some(w as f32)
+    }
+}
+
+macro_rules! int_casts{
+    ($($t:ty => $($w:ty),*);*) => {
+        $($(
+            impl Castable<$w> for $t{
+                fn cast(&self) -> $w{
+                    <$w>::try_from(*self).expect("integer does not fit the type it is written as")
+                }
+
+                fn uncast(w: $w) -> Option<Self>{
+                    <$t>::try_from(w).ok()
+                }
+            }
+        )*)*
+    };
+}
+
+int_casts!(u8 => u16, u32, u64; u16 => u8, u32, u64; u32 => u8, u16, u64; u64 => u8, u16, u32; usize => u8, u16, u32);
+
+/// A value written as a W, see Castable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cast<W>(PhantomData<W>);
+
+impl<T: Castable<W>, W: Bufferable> FieldEncoding<T> for Cast<W>{
+    const MIN_LEN: usize = W::MIN_BUFFER_LEN;
+
+    fn write<S: Sink + ?Sized>(x: &T, vec: &mut S){
+        x.cast().into_buffer(vec);
+    }
+
+    fn read(buf: &mut ReadBuffer) -> Option<T>{
+        T::uncast(W::from_buffer(buf)?)
+    }
+
+    fn len(x: &T) -> usize{
+        x.cast().buffer_len()
+    }
+}
+
+/// The bitmap bufferable_struct writes in front of a struct with optional fields:
+/// one bit per optional field that is set when it is present, the first field in the highest
+/// bit of the first byte, padded with zero bits to whole bytes.
+/// Only present fields are written after it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Presence{
+    bytes: Vec<u8>,
+    next: usize,
+    count: usize,
+}
+
+impl Presence{
+    /// Bytes the bitmap of count optional fields takes.
+    pub const fn byte_len(count: usize) -> usize{
+        count.div_ceil(8)
+    }
+    /// Write the bitmap of which fields are present.
+    pub fn write<S: Sink + ?Sized>(present: &[bool], vec: &mut S){
+        for chunk in present.chunks(8){
+            let byte = chunk.iter().enumerate()
+                .fold(0u8, |byte, (i, &set)| byte | ((set as u8) << (7 - i)));
+            vec.write_bytes(&[byte]);
+        }
+    }
+    /// Read the bitmap of count fields. Padding bits have to be zero.
+    pub fn read(buf: &mut ReadBuffer, count: usize) -> Result<Self, BufferError>{
+        let bytes = buf.check_bytes(Self::byte_len(count))?;
+        let used = count % 8;
+        if used != 0 && bytes[bytes.len() - 1] & (0xff >> used) != 0{
+            return Err(BufferError::Invalid);
+        }
+        Ok(Self{ bytes: bytes.to_vec(), next: 0, count })
+    }
+}
+
+/// Whether each field is present, in order.
+impl Iterator for Presence{
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool>{
+        if self.next == self.count{
+            return Option::None;
+        }
+        let set = self.bytes[self.next / 8] & (0x80 >> (self.next % 8)) != 0;
+        self.next += 1;
+        Option::Some(set)
+    }
+}
+
+/// An f64, or other Castable type, that is written as an f32, so it is stored at lower precision
+/// without changing its type in memory. Inside a bufferable_struct `x: f64 as Cast<f32>`
+/// does the same to a field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct F32Of<T>(pub T);
+
+impl<T: Castable<f32>> Bufferable for F32Of<T>{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.0.cast().into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.0.cast().into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        T::uncast(f32::from_buffer(buf)?).map(F32Of)
+    }
+
+    fn buffer_len(&self) -> usize{
+        4
+    }
+}
+
+/// An f32 or f64 that is written as a half::f16, like F32Of, with the `half` feature.
+/// Inside a bufferable_struct that is `x: f64 as Cast<half::f16>`.
+#[cfg(feature = "half")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct F16Of<T>(pub T);
+
+#[cfg(feature = "half")]
+impl<T: Castable<half::f16>> Bufferable for F16Of<T>{
+    const MIN_BUFFER_LEN: usize = 2;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.0.cast().into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.0.cast().into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        T::uncast(half::f16::from_buffer(buf)?).map(F16Of)
+    }
+
+    fn buffer_len(&self) -> usize{
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::fields::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Record<T>{ count: u64, tags: Vec<T>, blob: ByteVec, small: u64, temp: f32, le: u16 }
+    bufferable_struct!{ Record<T>{
+        count: u64 as Varint,
+        tags: Vec<T> as Prefixed<u8>,
+        blob: ByteVec as Prefixed<u32>,
+        small: u64 as Cast<u8>,
+        temp: f32 as Cast<f64>,
+        le: u16 as LittleEndian,
+    } }
+
+    #[derive(Debug, PartialEq)]
+    struct Legacy{ name: String, code: String, title: String, note: String }
+    bufferable_struct!{ Legacy{
+        name: String as NullTerminated,
+        code: String as FixedWidth<4>,
+        title: String as Utf16,
+        note: String,
+    } }
+
+    #[test]
+    fn test_string_fields(){
+        let legacy = Legacy{ name: String::from("ab"), code: String::from("x"), title: String::from("é"), note: String::from("n") };
+        let bytes = encode(&legacy);
+        let mut expected = vec![b'a', b'b', 0, b'x', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0xe9, 0];
+        expected.extend_from_slice(&encode(&String::from("n")));
+        assert_eq!(expected, bytes);
+        assert_eq!(legacy.buffer_len(), bytes.len());
+        assert_eq!(1 + 4 + 8 + 8, Legacy::MIN_BUFFER_LEN);
+        assert_eq!(Ok(()), validate::<Legacy>(&mut ReadBuffer::from_raw(bytes.clone())));
+        assert_eq!(Some(legacy), decode(&bytes));
+        assert_eq!(None, <NullTerminated as FieldEncoding<String>>::read(&mut ReadBuffer::from_raw(b"ab".to_vec())));
+        assert!(std::panic::catch_unwind(|| <FixedWidth<2> as FieldEncoding<String>>::write(&String::from("abc"), &mut Vec::new())).is_err());
+        assert!(std::panic::catch_unwind(|| <NullTerminated as FieldEncoding<String>>::write(&String::from("a\0b"), &mut Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_fields(){
+        let record = Record{ count: 5, tags: vec![1u16, 2], blob: ByteVec(vec![9]), small: 200, temp: 1.5, le: 0x0102 };
+        let bytes = encode(&record);
+        let mut expected = vec![5, 2, 0, 1, 0, 2, 0, 0, 0, 1, 9, 200];
+        expected.extend_from_slice(&1.5f64.to_be_bytes());
+        expected.extend_from_slice(&[2, 1]);
+        assert_eq!(expected, bytes);
+        assert_eq!(record.buffer_len(), bytes.len());
+        assert_eq!(1 + 1 + 4 + 1 + 8 + 2, Record::<u16>::MIN_BUFFER_LEN);
+        assert_eq!(Some(record), decode(&bytes));
+        // a u8 that does not fit u64 as u8 can not come back, a varint too long for a u16 neither
+        assert_eq!(None, <Varint as FieldEncoding<u16>>::read(&mut ReadBuffer::from_raw(vec![0xff, 0xff, 0x04])));
+        assert_eq!(Some(300), <Varint as FieldEncoding<u16>>::read(&mut ReadBuffer::from_raw(vec![0xac, 0x02])));
+        assert_eq!(None, <Cast<u64> as FieldEncoding<u8>>::read(&mut ReadBuffer::from_raw(encode(&256u64))));
+        assert_eq!(None, <Prefixed<u8> as FieldEncoding<String>>::read(&mut ReadBuffer::from_raw(vec![1, 0xff])));
+        assert!(std::panic::catch_unwind(|| encode(&Record{ small: 256, ..Record{ count: 0, tags: vec![0u8], blob: ByteVec(vec![]), small: 0, temp: 0.0, le: 0 } })).is_err());
+        assert!(std::panic::catch_unwind(|| <Prefixed<u8> as FieldEncoding<Vec<u8>>>::write(&vec![0; 256], &mut Vec::new())).is_err());
+        let stored = vec![F32Of(0.5f64), F32Of(1.0 / 3.0)];
+        let bytes = encode(&stored);
+        assert_eq!(8 + 8, bytes.len());
+        assert_eq!(Some(vec![0.5, f64::from(1.0f32 / 3.0)]), decode::<Vec<F32Of<f64>>>(&bytes).map(|v| v.into_iter().map(|x| x.0).collect()));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Telemetry{ delta: i32, name: String, samples: Vec<u16> }
+    bufferable_struct!{ Telemetry{
+        delta: i32 as Zigzag,
+        name: String as Prefixed<varint::VarInt>,
+        samples: Vec<u16> as Prefixed<varint::VarInt>,
+    } }
+
+    #[test]
+    fn test_varint_fields(){
+        let t = Telemetry{ delta: -2, name: String::from("t"), samples: vec![7; 130] };
+        let bytes = encode(&t);
+        assert_eq!(vec![3, 1, b't', 0x82, 0x01, 0, 7], bytes[..7].to_vec());
+        assert_eq!(t.buffer_len(), bytes.len());
+        assert_eq!(Some(t), decode(&bytes));
+        assert_eq!(None, <Zigzag as FieldEncoding<i8>>::read(&mut ReadBuffer::from_raw(encode(&varint::VarIntSigned(-129)))));
+        assert_eq!(Some(i64::MIN), <Zigzag as FieldEncoding<i64>>::read(&mut ReadBuffer::from_raw(encode(&varint::VarIntSigned(i64::MIN)))));
+    }
+}
@@ -0,0 +1,186 @@
+//! Writing into a fixed size array and reading from a slice, without heap allocation.
+//! For targets with little memory: FixedBuffer<N> holds at most N bytes on the stack
+//! and refuses values that do not fit, SliceReader reads numbers and borrowed
+//! strings straight from a &[u8]. Both use the same layout as Buffer and ReadBuffer.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::fixed::*;
+//! let mut buffer = FixedBuffer::<16>::new();
+//! assert!(buffer.encode(&(1u16, 2.5f32)));
+//! assert!(buffer.encode(&String::from("ab")));
+//! assert!(!buffer.encode(&7u64));
+//! let mut reader = SliceReader::new(buffer.as_slice());
+//! assert_eq!(Some(1), reader.read_u16());
+//! assert_eq!(Some(2.5), reader.read_f32());
+//! assert_eq!(Some("ab"), reader.read_str());
+//! assert!(reader.is_empty());
+//! ```
+use std::convert::TryFrom;
+use crate::{ Bufferable, Sink };
+
+/// Sink backed by an array of N bytes.
+/// A write that does not fit is dropped and marks the buffer as overflowed.
+pub struct FixedBuffer<const N: usize>{
+    bytes: [u8; N],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<const N: usize> FixedBuffer<N>{
+    /// An empty buffer.
+    pub fn new() -> Self{
+        Self{ bytes: [0; N], len: 0, overflowed: false }
+    }
+    /// Write x if it fits in what is left, otherwise write nothing and give false.
+    pub fn encode<T: Bufferable>(&mut self, x: &T) -> bool{
+        if self.overflowed || x.buffer_len() > self.remaining(){
+            return false;
+        }
+        x.copy_into_buffer(self);
+        !self.overflowed
+    }
+    /// The bytes written so far.
+    pub fn as_slice(&self) -> &[u8]{
+        &self.bytes[..self.len]
+    }
+    /// Number of bytes written.
+    pub fn len(&self) -> usize{
+        self.len
+    }
+    /// Whether nothing is written.
+    pub fn is_empty(&self) -> bool{
+        self.len == 0
+    }
+    /// Bytes that still fit.
+    pub fn remaining(&self) -> usize{
+        N - self.len
+    }
+    /// Whether a write through Sink did not fit. The bytes before it are still there.
+    pub fn overflowed(&self) -> bool{
+        self.overflowed
+    }
+    /// Empty the buffer and forget any overflow.
+    pub fn clear(&mut self){
+        self.len = 0;
+        self.overflowed = false;
+    }
+}
+
+impl<const N: usize> Default for FixedBuffer<N>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl<const N: usize> Sink for FixedBuffer<N>{
+    fn write_bytes(&mut self, bytes: &[u8]){
+        if self.overflowed || bytes.len() > self.remaining(){
+            self.overflowed = true;
+            return;
+        }
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+/// Reads values from a borrowed slice, strings and bytes are borrowed as well.
+pub struct SliceReader<'a>{
+    bytes: &'a [u8],
+}
+
+impl<'a> SliceReader<'a>{
+    /// Read from the start of bytes.
+    pub fn new(bytes: &'a [u8]) -> Self{
+        Self{ bytes }
+    }
+    /// Bytes left.
+    pub fn remaining(&self) -> usize{
+        self.bytes.len()
+    }
+    /// Whether everything is read.
+    pub fn is_empty(&self) -> bool{
+        self.bytes.is_empty()
+    }
+    /// The next len bytes as they are.
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]>{
+        if len > self.bytes.len(){
+            return Option::None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Option::Some(taken)
+    }
+    fn take<const M: usize>(&mut self) -> Option<[u8; M]>{
+        <[u8; M]>::try_from(self.read_bytes(M)?).ok()
+    }
+    /// Read a u8.
+    pub fn read_u8(&mut self) -> Option<u8>{
+        Option::Some(self.take::<1>()?[0])
+    }
+    /// Read a u16.
+    pub fn read_u16(&mut self) -> Option<u16>{
+        Option::Some(u16::from_be_bytes(self.take()?))
+    }
+    /// Read a u32.
+    pub fn read_u32(&mut self) -> Option<u32>{
+        Option::Some(u32::from_be_bytes(self.take()?))
+    }
+    /// Read a u64.
+    pub fn read_u64(&mut self) -> Option<u64>{
+        Option::Some(u64::from_be_bytes(self.take()?))
+    }
+    /// Read a f32.
+    pub fn read_f32(&mut self) -> Option<f32>{
+        Option::Some(f32::from_be_bytes(self.take()?))
+    }
+    /// Read a f64.
+    pub fn read_f64(&mut self) -> Option<f64>{
+        Option::Some(f64::from_be_bytes(self.take()?))
+    }
+    /// Read a length prefix, then that many bytes, as written for a ByteVec.
+    pub fn read_blob(&mut self) -> Option<&'a [u8]>{
+        let len = usize::try_from(self.read_u64()?).ok()?;
+        self.read_bytes(len)
+    }
+    /// Read a String as a borrowed str. None if it is not UTF-8.
+    pub fn read_str(&mut self) -> Option<&'a str>{
+        std::str::from_utf8(self.read_blob()?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::fixed::*;
+
+    #[test]
+    fn test_fixed(){
+        let mut buffer = FixedBuffer::<32>::new();
+        assert!(buffer.encode(&vec![1u32, 2, 3]));
+        assert_eq!(20, buffer.len());
+        // does not fit, nothing is written
+        assert!(!buffer.encode(&ByteVec(vec![0; 5])));
+        assert_eq!(20, buffer.len());
+        assert!(!buffer.overflowed());
+        assert!(buffer.encode(&ByteVec(vec![9; 4])));
+        assert_eq!(0, buffer.remaining());
+        let mut vec = Vec::new();
+        vec![1u32, 2, 3].into_buffer(&mut vec);
+        ByteVec(vec![9; 4]).into_buffer(&mut vec);
+        assert_eq!(&vec[..], buffer.as_slice());
+        // writing through Sink directly
+        1u8.into_buffer(&mut buffer);
+        assert!(buffer.overflowed());
+        buffer.clear();
+        assert!(buffer.is_empty() && !buffer.overflowed());
+        let mut reader = SliceReader::new(&vec);
+        assert_eq!(Some(3), reader.read_u64());
+        assert_eq!(Some(1), reader.read_u32());
+        assert_eq!(Some(&[0, 0, 0, 2][..]), reader.read_bytes(4));
+        reader.read_u32();
+        assert_eq!(Some(&[9; 4][..]), reader.read_blob());
+        assert_eq!(None, reader.read_u8());
+        assert_eq!(None, SliceReader::new(&[0, 0, 0, 0, 0, 0, 0, 1, 0xff]).read_str());
+    }
+}
@@ -0,0 +1,246 @@
+//! Logging records to raw NOR flash, in pages of a fixed size like 256 bytes.
+//! Every record is its length (u16), the CRC-32 of its bytes (u32) and the bytes,
+//! or, picked per record with append_with, a CRC-16 (u16) instead of the CRC-32,
+//! marked by the top bit of the length. A record never crosses the end of a page: when it does not fit in what is left,
+//! the rest of the page is filled with 0xff and the record starts on the next page.
+//! Erased flash reads as 0xff, so a length of 0xffff means the rest of the page is empty,
+//! and an empty page means the log ends there. scan finds the records again after a reset,
+//! stopping at a record that was cut off halfway or does not check out.
+//! # Example
+//! ```
+//! use bin_buffer::checksum::Checksum;
+//! use bin_buffer::flash::*;
+//! let mut log = FlashWriter::new(Vec::new(), 16);
+//! assert!(log.append(&1u32));
+//! assert!(log.append(&2u64)); // does not fit behind the first, goes to the second page
+//! assert!(!log.append(&[0u8; 11].to_vec())); // never fits in a page
+//! let mut flash = log.into_inner();
+//! assert_eq!(30, flash.len());
+//! flash.resize(64, ERASED);
+//! let scan = scan(&flash, 16);
+//! assert_eq!(vec![Some(1u32)], scan.values::<u32>(&flash).take(1).collect::<Vec<_>>());
+//! assert_eq!(32, scan.end);
+//! let mut log = FlashWriter::resume(Vec::new(), 16, scan.end);
+//! assert!(log.append_with(&3u8, Checksum::Crc16));
+//! ```
+use crate::{ Bufferable, ReadBuffer, Sink };
+use crate::checksum::Checksum;
+
+/// Value of an erased byte of flash.
+pub const ERASED: u8 = 0xff;
+
+/// Bytes in front of a record with a CRC-32: the length and the CRC.
+pub const RECORD_HEADER_LEN: usize = 6;
+
+// Set in the length of records with a CRC-16.
+const CRC16_FLAG: u16 = 0x8000;
+
+/// Whether every byte of bytes is erased.
+pub fn is_erased(bytes: &[u8]) -> bool{
+    bytes.iter().all(|&b| b == ERASED)
+}
+
+/// The largest record with a CRC-32 that fits in a page of page_size bytes.
+pub fn max_record_len(page_size: usize) -> usize{
+    max_len(page_size, Checksum::Crc32)
+}
+
+fn max_len(page_size: usize, checksum: Checksum) -> usize{
+    // below the flag, and 0x7fff with the flag would read as erased
+    page_size.saturating_sub(2 + checksum.width()).min(usize::from(CRC16_FLAG) - 2)
+}
+
+/// Writes records to a Sink standing for the flash, starting at a page boundary or where a scan ended.
+pub struct FlashWriter<S: Sink>{
+    sink: S,
+    page_size: usize,
+    pos: usize,
+}
+
+impl<S: Sink> FlashWriter<S>{
+    /// Start writing at offset 0 of the flash, in pages of page_size bytes.
+    pub fn new(sink: S, page_size: usize) -> Self{
+        Self::resume(sink, page_size, 0)
+    }
+    /// Go on writing at pos, usually the end of a scan. The sink gets the bytes from pos on.
+    pub fn resume(sink: S, page_size: usize, pos: usize) -> Self{
+        Self{ sink, page_size, pos }
+    }
+    /// Offset in the flash where the next byte goes.
+    pub fn position(&self) -> usize{
+        self.pos
+    }
+    /// Bytes left in the current page.
+    pub fn page_remaining(&self) -> usize{
+        self.page_size - self.pos % self.page_size
+    }
+    /// Fill the rest of the current page with 0xff, so the next record starts on a new page.
+    /// Does nothing at the start of a page.
+    pub fn pad_page(&mut self){
+        if !self.pos.is_multiple_of(self.page_size){
+            let pad = self.page_remaining();
+            self.sink.write_bytes(&vec![ERASED; pad]);
+            self.pos += pad;
+        }
+    }
+    /// Append a record holding bytes, on the next page if it does not fit in this one.
+    /// False, without writing anything, if it is longer than max_record_len.
+    pub fn append_bytes(&mut self, bytes: &[u8]) -> bool{
+        self.append_bytes_with(bytes, Checksum::Crc32)
+    }
+    /// Append a record holding bytes with the given checksum.
+    /// A CRC-16 makes the record two bytes shorter and lets it be two bytes longer.
+    pub fn append_bytes_with(&mut self, bytes: &[u8], checksum: Checksum) -> bool{
+        if bytes.len() > max_len(self.page_size, checksum){
+            return false;
+        }
+        let header = 2 + checksum.width();
+        if header + bytes.len() > self.page_remaining(){
+            self.pad_page();
+        }
+        let flag = if checksum == Checksum::Crc16 { CRC16_FLAG } else { 0 };
+        let mut record = Vec::with_capacity(header + bytes.len());
+        (bytes.len() as u16 | flag).into_buffer(&mut record);
+        record.extend_from_slice(&checksum.of(bytes));
+        record.extend_from_slice(bytes);
+        self.sink.write_bytes(&record);
+        self.pos += record.len();
+        true
+    }
+    /// Append a record holding x.
+    pub fn append<T: Bufferable>(&mut self, x: &T) -> bool{
+        self.append_with(x, Checksum::Crc32)
+    }
+    /// Append a record holding x with the given checksum.
+    pub fn append_with<T: Bufferable>(&mut self, x: &T, checksum: Checksum) -> bool{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.append_bytes_with(&bytes, checksum)
+    }
+    /// The sink.
+    pub fn into_inner(self) -> S{
+        self.sink
+    }
+}
+
+/// What scan found in an image of the flash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlashScan{
+    /// (start, end) of the bytes of every record that checks out, oldest first.
+    pub records: Vec<(usize, usize)>,
+    /// Where the next record can be written.
+    pub end: usize,
+    /// Whether the scan stopped at bytes that are not erased but are not a record either,
+    /// like a record cut off by a reset. Those bytes have to be erased before writing at end.
+    pub damaged: bool,
+}
+
+impl FlashScan{
+    /// The bytes of every record.
+    pub fn records<'a>(&'a self, flash: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a{
+        self.records.iter().map(move |(start, end)| &flash[*start..*end])
+    }
+    /// Every record read as one T, None for records that do not decode
+    /// or have bytes left over.
+    pub fn values<'a, T: Bufferable + 'a>(&'a self, flash: &'a [u8]) -> impl Iterator<Item = Option<T>> + 'a{
+        self.records(flash).map(|bytes|{
+            let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+            let x = T::from_buffer(&mut buf)?;
+            if buf.remaining() != 0{
+                return Option::None;
+            }
+            Option::Some(x)
+        })
+    }
+}
+
+/// Find the records in an image of the flash written by FlashWriter with the same page_size.
+pub fn scan(flash: &[u8], page_size: usize) -> FlashScan{
+    let mut records = Vec::new();
+    let mut pos = 0;
+    let mut damaged = false;
+    while pos < flash.len(){
+        let page_end = (pos - pos % page_size + page_size).min(flash.len());
+        if page_end - pos < 4 || is_erased(&flash[pos..pos + 2]){
+            // the rest of this page was never written
+            if !is_erased(&flash[pos..page_end]){
+                damaged = true;
+                break;
+            }
+            if pos.is_multiple_of(page_size) || page_end == flash.len(){
+                break;
+            }
+            pos = page_end;
+            continue;
+        }
+        let len = u16::from_be_bytes([flash[pos], flash[pos + 1]]);
+        let checksum = if len & CRC16_FLAG != 0 { Checksum::Crc16 } else { Checksum::Crc32 };
+        let len = usize::from(len & !CRC16_FLAG);
+        let start = pos + 2 + checksum.width();
+        if start + len > page_end || flash[pos + 2..start] != checksum.of(&flash[start..start + len])[..]{
+            damaged = true;
+            break;
+        }
+        records.push((start, start + len));
+        pos = start + len;
+    }
+    FlashScan{ records, end: pos, damaged }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::flash::*;
+
+    #[test]
+    fn test_flash(){
+        let mut log = FlashWriter::new(Vec::new(), 32);
+        for i in 0..10u16{
+            assert!(log.append(&(i, 0xffu8)));
+        }
+        assert_eq!(26, max_record_len(32));
+        assert!(log.append_bytes(&[7; 26]));
+        assert!(!log.append_bytes(&[7; 27]));
+        let mut flash = log.into_inner();
+        // 9 byte records, three per page, then the big one on a page of its own
+        assert_eq!(5 * 32, flash.len());
+        assert!(flash.chunks(32).all(|page| page.len() == 32));
+        flash.resize(8 * 32, ERASED);
+        let found = scan(&flash, 32);
+        assert_eq!(11, found.records.len());
+        assert_eq!(5 * 32, found.end);
+        assert!(!found.damaged);
+        let values: Vec<Option<(u16, u8)>> = found.values(&flash).take(10).collect();
+        assert_eq!((0..10).map(|i| Some((i, 0xff))).collect::<Vec<_>>(), values);
+        // a reset halfway through a record
+        let mut log = FlashWriter::resume(Vec::new(), 32, found.end);
+        log.append(&5u32);
+        let torn = log.into_inner();
+        flash[found.end..found.end + 8].copy_from_slice(&torn[..8]);
+        let again = scan(&flash, 32);
+        assert_eq!(11, again.records.len());
+        assert_eq!(found.end, again.end);
+        assert!(again.damaged);
+        // nothing written yet
+        let empty = scan(&[ERASED; 64], 32);
+        assert_eq!((0, 0, false), (empty.records.len(), empty.end, empty.damaged));
+        let mut log = FlashWriter::new(Vec::new(), 32);
+        log.append(&1u8);
+        log.pad_page();
+        log.pad_page();
+        assert_eq!(32, log.position());
+        // CRC-16 records, mixed with CRC-32 ones
+        let mut log = FlashWriter::new(Vec::new(), 16);
+        assert!(log.append_with(&7u64, Checksum::Crc16));
+        assert!(log.append_with(&9u16, Checksum::Crc16));
+        assert!(log.append(&1u16));
+        assert!(log.append_bytes_with(&[1; 12], Checksum::Crc16));
+        assert!(!log.append_bytes_with(&[1; 13], Checksum::Crc16));
+        let mut flash = log.into_inner();
+        assert_eq!(3 * 16, flash.len());
+        let found = scan(&flash, 16);
+        assert_eq!(4, found.records.len());
+        assert_eq!(vec![&[1u8; 12][..]], found.records(&flash).skip(3).collect::<Vec<_>>());
+        flash[34] ^= 1;
+        assert_eq!(3, scan(&flash, 16).records.len());
+    }
+}
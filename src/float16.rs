@@ -0,0 +1,79 @@
+//! Bufferable for half::f16, enabled with the `half` feature.
+use std::convert::TryFrom;
+use crate::{ Bufferable, ReadBuffer, Sink };
+use crate::fields::Castable;
+use half::f16;
+
+/// Implements Bufferable for f16, written as 2 big endian bytes.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use half::f16;
+/// let x = f16::from_f32(1.5);
+/// assert_eq!(vec![0x3e, 0x00], encode(&x));
+/// assert_eq!(Some(x), decode(&encode(&x)));
+/// ```
+impl Bufferable for f16{
+    const MIN_BUFFER_LEN: usize = 2;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        vec.write_bytes(&self.to_be_bytes());
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(&self.to_be_bytes());
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some(f16::from_be_bytes(<[u8; 2]>::try_from(buf.read_bytes(2)?).ok()?))
+    }
+
+    fn buffer_len(&self) -> usize{
+        2
+    }
+}
+
+impl Castable<f16> for f32{
+    fn cast(&self) -> f16{
+        f16::from_f32(*self)
+    }
+
+    fn uncast(w: f16) -> Option<Self>{
+        Option::Some(w.to_f32())
+    }
+}
+
+impl Castable<f16> for f64{
+    fn cast(&self) -> f16{
+        f16::from_f64(*self)
+    }
+
+    fn uncast(w: f16) -> Option<Self>{
+        Option::Some(w.to_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::fields::*;
+    use half::f16;
+
+    #[derive(Debug, PartialEq)]
+    struct Sample{ t: f64, level: f32 }
+    bufferable_struct!{ Sample{ t: f64 as Cast<f16>, level: f32 as Cast<f16> } }
+
+    #[test]
+    fn test_f16(){
+        let x = vec![f16::from_f32(-2.0), f16::from_f32(0.25)];
+        let bytes = encode(&x);
+        assert_eq!(8 + 4, bytes.len());
+        assert_eq!(Some(x), decode(&bytes));
+        let sample = Sample{ t: 0.5, level: 1024.0 };
+        assert_eq!(4, encode(&sample).len());
+        assert_eq!(Some(sample), decode(&encode(&Sample{ t: 0.5, level: 1024.0 })));
+        // precision is lost on the way
+        let back = decode::<F16Of<f64>>(&encode(&F16Of(0.1f64))).unwrap().0;
+        assert!(back != 0.1 && (back - 0.1).abs() < 1e-3);
+    }
+}
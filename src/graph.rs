@@ -0,0 +1,350 @@
+//! Writing values that share parts through Rc or Arc, keeping the sharing.
+//! Normally every Rc is written out in full, so a material used by a thousand meshes is
+//! written a thousand times and read back as a thousand copies. A GraphWriter writes every
+//! shared value once, the first time it comes across it, and a u32 reference to it after that.
+//! The reference is 0 in front of a value written in full, and id + 1 for a value written
+//! before, ids counting the shared values in the order they were written.
+//! A GraphReader hands out clones of one Rc for every reference, so the sharing comes back.
+//! Plain Rc and Arc can not hold themselves, a value that refers to itself is a cycle:
+//! writing it stops with a CycleError instead of recursing forever, and reading a reference to a
+//! value that is not done yet fails.
+//! Structs holding Rc or Arc implement GraphBufferable, writing their fields with the writer.
+//! Every Bufferable type already is GraphBufferable, sequences go through write_seq and read_vec.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::graph::*;
+//! use std::rc::Rc;
+//! struct Mesh{ name: String, material: Rc<String> }
+//! impl GraphBufferable for Mesh{
+//!     fn write_graph<S: Sink + ?Sized>(&self, graph: &mut GraphWriter, vec: &mut S) -> Result<(), CycleError>{
+//!         graph.write(&self.name, vec)?;
+//!         graph.write(&self.material, vec)
+//!     }
+//!     fn read_graph(graph: &mut GraphReader, buf: &mut ReadBuffer) -> Option<Self>{
+//!         Some(Mesh{ name: graph.read(buf)?, material: graph.read(buf)? })
+//!     }
+//! }
+//! let steel = Rc::new(String::from("steel"));
+//! let meshes: Vec<Mesh> = (0..100).map(|i| Mesh{ name: i.to_string(), material: steel.clone() }).collect();
+//! let mut bytes = Vec::new();
+//! GraphWriter::new().write_seq(&meshes, &mut bytes).unwrap();
+//! // the material is written once, every other mesh refers to it with 4 bytes
+//! assert!(bytes.len() < 1500);
+//! let back: Vec<Mesh> = GraphReader::new().read_vec(&mut ReadBuffer::from_raw(bytes)).unwrap();
+//! assert!(Rc::ptr_eq(&back[0].material, &back[99].material));
+//! assert_eq!("steel", back[42].material.as_str());
+//! ```
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::{ Buffer, Bufferable, ReadBuffer, Sink };
+
+/// Values that can be written with a GraphWriter and read with a GraphReader.
+pub trait GraphBufferable: Sized{
+    /// Write the value, writing what it holds through graph.
+    fn write_graph<S: Sink + ?Sized>(&self, graph: &mut GraphWriter, vec: &mut S) -> Result<(), CycleError>;
+    /// Read the value, reading what it holds through graph.
+    fn read_graph(graph: &mut GraphReader, buf: &mut ReadBuffer) -> Option<Self>;
+}
+
+impl<T: Bufferable> GraphBufferable for T{
+    fn write_graph<S: Sink + ?Sized>(&self, _: &mut GraphWriter, vec: &mut S) -> Result<(), CycleError>{
+        self.copy_into_buffer(vec);
+        Ok(())
+    }
+    fn read_graph(_: &mut GraphReader, buf: &mut ReadBuffer) -> Option<Self>{
+        T::from_buffer(buf)
+    }
+}
+
+/// Written once, referenced after that.
+impl<T: GraphBufferable + 'static> GraphBufferable for Rc<T>{
+    fn write_graph<S: Sink + ?Sized>(&self, graph: &mut GraphWriter, vec: &mut S) -> Result<(), CycleError>{
+        if graph.reference(Rc::as_ptr(self) as *const (), Box::new(self.clone()), vec)?{
+            let ptr = Rc::as_ptr(self) as *const ();
+            T::write_graph(self, graph, vec)?;
+            graph.done(ptr);
+        }
+        Ok(())
+    }
+    fn read_graph(graph: &mut GraphReader, buf: &mut ReadBuffer) -> Option<Self>{
+        match graph.reference(buf)?{
+            Reference::Known(Shared::Rc(x)) => x.downcast().ok(),
+            Reference::Known(Shared::Arc(_)) => Option::None,
+            Reference::New(id) => {
+                let x = Rc::new(buf.nested(|buf| T::read_graph(graph, buf))?);
+                graph.shared[id] = Option::Some(Shared::Rc(x.clone()));
+                Option::Some(x)
+            },
+        }
+    }
+}
+
+/// Written once, referenced after that.
+impl<T: GraphBufferable + Send + Sync + 'static> GraphBufferable for Arc<T>{
+    fn write_graph<S: Sink + ?Sized>(&self, graph: &mut GraphWriter, vec: &mut S) -> Result<(), CycleError>{
+        if graph.reference(Arc::as_ptr(self) as *const (), Box::new(self.clone()), vec)?{
+            let ptr = Arc::as_ptr(self) as *const ();
+            T::write_graph(self, graph, vec)?;
+            graph.done(ptr);
+        }
+        Ok(())
+    }
+    fn read_graph(graph: &mut GraphReader, buf: &mut ReadBuffer) -> Option<Self>{
+        match graph.reference(buf)?{
+            Reference::Known(Shared::Arc(x)) => x.downcast().ok(),
+            Reference::Known(Shared::Rc(_)) => Option::None,
+            Reference::New(id) => {
+                let x = Arc::new(buf.nested(|buf| T::read_graph(graph, buf))?);
+                graph.shared[id] = Option::Some(Shared::Arc(x.clone()));
+                Option::Some(x)
+            },
+        }
+    }
+}
+
+/// A value holds itself, through Rc or Arc.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CycleError;
+
+impl std::fmt::Display for CycleError{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "a shared value holds itself")
+    }
+}
+
+impl std::error::Error for CycleError{}
+
+/// Keeps track of the shared values written so far.
+/// It holds a clone of each, so no other value can take its address while the writer lives.
+#[derive(Default)]
+pub struct GraphWriter{
+    ids: HashMap<*const (), (u32, bool)>,
+    keep: Vec<Box<dyn Any>>,
+    cycle: bool,
+}
+
+impl GraphWriter{
+    /// A writer that has not seen any shared values.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Write x, sharing with everything this writer wrote before.
+    /// Fails if x holds a cycle, or one was found before. What was written then is not readable.
+    pub fn write<T: GraphBufferable, S: Sink + ?Sized>(&mut self, x: &T, vec: &mut S) -> Result<(), CycleError>{
+        if self.cycle{
+            return Err(CycleError);
+        }
+        x.write_graph(self, vec)
+    }
+    /// Write the length of xs and then each of them, failing like write.
+    pub fn write_seq<T: GraphBufferable, S: Sink + ?Sized>(&mut self, xs: &[T], vec: &mut S) -> Result<(), CycleError>{
+        if self.cycle{
+            return Err(CycleError);
+        }
+        (xs.len() as u64).into_buffer(vec);
+        for x in xs{
+            x.write_graph(self, vec)?;
+        }
+        Ok(())
+    }
+    /// Number of shared values written.
+    pub fn shared(&self) -> usize{
+        self.ids.len()
+    }
+    /// Whether a value was found to hold itself. What was written since is not readable.
+    pub fn has_cycle(&self) -> bool{
+        self.cycle
+    }
+    // Write the reference to the value at ptr, true if the value itself has to follow.
+    fn reference<S: Sink + ?Sized>(&mut self, ptr: *const (), keep: Box<dyn Any>, vec: &mut S) -> Result<bool, CycleError>{
+        match self.ids.get(&ptr){
+            Option::Some(&(id, true)) => {
+                (id + 1).into_buffer(vec);
+                Ok(false)
+            },
+            Option::Some(_) => {
+                self.cycle = true;
+                Err(CycleError)
+            },
+            Option::None => {
+                let id = u32::try_from(self.ids.len()).expect("more than u32::MAX - 1 shared values");
+                self.ids.insert(ptr, (id, false));
+                self.keep.push(keep);
+                0u32.into_buffer(vec);
+                Ok(true)
+            },
+        }
+    }
+    fn done(&mut self, ptr: *const ()){
+        if let Option::Some(entry) = self.ids.get_mut(&ptr){
+            entry.1 = true;
+        }
+    }
+}
+
+enum Shared{
+    Rc(Rc<dyn Any>),
+    Arc(Arc<dyn Any + Send + Sync>),
+}
+
+enum Reference{
+    Known(Shared),
+    New(usize),
+}
+
+/// Keeps track of the shared values read so far.
+#[derive(Default)]
+pub struct GraphReader{
+    shared: Vec<Option<Shared>>,
+}
+
+impl GraphReader{
+    /// A reader that has not seen any shared values.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Read a T, sharing with everything this reader read before.
+    pub fn read<T: GraphBufferable>(&mut self, buf: &mut ReadBuffer) -> Option<T>{
+        T::read_graph(self, buf)
+    }
+    /// Read what write_seq wrote.
+    pub fn read_vec<T: GraphBufferable>(&mut self, buf: &mut ReadBuffer) -> Option<Vec<T>>{
+        let len = buf.read_len()?;
+        buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+        let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+        for _ in 0..len{
+            vec.push(T::read_graph(self, buf)?);
+        }
+        Option::Some(vec)
+    }
+    /// Number of shared values read.
+    pub fn shared(&self) -> usize{
+        self.shared.len()
+    }
+    // None for a reference to a value that is not there or not done, the start of a cycle.
+    fn reference(&mut self, buf: &mut ReadBuffer) -> Option<Reference>{
+        match u32::from_buffer(buf)?{
+            0 => {
+                self.shared.push(Option::None);
+                Option::Some(Reference::New(self.shared.len() - 1))
+            },
+            id => Option::Some(Reference::Known(match self.shared.get(id as usize - 1)?.as_ref()?{
+                Shared::Rc(x) => Shared::Rc(x.clone()),
+                Shared::Arc(x) => Shared::Arc(x.clone()),
+            })),
+        }
+    }
+}
+
+/// Write x with a new GraphWriter. None if it holds a cycle.
+pub fn encode_graph<T: GraphBufferable>(x: &T) -> Option<Buffer>{
+    let mut buffer = Vec::new();
+    GraphWriter::new().write(x, &mut buffer).ok()?;
+    Option::Some(buffer)
+}
+
+/// Read what encode_graph wrote, None unless that is exactly all of bytes.
+pub fn decode_graph<T: GraphBufferable>(bytes: &[u8]) -> Option<T>{
+    let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+    let x = GraphReader::new().read(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    Option::Some(x)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::graph::*;
+    use std::cell::RefCell;
+
+    // a node that can point at any node, itself included
+    struct Node{ value: u32, next: RefCell<Option<Rc<Node>>> }
+
+    impl GraphBufferable for Node{
+        fn write_graph<S: Sink + ?Sized>(&self, graph: &mut GraphWriter, vec: &mut S) -> Result<(), CycleError>{
+            graph.write(&self.value, vec)?;
+            match &*self.next.borrow(){
+                Some(next) => { graph.write(&1u8, vec)?; graph.write(next, vec) },
+                None => graph.write(&0u8, vec),
+            }
+        }
+        fn read_graph(graph: &mut GraphReader, buf: &mut ReadBuffer) -> Option<Self>{
+            let value = graph.read(buf)?;
+            let next = match graph.read::<u8>(buf)?{
+                0 => None,
+                _ => Some(graph.read(buf)?),
+            };
+            Some(Node{ value, next: RefCell::new(next) })
+        }
+    }
+
+    #[test]
+    fn test_graph(){
+        let shared = Arc::new(vec![1u16, 2]);
+        let xs = vec![shared.clone(), Arc::new(vec![3]), shared.clone()];
+        let mut graph = GraphWriter::new();
+        let mut bytes = Vec::new();
+        graph.write_seq(&xs, &mut bytes).unwrap();
+        assert_eq!(2, graph.shared());
+        // the third element refers to the first shared value
+        assert_eq!(&[0, 0, 0, 1], &bytes[bytes.len() - 4..]);
+        let mut reader = GraphReader::new();
+        let back: Vec<Arc<Vec<u16>>> = reader.read_vec(&mut ReadBuffer::from_raw(bytes.clone())).unwrap();
+        assert_eq!(2, reader.shared());
+        assert!(Arc::ptr_eq(&back[0], &back[2]) && !Arc::ptr_eq(&back[0], &back[1]));
+        assert_eq!(vec![3], *back[1]);
+        // the bytes do not say whether it was an Rc or an Arc
+        let mut reader = GraphReader::new();
+        let back: Vec<Rc<Vec<u16>>> = reader.read_vec(&mut ReadBuffer::from_raw(bytes.clone())).unwrap();
+        assert!(Rc::ptr_eq(&back[0], &back[2]));
+        // but one shared value can not be both
+        let mut reader = GraphReader::new();
+        let mut buf = ReadBuffer::from_raw(bytes[8..].to_vec());
+        assert!(reader.read::<Arc<Vec<u16>>>(&mut buf).is_some());
+        assert!(reader.read::<Rc<Vec<u16>>>(&mut ReadBuffer::from_raw(vec![0, 0, 0, 1])).is_none());
+        // sharing carries over between values written with the same writer
+        let a = Rc::new(Node{ value: 1, next: RefCell::new(None) });
+        let b = Rc::new(Node{ value: 2, next: RefCell::new(Some(a.clone())) });
+        let mut graph = GraphWriter::new();
+        let mut bytes = Vec::new();
+        graph.write(&b, &mut bytes).unwrap();
+        graph.write(&a, &mut bytes).unwrap();
+        assert_eq!(&[0, 0, 0, 2], &bytes[bytes.len() - 4..]);
+        let mut reader = GraphReader::new();
+        let mut buf = ReadBuffer::from_raw(bytes);
+        let b2: Rc<Node> = reader.read(&mut buf).unwrap();
+        let a2: Rc<Node> = reader.read(&mut buf).unwrap();
+        assert!(Rc::ptr_eq(b2.next.borrow().as_ref().unwrap(), &a2));
+        assert_eq!((2, 1), (b2.value, a2.value));
+    }
+
+    #[test]
+    fn test_graph_cycle(){
+        let a = Rc::new(Node{ value: 1, next: RefCell::new(None) });
+        let b = Rc::new(Node{ value: 2, next: RefCell::new(Some(a.clone())) });
+        *a.next.borrow_mut() = Some(b.clone());
+        assert!(encode_graph(&a).is_none());
+        let mut graph = GraphWriter::new();
+        let mut bytes = Vec::new();
+        assert_eq!(Err(CycleError), graph.write(&a, &mut bytes));
+        assert!(graph.has_cycle());
+        // the writer stays failed, also for values without a cycle
+        assert_eq!(Err(CycleError), graph.write(&1u8, &mut bytes));
+        assert_eq!(Err(CycleError), GraphWriter::new().write_seq(std::slice::from_ref(&b), &mut bytes));
+        a.next.borrow_mut().take();
+        assert!(encode_graph(&a).is_some());
+        // a node referring to itself while it is being read
+        let forged = vec![0, 0, 0, 0, 0, 0, 0, 9, 1, 0, 0, 0, 1];
+        assert!(decode_graph::<Rc<Node>>(&forged).is_none());
+        // a reference to a value that was never written
+        assert!(decode_graph::<Rc<u8>>(&[0, 0, 0, 3]).is_none());
+        assert!(decode_graph::<Rc<u8>>(&[0, 0, 0, 0, 4, 9]).is_none());
+        assert_eq!(Some(4), decode_graph::<Rc<u8>>(&[0, 0, 0, 0, 4]).map(|x| *x));
+    }
+}
@@ -0,0 +1,241 @@
+//! The first thing two peers send each other, so builds that do not speak the same
+//! format fail right away instead of decoding garbage.
+//! A Handshake is a magic number, the wire format version and two feature bitsets:
+//! the features a side supports and the ones it can not do without. Both sides send theirs,
+//! check the other one with negotiate and go on with the features they both support.
+//! It is written as the magic (4 bytes), the version (u32), the supported features (u64)
+//! and the required features (u64), without a frame around it, so a peer speaking
+//! something else is caught after 24 bytes.
+//! # Example
+//! ```
+//! use bin_buffer::handshake::*;
+//! let server = Handshake::new(3).supporting(FEATURE_COMPRESSION | FEATURE_VARINTS);
+//! let client = Handshake::new(3).supporting(FEATURE_VARINTS).requiring(FEATURE_VARINTS);
+//! assert_eq!(Ok(FEATURE_VARINTS), server.negotiate(&client));
+//! assert_eq!(Ok(FEATURE_VARINTS), client.negotiate(&server));
+//! let old = Handshake::new(2).supporting(FEATURE_VARINTS);
+//! let err = server.negotiate(&old).unwrap_err();
+//! assert_eq!(HandshakeError::Version{ ours: 3, theirs: 2 }, err);
+//! assert_eq!("wire format version 2 does not match ours, 3", err.to_string());
+//! ```
+use std::io::{ self, Read, Write };
+use std::convert::TryFrom;
+use crate::{ Bufferable, ReadBuffer, Sink };
+
+/// Magic number of a handshake made with Handshake::new.
+pub const MAGIC: [u8; 4] = *b"BBHS";
+/// Bytes a handshake takes.
+pub const HANDSHAKE_LEN: usize = 24;
+
+/// Values may be compressed.
+pub const FEATURE_COMPRESSION: u64 = 1;
+/// Integers may be written as varints.
+pub const FEATURE_VARINTS: u64 = 1 << 1;
+/// Buffers may carry checksums.
+pub const FEATURE_CHECKSUMS: u64 = 1 << 2;
+/// Buffers may be signed.
+pub const FEATURE_SIGNATURES: u64 = 1 << 3;
+
+/// What one side says about itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handshake{
+    /// Tells the protocol apart from anything else on the stream.
+    pub magic: [u8; 4],
+    /// Version of the wire format, both sides have to use the same one.
+    pub version: u32,
+    /// Features it can use.
+    pub supported: u64,
+    /// Features it can not do without.
+    pub required: u64,
+}
+
+/// Why a handshake failed.
+#[derive(Debug)]
+pub enum HandshakeError{
+    /// The stream failed, or ended before the whole handshake was read.
+    Io(io::Error),
+    /// The other side does not speak this protocol, it sent this instead of the magic.
+    Magic([u8; 4]),
+    /// The other side uses another version of the wire format.
+    Version{
+        /// The version we use.
+        ours: u32,
+        /// The version they sent.
+        theirs: u32,
+    },
+    /// The other side does not support these features we require.
+    PeerLacks(u64),
+    /// We do not support these features the other side requires.
+    WeLack(u64),
+}
+
+impl PartialEq for HandshakeError{
+    fn eq(&self, other: &Self) -> bool{
+        match (self, other){
+            (Self::Io(a), Self::Io(b)) => a.kind() == b.kind(),
+            (Self::Magic(a), Self::Magic(b)) => a == b,
+            (Self::Version{ ours: a, theirs: b }, Self::Version{ ours: c, theirs: d }) => a == c && b == d,
+            (Self::PeerLacks(a), Self::PeerLacks(b)) | (Self::WeLack(a), Self::WeLack(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for HandshakeError{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            Self::Io(e) => write!(f, "handshake failed: {}", e),
+            Self::Magic(magic) => write!(f, "not the same protocol, got magic {:?}", magic),
+            Self::Version{ ours, theirs } =>
+                write!(f, "wire format version {} does not match ours, {}", theirs, ours),
+            Self::PeerLacks(bits) => write!(f, "the other side does not support required features {:#x}", bits),
+            Self::WeLack(bits) => write!(f, "the other side requires features {:#x} we do not support", bits),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>{
+        match self{
+            Self::Io(e) => Option::Some(e),
+            _ => Option::None,
+        }
+    }
+}
+
+impl From<io::Error> for HandshakeError{
+    fn from(e: io::Error) -> Self{
+        Self::Io(e)
+    }
+}
+
+/// A mismatch becomes InvalidData, a failed stream keeps its error.
+impl From<HandshakeError> for io::Error{
+    fn from(e: HandshakeError) -> Self{
+        match e{
+            HandshakeError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+impl Handshake{
+    /// A handshake with MAGIC for version, without features.
+    pub fn new(version: u32) -> Self{
+        Self{ magic: MAGIC, version, supported: 0, required: 0 }
+    }
+    /// Use a magic number of your own protocol.
+    pub fn with_magic(mut self, magic: [u8; 4]) -> Self{
+        self.magic = magic;
+        self
+    }
+    /// Also support features.
+    pub fn supporting(mut self, features: u64) -> Self{
+        self.supported |= features;
+        self
+    }
+    /// Also require features, which are then supported as well.
+    pub fn requiring(mut self, features: u64) -> Self{
+        self.supported |= features;
+        self.required |= features;
+        self
+    }
+    /// Check the handshake of the other side against ours.
+    /// Gives the features both support, which both sides then agree on.
+    pub fn negotiate(&self, theirs: &Handshake) -> Result<u64, HandshakeError>{
+        if theirs.magic != self.magic{
+            return Err(HandshakeError::Magic(theirs.magic));
+        }
+        if theirs.version != self.version{
+            return Err(HandshakeError::Version{ ours: self.version, theirs: theirs.version });
+        }
+        let peer_lacks = self.required & !theirs.supported;
+        if peer_lacks != 0{
+            return Err(HandshakeError::PeerLacks(peer_lacks));
+        }
+        let we_lack = theirs.required & !self.supported;
+        if we_lack != 0{
+            return Err(HandshakeError::WeLack(we_lack));
+        }
+        Ok(self.supported & theirs.supported)
+    }
+    /// Write ours, read theirs and negotiate. Both sides can call this at the same time.
+    pub fn exchange<S: Read + Write + ?Sized>(&self, stream: &mut S) -> Result<u64, HandshakeError>{
+        let mut ours = Vec::with_capacity(HANDSHAKE_LEN);
+        self.copy_into_buffer(&mut ours);
+        stream.write_all(&ours)?;
+        stream.flush()?;
+        let mut theirs = [0u8; HANDSHAKE_LEN];
+        stream.read_exact(&mut theirs)?;
+        // 24 bytes always decode to a handshake
+        let theirs = crate::decode::<Handshake>(&theirs).expect("handshake of the right length");
+        self.negotiate(&theirs)
+    }
+}
+
+impl Bufferable for Handshake{
+    const MIN_BUFFER_LEN: usize = HANDSHAKE_LEN;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(&self.magic);
+        self.version.into_buffer(vec);
+        self.supported.into_buffer(vec);
+        self.required.into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let magic = <[u8; 4]>::try_from(buf.read_bytes(4)?).ok()?;
+        let version = u32::from_buffer(buf)?;
+        let supported = u64::from_buffer(buf)?;
+        let required = u64::from_buffer(buf)?;
+        Option::Some(Self{ magic, version, supported, required })
+    }
+
+    fn buffer_len(&self) -> usize{
+        HANDSHAKE_LEN
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::handshake::*;
+
+    struct Pipe{ input: std::io::Cursor<Vec<u8>>, output: Vec<u8> }
+    impl std::io::Read for Pipe{
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>{ self.input.read(buf) }
+    }
+    impl std::io::Write for Pipe{
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>{ self.output.write(buf) }
+        fn flush(&mut self) -> std::io::Result<()>{ Ok(()) }
+    }
+
+    #[test]
+    fn test_handshake(){
+        let ours = Handshake::new(1).supporting(FEATURE_COMPRESSION).requiring(FEATURE_CHECKSUMS);
+        let bytes = encode(&ours);
+        assert_eq!(HANDSHAKE_LEN, bytes.len());
+        assert_eq!(b"BBHS", &bytes[..4]);
+        assert_eq!(Some(ours), decode::<Handshake>(&bytes));
+        let theirs = Handshake::new(1).supporting(FEATURE_CHECKSUMS | FEATURE_VARINTS);
+        let mut pipe = Pipe{ input: std::io::Cursor::new(encode(&theirs)), output: Vec::new() };
+        assert_eq!(Ok(FEATURE_CHECKSUMS), ours.exchange(&mut pipe));
+        assert_eq!(bytes, pipe.output);
+        // every kind of mismatch
+        assert_eq!(Err(HandshakeError::PeerLacks(FEATURE_CHECKSUMS)), ours.negotiate(&Handshake::new(1)));
+        let demanding = Handshake::new(1).requiring(FEATURE_SIGNATURES | FEATURE_CHECKSUMS);
+        assert_eq!(Err(HandshakeError::WeLack(FEATURE_SIGNATURES)), ours.negotiate(&demanding));
+        let other = Handshake::new(1).with_magic(*b"HTTP");
+        assert_eq!(Err(HandshakeError::Magic(*b"HTTP")), ours.negotiate(&other));
+        let mut garbage = Pipe{ input: std::io::Cursor::new(b"GET / HTTP/1.1\r\n".to_vec()), output: Vec::new() };
+        let err = ours.exchange(&mut garbage).unwrap_err();
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, std::io::Error::from(err).kind());
+        let err = std::io::Error::from(ours.negotiate(&Handshake::new(2)).unwrap_err());
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+        assert!(err.to_string().contains("version 2"));
+    }
+}
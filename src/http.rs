@@ -0,0 +1,304 @@
+//! Loading buffers over HTTP, enabled with the `http` feature.
+//! buffer_read_url is the buffer_read_file of files on a web server: it GETs the url and
+//! returns the body, growing it as the bytes arrive up to the Content-Length when the server sends one.
+//! It speaks plain HTTP/1.1 over a TcpStream and has no dependencies, so https urls are
+//! not supported; put a TLS terminating proxy in front, or fetch those yourself.
+//! Redirects are followed, chunked bodies are put back together.
+//! # Example
+//! ```no_run
+//! use bin_buffer::*;
+//! use bin_buffer::http::*;
+//! let bytes = buffer_read_url("http://example.com/assets/level1.bin").unwrap();
+//! let level = decode::<Vec<u32>>(&bytes);
+//! ```
+use std::convert::TryFrom;
+use std::future::Future;
+use std::io::{ self, BufRead, BufReader, Read, Write };
+use std::net::TcpStream;
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex };
+use std::task::{ Context, Poll, Waker };
+use std::time::Duration;
+use crate::Buffer;
+
+/// How long a fetch waits on the server before failing with TimedOut.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+/// Largest body a fetch accepts, 1 GiB.
+pub const DEFAULT_MAX_BODY_LEN: usize = 1 << 30;
+/// Most redirects followed for one fetch.
+pub const MAX_REDIRECTS: usize = 5;
+// Longest status line or header.
+const MAX_LINE_LEN: usize = 8 << 10;
+// Most a length from the server allocates before the bytes are there.
+const MAX_PREALLOC: usize = 64 << 10;
+
+fn invalid(text: &str) -> io::Error{
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+// Host as written, host with port to connect to and path of an http url.
+fn split_url(url: &str) -> io::Result<(String, String, String)>{
+    if url.starts_with("https://"){
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "https is not supported"));
+    }
+    let rest = url.strip_prefix("http://").ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an http url"))?;
+    let (host, path) = match rest.find('/'){
+        Option::Some(i) => (&rest[..i], &rest[i..]),
+        Option::None => (rest, "/"),
+    };
+    if host.is_empty(){
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "url has no host"));
+    }
+    // a colon after the brackets of an IPv6 address starts the port
+    let addr = if host.rsplit(']').next().unwrap_or("").contains(':') { String::from(host) } else { format!("{}:80", host) };
+    Ok((String::from(host), addr, String::from(path)))
+}
+
+fn read_line<R: BufRead>(r: &mut R) -> io::Result<String>{
+    let mut line = Vec::new();
+    r.take(MAX_LINE_LEN as u64).read_until(b'\n', &mut line)?;
+    if line.last() != Option::Some(&b'\n'){
+        return Err(invalid("header line too long or cut off"));
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("header is not UTF-8"))?;
+    Ok(String::from(line.trim_end()))
+}
+
+fn read_chunked<R: BufRead>(r: &mut R, max_len: usize) -> io::Result<Buffer>{
+    let mut body = Vec::new();
+    loop{
+        let line = read_line(r)?;
+        let size = line.split(';').next().unwrap_or("").trim();
+        let len = usize::from_str_radix(size, 16).map_err(|_| invalid("bad chunk size"))?;
+        if len == 0{
+            // trailers
+            while !read_line(r)?.is_empty(){}
+            return Ok(body);
+        }
+        if len > max_len - body.len(){
+            return Err(invalid("body too long"));
+        }
+        read_len(r, len, &mut body)?;
+        if !read_line(r)?.is_empty(){
+            return Err(invalid("chunk does not end in a line break"));
+        }
+    }
+}
+
+// Read len more bytes into body, growing it as they arrive.
+fn read_len<R: Read>(r: &mut R, len: usize, body: &mut Buffer) -> io::Result<()>{
+    let start = body.len();
+    body.reserve(len.min(MAX_PREALLOC));
+    r.take(len as u64).read_to_end(body)?;
+    if body.len() - start < len{
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "body cut off"));
+    }
+    Ok(())
+}
+
+enum Response{
+    Body(Buffer),
+    Redirect(String),
+}
+
+fn get(url: &str, max_len: usize) -> io::Result<Response>{
+    let (host, addr, path) = split_url(url)?;
+    let stream = TcpStream::connect(&addr)?;
+    stream.set_read_timeout(Option::Some(TIMEOUT))?;
+    stream.set_write_timeout(Option::Some(TIMEOUT))?;
+    let mut w = &stream;
+    write!(w, "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: bin_buffer\r\nAccept-Encoding: identity\r\nConnection: close\r\n\r\n", path, host)?;
+    w.flush()?;
+    let mut r = BufReader::new(&stream);
+    let status = read_line(&mut r)?;
+    let code: u16 = status.split(' ').nth(1).and_then(|c| c.parse().ok()).ok_or_else(|| invalid("bad status line"))?;
+    let mut len = Option::None;
+    let mut chunked = false;
+    let mut location = Option::None;
+    loop{
+        let line = read_line(&mut r)?;
+        if line.is_empty(){
+            break;
+        }
+        let (name, value) = line.split_at(line.find(':').ok_or_else(|| invalid("bad header"))?);
+        let value = value[1..].trim();
+        match name.to_ascii_lowercase().as_str(){
+            "content-length" => len = Option::Some(value.parse::<u64>().map_err(|_| invalid("bad Content-Length"))?),
+            "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+            "location" => location = Option::Some(String::from(value)),
+            _ => {},
+        }
+    }
+    match code{
+        200 => {},
+        301 | 302 | 303 | 307 | 308 => {
+            let location = location.ok_or_else(|| invalid("redirect without Location"))?;
+            // a path on the same server
+            if location.starts_with('/'){
+                return Ok(Response::Redirect(format!("http://{}{}", host, location)));
+            }
+            return Ok(Response::Redirect(location));
+        },
+        404 => return Err(io::Error::new(io::ErrorKind::NotFound, status)),
+        _ => return Err(io::Error::other(status)),
+    }
+    if chunked{
+        return Ok(Response::Body(read_chunked(&mut r, max_len)?));
+    }
+    match len{
+        Option::Some(len) => {
+            let len = usize::try_from(len).ok().filter(|len| *len <= max_len).ok_or_else(|| invalid("body too long"))?;
+            let mut body = Vec::new();
+            read_len(&mut r, len, &mut body)?;
+            Ok(Response::Body(body))
+        },
+        Option::None => {
+            let mut body = Vec::new();
+            r.take(max_len as u64 + 1).read_to_end(&mut body)?;
+            if body.len() > max_len{
+                return Err(invalid("body too long"));
+            }
+            Ok(Response::Body(body))
+        },
+    }
+}
+
+/// GET the body of url, at most DEFAULT_MAX_BODY_LEN bytes.
+/// Fails with NotFound on a 404, with Unsupported for https urls,
+/// with InvalidData if the response is broken or too long and with Other on other statuses.
+pub fn buffer_read_url(url: &str) -> io::Result<Buffer>{
+    buffer_read_url_with_max(url, DEFAULT_MAX_BODY_LEN)
+}
+
+/// GET the body of url, failing with InvalidData if it is longer than max_len.
+pub fn buffer_read_url_with_max(url: &str, max_len: usize) -> io::Result<Buffer>{
+    let mut url = String::from(url);
+    for _ in 0..=MAX_REDIRECTS{
+        match get(&url, max_len)?{
+            Response::Body(body) => return Ok(body),
+            Response::Redirect(next) => url = next,
+        }
+    }
+    Err(invalid("too many redirects"))
+}
+
+#[derive(Default)]
+struct FetchState{
+    result: Option<io::Result<Buffer>>,
+    waker: Option<Waker>,
+}
+
+/// A buffer_read_url running on its own thread, see buffer_read_url_async.
+pub struct UrlFetch{
+    state: Arc<Mutex<FetchState>>,
+}
+
+impl Future for UrlFetch{
+    type Output = io::Result<Buffer>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>{
+        let mut state = self.state.lock().unwrap();
+        match state.result.take(){
+            Option::Some(result) => Poll::Ready(result),
+            Option::None => {
+                state.waker = Option::Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+/// buffer_read_url as a future, for any executor. The fetch starts right away
+/// on a thread of its own and the future is ready when it is done.
+/// # Example
+/// ```no_run
+/// use bin_buffer::http::*;
+/// async fn load() -> std::io::Result<Vec<u8>>{
+///     buffer_read_url_async("http://example.com/assets/level1.bin").await
+/// }
+/// ```
+pub fn buffer_read_url_async(url: &str) -> UrlFetch{
+    let state = Arc::new(Mutex::new(FetchState::default()));
+    let shared = state.clone();
+    let url = String::from(url);
+    let started = std::thread::Builder::new().name(String::from("bin_buffer fetch")).spawn(move ||{
+        let result = buffer_read_url(&url);
+        let mut state = shared.lock().unwrap();
+        state.result = Option::Some(result);
+        if let Option::Some(waker) = state.waker.take(){
+            waker.wake();
+        }
+    });
+    if let Err(e) = started{
+        state.lock().unwrap().result = Option::Some(Err(e));
+    }
+    UrlFetch{ state }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::http::*;
+    use std::net::TcpListener;
+
+    // Answer every connection with the next of responses.
+    fn serve(responses: Vec<Vec<u8>>) -> String{
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move ||{
+            for response in responses{
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut r = BufReader::new(stream.try_clone().unwrap());
+                while read_line(&mut r).unwrap() != ""{}
+                stream.write_all(&response).unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    struct Wake;
+    impl std::task::Wake for Wake{
+        fn wake(self: Arc<Self>){}
+    }
+
+    #[test]
+    fn test_http(){
+        let base = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n\x00\x01\x02\x03".to_vec(),
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n3\r\nabc\r\n2;x=y\r\nde\r\n0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 302 Found\r\nLocation: /moved\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.0 200 OK\r\n\r\nuntil the end".to_vec(),
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n".to_vec(),
+            b"HTTP/1.1 500 Oops\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000\r\n\r\nabc".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nasy".to_vec(),
+        ]);
+        let url = format!("{}/a.bin", base);
+        assert_eq!(vec![0, 1, 2, 3], buffer_read_url(&url).unwrap());
+        assert_eq!(b"abcde".to_vec(), buffer_read_url(&url).unwrap());
+        assert_eq!(b"until the end".to_vec(), buffer_read_url(&url).unwrap());
+        assert_eq!(io::ErrorKind::NotFound, buffer_read_url(&url).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, buffer_read_url_with_max(&url, 10).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::Other, buffer_read_url(&url).unwrap_err().kind());
+        // a large length alone does not allocate the body
+        assert_eq!(io::ErrorKind::UnexpectedEof, buffer_read_url(&url).unwrap_err().kind());
+        // the future
+        let mut fetch = buffer_read_url_async(&url);
+        let waker = Waker::from(Arc::new(Wake));
+        let mut cx = Context::from_waker(&waker);
+        let body = loop{
+            if let Poll::Ready(body) = Pin::new(&mut fetch).poll(&mut cx){
+                break body;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(b"asy".to_vec(), body.unwrap());
+        assert_eq!(io::ErrorKind::Unsupported, buffer_read_url("https://example.com/").unwrap_err().kind());
+        // the Host header is the authority as written, the port only goes to connect
+        let split = |url| split_url(url).unwrap();
+        assert_eq!((String::from("example.com"), String::from("example.com:80"), String::from("/a")), split("http://example.com/a"));
+        assert_eq!((String::from("example.com:8080"), String::from("example.com:8080"), String::from("/")), split("http://example.com:8080"));
+        assert_eq!((String::from("[::1]"), String::from("[::1]:80"), String::from("/")), split("http://[::1]"));
+        assert_eq!(String::from("[::1]:81"), split("http://[::1]:81/").1);
+        assert_eq!(io::ErrorKind::InvalidInput, buffer_read_url("ftp://example.com/").unwrap_err().kind());
+    }
+}
@@ -0,0 +1,75 @@
+//! Spans and events for the `tracing` feature: every traced operation gets a debug span
+//! named after it, holding what it works on, and ends with an event giving the bytes
+//! it handled and how long it took. Without the feature traced only calls f.
+
+/// Run f inside a span for op on what, then report len of the result.
+#[cfg(feature = "tracing")]
+pub(crate) fn traced<R, F, L>(op: &'static str, what: &dyn std::fmt::Display, f: F, len: L) -> R
+    where F: FnOnce() -> R, L: FnOnce(&R) -> usize
+{
+    let span = tracing::debug_span!("bin_buffer", op, what = %what);
+    let _entered = span.enter();
+    let start = std::time::Instant::now();
+    let res = f();
+    tracing::debug!(bytes = len(&res), micros = start.elapsed().as_micros() as u64, "{} done", op);
+    res
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn traced<R, F, L>(_: &'static str, _: &dyn std::fmt::Display, f: F, _: L) -> R
+    where F: FnOnce() -> R, L: FnOnce(&R) -> usize
+{
+    f()
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests{
+    use crate::*;
+    use std::sync::{ Arc, Mutex };
+    use tracing::field::{ Field, Visit };
+    use tracing::span::{ Attributes, Id, Record };
+    use tracing::{ Event, Metadata, Subscriber };
+
+    // Collects the fields of every span and event.
+    struct Collect(Arc<Mutex<Vec<String>>>);
+    struct Fields<'a>(&'a mut String);
+    impl Visit for Fields<'_>{
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug){
+            self.0.push_str(&format!("{}={:?} ", field.name(), value));
+        }
+    }
+    impl Subscriber for Collect{
+        fn enabled(&self, _: &Metadata<'_>) -> bool{ true }
+        fn new_span(&self, span: &Attributes<'_>) -> Id{
+            let mut line = String::new();
+            span.record(&mut Fields(&mut line));
+            self.0.lock().unwrap().push(line);
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>){}
+        fn record_follows_from(&self, _: &Id, _: &Id){}
+        fn event(&self, event: &Event<'_>){
+            let mut line = String::new();
+            event.record(&mut Fields(&mut line));
+            self.0.lock().unwrap().push(line);
+        }
+        fn enter(&self, _: &Id){}
+        fn exit(&self, _: &Id){}
+    }
+
+    #[test]
+    fn test_tracing(){
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(Collect(lines.clone()), ||{
+            let bytes = encode(&vec![1u32, 2]);
+            assert_eq!(Some(vec![1u32, 2]), decode::<Vec<u32>>(&bytes));
+        });
+        let lines = lines.lock().unwrap();
+        assert_eq!(4, lines.len());
+        assert!(lines[0].starts_with("op=\"encode\" what=alloc::vec::Vec<u32>"));
+        assert!(lines[1].contains("bytes=16 "));
+        assert!(lines[2].starts_with("op=\"decode\""));
+        assert!(lines[3].contains("bytes=16 "));
+    }
+}
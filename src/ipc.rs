@@ -0,0 +1,195 @@
+//! Exchanging values between local processes, such as a daemon and its command line tool.
+//! A Connection wraps any stream and starts with a handshake in which both sides send
+//! a magic number and their wire format version, so mismatched builds fail right away
+//! instead of misreading each other. After that values go as frames, like in net.
+//! On unix Listener and connect use Unix domain sockets.
+//! On windows connect_pipe opens the client end of a named pipe,
+//! the server end has no std API and has to be created by the caller.
+//! # Example
+//! ```
+//! use bin_buffer::ipc::*;
+//! # #[cfg(unix)]{
+//! let path = std::env::temp_dir().join("bin_buffer_ipc_doc.sock");
+//! let listener = Listener::bind(&path).unwrap();
+//! let client = std::thread::spawn(move ||{
+//!     let mut conn = connect(&path).unwrap();
+//!     conn.send(&String::from("status")).unwrap();
+//!     conn.recv::<u32>().unwrap()
+//! });
+//! let mut conn = listener.accept().unwrap();
+//! assert_eq!(String::from("status"), conn.recv::<String>().unwrap());
+//! conn.send(&1u32).unwrap();
+//! assert_eq!(1, client.join().unwrap());
+//! # }
+//! ```
+use std::io::{ self, Read, Write };
+use crate::Bufferable;
+use crate::net::{ recv_from, send_over };
+
+/// Sent first in the handshake.
+pub const MAGIC: [u8; 4] = *b"BINB";
+/// Version of the wire format used by this build, the default for the handshake.
+pub const WIRE_VERSION: u32 = 1;
+
+/// Write our magic number and version, then read and check the other side's.
+/// Fails with InvalidData if the magic is wrong or the versions differ.
+pub fn handshake<S: Read + Write>(stream: &mut S, version: u32) -> io::Result<()>{
+    let mut hello = [0u8; 8];
+    hello[..4].copy_from_slice(&MAGIC);
+    hello[4..].copy_from_slice(&version.to_be_bytes());
+    stream.write_all(&hello)?;
+    stream.flush()?;
+    let mut other = [0u8; 8];
+    stream.read_exact(&mut other)?;
+    if other[..4] != MAGIC{
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a bin_buffer connection"));
+    }
+    let mut theirs = [0u8; 4];
+    theirs.copy_from_slice(&other[4..]);
+    let theirs = u32::from_be_bytes(theirs);
+    if theirs != version{
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("wire format version {} does not match ours, {}", theirs, version)));
+    }
+    Ok(())
+}
+
+/// A stream on which the handshake is done.
+pub struct Connection<S: Read + Write>{
+    stream: S,
+}
+
+impl<S: Read + Write> Connection<S>{
+    /// Do the handshake with WIRE_VERSION.
+    pub fn new(stream: S) -> io::Result<Self>{
+        Self::with_version(stream, WIRE_VERSION)
+    }
+    /// Do the handshake with a version of your own, for when the messages change.
+    pub fn with_version(mut stream: S, version: u32) -> io::Result<Self>{
+        handshake(&mut stream, version)?;
+        Ok(Self{ stream })
+    }
+    /// Send a value.
+    pub fn send<T: Bufferable>(&mut self, x: &T) -> io::Result<()>{
+        send_over(x, &mut self.stream)
+    }
+    /// Receive a value.
+    pub fn recv<T: Bufferable>(&mut self) -> io::Result<T>{
+        recv_from(&mut self.stream)
+    }
+    /// The stream, to set timeouts on for example.
+    pub fn get_ref(&self) -> &S{
+        &self.stream
+    }
+    /// Give back the stream.
+    pub fn into_inner(self) -> S{
+        self.stream
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::*;
+
+#[cfg(unix)]
+mod unix{
+    use std::io;
+    use std::os::unix::net::{ UnixListener, UnixStream };
+    use std::path::{ Path, PathBuf };
+    use super::Connection;
+
+    /// Connect to the Unix socket at path.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Connection<UnixStream>>{
+        Connection::new(UnixStream::connect(path)?)
+    }
+
+    /// Listens on a Unix socket, removing the socket file when dropped.
+    pub struct Listener{
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Listener{
+        /// Listen at path, replacing a socket file left behind by an earlier run.
+        pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self>{
+            let path = path.as_ref().to_path_buf();
+            if path.exists(){
+                std::fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)?;
+            Ok(Self{ listener, path })
+        }
+        /// Wait for the next client and do the handshake.
+        pub fn accept(&self) -> io::Result<Connection<UnixStream>>{
+            let (stream, _) = self.listener.accept()?;
+            Connection::new(stream)
+        }
+    }
+
+    impl Drop for Listener{
+        fn drop(&mut self){
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+/// Open the client end of the named pipe with the given name, as in \\.\pipe\name.
+#[cfg(windows)]
+pub fn connect_pipe(name: &str) -> io::Result<Connection<std::fs::File>>{
+    let file = std::fs::OpenOptions::new().read(true).write(true)
+        .open(format!(r"\\.\pipe\{}", name))?;
+    Connection::new(file)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ipc::*;
+
+    // both ends in one process, the handshake is written before it is read
+    struct Pipe{ input: io::Cursor<Vec<u8>>, output: Vec<u8> }
+    impl Read for Pipe{
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>{ self.input.read(buf) }
+    }
+    impl Write for Pipe{
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize>{ self.output.write(buf) }
+        fn flush(&mut self) -> io::Result<()>{ Ok(()) }
+    }
+
+    #[test]
+    fn test_ipc(){
+        let mut hello = b"BINB".to_vec();
+        hello.extend_from_slice(&WIRE_VERSION.to_be_bytes());
+        crate::net::send_over(&5u8, &mut hello).unwrap();
+        let pipe = Pipe{ input: io::Cursor::new(hello.clone()), output: Vec::new() };
+        let mut conn = Connection::new(pipe).unwrap();
+        assert_eq!(5u8, conn.recv::<u8>().unwrap());
+        conn.send(&6u8).unwrap();
+        let mut expected = hello[..8].to_vec();
+        crate::net::send_over(&6u8, &mut expected).unwrap();
+        assert_eq!(expected, conn.into_inner().output);
+        // other version and no magic
+        let pipe = Pipe{ input: io::Cursor::new(hello.clone()), output: Vec::new() };
+        let err = Connection::with_version(pipe, 2).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert!(err.to_string().contains("version 1"));
+        let pipe = Pipe{ input: io::Cursor::new(vec![0; 8]), output: Vec::new() };
+        assert_eq!(io::ErrorKind::InvalidData, Connection::new(pipe).err().unwrap().kind());
+        // over a unix socket
+        #[cfg(unix)]{
+            let path = std::env::temp_dir().join("bin_buffer_ipc_test.sock");
+            let listener = Listener::bind(&path).unwrap();
+            let client_path = path.clone();
+            let client = std::thread::spawn(move ||{
+                let mut conn = connect(&client_path).unwrap();
+                for i in 0..5u64{
+                    conn.send(&vec![i; 3]).unwrap();
+                }
+            });
+            let mut conn = listener.accept().unwrap();
+            for i in 0..5u64{
+                assert_eq!(vec![i; 3], conn.recv::<Vec<u64>>().unwrap());
+            }
+            client.join().unwrap();
+            drop(listener);
+            assert!(!path.exists());
+        }
+    }
+}
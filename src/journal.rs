@@ -0,0 +1,263 @@
+//! An append only journal file, safe to build a write ahead log on.
+//! Every record is written as its length (u64), the CRC-32 of its bytes (u32) and the bytes.
+//! When a crash leaves half a record at the end, or the tail got corrupted, Journal::open
+//! finds the first record that does not check out and cuts the file off right before it,
+//! so the records that are left are exactly the ones that were written completely.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::journal::*;
+//! let path = std::env::temp_dir().join("bin_buffer_journal_doc.log");
+//! let _ = std::fs::remove_file(&path);
+//! let mut journal = Journal::open(&path).unwrap();
+//! journal.append(&String::from("create")).unwrap();
+//! journal.append(&String::from("update")).unwrap();
+//! // a crash halfway through the next record
+//! buffer_write_file_append(&path, &[0, 0, 0, 0, 0, 0, 0, 9, 1]);
+//! let journal = Journal::open(&path).unwrap();
+//! assert_eq!(9, journal.truncated());
+//! let values: Vec<Option<String>> = journal.values().collect();
+//! assert_eq!(vec![Some(String::from("create")), Some(String::from("update"))], values);
+//! ```
+use std::convert::TryFrom;
+use std::fs::{ File, OpenOptions };
+use std::io::{ self, Read, Seek, SeekFrom, Write };
+use crate::{ Buffer, Bufferable, ReadBuffer };
+pub use crate::checksum::crc32;
+
+/// Bytes in front of every record: the length and the CRC.
+pub const RECORD_HEADER_LEN: usize = 12;
+
+// Records that check out, as (start, end) of their bytes, and where the last one ends.
+fn scan(bytes: &[u8]) -> (Vec<(usize, usize)>, usize){
+    let mut records = Vec::new();
+    let mut pos = 0;
+    while bytes.len() - pos >= RECORD_HEADER_LEN{
+        let len = u64::from_be_bytes(<[u8; 8]>::try_from(&bytes[pos..pos + 8]).unwrap());
+        let crc = u32::from_be_bytes(<[u8; 4]>::try_from(&bytes[pos + 8..pos + 12]).unwrap());
+        let start = pos + RECORD_HEADER_LEN;
+        let end = match usize::try_from(len).ok().and_then(|len| start.checked_add(len)){
+            Option::Some(end) if end <= bytes.len() => end,
+            _ => break,
+        };
+        if crc32(&bytes[start..end]) != crc{
+            break;
+        }
+        records.push((start, end));
+        pos = end;
+    }
+    (records, pos)
+}
+
+/// What verify found in a journal file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JournalReport{
+    /// Number of records that check out.
+    pub records: usize,
+    /// Bytes up to the end of the last record that checks out.
+    pub valid_len: u64,
+    /// Bytes in the file.
+    pub file_len: u64,
+}
+
+impl JournalReport{
+    /// Whether every byte of the file belongs to a record that checks out.
+    pub fn is_intact(&self) -> bool{
+        self.valid_len == self.file_len
+    }
+}
+
+fn report(bytes: &[u8]) -> JournalReport{
+    let (records, valid) = scan(bytes);
+    JournalReport{ records: records.len(), valid_len: valid as u64, file_len: bytes.len() as u64 }
+}
+
+/// Check the framing and the CRC of every record of the journal at path, without changing it.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::journal::*;
+/// let path = std::env::temp_dir().join("bin_buffer_verify_doc.log");
+/// let _ = std::fs::remove_file(&path);
+/// Journal::open(&path).unwrap().append(&1u32).unwrap();
+/// assert!(verify(&path).unwrap().is_intact());
+/// buffer_write_file_append(&path, &[0, 0, 0]);
+/// let report = verify(&path).unwrap();
+/// assert_eq!((1, 16, 19), (report.records, report.valid_len, report.file_len));
+/// repair(&path).unwrap();
+/// assert!(verify(&path).unwrap().is_intact());
+/// ```
+pub fn verify(path: &std::path::Path) -> io::Result<JournalReport>{
+    Ok(report(&std::fs::read(path)?))
+}
+
+/// Cut a broken tail off the journal at path, as Journal::open does.
+/// Gives the report of the file from before the repair.
+pub fn repair(path: &std::path::Path) -> io::Result<JournalReport>{
+    let report = verify(path)?;
+    if !report.is_intact(){
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(report.valid_len)?;
+        file.sync_data()?;
+    }
+    Ok(report)
+}
+
+/// Write the records of the journal at path that check out to a new journal at to,
+/// leaving the damaged file as it is.
+pub fn salvage(path: &std::path::Path, to: &std::path::Path) -> io::Result<JournalReport>{
+    let bytes = std::fs::read(path)?;
+    let report = report(&bytes);
+    let mut file = File::create(to)?;
+    file.write_all(&bytes[..report.valid_len as usize])?;
+    file.sync_data()?;
+    Ok(report)
+}
+
+/// An open journal file with the records that were in it.
+pub struct Journal{
+    file: File,
+    bytes: Buffer,
+    records: Vec<(usize, usize)>,
+    truncated: u64,
+}
+
+impl Journal{
+    /// Open the journal at path, creating it if needed.
+    /// A broken tail is cut off the file, see truncated.
+    pub fn open(path: &std::path::Path) -> io::Result<Self>{
+        crate::instrument::traced("open journal", &path.display(), || Self::open_file(path),
+            |journal| journal.as_ref().map_or(0, |j| j.bytes.len()))
+    }
+
+    fn open_file(path: &std::path::Path) -> io::Result<Self>{
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let (records, valid) = scan(&bytes);
+        let truncated = (bytes.len() - valid) as u64;
+        if truncated > 0{
+            bytes.truncate(valid);
+            file.set_len(valid as u64)?;
+            file.sync_data()?;
+        }
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self{ file, bytes, records, truncated })
+    }
+    /// How many bytes of broken tail open cut off, 0 if the file was whole.
+    pub fn truncated(&self) -> u64{
+        self.truncated
+    }
+    /// Number of records.
+    pub fn len(&self) -> usize{
+        self.records.len()
+    }
+    /// Whether there are no records.
+    pub fn is_empty(&self) -> bool{
+        self.records.is_empty()
+    }
+    /// The bytes of every record, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = &[u8]>{
+        self.records.iter().map(move |(start, end)| &self.bytes[*start..*end])
+    }
+    /// The bytes of record i, None if there are not that many.
+    pub fn record(&self, i: usize) -> Option<&[u8]>{
+        self.records.get(i).map(|(start, end)| &self.bytes[*start..*end])
+    }
+    /// Every record read as one T, None for records that do not decode
+    /// or have bytes left over.
+    pub fn values<T: Bufferable>(&self) -> impl Iterator<Item = Option<T>> + '_{
+        self.records().map(|bytes|{
+            let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+            let x = T::from_buffer(&mut buf)?;
+            if buf.remaining() != 0{
+                return Option::None;
+            }
+            Option::Some(x)
+        })
+    }
+    /// Append a record holding bytes, written to the file with a single write.
+    /// It is only sure to survive a crash after sync.
+    pub fn append_bytes(&mut self, bytes: &[u8]) -> io::Result<()>{
+        crate::instrument::traced("append journal record", &self.records.len(), || self.write_record(bytes), |_| bytes.len())
+    }
+
+    fn write_record(&mut self, bytes: &[u8]) -> io::Result<()>{
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + bytes.len());
+        (bytes.len() as u64).into_buffer(&mut record);
+        crc32(bytes).into_buffer(&mut record);
+        record.extend_from_slice(bytes);
+        self.file.write_all(&record)?;
+        let start = self.bytes.len() + RECORD_HEADER_LEN;
+        self.bytes.extend_from_slice(&record);
+        self.records.push((start, start + bytes.len()));
+        Ok(())
+    }
+    /// Append a record holding x.
+    pub fn append<T: Bufferable>(&mut self, x: &T) -> io::Result<()>{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.append_bytes(&bytes)
+    }
+    /// Wait until everything appended is on disk.
+    pub fn sync(&mut self) -> io::Result<()>{
+        let len = self.bytes.len();
+        crate::instrument::traced("sync journal", &len, || self.file.sync_data(), |_| len)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::journal::*;
+
+    #[test]
+    fn test_journal(){
+        assert_eq!(0, crc32(&[]));
+        let path = std::env::temp_dir().join("bin_buffer_journal_test.log");
+        let _ = std::fs::remove_file(&path);
+        let mut journal = Journal::open(&path).unwrap();
+        assert!(journal.is_empty());
+        journal.append(&vec![1u32, 2]).unwrap();
+        journal.append_bytes(&[]).unwrap();
+        journal.append(&7u8).unwrap();
+        journal.sync().unwrap();
+        assert_eq!(vec![16, 0, 1], journal.records().map(|r| r.len()).collect::<Vec<_>>());
+        let whole = buffer_read_file(&path).unwrap();
+        assert_eq!(3 * RECORD_HEADER_LEN + 17, whole.len());
+        drop(journal);
+        // reopening a whole file changes nothing, and appending goes after the old records
+        let mut journal = Journal::open(&path).unwrap();
+        assert_eq!((3, 0), (journal.len(), journal.truncated()));
+        journal.append(&9u8).unwrap();
+        drop(journal);
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(vec![Some(7u8), Some(9)], journal.values::<u8>().skip(2).collect::<Vec<_>>());
+        assert_eq!(None, journal.values::<u8>().next().unwrap());
+        drop(journal);
+        // a flipped bit in the third record drops it and everything after it
+        let mut bad = buffer_read_file(&path).unwrap();
+        bad[2 * RECORD_HEADER_LEN + 16 + 12] ^= 1;
+        buffer_write_file(&path, &bad);
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(2, journal.len());
+        assert_eq!((RECORD_HEADER_LEN as u64 + 1) * 2, journal.truncated());
+        assert_eq!(2 * RECORD_HEADER_LEN as u64 + 16, std::fs::metadata(&path).unwrap().len());
+        drop(journal);
+        // a length that goes past the end
+        buffer_write_file_append(&path, &[0xff; 12]);
+        assert_eq!(2, Journal::open(&path).unwrap().len());
+        // verify, salvage and repair
+        buffer_write_file_append(&path, &[1; 5]);
+        let report = verify(&path).unwrap();
+        assert_eq!(JournalReport{ records: 2, valid_len: 2 * RECORD_HEADER_LEN as u64 + 16, file_len: 2 * RECORD_HEADER_LEN as u64 + 21 }, report);
+        let saved = std::env::temp_dir().join("bin_buffer_journal_salvaged.log");
+        assert_eq!(report, salvage(&path, &saved).unwrap());
+        assert!(verify(&saved).unwrap().is_intact());
+        assert_eq!(report.file_len, std::fs::metadata(&path).unwrap().len());
+        assert_eq!(report, repair(&path).unwrap());
+        assert_eq!(buffer_read_file(&saved), buffer_read_file(&path));
+        let _ = std::fs::remove_file(&saved);
+        let _ = std::fs::remove_file(&path);
+    }
+}
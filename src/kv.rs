@@ -0,0 +1,343 @@
+//! A small persistent key-value store on a journal file.
+//! Every put and delete is appended to the journal as a record: a u8 that is 0 for a put
+//! and 1 for a delete, the key as a String and for a put the bytes of the value.
+//! An index in memory points every key at its last put, so a get does not touch the file.
+//! Records that are overwritten or deleted stay in the file until it is compacted: once there
+//! are more of them than live keys and at least COMPACT_MIN_GARBAGE, a background thread
+//! writes the live keys to a new journal and swaps it in, while puts and deletes go on.
+//! # Example
+//! ```
+//! use bin_buffer::kv::*;
+//! let path = std::env::temp_dir().join("bin_buffer_kv_doc.log");
+//! let _ = std::fs::remove_file(&path);
+//! let store = KvStore::open(&path).unwrap();
+//! store.put("ann", &(12u32, String::from("red"))).unwrap();
+//! store.put("bob", &(7u32, String::from("blue"))).unwrap();
+//! assert!(store.delete("bob").unwrap());
+//! drop(store);
+//! let store = KvStore::open(&path).unwrap();
+//! assert_eq!(Some((12u32, String::from("red"))), store.get("ann"));
+//! assert_eq!(None, store.get::<(u32, String)>("bob"));
+//! assert_eq!(vec!["ann"], store.keys());
+//! ```
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex, MutexGuard };
+use std::thread::JoinHandle;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use crate::journal::Journal;
+use crate::store::BufferStore;
+
+/// Overwritten and deleted records there have to be at least before the store is compacted.
+pub const COMPACT_MIN_GARBAGE: usize = 1024;
+
+const PUT: u8 = 0;
+const DELETE: u8 = 1;
+
+fn record(key: &str, value: Option<&[u8]>) -> Buffer{
+    let mut record = Vec::with_capacity(9 + key.len() + value.map_or(0, |v| v.len()));
+    if value.is_some() { PUT } else { DELETE }.into_buffer(&mut record);
+    String::from(key).into_buffer(&mut record);
+    if let Option::Some(value) = value{
+        record.extend_from_slice(value);
+    }
+    record
+}
+
+// The key of a record, whether it is a put and where its value starts.
+fn parse(record: &[u8]) -> Option<(String, bool, usize)>{
+    let mut buf = ReadBuffer::borrowed(record, Default::default());
+    let op = u8::from_buffer(&mut buf)?;
+    let key = String::from_buffer(&mut buf)?;
+    match op{
+        PUT => Option::Some((key, true, record.len() - buf.remaining())),
+        DELETE if buf.remaining() == 0 => Option::Some((key, false, record.len())),
+        _ => Option::None,
+    }
+}
+
+struct Inner{
+    path: PathBuf,
+    journal: Journal,
+    // key to the record of its last put
+    index: BTreeMap<String, usize>,
+    garbage: usize,
+    // puts and deletes done while a compaction is writing the new file
+    pending: Option<Vec<Buffer>>,
+}
+
+impl Inner{
+    fn load(path: &Path) -> io::Result<Self>{
+        let journal = Journal::open(path)?;
+        let mut index = BTreeMap::new();
+        let mut garbage = 0;
+        for (i, bytes) in journal.records().enumerate(){
+            let (key, put, _) = parse(bytes).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a key-value record"))?;
+            let old = if put { index.insert(key, i) } else { garbage += 1; index.remove(&key) };
+            if old.is_some(){
+                garbage += 1;
+            }
+        }
+        Ok(Self{ path: path.to_path_buf(), journal, index, garbage, pending: Option::None })
+    }
+    fn append(&mut self, record: Buffer) -> io::Result<()>{
+        self.journal.append_bytes(&record)?;
+        if let Option::Some(pending) = &mut self.pending{
+            pending.push(record);
+        }
+        Ok(())
+    }
+    fn wants_compaction(&self) -> bool{
+        self.pending.is_none() && self.garbage >= COMPACT_MIN_GARBAGE && self.garbage > self.index.len()
+    }
+}
+
+fn compact_path(path: &Path) -> PathBuf{
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".compact");
+    path.with_file_name(name)
+}
+
+fn write_records(path: &Path, records: &[Buffer]) -> io::Result<Journal>{
+    let _ = std::fs::remove_file(path);
+    let mut journal = Journal::open(path)?;
+    for record in records{
+        journal.append_bytes(record)?;
+    }
+    journal.sync()?;
+    Ok(journal)
+}
+
+fn compact(inner: &Mutex<Inner>) -> io::Result<()>{
+    let (path, live) = {
+        let mut inner = lock(inner)?;
+        if inner.pending.is_some(){
+            return Ok(());
+        }
+        inner.pending = Option::Some(Vec::new());
+        let live: Vec<Buffer> = inner.index.values().filter_map(|&i| inner.journal.record(i).map(|r| r.to_vec())).collect();
+        (inner.path.clone(), live)
+    };
+    let to = compact_path(&path);
+    let written = write_records(&to, &live);
+    let mut inner = lock(inner)?;
+    let pending = inner.pending.take().unwrap_or_default();
+    let mut journal = written?;
+    for record in &pending{
+        journal.append_bytes(record)?;
+    }
+    journal.sync()?;
+    drop(journal);
+    std::fs::rename(&to, &path)?;
+    *inner = Inner::load(&path)?;
+    Ok(())
+}
+
+fn lock(inner: &Mutex<Inner>) -> io::Result<MutexGuard<'_, Inner>>{
+    inner.lock().map_err(|_| io::Error::other("key-value store poisoned"))
+}
+
+/// A key-value store kept in a journal file. Can be shared between threads.
+pub struct KvStore{
+    inner: Arc<Mutex<Inner>>,
+    compaction: Mutex<Option<JoinHandle<io::Result<()>>>>,
+}
+
+impl KvStore{
+    /// Open the store in the journal at path, creating it if needed.
+    /// A torn tail is cut off as Journal::open does.
+    /// Fails with InvalidData if a record is not a put or delete.
+    pub fn open(path: &Path) -> io::Result<Self>{
+        Ok(Self{ inner: Arc::new(Mutex::new(Inner::load(path)?)), compaction: Mutex::new(Option::None) })
+    }
+    fn change(&self, key: &str, value: Option<&[u8]>) -> io::Result<bool>{
+        let mut inner = lock(&self.inner)?;
+        let existed = inner.index.contains_key(key);
+        if value.is_none() && !existed{
+            return Ok(false);
+        }
+        inner.append(record(key, value))?;
+        if value.is_some(){
+            let i = inner.journal.len() - 1;
+            inner.index.insert(String::from(key), i);
+        }
+        else{
+            inner.index.remove(key);
+            inner.garbage += 1;
+        }
+        if existed{
+            inner.garbage += 1;
+        }
+        let compact = inner.wants_compaction();
+        drop(inner);
+        if compact{
+            self.start_compaction();
+        }
+        Ok(existed)
+    }
+    /// Keep bytes under key, replacing what was there. Returns whether there was something.
+    /// It is only sure to survive a crash after sync.
+    pub fn put_bytes(&self, key: &str, bytes: &[u8]) -> io::Result<bool>{
+        self.change(key, Option::Some(bytes))
+    }
+    /// Keep x under key.
+    pub fn put<T: Bufferable>(&self, key: &str, x: &T) -> io::Result<bool>{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.put_bytes(key, &bytes)
+    }
+    /// Remove key. Returns whether it was there.
+    pub fn delete(&self, key: &str) -> io::Result<bool>{
+        self.change(key, Option::None)
+    }
+    /// The bytes under key.
+    pub fn get_bytes(&self, key: &str) -> Option<Buffer>{
+        let inner = lock(&self.inner).ok()?;
+        let record = inner.journal.record(*inner.index.get(key)?)?;
+        let (_, _, start) = parse(record)?;
+        Option::Some(record[start..].to_vec())
+    }
+    /// What is under key read as one T. None if it is missing, does not decode or has bytes left over.
+    pub fn get<T: Bufferable>(&self, key: &str) -> Option<T>{
+        let bytes = self.get_bytes(key)?;
+        let mut buf = ReadBuffer::borrowed(&bytes, Default::default());
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// Whether there is something under key.
+    pub fn contains(&self, key: &str) -> bool{
+        lock(&self.inner).is_ok_and(|inner| inner.index.contains_key(key))
+    }
+    /// All keys, sorted.
+    pub fn keys(&self) -> Vec<String>{
+        lock(&self.inner).map(|inner| inner.index.keys().cloned().collect()).unwrap_or_default()
+    }
+    /// Number of keys.
+    pub fn len(&self) -> usize{
+        lock(&self.inner).map(|inner| inner.index.len()).unwrap_or(0)
+    }
+    /// Whether there are no keys.
+    pub fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+    /// Overwritten and deleted records still in the file.
+    pub fn garbage(&self) -> usize{
+        lock(&self.inner).map(|inner| inner.garbage).unwrap_or(0)
+    }
+    /// Wait until everything put and deleted is on disk.
+    pub fn sync(&self) -> io::Result<()>{
+        lock(&self.inner)?.journal.sync()
+    }
+    /// Compact the file now, waiting until it is done.
+    pub fn compact(&self) -> io::Result<()>{
+        self.wait_for_compaction()?;
+        compact(&self.inner)
+    }
+    fn start_compaction(&self){
+        let mut running = match self.compaction.lock(){
+            Ok(r) => r,
+            Err(_) => return,
+        };
+        if running.as_ref().is_some_and(|t| !t.is_finished()){
+            return;
+        }
+        if let Option::Some(done) = running.take(){
+            let _ = done.join();
+        }
+        let inner = self.inner.clone();
+        *running = Option::Some(std::thread::spawn(move || compact(&inner)));
+    }
+    /// Wait for a background compaction to finish, with its result.
+    pub fn wait_for_compaction(&self) -> io::Result<()>{
+        let running = self.compaction.lock().map_err(|_| io::Error::other("key-value store poisoned"))?.take();
+        match running.map(|t| t.join()){
+            Option::None => Ok(()),
+            Option::Some(Ok(result)) => result,
+            Option::Some(Err(_)) => Err(io::Error::other("compaction panicked")),
+        }
+    }
+}
+
+impl Drop for KvStore{
+    fn drop(&mut self){
+        let _ = self.wait_for_compaction();
+    }
+}
+
+impl BufferStore for KvStore{
+    fn get(&self, key: &str) -> Option<Buffer>{
+        self.get_bytes(key)
+    }
+    fn put(&mut self, key: &str, bytes: &[u8]) -> bool{
+        self.put_bytes(key, bytes).is_ok()
+    }
+    fn list(&self) -> Vec<String>{
+        self.keys()
+    }
+    fn remove(&mut self, key: &str) -> bool{
+        self.delete(key).unwrap_or(false)
+    }
+    fn contains(&self, key: &str) -> bool{
+        KvStore::contains(self, key)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::kv::*;
+
+    #[test]
+    fn test_kv(){
+        let path = std::env::temp_dir().join("bin_buffer_kv_test.log");
+        let _ = std::fs::remove_file(&path);
+        let store = KvStore::open(&path).unwrap();
+        assert!(store.is_empty());
+        assert!(!store.put("a", &1u32).unwrap());
+        assert!(store.put("a", &2u32).unwrap());
+        assert!(!store.put_bytes("", &[]).unwrap());
+        assert!(!store.delete("missing").unwrap());
+        assert_eq!(Some(2u32), store.get("a"));
+        assert_eq!(None, store.get::<u8>("a"));
+        assert_eq!(Some(Vec::new()), store.get_bytes(""));
+        assert_eq!((2, 1), (store.len(), store.garbage()));
+        store.sync().unwrap();
+        drop(store);
+        let mut store = KvStore::open(&path).unwrap();
+        assert_eq!((vec![String::new(), String::from("a")], 1), (store.keys(), store.garbage()));
+        assert!(BufferStore::remove(&mut store, ""));
+        store.compact().unwrap();
+        assert_eq!(0, store.garbage());
+        assert_eq!(Some(2u32), store.load("a"));
+        // enough overwrites start a compaction in the background
+        for i in 0..3 * COMPACT_MIN_GARBAGE as u32{
+            store.put(&format!("k{}", i % 10), &i).unwrap();
+        }
+        store.wait_for_compaction().unwrap();
+        assert!(store.garbage() < 2 * COMPACT_MIN_GARBAGE);
+        let last = 3 * COMPACT_MIN_GARBAGE as u32 - 1;
+        assert_eq!(Some(last), store.get(&format!("k{}", last % 10)));
+        store.compact().unwrap();
+        drop(store);
+        let len = std::fs::metadata(&path).unwrap().len();
+        assert!(len < 1000, "{}", len);
+        let store = KvStore::open(&path).unwrap();
+        assert_eq!((11, 0), (store.len(), store.garbage()));
+        drop(store);
+        buffer_write_file_append(&path, &journal_record(&[7, 7]));
+        assert_eq!(io::ErrorKind::InvalidData, KvStore::open(&path).err().unwrap().kind());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn journal_record(bytes: &[u8]) -> Vec<u8>{
+        let mut record = Vec::new();
+        (bytes.len() as u64).into_buffer(&mut record);
+        crate::journal::crc32(bytes).into_buffer(&mut record);
+        record.extend_from_slice(bytes);
+        record
+    }
+}
@@ -17,508 +17,3609 @@
 //! assert_eq!(Some(y), String::from_buffer(&mut buffer));
 //! assert_eq!(Some(z), <(f64,f64)>::from_buffer(&mut buffer));
 //! ```
+#[cfg(feature = "fs")]
 use std::io::prelude::*;
+#[cfg(feature = "fs")]
 use std::fs::OpenOptions;
+use std::convert::TryFrom;
+use std::borrow::Cow;
+use std::collections::{ HashMap, HashSet, BTreeMap, BTreeSet, VecDeque };
+use std::hash::{ Hash, BuildHasher };
+
+pub mod stats;
+pub mod chunked;
+pub mod tagged;
+pub mod named;
+pub mod varint;
+pub mod fields;
+pub mod compat;
+pub mod schema;
+pub mod net;
+pub use net::{ ReadBufferableExt, WriteBufferableExt };
+pub mod ipc;
+pub mod handshake;
+pub mod channel;
+pub mod recording;
+pub mod vectors;
+pub mod fixed;
+pub mod registry;
+pub mod graph;
+pub mod envelope;
+pub mod rpc;
+pub mod enums;
+pub mod columnar;
+pub mod sections;
+pub mod container;
+pub mod merkle;
+pub mod archive;
+pub mod store;
+pub mod codegen;
+pub mod describe;
+pub mod testing;
+pub mod patch;
+pub mod delta;
+pub mod csv;
+pub mod consts;
+pub mod checksum;
+pub mod pipeline;
+pub mod flash;
+#[cfg(feature = "fs")]
+pub mod journal;
+#[cfg(feature = "fs")]
+pub mod kv;
+#[cfg(feature = "fs")]
+pub mod wal;
+#[cfg(feature = "fs")]
+pub mod saves;
+#[cfg(feature = "notify")]
+pub mod watch;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "tungstenite")]
+pub mod ws;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
+#[cfg(feature = "shm")]
+pub mod shm;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "zeroize")]
+pub mod secret;
+#[cfg(feature = "ed25519")]
+pub mod sign;
+#[cfg(feature = "entropy")]
+pub mod entropy;
+#[cfg(feature = "compression")]
+pub mod compress;
+#[cfg(feature = "serde")]
+pub mod serde_buffer;
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(feature = "async")]
+pub use async_io::{ AsyncBufferWriter, AsyncBufferReader };
+#[cfg(all(feature = "async", feature = "fs"))]
+pub use async_io::{ buffer_read_file_async, buffer_write_file_async };
+#[cfg(feature = "npy")]
+pub mod npy;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod macros;
+mod instrument;
+mod write_buffer;
+pub use write_buffer::{ WriteBuffer, LenPrefix };
+mod stream;
+pub use stream::{ BufferWriter, BufferReader };
+mod shared;
+pub use shared::SharedReadBuffer;
+mod budget;
+pub use budget::AllocTracker;
+mod sparse;
+pub use sparse::SparseVec;
+mod bits;
+pub use bits::BitVec;
+mod dict;
+pub use dict::DictEncoded;
+mod versioned;
+pub use versioned::{ Versioned, Migrate, Migrations, Migrator };
+#[cfg(feature = "derive")]
+pub use bin_buffer_derive::Bufferable;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "rayon")]
+pub mod par;
+#[cfg(feature = "bumpalo")]
+pub mod arena;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "num-complex")]
+mod complex;
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "half")]
+mod float16;
+#[cfg(feature = "time")]
+mod datetime;
 
 /// Buffer: a Vector of bytes
 pub type Buffer = Vec<u8>;
 
+/// Destination that Bufferable objects are written to.
+/// Buffer is the usual one, other Sinks let you encode without collecting
+/// everything in a single Vec first.
+pub trait Sink{
+    /// Add bytes to the end.
+    fn write_bytes(&mut self, bytes: &[u8]);
+    /// Get ready for about this many more bytes. Does nothing by default.
+    fn reserve(&mut self, additional: usize){
+        let _ = additional;
+    }
+    /// The Vec behind this Sink, if it is one, so bulk writes can fill it directly.
+    fn as_vec(&mut self) -> Option<&mut Buffer>{
+        Option::None
+    }
+    /// The byte order numbers and lengths are written in, Big unless this is an EndianSink.
+    fn endian(&self) -> Endian{
+        Endian::Big
+    }
+    /// Copy x to the end and give back the sink, so a message is written in one chain.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// buffer.put(&1u16).put(&String::from("name")).put(&(0.5f32, 1.5f32));
+    /// let mut read = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Some((1u16, String::from("name"), (0.5f32, 1.5f32))), Bufferable::from_buffer(&mut read));
+    /// ```
+    fn put<T: Bufferable>(&mut self, x: &T) -> &mut Self where Self: Sized{
+        x.copy_into_buffer(self);
+        self
+    }
+}
+
+impl Sink for Buffer{
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.extend_from_slice(bytes);
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize){
+        Vec::reserve(self, additional);
+    }
+
+    #[inline]
+    fn as_vec(&mut self) -> Option<&mut Buffer>{
+        Option::Some(self)
+    }
+}
+
+/// Forwards to the Sink it points to, so a &mut S can be passed on as a &mut dyn Sink.
+impl<S: Sink + ?Sized> Sink for &mut S{
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]){
+        (**self).write_bytes(bytes);
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize){
+        (**self).reserve(additional);
+    }
+
+    #[inline]
+    fn as_vec(&mut self) -> Option<&mut Buffer>{
+        (**self).as_vec()
+    }
+
+    #[inline]
+    fn endian(&self) -> Endian{
+        (**self).endian()
+    }
+}
+
+/// Byte order of numbers, and of the lengths in front of strings and collections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Endian{
+    /// Most significant byte first, the layout of a Buffer unless told otherwise.
+    #[default]
+    Big,
+    /// Least significant byte first, as C programs on x86 and ARM write their structs.
+    Little,
+}
+
+/// Sink writing numbers and lengths in another byte order into the Sink it wraps,
+/// so the same Bufferable impls can write little endian data.
+/// Read it back with ReadBuffer::with_endian.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = Vec::new();
+/// let mut sink = EndianSink::new(&mut buffer, Endian::Little);
+/// sink.put(&1u32).put(&vec![2u16]);
+/// assert_eq!(vec![1,0,0,0, 1,0,0,0,0,0,0,0, 2,0], buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer).with_endian(Endian::Little);
+/// assert_eq!(Some((1u32, vec![2u16])), Bufferable::from_buffer(&mut buffer));
+/// ```
+pub struct EndianSink<S>{
+    sink: S,
+    endian: Endian,
+}
+
+impl<S: Sink> EndianSink<S>{
+    /// Write into sink in the byte order endian.
+    pub fn new(sink: S, endian: Endian) -> Self{
+        Self{ sink, endian }
+    }
+    /// The Sink written to.
+    pub fn get_ref(&self) -> &S{
+        &self.sink
+    }
+    /// The Sink written to, as mutable.
+    pub fn get_mut(&mut self) -> &mut S{
+        &mut self.sink
+    }
+    /// Give back the Sink written to.
+    pub fn into_inner(self) -> S{
+        self.sink
+    }
+}
+
+impl<S: Sink> Sink for EndianSink<S>{
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.sink.write_bytes(bytes);
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize){
+        self.sink.reserve(additional);
+    }
+
+    // Writing straight into the Vec would skip the byte order, so only big endian gives it out.
+    #[inline]
+    fn as_vec(&mut self) -> Option<&mut Buffer>{
+        match self.endian{
+            Endian::Big => self.sink.as_vec(),
+            Endian::Little => Option::None,
+        }
+    }
+
+    #[inline]
+    fn endian(&self) -> Endian{
+        self.endian
+    }
+}
+
+/// Sink that only counts the bytes written to it, see encoded_size_of.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut counter = SizeCounter::new();
+/// 1u32.into_buffer(&mut counter);
+/// String::from("abc").into_buffer(&mut counter);
+/// assert_eq!(15, counter.len());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizeCounter{
+    len: usize,
+}
+
+impl SizeCounter{
+    /// A counter at 0.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Bytes written so far.
+    pub fn len(&self) -> usize{
+        self.len
+    }
+    /// Whether nothing is written.
+    pub fn is_empty(&self) -> bool{
+        self.len == 0
+    }
+}
+
+impl Sink for SizeCounter{
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.len += bytes.len();
+    }
+}
+
+/// How deep collections may be nested by default when reading, see ReadBuffer::with_max_depth.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Limits on what a ReadBuffer will decode, for reading untrusted data.
+/// Reading fails as soon as one of them is crossed.
+/// By default only the depth is limited.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let limits = DecodeLimits{ max_elements: 2, ..DecodeLimits::default() };
+/// let mut buffer = Vec::new();
+/// vec![1u8,2,3].into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer).with_limits(limits);
+/// assert_eq!(None, Vec::<u8>::from_buffer(&mut buffer));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DecodeLimits{
+    /// How deep collections may be nested.
+    pub max_depth: usize,
+    /// The most elements a single collection may have.
+    pub max_elements: usize,
+    /// The longest a single string may be, in bytes.
+    pub max_string_len: usize,
+    /// The most bytes all decoded collections and strings together may take in memory.
+    pub max_total_bytes: usize,
+}
+
+impl Default for DecodeLimits{
+    fn default() -> Self{
+        Self{
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_elements: usize::MAX,
+            max_string_len: usize::MAX,
+            max_total_bytes: usize::MAX,
+        }
+    }
+}
+
+/// Why a buffer does not hold a valid value, as given by validate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BufferError{
+    /// The buffer ends before the value does: a read of needed bytes found only available left.
+    UnexpectedEof{
+        /// Bytes the read asked for.
+        needed: usize,
+        /// Bytes that were left.
+        available: usize,
+    },
+    /// A length does not fit in a usize, or goes over one of the DecodeLimits.
+    LengthOverflow,
+    /// Collections are nested deeper than DecodeLimits::max_depth.
+    TooDeep,
+    /// A string is not valid UTF-8, or not valid UTF-16 for a WideString.
+    InvalidUtf8,
+    /// An enum discriminant that no variant has.
+    InvalidDiscriminant,
+    /// Any other bytes the type does not accept.
+    Invalid,
+    /// Decoding would allocate more than is left of the AllocTracker of the buffer.
+    BudgetExceeded,
+    /// The value decodes, but bytes are left after it where it should take up all of them.
+    TrailingBytes,
+}
+
+impl std::fmt::Display for BufferError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        let text = match self{
+            BufferError::UnexpectedEof{ needed, available } => {
+                return write!(f, "buffer ends in the middle of a value, needed {} bytes but {} are left", needed, available);
+            },
+            BufferError::LengthOverflow => "length is too long",
+            BufferError::TooDeep => "collections are nested too deep",
+            BufferError::InvalidUtf8 => "string is not valid UTF-8",
+            BufferError::InvalidDiscriminant => "enum discriminant has no variant",
+            BufferError::Invalid => "invalid value",
+            BufferError::BudgetExceeded => "decoding allocates more than the budget",
+            BufferError::TrailingBytes => "bytes are left after the value",
+        };
+        f.write_str(text)
+    }
+}
+
+impl std::error::Error for BufferError{}
+
+/// A BufferError with where in the buffer it happened, as given by try_from_buffer and try_decode.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut bytes = encode(&(7u32, String::from("name")));
+/// bytes.truncate(14);
+/// let err = try_decode::<(u32, String)>(&bytes).unwrap_err();
+/// assert_eq!(BufferError::UnexpectedEof{ needed: 4, available: 2 }, err.error);
+/// assert_eq!(12, err.offset);
+/// assert_eq!("buffer ends in the middle of a value, needed 4 bytes but 2 are left at byte 12 of 14", err.to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError{
+    /// What is wrong.
+    pub error: BufferError,
+    /// How far into the buffer reading got before it failed.
+    /// For a buffer that ends too soon that is where the missing bytes should start,
+    /// for a bad string or a bad discriminant it is right after it.
+    pub offset: usize,
+    /// Length of the buffer.
+    pub len: usize,
+}
+
+impl std::fmt::Display for DecodeError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "{} at byte {} of {}", self.error, self.offset, self.len)
+    }
+}
+
+impl std::error::Error for DecodeError{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>{
+        Option::Some(&self.error)
+    }
+}
+
+impl From<DecodeError> for BufferError{
+    fn from(e: DecodeError) -> Self{
+        e.error
+    }
+}
+
 /// Buffer from which we can read.
-pub struct ReadBuffer{
-    buffer: Buffer,
+/// It owns its bytes, except for forks which borrow them from the ReadBuffer they came from.
+pub struct ReadBuffer<'a>{
+    buffer: Cow<'a, [u8]>,
     iter: usize,
+    depth: usize,
+    decoded: usize,
+    limits: DecodeLimits,
+    tracker: Option<AllocTracker>,
+    endian: Endian,
+    // The last read that did not fit in what was left, as (needed, available).
+    short_read: Option<(usize, usize)>,
+}
+
+impl<'a> ReadBuffer<'a>{
+    /// Create ReadBuffer from Buffer.
+    pub fn from_raw(vec: Buffer) -> Self{
+        Self{
+            buffer: Cow::Owned(vec),
+            iter: 0,
+            depth: 0,
+            decoded: 0,
+            limits: DecodeLimits::default(),
+            tracker: Option::None,
+            endian: Endian::Big,
+            short_read: Option::None,
+        }
+    }
+    /// Create a ReadBuffer over bytes it borrows, like a memory mapped file or a
+    /// received frame, without copying them.
+    /// borrow_bytes, borrow_str and borrow_blob then give slices that outlive the ReadBuffer.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let frame = encode(&(3u16, String::from("name")));
+    /// let name = {
+    ///     let mut buffer = ReadBuffer::from_slice(&frame);
+    ///     assert_eq!(Some(3), u16::from_buffer(&mut buffer));
+    ///     buffer.borrow_str().unwrap()
+    /// };
+    /// assert_eq!("name", name);
+    /// ```
+    pub fn from_slice(bytes: &'a [u8]) -> Self{
+        Self::borrowed(bytes, DecodeLimits::default())
+    }
+    // A ReadBuffer borrowing bytes, starting at their start.
+    pub(crate) fn borrowed(bytes: &'a [u8], limits: DecodeLimits) -> Self{
+        Self{
+            buffer: Cow::Borrowed(bytes),
+            iter: 0,
+            depth: 0,
+            decoded: 0,
+            limits,
+            tracker: Option::None,
+            endian: Endian::Big,
+            short_read: Option::None,
+        }
+    }
+    /// Read from the segments one after the other, as if they were one buffer,
+    /// see chunked::ChainedReadBuffer.
+    pub fn chain<I: IntoIterator<Item = &'a [u8]>>(segments: I) -> chunked::ChainedReadBuffer<'a>{
+        chunked::ChainedReadBuffer::new(segments)
+    }
+    /// Set the limits that reading from this buffer has to stay within.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self{
+        self.limits = limits;
+        self
+    }
+    /// The limits reading has to stay within.
+    pub fn limits(&self) -> &DecodeLimits{
+        &self.limits
+    }
+    /// Set how deep collections may be nested before reading fails.
+    /// Hostile input like a Vec<Vec<Vec<...>>> thousands deep
+    /// can then not overflow the stack.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// vec![vec![vec![1u8]]].into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer).with_max_depth(2);
+    /// assert_eq!(None, Vec::<Vec<Vec<u8>>>::from_buffer(&mut buffer));
+    /// ```
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self{
+        self.limits.max_depth = max_depth;
+        self
+    }
+    /// How deep collections may be nested.
+    pub fn max_depth(&self) -> usize{
+        self.limits.max_depth
+    }
+    /// Count what decoding from this buffer, and its forks and takes, allocates against tracker.
+    pub fn with_tracker(mut self, tracker: AllocTracker) -> Self{
+        self.tracker = Option::Some(tracker);
+        self
+    }
+    /// Read numbers and lengths in the byte order endian, for data written through an EndianSink
+    /// or by programs that write little endian. Forks and takes keep it.
+    pub fn with_endian(mut self, endian: Endian) -> Self{
+        self.endian = endian;
+        self
+    }
+    /// The byte order numbers and lengths are read in.
+    pub fn endian(&self) -> Endian{
+        self.endian
+    }
+    /// The tracker allocations are counted against, if there is one.
+    pub fn tracker(&self) -> Option<&AllocTracker>{
+        self.tracker.as_ref()
+    }
+    /// How many bytes of memory the collections and strings read so far take,
+    /// as counted against DecodeLimits::max_total_bytes.
+    pub fn decoded_bytes(&self) -> usize{
+        self.decoded
+    }
+    /// Read the length of a collection, failing if it has more than
+    /// DecodeLimits::max_elements elements.
+    /// Custom collections should read their length with this.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// vec![1u8,2,3].into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Some(3), buffer.read_len());
+    /// ```
+    pub fn read_len(&mut self) -> Option<usize>{
+        self.read_prefix(self.limits.max_elements)
+    }
+    /// Count bytes of memory about to be used for decoded data,
+    /// failing if the total goes over DecodeLimits::max_total_bytes
+    /// or the bytes do not fit in what is left of the AllocTracker.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let limits = DecodeLimits{ max_total_bytes: 10, ..DecodeLimits::default() };
+    /// let mut buffer = ReadBuffer::from_raw(Vec::new()).with_limits(limits);
+    /// assert_eq!(Some(()), buffer.use_bytes(6));
+    /// assert_eq!(None, buffer.use_bytes(6));
+    /// ```
+    pub fn use_bytes(&mut self, bytes: usize) -> Option<()>{
+        let total = self.decoded.checked_add(bytes)?;
+        if total > self.limits.max_total_bytes{
+            return Option::None;
+        }
+        if let Option::Some(tracker) = &self.tracker{
+            if !tracker.charge(bytes){
+                return Option::None;
+            }
+        }
+        self.decoded = total;
+        Option::Some(())
+    }
+    // Read a length prefix and check it against max.
+    fn read_prefix(&mut self, max: usize) -> Option<usize>{
+        let len = usize::try_from(u64::from_buffer(self)?).ok()?;
+        if len > max{
+            return Option::None;
+        }
+        Option::Some(len)
+    }
+    // Read the bytes of a string, checking max_string_len.
+    fn read_str_bytes(&mut self) -> Option<&[u8]>{
+        let len = self.read_prefix(self.limits.max_string_len)?;
+        self.read_bytes(len)
+    }
+    // read_prefix for validating.
+    fn check_prefix(&mut self, max: usize) -> Result<usize, BufferError>{
+        let len = u64::from_buffer(self).ok_or_else(|| self.eof(8))?;
+        usize::try_from(len).ok().filter(|len| *len <= max).ok_or(BufferError::LengthOverflow)
+    }
+    // read_bytes for validating.
+    fn check_bytes(&mut self, len: usize) -> Result<&[u8], BufferError>{
+        let available = self.remaining();
+        self.read_bytes(len).ok_or(BufferError::UnexpectedEof{ needed: len, available })
+    }
+    // A DecodeError for error where the buffer is now, moving it back to start.
+    fn fail_at(&mut self, start: usize, error: BufferError) -> DecodeError{
+        let e = DecodeError{ error, offset: self.iter, len: self.buffer.len() };
+        self.iter = start;
+        e
+    }
+    // The error for a read of needed bytes that does not fit in what is left.
+    fn eof(&self, needed: usize) -> BufferError{
+        BufferError::UnexpectedEof{ needed, available: self.remaining() }
+    }
+    // use_bytes for validating. Nothing is allocated, so the tracker is only looked at.
+    fn check_use_bytes(&mut self, bytes: usize) -> Result<(), BufferError>{
+        let total = self.decoded.checked_add(bytes).ok_or(BufferError::LengthOverflow)?;
+        if total > self.limits.max_total_bytes{
+            return Err(BufferError::LengthOverflow);
+        }
+        if self.tracker.as_ref().is_some_and(|t| bytes > t.remaining()){
+            return Err(BufferError::BudgetExceeded);
+        }
+        self.decoded = total;
+        Ok(())
+    }
+    // Elements that take no bytes do not move through the buffer, so a count of them is only
+    // believed up to the bytes that are left. Otherwise a bogus count loops for ages reading nothing.
+    fn check_count(&self, len: usize, min_len: usize) -> Result<(), BufferError>{
+        if min_len == 0 && len > self.remaining(){
+            return Err(BufferError::LengthOverflow);
+        }
+        Ok(())
+    }
+    // nested for validating.
+    fn check_nested<F: FnOnce(&mut Self) -> Result<(), BufferError>>(&mut self, f: F) -> Result<(), BufferError>{
+        if self.depth >= self.limits.max_depth{
+            return Err(BufferError::TooDeep);
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+    /// Move past the next len bytes without looking at them.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![1,2,3]);
+    /// assert_eq!(Ok(()), buffer.skip(2));
+    /// assert_eq!(Err(BufferError::UnexpectedEof{ needed: 2, available: 1 }), buffer.skip(2));
+    /// assert_eq!(Some(3), u8::from_buffer(&mut buffer));
+    /// ```
+    pub fn skip(&mut self, len: usize) -> Result<(), BufferError>{
+        self.check_bytes(len).map(drop)
+    }
+    /// Read Ts until the buffer ends, for files of records without a count in front.
+    /// A record that is cut short or does not decode gives the error validate finds for it,
+    /// and leaves the buffer at the start of that record.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// (1u16, String::from("one")).into_buffer(&mut buffer);
+    /// (2u16, String::from("two")).into_buffer(&mut buffer);
+    /// let whole = buffer.len();
+    /// let mut read = ReadBuffer::from_raw(buffer.clone());
+    /// assert_eq!(Ok(vec![(1, String::from("one")), (2, String::from("two"))]), read.read_all::<(u16, String)>());
+    /// // half of a third record
+    /// (3u16, String::from("three")).into_buffer(&mut buffer);
+    /// buffer.truncate(whole + 6);
+    /// let mut read = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Err(BufferError::UnexpectedEof{ needed: 8, available: 4 }), read.read_all::<(u16, String)>());
+    /// assert_eq!(whole, read.consumed());
+    /// ```
+    pub fn read_all<T: Bufferable>(&mut self) -> Result<Vec<T>, BufferError>{
+        let mut xs = Vec::new();
+        while self.remaining() > 0{
+            let (iter, decoded) = (self.iter, self.decoded);
+            match T::from_buffer(self){
+                // a record of no bytes would never get to the end
+                Option::Some(x) if self.iter > iter => xs.push(x),
+                _ => {
+                    self.iter = iter;
+                    self.decoded = decoded;
+                    return Err(self.fork().skip_value::<T>().err().unwrap_or(BufferError::Invalid));
+                },
+            }
+        }
+        Ok(xs)
+    }
+    /// Read a T, with the reason it failed when it does: BufferError::BudgetExceeded if the
+    /// AllocTracker ran out while reading it, otherwise the error validate finds.
+    /// On an error the buffer stays where it was, but what was counted against the tracker stays counted.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut read = ReadBuffer::from_raw(vec![0, 0, 0, 0, 0, 0, 0, 9]);
+    /// assert_eq!(Err(BufferError::UnexpectedEof{ needed: 9, available: 0 }), read.read_checked::<String>());
+    /// assert_eq!(Ok(9), read.read_checked::<u64>());
+    /// ```
+    pub fn read_checked<T: Bufferable>(&mut self) -> Result<T, BufferError>{
+        self.read_located().map_err(|e| e.error)
+    }
+    /// read_checked, also giving the offset where reading failed. See Bufferable::try_from_buffer.
+    pub fn read_located<T: Bufferable>(&mut self) -> Result<T, DecodeError>{
+        T::try_from_buffer(self)
+    }
+    // Read with from_buffer, and on a failure go back and find the reason with validate.
+    fn locate<T: Bufferable>(&mut self, from_buffer: fn(&mut Self) -> Option<T>) -> Result<T, DecodeError>{
+        let (iter, decoded) = (self.iter, self.decoded);
+        let exceeded = self.tracker.as_ref().is_some_and(AllocTracker::exceeded);
+        match from_buffer(self){
+            Option::Some(x) => Ok(x),
+            Option::None => {
+                self.iter = iter;
+                self.decoded = decoded;
+                let len = self.buffer.len();
+                if !exceeded && self.tracker.as_ref().is_some_and(AllocTracker::exceeded){
+                    return Err(DecodeError{ error: BufferError::BudgetExceeded, offset: iter, len });
+                }
+                let mut fork = self.fork();
+                let error = T::validate(&mut fork).err().unwrap_or(BufferError::Invalid);
+                Err(DecodeError{ error, offset: fork.iter, len })
+            },
+        }
+    }
+    /// Move past a T without decoding it, so nothing is allocated for its strings and Vecs.
+    /// The value is checked like validate does. On an error the buffer stays where it was.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// (String::from("a long description"), vec![1u32; 100]).into_buffer(&mut buffer);
+    /// 7u8.into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Ok(()), buffer.skip_value::<(String, Vec<u32>)>());
+    /// assert_eq!(Some(7), u8::from_buffer(&mut buffer));
+    /// ```
+    pub fn skip_value<T: Bufferable>(&mut self) -> Result<(), BufferError>{
+        let (iter, decoded) = (self.iter, self.decoded);
+        let res = T::validate(self);
+        if res.is_err(){
+            self.iter = iter;
+            self.decoded = decoded;
+        }
+        res
+    }
+    /// Read something one level deeper.
+    /// Returns None without calling f when that would go past the maximum depth.
+    /// Recursive Bufferable impls should read their children with this.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![0,7]).with_max_depth(1);
+    /// assert_eq!(Some(7), buffer.nested(|buf| u16::from_buffer(buf)));
+    /// assert_eq!(None, buffer.nested(|buf| buf.nested(|buf| u16::from_buffer(buf))));
+    /// ```
+    pub fn nested<T, F: FnOnce(&mut Self) -> Option<T>>(&mut self, f: F) -> Option<T>{
+        if self.depth >= self.limits.max_depth{
+            return Option::None;
+        }
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+    /// Turn ReadBuffer into Buffer. Copies the bytes if this is a fork.
+    pub fn into_raw(self) -> Buffer{
+        self.buffer.into_owned()
+    }
+    /// A second ReadBuffer over the same bytes, starting where this one is,
+    /// with the same limits and depth. It borrows the bytes instead of copying them,
+    /// and reading from it leaves this one where it was.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// String::from("not a number").into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// let mut fork = buffer.fork();
+    /// assert_eq!(Some(12), u64::from_buffer(&mut fork));
+    /// assert_eq!(0, buffer.consumed());
+    /// ```
+    pub fn fork(&self) -> ReadBuffer<'_>{
+        ReadBuffer{
+            buffer: Cow::Borrowed(&self.buffer),
+            iter: self.iter,
+            depth: self.depth,
+            decoded: self.decoded,
+            limits: self.limits,
+            tracker: self.tracker.clone(),
+            endian: self.endian,
+            short_read: Option::None,
+        }
+    }
+    /// A ReadBuffer over only the next len bytes, with the same limits and depth,
+    /// and move this one past them. Anything read from it can not go past those bytes,
+    /// so a nested payload that lies about its contents can not eat what comes after it.
+    /// None, without moving, if there are not len bytes left.
+    /// Where std::io::Read is imported buffer.take(len) means Read::take,
+    /// write ReadBuffer::take(&mut buffer, len) there.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![0,0,0,0,0,0,0,4, 0,1, 0,2, 9]);
+    /// let len = u64::from_buffer(&mut buffer).unwrap() as usize;
+    /// let mut payload = buffer.take(len).unwrap();
+    /// assert_eq!(Some((1u16, 2u16)), <(u16,u16)>::from_buffer(&mut payload));
+    /// assert_eq!(None, u8::from_buffer(&mut payload));
+    /// assert_eq!(Some(9), u8::from_buffer(&mut buffer));
+    /// ```
+    pub fn take(&mut self, len: usize) -> Option<ReadBuffer<'_>>{
+        if len > self.remaining(){
+            return Option::None;
+        }
+        let start = self.iter;
+        self.iter += len;
+        Option::Some(ReadBuffer{
+            buffer: Cow::Borrowed(&self.buffer[start..start + len]),
+            iter: 0,
+            depth: self.depth,
+            decoded: self.decoded,
+            limits: self.limits,
+            tracker: self.tracker.clone(),
+            endian: self.endian,
+            short_read: Option::None,
+        })
+    }
+    /// take with everything that is left, leaving this buffer at the end.
+    pub fn take_remaining(&mut self) -> ReadBuffer<'_>{
+        let len = self.remaining();
+        self.take(len).unwrap()
+    }
+    /// Try reading with f on a fork. If it gives Some, this buffer moves past
+    /// what f read, otherwise it stays where it was, so the next attempt
+    /// starts from the same spot.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// String::from("hi").into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(None, buffer.speculate(|b| <(u64,u64)>::from_buffer(b)));
+    /// assert_eq!(Some(String::from("hi")), buffer.speculate(|b| String::from_buffer(b)));
+    /// assert_eq!(0, buffer.remaining());
+    /// ```
+    pub fn speculate<T, F: FnOnce(&mut ReadBuffer<'_>) -> Option<T>>(&mut self, f: F) -> Option<T>{
+        let mut fork = self.fork();
+        let x = f(&mut fork)?;
+        let (iter, decoded) = (fork.iter, fork.decoded);
+        self.iter = iter;
+        self.decoded = decoded;
+        Option::Some(x)
+    }
+    /// All bytes of the buffer, read or not.
+    pub fn as_slice(&self) -> &[u8]{
+        &self.buffer
+    }
+    /// How many bytes are read, which is where the next read starts.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![0,1,2]);
+    /// u16::from_buffer(&mut buffer);
+    /// assert_eq!(2, buffer.consumed());
+    /// assert_eq!(&[2][..], &buffer.as_slice()[buffer.consumed()..]);
+    /// ```
+    pub fn consumed(&self) -> usize{
+        self.iter
+    }
+    /// Where the next read starts, the same as consumed.
+    pub fn position(&self) -> usize{
+        self.iter
+    }
+    /// Move to pos, forward or back, so the next read starts there.
+    /// Moving past the end gives UnexpectedEof and leaves the buffer where it was.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![1,2,3]);
+    /// assert_eq!(Ok(()), buffer.set_position(2));
+    /// assert_eq!(Some(3), u8::from_buffer(&mut buffer));
+    /// assert_eq!(Err(BufferError::UnexpectedEof{ needed: 1, available: 0 }), buffer.set_position(4));
+    /// assert_eq!(Ok(()), buffer.set_position(0));
+    /// assert_eq!(Some(1), u8::from_buffer(&mut buffer));
+    /// ```
+    pub fn set_position(&mut self, pos: usize) -> Result<(), BufferError>{
+        if pos > self.buffer.len(){
+            return Err(self.eof(pos - self.iter));
+        }
+        self.iter = pos;
+        Ok(())
+    }
+    /// Read the next T without moving past it, like a tag byte that says what follows.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![2, 0, 7]);
+    /// assert_eq!(Some(2u8), buffer.peek());
+    /// assert_eq!(Some(0x0200u16), buffer.peek());
+    /// assert_eq!(0, buffer.position());
+    /// ```
+    pub fn peek<T: Bufferable>(&self) -> Option<T>{
+        T::from_buffer(&mut self.fork())
+    }
+    /// If the ReadBuffer is empty.
+    pub fn is_empty(&self) -> bool{
+        self.buffer.is_empty()
+    }
+    /// How many bytes are left to read.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![0,1,2]);
+    /// u16::from_buffer(&mut buffer);
+    /// assert_eq!(1, buffer.remaining());
+    /// ```
+    pub fn remaining(&self) -> usize{
+        self.buffer.len() - self.iter
+    }
+    /// Read a trailing field, or T::default() if the buffer has already ended.
+    /// Reading a struct whose last fields use this still works on files written before
+    /// those fields were added. Only the end of the buffer counts as missing, so
+    /// this is for the last value in a buffer, not for values inside a Vec.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// // written before the u32 was added
+    /// let mut buffer = Vec::new();
+    /// String::from("old").into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Some(String::from("old")), String::from_buffer(&mut buffer));
+    /// assert_eq!(Some(0u32), buffer.read_or_default());
+    /// ```
+    pub fn read_or_default<T: Bufferable + Default>(&mut self) -> Option<T>{
+        if self.remaining() == 0{
+            Option::Some(T::default())
+        } else {
+            T::from_buffer(self)
+        }
+    }
+    /// Read a String written to the buffer without copying it.
+    /// The returned str borrows from the ReadBuffer.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// String::from("hello").into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Some("hello"), buffer.read_str());
+    /// assert_eq!(None, buffer.read_str());
+    /// ```
+    pub fn read_str(&mut self) -> Option<&str>{
+        std::str::from_utf8(self.read_str_bytes()?).ok()
+    }
+    /// Read a 0 terminated string, as C writes them, without copying it.
+    /// The CStr borrows from the ReadBuffer and the 0 is read as well.
+    /// None if there is no 0 in the rest of the buffer,
+    /// or the string is longer than DecodeLimits::max_string_len.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![b'h', b'i', 0, b'x']);
+    /// assert_eq!(Some("hi"), buffer.read_cstr().and_then(|s| s.to_str().ok()));
+    /// assert_eq!(None, buffer.read_cstr());
+    /// ```
+    pub fn read_cstr(&mut self) -> Option<&std::ffi::CStr>{
+        let rest = &self.buffer[self.iter..];
+        let len = rest.iter().position(|b| *b == 0)?;
+        if len > self.limits.max_string_len{
+            return Option::None;
+        }
+        let bytes = self.read_bytes(len + 1)?;
+        std::ffi::CStr::from_bytes_with_nul(bytes).ok()
+    }
+    /// Read a length prefixed block of bytes without copying it.
+    /// Reads the same layout as ByteVec and Vec<u8> write.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// ByteVec(vec![9,8,7]).into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// assert_eq!(Some(&[9u8,8,7][..]), buffer.read_blob());
+    /// ```
+    pub fn read_blob(&mut self) -> Option<&[u8]>{
+        let len = self.read_len()?;
+        self.read_bytes(len)
+    }
+    /// Read a Vec lazily, one element at a time.
+    /// Reads the length now and returns an iterator that decodes an element
+    /// each time it is advanced. Items are None if an element failed to decode,
+    /// after which the iterator ends. Dropping it early leaves the ReadBuffer
+    /// right after the last element that was read.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// vec![1u32,20,300].into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// let big = buffer.read_seq::<u32>().unwrap().flatten().find(|x| *x > 10);
+    /// assert_eq!(Some(20), big);
+    /// ```
+    pub fn read_seq<T: Bufferable>(&mut self) -> Option<SeqReader<'_, 'a, T>>{
+        let len = self.read_len()? as u64;
+        Option::Some(SeqReader{
+            buf: self,
+            left: len,
+            marker: std::marker::PhantomData,
+        })
+    }
+    /// Read the next N bytes into an array, or None if there are not enough left.
+    #[inline]
+    fn take_array<const N: usize>(&mut self) -> Option<[u8; N]>{
+        if N > self.remaining(){
+            self.short_read = Option::Some((N, self.remaining()));
+            return Option::None;
+        }
+        let bytes = self.buffer.get(self.iter..)?.get(..N)?;
+        self.iter += N;
+        <[u8; N]>::try_from(bytes).ok()
+    }
+    /// Read the next N bytes into an array without checking there are enough.
+    /// # Safety
+    /// There must be at least N bytes left.
+    #[inline]
+    unsafe fn take_array_unchecked<const N: usize>(&mut self) -> [u8; N]{
+        debug_assert!(self.remaining() >= N);
+        let bytes = (self.buffer.as_ptr().add(self.iter) as *const [u8; N]).read();
+        self.iter += N;
+        bytes
+    }
+    /// Read the next len bytes without copying them.
+    /// Returns None if there are not enough bytes left.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![1,2,3,4,5]);
+    /// assert_eq!(Some(&[1u8,2][..]), buffer.read_bytes(2));
+    /// assert_eq!(None, buffer.read_bytes(4));
+    /// assert_eq!(Some(&[3u8,4,5][..]), buffer.read_bytes(3));
+    /// ```
+    #[inline]
+    pub fn read_bytes(&mut self, len: usize) -> Option<&[u8]>{
+        if len > self.remaining(){
+            self.short_read = Option::Some((len, self.remaining()));
+            return Option::None;
+        }
+        let bytes = self.buffer.get(self.iter..)?.get(..len)?;
+        self.iter += len;
+        Option::Some(bytes)
+    }
+    /// The bytes this ReadBuffer borrows, None if it owns them.
+    fn borrowed_bytes(&self) -> Option<&'a [u8]>{
+        match &self.buffer{
+            Cow::Borrowed(bytes) => Option::Some(*bytes),
+            Cow::Owned(_) => Option::None,
+        }
+    }
+    /// read_bytes, giving a slice into the borrowed bytes that lives as long as they do
+    /// instead of as long as this borrow of the ReadBuffer.
+    /// None, without moving, for a ReadBuffer that owns its bytes, see from_slice.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let data = [1u8, 2, 3];
+    /// let mut buffer = ReadBuffer::from_slice(&data);
+    /// let first = buffer.borrow_bytes(2).unwrap();
+    /// let rest = buffer.borrow_bytes(1).unwrap();
+    /// assert_eq!((&[1u8, 2][..], &[3u8][..]), (first, rest));
+    /// assert_eq!(None, ReadBuffer::from_raw(data.to_vec()).borrow_bytes(2));
+    /// ```
+    pub fn borrow_bytes(&mut self, len: usize) -> Option<&'a [u8]>{
+        let bytes = self.borrowed_bytes()?.get(self.iter..)?.get(..len)?;
+        self.iter += len;
+        Option::Some(bytes)
+    }
+    /// read_str giving a str into the borrowed bytes, see borrow_bytes.
+    pub fn borrow_str(&mut self) -> Option<&'a str>{
+        let all = self.borrowed_bytes()?;
+        let len = self.read_str_bytes()?.len();
+        std::str::from_utf8(&all[self.iter - len..self.iter]).ok()
+    }
+    /// read_blob giving a slice into the borrowed bytes, see borrow_bytes.
+    pub fn borrow_blob(&mut self) -> Option<&'a [u8]>{
+        self.borrowed_bytes()?;
+        let len = self.read_len()?;
+        self.borrow_bytes(len)
+    }
+}
+
+/// Shows the length, the position and the next bytes in hex, and the depth while inside nested collections.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = ReadBuffer::from_raw(vec![0,1,2,255]);
+/// u8::from_buffer(&mut buffer);
+/// assert_eq!("ReadBuffer { len: 4, pos: 1, next: [01 02 ff] }", format!("{:?}", buffer));
+/// ```
+impl std::fmt::Debug for ReadBuffer<'_>{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        const WINDOW: usize = 16;
+        let next = &self.buffer[self.iter..];
+        write!(f, "ReadBuffer {{ len: {}, pos: {}, next: [", self.buffer.len(), self.iter)?;
+        for (i, byte) in next.iter().take(WINDOW).enumerate(){
+            if i > 0 { write!(f, " ")?; }
+            write!(f, "{:02x}", byte)?;
+        }
+        if next.len() > WINDOW{
+            write!(f, " ..")?;
+        }
+        if self.depth > 0{
+            write!(f, "], depth: {} }}", self.depth)
+        } else {
+            write!(f, "] }}")
+        }
+    }
+}
+
+/// Reads the bytes that are left, so a ReadBuffer can be handed to parsers that want io::Read.
+impl std::io::Read for ReadBuffer<'_>{
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize>{
+        let len = out.len().min(self.remaining());
+        out[..len].copy_from_slice(&self.buffer[self.iter..self.iter + len]);
+        self.iter += len;
+        Ok(len)
+    }
+}
+
+impl std::io::BufRead for ReadBuffer<'_>{
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]>{
+        Ok(&self.buffer[self.iter..])
+    }
+
+    fn consume(&mut self, amt: usize){
+        self.iter += amt.min(self.remaining());
+    }
+}
+
+/// Positions count from the start of the whole buffer, as consumed does.
+/// Seeking before the start or past the end is an InvalidInput error.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use std::io::{ Read, Seek, SeekFrom };
+/// let mut buffer = ReadBuffer::from_raw(vec![0, 1, 2, 3, 4]);
+/// assert_eq!(3, buffer.seek(SeekFrom::End(-2)).unwrap());
+/// assert_eq!(Some(3), u8::from_buffer(&mut buffer));
+/// buffer.seek(SeekFrom::Current(-3)).unwrap();
+/// let mut bytes = Vec::new();
+/// buffer.read_to_end(&mut bytes).unwrap();
+/// assert_eq!(vec![1, 2, 3, 4], bytes);
+/// assert!(buffer.seek(SeekFrom::Start(6)).is_err());
+/// ```
+impl std::io::Seek for ReadBuffer<'_>{
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64>{
+        let (base, offset) = match pos{
+            std::io::SeekFrom::Start(n) => (0, i64::try_from(n).ok()),
+            std::io::SeekFrom::End(n) => (self.buffer.len(), Option::Some(n)),
+            std::io::SeekFrom::Current(n) => (self.iter, Option::Some(n)),
+        };
+        let target = offset.and_then(|offset| i64::try_from(base).ok()?.checked_add(offset))
+            .and_then(|target| usize::try_from(target).ok())
+            .filter(|target| *target <= self.buffer.len());
+        match target{
+            Option::Some(target) => {
+                self.iter = target;
+                Ok(target as u64)
+            },
+            Option::None => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                "seek before the start or past the end of the buffer")),
+        }
+    }
+}
+
+impl From<Buffer> for ReadBuffer<'_>{
+    fn from(vec: Buffer) -> Self{
+        Self::from_raw(vec)
+    }
+}
+
+impl<'a> From<&'a [u8]> for ReadBuffer<'a>{
+    fn from(bytes: &'a [u8]) -> Self{
+        Self::from_slice(bytes)
+    }
+}
+
+impl From<ReadBuffer<'_>> for Buffer{
+    fn from(buffer: ReadBuffer<'_>) -> Self{
+        buffer.into_raw()
+    }
+}
+
+/// Lazy iterator over a length prefixed sequence, made by ReadBuffer::read_seq.
+pub struct SeqReader<'a, 'b, T>{
+    buf: &'a mut ReadBuffer<'b>,
+    left: u64,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<'a, 'b, T> SeqReader<'a, 'b, T>{
+    /// How many elements have not been read yet.
+    pub fn left(&self) -> u64{
+        self.left
+    }
+}
+
+impl<'a, 'b, T: Bufferable> Iterator for SeqReader<'a, 'b, T>{
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Option<T>>{
+        if self.left == 0 { return Option::None; }
+        let x = T::from_buffer(self.buf);
+        self.left = if x.is_some() { self.left - 1 } else { 0 };
+        Option::Some(x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>){
+        let left = usize::try_from(self.left).unwrap_or(usize::MAX);
+        (0, Option::Some(left))
+    }
+}
+
+impl<'a, 'b, T: Bufferable> std::iter::FusedIterator for SeqReader<'a, 'b, T>{}
+
+/// Object can be read from a ReadBuffer and written to a Buffer or any other Sink
+pub trait Bufferable where Self: std::marker::Sized{
+    /// The fewest bytes any value of this type takes up in a buffer.
+    /// Used to bound preallocation when decoding, so it must never be more
+    /// than the real minimum.
+    const MIN_BUFFER_LEN: usize = 1;
+    /// Consume yourself and add to the end of the buffer
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S);
+    /// Copy yourself and add to the end of the buffer.
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S);
+    /// Read object from buffer.
+    /// The default is a thin wrapper over try_from_buffer, so a type implements one of the two.
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        Self::try_from_buffer(buf).ok()
+    }
+    /// Copy a whole slice of yourself to the end of the buffer, without a length.
+    /// Vec uses this, so types with a fixed size can override it to encode the
+    /// slice in one pass instead of element by element.
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        for x in slice{
+            x.copy_into_buffer(vec);
+        }
+    }
+    /// Read len objects from the buffer, as written by copy_slice_into_buffer.
+    /// Vec uses this, so types with a fixed size can override it to decode
+    /// all elements from one checked slice.
+    /// Elements of no bytes can not be more than the bytes that are left.
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        buf.check_count(len, Self::MIN_BUFFER_LEN).ok()?;
+        // A bogus length can't make us allocate more elements than could fit in
+        // the bytes that are left.
+        let cap = len.min(buf.remaining() / Self::MIN_BUFFER_LEN.max(1));
+        let mut vec = Vec::with_capacity(cap);
+        for _ in 0..len{
+            vec.push(Self::from_buffer(buf)?);
+        }
+        Option::Some(vec)
+    }
+    /// Read object from buffer, skipping the bounds checks from_buffer does.
+    /// The default just calls from_buffer, the fixed size types and tuples of
+    /// them read straight from memory.
+    /// # Safety
+    /// The buffer must hold a valid encoding of Self at the current position,
+    /// for example because the caller checked remaining() against the size
+    /// of a fixed size record.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = Vec::new();
+    /// (1u32,2.0f32).into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// if buffer.remaining() >= 8{
+    ///     let x = unsafe{ <(u32,f32)>::from_buffer_unchecked(&mut buffer) };
+    ///     assert_eq!((1,2.0), x);
+    /// }
+    /// ```
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        match Self::from_buffer(buf){
+            Some(x) => x,
+            None => std::hint::unreachable_unchecked(),
+        }
+    }
+    /// Read object from buffer, saying why and where it failed when it does.
+    /// The number types, bool and char read this way and from_buffer only drops the error.
+    /// For types that implement from_buffer instead, the default calls it and looks for the
+    /// reason with validate only when that fails, so the successful path costs nothing extra.
+    /// On an error the buffer stays where it was.
+    /// The error is a DecodeError, a BufferError together with the offset it happened at,
+    /// ReadBuffer::read_checked gives only the BufferError.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = ReadBuffer::from_raw(vec![0,0,0,0,0,0,0,2, 0xff, 0xfe]);
+    /// let err = String::try_from_buffer(&mut buffer).unwrap_err();
+    /// assert_eq!(BufferError::InvalidUtf8, err.error);
+    /// assert_eq!(0, buffer.consumed());
+    /// ```
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        buf.locate(Self::from_buffer)
+    }
+    /// Check that a valid Self comes next and move past it, without building the value.
+    /// The default decodes the value and drops it, types that allocate override this
+    /// to check their bytes where they are. When decoding fails the default gives
+    /// UnexpectedEof if a read ran past the end, and Invalid otherwise.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.short_read = Option::None;
+        Self::from_buffer(buf).map(drop).ok_or_else(|| match buf.short_read.take(){
+            Option::Some((needed, available)) => BufferError::UnexpectedEof{ needed, available },
+            Option::None => BufferError::Invalid,
+        })
+    }
+    /// Read object from buffer into self, reusing the memory self already has.
+    /// The default reads a new value and replaces self, String, ByteVec, Vec and tuples
+    /// keep their allocations. If it fails self holds some valid value, but which is unspecified.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut name = String::with_capacity(64);
+    /// name.read_from_buffer(&mut ReadBuffer::from_raw(encode(&String::from("abc")))).unwrap();
+    /// assert_eq!("abc", name);
+    /// assert!(name.capacity() >= 64);
+    /// ```
+    fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+        *self = Self::from_buffer(buf)?;
+        Option::Some(())
+    }
+    /// Validate len values as written by copy_slice_into_buffer.
+    /// Types with a fixed size can override this to skip them all at once.
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_count(len, Self::MIN_BUFFER_LEN)?;
+        for _ in 0..len{
+            Self::validate(buf)?;
+        }
+        Ok(())
+    }
+    /// How many bytes copy_into_buffer will write, used to reserve space up front.
+    /// This is only a hint: the default of 0 means unknown and reserves nothing.
+    fn buffer_len(&self) -> usize{
+        0
+    }
+    /// How many bytes copy_slice_into_buffer will write for this slice.
+    /// Types with a fixed size can override this to skip walking the elements.
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.iter().map(Self::buffer_len).sum()
+    }
 }
+/// Numbers that are N plain bytes in memory, so the simd paths can treat a
+/// slice of them as raw bytes.
+/// # Safety
+/// Implementors must have no padding and every bit pattern must be valid.
+unsafe trait Number: Copy{}
+unsafe impl Number for u64{}
+unsafe impl Number for u32{}
+unsafe impl Number for u16{}
+unsafe impl Number for f64{}
+unsafe impl Number for f32{}
+unsafe impl Number for i8{}
+unsafe impl Number for i16{}
+unsafe impl Number for i32{}
+unsafe impl Number for i64{}
+unsafe impl Number for i128{}
+unsafe impl Number for u128{}
+/// Write a number in the byte order of vec.
+#[inline]
+fn write_number<T, S: Sink + ?Sized, const N: usize>(x: T, vec: &mut S, to_be: fn(T) -> [u8; N], to_le: fn(T) -> [u8; N]){
+    let bytes = match vec.endian(){
+        Endian::Big => to_be(x),
+        Endian::Little => to_le(x),
+    };
+    vec.write_bytes(&bytes);
+}
+/// Read a number in the byte order of buf.
+#[inline]
+fn read_number<T, const N: usize>(buf: &mut ReadBuffer, from_be: fn([u8; N]) -> T, from_le: fn([u8; N]) -> T) -> Result<T, DecodeError>{
+    let bytes = match buf.take_array(){
+        Option::Some(bytes) => bytes,
+        Option::None => {
+            let error = buf.eof(N);
+            return Err(buf.fail_at(buf.iter, error));
+        },
+    };
+    Ok(match buf.endian{
+        Endian::Big => from_be(bytes),
+        Endian::Little => from_le(bytes),
+    })
+}
+/// read_number without the bounds check.
+/// # Safety
+/// There have to be N bytes left in buf.
+#[inline]
+unsafe fn read_number_unchecked<T, const N: usize>(buf: &mut ReadBuffer, from_be: fn([u8; N]) -> T, from_le: fn([u8; N]) -> T) -> T{
+    let bytes = buf.take_array_unchecked();
+    match buf.endian{
+        Endian::Big => from_be(bytes),
+        Endian::Little => from_le(bytes),
+    }
+}
+/// Bulk decode numbers in the byte order of buf, with the SIMD byte swap if the simd feature is on.
+#[inline]
+fn numbers_from_buffer<T: Number, const N: usize>(len: usize, buf: &mut ReadBuffer, from_be: fn([u8; N]) -> T, from_le: fn([u8; N]) -> T) -> Option<Vec<T>>{
+    match buf.endian{
+        #[cfg(feature = "simd")]
+        Endian::Big => { let _ = from_be; simd::vec_from_buffer::<T, N>(len, buf) },
+        #[cfg(not(feature = "simd"))]
+        Endian::Big => bulk_from_buffer(len, buf, from_be),
+        Endian::Little => bulk_from_buffer(len, buf, from_le),
+    }
+}
+/// Bulk encode numbers in the byte order of vec, with the SIMD byte swap if the simd feature is on.
+#[inline]
+fn numbers_into_buffer<T: Number, S: Sink + ?Sized, const N: usize>(slice: &[T], vec: &mut S, to_be: fn(T) -> [u8; N], to_le: fn(T) -> [u8; N]){
+    if vec.endian() == Endian::Little{
+        bulk_into_buffer(slice, vec, to_le);
+        return;
+    }
+    #[cfg(feature = "simd")]
+    {
+        if let Some(vec) = vec.as_vec(){
+            simd::slice_into_buffer::<T, N>(slice, vec);
+            return;
+        }
+    }
+    bulk_into_buffer(slice, vec, to_be);
+}
+/// Decode len fixed size values from a single bounds checked slice.
+#[inline]
+fn bulk_from_buffer<T, const N: usize>(len: usize, buf: &mut ReadBuffer, from_bytes: fn([u8; N]) -> T) -> Option<Vec<T>>{
+    let bytes = buf.read_bytes(len.checked_mul(N)?)?;
+    let mut vec = Vec::with_capacity(len);
+    vec.extend(bytes.chunks_exact(N).map(|chunk|{
+        let mut array = [0u8; N];
+        array.copy_from_slice(chunk);
+        from_bytes(array)
+    }));
+    Option::Some(vec)
+}
+/// Encode a slice of fixed size values in one pass over the grown buffer,
+/// or in blocks through a small stack buffer if the Sink is not a Vec.
+#[inline]
+fn bulk_into_buffer<T: Copy, S: Sink + ?Sized, const N: usize>(slice: &[T], vec: &mut S, to_bytes: fn(T) -> [u8; N]){
+    if let Some(vec) = vec.as_vec(){
+        let start = vec.len();
+        vec.resize(start + slice.len() * N, 0);
+        for (chunk, x) in vec[start..].chunks_exact_mut(N).zip(slice){
+            chunk.copy_from_slice(&to_bytes(*x));
+        }
+        return;
+    }
+    let mut block = [0u8; 512];
+    for part in slice.chunks(block.len() / N){
+        for (chunk, x) in block.chunks_exact_mut(N).zip(part){
+            chunk.copy_from_slice(&to_bytes(*x));
+        }
+        vec.write_bytes(&block[..part.len() * N]);
+    }
+}
+/// Implements Bufferable for u64.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 81234u64;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = u64::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for u64{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_number(self, vec, u64::to_be_bytes, u64::to_le_bytes);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        numbers_into_buffer(slice, vec, u64::to_be_bytes, u64::to_le_bytes);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        numbers_from_buffer(len, buf, u64::from_be_bytes, u64::from_le_bytes)
+    }
+
+    #[inline]
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        read_number(buf, u64::from_be_bytes, u64::from_le_bytes)
+    }
+
+    fn buffer_len(&self) -> usize{
+        8
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 8
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(8)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(len.checked_mul(8).ok_or(BufferError::LengthOverflow)?)
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        read_number_unchecked(buf, u64::from_be_bytes, u64::from_le_bytes)
+    }
+}
+/// Implements Bufferable for usize.
+/// Always written as 8 bytes, reading fails if the value does not fit.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 81234usize;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = usize::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for usize{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        (self as u64).into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        match vec.endian(){
+            Endian::Big => bulk_into_buffer(slice, vec, |x| (x as u64).to_be_bytes()),
+            Endian::Little => bulk_into_buffer(slice, vec, |x| (x as u64).to_le_bytes()),
+        }
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        let vec = u64::vec_from_buffer(len, buf)?;
+        vec.into_iter().map(|x| usize::try_from(x).ok()).collect()
+    }
+
+    #[inline]
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        usize::try_from(u64::from_buffer(buf)?).ok()
+    }
+
+    fn buffer_len(&self) -> usize{
+        8
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 8
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        u64::from_buffer_unchecked(buf) as usize
+    }
+}
+/// Implements Bufferable for u32.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 71u32;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = u32::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for u32{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_number(self, vec, u32::to_be_bytes, u32::to_le_bytes);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        numbers_into_buffer(slice, vec, u32::to_be_bytes, u32::to_le_bytes);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        numbers_from_buffer(len, buf, u32::from_be_bytes, u32::from_le_bytes)
+    }
+
+    #[inline]
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        read_number(buf, u32::from_be_bytes, u32::from_le_bytes)
+    }
+
+    fn buffer_len(&self) -> usize{
+        4
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 4
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(4)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(len.checked_mul(4).ok_or(BufferError::LengthOverflow)?)
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        read_number_unchecked(buf, u32::from_be_bytes, u32::from_le_bytes)
+    }
+}
+/// Implements Bufferable for u16.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 31u16;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = u16::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for u16{
+    const MIN_BUFFER_LEN: usize = 2;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_number(self, vec, u16::to_be_bytes, u16::to_le_bytes);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        numbers_into_buffer(slice, vec, u16::to_be_bytes, u16::to_le_bytes);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        numbers_from_buffer(len, buf, u16::from_be_bytes, u16::from_le_bytes)
+    }
+
+    #[inline]
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        read_number(buf, u16::from_be_bytes, u16::from_le_bytes)
+    }
+
+    fn buffer_len(&self) -> usize{
+        2
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 2
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(2)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(len.checked_mul(2).ok_or(BufferError::LengthOverflow)?)
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        read_number_unchecked(buf, u16::from_be_bytes, u16::from_le_bytes)
+    }
+}
+/// Implements Bufferable for u8.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 1u8;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = u8::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for u8{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        vec.write_bytes(&[self]);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        vec.write_bytes(slice);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        Option::Some(buf.read_bytes(len)?.to_vec())
+    }
+
+    #[inline]
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        read_number(buf, |[x]| x, |[x]| x)
+    }
+
+    fn buffer_len(&self) -> usize{
+        1
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len()
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(1)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(len)
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        buf.take_array_unchecked::<1>()[0]
+    }
+}
+/// Implements Bufferable for f64.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 1.001f64;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = f64::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for f64{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_number(self, vec, f64::to_be_bytes, f64::to_le_bytes);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        numbers_into_buffer(slice, vec, f64::to_be_bytes, f64::to_le_bytes);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        numbers_from_buffer(len, buf, f64::from_be_bytes, f64::from_le_bytes)
+    }
+
+    #[inline]
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        read_number(buf, f64::from_be_bytes, f64::from_le_bytes)
+    }
+
+    fn buffer_len(&self) -> usize{
+        8
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 8
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(8)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(len.checked_mul(8).ok_or(BufferError::LengthOverflow)?)
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        read_number_unchecked(buf, f64::from_be_bytes, f64::from_le_bytes)
+    }
+}
+/// Implements Bufferable for f32.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = 1.001f32;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = f32::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for f32{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_number(self, vec, f32::to_be_bytes, f32::to_le_bytes);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        numbers_into_buffer(slice, vec, f32::to_be_bytes, f32::to_le_bytes);
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        numbers_from_buffer(len, buf, f32::from_be_bytes, f32::from_le_bytes)
+    }
+
+    #[inline]
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        read_number(buf, f32::from_be_bytes, f32::from_le_bytes)
+    }
+
+    fn buffer_len(&self) -> usize{
+        4
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 4
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(4)
+    }
+
+    fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.skip(len.checked_mul(4).ok_or(BufferError::LengthOverflow)?)
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        read_number_unchecked(buf, f32::from_be_bytes, f32::from_le_bytes)
+    }
+}
+/// Implements Bufferable for a fixed size integer as its big endian two's complement bytes.
+macro_rules! be_int_impl{
+    ($t:ty, $n:expr, $x:expr) => {
+        #[doc = concat!("Implements Bufferable for ", stringify!($t), ".")]
+        /// # Example
+        /// ```
+        /// use bin_buffer::*;
+        #[doc = concat!("let x = ", stringify!($x), stringify!($t), ";")]
+        /// let mut buffer = Vec::new();
+        /// x.into_buffer(&mut buffer);
+        /// let mut buffer = ReadBuffer::from_raw(buffer);
+        #[doc = concat!("assert_eq!(Some(x), ", stringify!($t), "::from_buffer(&mut buffer));")]
+        /// ```
+        impl Bufferable for $t{
+            const MIN_BUFFER_LEN: usize = $n;
+
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                write_number(self, vec, <$t>::to_be_bytes, <$t>::to_le_bytes);
+            }
+
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                (*self).into_buffer(vec);
+            }
+
+            fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+                numbers_into_buffer(slice, vec, <$t>::to_be_bytes, <$t>::to_le_bytes);
+            }
+
+            fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+                numbers_from_buffer(len, buf, <$t>::from_be_bytes, <$t>::from_le_bytes)
+            }
+
+            #[inline]
+            fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+                read_number(buf, <$t>::from_be_bytes, <$t>::from_le_bytes)
+            }
+
+            fn buffer_len(&self) -> usize{
+                $n
+            }
+
+            fn slice_buffer_len(slice: &[Self]) -> usize{
+                slice.len() * $n
+            }
+
+            fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+                buf.skip($n)
+            }
+
+            fn validate_slice(len: usize, buf: &mut ReadBuffer) -> Result<(), BufferError>{
+                buf.skip(len.checked_mul($n).ok_or(BufferError::LengthOverflow)?)
+            }
+
+            #[inline]
+            unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+                read_number_unchecked(buf, <$t>::from_be_bytes, <$t>::from_le_bytes)
+            }
+        }
+    };
+}
+be_int_impl!(i8, 1, -8);
+be_int_impl!(i16, 2, -1600);
+be_int_impl!(i32, 4, -71);
+be_int_impl!(i64, 8, -81234);
+be_int_impl!(i128, 16, -1);
+be_int_impl!(u128, 16, 340282366920938463463374607431768211455);
+/// Implements Bufferable for isize.
+/// Always written as 8 bytes like an i64, reading fails if the value does not fit.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = -81234isize;
+/// let mut buffer = Vec::new();
+/// x.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = isize::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for isize{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        (self as i64).into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        match vec.endian(){
+            Endian::Big => bulk_into_buffer(slice, vec, |x| (x as i64).to_be_bytes()),
+            Endian::Little => bulk_into_buffer(slice, vec, |x| (x as i64).to_le_bytes()),
+        }
+    }
+
+    fn vec_from_buffer(len: usize, buf: &mut ReadBuffer) -> Option<Vec<Self>>{
+        let vec = i64::vec_from_buffer(len, buf)?;
+        vec.into_iter().map(|x| isize::try_from(x).ok()).collect()
+    }
+
+    #[inline]
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        isize::try_from(i64::from_buffer(buf)?).ok()
+    }
+
+    fn buffer_len(&self) -> usize{
+        8
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 8
+    }
+
+    #[inline]
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        i64::from_buffer_unchecked(buf) as isize
+    }
+}
+/// Implements Bufferable for bool.
+/// Written as one byte, 0 or 1, any other byte does not decode.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = Vec::new();
+/// true.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(true), bool::from_buffer(&mut buffer));
+/// ```
+impl Bufferable for bool{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        vec.write_bytes(&[self as u8]);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        let start = buf.iter;
+        match u8::try_from_buffer(buf)?{
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(buf.fail_at(start, BufferError::Invalid)),
+        }
+    }
+
+    fn buffer_len(&self) -> usize{
+        1
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len()
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        match buf.take_array::<1>().ok_or_else(|| buf.eof(1))?{
+            [0] | [1] => Ok(()),
+            _ => Err(BufferError::Invalid),
+        }
+    }
+}
+/// Implements Bufferable for char.
+/// Written as its scalar value in a u32, values that are not a char do not decode.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = Vec::new();
+/// 'ß'.into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some('ß'), char::from_buffer(&mut buffer));
+/// ```
+impl Bufferable for char{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        (self as u32).into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
+    }
+
+    fn copy_slice_into_buffer<S: Sink + ?Sized>(slice: &[Self], vec: &mut S){
+        match vec.endian(){
+            Endian::Big => bulk_into_buffer(slice, vec, |x| (x as u32).to_be_bytes()),
+            Endian::Little => bulk_into_buffer(slice, vec, |x| (x as u32).to_le_bytes()),
+        }
+    }
+
+    fn try_from_buffer(buf: &mut ReadBuffer) -> Result<Self, DecodeError>{
+        let start = buf.iter;
+        let x = u32::try_from_buffer(buf)?;
+        char::from_u32(x).ok_or_else(|| buf.fail_at(start, BufferError::Invalid))
+    }
+
+    fn buffer_len(&self) -> usize{
+        4
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 4
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let x = u32::from_buffer(buf).ok_or_else(|| buf.eof(4))?;
+        char::from_u32(x).map(drop).ok_or(BufferError::Invalid)
+    }
+}
+/// Implements Bufferable for String.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = String::from("cool and good");
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = String::from_buffer(&mut buffer);
+/// ```
+impl Bufferable for String{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        let bytes = self.as_bytes();
+        let len = bytes.len();
+        (len as u64).into_buffer(vec);
+        vec.write_bytes(bytes);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let string = buf.read_str()?;
+        let len = string.len();
+        let string = string.to_owned();
+        buf.use_bytes(len)?;
+        Option::Some(string)
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let len = buf.check_prefix(buf.limits.max_string_len)?;
+        std::str::from_utf8(buf.check_bytes(len)?).map_err(|_| BufferError::InvalidUtf8)?;
+        buf.check_use_bytes(len)
+    }
+
+    fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+        let string = buf.read_str()?;
+        let len = string.len();
+        self.clear();
+        self.push_str(string);
+        buf.use_bytes(len)
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.len()
+    }
+}
+/// Encode x into a new Buffer.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// assert_eq!(vec![0,1], encode(&1u16));
+/// ```
+pub fn encode<T: Bufferable>(x: &T) -> Buffer{
+    instrument::traced("encode", &std::any::type_name::<T>(), ||{
+        let mut buffer = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut buffer);
+        buffer
+    }, Vec::len)
+}
+/// The exact number of bytes x encodes to, found by encoding it into a SizeCounter.
+/// Unlike buffer_len this is never a guess, but it walks the whole value.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// assert_eq!(8 + 3 * 2, encoded_size_of(&vec![1u16, 2, 3]));
+/// assert_eq!(encode(&String::from("hi")).len(), encoded_size_of(&String::from("hi")));
+/// ```
+pub fn encoded_size_of<T: Bufferable>(x: &T) -> usize{
+    let mut counter = SizeCounter::new();
+    x.copy_into_buffer(&mut counter);
+    counter.len()
+}
+/// Write the items of iter as a Vec<T>, without collecting them first. Gives the number of items.
+/// If the size_hint of iter is exact, as it is for an ExactSizeIterator, the length goes in front
+/// right away. Otherwise it is patched in afterwards when vec is a Vec,
+/// and for other sinks the items are gathered in a Buffer first.
+/// Panics if an iterator with an exact size_hint gives another number of items.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = Vec::new();
+/// let words = "a bb ccc".split(' ').filter(|w| w.len() > 1).map(String::from);
+/// assert_eq!(2, write_iter(words, &mut buffer));
+/// assert_eq!(Some(vec![String::from("bb"), String::from("ccc")]), decode(&buffer));
+/// ```
+pub fn write_iter<T, I, S>(iter: I, vec: &mut S) -> usize
+    where T: Bufferable, I: IntoIterator<Item = T>, S: Sink + ?Sized
+{
+    let iter = iter.into_iter();
+    let mut count = 0;
+    if let (lower, Option::Some(upper)) = iter.size_hint(){
+        if lower == upper{
+            (lower as u64).into_buffer(vec);
+            for x in iter{
+                x.into_buffer(vec);
+                count += 1;
+            }
+            assert_eq!(lower, count, "iterator gave another number of items than its size_hint");
+            return count;
+        }
+    }
+    if let Option::Some(v) = vec.as_vec(){
+        let at = v.len();
+        0u64.into_buffer(v);
+        for x in iter{
+            x.into_buffer(v);
+            count += 1;
+        }
+        v[at..at + 8].copy_from_slice(&(count as u64).to_be_bytes());
+    } else {
+        let mut body = EndianSink::new(Vec::new(), vec.endian());
+        for x in iter{
+            x.into_buffer(&mut body);
+            count += 1;
+        }
+        (count as u64).into_buffer(vec);
+        vec.write_bytes(body.get_ref());
+    }
+    count
+}
+/// Decode a T that takes up all of bytes. None if it does not decode or bytes are left over.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let bytes = encode(&(1u8, String::from("a")));
+/// assert_eq!(Some((1u8, String::from("a"))), decode(&bytes));
+/// assert_eq!(None, decode::<u8>(&bytes));
+/// ```
+pub fn decode<T: Bufferable>(bytes: &[u8]) -> Option<T>{
+    instrument::traced("decode", &std::any::type_name::<T>(), ||{
+        let mut buffer = ReadBuffer::from_slice(bytes);
+        let x = T::from_buffer(&mut buffer)?;
+        if buffer.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }, |_| bytes.len())
+}
+/// decode with the reason and offset when it fails, BufferError::TrailingBytes if bytes are left over.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let bytes = encode(&(1u8, String::from("a")));
+/// assert_eq!(Ok((1u8, String::from("a"))), try_decode(&bytes));
+/// let err = try_decode::<u8>(&bytes).unwrap_err();
+/// assert_eq!((BufferError::TrailingBytes, 1), (err.error, err.offset));
+/// ```
+pub fn try_decode<T: Bufferable>(bytes: &[u8]) -> Result<T, DecodeError>{
+    instrument::traced("decode", &std::any::type_name::<T>(), ||{
+        let mut buffer = ReadBuffer::borrowed(bytes, DecodeLimits::default());
+        let x = T::try_from_buffer(&mut buffer)?;
+        if buffer.remaining() != 0{
+            return Err(DecodeError{ error: BufferError::TrailingBytes, offset: buffer.consumed(), len: bytes.len() });
+        }
+        Ok(x)
+    }, |_| bytes.len())
+}
+/// Read a Vec<T> into vec, reusing its allocation and those of its elements.
+/// What vec held before is gone, also when it fails.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut names: Vec<String> = Vec::new();
+/// for frame in &[vec!["a", "b"], vec!["c"]]{
+///     let bytes = encode(&frame.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+///     decode_into(&mut ReadBuffer::from_raw(bytes), &mut names).unwrap();
+/// }
+/// assert_eq!(vec![String::from("c")], names);
+/// ```
+pub fn decode_into<T: Bufferable>(buf: &mut ReadBuffer, vec: &mut Vec<T>) -> Option<()>{
+    vec.read_from_buffer(buf)
+}
+/// Read a Vec<T> into the start of slice, reusing the elements there.
+/// Gives the number of elements read, None if they do not fit in slice or do not decode.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut slots = [0u32; 4];
+/// assert_eq!(Some(2), decode_into_slice(&mut ReadBuffer::from_raw(encode(&vec![7u32, 8])), &mut slots));
+/// assert_eq!([7, 8, 0, 0], slots);
+/// assert_eq!(None, decode_into_slice(&mut ReadBuffer::from_raw(encode(&vec![1u32; 5])), &mut slots));
+/// ```
+pub fn decode_into_slice<T: Bufferable>(buf: &mut ReadBuffer, slice: &mut [T]) -> Option<usize>{
+    buf.nested(|buf|{
+        let len = buf.read_len()?;
+        for x in slice.get_mut(..len)?{
+            x.read_from_buffer(buf)?;
+        }
+        Option::Some(len)
+    })
+}
+/// Check that a valid T comes next in buf and move past it, without building it.
+/// Checks lengths against the limits of buf, UTF-8 and discriminants, so anything that
+/// validates also decodes, except for duplicate keys in maps and sets.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut bytes = Vec::new();
+/// vec![String::from("ok"), String::from("fine")].into_buffer(&mut bytes);
+/// assert_eq!(Ok(()), validate::<Vec<String>>(&mut ReadBuffer::from_raw(bytes.clone())));
+/// let last = bytes.len() - 1;
+/// bytes[last] = 0xff;
+/// assert_eq!(Err(BufferError::InvalidUtf8), validate::<Vec<String>>(&mut ReadBuffer::from_raw(bytes.clone())));
+/// assert_eq!(Err(BufferError::UnexpectedEof{ needed: 4, available: 3 }), validate::<Vec<String>>(&mut ReadBuffer::from_raw(bytes[..last].to_vec())));
+/// ```
+pub fn validate<T: Bufferable>(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+    T::validate(buf)
+}
+/// encode and decode as methods, for every Bufferable type.
+/// Stands in for From and TryFrom<&[u8]>, which can not be implemented for the std types.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let bytes = vec![1.5f32, 2.0].to_bytes();
+/// assert_eq!(Some(vec![1.5f32, 2.0]), Vec::<f32>::from_bytes(&bytes));
+/// ```
+pub trait BufferableExt: Bufferable{
+    /// See encode.
+    fn to_bytes(&self) -> Buffer{
+        encode(self)
+    }
+    /// Encode into a Buffer with room for exactly the bytes written, see encoded_size_of.
+    /// It walks the value twice, once to count and once to write, but never grows the Buffer,
+    /// even for values whose buffer_len is only a guess, and keeps no spare capacity around.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let records = vec![(1u32, String::from("a")), (2, String::from("bc"))];
+    /// let bytes = records.to_buffer_exact();
+    /// assert_eq!(encode(&records), bytes);
+    /// assert_eq!(bytes.len(), bytes.capacity());
+    /// ```
+    fn to_buffer_exact(&self) -> Buffer{
+        let mut buffer = Vec::with_capacity(encoded_size_of(self));
+        self.copy_into_buffer(&mut buffer);
+        buffer
+    }
+    /// See decode.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>{
+        decode(bytes)
+    }
+}
+
+impl<T: Bufferable> BufferableExt for T{}
+/// Just copies the content of the second buffer to the end of the first buffer.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = vec![0,1,2,3];
+/// let source = &vec![4,5];
+/// buffer_append_buffer(&mut buffer, source);
+/// assert_eq!(buffer,vec![0,1,2,3,4,5]);
+/// ```
+pub fn buffer_append_buffer(vec: &mut Buffer, string: &[u8]){
+    vec.extend_from_slice(string);
+}
+/// Join two encoded Vec<T> into one encoded Vec<T> with the elements of a and then b,
+/// without decoding them: only the length in front is rewritten.
+/// Works for Vec and ByteVec, not for HashMap or HashSet, whose entries have to stay sorted.
+/// None if a or b is too short to start with a length.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let a = encode(&vec![String::from("a"), String::from("b")]);
+/// let b = encode(&vec![String::from("c")]);
+/// let merged = merge_encoded_vecs(&a, &b).unwrap();
+/// assert_eq!(Some(vec![String::from("a"), String::from("b"), String::from("c")]), decode(&merged));
+/// ```
+pub fn merge_encoded_vecs(a: &[u8], b: &[u8]) -> Option<Buffer>{
+    merge_many_encoded_vecs(&[a, b])
+}
+/// merge_encoded_vecs for any number of parts, in order. No parts gives an empty Vec.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let shards: Vec<Buffer> = (0..4u32).map(|i| encode(&vec![i; 3])).collect();
+/// let parts: Vec<&[u8]> = shards.iter().map(|s| &s[..]).collect();
+/// let merged = merge_many_encoded_vecs(&parts).unwrap();
+/// assert_eq!(Some(vec![0u32, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3]), decode(&merged));
+/// ```
+pub fn merge_many_encoded_vecs(parts: &[&[u8]]) -> Option<Buffer>{
+    let mut len = 0u64;
+    let mut bytes = 8;
+    for part in parts{
+        let prefix = <[u8; 8]>::try_from(part.get(..8)?).ok()?;
+        len = len.checked_add(u64::from_be_bytes(prefix))?;
+        bytes += part.len() - 8;
+    }
+    let mut merged = Vec::with_capacity(bytes);
+    len.into_buffer(&mut merged);
+    for part in parts{
+        merged.extend_from_slice(&part[8..]);
+    }
+    Option::Some(merged)
+}
+/// Writes a buffer to a file.
+/// Will create a new file if none exists or overwrite otherwise.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let buf = vec![0,1,2];
+/// let path = std::path::Path::new("./buffer0.ntbr");
+/// buffer_write_file(&path, &buf);
+/// let res = buffer_read_file(&path);
+/// assert_eq!(res, Option::Some(buf));
+/// ```
+#[cfg(feature = "fs")]
+pub fn buffer_write_file(path: &std::path::Path, vec: &[u8]) -> bool{
+    instrument::traced("write file", &path.display(), ||{
+        let file = if let Ok(f) =
+            OpenOptions::new().write(true).create(true).truncate(true).open(path) { f }
+        else { return false; };
+        let mut opened = file;
+        if opened.write_all(vec).is_err() {return false;}
+        true
+    }, |_| vec.len())
+}
+/// Writes a buffer to the end of a file.
+/// Will create a new file if none exists.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let a = vec![0,1,2];
+/// let path = std::path::Path::new("./buffer1.ntbr");
+/// buffer_write_file(&path, &a);
+/// let b = vec![3,4];
+/// buffer_write_file_append(&path, &b);
+/// let res = buffer_read_file(&path);
+/// assert_eq!(res, Option::Some(vec![0,1,2,3,4]));
+/// ```
+#[cfg(feature = "fs")]
+pub fn buffer_write_file_append(path: &std::path::Path, vec: &[u8]) -> bool{
+    instrument::traced("append file", &path.display(), ||{
+        let file = if let Ok(f) =
+            OpenOptions::new().create(true).append(true).open(path) { f }
+        else { return false; };
+        let mut opened = file;
+        if opened.write_all(vec).is_err() {return false;}
+        true
+    }, |_| vec.len())
+}
+/// Reads a buffer from a file.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let path = std::path::Path::new("./buffer2.ntbr");
+/// let buffer = vec![0,1,2,3];
+/// buffer_write_file(&path, &buffer);
+/// let read_result = buffer_read_file(&path);
+/// assert_eq!(read_result, Option::Some(buffer));
+/// ```
+#[cfg(feature = "fs")]
+pub fn buffer_read_file(path: &std::path::Path) -> Option<Buffer>{
+    instrument::traced("read file", &path.display(), ||{
+        let file = if let Ok(f) =
+            OpenOptions::new().read(true).open(path) { f }
+        else { return Option::None; };
+        let mut opened = file;
+        let mut vec: Buffer = Vec::new();
+        if opened.read_to_end(&mut vec).is_err() { return Option::None; }
+        Option::Some(vec)
+    }, |vec| vec.as_ref().map_or(0, Vec::len))
+}
+/// Writes a buffer to a file with a CRC-32 trailer, see checksum::ChecksumWriter.
+/// Will create a new file if none exists, or overwrite otherwise.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let path = std::env::temp_dir().join("bin_buffer_checked_doc.ntbr");
+/// buffer_write_file_checked(&path, &[0, 1, 2]);
+/// assert_eq!(Some(Ok(vec![0, 1, 2])), buffer_read_file_checked(&path));
+/// assert_eq!(Some(vec![0, 1, 2, 0x08, 0x54, 0x89, 0x7f]), buffer_read_file(&path));
+/// // a flipped bit on the disk
+/// buffer_write_file(&path, &[0, 1, 3, 0x08, 0x54, 0x89, 0x7f]);
+/// assert!(matches!(buffer_read_file_checked(&path), Some(Err(checksum::ChecksumError::Mismatch{ .. }))));
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(feature = "fs")]
+pub fn buffer_write_file_checked(path: &std::path::Path, vec: &[u8]) -> bool{
+    let mut sink = checksum::ChecksumWriter::new(Vec::with_capacity(vec.len() + 4));
+    sink.write_bytes(vec);
+    buffer_write_file(path, &sink.finish())
+}
+/// Reads a buffer written by buffer_write_file_checked and checks its trailer.
+/// None if the file can not be read, an error if it is damaged.
+#[cfg(feature = "fs")]
+pub fn buffer_read_file_checked(path: &std::path::Path) -> Option<Result<Buffer, checksum::ChecksumError>>{
+    let mut vec = buffer_read_file(path)?;
+    Option::Some(match checksum::verify_crc32_trailer(&vec){
+        Ok(body) => {
+            let len = body.len();
+            vec.truncate(len);
+            Ok(vec)
+        },
+        Err(e) => Err(e),
+    })
+}
+/// Writes a buffer to a file compressed, see compress::compress_buffer.
+/// Will create a new file if none exists, or overwrite otherwise.
+/// With the `compression` feature.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let path = std::env::temp_dir().join("bin_buffer_compressed_doc.ntbr");
+/// let world = encode(&vec![0u32; 10000]);
+/// buffer_write_file_compressed(&path, &world);
+/// assert!(buffer_read_file(&path).unwrap().len() < 1000);
+/// assert_eq!(Some(world), buffer_read_file_compressed(&path));
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(all(feature = "fs", feature = "compression"))]
+pub fn buffer_write_file_compressed(path: &std::path::Path, vec: &[u8]) -> bool{
+    buffer_write_file(path, &compress::compress_buffer(vec))
+}
+/// Reads a buffer written by buffer_write_file_compressed and decompresses it.
+/// None if the file can not be read or does not decompress.
+#[cfg(all(feature = "fs", feature = "compression"))]
+pub fn buffer_read_file_compressed(path: &std::path::Path) -> Option<Buffer>{
+    compress::decompress_buffer(&buffer_read_file(path)?)
+}
+/// Implements Bufferable for Vec<Bufferable>
+/// Elements that take no bytes, like a unit struct, are only read while the length is at most
+/// the bytes left after it, so a bogus length can not keep the reader looping on nothing.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x = vec![0.0f32,1.0,2.0,3.0,4.0,5.5];
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = Vec::<f32>::from_buffer(&mut buffer);
+/// ```
+impl<T: Bufferable> Bufferable for Vec<T>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+        self.copy_into_buffer(buf);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+        buf.reserve(self.buffer_len());
+        let len = self.len() as u64;
+        len.into_buffer(buf);
+        T::copy_slice_into_buffer(self, buf);
+    }
 
-impl ReadBuffer{
-    /// Create ReadBuffer from Buffer.
-    pub fn from_raw(vec: Buffer) -> Self{
-        Self{
-            buffer: vec,
-            iter: 0,
-        }
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            T::vec_from_buffer(len, buf)
+        })
     }
-    /// Turn ReadBuffer into Buffer.
-    pub fn into_raw(self) -> Buffer{
-        self.buffer
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_nested(|buf|{
+            let len = buf.check_prefix(buf.limits.max_elements)?;
+            buf.check_use_bytes(len.checked_mul(std::mem::size_of::<T>()).ok_or(BufferError::LengthOverflow)?)?;
+            T::validate_slice(len, buf)
+        })
     }
-    /// If the ReadBuffer is empty.
-    pub fn is_empty(&self) -> bool{
-        self.buffer.is_empty()
+
+    /// Elements that are already there are read into, the rest is pushed.
+    fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            buf.check_count(len, T::MIN_BUFFER_LEN).ok()?;
+            self.truncate(len);
+            for x in self.iter_mut(){
+                x.read_from_buffer(buf)?;
+            }
+            self.reserve((len - self.len()).min(buf.remaining() / T::MIN_BUFFER_LEN.max(1)));
+            while self.len() < len{
+                self.push(T::from_buffer(buf)?);
+            }
+            Option::Some(())
+        })
     }
-}
 
-/// Object can be read and written to a Buffer
-pub trait Bufferable where Self: std::marker::Sized{
-    /// Consume yourself and add to the end of the buffer
-    fn into_buffer(self, vec: &mut Buffer);
-    /// Copy yourself and add to the end of the buffer.
-    fn copy_into_buffer(&self, vec: &mut Buffer);
-    /// Read object from buffer
-    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>;
+    fn buffer_len(&self) -> usize{
+        8 + T::slice_buffer_len(self)
+    }
 }
-/// Implements Bufferable for u64.
+// Encode every item on its own and sort them by their bytes,
+// so the output does not depend on the order of iteration.
+fn sorted_encoded<'a, T: Bufferable + 'a>(items: impl Iterator<Item = &'a T>, endian: Endian) -> Vec<Buffer>{
+    let mut encoded: Vec<Buffer> = items.map(|x|{
+        let mut vec = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut EndianSink::new(&mut vec, endian));
+        vec
+    }).collect();
+    encoded.sort_unstable();
+    encoded
+}
+/// Implements Bufferable for arrays where T is Bufferable.
+/// The length is part of the type, so only the elements are written, without a length in front.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 81234u64;
+/// let x = [1.0f32, 2.0, 3.0];
 /// let mut buffer = Vec::new();
 /// x.into_buffer(&mut buffer);
+/// assert_eq!(12, buffer.len());
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = u64::from_buffer(&mut buffer);
+/// assert_eq!(Some(x), <[f32; 3]>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for u64{
-    fn into_buffer(self, vec: &mut Buffer){
-        vec.push(((self >> 56) & 0xff) as u8);
-        vec.push(((self >> 48) & 0xff) as u8);
-        vec.push(((self >> 40) & 0xff) as u8);
-        vec.push(((self >> 32) & 0xff) as u8);
-        vec.push(((self >> 24) & 0xff) as u8);
-        vec.push(((self >> 16) & 0xff) as u8);
-        vec.push(((self >> 8) & 0xff) as u8);
-        vec.push((self & 0xff) as u8);
+impl<T: Bufferable, const N: usize> Bufferable for [T; N]{
+    const MIN_BUFFER_LEN: usize = T::MIN_BUFFER_LEN * N;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        T::copy_slice_into_buffer(self, vec);
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 8 > buf.buffer.len() { return Option::None; }
-        let mut val: u64 = 0;
-        val += u64::from(buf.buffer[(buf.iter    )]) << 56;
-        val += u64::from(buf.buffer[(buf.iter + 1)]) << 48;
-        val += u64::from(buf.buffer[(buf.iter + 2)]) << 40;
-        val += u64::from(buf.buffer[(buf.iter + 3)]) << 32;
-        val += u64::from(buf.buffer[(buf.iter + 4)]) << 24;
-        val += u64::from(buf.buffer[(buf.iter + 5)]) << 16;
-        val += u64::from(buf.buffer[(buf.iter + 6)]) << 8;
-        val += u64::from(buf.buffer[(buf.iter + 7)]);
-        buf.iter += 8;
-        Option::Some(val)
+        let mut ok = true;
+        let xs = [(); N].map(|()|{
+            let x = if ok { T::from_buffer(buf) } else { Option::None };
+            ok = x.is_some();
+            x
+        });
+        if !ok{
+            return Option::None;
+        }
+        Option::Some(xs.map(Option::unwrap))
+    }
+
+    unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+        [(); N].map(|()| T::from_buffer_unchecked(buf))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        // N does not come from the buffer, so elements of no bytes need no check against what is left
+        if T::MIN_BUFFER_LEN == 0{
+            return (0..N).try_for_each(|_| T::validate(buf));
+        }
+        T::validate_slice(N, buf)
+    }
+
+    fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+        for x in self.iter_mut(){
+            x.read_from_buffer(buf)?;
+        }
+        Option::Some(())
+    }
+
+    fn buffer_len(&self) -> usize{
+        T::slice_buffer_len(self)
     }
 }
-/// Implements Bufferable for usize.
-/// Only for 64 bit.
+/// Write a slice as a Vec<T>, without copying it into a Vec first.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 81234usize;
+/// let points = [(0.0f32, 1.0f32), (2.0, 3.0), (4.0, 5.0)];
 /// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
+/// write_slice(&points[1..], &mut buffer);
+/// assert_eq!(Some(points[1..].to_vec()), decode(&buffer));
+/// ```
+pub fn write_slice<T: Bufferable, S: Sink + ?Sized>(slice: &[T], vec: &mut S){
+    vec.reserve(8 + T::slice_buffer_len(slice));
+    (slice.len() as u64).into_buffer(vec);
+    T::copy_slice_into_buffer(slice, vec);
+}
+/// Implements Bufferable for HashMap where K and V are Bufferable.
+/// Entries are sorted by the bytes of their key, so equal maps always give equal buffers.
+/// In the buffer it looks like a Vec<(K,V)>. Reading fails on duplicate keys.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use std::collections::HashMap;
+/// let mut x = HashMap::new();
+/// x.insert(String::from("one"), 1u32);
+/// x.insert(String::from("two"), 2u32);
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = usize::from_buffer(&mut buffer);
+/// let y = HashMap::<String,u32>::from_buffer(&mut buffer);
+/// assert_eq!(Some(x), y);
 /// ```
-impl Bufferable for usize{
-    fn into_buffer(self, vec: &mut Buffer){
-        vec.push(((self >> 56) & 0xff) as u8);
-        vec.push(((self >> 48) & 0xff) as u8);
-        vec.push(((self >> 40) & 0xff) as u8);
-        vec.push(((self >> 32) & 0xff) as u8);
-        vec.push(((self >> 24) & 0xff) as u8);
-        vec.push(((self >> 16) & 0xff) as u8);
-        vec.push(((self >> 8) & 0xff) as u8);
-        vec.push((self & 0xff) as u8);
+impl<K, V, H> Bufferable for HashMap<K,V,H>
+    where
+        K: Bufferable + Eq + Hash,
+        V: Bufferable,
+        H: BuildHasher + Default,
+{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+        self.copy_into_buffer(buf);
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+        let endian = buf.endian();
+        let mut entries: Vec<(Buffer, &V)> = self.iter().map(|(k, v)|{
+            let mut vec = Vec::with_capacity(k.buffer_len());
+            k.copy_into_buffer(&mut EndianSink::new(&mut vec, endian));
+            (vec, v)
+        }).collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        buf.reserve(self.buffer_len());
+        (self.len() as u64).into_buffer(buf);
+        for (k, v) in entries{
+            buf.write_bytes(&k);
+            v.copy_into_buffer(buf);
+        }
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 8 > buf.buffer.len() { return Option::None; }
-        let mut val: usize = 0;
-        val += usize::from(buf.buffer[(buf.iter    )]) << 56;
-        val += usize::from(buf.buffer[(buf.iter + 1)]) << 48;
-        val += usize::from(buf.buffer[(buf.iter + 2)]) << 40;
-        val += usize::from(buf.buffer[(buf.iter + 3)]) << 32;
-        val += usize::from(buf.buffer[(buf.iter + 4)]) << 24;
-        val += usize::from(buf.buffer[(buf.iter + 5)]) << 16;
-        val += usize::from(buf.buffer[(buf.iter + 6)]) << 8;
-        val += usize::from(buf.buffer[(buf.iter + 7)]);
-        buf.iter += 8;
-        Option::Some(val)
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<(K,V)>())?)?;
+            let min = (K::MIN_BUFFER_LEN + V::MIN_BUFFER_LEN).max(1);
+            let mut map = HashMap::with_capacity_and_hasher(len.min(buf.remaining() / min), H::default());
+            for _ in 0..len{
+                let k = K::from_buffer(buf)?;
+                let v = V::from_buffer(buf)?;
+                if map.insert(k, v).is_some(){
+                    return Option::None;
+                }
+            }
+            Option::Some(map)
+        })
+    }
+
+    // Duplicate keys are only found by decoding.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_nested(|buf|{
+            let len = buf.check_prefix(buf.limits.max_elements)?;
+            buf.check_use_bytes(len.checked_mul(std::mem::size_of::<(K,V)>()).ok_or(BufferError::LengthOverflow)?)?;
+            buf.check_count(len, K::MIN_BUFFER_LEN + V::MIN_BUFFER_LEN)?;
+            for _ in 0..len{
+                K::validate(buf)?;
+                V::validate(buf)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.iter().map(|(k, v)| k.buffer_len() + v.buffer_len()).sum::<usize>()
     }
 }
-/// Implements Bufferable for u32.
+/// Implements Bufferable for HashSet where T is Bufferable.
+/// Items are sorted by their bytes, so equal sets always give equal buffers.
+/// In the buffer it looks like a Vec<T>. Reading fails on duplicate items.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 71u32;
+/// use std::collections::HashSet;
+/// let x: HashSet<u16> = vec![3,1,2].into_iter().collect();
 /// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
+/// x.copy_into_buffer(&mut buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = u32::from_buffer(&mut buffer);
+/// assert_eq!(Some(vec![1u16,2,3]), Vec::<u16>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for u32{
-    fn into_buffer(self, vec: &mut Buffer){
-        vec.push(((self >> 24) & 0xff) as u8);
-        vec.push(((self >> 16) & 0xff) as u8);
-        vec.push(((self >> 8) & 0xff) as u8);
-        vec.push((self & 0xff) as u8);
+impl<T, H> Bufferable for HashSet<T,H>
+    where
+        T: Bufferable + Eq + Hash,
+        H: BuildHasher + Default,
+{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+        self.copy_into_buffer(buf);
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+        let items = sorted_encoded(self.iter(), buf.endian());
+        buf.reserve(self.buffer_len());
+        (self.len() as u64).into_buffer(buf);
+        for x in items{
+            buf.write_bytes(&x);
+        }
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 4 > buf.buffer.len() { return Option::None; }
-        let mut val: u32 = 0;
-        val += u32::from(buf.buffer[(buf.iter    )]) << 24;
-        val += u32::from(buf.buffer[(buf.iter + 1)]) << 16;
-        val += u32::from(buf.buffer[(buf.iter + 2)]) << 8;
-        val += u32::from(buf.buffer[(buf.iter + 3)]);
-        buf.iter += 4;
-        Option::Some(val)
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            let cap = len.min(buf.remaining() / T::MIN_BUFFER_LEN.max(1));
+            let mut set = HashSet::with_capacity_and_hasher(cap, H::default());
+            for _ in 0..len{
+                if !set.insert(T::from_buffer(buf)?){
+                    return Option::None;
+                }
+            }
+            Option::Some(set)
+        })
+    }
+
+    // Duplicate items are only found by decoding.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_nested(|buf|{
+            let len = buf.check_prefix(buf.limits.max_elements)?;
+            buf.check_use_bytes(len.checked_mul(std::mem::size_of::<T>()).ok_or(BufferError::LengthOverflow)?)?;
+            T::validate_slice(len, buf)
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.iter().map(Bufferable::buffer_len).sum::<usize>()
     }
 }
-/// Implements Bufferable for u16.
+/// Implements Bufferable for BTreeMap where K and V are Bufferable.
+/// Entries are written in key order, in the buffer it looks like a Vec<(K,V)>,
+/// so it reads a HashMap as well. Reading fails on duplicate keys.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 31u16;
+/// use std::collections::BTreeMap;
+/// let mut x = BTreeMap::new();
+/// x.insert(2u8, String::from("two"));
+/// x.insert(1u8, String::from("one"));
 /// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
+/// x.copy_into_buffer(&mut buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = u16::from_buffer(&mut buffer);
+/// assert_eq!(Some(x), BTreeMap::<u8,String>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for u16{
-    fn into_buffer(self, vec: &mut Buffer){
-        vec.push(((self >> 8) & 0xff) as u8);
-        vec.push((self & 0xff) as u8);
+impl<K: Bufferable + Ord, V: Bufferable> Bufferable for BTreeMap<K,V>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+        self.copy_into_buffer(buf);
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+        buf.reserve(self.buffer_len());
+        (self.len() as u64).into_buffer(buf);
+        for (k, v) in self{
+            k.copy_into_buffer(buf);
+            v.copy_into_buffer(buf);
+        }
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 2 > buf.buffer.len() { return Option::None; }
-        let mut val: u16 = 0;
-        val += u16::from(buf.buffer[(buf.iter    )]) << 8;
-        val += u16::from(buf.buffer[(buf.iter + 1)]);
-        buf.iter += 2;
-        Option::Some(val)
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<(K,V)>())?)?;
+            let mut map = BTreeMap::new();
+            for _ in 0..len{
+                let k = K::from_buffer(buf)?;
+                let v = V::from_buffer(buf)?;
+                if map.insert(k, v).is_some(){
+                    return Option::None;
+                }
+            }
+            Option::Some(map)
+        })
+    }
+
+    // Duplicate keys are only found by decoding.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_nested(|buf|{
+            let len = buf.check_prefix(buf.limits.max_elements)?;
+            buf.check_use_bytes(len.checked_mul(std::mem::size_of::<(K,V)>()).ok_or(BufferError::LengthOverflow)?)?;
+            buf.check_count(len, K::MIN_BUFFER_LEN + V::MIN_BUFFER_LEN)?;
+            for _ in 0..len{
+                K::validate(buf)?;
+                V::validate(buf)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.iter().map(|(k, v)| k.buffer_len() + v.buffer_len()).sum::<usize>()
     }
 }
-/// Implements Bufferable for u8.
+/// Implements Bufferable for BTreeSet where T is Bufferable.
+/// Items are written in order, in the buffer it looks like a Vec<T>. Reading fails on duplicate items.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 1u8;
+/// use std::collections::BTreeSet;
+/// let x: BTreeSet<i32> = vec![3,-1,2].into_iter().collect();
 /// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
+/// x.copy_into_buffer(&mut buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = u8::from_buffer(&mut buffer);
+/// assert_eq!(Some(vec![-1,2,3]), Vec::<i32>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for u8{
-    fn into_buffer(self, vec: &mut Buffer){
-        vec.push(self);
+impl<T: Bufferable + Ord> Bufferable for BTreeSet<T>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+        self.copy_into_buffer(buf);
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+        buf.reserve(self.buffer_len());
+        (self.len() as u64).into_buffer(buf);
+        for x in self{
+            x.copy_into_buffer(buf);
+        }
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 1 > buf.buffer.len() { return Option::None; }
-        let val = buf.buffer[buf.iter];
-        buf.iter += 1;
-        Option::Some(val)
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            let mut set = BTreeSet::new();
+            for _ in 0..len{
+                if !set.insert(T::from_buffer(buf)?){
+                    return Option::None;
+                }
+            }
+            Option::Some(set)
+        })
+    }
+
+    // Duplicate items are only found by decoding.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_nested(|buf|{
+            let len = buf.check_prefix(buf.limits.max_elements)?;
+            buf.check_use_bytes(len.checked_mul(std::mem::size_of::<T>()).ok_or(BufferError::LengthOverflow)?)?;
+            T::validate_slice(len, buf)
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.iter().map(Bufferable::buffer_len).sum::<usize>()
     }
 }
-/// Implements Bufferable for f64.
+/// Implements Bufferable for VecDeque where T is Bufferable.
+/// In the buffer it looks exactly like a Vec<T>, front first.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 1.001f64;
+/// use std::collections::VecDeque;
+/// let mut x = VecDeque::new();
+/// x.push_back(2u32);
+/// x.push_front(1u32);
 /// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
+/// x.copy_into_buffer(&mut buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = f64::from_buffer(&mut buffer);
+/// assert_eq!(Some(vec![1u32,2]), Vec::<u32>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for f64{
-    fn into_buffer(self, vec: &mut Buffer){
-        let bytes = self.to_be_bytes();
-        for b in bytes.iter(){
-            vec.push(*b);
-        }
+impl<T: Bufferable> Bufferable for VecDeque<T>{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+        self.copy_into_buffer(buf);
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+        buf.reserve(self.buffer_len());
+        (self.len() as u64).into_buffer(buf);
+        let (front, back) = self.as_slices();
+        T::copy_slice_into_buffer(front, buf);
+        T::copy_slice_into_buffer(back, buf);
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 8 > buf.buffer.len() { return Option::None; }
-        let mut bytes = [0u8; 8];
-        for (i, byte) in bytes.iter_mut().enumerate(){
-            *byte = buf.buffer[buf.iter + i];
-        }
-        buf.iter += 8;
-        Option::Some(f64::from_be_bytes(bytes))
+        Vec::<T>::from_buffer(buf).map(VecDeque::from)
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        Vec::<T>::validate(buf)
+    }
+
+    fn buffer_len(&self) -> usize{
+        let (front, back) = self.as_slices();
+        8 + T::slice_buffer_len(front) + T::slice_buffer_len(back)
     }
 }
-/// Implements Bufferable for f32.
+/// Implements Bufferable for Option where T is Bufferable.
+/// Written as a tag byte, 0 for None and 1 for Some followed by the value.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = 1.001f32;
+/// let x = (Some(5u16), Option::<u16>::None);
 /// let mut buffer = Vec::new();
 /// x.into_buffer(&mut buffer);
+/// assert_eq!(vec![1, 0, 5, 0], buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = f32::from_buffer(&mut buffer);
+/// assert_eq!(Some(x), <(Option<u16>, Option<u16>)>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for f32{
-    fn into_buffer(self, vec: &mut Buffer){
-        let bytes = self.to_be_bytes();
-        for b in bytes.iter(){
-            vec.push(*b);
+impl<T: Bufferable> Bufferable for Option<T>{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        match self{
+            Option::Some(x) => {
+                1u8.into_buffer(vec);
+                x.into_buffer(vec);
+            },
+            Option::None => 0u8.into_buffer(vec),
         }
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        self.clone().into_buffer(vec);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        match self{
+            Option::Some(x) => {
+                1u8.into_buffer(vec);
+                x.copy_into_buffer(vec);
+            },
+            Option::None => 0u8.into_buffer(vec),
+        }
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        if buf.iter + 4 > buf.buffer.len() { return Option::None; }
-        let mut bytes = [0u8; 4];
-        for (i,byte) in bytes.iter_mut().enumerate(){
-            *byte = buf.buffer[buf.iter + i];
+        match u8::from_buffer(buf)?{
+            0 => Option::Some(Option::None),
+            1 => Option::Some(Option::Some(T::from_buffer(buf)?)),
+            _ => Option::None,
+        }
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        match u8::from_buffer(buf).ok_or_else(|| buf.eof(1))?{
+            0 => Ok(()),
+            1 => T::validate(buf),
+            _ => Err(BufferError::InvalidDiscriminant),
+        }
+    }
+
+    /// A value that is already there is read into when the buffer holds one too.
+    fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+        match (u8::from_buffer(buf)?, self.as_mut()){
+            (0, _) => *self = Option::None,
+            (1, Option::Some(x)) => x.read_from_buffer(buf)?,
+            (1, Option::None) => *self = Option::Some(T::from_buffer(buf)?),
+            _ => return Option::None,
         }
-        buf.iter += 4;
-        Option::Some(f32::from_be_bytes(bytes))
+        Option::Some(())
+    }
+
+    fn buffer_len(&self) -> usize{
+        1 + self.as_ref().map_or(0, Bufferable::buffer_len)
     }
 }
-/// Implements Bufferable for String.
+/// Implements Bufferable for Result where T and E are Bufferable.
+/// Written as a tag byte, 0 for Ok and 1 for Err, followed by the value.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = String::from("cool and good");
+/// let x: Result<u32, String> = Err(String::from("not found"));
 /// let mut buffer = Vec::new();
 /// x.copy_into_buffer(&mut buffer);
 /// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = String::from_buffer(&mut buffer);
+/// assert_eq!(Some(x), Result::<u32, String>::from_buffer(&mut buffer));
 /// ```
-impl Bufferable for String{
-    fn into_buffer(self, vec: &mut Buffer){
-        self.copy_into_buffer(vec);
+impl<T: Bufferable, E: Bufferable> Bufferable for Result<T,E>{
+    const MIN_BUFFER_LEN: usize = 1 + if T::MIN_BUFFER_LEN < E::MIN_BUFFER_LEN { T::MIN_BUFFER_LEN } else { E::MIN_BUFFER_LEN };
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        match self{
+            Ok(x) => {
+                0u8.into_buffer(vec);
+                x.into_buffer(vec);
+            },
+            Err(e) => {
+                1u8.into_buffer(vec);
+                e.into_buffer(vec);
+            },
+        }
     }
 
-    fn copy_into_buffer(&self, vec: &mut Buffer){
-        let bytes = self.as_bytes();
-        let len = bytes.len();
-        (len as u64).into_buffer(vec);
-        for b in bytes.iter(){
-            vec.push(*b);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        match self{
+            Ok(x) => {
+                0u8.into_buffer(vec);
+                x.copy_into_buffer(vec);
+            },
+            Err(e) => {
+                1u8.into_buffer(vec);
+                e.copy_into_buffer(vec);
+            },
         }
     }
 
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        let len = (u64::from_buffer(buf))? as usize;
-        if buf.iter + len > buf.buffer.len() { return Option::None; }
-        let mut bytes = Vec::new();
-        for i in 0..len{
-            bytes.push(buf.buffer[buf.iter + i]);
+        match u8::from_buffer(buf)?{
+            0 => Option::Some(Ok(T::from_buffer(buf)?)),
+            1 => Option::Some(Err(E::from_buffer(buf)?)),
+            _ => Option::None,
+        }
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        match u8::from_buffer(buf).ok_or_else(|| buf.eof(1))?{
+            0 => T::validate(buf),
+            1 => E::validate(buf),
+            _ => Err(BufferError::InvalidDiscriminant),
+        }
+    }
+
+    fn buffer_len(&self) -> usize{
+        1 + match self{
+            Ok(x) => x.buffer_len(),
+            Err(e) => e.buffer_len(),
         }
-        buf.iter += len;
-        if let Ok(r) = String::from_utf8(bytes) { Some(r) }
-        else { Option::None }
     }
 }
-/// Just copies the content of the second buffer to the end of the first buffer.
+/// A Vec of bytes that is written and read as a single block.
+/// In the buffer it looks exactly like a Vec<u8>, so the two can be mixed.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let mut buffer = vec![0,1,2,3];
-/// let source = &vec![4,5];
-/// buffer_append_buffer(&mut buffer, source);
-/// assert_eq!(buffer,vec![0,1,2,3,4,5]);
+/// let x = ByteVec(vec![0u8,1,2,3,255]);
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let y = ByteVec::from_buffer(&mut buffer);
+/// assert_eq!(Some(x), y);
 /// ```
-pub fn buffer_append_buffer(vec: &mut Buffer, string: &[u8]){
-    for byte in string{
-        vec.push(*byte);
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ByteVec(pub Vec<u8>);
+
+impl std::ops::Deref for ByteVec{
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Vec<u8>{
+        &self.0
     }
 }
-/// Writes a buffer to a file.
-/// Will create a new file if none exists or overwrite otherwise.
+
+impl std::ops::DerefMut for ByteVec{
+    fn deref_mut(&mut self) -> &mut Vec<u8>{
+        &mut self.0
+    }
+}
+
+impl From<Vec<u8>> for ByteVec{
+    fn from(vec: Vec<u8>) -> Self{
+        Self(vec)
+    }
+}
+
+impl From<ByteVec> for Vec<u8>{
+    fn from(bytes: ByteVec) -> Self{
+        bytes.0
+    }
+}
+
+impl Bufferable for ByteVec{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (self.0.len() as u64).into_buffer(vec);
+        vec.write_bytes(&self.0);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let len = buf.read_len()?;
+        buf.use_bytes(len)?;
+        Option::Some(Self(buf.read_bytes(len)?.to_vec()))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let len = buf.check_prefix(buf.limits.max_elements)?;
+        buf.check_use_bytes(len)?;
+        buf.skip(len)
+    }
+
+    fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+        let len = buf.read_len()?;
+        buf.use_bytes(len)?;
+        let bytes = buf.read_bytes(len)?;
+        self.0.clear();
+        self.0.extend_from_slice(bytes);
+        Option::Some(())
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.0.len()
+    }
+}
+/// A String that decodes invalid UTF-8 with replacement characters instead of failing.
+/// It has the same layout as String, use it to read data with a few corrupted strings.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let buf = vec![0,1,2];
-/// let path = std::path::Path::new("./buffer0.ntbr");
-/// buffer_write_file(&path, &buf);
-/// let res = buffer_read_file(&path);
-/// assert_eq!(res, Option::Some(buf));
+/// let mut buffer = Vec::new();
+/// ByteVec(vec![b'h', b'i', 0xff]).into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let x = LossyString::from_buffer(&mut buffer).unwrap();
+/// assert_eq!("hi\u{FFFD}", x.as_str());
 /// ```
-pub fn buffer_write_file(path: &std::path::Path, vec: &[u8]) -> bool{
-    let file = if let Ok(f) =
-        OpenOptions::new().write(true).create(true).truncate(true).open(path) { f }
-    else { return false; };
-    let mut opened = file;
-    if opened.write_all(&vec).is_err() {return false;}
-    true
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LossyString(pub String);
+
+impl std::ops::Deref for LossyString{
+    type Target = String;
+    fn deref(&self) -> &String{
+        &self.0
+    }
 }
-/// Writes a buffer to the end of a file.
-/// Will create a new file if none exists.
+
+impl std::ops::DerefMut for LossyString{
+    fn deref_mut(&mut self) -> &mut String{
+        &mut self.0
+    }
+}
+
+impl From<String> for LossyString{
+    fn from(string: String) -> Self{
+        Self(string)
+    }
+}
+
+impl From<LossyString> for String{
+    fn from(string: LossyString) -> Self{
+        string.0
+    }
+}
+
+impl Bufferable for LossyString{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.0.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.0.copy_into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let string = String::from_utf8_lossy(buf.read_str_bytes()?).into_owned();
+        buf.use_bytes(string.len())?;
+        Option::Some(Self(string))
+    }
+
+    // Counts the bytes as written, replacement characters can make the decoded string longer.
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let len = buf.check_prefix(buf.limits.max_string_len)?;
+        buf.skip(len)?;
+        buf.check_use_bytes(len)
+    }
+
+    fn buffer_len(&self) -> usize{
+        self.0.buffer_len()
+    }
+}
+/// Implements Bufferable for CString, written as its bytes and a 0, without a length.
+/// For files made by C programs, use String for everything else.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let a = vec![0,1,2];
-/// let path = std::path::Path::new("./buffer1.ntbr");
-/// buffer_write_file(&path, &a);
-/// let b = vec![3,4];
-/// buffer_write_file_append(&path, &b);
-/// let res = buffer_read_file(&path);
-/// assert_eq!(res, Option::Some(vec![0,1,2,3,4]));
+/// use std::ffi::CString;
+/// let x = CString::new("hello").unwrap();
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// assert_eq!(b"hello\0".to_vec(), buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(x), CString::from_buffer(&mut buffer));
 /// ```
-pub fn buffer_write_file_append(path: &std::path::Path, vec: &[u8]) -> bool{
-    let file = if let Ok(f) =
-        OpenOptions::new().write(true).create(true).append(true).open(path) { f }
-    else { return false; };
-    let mut opened = file;
-    if opened.write_all(&vec).is_err() {return false;}
-    true
+impl Bufferable for std::ffi::CString{
+    const MIN_BUFFER_LEN: usize = 1;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(self.as_bytes_with_nul());
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let string = buf.read_cstr()?.to_owned();
+        buf.use_bytes(string.as_bytes().len())?;
+        Option::Some(string)
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let remaining = buf.remaining();
+        // without a 0 the bytes end inside the string, one more is needed at least
+        let len = buf.buffer[buf.iter..].iter().position(|b| *b == 0).ok_or_else(|| buf.eof(remaining + 1))?;
+        if len > buf.limits.max_string_len{
+            return Err(BufferError::LengthOverflow);
+        }
+        buf.check_bytes(len + 1)?;
+        buf.check_use_bytes(len)
+    }
+
+    fn buffer_len(&self) -> usize{
+        self.as_bytes_with_nul().len()
+    }
 }
-/// Reads a buffer from a file.
+/// String written as UTF-16 little endian, for Windows and other formats that use it.
+/// A u64 with the number of code units (not bytes) comes first, like the length of a String.
+/// Reading fails on unpaired surrogates and counts the length against DecodeLimits::max_string_len.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let path = std::path::Path::new("./buffer2.ntbr");
-/// let buffer = vec![0,1,2,3];
-/// buffer_write_file(&path, &buffer);
-/// let read_result = buffer_read_file(&path);
-/// assert_eq!(read_result, Option::Some(buffer));
+/// let mut buffer = Vec::new();
+/// WideString(String::from("hé")).into_buffer(&mut buffer);
+/// assert_eq!(vec![0,0,0,0,0,0,0,2, b'h',0, 0xe9,0], buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!("hé", WideString::from_buffer(&mut buffer).unwrap().as_str());
 /// ```
-pub fn buffer_read_file(path: &std::path::Path) -> Option<Buffer>{
-    let file = if let Ok(f) =
-        OpenOptions::new().read(true).open(path) { f }
-    else { return Option::None; };
-    let mut opened = file;
-    let mut vec: Buffer = Vec::new();
-    if opened.read_to_end(&mut vec).is_err() { return Option::None; }
-    Option::Some(vec)
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WideString(pub String);
+
+impl std::ops::Deref for WideString{
+    type Target = String;
+    fn deref(&self) -> &String{
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for WideString{
+    fn deref_mut(&mut self) -> &mut String{
+        &mut self.0
+    }
+}
+
+impl From<String> for WideString{
+    fn from(string: String) -> Self{
+        Self(string)
+    }
+}
+
+impl From<WideString> for String{
+    fn from(string: WideString) -> Self{
+        string.0
+    }
+}
+
+impl Bufferable for WideString{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.reserve(self.buffer_len());
+        (self.0.encode_utf16().count() as u64).into_buffer(vec);
+        for unit in self.0.encode_utf16(){
+            vec.write_bytes(&unit.to_le_bytes());
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let len = buf.read_prefix(buf.limits.max_string_len)?;
+        let bytes = buf.read_bytes(len.checked_mul(2)?)?;
+        let units = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let string = std::char::decode_utf16(units).collect::<Result<String, _>>().ok()?;
+        buf.use_bytes(string.len())?;
+        Option::Some(Self(string))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let len = buf.check_prefix(buf.limits.max_string_len)?;
+        let bytes = buf.check_bytes(len.checked_mul(2).ok_or(BufferError::LengthOverflow)?)?;
+        let units = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let mut utf8_len = 0;
+        for c in std::char::decode_utf16(units){
+            utf8_len += c.map_err(|_| BufferError::InvalidUtf8)?.len_utf8();
+        }
+        buf.check_use_bytes(utf8_len)
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + 2 * self.0.encode_utf16().count()
+    }
 }
-/// Implements Bufferable for Vec<Bufferable + Clone>
+/// String that is always written as exactly N bytes, for fixed width fields in other formats.
+/// Shorter strings are padded with 0 bytes. FixedStr::new cuts longer strings off
+/// at the last char that still fits, and at the first 0 byte, so it reads back the same.
+/// Reading takes N bytes and ends the string at the first 0 byte, ignoring what follows.
+/// None if that part is not UTF-8.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = vec![0.0f32,1.0,2.0,3.0,4.0,5.5];
+/// let x = FixedStr::<4>::new("ab");
 /// let mut buffer = Vec::new();
 /// x.copy_into_buffer(&mut buffer);
-/// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y = Vec::<f32>::from_buffer(&mut buffer);
+/// assert_eq!(vec![b'a', b'b', 0, 0], buffer);
+/// assert_eq!("héll", FixedStr::<5>::new("héllo").as_str());
+/// assert_eq!(None, FixedStr::<5>::try_new("héllo"));
+/// let mut buffer = ReadBuffer::from_raw(vec![b'o', b'k', 0, b'?']);
+/// assert_eq!(Some(FixedStr::new("ok")), FixedStr::<4>::from_buffer(&mut buffer));
 /// ```
-impl<T: Bufferable + Clone> Bufferable for Vec<T>{
-    fn into_buffer(self, buf: &mut Buffer){
-        let len = self.len() as u64;
-        len.into_buffer(buf);
-        for x in self{
-            x.into_buffer(buf);
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedStr<const N: usize>(String);
+
+impl<const N: usize> FixedStr<N>{
+    /// Take string, cut off at the first 0 byte or the last char that fits in N bytes.
+    pub fn new(string: &str) -> Self{
+        let string = string.split('\0').next().unwrap_or("");
+        let mut end = string.len().min(N);
+        while !string.is_char_boundary(end){
+            end -= 1;
+        }
+        Self(string[..end].to_owned())
+    }
+    /// Take string only if it fits in N bytes and has no 0 bytes.
+    pub fn try_new(string: &str) -> Option<Self>{
+        if string.len() > N || string.contains('\0'){
+            return Option::None;
         }
+        Option::Some(Self(string.to_owned()))
+    }
+    /// The string, without padding.
+    pub fn as_str(&self) -> &str{
+        &self.0
     }
+}
 
-    fn copy_into_buffer(&self, buf: &mut Buffer){
-        self.clone().into_buffer(buf);
+impl<const N: usize> std::ops::Deref for FixedStr<N>{
+    type Target = str;
+    fn deref(&self) -> &str{
+        &self.0
     }
+}
 
-    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        let len = (u64::from_buffer(buf))?;
-        let mut vec = Vec::new();
-        for _ in 0..len{
-            let x = (T::from_buffer(buf))?;
-            vec.push(x);
+impl<const N: usize> From<FixedStr<N>> for String{
+    fn from(string: FixedStr<N>) -> Self{
+        string.0
+    }
+}
+
+impl<const N: usize> Bufferable for FixedStr<N>{
+    const MIN_BUFFER_LEN: usize = N;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        vec.write_bytes(self.0.as_bytes());
+        let zeros = [0u8; 64];
+        let mut padding = N - self.0.len();
+        while padding > 0{
+            let n = padding.min(zeros.len());
+            vec.write_bytes(&zeros[..n]);
+            padding -= n;
         }
-        Option::Some(vec)
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let bytes = buf.read_bytes(N)?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(N);
+        let string = std::str::from_utf8(&bytes[..end]).ok()?.to_owned();
+        buf.use_bytes(string.len())?;
+        Option::Some(Self(string))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        let bytes = buf.check_bytes(N)?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(N);
+        std::str::from_utf8(&bytes[..end]).map_err(|_| BufferError::InvalidUtf8)?;
+        buf.check_use_bytes(end)
+    }
+
+    fn buffer_len(&self) -> usize{
+        N
     }
 }
-/// Implements Bufferable for tuples where all U,V are Bufferable and Clone.
+/// Make a f64 canonical: every NaN becomes the same quiet NaN and -0.0 becomes 0.0.
+/// Floats that compare equal then always have the same bytes.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = (0.0f64,-12345.4321f64);
-/// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
-/// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y =  <(f64,f64)>::from_buffer(&mut buffer);
+/// assert_eq!(0.0f64.to_bits(), canonical_f64(-0.0).to_bits());
+/// assert_eq!(f64::NAN.to_bits(), canonical_f64(-f64::NAN).to_bits());
 /// ```
-impl<U: Bufferable + Clone, V: Bufferable + Clone> Bufferable for (U,V){
-    fn into_buffer(self, buf: &mut Buffer){
-        self.0.into_buffer(buf);
-        self.1.into_buffer(buf);
+pub fn canonical_f64(x: f64) -> f64{
+    if x.is_nan(){
+        f64::NAN
+    } else if x == 0.0{
+        0.0
+    } else {
+        x
     }
-
-    fn copy_into_buffer(&self, buf: &mut Buffer){
-        self.clone().into_buffer(buf);
-    }
-
-    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        let x = (U::from_buffer(buf))?;
-        let y = (V::from_buffer(buf))?;
-        Option::Some((x,y))
+}
+/// Make a f32 canonical: every NaN becomes the same quiet NaN and -0.0 becomes 0.0.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// assert_eq!(0.0f32.to_bits(), canonical_f32(-0.0).to_bits());
+/// ```
+pub fn canonical_f32(x: f32) -> f32{
+    if x.is_nan(){
+        f32::NAN
+    } else if x == 0.0{
+        0.0
+    } else {
+        x
     }
 }
-/// Implements Bufferable for tuples (U,V,W) where all U,V,W are Bufferable and Clone.
+/// A f64 that is made canonical when encoded, see canonical_f64.
+/// It has the same layout as f64, so it can read buffers written with plain f64's,
+/// and buffers with equal values are equal byte for byte, good for hashing or cache keys.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = (0.0f64,-12345.4321f64,9999.0f64);
-/// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
-/// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y= <(f64,f64,f64)>::from_buffer(&mut buffer);
+/// let mut a = Vec::new();
+/// let mut b = Vec::new();
+/// vec![CanonicalF64(0.0), CanonicalF64(f64::NAN)].into_buffer(&mut a);
+/// vec![CanonicalF64(-0.0), CanonicalF64(-f64::NAN)].into_buffer(&mut b);
+/// assert_eq!(a, b);
 /// ```
-impl<U: Bufferable + Clone, V: Bufferable + Clone, W: Bufferable + Clone>
-    Bufferable for (U,V,W){
-    fn into_buffer(self, buf: &mut Buffer){
-        self.0.into_buffer(buf);
-        self.1.into_buffer(buf);
-        self.2.into_buffer(buf);
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct CanonicalF64(pub f64);
+
+impl Bufferable for CanonicalF64{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        canonical_f64(self.0).into_buffer(vec);
     }
 
-    fn copy_into_buffer(&self, buf: &mut Buffer){
-        self.clone().into_buffer(buf);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
     }
 
+    #[inline]
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        let x = (U::from_buffer(buf))?;
-        let y = (V::from_buffer(buf))?;
-        let z = (W::from_buffer(buf))?;
-        Option::Some((x,y,z))
+        Option::Some(Self(f64::from_buffer(buf)?))
+    }
+
+    fn buffer_len(&self) -> usize{
+        8
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 8
     }
 }
-/// Implements Bufferable for tuples (U,V,W,X) where all U,V,W,X are Bufferable and Clone.
+/// A f32 that is made canonical when encoded, see canonical_f32.
 /// # Example
 /// ```
 /// use bin_buffer::*;
-/// let x = (0.064,-12345.4321,9999.0,-999.0);
-/// let mut buffer = Vec::new();
-/// x.into_buffer(&mut buffer);
-/// let mut buffer = ReadBuffer::from_raw(buffer);
-/// let y= <(f64,f64,f64,f64)>::from_buffer(&mut buffer);
+/// let mut a = Vec::new();
+/// let mut b = Vec::new();
+/// CanonicalF32(0.0).into_buffer(&mut a);
+/// CanonicalF32(-0.0).into_buffer(&mut b);
+/// assert_eq!(a, b);
 /// ```
-impl<U: Bufferable + Clone, V: Bufferable + Clone, W: Bufferable + Clone, X: Bufferable + Clone>
-    Bufferable for (U,V,W,X){
-    fn into_buffer(self, buf: &mut Buffer){
-        self.0.into_buffer(buf);
-        self.1.into_buffer(buf);
-        self.2.into_buffer(buf);
-        self.3.into_buffer(buf);
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct CanonicalF32(pub f32);
+
+impl Bufferable for CanonicalF32{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        canonical_f32(self.0).into_buffer(vec);
     }
 
-    fn copy_into_buffer(&self, buf: &mut Buffer){
-        self.clone().into_buffer(buf);
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (*self).into_buffer(vec);
     }
 
+    #[inline]
     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
-        let x = (U::from_buffer(buf))?;
-        let y = (V::from_buffer(buf))?;
-        let z = (W::from_buffer(buf))?;
-        let w = (X::from_buffer(buf))?;
-        Option::Some((x,y,z,w))
+        Option::Some(Self(f32::from_buffer(buf)?))
+    }
+
+    fn buffer_len(&self) -> usize{
+        4
+    }
+
+    fn slice_buffer_len(slice: &[Self]) -> usize{
+        slice.len() * 4
     }
 }
+// Implements Bufferable for a tuple, its fields one after the other.
+// Tuples longer than 12 are better off as a struct, see bufferable_struct and derive.
+macro_rules! tuple_impl{
+    ($(#[$attr:meta])* $($t:ident $i:tt),+) => {
+        $(#[$attr])*
+        impl<$($t: Bufferable),+> Bufferable for ($($t,)+){
+            const MIN_BUFFER_LEN: usize = 0 $( + $t::MIN_BUFFER_LEN )+;
+
+            fn into_buffer<S: Sink + ?Sized>(self, buf: &mut S){
+                buf.reserve(self.buffer_len());
+                $( self.$i.into_buffer(buf); )+
+            }
+
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, buf: &mut S){
+                buf.reserve(self.buffer_len());
+                $( self.$i.copy_into_buffer(buf); )+
+            }
+
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(($( $t::from_buffer(buf)?, )+))
+            }
+
+            fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+                $( $t::validate(buf)?; )+
+                Ok(())
+            }
+
+            fn read_from_buffer(&mut self, buf: &mut ReadBuffer) -> Option<()>{
+                $( self.$i.read_from_buffer(buf)?; )+
+                Option::Some(())
+            }
+
+            fn buffer_len(&self) -> usize{
+                0 $( + self.$i.buffer_len() )+
+            }
+
+            #[inline]
+            unsafe fn from_buffer_unchecked(buf: &mut ReadBuffer) -> Self{
+                ($( $t::from_buffer_unchecked(buf), )+)
+            }
+        }
+    };
+}
+
+tuple_impl!(
+    /// Implements Bufferable for tuples of 2 up to 12 Bufferable types.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let x = (0.0f64,-12345.4321f64);
+    /// let mut buffer = Vec::new();
+    /// x.into_buffer(&mut buffer);
+    /// let mut buffer = ReadBuffer::from_raw(buffer);
+    /// let y =  <(f64,f64)>::from_buffer(&mut buffer);
+    /// let x = (0.0f64,-12345.4321f64,9999.0f64,-999.0f64);
+    /// assert_eq!(Some(x), decode::<(f64,f64,f64,f64)>(&encode(&x)));
+    /// let record = (1u32, 2u32, 0.5f32, 1.5f32, String::from("name"));
+    /// assert_eq!(Some(record.clone()), decode(&encode(&record)));
+    /// ```
+    A 0, B 1);
+tuple_impl!(A 0, B 1, C 2);
+tuple_impl!(A 0, B 1, C 2, D 3);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
 
 #[cfg(test)]
 mod tests{
@@ -609,6 +3710,127 @@ mod tests{
         assert_eq!(Option::None, String::from_buffer(&mut buffer));
     }
     #[test]
+    fn test_string_invalid(){
+        let mut buffer = Vec::new();
+        vec![0xc3u8,0x28].into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(None, String::from_buffer(&mut buffer));
+        let mut buffer = ReadBuffer::from_raw(vec![255;12]);
+        assert_eq!(None, String::from_buffer(&mut buffer));
+    }
+    #[test]
+    fn test_read_str(){
+        let mut buffer = Vec::new();
+        String::from("one").into_buffer(&mut buffer);
+        String::from("").into_buffer(&mut buffer);
+        ByteVec(vec![0xff,0xfe]).into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some("one"), buffer.read_str());
+        assert_eq!(Some(""), buffer.read_str());
+        assert_eq!(None, buffer.read_str());
+        assert_eq!(None, buffer.read_str());
+    }
+    #[test]
+    fn test_read_bytes(){
+        let mut buffer = Vec::new();
+        1u8.into_buffer(&mut buffer);
+        vec![4u8,5,6].into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(&[1u8][..]), buffer.read_bytes(1));
+        assert_eq!(Some(&[4u8,5,6][..]), buffer.read_blob());
+        assert_eq!(Some(&[][..]), buffer.read_bytes(0));
+        assert_eq!(None, buffer.read_bytes(1));
+        let mut buffer = ReadBuffer::from_raw(vec![255;8]);
+        assert_eq!(None, buffer.read_blob());
+    }
+    #[test]
+    fn test_unchecked(){
+        let records = vec![(1u32,0.5f32,7u16,1usize),(2,-1.0,8,2),(3,9.5,9,3)];
+        let mut buffer = Vec::new();
+        for r in &records{
+            r.copy_into_buffer(&mut buffer);
+        }
+        String::from("end").into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert!(buffer.remaining() >= records.len() * 18);
+        for r in &records{
+            let x = unsafe{ <(u32,f32,u16,usize)>::from_buffer_unchecked(&mut buffer) };
+            assert_eq!(*r, x);
+        }
+        assert_eq!(String::from("end"), unsafe{ String::from_buffer_unchecked(&mut buffer) });
+        assert_eq!(0, buffer.remaining());
+    }
+    #[test]
+    fn test_vec_bogus_len(){
+        let mut buffer = Vec::new();
+        u64::MAX.into_buffer(&mut buffer);
+        String::from("x").into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(None, Vec::<String>::from_buffer(&mut buffer));
+        let mut buffer = Vec::new();
+        (1u64 << 40).into_buffer(&mut buffer);
+        (1u32,2u64).into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(None, Vec::<Vec<(u32,u64)>>::from_buffer(&mut buffer));
+    }
+    fn decode_all<T: Bufferable>(data: &[u8]){
+        let mut buffer = ReadBuffer::from_raw(data.to_vec());
+        while T::from_buffer(&mut buffer).is_some() {}
+    }
+    #[test]
+    fn test_arbitrary_input(){
+        // Same idea as the targets in fuzz/, with cheap pseudo random input.
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = ||{
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut valid = Vec::new();
+        (String::from("valid"),vec![vec![1u16,2],vec![]],vec![String::from("x")]).into_buffer(&mut valid);
+        for round in 0..2000{
+            let mut data = if round % 2 == 0 { valid.clone() } else { Vec::new() };
+            for _ in 0..(next() % 64){
+                data.push(next() as u8);
+            }
+            if !data.is_empty(){
+                let i = next() as usize % data.len();
+                data[i] = next() as u8;
+            }
+            decode_all::<u64>(&data);
+            decode_all::<usize>(&data);
+            decode_all::<f32>(&data);
+            decode_all::<String>(&data);
+            decode_all::<ByteVec>(&data);
+            decode_all::<Vec<u32>>(&data);
+            decode_all::<Vec<String>>(&data);
+            decode_all::<Vec<Vec<u16>>>(&data);
+            decode_all::<(String,Vec<Vec<u16>>,Vec<String>)>(&data);
+            decode_all::<(u8,f64,Vec<u8>,String)>(&data);
+        }
+    }
+    #[test]
+    fn test_read_seq(){
+        let mut buffer = Vec::new();
+        vec![String::from("a"),String::from("b"),String::from("c")].into_buffer(&mut buffer);
+        9u8.into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        let mut seq = buffer.read_seq::<String>().unwrap();
+        assert_eq!(3, seq.left());
+        assert_eq!(Some(Some(String::from("a"))), seq.next());
+        assert_eq!(2, seq.left());
+        let rest: Option<Vec<String>> = seq.collect();
+        assert_eq!(Some(vec![String::from("b"),String::from("c")]), rest);
+        assert_eq!(Some(9), u8::from_buffer(&mut buffer));
+        let mut buffer = Vec::new();
+        vec![1u16,2].into_buffer(&mut buffer);
+        buffer.pop();
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        let items: Vec<Option<u16>> = buffer.read_seq::<u16>().unwrap().collect();
+        assert_eq!(vec![Some(1),None], items);
+    }
+    #[test]
     fn test_f64_tuple(){
         let x = (0.0f64,-12345.4321f64);
         let mut buffer = Vec::new();
@@ -635,6 +3857,21 @@ mod tests{
         assert_eq!(None, u8::from_buffer(&mut buffer));
     }
     #[test]
+    fn test_long_tuples(){
+        let record = (1u32, 2u32, 0.5f32, 1.5f32, String::from("name"));
+        let bytes = encode(&record);
+        assert_eq!(4 + 4 + 4 + 4 + 12, bytes.len());
+        assert_eq!(Some(record.clone()), decode(&bytes));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 8, available: 4 }), validate::<(u32, u32, f32, f32, String)>(&mut ReadBuffer::from_slice(&bytes[..20])));
+        let x = (1u8, 2u16, 3u32, 4u64, 5i8, 6i16, 7i32, 8i64, true, 'a', String::from("b"), vec![9u8]);
+        assert_eq!(1 + 2 + 4 + 8 + 1 + 2 + 4 + 8 + 1 + 4 + 9 + 9, encoded_size_of(&x));
+        assert_eq!(1 + 2 + 4 + 8 + 1 + 2 + 4 + 8 + 1 + 4 + 8 + 8, <(u8, u16, u32, u64, i8, i16, i32, i64, bool, char, String, Vec<u8>)>::MIN_BUFFER_LEN);
+        let mut y = Default::default();
+        let mut buffer = ReadBuffer::from_raw(encode(&x));
+        assert_eq!(Some(()), Bufferable::read_from_buffer(&mut y, &mut buffer));
+        assert_eq!(x, y);
+    }
+    #[test]
     fn test_vec(){
         let x = vec![0.0f32,1.0,2.0,3.0,4.0,5.5];
         let mut buffer = Vec::new();
@@ -643,5 +3880,784 @@ mod tests{
         assert_eq!(Some(x), Vec::<f32>::from_buffer(&mut buffer));
         assert_eq!(None, u8::from_buffer(&mut buffer));
     }
-}
+    #[test]
+    fn test_max_depth(){
+        // a recursive type, like a derived tree would be
+        #[derive(Debug, PartialEq)]
+        struct Tree(Vec<Tree>);
+        impl Bufferable for Tree{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                self.0.copy_into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Tree(Vec::from_buffer(buf)?))
+            }
+        }
+        // 100000 nested Vecs each holding one element: 1u64 repeated, then a 0u64
+        let mut buffer = Vec::new();
+        for _ in 0..100000 { 1u64.into_buffer(&mut buffer); }
+        0u64.into_buffer(&mut buffer);
+        let mut read = ReadBuffer::from_raw(buffer);
+        assert_eq!(DEFAULT_MAX_DEPTH, read.max_depth());
+        assert_eq!(None, Tree::from_buffer(&mut read));
+        let mut small = Vec::new();
+        Tree(vec![Tree(vec![Tree(vec![])]), Tree(vec![])]).into_buffer(&mut small);
+        let mut small = ReadBuffer::from_raw(small).with_max_depth(3);
+        assert_eq!(Some(Tree(vec![Tree(vec![Tree(vec![])]), Tree(vec![])])), Tree::from_buffer(&mut small));
+    }
+
+    #[test]
+    fn test_decode_limits(){
+        let mut buffer = Vec::new();
+        vec![String::from("short"), String::from("a bit longer")].into_buffer(&mut buffer);
+        let read = |limits| Vec::<String>::from_buffer(&mut ReadBuffer::from_raw(buffer.clone()).with_limits(limits));
+        let default = DecodeLimits::default();
+        assert!(read(default).is_some());
+        assert!(read(DecodeLimits{ max_elements: 2, ..default }).is_some());
+        assert!(read(DecodeLimits{ max_elements: 1, ..default }).is_none());
+        assert!(read(DecodeLimits{ max_string_len: 12, ..default }).is_some());
+        assert!(read(DecodeLimits{ max_string_len: 11, ..default }).is_none());
+        let total = 2 * std::mem::size_of::<String>() + 5 + 12;
+        assert!(read(DecodeLimits{ max_total_bytes: total, ..default }).is_some());
+        assert!(read(DecodeLimits{ max_total_bytes: total - 1, ..default }).is_none());
+        let mut buffer = ReadBuffer::from_raw(buffer.clone());
+        Vec::<String>::from_buffer(&mut buffer).unwrap();
+        assert_eq!(total, buffer.decoded_bytes());
+        // a huge length never gets to allocate
+        let mut buffer = Vec::new();
+        (u64::MAX / 2).into_buffer(&mut buffer);
+        let limits = DecodeLimits{ max_total_bytes: 1 << 20, ..default };
+        let mut buffer = ReadBuffer::from_raw(buffer).with_limits(limits);
+        assert_eq!(None, Vec::<u64>::from_buffer(&mut buffer));
+    }
+
+    #[test]
+    fn test_read_or_default(){
+        #[derive(Debug, PartialEq)]
+        struct Settings{ name: String, volume: u8, tags: Vec<String> }
+        impl Bufferable for Settings{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                self.name.copy_into_buffer(vec);
+                self.volume.copy_into_buffer(vec);
+                self.tags.copy_into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Settings{
+                    name: String::from_buffer(buf)?,
+                    volume: buf.read_or_default()?,
+                    tags: buf.read_or_default()?,
+                })
+            }
+        }
+        let mut old = Vec::new();
+        String::from("a").into_buffer(&mut old);
+        let mut middle = old.clone();
+        7u8.into_buffer(&mut middle);
+        let new = Settings{ name: String::from("a"), volume: 7, tags: vec![String::from("b")] };
+        let read = |vec: Buffer| Settings::from_buffer(&mut ReadBuffer::from_raw(vec)).unwrap();
+        assert_eq!(Settings{ name: String::from("a"), volume: 0, tags: vec![] }, read(old));
+        assert_eq!(Settings{ name: String::from("a"), volume: 7, tags: vec![] }, read(middle.clone()));
+        let mut buffer = Vec::new();
+        new.copy_into_buffer(&mut buffer);
+        assert_eq!(new, read(buffer));
+        // a field that is there but cut short is still an error
+        middle.push(0);
+        assert_eq!(None, Settings::from_buffer(&mut ReadBuffer::from_raw(middle)));
+    }
 
+    #[test]
+    fn test_hash_map(){
+        use std::collections::{ HashMap, HashSet };
+        let words = ["zero", "one", "two", "three", "four", "five", "six", "seven"];
+        let a: HashMap<String,u32> = words.iter().enumerate().map(|(i, w)| (w.to_string(), i as u32)).collect();
+        let b: HashMap<String,u32> = words.iter().enumerate().rev().map(|(i, w)| (w.to_string(), i as u32)).collect();
+        let mut buf_a = Vec::new();
+        let mut buf_b = Vec::new();
+        a.copy_into_buffer(&mut buf_a);
+        b.copy_into_buffer(&mut buf_b);
+        assert_eq!(buf_a, buf_b);
+        assert_eq!(a.buffer_len(), buf_a.len());
+        let mut buffer = ReadBuffer::from_raw(buf_a);
+        assert_eq!(Some(a), HashMap::from_buffer(&mut buffer));
+
+        let set: HashSet<u64> = (0..100).rev().collect();
+        let mut buffer = Vec::new();
+        set.copy_into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some((0..100).collect::<Vec<u64>>()), Vec::from_buffer(&mut buffer));
+
+        let mut buffer = Vec::new();
+        vec![(1u8,2u8),(1,3)].into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(None, HashMap::<u8,u8>::from_buffer(&mut buffer));
+    }
+
+    #[test]
+    fn test_lossy_string(){
+        let mut buffer = Vec::new();
+        String::from("fine").into_buffer(&mut buffer);
+        ByteVec(vec![0xc3, b'x', 0xe2, 0x82]).into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(LossyString::from(String::from("fine"))), LossyString::from_buffer(&mut buffer));
+        assert_eq!("\u{FFFD}x\u{FFFD}", LossyString::from_buffer(&mut buffer).unwrap().as_str());
+        assert_eq!(None, LossyString::from_buffer(&mut buffer));
+    }
+
+    #[test]
+    fn test_canonical_floats(){
+        let weird_nan = f64::from_bits(0x7ff8_dead_beef_0001);
+        assert!(weird_nan.is_nan());
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        (CanonicalF64(weird_nan), CanonicalF64(-0.0), CanonicalF32(-f32::NAN)).into_buffer(&mut a);
+        (CanonicalF64(f64::NAN), CanonicalF64(0.0), CanonicalF32(f32::NAN)).into_buffer(&mut b);
+        assert_eq!(a, b);
+        let mut buffer = ReadBuffer::from_raw(a);
+        let (x, y, z) = <(f64,f64,f32)>::from_buffer(&mut buffer).unwrap();
+        assert_eq!(f64::NAN.to_bits(), x.to_bits());
+        assert_eq!(0.0f64.to_bits(), y.to_bits());
+        assert_eq!(f32::NAN.to_bits(), z.to_bits());
+        assert_eq!(1.5, canonical_f64(1.5));
+        assert_eq!(f32::NEG_INFINITY, canonical_f32(f32::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_vec_bulk(){
+        let a = vec![0u16,1,u16::MAX];
+        let b = vec![0u32,7,u32::MAX];
+        let c = vec![0u64,1 << 40,u64::MAX];
+        let d = vec![0usize,123456];
+        let e = vec![-1.5f64,0.0,f64::MAX];
+        let mut buffer = Vec::new();
+        a.copy_into_buffer(&mut buffer);
+        b.copy_into_buffer(&mut buffer);
+        c.copy_into_buffer(&mut buffer);
+        d.copy_into_buffer(&mut buffer);
+        e.copy_into_buffer(&mut buffer);
+        assert_eq!(buffer.len(), 5 * 8 + 3 * 2 + 3 * 4 + 3 * 8 + 2 * 8 + 3 * 8);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(a), Vec::<u16>::from_buffer(&mut buffer));
+        assert_eq!(Some(b), Vec::<u32>::from_buffer(&mut buffer));
+        assert_eq!(Some(c), Vec::<u64>::from_buffer(&mut buffer));
+        assert_eq!(Some(d), Vec::<usize>::from_buffer(&mut buffer));
+        assert_eq!(Some(e), Vec::<f64>::from_buffer(&mut buffer));
+        assert_eq!(None, u8::from_buffer(&mut buffer));
+        let mut buffer = Vec::new();
+        vec![1u32,2,3].copy_into_buffer(&mut buffer);
+        buffer.pop();
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(None, Vec::<u32>::from_buffer(&mut buffer));
+    }
+    #[derive(Debug, PartialEq)]
+    struct NoClone(u32);
+    impl Bufferable for NoClone{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+            self.0.into_buffer(vec);
+        }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+            self.0.copy_into_buffer(vec);
+        }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+            Option::Some(NoClone(u32::from_buffer(buf)?))
+        }
+    }
+    #[test]
+    fn test_no_clone(){
+        let x = vec![NoClone(1),NoClone(2)];
+        let y = (NoClone(3),vec![NoClone(4)],NoClone(5),NoClone(6));
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        y.copy_into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(x), Vec::<NoClone>::from_buffer(&mut buffer));
+        assert_eq!(Some(y), <(NoClone,Vec<NoClone>,NoClone,NoClone)>::from_buffer(&mut buffer));
+    }
+    #[test]
+    fn test_buffer_len(){
+        let x = (vec![String::from("abc"),String::new()],1u8,vec![1.0f32,2.0],ByteVec(vec![1,2]));
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        assert_eq!(buffer.len(), x.buffer_len());
+        assert!(buffer.capacity() >= x.buffer_len());
+        assert_eq!(0, NoClone(1).buffer_len());
+        assert_eq!(8, vec![NoClone(1)].buffer_len());
+    }
+    struct CountSink(usize);
+    impl Sink for CountSink{
+        fn write_bytes(&mut self, bytes: &[u8]){
+            self.0 += bytes.len();
+        }
+    }
+    #[test]
+    fn test_sink(){
+        let x = (vec![1u16; 1000],vec![2.0f64; 100],String::from("sink"),vec![3usize; 70]);
+        let mut counter = CountSink(0);
+        x.copy_into_buffer(&mut counter);
+        assert_eq!(x.buffer_len(), counter.0);
+        let dynamic: &mut dyn Sink = &mut counter;
+        7u8.into_buffer(dynamic);
+        assert_eq!(x.buffer_len() + 1, counter.0);
+    }
+    #[test]
+    fn test_byte_vec(){
+        let x = vec![0u8,1,2,3,4,255];
+        let mut buffer = Vec::new();
+        ByteVec(x.clone()).into_buffer(&mut buffer);
+        x.copy_into_buffer(&mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(x.clone()), Vec::<u8>::from_buffer(&mut buffer));
+        assert_eq!(Some(ByteVec(x)), ByteVec::from_buffer(&mut buffer));
+        assert_eq!(None, ByteVec::from_buffer(&mut buffer));
+    }
+    #[test]
+    fn test_encode_decode(){
+        let x = (vec![String::from("a")], 2u64, ByteVec(vec![3]));
+        let bytes = encode(&x);
+        assert_eq!(x.buffer_len(), bytes.len());
+        assert_eq!(Some(x.clone()), decode(&bytes));
+        assert_eq!(bytes, x.to_bytes());
+        assert_eq!(Some(x), <(Vec<String>,u64,ByteVec)>::from_bytes(&bytes));
+        assert_eq!(None, decode::<(Vec<String>,u64,ByteVec)>(&bytes[1..]));
+        assert_eq!(None, u32::from_bytes(&[0, 0, 0, 1, 0]));
+        let buffer: ReadBuffer = bytes.clone().into();
+        assert_eq!(bytes, Buffer::from(buffer));
+    }
+    #[test]
+    fn test_to_buffer_exact(){
+        let mut map = HashMap::new();
+        map.insert(String::from("k"), vec![Some(1u16), None]);
+        let deque: VecDeque<i32> = (0..5).collect();
+        let x = (map, deque, [Ok::<u8, String>(1), Err(String::from("e"))], 'c', true, -1isize);
+        let bytes = x.to_buffer_exact();
+        assert_eq!(encode(&x), bytes);
+        assert_eq!(bytes.len(), bytes.capacity());
+        // the built in types know their size without walking the value twice
+        assert_eq!(encoded_size_of(&x), x.buffer_len());
+        let nested = vec![vec![String::from("ab")], Vec::new()];
+        assert_eq!(encoded_size_of(&nested), nested.buffer_len());
+        assert_eq!(nested.to_buffer_exact().capacity(), encode(&nested).len());
+    }
+    #[test]
+    fn test_read_buffer_debug(){
+        let mut buffer = ReadBuffer::from_raw((0..40).collect());
+        assert_eq!(0, buffer.consumed());
+        u64::from_buffer(&mut buffer);
+        assert_eq!(8, buffer.consumed());
+        assert_eq!(40, buffer.as_slice().len());
+        assert_eq!("ReadBuffer { len: 40, pos: 8, next: [08 09 0a 0b 0c 0d 0e 0f 10 11 12 13 14 15 16 17 ..] }",
+            format!("{:?}", buffer));
+        buffer.read_bytes(32);
+        assert_eq!("ReadBuffer { len: 40, pos: 40, next: [] }", format!("{:?}", buffer));
+    }
+    #[test]
+    fn test_fixed_str(){
+        let xs = [FixedStr::<8>::new("name"), FixedStr::new(""), FixedStr::new("12345678"), FixedStr::new("123456789")];
+        assert_eq!("12345678", xs[3].as_str());
+        assert_eq!("a", &*FixedStr::<8>::new("a\0b"));
+        assert_eq!("", FixedStr::<1>::new("é").as_str());
+        assert_eq!(None, FixedStr::<8>::try_new("a\0b"));
+        let mut buffer = Vec::new();
+        for x in xs.iter(){
+            x.copy_into_buffer(&mut buffer);
+        }
+        assert_eq!(32, buffer.len());
+        assert_eq!(&[b'n', b'a', b'm', b'e', 0, 0, 0, 0], &buffer[..8]);
+        let mut read = ReadBuffer::from_raw(buffer);
+        for x in xs.iter(){
+            assert_eq!(Some(x.clone()), FixedStr::<8>::from_buffer(&mut read));
+        }
+        assert_eq!(None, FixedStr::<8>::from_buffer(&mut read));
+        assert_eq!(None, FixedStr::<2>::from_buffer(&mut ReadBuffer::from_raw(vec![0xff, 0])));
+        // longer than the stack block used for padding
+        let mut buffer = Vec::new();
+        FixedStr::<200>::new("x").into_buffer(&mut buffer);
+        assert_eq!(200, buffer.len());
+    }
+    #[test]
+    fn test_cstring(){
+        use std::ffi::CString;
+        let xs = vec![CString::new("a").unwrap(), CString::new("").unwrap(), CString::new(vec![0xffu8, 1]).unwrap()];
+        let mut buffer = Vec::new();
+        xs.copy_into_buffer(&mut buffer);
+        assert_eq!(xs.buffer_len(), buffer.len());
+        assert_eq!(&[b'a', 0, 0, 0xff, 1, 0], &buffer[8..]);
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!(Some(xs), Vec::<CString>::from_buffer(&mut read));
+        // no terminator
+        assert_eq!(None, CString::from_buffer(&mut ReadBuffer::from_raw(vec![b'a', b'b'])));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 3, available: 2 }), validate::<CString>(&mut ReadBuffer::from_raw(vec![b'a', b'b'])));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 1, available: 0 }), validate::<CString>(&mut ReadBuffer::from_raw(Vec::new())));
+        let limits = DecodeLimits{ max_string_len: 1, ..DecodeLimits::default() };
+        let mut read = ReadBuffer::from_raw(b"ab\0".to_vec()).with_limits(limits);
+        assert_eq!(None, CString::from_buffer(&mut read));
+        assert_eq!(0, read.consumed());
+        assert_eq!(Err(BufferError::LengthOverflow), validate::<CString>(&mut ReadBuffer::from_raw(b"ab\0".to_vec()).with_limits(limits)));
+        assert_eq!(Ok(()), validate::<CString>(&mut ReadBuffer::from_raw(b"ab\0".to_vec())));
+    }
+    #[test]
+    fn test_wide_string(){
+        let x = WideString(String::from("a😀"));
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        assert_eq!(x.buffer_len(), buffer.len());
+        // a surrogate pair for the emoji
+        assert_eq!(vec![0,0,0,0,0,0,0,3, b'a',0, 0x3d,0xd8, 0x00,0xde], buffer);
+        let mut read = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(x), WideString::from_buffer(&mut read));
+        // unpaired surrogate, odd bytes
+        assert_eq!(None, WideString::from_buffer(&mut ReadBuffer::from_raw(vec![0,0,0,0,0,0,0,1, 0x3d,0xd8])));
+        assert_eq!(None, WideString::from_buffer(&mut ReadBuffer::from_raw(vec![0,0,0,0,0,0,0,1, b'a'])));
+        let limits = DecodeLimits{ max_string_len: 2, ..DecodeLimits::default() };
+        let mut buffer = Vec::new();
+        WideString(String::from("abc")).into_buffer(&mut buffer);
+        assert_eq!(None, WideString::from_buffer(&mut ReadBuffer::from_raw(buffer).with_limits(limits)));
+    }
+    #[test]
+    fn test_fork(){
+        // try a message as (u8,u8) first, then as String
+        let mut buffer = Vec::new();
+        String::from("message").into_buffer(&mut buffer);
+        7u16.into_buffer(&mut buffer);
+        let limits = DecodeLimits{ max_total_bytes: 100, ..DecodeLimits::default() };
+        let mut read = ReadBuffer::from_raw(buffer).with_limits(limits);
+        {
+            let mut fork = read.fork();
+            assert_eq!(Some(0), u8::from_buffer(&mut fork));
+            assert_eq!(1, fork.consumed());
+            assert_eq!(100, fork.limits().max_total_bytes);
+            let mut fork2 = fork.fork();
+            assert_eq!(Some(vec![0u8; 6]), fork2.read_bytes(6).map(|b| b.to_vec()));
+            assert_eq!(1, fork.consumed());
+        }
+        assert_eq!(0, read.consumed());
+        assert_eq!(None, read.speculate(|b| Some((u8::from_buffer(b)?, u64::from_buffer(b)?)).filter(|x| x.0 != 0)));
+        assert_eq!(Some(String::from("message")), read.speculate(String::from_buffer));
+        assert_eq!(15, read.consumed());
+        assert_eq!(7, read.decoded_bytes());
+        assert_eq!(vec![0, 7], read.fork().into_raw()[15..].to_vec());
+        assert_eq!(Some(7), u16::from_buffer(&mut read));
+    }
+
+    #[test]
+    fn test_peek_seek(){
+        // records of a tag byte and a length, tag 1 holds a u32, others are skipped
+        let mut buffer = Vec::new();
+        for (tag, body) in [(1u8, vec![0, 0, 0, 5]), (9, vec![1, 2, 3]), (1, vec![0, 0, 1, 0])]{
+            tag.into_buffer(&mut buffer);
+            (body.len() as u8).into_buffer(&mut buffer);
+            buffer.extend_from_slice(&body);
+        }
+        let mut read = ReadBuffer::from_raw(buffer);
+        let mut found = Vec::new();
+        while read.remaining() > 0{
+            if read.peek::<u8>() == Some(1){
+                assert_eq!(Some((1u8, 4u8)), Bufferable::from_buffer(&mut read));
+                found.push(u32::from_buffer(&mut read).unwrap());
+            } else {
+                let start = read.position();
+                let len = read.peek::<(u8, u8)>().unwrap().1 as usize;
+                assert_eq!(Ok(()), read.skip(2 + len));
+                assert_eq!(start + 5, read.position());
+            }
+        }
+        assert_eq!(vec![5, 256], found);
+        assert_eq!(None, read.peek::<u8>());
+        assert_eq!(Ok(()), read.set_position(6));
+        assert_eq!(Some(9u8), read.peek());
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 12, available: 11 }), read.set_position(18));
+        assert_eq!(6, read.position());
+    }
+
+    #[test]
+    fn test_io(){
+        use std::io::{ BufRead, Read, Seek, SeekFrom };
+        let mut buffer = Vec::new();
+        7u32.into_buffer(&mut buffer);
+        buffer.extend_from_slice(b"line one\nline two\n");
+        let mut read = ReadBuffer::from_raw(buffer);
+        assert_eq!(Some(7), u32::from_buffer(&mut read));
+        let mut line = String::new();
+        read.read_line(&mut line).unwrap();
+        assert_eq!("line one\n", line);
+        let mut four = [0; 4];
+        read.read_exact(&mut four).unwrap();
+        assert_eq!(b"line", &four);
+        assert_eq!(Ok(4), read.seek(SeekFrom::Start(4)).map_err(|_| ()));
+        assert_eq!(Ok(4), read.stream_position().map_err(|_| ()));
+        assert!(read.seek(SeekFrom::Current(-5)).is_err());
+        assert!(read.seek(SeekFrom::Start(u64::MAX)).is_err());
+        assert_eq!(4, read.consumed());
+        read.consume(100);
+        assert_eq!(0, read.remaining());
+        assert_eq!(0, read.read(&mut four).unwrap());
+    }
+
+    #[test]
+    fn test_merge_encoded_vecs(){
+        let a = encode(&vec![1u16, 2]);
+        let b = encode(&Vec::<u16>::new());
+        let c = encode(&vec![3u16]);
+        assert_eq!(encode(&vec![1u16, 2, 3]), merge_many_encoded_vecs(&[&a, &b, &c]).unwrap());
+        assert_eq!(Some(encode(&vec![1u16, 2])), merge_encoded_vecs(&a, &b));
+        assert_eq!(Some(encode(&Vec::<u16>::new())), merge_many_encoded_vecs(&[]));
+        let x = encode(&ByteVec(vec![9, 8]));
+        assert_eq!(Some(vec![9u8, 8, 1, 2]), decode::<Vec<u8>>(&merge_encoded_vecs(&x, &encode(&vec![1u8, 2])).unwrap()));
+        assert_eq!(None, merge_encoded_vecs(&a, &[0, 0, 0]));
+        assert_eq!(None, merge_encoded_vecs(&[0xff; 8], &[0xff; 8]));
+    }
+
+    #[test]
+    fn test_take(){
+        let mut buffer = Vec::new();
+        vec![1u8, 2, 3].into_buffer(&mut buffer);
+        String::from("after").into_buffer(&mut buffer);
+        let mut read = ReadBuffer::from_raw(buffer).with_max_depth(1);
+        {
+            // the Vec claims more elements than the taken bytes hold
+            // the lib imports io::Read when fs is on, so take has to be named in full
+            let mut part = ReadBuffer::take(&mut read, 10).unwrap();
+            assert_eq!(10, part.remaining());
+            assert_eq!(1, part.max_depth());
+            assert_eq!(None, Vec::<u8>::from_buffer(&mut part));
+        }
+        assert_eq!(10, read.consumed());
+        assert!(ReadBuffer::take(&mut read, 100).is_none());
+        assert_eq!(10, read.consumed());
+        read.skip(1).unwrap();
+        let mut rest = read.take_remaining();
+        assert_eq!(Some(String::from("after")), String::from_buffer(&mut rest));
+        assert_eq!(0, read.remaining());
+        assert_eq!(0, read.take_remaining().remaining());
+    }
+
+    #[test]
+    fn test_validate(){
+        type Record = (Vec<(u32, String)>, HashMap<u16, ByteVec>, (WideString, FixedStr<4>, f64, LossyString));
+        let mut map = HashMap::new();
+        map.insert(3u16, ByteVec(vec![1, 2]));
+        let x: Record = (vec![(1, String::from("a")), (2, String::from("bé"))], map,
+            (WideString(String::from("wide")), FixedStr::new("ab"), 0.5, LossyString(String::from("x"))));
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        9u8.into_buffer(&mut buffer);
+        // moves past exactly what decoding reads
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!(Ok(()), validate::<Record>(&mut read));
+        assert_eq!(Some(9), u8::from_buffer(&mut read));
+        // every cut short buffer fails
+        for end in 0..buffer.len() - 1{
+            let err = validate::<Record>(&mut ReadBuffer::from_raw(buffer[..end].to_vec()));
+            assert!(matches!(err, Err(BufferError::UnexpectedEof{ needed, available }) if needed > available), "{:?}", err);
+        }
+        // limits are checked like decoding does
+        let limits = DecodeLimits{ max_elements: 1, ..DecodeLimits::default() };
+        assert_eq!(Err(BufferError::LengthOverflow), validate::<Record>(&mut ReadBuffer::from_raw(buffer.clone()).with_limits(limits)));
+        let mut nested = Vec::new();
+        vec![vec![vec![1u8]]].into_buffer(&mut nested);
+        assert_eq!(Err(BufferError::TooDeep), validate::<Vec<Vec<Vec<u8>>>>(&mut ReadBuffer::from_raw(nested).with_max_depth(2)));
+        // an unpaired surrogate in a WideString
+        let mut wide = Vec::new();
+        1u64.into_buffer(&mut wide);
+        wide.extend_from_slice(&0xd800u16.to_le_bytes());
+        assert_eq!(Err(BufferError::InvalidUtf8), validate::<WideString>(&mut ReadBuffer::from_raw(wide)));
+        // duplicate keys validate, but do not decode
+        let mut dup = Vec::new();
+        vec![(1u8, 1u8), (1, 2)].into_buffer(&mut dup);
+        assert_eq!(Ok(()), validate::<HashMap<u8, u8>>(&mut ReadBuffer::from_raw(dup.clone())));
+        assert_eq!(None, HashMap::<u8, u8>::from_buffer(&mut ReadBuffer::from_raw(dup)));
+        assert_eq!("string is not valid UTF-8", BufferError::InvalidUtf8.to_string());
+        // skip_value moves past the same bytes, or not at all
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!(Ok(()), read.skip_value::<Record>());
+        assert_eq!(buffer.len() - 1, read.consumed());
+        let mut read = ReadBuffer::from_raw(buffer[..20].to_vec());
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 1, available: 0 }), read.skip_value::<Record>());
+        assert_eq!((0, 0), (read.consumed(), read.decoded_bytes()));
+        // read_all stops at a bad record, and at records of no bytes
+        let mut read = ReadBuffer::from_raw(vec![0,0,0,0,0,0,0,1, b'a', 0,0,0,0,0,0,0,1, 0xff]);
+        assert_eq!(Err(BufferError::InvalidUtf8), read.read_all::<String>());
+        assert_eq!(9, read.consumed());
+        assert_eq!(Err(BufferError::Invalid), ReadBuffer::from_raw(vec![1]).read_all::<FixedStr<0>>());
+        assert_eq!(Ok(Vec::new()), ReadBuffer::from_raw(Vec::new()).read_all::<FixedStr<0>>());
+    }
+
+    #[test]
+    fn test_encoded_size_of(){
+        // a type without a buffer_len, nested in ones that have it
+        struct NoHint(String);
+        impl Bufferable for NoHint{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.0.into_buffer(vec); }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ self.0.copy_into_buffer(vec); }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{ Some(NoHint(String::from_buffer(buf)?)) }
+        }
+        let x = vec![(NoHint(String::from("first")), 1u32), (NoHint(String::new()), 2)];
+        assert_eq!(8 + 8 + 5 + 4 + 8 + 4, encoded_size_of(&x));
+        assert_eq!(encode(&x).len(), encoded_size_of(&x));
+        assert_ne!(x.buffer_len(), encoded_size_of(&x));
+        let mut counter = SizeCounter::new();
+        assert!(counter.is_empty());
+        vec![0u64; 1000].copy_into_buffer(&mut counter);
+        assert_eq!(8008, counter.len());
+    }
+
+    #[test]
+    fn test_write_iter(){
+        let expected = encode(&vec![1u16, 3, 5]);
+        // exact size, patched in a Vec, and gathered for another sink
+        let mut buffer = vec![9];
+        assert_eq!(3, write_iter(vec![1u16, 3, 5], &mut buffer));
+        assert_eq!(&expected[..], &buffer[1..]);
+        let mut buffer = vec![9];
+        assert_eq!(3, write_iter((0..6u16).filter(|x| x % 2 == 1), &mut buffer));
+        assert_eq!(&expected[..], &buffer[1..]);
+        let mut counter = SizeCounter::new();
+        assert_eq!(3, write_iter((0..6u16).filter(|x| x % 2 == 1), &mut counter));
+        assert_eq!(expected.len(), counter.len());
+        let mut sink = checksum::HashingSink::new(Vec::new(), checksum::Crc32Hasher::new());
+        write_iter((0..6u16).filter(|x| x % 2 == 1), &mut sink);
+        assert_eq!(expected, sink.finish().0);
+        let mut buffer = Vec::new();
+        assert_eq!(0, write_iter(std::iter::empty::<String>(), &mut buffer));
+        assert_eq!(Some(Vec::<String>::new()), decode(&buffer));
+    }
+
+    #[test]
+    fn test_decode_into(){
+        let frames = [
+            vec![(String::from("alpha"), ByteVec(vec![1, 2, 3]), 1u32), (String::from("beta"), ByteVec(vec![4]), 2)],
+            vec![(String::from("g"), ByteVec(Vec::new()), 3)],
+            vec![(String::from("d"), ByteVec(vec![5]), 4), (String::from("e"), ByteVec(vec![6]), 5), (String::from("f"), ByteVec(vec![]), 6)],
+        ];
+        let mut xs = Vec::new();
+        let mut first = std::ptr::null();
+        for (i, frame) in frames.iter().enumerate(){
+            decode_into(&mut ReadBuffer::from_raw(encode(frame)), &mut xs).unwrap();
+            assert_eq!(frame, &xs);
+            if i == 0{
+                first = xs[0].0.as_ptr();
+            }
+        }
+        // the first string kept its allocation the whole time
+        assert_eq!(first, xs[0].0.as_ptr());
+        assert!(xs[0].0.capacity() >= 5);
+        // failing halfway leaves valid values behind
+        let bytes = encode(&frames[0]);
+        assert_eq!(None, decode_into(&mut ReadBuffer::from_raw(bytes[..bytes.len() - 2].to_vec()), &mut xs));
+        let mut strings = vec![String::from("x"); 3];
+        assert_eq!(Some(2), decode_into_slice(&mut ReadBuffer::from_raw(encode(&vec![String::from("p"), String::from("q")])), &mut strings));
+        assert_eq!(vec![String::from("p"), String::from("q"), String::from("x")], strings);
+        assert_eq!(None, decode_into_slice(&mut ReadBuffer::from_raw(encode(&vec![0u8; 4])), &mut [0u8; 3]));
+        // limits still hold
+        let limits = DecodeLimits{ max_elements: 2, ..DecodeLimits::default() };
+        let mut buf = ReadBuffer::from_raw(encode(&frames[2])).with_limits(limits);
+        assert_eq!(None, decode_into(&mut buf, &mut xs));
+    }
+
+    #[test]
+    fn test_signed_bool_char(){
+        let x = (-1i8, i16::MIN, -70000i32, i64::MIN + 1);
+        let bytes = encode(&x);
+        assert_eq!(vec![0xff, 0x80, 0], bytes[..3].to_vec());
+        assert_eq!(Some(x), decode(&bytes));
+        assert_eq!(Some((i128::MIN, u128::MAX)), decode(&encode(&(i128::MIN, u128::MAX))));
+        let xs = vec![-3i32, 0, i32::MAX, i32::MIN];
+        assert_eq!(4 * 4 + 8, encoded_size_of(&xs));
+        assert_eq!(Some(xs.clone()), decode(&encode(&xs)));
+        // isize is an i64 on every platform
+        assert_eq!(encode(&-5i64), encode(&-5isize));
+        assert_eq!(Some(vec![-5isize, 7]), decode(&encode(&vec![-5i64, 7])));
+        assert_eq!(Some((true, false, 'ß', '\u{10ffff}')), decode(&encode(&(true, false, 'ß', '\u{10ffff}'))));
+        assert_eq!(vec![1, 0], encode(&vec![true, false])[8..].to_vec());
+        assert_eq!(None, decode::<bool>(&[2]));
+        assert_eq!(Err(BufferError::Invalid), validate::<bool>(&mut ReadBuffer::from_raw(vec![2])));
+        assert_eq!(None, decode::<char>(&0xd800u32.to_be_bytes()));
+        assert_eq!(Err(BufferError::Invalid), validate::<char>(&mut ReadBuffer::from_raw(0x110000u32.to_be_bytes().to_vec())));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 4, available: 2 }), validate::<char>(&mut ReadBuffer::from_raw(vec![0, 0])));
+    }
+
+    #[test]
+    fn test_std_collections(){
+        use std::collections::{ BTreeMap, BTreeSet, VecDeque };
+        let x = vec![Some(String::from("a")), None, Some(String::new())];
+        assert_eq!(Some(x.clone()), decode(&encode(&x)));
+        assert_eq!(encoded_size_of(&x), x.buffer_len());
+        assert_eq!(None, decode::<Option<u8>>(&[2, 0]));
+        assert_eq!(Err(BufferError::InvalidDiscriminant), validate::<Option<u8>>(&mut ReadBuffer::from_raw(vec![2, 0])));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 4, available: 1 }), validate::<Option<u32>>(&mut ReadBuffer::from_raw(vec![1, 0])));
+        let mut y = Some(String::with_capacity(64));
+        y.read_from_buffer(&mut ReadBuffer::from_raw(encode(&Some(String::from("kept"))))).unwrap();
+        assert!(y.as_ref().unwrap().capacity() >= 64);
+        let r: Vec<Result<u8, String>> = vec![Ok(1), Err(String::from("bad"))];
+        assert_eq!(vec![0, 1, 1], encode(&r)[8..11].to_vec());
+        assert_eq!(Some(r.clone()), decode(&encode(&r)));
+        assert_eq!(None, decode::<Result<u8, u8>>(&[3, 0]));
+        // the maps and sets look like their hash counterparts
+        let map: BTreeMap<String, i64> = vec![(String::from("x"), -1), (String::from("y"), 2)].into_iter().collect();
+        let hash: HashMap<String, i64> = map.clone().into_iter().collect();
+        assert_eq!(Some(hash), decode(&encode(&map)));
+        assert_eq!(Some(map.clone()), decode(&encode(&map)));
+        assert_eq!(None, decode::<BTreeMap<u8, u8>>(&encode(&vec![(1u8, 2u8), (1, 3)])));
+        assert_eq!(None, decode::<BTreeSet<u8>>(&encode(&vec![4u8, 4])));
+        let set: BTreeSet<u16> = vec![9, 3, 5].into_iter().collect();
+        assert_eq!(Some(vec![3u16, 5, 9]), decode(&encode(&set)));
+        let mut deque: VecDeque<u32> = (0..5).collect();
+        deque.rotate_left(3);
+        deque.push_front(7);
+        assert_eq!(encode(&deque.iter().copied().collect::<Vec<_>>()), encode(&deque));
+        assert_eq!(Some(deque.clone()), decode(&encode(&deque)));
+        assert_eq!(Ok(()), validate::<VecDeque<u32>>(&mut ReadBuffer::from_raw(encode(&deque))));
+    }
+
+    #[test]
+    fn test_endian(){
+        let x = (0x0102u16, vec![1.5f32, -2.0], String::from("le"), (-3i64, 'a', Some(7u128)));
+        let mut le = Vec::new();
+        x.copy_into_buffer(&mut EndianSink::new(&mut le, Endian::Little));
+        assert_eq!(vec![2, 1, 2, 0, 0, 0, 0, 0, 0, 0], le[..10].to_vec());
+        assert_eq!(encoded_size_of(&x), le.len());
+        assert_ne!(encode(&x), le);
+        let mut buf = ReadBuffer::from_raw(le.clone()).with_endian(Endian::Little);
+        assert_eq!(Some(x.clone()), Bufferable::from_buffer(&mut buf));
+        assert_eq!(Ok(()), validate::<(u16, Vec<f32>, String, (i64, char, Option<u128>))>(&mut ReadBuffer::from_raw(le.clone()).with_endian(Endian::Little)));
+        // the wrong byte order reads a far too long Vec
+        assert_eq!(None, <(u16, Vec<f32>)>::from_buffer(&mut ReadBuffer::from_raw(le)));
+        // bulk numbers, usize and sets with their keys sorted by their little endian bytes
+        let xs: Vec<u64> = (0..100).map(|i| i * 0x0101).collect();
+        let sizes = vec![1usize, 300];
+        let set: HashSet<u32> = vec![1, 256].into_iter().collect();
+        let mut le = Vec::new();
+        let mut sink = EndianSink::new(&mut le, Endian::Little);
+        sink.put(&xs).put(&sizes).put(&set);
+        write_iter((0..3u16).filter(|x| x % 2 == 0), &mut sink);
+        assert_eq!(Endian::Little, sink.endian());
+        assert_eq!(vec![0, 1, 0, 0, 1, 0, 0, 0], le[le.len() - 20..le.len() - 12].to_vec());
+        let mut buf = ReadBuffer::from_raw(le).with_endian(Endian::Little);
+        assert_eq!(Some(xs), Bufferable::from_buffer(&mut buf));
+        assert_eq!(Some(sizes), Bufferable::from_buffer(&mut buf));
+        assert_eq!(Some(set), Bufferable::from_buffer(&mut buf));
+        assert_eq!(Endian::Little, buf.fork().endian());
+        assert_eq!(Some(vec![0u16, 2]), Bufferable::from_buffer(&mut buf));
+        // big endian through the wrapper is a plain Buffer
+        let mut be = Vec::new();
+        EndianSink::new(&mut be, Endian::Big).put(&(5u32, vec![1u16, 2]));
+        assert_eq!(encode(&(5u32, vec![1u16, 2])), be);
+    }
+
+    #[test]
+    fn test_try_from_buffer(){
+        let x = vec![String::from("ok"), String::from("bad")];
+        let mut bytes = encode(&x);
+        assert_eq!(Ok(x.clone()), try_decode(&bytes));
+        bytes[28] = 0xff;
+        let err = try_decode::<Vec<String>>(&bytes).unwrap_err();
+        assert_eq!(DecodeError{ error: BufferError::InvalidUtf8, offset: 29, len: 29 }, err);
+        assert_eq!("string is not valid UTF-8 at byte 29 of 29", err.to_string());
+        assert_eq!(BufferError::InvalidUtf8, BufferError::from(err));
+        // the length of the second string says more than there is
+        bytes[25] = 9;
+        let err = try_decode::<Vec<String>>(&bytes).unwrap_err();
+        assert_eq!((BufferError::UnexpectedEof{ needed: 9, available: 3 }, 26), (err.error, err.offset));
+        let mut buf = ReadBuffer::from_raw(vec![0, 0, 0, 0, 0, 0, 0, 2, 7, 2, 3]);
+        buf.skip(8).unwrap();
+        let err = <(u8, Option<u8>)>::try_from_buffer(&mut buf).unwrap_err();
+        assert_eq!((BufferError::InvalidDiscriminant, 10), (err.error, err.offset));
+        assert_eq!(8, buf.consumed());
+        assert_eq!(Ok(7), u8::try_from_buffer(&mut buf));
+        let limits = DecodeLimits{ max_elements: 1, ..DecodeLimits::default() };
+        let mut buf = ReadBuffer::from_raw(encode(&x)).with_limits(limits);
+        assert_eq!(Err(DecodeError{ error: BufferError::LengthOverflow, offset: 8, len: 29 }), buf.read_located::<Vec<String>>());
+        assert_eq!(Err(BufferError::TrailingBytes), try_decode::<u16>(&[0, 1, 2]).map_err(BufferError::from));
+        // a type with the default validate still tells a short buffer from a bad value
+        #[derive(Debug, PartialEq)]
+        struct Even(u16);
+        impl Bufferable for Even{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.0.into_buffer(vec); }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ self.0.copy_into_buffer(vec); }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Even(u16::from_buffer(buf)?)).filter(|x| x.0 % 2 == 0)
+            }
+        }
+        assert_eq!(Ok(Even(4)), try_decode(&[0, 4]));
+        assert_eq!(BufferError::UnexpectedEof{ needed: 2, available: 1 }, try_decode::<Even>(&[0]).unwrap_err().error);
+        assert_eq!(BufferError::Invalid, try_decode::<Even>(&[0, 3]).unwrap_err().error);
+        assert_eq!(BufferError::UnexpectedEof{ needed: 2, available: 0 }, try_decode::<(Even, Even)>(&[0, 2]).unwrap_err().error);
+        // and when it runs out on a trailing u8
+        struct Tail(u16, u8);
+        impl Bufferable for Tail{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ (self.0, self.1).into_buffer(vec); }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ (self.0, self.1).into_buffer(vec); }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Tail(u16::from_buffer(buf)?, u8::from_buffer(buf)?))
+            }
+        }
+        let eof = BufferError::UnexpectedEof{ needed: 1, available: 0 };
+        assert_eq!(Err(eof), validate::<Tail>(&mut ReadBuffer::from_raw(vec![0, 1])));
+        assert_eq!(eof, ReadBuffer::from_raw(vec![0, 1]).read_checked::<Tail>().err().unwrap());
+        // the primitives read with try_from_buffer and stay where they were on an error
+        let mut buf = ReadBuffer::from_raw(vec![2, 0, 0]);
+        assert_eq!(Err(DecodeError{ error: BufferError::Invalid, offset: 1, len: 3 }), bool::try_from_buffer(&mut buf));
+        assert_eq!(Err(DecodeError{ error: BufferError::UnexpectedEof{ needed: 4, available: 3 }, offset: 0, len: 3 }), u32::try_from_buffer(&mut buf));
+        assert_eq!(0, buf.consumed());
+        assert_eq!(None, char::from_buffer(&mut ReadBuffer::from_raw(vec![0, 0x11, 0, 0])));
+        assert_eq!(BufferError::Invalid, char::try_from_buffer(&mut ReadBuffer::from_raw(vec![0, 0x11, 0, 0])).unwrap_err().error);
+        assert_eq!(Ok('a'), char::try_from_buffer(&mut ReadBuffer::from_raw(vec![0, 0, 0, 0x61])));
+    }
+
+    #[test]
+    fn test_borrowed_reads(){
+        let frame = encode(&(String::from("zero"), ByteVec(vec![1, 2]), 5u8, String::from("copy")));
+        let (a, b, c) = {
+            let mut buf = ReadBuffer::from(&frame[..]);
+            let a = buf.borrow_str().unwrap();
+            let b = buf.borrow_blob().unwrap();
+            assert_eq!(Some(5), u8::from_buffer(&mut buf));
+            let c = buf.borrow_str().unwrap();
+            assert_eq!(None, buf.borrow_bytes(1));
+            (a, b, c)
+        };
+        assert_eq!(("zero", &[1u8, 2][..], "copy"), (a, b, c));
+        assert_eq!(frame[8..].as_ptr(), a.as_ptr());
+        // an owned buffer does not move
+        let mut owned = ReadBuffer::from_raw(frame.clone());
+        assert_eq!(None, owned.borrow_str());
+        assert_eq!(0, owned.consumed());
+        let limits = DecodeLimits{ max_string_len: 3, ..DecodeLimits::default() };
+        assert_eq!(None, ReadBuffer::from_slice(&frame).with_limits(limits).borrow_str());
+        let mut bad = encode(&String::from("ab"));
+        bad[9] = 0xff;
+        assert_eq!(None, ReadBuffer::from_slice(&bad).borrow_str());
+    }
+
+    #[test]
+    fn test_arrays(){
+        let key = [7u8; 16];
+        assert_eq!(key.to_vec(), encode(&key));
+        let tri = [[0.0f32, 1.0, 2.0], [3.0, 4.0, 5.0]];
+        assert_eq!(24, encoded_size_of(&tri));
+        assert_eq!(24, <[[f32; 3]; 2]>::MIN_BUFFER_LEN);
+        assert_eq!(Some(tri), decode(&encode(&tri)));
+        let names = [String::from("a"), String::from("bc")];
+        let bytes = encode(&names);
+        assert_eq!(names.buffer_len(), bytes.len());
+        assert_eq!(Some(names.clone()), decode(&bytes));
+        assert_eq!(None, decode::<[String; 2]>(&bytes[..bytes.len() - 1]));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 8, available: 7 }), validate::<[u32; 2]>(&mut ReadBuffer::from_raw(vec![0; 7])));
+        let mut x = [String::with_capacity(32), String::new()];
+        x.read_from_buffer(&mut ReadBuffer::from_raw(bytes)).unwrap();
+        assert_eq!(names, x);
+        assert!(x[0].capacity() >= 32);
+        assert_eq!(Some(Vec::<[u8; 2]>::new()), decode(&encode(&Vec::<[u8; 2]>::new())));
+        let mut buffer = Vec::new();
+        write_slice(&tri[..1], &mut buffer);
+        assert_eq!(Some(vec![tri[0]]), decode(&buffer));
+        let mut buffer = ReadBuffer::from_raw(encode(&[1u16, 2]));
+        assert_eq!(4, buffer.remaining());
+        assert_eq!([1, 2], unsafe{ <[u16; 2]>::from_buffer_unchecked(&mut buffer) });
+    }
+}
@@ -0,0 +1,370 @@
+/// Write several values to a Sink in order, the short form of a copy_into_buffer call for each.
+/// `bin_write!(vec; a, b, c)` writes a, b and c, which are taken by reference, to vec,
+/// a &mut to a Sink like the one copy_into_buffer gets.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// struct Player{ id: u16, name: String, pos: (f64, f64) }
+/// impl Bufferable for Player{
+///     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.copy_into_buffer(vec); }
+///     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+///         bin_write!(vec; self.id, self.name, self.pos);
+///     }
+///     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+///         let (id, name, pos) = bin_read!(buf => (u16, String, (f64, f64)));
+///         Some(Player{ id, name, pos })
+///     }
+/// }
+/// let bytes = encode(&Player{ id: 3, name: String::from("ann"), pos: (1.0, 2.0) });
+/// let player = decode::<Player>(&bytes).unwrap();
+/// assert_eq!((3, "ann", (1.0, 2.0)), (player.id, &player.name[..], player.pos));
+/// ```
+#[macro_export]
+macro_rules! bin_write{
+    ($vec:expr; $( $x:expr ),* $(,)?) => {{
+        let sink: &mut _ = $vec;
+        $( $crate::Bufferable::copy_into_buffer(&$x, sink); )*
+    }};
+}
+
+/// Read values from a &mut ReadBuffer, returning None from the surrounding function if one
+/// does not decode, like `?` on every from_buffer call.
+/// `bin_read!(buf => T)` reads one T, `bin_read!(buf => (A, B, C))` reads an A, a B and a C
+/// and gives them as a tuple, of any length. See bin_write for an example.
+#[macro_export]
+macro_rules! bin_read{
+    ($buf:expr => ( $( $ty:ty ),+ $(,)? )) => {{
+        let buf: &mut $crate::ReadBuffer = $buf;
+        ( $( <$ty as $crate::Bufferable>::from_buffer(buf)? ),+ )
+    }};
+    ($buf:expr => $ty:ty) => {
+        <$ty as $crate::Bufferable>::from_buffer($buf)?
+    };
+}
+
+/// Implement Bufferable for a single field tuple struct so it encodes exactly as its field,
+/// without a byte of overhead: `bufferable_newtype!(UserId(u64))` makes UserId read and
+/// write like a u64, so a field can switch between the two without changing the format.
+/// Type parameters get a Bufferable bound, like with bufferable_struct.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// #[derive(Debug, PartialEq)]
+/// struct UserId(u64);
+/// bufferable_newtype!(UserId(u64));
+/// assert_eq!(encode(&7u64), encode(&UserId(7)));
+/// assert_eq!(Some(UserId(7)), decode(&encode(&7u64)));
+/// ```
+#[macro_export]
+macro_rules! bufferable_newtype{
+    ($name:ident $( < $( $gen:ident ),+ $(,)? > )? ($inner:ty)) => {
+        impl $( < $( $gen: $crate::Bufferable ),+ > )? $crate::Bufferable for $name $( < $( $gen ),+ > )?{
+            const MIN_BUFFER_LEN: usize = <$inner as $crate::Bufferable>::MIN_BUFFER_LEN;
+
+            fn into_buffer<S: $crate::Sink + ?Sized>(self, vec: &mut S){
+                $crate::Bufferable::into_buffer(self.0, vec);
+            }
+
+            fn copy_into_buffer<S: $crate::Sink + ?Sized>(&self, vec: &mut S){
+                $crate::Bufferable::copy_into_buffer(&self.0, vec);
+            }
+
+            fn from_buffer(buf: &mut $crate::ReadBuffer) -> Option<Self>{
+                Option::Some($name(<$inner as $crate::Bufferable>::from_buffer(buf)?))
+            }
+
+            fn validate(buf: &mut $crate::ReadBuffer) -> Result<(), $crate::BufferError>{
+                <$inner as $crate::Bufferable>::validate(buf)
+            }
+
+            fn validate_slice(len: usize, buf: &mut $crate::ReadBuffer) -> Result<(), $crate::BufferError>{
+                <$inner as $crate::Bufferable>::validate_slice(len, buf)
+            }
+
+            fn buffer_len(&self) -> usize{
+                $crate::Bufferable::buffer_len(&self.0)
+            }
+        }
+    };
+}
+
+/// Implement Bufferable for a struct by writing its fields one after the other,
+/// in the order they are listed here, which does not have to be the order they are declared in:
+/// the declaration can be reorganized without changing the format.
+/// Tuple struct fields are listed by their index, like `Point(0: f32, 1: f32)`,
+/// and a unit struct as `Marker;`, which writes nothing.
+/// A field can be written another way by naming an encoding from the fields module after it,
+/// like `id: u32 as fields::Varint`.
+/// Fields of type `Option<T>` go in an `optional { ... }` group after the others, listed with
+/// their T. They are written as a bitmap with one bit per optional field in front of the struct,
+/// after which only the fields that are present follow, see fields::Presence.
+/// Type parameters get a Bufferable bound, `#[bufferable(bound(...))]` in front replaces
+/// that with where clauses of your own, for parameters that are not written themselves
+/// or need more than Bufferable.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// #[derive(Debug, PartialEq)]
+/// struct Pair<T>{ a: T, b: T }
+/// bufferable_struct!{ Pair<T>{ a: T, b: T } }
+/// #[derive(Debug, PartialEq)]
+/// struct Tagged<K, V>{ key: K, values: Vec<V> }
+/// bufferable_struct!{
+///     #[bufferable(bound(K: Bufferable + Clone, V: Bufferable))]
+///     Tagged<K, V>{ key: K, values: Vec<V> }
+/// }
+/// // declared x, y but written y, x
+/// #[derive(Debug, PartialEq)]
+/// struct Point(f32, f32);
+/// bufferable_struct!{ Point(1: f32, 0: f32) }
+/// #[derive(Debug, PartialEq)]
+/// struct Ping;
+/// bufferable_struct!{ Ping; }
+/// #[derive(Debug, PartialEq)]
+/// struct Patch{ id: u32, name: Option<String>, size: Option<u64> }
+/// bufferable_struct!{ Patch{ id: u32 } optional { name: String, size: u64 } }
+/// let pair = Pair{ a: 1u16, b: 2 };
+/// assert_eq!(encode(&(1u16, 2u16)), encode(&pair));
+/// assert_eq!(Some(pair), decode(&encode(&(1u16, 2u16))));
+/// let tagged = Tagged{ key: String::from("k"), values: vec![1u8, 2] };
+/// assert_eq!(Some(tagged), decode(&encode(&(String::from("k"), vec![1u8, 2]))));
+/// assert_eq!(encode(&(2.0f32, 1.0f32)), encode(&Point(1.0, 2.0)));
+/// assert_eq!(0, encode(&Ping).len());
+/// // the bitmap says only size is there
+/// let patch = Patch{ id: 1, name: None, size: Some(2) };
+/// assert_eq!(encode(&(0b0100_0000u8, 1u32, 2u64)), encode(&patch));
+/// assert_eq!(Some(patch), decode(&encode(&(0b0100_0000u8, 1u32, 2u64))));
+/// ```
+#[macro_export]
+macro_rules! bufferable_struct{
+    (#[bufferable(bound( $( $bound:tt )* ))] $name:ident $( < $( $gen:ident ),+ $(,)? > )?
+        { $( $field:ident : $ty:ty $( as $enc:ty )? ),* $(,)? }
+        $( optional { $( $ofield:ident : $oty:ty $( as $oenc:ty )? ),* $(,)? } )?) => {
+        $crate::bufferable_struct!{ @impl [ $( $( $gen ),+ )? ] [ $( $( $gen ),+ )? ] [ $( $bound )* ]
+            $name { $( $field : $ty $( as $enc )? ),* } [ $( $( $ofield : $oty $( as $oenc )? ),* )? ] }
+    };
+    (#[bufferable(bound( $( $bound:tt )* ))] $name:ident $( < $( $gen:ident ),+ $(,)? > )?
+        ( $( $index:tt : $ty:ty $( as $enc:ty )? ),* $(,)? )) => {
+        $crate::bufferable_struct!{ @impl [ $( $( $gen ),+ )? ] [ $( $( $gen ),+ )? ] [ $( $bound )* ]
+            $name { $( $index : $ty $( as $enc )? ),* } [] }
+    };
+    ($name:ident $( < $( $gen:ident ),+ $(,)? > )? { $( $field:ident : $ty:ty $( as $enc:ty )? ),* $(,)? }
+        $( optional { $( $ofield:ident : $oty:ty $( as $oenc:ty )? ),* $(,)? } )?) => {
+        $crate::bufferable_struct!{ @impl [ $( $( $gen: $crate::Bufferable ),+ )? ] [ $( $( $gen ),+ )? ] []
+            $name { $( $field : $ty $( as $enc )? ),* } [ $( $( $ofield : $oty $( as $oenc )? ),* )? ] }
+    };
+    ($name:ident $( < $( $gen:ident ),+ $(,)? > )? ( $( $index:tt : $ty:ty $( as $enc:ty )? ),* $(,)? )) => {
+        $crate::bufferable_struct!{ @impl [ $( $( $gen: $crate::Bufferable ),+ )? ] [ $( $( $gen ),+ )? ] []
+            $name { $( $index : $ty $( as $enc )? ),* } [] }
+    };
+    ($name:ident;) => {
+        $crate::bufferable_struct!{ @impl [] [] [] $name {} [] }
+    };
+    (@enc) => { $crate::fields::Plain };
+    (@enc $enc:ty) => { $enc };
+    (@count) => { 0 };
+    (@count $head:tt $( $tail:tt )*) => { 1 + $crate::bufferable_struct!(@count $( $tail )*) };
+    (@impl [ $( $impl_gen:tt )* ] [ $( $ty_gen:tt )* ] [ $( $bound:tt )* ] $name:ident
+        { $( $field:tt : $ty:ty $( as $enc:ty )? ),* } [ $( $ofield:ident : $oty:ty $( as $oenc:ty )? ),* ]) => {
+        impl< $( $impl_gen )* > $crate::Bufferable for $name< $( $ty_gen )* > where $( $bound )* {
+            const MIN_BUFFER_LEN: usize = $crate::fields::Presence::byte_len($crate::bufferable_struct!(@count $( $ofield )*))
+                $( + <$crate::bufferable_struct!(@enc $( $enc )?) as $crate::fields::FieldEncoding<$ty>>::MIN_LEN )*;
+
+            #[allow(unused_variables)]
+            fn into_buffer<S: $crate::Sink + ?Sized>(self, vec: &mut S){
+                $crate::fields::Presence::write(&[ $( self.$ofield.is_some() ),* ], vec);
+                $( <$crate::bufferable_struct!(@enc $( $enc )?) as $crate::fields::FieldEncoding<$ty>>::write_owned(self.$field, vec); )*
+                $( if let Option::Some(x) = self.$ofield{
+                    <$crate::bufferable_struct!(@enc $( $oenc )?) as $crate::fields::FieldEncoding<$oty>>::write_owned(x, vec);
+                } )*
+            }
+
+            #[allow(unused_variables)]
+            fn copy_into_buffer<S: $crate::Sink + ?Sized>(&self, vec: &mut S){
+                $crate::fields::Presence::write(&[ $( self.$ofield.is_some() ),* ], vec);
+                $( <$crate::bufferable_struct!(@enc $( $enc )?) as $crate::fields::FieldEncoding<$ty>>::write(&self.$field, vec); )*
+                $( if let Option::Some(x) = &self.$ofield{
+                    <$crate::bufferable_struct!(@enc $( $oenc )?) as $crate::fields::FieldEncoding<$oty>>::write(x, vec);
+                } )*
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn from_buffer(buf: &mut $crate::ReadBuffer) -> Option<Self>{
+                let mut presence = $crate::fields::Presence::read(buf, $crate::bufferable_struct!(@count $( $ofield )*)).ok()?;
+                Option::Some($name{
+                    $( $field: <$crate::bufferable_struct!(@enc $( $enc )?) as $crate::fields::FieldEncoding<$ty>>::read(buf)?, )*
+                    $( $ofield: if presence.next()?{
+                        Option::Some(<$crate::bufferable_struct!(@enc $( $oenc )?) as $crate::fields::FieldEncoding<$oty>>::read(buf)?)
+                    } else {
+                        Option::None
+                    }, )*
+                })
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn validate(buf: &mut $crate::ReadBuffer) -> Result<(), $crate::BufferError>{
+                let mut presence = $crate::fields::Presence::read(buf, $crate::bufferable_struct!(@count $( $ofield )*))?;
+                $( <$crate::bufferable_struct!(@enc $( $enc )?) as $crate::fields::FieldEncoding<$ty>>::validate(buf)?; )*
+                $( if presence.next() == Option::Some(true){
+                    <$crate::bufferable_struct!(@enc $( $oenc )?) as $crate::fields::FieldEncoding<$oty>>::validate(buf)?;
+                } )*
+                Ok(())
+            }
+
+            fn buffer_len(&self) -> usize{
+                $crate::fields::Presence::byte_len($crate::bufferable_struct!(@count $( $ofield )*))
+                    $( + <$crate::bufferable_struct!(@enc $( $enc )?) as $crate::fields::FieldEncoding<$ty>>::len(&self.$field) )*
+                    $( + self.$ofield.as_ref().map_or(0, <$crate::bufferable_struct!(@enc $( $oenc )?) as $crate::fields::FieldEncoding<$oty>>::len) )*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    fn read_header(buf: &mut ReadBuffer) -> Option<(u8, u16, u32, u64, String, Vec<u8>)>{
+        let magic = bin_read!(buf => u8);
+        Some((magic, bin_read!(buf => u16), bin_read!(buf => u32), bin_read!(buf => u64),
+            bin_read!(buf => String), bin_read!(buf => Vec<u8>)))
+    }
+
+    fn read_six(buf: &mut ReadBuffer) -> Option<(u8, u16, u32, u64, String, Vec<u8>)>{
+        Some(bin_read!(buf => (u8, u16, u32, u64, String, Vec<u8>,)))
+    }
+
+    #[test]
+    fn test_macros(){
+        let mut vec = Vec::new();
+        bin_write!(&mut vec; 1u8, 2u16, 3u32, 4u64, String::from("five"), vec![6u8]);
+        let mut expected = Vec::new();
+        (1u8, 2u16, 3u32, 4u64).into_buffer(&mut expected);
+        (String::from("five"), vec![6u8]).into_buffer(&mut expected);
+        assert_eq!(expected, vec);
+        let six = (1u8, 2u16, 3u32, 4u64, String::from("five"), vec![6u8]);
+        assert_eq!(Some(six.clone()), read_header(&mut ReadBuffer::from_raw(vec.clone())));
+        assert_eq!(Some(six), read_six(&mut ReadBuffer::from_raw(vec.clone())));
+        // returns early on the first value that does not decode
+        vec.pop();
+        assert_eq!(None, read_six(&mut ReadBuffer::from_raw(vec)));
+        let mut counter = SizeCounter::new();
+        bin_write!(&mut counter; 0u32);
+        assert_eq!(4, counter.len());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Name(String);
+    bufferable_newtype!(Name(String));
+    #[derive(Debug, PartialEq)]
+    struct Sorted<T>(Vec<T>);
+    bufferable_newtype!(Sorted<T>(Vec<T>));
+
+    #[test]
+    fn test_newtype(){
+        let names = vec![Name(String::from("a")), Name(String::from("bc"))];
+        let bytes = encode(&names);
+        assert_eq!(encode(&vec![String::from("a"), String::from("bc")]), bytes);
+        assert_eq!(names.buffer_len(), bytes.len());
+        assert_eq!(Some(names), decode(&bytes));
+        assert_eq!(String::MIN_BUFFER_LEN, Name::MIN_BUFFER_LEN);
+        let mut bad = ReadBuffer::from_raw(vec![0, 0, 0, 0, 0, 0, 0, 1, 0xff]);
+        assert_eq!(Err(BufferError::InvalidUtf8), validate::<Name>(&mut bad));
+        assert_eq!(Some(Sorted(vec![1u8, 2])), decode(&encode(&vec![1u8, 2])));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Grid<T>{ width: u16, cells: Vec<T> }
+    bufferable_struct!{ Grid<T>{ width: u16, cells: Vec<T> } }
+
+    // M is only a marker, it is never written
+    #[derive(Debug, PartialEq)]
+    struct Id<M>{ raw: u32, marker: std::marker::PhantomData<M> }
+    impl<M> Bufferable for std::marker::PhantomData<M>{
+        const MIN_BUFFER_LEN: usize = 0;
+        fn into_buffer<S: Sink + ?Sized>(self, _: &mut S){}
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, _: &mut S){}
+        fn from_buffer(_: &mut ReadBuffer) -> Option<Self>{ Some(std::marker::PhantomData) }
+    }
+    bufferable_struct!{ #[bufferable(bound())] Id<M>{ raw: u32, marker: std::marker::PhantomData<M> } }
+
+    #[derive(Debug, PartialEq)]
+    struct Empty{}
+    bufferable_struct!{ Empty{} }
+
+    #[test]
+    fn test_struct(){
+        let grid = Grid{ width: 2, cells: vec![(1u8, 2u8), (3, 4)] };
+        let bytes = encode(&grid);
+        assert_eq!(encode(&(2u16, vec![(1u8, 2u8), (3, 4)])), bytes);
+        assert_eq!(grid.buffer_len(), bytes.len());
+        assert_eq!(10, Grid::<u8>::MIN_BUFFER_LEN);
+        assert_eq!(Some(grid), decode(&bytes));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 1, available: 0 }), validate::<Grid<(u8, u8)>>(&mut ReadBuffer::from_raw(bytes[..12].to_vec())));
+        // a type that is not Bufferable as the marker
+        struct NotBufferable;
+        let id = Id::<NotBufferable>{ raw: 5, marker: std::marker::PhantomData };
+        assert_eq!(encode(&5u32), encode(&id));
+        assert_eq!(Some(5), decode::<Id<NotBufferable>>(&encode(&5u32)).map(|id| id.raw));
+        assert_eq!(Some(Empty{}), decode(&[]));
+    }
+
+    // the wire order is the listed order, whatever the declaration order is
+    #[derive(Debug, PartialEq)]
+    struct Reordered{ name: String, id: u32, tags: Vec<u8> }
+    bufferable_struct!{ Reordered{ id: u32, tags: Vec<u8>, name: String } }
+    #[derive(Debug, PartialEq)]
+    struct Span<T>(T, T, u8);
+    bufferable_struct!{ Span<T>(2: u8, 0: T, 1: T,) }
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Unit;
+    bufferable_struct!{ Unit; }
+
+    #[test]
+    fn test_struct_order(){
+        let x = Reordered{ name: String::from("n"), id: 9, tags: vec![1] };
+        assert_eq!(encode(&(9u32, vec![1u8], String::from("n"))), encode(&x));
+        assert_eq!(Some(x), decode(&encode(&(9u32, vec![1u8], String::from("n")))));
+        let span = Span(10u16, 20u16, 3);
+        assert_eq!(encode(&(3u8, 10u16, 20u16)), encode(&span));
+        assert_eq!(Some(span), decode(&encode(&(3u8, 10u16, 20u16))));
+        assert_eq!(0, Unit::MIN_BUFFER_LEN);
+        assert_eq!(Some((vec![Unit, Unit], 7u16)), decode(&encode(&(vec![Unit, Unit], 7u16))));
+        // a huge count of elements with no bytes is refused instead of looping on nothing
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(None, decode::<Vec<Unit>>(&huge));
+        assert_eq!(None, decode::<Vec<[u32; 0]>>(&huge));
+        assert_eq!(None, decode::<Vec<Unit>>(&[0, 0, 0, 0, 0, 0, 0, 1 << 4]));
+        assert_eq!(None, Vec::<Unit>::new().read_from_buffer(&mut ReadBuffer::from_raw(huge.to_vec())));
+        assert_eq!(Err(BufferError::LengthOverflow), validate::<Vec<Unit>>(&mut ReadBuffer::from_raw(huge.to_vec())));
+        assert_eq!(Err(BufferError::LengthOverflow), validate::<std::collections::BTreeMap<Unit, Unit>>(&mut ReadBuffer::from_raw(huge.to_vec())));
+        assert_eq!(Ok(()), validate::<[Unit; 3]>(&mut ReadBuffer::from_raw(Vec::new())));
+    }
+
+    #[derive(Debug, PartialEq, Default)]
+    struct Sparse{
+        id: u16, a: Option<u8>, b: Option<u8>, c: Option<u8>, d: Option<String>, e: Option<u8>,
+        f: Option<u8>, g: Option<u8>, h: Option<u8>, i: Option<u32>,
+    }
+    bufferable_struct!{ Sparse{ id: u16 } optional { a: u8, b: u8, c: u8, d: String, e: u8, f: u8, g: u8, h: u8, i: u32 as fields::Varint } }
+
+    #[test]
+    fn test_struct_optional(){
+        let empty = Sparse::default();
+        assert_eq!(vec![0, 0, 0, 0], encode(&empty));
+        assert_eq!(4, Sparse::MIN_BUFFER_LEN);
+        let x = Sparse{ id: 7, b: Some(2), d: Some(String::from("d")), i: Some(300), ..Sparse::default() };
+        let bytes = encode(&x);
+        let mut expected = vec![0b0101_0000, 0b1000_0000, 0, 7, 2];
+        expected.extend_from_slice(&encode(&String::from("d")));
+        expected.extend_from_slice(&[0xac, 0x02]);
+        assert_eq!(expected, bytes);
+        assert_eq!(x.buffer_len(), bytes.len());
+        assert_eq!(Ok(()), validate::<Sparse>(&mut ReadBuffer::from_raw(bytes.clone())));
+        assert_eq!(Some(x), decode(&bytes));
+        // a padding bit that is set
+        assert_eq!(None, decode::<Sparse>(&[0, 0b0100_0000, 0, 7]));
+        assert_eq!(Err(BufferError::Invalid), validate::<Sparse>(&mut ReadBuffer::from_raw(vec![0, 1, 0, 7])));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 1, available: 0 }), validate::<Sparse>(&mut ReadBuffer::from_raw(vec![0x80, 0, 0, 7])));
+    }
+}
@@ -0,0 +1,248 @@
+//! Merkle trees over fixed size chunks of a file, so a reader that fetches only part of it,
+//! with a range request or by jumping to one entry of an archive, can check the bytes it got.
+//! The leaves are the SHA-256 of every chunk and the root is the hash over all of them.
+//! With a root that is trusted, a MerkleTree read from anywhere is checked with verify_root,
+//! after which any chunk can be checked against its leaf. A reader that does not want to fetch
+//! all the leaves can check a chunk with a proof of log2(chunks) hashes instead, see verify_proof.
+//! Leaves hash a 0 byte and the chunk, inner nodes a 1 byte and their children, and a node
+//! without a sibling moves up a level as it is, like RFC 6962 does.
+//! A MerkleTree is written as the chunk size (u64), the file length (u64) and the leaves.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::merkle::*;
+//! let file: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+//! let tree = MerkleTree::build(&file, 256);
+//! let root = tree.root();
+//! // the reader gets the tree from the file, but the root from somewhere it trusts
+//! let tree = decode::<MerkleTree>(&encode(&tree)).unwrap();
+//! assert!(tree.verify_root(&root));
+//! let (start, end) = tree.covering(300, 100);
+//! assert_eq!((256, 512), (start, end));
+//! assert!(tree.verify_range(start, &file[start as usize..end as usize]));
+//! let proof = tree.proof(3).unwrap();
+//! assert!(verify_proof(&root, 3, tree.chunk_count(), &file[768..], &proof));
+//! ```
+use crate::{ Bufferable, ReadBuffer, Sink };
+use crate::checksum::sha256;
+
+/// A SHA-256 hash.
+pub type Hash = [u8; 32];
+
+/// Hash of a chunk, a leaf of the tree.
+pub fn leaf_hash(chunk: &[u8]) -> Hash{
+    sha256(&[&[0], chunk])
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash{
+    sha256(&[&[1], left, right])
+}
+
+// The next level up: pairs hashed together, a last node without a sibling moved up.
+fn level_up(level: &[Hash]) -> Vec<Hash>{
+    level.chunks(2).map(|pair| if pair.len() == 2 { node_hash(&pair[0], &pair[1]) } else { pair[0] }).collect()
+}
+
+/// The hashes of every chunk of a file and what is needed to find the chunks again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree{
+    chunk_size: u64,
+    len: u64,
+    leaves: Vec<Hash>,
+}
+
+impl MerkleTree{
+    /// The tree over bytes cut in chunks of chunk_size, the last one possibly shorter.
+    /// Panics if chunk_size is 0.
+    pub fn build(bytes: &[u8], chunk_size: usize) -> Self{
+        assert!(chunk_size > 0, "merkle chunk size is 0");
+        let leaves = bytes.chunks(chunk_size).map(leaf_hash).collect();
+        Self{ chunk_size: chunk_size as u64, len: bytes.len() as u64, leaves }
+    }
+    /// Bytes in a chunk.
+    pub fn chunk_size(&self) -> u64{
+        self.chunk_size
+    }
+    /// Bytes in the file.
+    pub fn file_len(&self) -> u64{
+        self.len
+    }
+    /// Number of chunks.
+    pub fn chunk_count(&self) -> usize{
+        self.leaves.len()
+    }
+    /// The hash of every chunk.
+    pub fn leaves(&self) -> &[Hash]{
+        &self.leaves
+    }
+    /// The root, the hash of the empty string for an empty file.
+    pub fn root(&self) -> Hash{
+        if self.leaves.is_empty(){
+            return sha256(&[]);
+        }
+        let mut level = self.leaves.clone();
+        while level.len() > 1{
+            level = level_up(&level);
+        }
+        level[0]
+    }
+    /// Whether this tree has the given root, so its leaves can be trusted.
+    pub fn verify_root(&self, root: &Hash) -> bool{
+        self.root() == *root
+    }
+    /// Where chunk i starts and ends in the file.
+    pub fn chunk_range(&self, i: usize) -> Option<(u64, u64)>{
+        if i >= self.leaves.len(){
+            return Option::None;
+        }
+        let start = i as u64 * self.chunk_size;
+        Option::Some((start, (start + self.chunk_size).min(self.len)))
+    }
+    /// The start and end of the whole chunks that hold the len bytes at offset,
+    /// the part of the file to fetch to be able to check them.
+    pub fn covering(&self, offset: u64, len: u64) -> (u64, u64){
+        let start = (offset / self.chunk_size * self.chunk_size).min(self.len);
+        let end = offset.saturating_add(len).min(self.len);
+        let end = end.div_ceil(self.chunk_size).saturating_mul(self.chunk_size).min(self.len);
+        (start, end.max(start))
+    }
+    /// Whether chunk i holds exactly bytes.
+    pub fn verify_chunk(&self, i: usize, bytes: &[u8]) -> bool{
+        self.chunk_range(i).is_some_and(|(start, end)| (end - start) as usize == bytes.len())
+            && self.leaves[i] == leaf_hash(bytes)
+    }
+    /// Whether bytes, starting at offset, are whole chunks of the file and all of them check out.
+    /// The range has to start and end on chunk boundaries or at the end of the file, as covering gives.
+    pub fn verify_range(&self, offset: u64, bytes: &[u8]) -> bool{
+        if !offset.is_multiple_of(self.chunk_size) || offset + bytes.len() as u64 > self.len{
+            return false;
+        }
+        let end = offset + bytes.len() as u64;
+        if !end.is_multiple_of(self.chunk_size) && end != self.len{
+            return false;
+        }
+        let first = (offset / self.chunk_size) as usize;
+        bytes.chunks(self.chunk_size as usize).enumerate().all(|(i, chunk)| self.verify_chunk(first + i, chunk))
+    }
+    /// The hashes that lead from leaf i to the root, for verify_proof.
+    pub fn proof(&self, i: usize) -> Option<Vec<Hash>>{
+        if i >= self.leaves.len(){
+            return Option::None;
+        }
+        let mut proof = Vec::new();
+        let mut level = self.leaves.clone();
+        let mut i = i;
+        while level.len() > 1{
+            let sibling = i ^ 1;
+            if sibling < level.len(){
+                proof.push(level[sibling]);
+            }
+            level = level_up(&level);
+            i /= 2;
+        }
+        Option::Some(proof)
+    }
+}
+
+/// Whether chunk is chunk i of count chunks of the file with the given root, using a proof from MerkleTree::proof.
+pub fn verify_proof(root: &Hash, i: usize, count: usize, chunk: &[u8], proof: &[Hash]) -> bool{
+    if i >= count{
+        return false;
+    }
+    let mut hash = leaf_hash(chunk);
+    let mut proof = proof.iter();
+    let (mut i, mut count) = (i, count);
+    while count > 1{
+        let sibling = i ^ 1;
+        if sibling < count{
+            let other = match proof.next(){
+                Option::Some(other) => other,
+                Option::None => return false,
+            };
+            hash = if i % 2 == 0 { node_hash(&hash, other) } else { node_hash(other, &hash) };
+        }
+        i /= 2;
+        count = count.div_ceil(2);
+    }
+    proof.next().is_none() && hash == *root
+}
+
+impl Bufferable for MerkleTree{
+    const MIN_BUFFER_LEN: usize = 24;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.chunk_size.into_buffer(vec);
+        self.len.into_buffer(vec);
+        (self.leaves.len() as u64).into_buffer(vec);
+        for leaf in &self.leaves{
+            vec.write_bytes(leaf);
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let chunk_size = u64::from_buffer(buf)?;
+        let len = u64::from_buffer(buf)?;
+        let count = buf.read_len()?;
+        if chunk_size == 0 || len.div_ceil(chunk_size) != count as u64{
+            return Option::None;
+        }
+        buf.use_bytes(count.checked_mul(32)?)?;
+        let bytes = buf.read_bytes(count.checked_mul(32)?)?;
+        let leaves = bytes.chunks(32).map(|c|{
+            let mut leaf = [0u8; 32];
+            leaf.copy_from_slice(c);
+            leaf
+        }).collect();
+        Option::Some(Self{ chunk_size, len, leaves })
+    }
+
+    fn buffer_len(&self) -> usize{
+        24 + 32 * self.leaves.len()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::merkle::*;
+
+    #[test]
+    fn test_merkle(){
+        let file: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        for chunk_size in [1usize, 64, 100, 999, 1000, 4096].iter(){
+            let tree = MerkleTree::build(&file, *chunk_size);
+            let root = tree.root();
+            for i in 0..tree.chunk_count(){
+                let (start, end) = tree.chunk_range(i).unwrap();
+                let chunk = &file[start as usize..end as usize];
+                assert!(tree.verify_chunk(i, chunk));
+                let proof = tree.proof(i).unwrap();
+                assert!(verify_proof(&root, i, tree.chunk_count(), chunk, &proof));
+                if tree.chunk_count() > 1{
+                    assert!(!verify_proof(&root, i ^ 1, tree.chunk_count(), chunk, &proof) || i ^ 1 >= tree.chunk_count());
+                }
+            }
+            assert_eq!(Some(tree.clone()), decode::<MerkleTree>(&encode(&tree)));
+        }
+        let tree = MerkleTree::build(&file, 100);
+        let mut changed = file.clone();
+        changed[450] ^= 1;
+        assert!(!tree.verify_range(400, &changed[400..500]));
+        assert!(tree.verify_range(400, &file[400..500]));
+        assert!(!tree.verify_range(400, &file[400..450]));
+        assert!(!tree.verify_range(450, &file[450..500]));
+        assert!(!MerkleTree::build(&changed, 100).verify_root(&tree.root()));
+        assert_eq!((900, 1000), tree.covering(950, 500));
+        assert_eq!((100, 200), tree.covering(150, 0));
+        let proof = tree.proof(4).unwrap();
+        assert!(!verify_proof(&tree.root(), 4, 10, &changed[400..500], &proof));
+        assert!(!verify_proof(&tree.root(), 4, 10, &file[400..500], &proof[1..]));
+        assert_eq!(sha256(&[]), MerkleTree::build(&[], 16).root());
+        assert_eq!(None, decode::<MerkleTree>(&encode(&(16u64, 33u64, 2u64))));
+    }
+
+}
@@ -0,0 +1,236 @@
+//! Converting between buffers and MessagePack, enabled with the `msgpack` feature.
+//! The layout of a buffer comes from a Schema, or from the buffer itself when it was written with SelfDescribing.
+//! Tuples become arrays. Integers use the smallest MessagePack int that fits and floats are always float 64.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::schema::*;
+//! use bin_buffer::msgpack::*;
+//! let mut buffer = Vec::new();
+//! (1u32, String::from("a")).into_buffer(&mut buffer);
+//! let schema = <(u32,String)>::schema();
+//! let packed = to_msgpack(&schema, &buffer).unwrap();
+//! assert_eq!(vec![0x92, 0x01, 0xa1, b'a'], packed);
+//! assert_eq!(Some(buffer), from_msgpack(&schema, &packed));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, ReadBuffer, Sink };
+use crate::schema::{ read_self_describing, Schema, Value };
+
+/// Write value as MessagePack.
+pub fn write_value<S: Sink + ?Sized>(value: &Value, vec: &mut S){
+    match value{
+        Value::UInt(x) => {
+            let x = *x;
+            if x < 0x80 { vec.write_bytes(&[x as u8]); }
+            else if x <= 0xff { vec.write_bytes(&[0xcc, x as u8]); }
+            else if x <= 0xffff { write_tagged(0xcd, &(x as u16).to_be_bytes(), vec); }
+            else if x <= 0xffff_ffff { write_tagged(0xce, &(x as u32).to_be_bytes(), vec); }
+            else { write_tagged(0xcf, &x.to_be_bytes(), vec); }
+        },
+        Value::Float(x) => write_tagged(0xcb, &x.to_be_bytes(), vec),
+        Value::Str(x) => {
+            write_len(x.len(), Option::Some(0xa0), [0xd9, 0xda, 0xdb], vec);
+            vec.write_bytes(x.as_bytes());
+        },
+        Value::Bytes(x) => {
+            write_len(x.len(), Option::None, [0xc4, 0xc5, 0xc6], vec);
+            vec.write_bytes(x);
+        },
+        Value::Seq(xs) => {
+            write_len(xs.len(), Option::Some(0x90), [0, 0xdc, 0xdd], vec);
+            for x in xs{
+                write_value(x, vec);
+            }
+        },
+        Value::Map(xs) => {
+            write_len(xs.len(), Option::Some(0x80), [0, 0xde, 0xdf], vec);
+            for (k, v) in xs{
+                write_value(k, vec);
+                write_value(v, vec);
+            }
+        },
+    }
+}
+
+fn write_tagged<S: Sink + ?Sized>(tag: u8, bytes: &[u8], vec: &mut S){
+    vec.write_bytes(&[tag]);
+    vec.write_bytes(bytes);
+}
+// Write a length using the fix form when it fits, then the 8, 16 or 32 bit form.
+// Arrays and maps have no 8 bit form (tag 0), their fix forms hold up to 15 instead of 31.
+fn write_len<S: Sink + ?Sized>(len: usize, fix: Option<u8>, tags: [u8; 3], vec: &mut S){
+    let fix_max = if tags[0] == 0 { 16 } else { 32 };
+    match fix{
+        Some(fix) if len < fix_max => vec.write_bytes(&[fix | len as u8]),
+        _ if tags[0] != 0 && len <= 0xff => vec.write_bytes(&[tags[0], len as u8]),
+        _ if len <= 0xffff => write_tagged(tags[1], &(len as u16).to_be_bytes(), vec),
+        _ => write_tagged(tags[2], &(len as u32).to_be_bytes(), vec),
+    }
+}
+
+fn take<const N: usize>(buf: &mut ReadBuffer) -> Option<[u8; N]>{
+    <[u8; N]>::try_from(buf.read_bytes(N)?).ok()
+}
+
+fn read_len(buf: &mut ReadBuffer, bits: u8) -> Option<usize>{
+    let len = match bits{
+        8 => u32::from(take::<1>(buf)?[0]),
+        16 => u32::from(u16::from_be_bytes(take(buf)?)),
+        _ => u32::from_be_bytes(take(buf)?),
+    };
+    let len = usize::try_from(len).ok()?;
+    if len > buf.limits().max_elements{
+        return Option::None;
+    }
+    Option::Some(len)
+}
+
+fn read_seq(buf: &mut ReadBuffer, len: usize) -> Option<Value>{
+    buf.nested(|buf|{
+        let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+        for _ in 0..len{
+            vec.push(read_value(buf)?);
+        }
+        Option::Some(Value::Seq(vec))
+    })
+}
+
+fn read_map(buf: &mut ReadBuffer, len: usize) -> Option<Value>{
+    buf.nested(|buf|{
+        let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+        for _ in 0..len{
+            vec.push((read_value(buf)?, read_value(buf)?));
+        }
+        Option::Some(Value::Map(vec))
+    })
+}
+
+fn read_str(buf: &mut ReadBuffer, len: usize) -> Option<Value>{
+    let bytes = buf.read_bytes(len)?;
+    std::str::from_utf8(bytes).ok().map(|s| Value::Str(s.to_owned()))
+}
+
+fn signed(x: i64) -> Option<Value>{
+    u64::try_from(x).ok().map(Value::UInt)
+}
+/// Read a MessagePack value.
+/// None for nil, booleans, negative integers and extension types, as buffers have no such values.
+pub fn read_value(buf: &mut ReadBuffer) -> Option<Value>{
+    let tag = take::<1>(buf)?[0];
+    match tag{
+        0x00..=0x7f => Option::Some(Value::UInt(u64::from(tag))),
+        0x80..=0x8f => read_map(buf, usize::from(tag & 0x0f)),
+        0x90..=0x9f => read_seq(buf, usize::from(tag & 0x0f)),
+        0xa0..=0xbf => read_str(buf, usize::from(tag & 0x1f)),
+        0xc4..=0xc6 => {
+            let len = read_len(buf, 8 << (tag - 0xc4))?;
+            Option::Some(Value::Bytes(buf.read_bytes(len)?.to_vec()))
+        },
+        0xca => Option::Some(Value::Float(f64::from(f32::from_be_bytes(take(buf)?)))),
+        0xcb => Option::Some(Value::Float(f64::from_be_bytes(take(buf)?))),
+        0xcc => Option::Some(Value::UInt(u64::from(take::<1>(buf)?[0]))),
+        0xcd => Option::Some(Value::UInt(u64::from(u16::from_be_bytes(take(buf)?)))),
+        0xce => Option::Some(Value::UInt(u64::from(u32::from_be_bytes(take(buf)?)))),
+        0xcf => Option::Some(Value::UInt(u64::from_be_bytes(take(buf)?))),
+        0xd0 => signed(i64::from(i8::from_be_bytes(take(buf)?))),
+        0xd1 => signed(i64::from(i16::from_be_bytes(take(buf)?))),
+        0xd2 => signed(i64::from(i32::from_be_bytes(take(buf)?))),
+        0xd3 => signed(i64::from_be_bytes(take(buf)?)),
+        0xd9..=0xdb => {
+            let len = read_len(buf, 8 << (tag - 0xd9))?;
+            read_str(buf, len)
+        },
+        0xdc => {
+            let len = read_len(buf, 16)?;
+            read_seq(buf, len)
+        },
+        0xdd => {
+            let len = read_len(buf, 32)?;
+            read_seq(buf, len)
+        },
+        0xde => {
+            let len = read_len(buf, 16)?;
+            read_map(buf, len)
+        },
+        0xdf => {
+            let len = read_len(buf, 32)?;
+            read_map(buf, len)
+        },
+        _ => Option::None,
+    }
+}
+/// Convert an encoded buffer with the given schema to MessagePack.
+/// None if the buffer does not hold exactly one such value.
+pub fn to_msgpack(schema: &Schema, buffer: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::from_raw(buffer.to_vec());
+    let value = schema.decode(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    let mut vec = Vec::new();
+    write_value(&value, &mut vec);
+    Option::Some(vec)
+}
+/// Convert a buffer written with SelfDescribing to MessagePack, leaving out the schema.
+pub fn self_describing_to_msgpack(buffer: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::from_raw(buffer.to_vec());
+    let (_, value) = read_self_describing(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    let mut vec = Vec::new();
+    write_value(&value, &mut vec);
+    Option::Some(vec)
+}
+/// Convert MessagePack to an encoded buffer with the given schema.
+/// None if it is not valid MessagePack or does not fit the schema.
+pub fn from_msgpack(schema: &Schema, packed: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::from_raw(packed.to_vec());
+    let value = read_value(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    let mut vec = Vec::new();
+    if !schema.encode(&value, &mut vec){
+        return Option::None;
+    }
+    Option::Some(vec)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::schema::*;
+    use crate::msgpack::*;
+
+    #[test]
+    fn test_msgpack(){
+        type T = ((u8, u16, u64, f32), (String, Vec<u32>, ByteVec));
+        let x: T = ((200, 1000, 1 << 40, 0.5), ("x".repeat(40), (0..20).collect(), ByteVec(vec![7; 3])));
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        let packed = to_msgpack(&T::schema(), &buffer).unwrap();
+        // what rmp-serde gives for the same values, with the f32 as a f64
+        let mut expected = vec![0x92, 0x94, 0xcc, 200, 0xcd, 0x03, 0xe8, 0xcf, 0, 0, 1, 0, 0, 0, 0, 0,
+            0xcb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0, 0x93, 0xd9, 40];
+        expected.extend_from_slice("x".repeat(40).as_bytes());
+        expected.extend_from_slice(&[0xdc, 0, 20]);
+        expected.extend(0..20u8);
+        expected.extend_from_slice(&[0xc4, 3, 7, 7, 7]);
+        assert_eq!(expected, packed);
+        assert_eq!(Some(buffer), from_msgpack(&T::schema(), &packed));
+        // maps
+        let mut map = HashMap::new();
+        map.insert(String::from("a"), 1u8);
+        let mut buffer = Vec::new();
+        SelfDescribing(map).into_buffer(&mut buffer);
+        assert_eq!(Some(vec![0x81, 0xa1, b'a', 1]), self_describing_to_msgpack(&buffer));
+        // a signed int that is not negative is fine, a negative one and nil are not
+        assert_eq!(Some(vec![5]), from_msgpack(&Schema::U8, &[0xd0, 5]));
+        assert_eq!(None, from_msgpack(&Schema::U8, &[0xff]));
+        assert_eq!(None, from_msgpack(&Schema::U8, &[0xc0]));
+        // too big for the schema
+        assert_eq!(None, from_msgpack(&Schema::U8, &[0xcd, 1, 0]));
+    }
+}
@@ -0,0 +1,215 @@
+//! Name-value encoding, for config files and other structs whose fields get reordered.
+//! A message starts with a table of the field names, each written once, followed by
+//! the fields as the index of their name, their length in bytes and the value.
+//! Readers look fields up by name, so the order they are written in does not matter.
+//! Like the tagged module, unknown names are skipped and missing fields get a default.
+//! This takes more room than tags, in exchange for not having to keep numbers in sync.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::named::*;
+//! let mut writer = NamedWriter::new();
+//! writer.field("height", &600u32);
+//! writer.field("width", &800u32);
+//! writer.field("title", &String::from("game"));
+//! let mut buffer = Vec::new();
+//! writer.finish(&mut buffer);
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! let fields = NamedFields::from_buffer(&mut buffer).unwrap();
+//! assert_eq!(Some(800u32), fields.get("width"));
+//! assert_eq!(Some(600u32), fields.get("height"));
+//! assert_eq!(Some(0u32), fields.get_or_default("depth"));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer, Sink };
+
+/// Writes the fields of one named message.
+pub struct NamedWriter{
+    names: Vec<String>,
+    body: Buffer,
+}
+
+impl NamedWriter{
+    /// Start an empty message.
+    pub fn new() -> Self{
+        Self{ names: Vec::new(), body: Vec::new() }
+    }
+    // Index of name in the table, adding it if it is new.
+    fn intern(&mut self, name: &str) -> u32{
+        match self.names.iter().position(|n| n == name){
+            Option::Some(index) => index as u32,
+            Option::None => {
+                self.names.push(String::from(name));
+                (self.names.len() - 1) as u32
+            },
+        }
+    }
+    /// Write x as field name.
+    pub fn field<T: Bufferable>(&mut self, name: &str, x: &T) -> &mut Self{
+        let index = self.intern(name);
+        index.into_buffer(&mut self.body);
+        let len_at = self.body.len();
+        0u64.into_buffer(&mut self.body);
+        x.copy_into_buffer(&mut self.body);
+        let len = (self.body.len() - len_at - 8) as u64;
+        self.body[len_at..len_at + 8].copy_from_slice(&len.to_be_bytes());
+        self
+    }
+    /// Write x as field name, unless it is the default value.
+    /// Readers using get_or_default get the same value back either way.
+    pub fn field_or_skip<T: Bufferable + Default + PartialEq>(&mut self, name: &str, x: &T) -> &mut Self{
+        if *x != T::default(){
+            self.field(name, x);
+        }
+        self
+    }
+    /// Write the message: its length in bytes, the names and then the fields.
+    pub fn finish<S: Sink + ?Sized>(self, vec: &mut S){
+        write_message(&self.names, &self.body, vec);
+    }
+}
+
+impl Default for NamedWriter{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+fn write_message<S: Sink + ?Sized>(names: &[String], body: &[u8], vec: &mut S){
+    let names_len = 8 + String::slice_buffer_len(names);
+    ((names_len + body.len()) as u64).into_buffer(vec);
+    (names.len() as u64).into_buffer(vec);
+    String::copy_slice_into_buffer(names, vec);
+    vec.write_bytes(body);
+}
+
+/// The fields of a named message that has been read.
+/// If a name appears more than once the last one counts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NamedFields{
+    names: Vec<String>,
+    body: Buffer,
+    fields: Vec<(u32, usize, usize)>,
+}
+
+impl NamedFields{
+    /// The bytes of field name, None if it is not there.
+    pub fn raw(&self, name: &str) -> Option<&[u8]>{
+        let index = self.names.iter().position(|n| n == name)? as u32;
+        let (_, start, end) = self.fields.iter().rev().find(|(i, _, _)| *i == index)?;
+        Option::Some(&self.body[*start..*end])
+    }
+    /// If field name is there.
+    pub fn contains(&self, name: &str) -> bool{
+        self.raw(name).is_some()
+    }
+    /// The names of the fields, in the order they were written.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_{
+        self.fields.iter().map(move |(i, _, _)| self.names[*i as usize].as_str())
+    }
+    /// Read field name. None if it is missing or does not decode as a T.
+    pub fn get<T: Bufferable>(&self, name: &str) -> Option<T>{
+        let mut buf = ReadBuffer::borrowed(self.raw(name)?, Default::default());
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// Read field name, or T::default() if it is missing.
+    /// None only if the field is there but does not decode as a T.
+    pub fn get_or_default<T: Bufferable + Default>(&self, name: &str) -> Option<T>{
+        if self.contains(name){
+            self.get(name)
+        } else {
+            Option::Some(T::default())
+        }
+    }
+}
+
+impl Bufferable for NamedFields{
+    const MIN_BUFFER_LEN: usize = 16;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        write_message(&self.names, &self.body, vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let message = buf.read_blob()?.to_vec();
+        buf.use_bytes(message.len())?;
+        let mut message = ReadBuffer::from_raw(message).with_limits(*buf.limits());
+        let names = Vec::<String>::from_buffer(&mut message)?;
+        let body = message.as_slice()[message.consumed()..].to_vec();
+        let mut fields = Vec::new();
+        let mut rest = ReadBuffer::borrowed(&body, Default::default());
+        while rest.remaining() > 0{
+            let index = u32::from_buffer(&mut rest)?;
+            if index as usize >= names.len(){
+                return Option::None;
+            }
+            let field_len = usize::try_from(u64::from_buffer(&mut rest)?).ok()?;
+            let start = rest.consumed();
+            rest.read_bytes(field_len)?;
+            fields.push((index, start, start + field_len));
+        }
+        Option::Some(Self{ names, body, fields })
+    }
+
+    fn buffer_len(&self) -> usize{
+        16 + String::slice_buffer_len(&self.names) + self.body.len()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::named::*;
+
+    #[test]
+    fn test_named(){
+        let mut writer = NamedWriter::new();
+        writer.field("list", &vec![1u16,2,3])
+            .field_or_skip("zero", &0u8)
+            .field_or_skip("kept", &String::from("yes"))
+            .field("list", &vec![4u16]);
+        let mut buffer = Vec::new();
+        writer.finish(&mut buffer);
+        5u8.into_buffer(&mut buffer);
+        // "list" is in the names once, even though it is written twice
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        u64::from_buffer(&mut read);
+        assert_eq!(Some(vec![String::from("list"), String::from("kept")]), Vec::<String>::from_buffer(&mut read));
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        let fields = NamedFields::from_buffer(&mut read).unwrap();
+        assert_eq!(Some(5), u8::from_buffer(&mut read));
+        assert_eq!(vec!["list", "kept", "list"], fields.names().collect::<Vec<_>>());
+        assert_eq!(Some(vec![4u16]), fields.get("list"));
+        assert!(!fields.contains("zero"));
+        assert_eq!(Some(0u8), fields.get_or_default("zero"));
+        assert_eq!(None, fields.get::<u64>("kept"));
+        // the same fields in another order read the same
+        let mut writer = NamedWriter::new();
+        writer.field("kept", &String::from("yes")).field("list", &vec![4u16]);
+        let mut other = Vec::new();
+        writer.finish(&mut other);
+        let other = NamedFields::from_buffer(&mut ReadBuffer::from_raw(other)).unwrap();
+        assert_eq!(fields.get::<String>("kept"), other.get("kept"));
+        assert_eq!(fields.get::<Vec<u16>>("list"), other.get("list"));
+        // writing the fields back gives the same bytes
+        let mut again = Vec::new();
+        fields.copy_into_buffer(&mut again);
+        assert_eq!(&buffer[..buffer.len() - 1], &again[..]);
+        assert_eq!(fields.buffer_len(), again.len());
+        // a name index outside the table
+        let mut bad = Vec::new();
+        20u64.into_buffer(&mut bad);
+        0u64.into_buffer(&mut bad);
+        0u32.into_buffer(&mut bad);
+        0u64.into_buffer(&mut bad);
+        assert_eq!(None, NamedFields::from_buffer(&mut ReadBuffer::from_raw(bad)));
+    }
+}
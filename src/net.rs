@@ -0,0 +1,350 @@
+//! Sending values over streams such as a TcpStream.
+//! Every value goes in its own frame: a u64 length followed by the encoded value,
+//! so the reader knows how much to wait for before decoding.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::net::*;
+//! let mut stream = Vec::new();
+//! send_over(&(1u32, String::from("hi")), &mut stream).unwrap();
+//! send_over(&2u8, &mut stream).unwrap();
+//! let mut stream = std::io::Cursor::new(stream);
+//! assert_eq!((1u32, String::from("hi")), recv_from::<(u32,String), _>(&mut stream).unwrap());
+//! assert_eq!(2u8, recv_from::<u8, _>(&mut stream).unwrap());
+//! ```
+//! The same is available on every Read and Write through ReadBufferableExt and WriteBufferableExt.
+//! DatagramCodec does the same for UdpSocket, one value per datagram.
+use std::io::{ self, Read, Write };
+use std::convert::TryFrom;
+use std::net::{ SocketAddr, TcpStream, ToSocketAddrs, UdpSocket };
+use std::time::{ Duration, Instant };
+use crate::{ Buffer, Bufferable, ReadBuffer };
+
+/// Largest frame recv_from accepts, 16 MiB.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 << 20;
+
+/// Write bytes as one frame.
+pub fn write_frame<W: Write + ?Sized>(bytes: &[u8], w: &mut W) -> io::Result<()>{
+    w.write_all(&(bytes.len() as u64).to_be_bytes())?;
+    w.write_all(bytes)?;
+    w.flush()
+}
+/// Read one frame. Fails with InvalidData if it is longer than max_len,
+/// with UnexpectedEof if the stream ends inside it
+/// and with TimedOut if a read timeout set on the stream runs out.
+/// The buffer grows as the bytes arrive, a length alone does not allocate it.
+pub fn read_frame<R: Read + ?Sized>(r: &mut R, max_len: usize) -> io::Result<Buffer>{
+    let mut len = [0u8; 8];
+    r.read_exact(&mut len).map_err(timed_out)?;
+    let len = usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX);
+    if len > max_len{
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+    let mut vec = Vec::new();
+    r.take(len as u64).read_to_end(&mut vec).map_err(timed_out)?;
+    if vec.len() < len{
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ends inside the frame"));
+    }
+    Ok(vec)
+}
+// Sockets report a read timeout as WouldBlock on unix, make that TimedOut everywhere.
+fn timed_out(e: io::Error) -> io::Error{
+    match e.kind(){
+        io::ErrorKind::WouldBlock => io::Error::new(io::ErrorKind::TimedOut, e),
+        _ => e,
+    }
+}
+// Reads from a stream until a deadline, every read gets the time that is left.
+struct Deadline<'a>{
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline<'_>{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>{
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left == Duration::from_secs(0){
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "deadline passed"));
+        }
+        self.stream.set_read_timeout(Option::Some(left))?;
+        (&*self.stream).read(buf)
+    }
+}
+/// Encode x and write it as one frame.
+pub fn send_over<T: Bufferable, W: Write + ?Sized>(x: &T, w: &mut W) -> io::Result<()>{
+    let mut buffer = Vec::with_capacity(x.buffer_len());
+    x.copy_into_buffer(&mut buffer);
+    write_frame(&buffer, w)
+}
+/// Read one frame of at most DEFAULT_MAX_FRAME_LEN bytes and decode it.
+/// Fails with InvalidData if the frame does not hold exactly one T.
+pub fn recv_from<T: Bufferable, R: Read + ?Sized>(r: &mut R) -> io::Result<T>{
+    recv_from_with_max(r, DEFAULT_MAX_FRAME_LEN)
+}
+/// recv_from with a custom largest frame length.
+pub fn recv_from_with_max<T: Bufferable, R: Read + ?Sized>(r: &mut R, max_len: usize) -> io::Result<T>{
+    decode_frame(read_frame(r, max_len)?)
+}
+
+pub(crate) fn decode_frame<T: Bufferable>(frame: Buffer) -> io::Result<T>{
+    let mut buffer = ReadBuffer::from_raw(frame);
+    match T::from_buffer(&mut buffer){
+        Some(x) if buffer.remaining() == 0 => Ok(x),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "frame does not hold the expected value")),
+    }
+}
+/// recv_from with a read timeout on the stream, so a peer that stops halfway
+/// through a frame can not block forever. The timeout is for the whole frame,
+/// a peer that sends a byte now and then does not keep it going.
+/// The stream's own timeout is put back afterwards.
+/// After a TimedOut error the stream may be inside a frame and should be closed.
+/// # Example
+/// ```
+/// use bin_buffer::net::*;
+/// use std::io::Write;
+/// use std::time::Duration;
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+/// let (mut server, _) = listener.accept().unwrap();
+/// send_over(&7u32, &mut client).unwrap();
+/// assert_eq!(7u32, recv_timeout::<u32>(&mut server, Duration::from_secs(5)).unwrap());
+/// // only half a frame
+/// client.write_all(&[0, 0, 0, 0, 0, 0, 0, 4, 1]).unwrap();
+/// let err = recv_timeout::<u32>(&mut server, Duration::from_millis(50)).unwrap_err();
+/// assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+/// ```
+pub fn recv_timeout<T: Bufferable>(stream: &mut TcpStream, timeout: Duration) -> io::Result<T>{
+    let old = stream.read_timeout()?;
+    let res = recv_from(&mut Deadline{ stream, deadline: Instant::now() + timeout });
+    stream.set_read_timeout(old)?;
+    res
+}
+
+/// Read framed values from anything that is Read: files, sockets, cursors.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut file = Vec::new();
+/// file.write_bufferable(&String::from("a"))?.write_bufferable(&vec![1u16, 2])?;
+/// let mut file = std::io::Cursor::new(file);
+/// assert_eq!(String::from("a"), file.read_bufferable::<String>().unwrap());
+/// assert_eq!(vec![1u16, 2], file.read_bufferable::<Vec<u16>>().unwrap());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub trait ReadBufferableExt: Read{
+    /// Read one frame and decode it, see recv_from.
+    fn read_bufferable<T: Bufferable>(&mut self) -> io::Result<T>{
+        recv_from(self)
+    }
+}
+
+impl<R: Read + ?Sized> ReadBufferableExt for R{}
+
+/// Write framed values to anything that is Write, see ReadBufferableExt.
+pub trait WriteBufferableExt: Write{
+    /// Encode x and write it as one frame, see send_over.
+    /// Gives back the writer, so frames can be chained: `w.write_bufferable(&x)?.write_bufferable(&y)?`.
+    fn write_bufferable<T: Bufferable>(&mut self, x: &T) -> io::Result<&mut Self>{
+        send_over(x, self)?;
+        Ok(self)
+    }
+}
+
+impl<W: Write + ?Sized> WriteBufferableExt for W{}
+
+/// Datagram size that fits through about any network without fragmenting.
+pub const DEFAULT_MTU: usize = 1200;
+
+/// Encodes values into datagrams of at most mtu bytes and decodes received ones.
+/// A sequenced codec puts a u32 sequence number in front of every value,
+/// so the receiving side can spot datagrams that arrive late or twice.
+/// # Example
+/// ```
+/// use bin_buffer::net::*;
+/// let mut sender = DatagramCodec::sequenced(DEFAULT_MTU);
+/// let mut receiver = DatagramCodec::sequenced(DEFAULT_MTU);
+/// let first = sender.encode(&(1.0f32, 2.0f32)).unwrap();
+/// let second = sender.encode(&(1.5f32, 2.5f32)).unwrap();
+/// let d = receiver.decode::<(f32,f32)>(&second).unwrap();
+/// assert_eq!((Some(1), false), (d.seq, d.stale));
+/// let d = receiver.decode::<(f32,f32)>(&first).unwrap();
+/// assert_eq!((Some(0), true), (d.seq, d.stale));
+/// assert!(sender.encode(&vec![0u8; 2000]).is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct DatagramCodec{
+    mtu: usize,
+    sequenced: bool,
+    next_seq: u32,
+    latest: Option<u32>,
+}
+
+/// A decoded datagram.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Datagram<T>{
+    /// The value it held.
+    pub value: T,
+    /// Its sequence number, None for a codec that is not sequenced.
+    pub seq: Option<u32>,
+    /// Whether a datagram with the same or a later sequence number was decoded before.
+    pub stale: bool,
+}
+
+impl DatagramCodec{
+    /// Codec without sequence numbers.
+    pub fn new(mtu: usize) -> Self{
+        Self{ mtu, sequenced: false, next_seq: 0, latest: Option::None }
+    }
+    /// Codec with a sequence number header.
+    pub fn sequenced(mtu: usize) -> Self{
+        Self{ sequenced: true, ..Self::new(mtu) }
+    }
+    /// Largest datagram it writes or accepts.
+    pub fn mtu(&self) -> usize{
+        self.mtu
+    }
+    /// Encode x into a datagram, taking the next sequence number.
+    /// Fails with InvalidInput if it would be larger than the mtu.
+    pub fn encode<T: Bufferable>(&mut self, x: &T) -> io::Result<Buffer>{
+        let header = if self.sequenced { 4 } else { 0 };
+        let len = header + x.buffer_len();
+        if len > self.mtu{
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "value does not fit the mtu"));
+        }
+        let mut buffer = Vec::with_capacity(len);
+        if self.sequenced{
+            self.next_seq.into_buffer(&mut buffer);
+            self.next_seq = self.next_seq.wrapping_add(1);
+        }
+        x.copy_into_buffer(&mut buffer);
+        Ok(buffer)
+    }
+    /// Decode a datagram. Fails with InvalidData if it is larger than the mtu
+    /// or does not hold exactly one T. Sequence numbers wrap around.
+    pub fn decode<T: Bufferable>(&mut self, bytes: &[u8]) -> io::Result<Datagram<T>>{
+        if bytes.len() > self.mtu{
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "datagram larger than the mtu"));
+        }
+        if !self.sequenced{
+            let value = decode_frame(bytes.to_vec())?;
+            return Ok(Datagram{ value, seq: Option::None, stale: false });
+        }
+        let (seq, value) = decode_frame::<(u32,T)>(bytes.to_vec())?;
+        let stale = match self.latest{
+            Some(latest) => (seq.wrapping_sub(latest) as i32) <= 0,
+            None => false,
+        };
+        if !stale{
+            self.latest = Option::Some(seq);
+        }
+        Ok(Datagram{ value, seq: Option::Some(seq), stale })
+    }
+    /// Encode x and send it to addr.
+    pub fn send_to<T: Bufferable, A: ToSocketAddrs>(&mut self, x: &T, socket: &UdpSocket, addr: A) -> io::Result<()>{
+        let datagram = self.encode(x)?;
+        socket.send_to(&datagram, addr)?;
+        Ok(())
+    }
+    /// Receive a datagram and decode it.
+    pub fn recv_from<T: Bufferable>(&mut self, socket: &UdpSocket) -> io::Result<(Datagram<T>, SocketAddr)>{
+        // one byte extra, so a datagram over the mtu is not cut off silently
+        let mut buffer = vec![0u8; self.mtu + 1];
+        let (len, addr) = socket.recv_from(&mut buffer)?;
+        Ok((self.decode(&buffer[..len])?, addr))
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::net::*;
+
+    #[test]
+    fn test_net(){
+        let x = vec![String::from("a"); 100];
+        let mut stream = Vec::new();
+        send_over(&x, &mut stream).unwrap();
+        send_over(&1u16, &mut stream).unwrap();
+        assert_eq!(8 + x.buffer_len() + 8 + 2, stream.len());
+        let mut r = std::io::Cursor::new(stream.clone());
+        assert_eq!(x, recv_from::<Vec<String>, _>(&mut r).unwrap());
+        // a u8 does not fill the u16 frame
+        assert_eq!(io::ErrorKind::InvalidData, recv_from::<u8, _>(&mut r).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::UnexpectedEof, recv_from::<u8, _>(&mut r).unwrap_err().kind());
+        let mut r = std::io::Cursor::new(stream.clone());
+        assert_eq!(io::ErrorKind::InvalidData, recv_from_with_max::<Vec<String>, _>(&mut r, 10).unwrap_err().kind());
+        let mut r = std::io::Cursor::new(&stream[..20]);
+        assert_eq!(io::ErrorKind::UnexpectedEof, recv_from::<Vec<String>, _>(&mut r).unwrap_err().kind());
+        // over tcp, from another thread
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sender = std::thread::spawn(move ||{
+            let mut client = TcpStream::connect(addr).unwrap();
+            for i in 0..10u32{
+                send_over(&(i, String::from("x")), &mut client).unwrap();
+            }
+        });
+        let (mut server, _) = listener.accept().unwrap();
+        for i in 0..10u32{
+            assert_eq!((i, String::from("x")), recv_timeout(&mut server, Duration::from_secs(5)).unwrap());
+        }
+        sender.join().unwrap();
+        assert_eq!(None, server.read_timeout().unwrap());
+        // a byte every 20ms would keep a timeout per read going, the deadline is for the frame
+        let dripper = std::thread::spawn(move ||{
+            let mut client = TcpStream::connect(addr).unwrap();
+            let _ = client.write_all(&[0, 0, 0, 0, 0, 0, 0, 100]);
+            for _ in 0..100{
+                if client.write_all(&[0]).is_err(){
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let (mut server, _) = listener.accept().unwrap();
+        let start = Instant::now();
+        let err = recv_timeout::<Vec<u8>>(&mut server, Duration::from_millis(200)).unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        drop(server);
+        dripper.join().unwrap();
+        // the extension traits use the same frames, also through dyn
+        let mut stream = Vec::new();
+        stream.write_bufferable(&x).unwrap();
+        let w: &mut dyn Write = &mut stream;
+        w.write_bufferable(&3u8).unwrap();
+        let mut r = std::io::Cursor::new(stream);
+        assert_eq!(x, recv_from::<Vec<String>, _>(&mut r).unwrap());
+        let r: &mut dyn Read = &mut r;
+        assert_eq!(3u8, r.read_bufferable::<u8>().unwrap());
+    }
+
+    #[test]
+    fn test_datagram(){
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        b.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut sender = DatagramCodec::new(64);
+        let mut receiver = DatagramCodec::new(64);
+        sender.send_to(&String::from("state"), &a, b.local_addr().unwrap()).unwrap();
+        let (d, from) = receiver.recv_from::<String>(&b).unwrap();
+        assert_eq!(Datagram{ value: String::from("state"), seq: None, stale: false }, d);
+        assert_eq!(a.local_addr().unwrap(), from);
+        // 8 byte length and 56 bytes fit exactly
+        assert!(sender.encode(&"x".repeat(56)).is_ok());
+        assert_eq!(io::ErrorKind::InvalidInput, sender.encode(&"x".repeat(57)).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, receiver.decode::<u8>(&[0; 65]).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, receiver.decode::<u8>(&[0, 0]).unwrap_err().kind());
+        // sequence numbers, across the wrap around
+        let mut sender = DatagramCodec::sequenced(64);
+        sender.next_seq = u32::MAX;
+        let mut receiver = DatagramCodec::sequenced(64);
+        let first = sender.encode(&1u8).unwrap();
+        let second = sender.encode(&2u8).unwrap();
+        assert_eq!(vec![0xff, 0xff, 0xff, 0xff, 1], first);
+        assert_eq!(Datagram{ value: 1u8, seq: Some(u32::MAX), stale: false }, receiver.decode(&first).unwrap());
+        assert_eq!(Datagram{ value: 2u8, seq: Some(0), stale: false }, receiver.decode(&second).unwrap());
+        assert!(receiver.decode::<u8>(&second).unwrap().stale);
+        assert!(receiver.decode::<u8>(&first).unwrap().stale);
+    }
+}
@@ -0,0 +1,230 @@
+//! Writing and reading numeric arrays as NumPy .npy files, so dumps can be opened
+//! with numpy.load without a conversion script.
+//! The files are version 1.0 of the format: the magic "\x93NUMPY", the version, a u16 little
+//! endian header length and a header that is a Python dict literal with the element type,
+//! the order and the shape, padded so the data starts at a multiple of 64 bytes.
+//! The elements follow in row-major order, little endian.
+//! Reading also takes version 2.0 and 3.0 headers and big endian elements,
+//! but not arrays in Fortran order.
+//! # Example
+//! ```
+//! use bin_buffer::npy::*;
+//! let bytes = rows_to_npy(&[vec![1.0f32, 2.0, 3.0], vec![4.0, 5.0, 6.0]]).unwrap();
+//! assert_eq!(0, (bytes.len() - 6 * 4) % 64);
+//! let array = from_npy::<f32>(&bytes).unwrap();
+//! assert_eq!(vec![2, 3], array.shape);
+//! assert_eq!(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]], array.rows().unwrap());
+//! ```
+use std::convert::TryFrom;
+use crate::Buffer;
+
+/// First bytes of every .npy file.
+pub const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// A number type that .npy files can hold.
+pub trait NpyElement: Copy{
+    /// The type in the header without the byte order, like "f4" or "u2".
+    const KIND: &'static str;
+    /// Append the little endian bytes of self.
+    fn write_le(self, out: &mut Buffer);
+    /// Read one from the start of bytes, which are big endian if big.
+    fn read(bytes: &[u8], big: bool) -> Self;
+}
+
+macro_rules! npy_element{
+    ($t:ty, $kind:expr) => {
+        impl NpyElement for $t{
+            const KIND: &'static str = $kind;
+            fn write_le(self, out: &mut Buffer){
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn read(bytes: &[u8], big: bool) -> Self{
+                let mut b = [0u8; std::mem::size_of::<$t>()];
+                b.copy_from_slice(&bytes[..std::mem::size_of::<$t>()]);
+                if big { <$t>::from_be_bytes(b) } else { <$t>::from_le_bytes(b) }
+            }
+        }
+    };
+}
+
+npy_element!(u8, "u1");
+npy_element!(u16, "u2");
+npy_element!(u32, "u4");
+npy_element!(u64, "u8");
+npy_element!(i8, "i1");
+npy_element!(i16, "i2");
+npy_element!(i32, "i4");
+npy_element!(i64, "i8");
+npy_element!(f32, "f4");
+npy_element!(f64, "f8");
+
+/// An array read from a .npy file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpyArray<T>{
+    /// Length of every dimension, empty for a single number.
+    pub shape: Vec<usize>,
+    /// The elements in row-major order.
+    pub data: Vec<T>,
+}
+
+impl<T: NpyElement> NpyArray<T>{
+    /// The rows of a 2D array, None if it has another number of dimensions.
+    pub fn rows(&self) -> Option<Vec<Vec<T>>>{
+        if self.shape.len() != 2{
+            return Option::None;
+        }
+        if self.shape[1] == 0{
+            return Option::Some(vec![Vec::new(); self.shape[0]]);
+        }
+        Option::Some(self.data.chunks(self.shape[1]).map(|row| row.to_vec()).collect())
+    }
+}
+
+/// data as a .npy file of the given shape. None if the shape does not hold data.len() elements.
+pub fn to_npy<T: NpyElement>(data: &[T], shape: &[usize]) -> Option<Buffer>{
+    let count = shape.iter().try_fold(1usize, |n, &d| n.checked_mul(d))?;
+    if count != data.len(){
+        return Option::None;
+    }
+    let order = if T::KIND.ends_with('1') { '|' } else { '<' };
+    let dims: Vec<String> = shape.iter().map(|d| d.to_string()).collect();
+    let shape = if dims.len() == 1 { format!("({},)", dims[0]) } else { format!("({})", dims.join(", ")) };
+    let mut header = format!("{{'descr': '{}{}', 'fortran_order': False, 'shape': {}, }}", order, T::KIND, shape);
+    // the data starts at a multiple of 64, the header ends with a newline
+    let total = (MAGIC.len() + 4 + header.len() + 1).div_ceil(64) * 64;
+    while MAGIC.len() + 4 + header.len() + 1 < total{
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut out = Vec::with_capacity(total + std::mem::size_of_val(data));
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&u16::try_from(header.len()).ok()?.to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for &x in data{
+        x.write_le(&mut out);
+    }
+    Option::Some(out)
+}
+
+/// data as a one dimensional .npy file.
+pub fn vec_to_npy<T: NpyElement>(data: &[T]) -> Buffer{
+    to_npy(data, &[data.len()]).unwrap()
+}
+
+/// rows as a two dimensional .npy file. None if the rows are not all as long.
+pub fn rows_to_npy<T: NpyElement>(rows: &[Vec<T>]) -> Option<Buffer>{
+    let width = rows.first().map_or(0, |r| r.len());
+    if rows.iter().any(|r| r.len() != width){
+        return Option::None;
+    }
+    let data: Vec<T> = rows.iter().flatten().copied().collect();
+    to_npy(&data, &[rows.len(), width])
+}
+
+// The text after key in the header, up to the next ',' outside of brackets or the end.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str>{
+    let start = header.find(&format!("'{}':", key))? + key.len() + 3;
+    let rest = header[start..].trim_start();
+    let mut depth = 0;
+    for (i, c) in rest.char_indices(){
+        match c{
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' | '}' if depth == 0 => return Option::Some(rest[..i].trim()),
+            _ => {},
+        }
+    }
+    Option::None
+}
+
+/// Read a .npy file holding elements of type T. None if it is not a .npy file,
+/// holds another element type, is in Fortran order or has too few bytes.
+pub fn from_npy<T: NpyElement>(bytes: &[u8]) -> Option<NpyArray<T>>{
+    if bytes.get(..6)? != MAGIC{
+        return Option::None;
+    }
+    let (header_len, start): (usize, usize) = match bytes.get(6)?{
+        1 => (u16::from_le_bytes([*bytes.get(8)?, *bytes.get(9)?]) as usize, 10),
+        2 | 3 => (u32::from_le_bytes(<[u8; 4]>::try_from(bytes.get(8..12)?).ok()?) as usize, 12),
+        _ => return Option::None,
+    };
+    let header = std::str::from_utf8(bytes.get(start..start.checked_add(header_len)?)?).ok()?;
+    let descr = header_value(header, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+    let (order, kind) = descr.split_at(1.min(descr.len()));
+    let big = match order{
+        "<" | "|" | "=" => false,
+        ">" => true,
+        _ => return Option::None,
+    };
+    if kind != T::KIND || header_value(header, "fortran_order")? != "False"{
+        return Option::None;
+    }
+    let dims = header_value(header, "shape")?.strip_prefix('(')?.strip_suffix(')')?;
+    let shape = dims.split(',').map(|d| d.trim()).filter(|d| !d.is_empty())
+        .map(|d| d.parse::<usize>().ok()).collect::<Option<Vec<usize>>>()?;
+    let count = shape.iter().try_fold(1usize, |n, &d| n.checked_mul(d))?;
+    let size = std::mem::size_of::<T>();
+    let body = bytes.get(start + header_len..)?;
+    if body.len() / size < count{
+        return Option::None;
+    }
+    let data = body.chunks_exact(size).take(count).map(|b| T::read(b, big)).collect();
+    Option::Some(NpyArray{ shape, data })
+}
+
+/// Write data as a .npy file at path. False if the shape does not fit or the file could not be written.
+#[cfg(feature = "fs")]
+pub fn write_npy<T: NpyElement>(path: &std::path::Path, data: &[T], shape: &[usize]) -> bool{
+    to_npy(data, shape).is_some_and(|bytes| crate::buffer_write_file(path, &bytes))
+}
+
+/// Read the .npy file at path.
+#[cfg(feature = "fs")]
+pub fn read_npy<T: NpyElement>(path: &std::path::Path) -> Option<NpyArray<T>>{
+    from_npy(&crate::buffer_read_file(path)?)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::npy::*;
+
+    #[test]
+    fn test_npy(){
+        let bytes = vec_to_npy(&[1i32, -2, 3]);
+        let header = std::str::from_utf8(&bytes[10..bytes.len() - 12]).unwrap();
+        assert_eq!("{'descr': '<i4', 'fortran_order': False, 'shape': (3,), }", header.trim_end());
+        assert!(header.ends_with(" \n"));
+        assert_eq!(128, bytes.len() - 12);
+        assert_eq!(vec![1, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff], bytes[128..136].to_vec());
+        assert_eq!(Some(NpyArray{ shape: vec![3], data: vec![1, -2, 3] }), from_npy::<i32>(&bytes));
+        assert_eq!(None, from_npy::<u32>(&bytes));
+        assert_eq!(None, from_npy::<i32>(&bytes[..bytes.len() - 1]));
+        // a single number, an empty array and a ragged one
+        let scalar = to_npy(&[2.5f64], &[]).unwrap();
+        assert!(std::str::from_utf8(&scalar[10..scalar.len() - 8]).unwrap().contains("'shape': ()"));
+        assert_eq!(Some(NpyArray{ shape: vec![], data: vec![2.5] }), from_npy(&scalar));
+        let empty = rows_to_npy::<u8>(&[Vec::new(), Vec::new()]).unwrap();
+        assert!(String::from_utf8_lossy(&empty).contains("'descr': '|u1'"));
+        assert_eq!(Some(vec![Vec::new(), Vec::new()]), from_npy::<u8>(&empty).unwrap().rows());
+        assert_eq!(None, rows_to_npy(&[vec![1u16], vec![]]));
+        assert_eq!(None, to_npy(&[1u16], &[2]));
+        // as numpy writes a big endian 2x2 array, with a version 1.0 header padded to 16 like older numpy
+        let mut be = b"\x93NUMPY\x01\x00\x46\x00{'descr': '>u2', 'fortran_order': False, 'shape': (2, 2), }          \n".to_vec();
+        assert_eq!(80, be.len());
+        be.extend_from_slice(&[0, 1, 0, 2, 0, 3, 1, 0]);
+        let array = from_npy::<u16>(&be).unwrap();
+        assert_eq!(Some(vec![vec![1, 2], vec![3, 256]]), array.rows());
+        let mut fortran = be.clone();
+        fortran[44..49].copy_from_slice(b"True ");
+        assert_eq!(None, from_npy::<u16>(&fortran));
+        #[cfg(feature = "fs")]
+        {
+            let path = std::env::temp_dir().join("bin_buffer_npy_test.npy");
+            assert!(write_npy(&path, &[1.5f32, 2.5], &[2, 1]));
+            assert_eq!(Some(vec![vec![1.5], vec![2.5]]), read_npy::<f32>(&path).unwrap().rows());
+            assert!(!write_npy(&path, &[1.5f32], &[2, 1]));
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
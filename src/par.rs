@@ -0,0 +1,64 @@
+//! Parallel encoding of large slices, enabled with the `rayon` feature.
+use crate::{ Buffer, Bufferable, EndianSink, Sink };
+use rayon::prelude::*;
+
+/// Below this many elements a slice is encoded on the current thread.
+const MIN_CHUNK: usize = 4096;
+
+/// Copy a slice to the end of the buffer like Vec::copy_into_buffer does,
+/// encoding chunks of it on the rayon thread pool and joining them in order.
+/// The output is byte for byte the same as the sequential encoding, in the byte order of buf.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let x: Vec<u32> = (0..100_000).collect();
+/// let mut par = Vec::new();
+/// par::par_copy_into_buffer(&x, &mut par);
+/// let mut seq = Vec::new();
+/// x.copy_into_buffer(&mut seq);
+/// assert_eq!(seq, par);
+/// ```
+pub fn par_copy_into_buffer<T: Bufferable + Sync, S: Sink + ?Sized>(slice: &[T], buf: &mut S){
+    (slice.len() as u64).into_buffer(buf);
+    let chunk = (slice.len() / (rayon::current_num_threads() * 4)).max(MIN_CHUNK);
+    if slice.len() <= chunk{
+        T::copy_slice_into_buffer(slice, buf);
+        return;
+    }
+    let endian = buf.endian();
+    let parts: Vec<Buffer> = slice.par_chunks(chunk).map(|part|{
+        let mut vec = EndianSink::new(Vec::with_capacity(T::slice_buffer_len(part)), endian);
+        T::copy_slice_into_buffer(part, &mut vec);
+        vec.into_inner()
+    }).collect();
+    buf.reserve(parts.iter().map(Vec::len).sum());
+    for part in parts{
+        buf.write_bytes(&part);
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    #[test]
+    fn test_par_copy_into_buffer(){
+        let x: Vec<(u64,String)> = (0..20_000).map(|i| (i, i.to_string())).collect();
+        let mut seq = Vec::new();
+        x.copy_into_buffer(&mut seq);
+        let mut par = Vec::new();
+        1u8.into_buffer(&mut par);
+        par::par_copy_into_buffer(&x, &mut par);
+        assert_eq!(seq[..], par[1..]);
+        let mut buffer = ReadBuffer::from_raw(par);
+        assert_eq!(Some(1), u8::from_buffer(&mut buffer));
+        assert_eq!(Some(x.clone()), Vec::<(u64,String)>::from_buffer(&mut buffer));
+        // little endian
+        let mut seq = EndianSink::new(Vec::new(), Endian::Little);
+        x.copy_into_buffer(&mut seq);
+        let mut par = EndianSink::new(Vec::new(), Endian::Little);
+        par::par_copy_into_buffer(&x, &mut par);
+        assert_eq!(seq.into_inner(), par.get_ref()[..]);
+        let mut buffer = ReadBuffer::from_raw(par.into_inner()).with_endian(Endian::Little);
+        assert_eq!(Some(x), Vec::<(u64,String)>::from_buffer(&mut buffer));
+    }
+}
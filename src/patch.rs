@@ -0,0 +1,179 @@
+//! Patches that turn one buffer into another, so an update only has to ship what changed.
+//! make_patch cuts the old buffer into blocks and looks for them in the new one with
+//! a rolling hash, the way rsync does. The patch is the old and new length followed by
+//! operations: copy a range of the old buffer (tag 0, offset u64, length u64) or
+//! insert bytes (tag 1, a length u64 and the bytes).
+//! # Example
+//! ```
+//! use bin_buffer::patch::*;
+//! let old: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+//! let mut new = old.clone();
+//! new[5000..5004].copy_from_slice(b"edit");
+//! new.extend_from_slice(b"more at the end");
+//! let patch = make_patch(&old, &new);
+//! assert!(patch.len() < 200);
+//! assert_eq!(Some(new), apply_patch(&old, &patch));
+//! ```
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+
+/// Block length make_patch uses. Changes are found to within about this many bytes.
+pub const DEFAULT_BLOCK_LEN: usize = 64;
+
+const COPY: u8 = 0;
+const INSERT: u8 = 1;
+const BASE: u64 = 0x100_0000_01b3;
+
+fn hash(block: &[u8]) -> u64{
+    block.iter().fold(0u64, |h, &b| h.wrapping_mul(BASE).wrapping_add(b as u64))
+}
+
+struct Ops{
+    patch: Buffer,
+    // copy that is not written yet, so copies of neighbouring blocks become one
+    copy: Option<(usize, usize)>,
+}
+
+impl Ops{
+    fn copy(&mut self, pos: usize, len: usize){
+        match &mut self.copy{
+            Option::Some((start, l)) if *start + *l == pos => *l += len,
+            _ => {
+                self.flush();
+                self.copy = Option::Some((pos, len));
+            },
+        }
+    }
+    fn insert(&mut self, bytes: &[u8]){
+        if bytes.is_empty(){
+            return;
+        }
+        self.flush();
+        INSERT.into_buffer(&mut self.patch);
+        (bytes.len() as u64).into_buffer(&mut self.patch);
+        self.patch.extend_from_slice(bytes);
+    }
+    fn flush(&mut self){
+        if let Option::Some((pos, len)) = self.copy.take(){
+            COPY.into_buffer(&mut self.patch);
+            (pos as u64).into_buffer(&mut self.patch);
+            (len as u64).into_buffer(&mut self.patch);
+        }
+    }
+}
+
+/// A patch from old to new, using DEFAULT_BLOCK_LEN.
+pub fn make_patch(old: &[u8], new: &[u8]) -> Buffer{
+    make_patch_with_block_len(old, new, DEFAULT_BLOCK_LEN)
+}
+
+/// A patch from old to new. Smaller blocks find more of old in new,
+/// bigger ones make the patch faster to make. Panics if block_len is 0.
+pub fn make_patch_with_block_len(old: &[u8], new: &[u8], block_len: usize) -> Buffer{
+    assert!(block_len > 0, "block length is 0");
+    let mut blocks: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, block) in old.chunks_exact(block_len).enumerate(){
+        blocks.entry(hash(block)).or_default().push(i * block_len);
+    }
+    let mut ops = Ops{ patch: Vec::new(), copy: Option::None };
+    (old.len() as u64).into_buffer(&mut ops.patch);
+    (new.len() as u64).into_buffer(&mut ops.patch);
+    // BASE to the power block_len - 1, to take the first byte out of the hash
+    let top = (1..block_len).fold(1u64, |p, _| p.wrapping_mul(BASE));
+    let mut literal = 0;
+    let mut pos = 0;
+    let mut h = Option::None;
+    while pos + block_len <= new.len(){
+        let window = &new[pos..pos + block_len];
+        let current = *h.get_or_insert_with(|| hash(window));
+        let found = blocks.get(&current)
+            .and_then(|starts| starts.iter().find(|&&s| &old[s..s + block_len] == window));
+        if let Option::Some(&start) = found{
+            // grow the match past the block as far as the bytes keep agreeing
+            let mut len = block_len;
+            while start + len < old.len() && pos + len < new.len() && old[start + len] == new[pos + len]{
+                len += 1;
+            }
+            ops.insert(&new[literal..pos]);
+            ops.copy(start, len);
+            pos += len;
+            literal = pos;
+            h = Option::None;
+        } else {
+            if pos + block_len < new.len(){
+                h = Option::Some(current.wrapping_sub((new[pos] as u64).wrapping_mul(top))
+                    .wrapping_mul(BASE).wrapping_add(new[pos + block_len] as u64));
+            }
+            pos += 1;
+        }
+    }
+    ops.insert(&new[literal..]);
+    ops.flush();
+    ops.patch
+}
+
+/// Apply a patch made by make_patch to old. None if the patch is broken
+/// or was made for a buffer of another length.
+pub fn apply_patch(old: &[u8], patch: &[u8]) -> Option<Buffer>{
+    let mut buf = ReadBuffer::borrowed(patch, Default::default());
+    let old_len = usize::try_from(u64::from_buffer(&mut buf)?).ok()?;
+    let new_len = usize::try_from(u64::from_buffer(&mut buf)?).ok()?;
+    if old_len != old.len(){
+        return Option::None;
+    }
+    let mut new = Vec::with_capacity(new_len.min(old.len() + patch.len()));
+    while buf.remaining() > 0{
+        match u8::from_buffer(&mut buf)?{
+            COPY => {
+                let pos = usize::try_from(u64::from_buffer(&mut buf)?).ok()?;
+                let len = usize::try_from(u64::from_buffer(&mut buf)?).ok()?;
+                new.extend_from_slice(old.get(pos..pos.checked_add(len)?)?);
+            },
+            INSERT => new.extend_from_slice(buf.read_blob()?),
+            _ => return Option::None,
+        }
+        if new.len() > new_len{
+            return Option::None;
+        }
+    }
+    if new.len() != new_len{
+        return Option::None;
+    }
+    Option::Some(new)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::patch::*;
+
+    #[test]
+    fn test_patch(){
+        let mut seed = 1u32;
+        let old: Vec<u8> = (0..50_000).map(|_|{ seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345); (seed >> 16) as u8 }).collect();
+        // moved blocks, a deletion, an insertion and a changed byte
+        let mut new = Vec::new();
+        new.extend_from_slice(&old[30_000..40_000]);
+        new.extend_from_slice(&old[..10_000]);
+        new.extend_from_slice(b"inserted bytes");
+        new.extend_from_slice(&old[20_000..]);
+        new[100] ^= 0xff;
+        let patch = make_patch(&old, &new);
+        assert!(patch.len() < 400, "{}", patch.len());
+        assert_eq!(Some(new.clone()), apply_patch(&old, &patch));
+        for block_len in [1, 7, 4096, 100_000].iter(){
+            let patch = make_patch_with_block_len(&old, &new, *block_len);
+            assert_eq!(Some(new.clone()), apply_patch(&old, &patch));
+        }
+        // empty buffers either way
+        assert_eq!(Some(Vec::new()), apply_patch(&old, &make_patch(&old, &[])));
+        assert_eq!(Some(new.clone()), apply_patch(&[], &make_patch(&[], &new)));
+        // the wrong old buffer, and a patch cut short
+        assert_eq!(None, apply_patch(&old[1..], &patch));
+        assert_eq!(None, apply_patch(&old, &patch[..patch.len() - 1]));
+        let mut bad = make_patch(&old, &old);
+        assert_eq!(1 + 8 + 8 + 16, bad.len());
+        bad[16] = 2;
+        assert_eq!(None, apply_patch(&old, &bad));
+    }
+}
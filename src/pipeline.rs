@@ -0,0 +1,237 @@
+//! Stages a buffer goes through after it is encoded, like compress, encrypt, checksum and frame,
+//! put together in a declared order around encode and decode.
+//! The header lists the stages, so a reader that knows all of them configures itself from it.
+//! A Pipeline writes the stage count (u8) and the id of every stage (u16 each), in the order
+//! they were applied, and then the bytes they made. Reading undoes them from last to first.
+//! The crate has stages for checksums and frames, with the `entropy` feature for Huffman coding
+//! and with the `compression` feature for LZ4.
+//! Encryption, or another compressor, is a Stage of your own: ids from 0x100 on are free.
+//! # Example
+//! ```
+//! use bin_buffer::pipeline::*;
+//! let writer = Pipeline::new().then(Crc32).then(Frame);
+//! let bytes = writer.encode(&(1u32, String::from("hi")));
+//! // a reader that knows the stages, in any order, follows the header
+//! let reader = Pipeline::new().then(Frame).then(Crc32);
+//! assert_eq!(Some((1u32, String::from("hi"))), reader.decode(&bytes));
+//! assert_eq!(vec![CRC32, FRAME], Pipeline::stages_of(&bytes).unwrap());
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer, Sink };
+use crate::checksum::crc32;
+
+/// Id of Crc32.
+pub const CRC32: u16 = 1;
+/// Id of Frame.
+pub const FRAME: u16 = 2;
+/// Id of Huffman.
+pub const HUFFMAN: u16 = 3;
+/// Id of Lz4.
+pub const LZ4: u16 = 4;
+
+/// A step bytes go through on the way out, and back on the way in.
+pub trait Stage{
+    /// Id of the stage in the header.
+    fn id(&self) -> u16;
+    /// Transform bytes on the way out.
+    fn apply(&self, bytes: Buffer) -> Buffer;
+    /// Undo apply, None if the bytes do not check out.
+    fn undo(&self, bytes: Buffer) -> Option<Buffer>;
+}
+
+/// Appends the CRC-32 of the bytes, undo checks and removes it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Crc32;
+
+impl Stage for Crc32{
+    fn id(&self) -> u16{
+        CRC32
+    }
+
+    fn apply(&self, mut bytes: Buffer) -> Buffer{
+        let crc = crc32(&bytes);
+        crc.into_buffer(&mut bytes);
+        bytes
+    }
+
+    fn undo(&self, mut bytes: Buffer) -> Option<Buffer>{
+        let at = bytes.len().checked_sub(4)?;
+        let crc = u32::from_be_bytes(<[u8; 4]>::try_from(&bytes[at..]).ok()?);
+        bytes.truncate(at);
+        if crc32(&bytes) != crc{
+            return Option::None;
+        }
+        Option::Some(bytes)
+    }
+}
+
+/// Puts the length (u64) in front, undo checks that exactly that many bytes follow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Frame;
+
+impl Stage for Frame{
+    fn id(&self) -> u16{
+        FRAME
+    }
+
+    fn apply(&self, bytes: Buffer) -> Buffer{
+        let mut framed = Vec::with_capacity(8 + bytes.len());
+        (bytes.len() as u64).into_buffer(&mut framed);
+        framed.write_bytes(&bytes);
+        framed
+    }
+
+    fn undo(&self, mut bytes: Buffer) -> Option<Buffer>{
+        let len = u64::from_be_bytes(<[u8; 8]>::try_from(bytes.get(..8)?).ok()?);
+        if len != bytes.len() as u64 - 8{
+            return Option::None;
+        }
+        bytes.drain(..8);
+        Option::Some(bytes)
+    }
+}
+
+/// Huffman codes the bytes, see entropy::encode_bytes. With the `entropy` feature.
+#[cfg(feature = "entropy")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Huffman;
+
+#[cfg(feature = "entropy")]
+impl Stage for Huffman{
+    fn id(&self) -> u16{
+        HUFFMAN
+    }
+
+    fn apply(&self, bytes: Buffer) -> Buffer{
+        crate::entropy::encode_bytes(&bytes)
+    }
+
+    fn undo(&self, bytes: Buffer) -> Option<Buffer>{
+        crate::entropy::decode_bytes(&bytes)
+    }
+}
+
+/// Compresses the bytes, see compress::compress_buffer. With the `compression` feature.
+#[cfg(feature = "compression")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lz4;
+
+#[cfg(feature = "compression")]
+impl Stage for Lz4{
+    fn id(&self) -> u16{
+        LZ4
+    }
+
+    fn apply(&self, bytes: Buffer) -> Buffer{
+        crate::compress::compress_buffer(&bytes)
+    }
+
+    fn undo(&self, bytes: Buffer) -> Option<Buffer>{
+        crate::compress::decompress_buffer(&bytes)
+    }
+}
+
+/// Stages in the order they are applied when writing, and the stages known when reading.
+#[derive(Default)]
+pub struct Pipeline{
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl Pipeline{
+    /// A pipeline without stages, which only writes the header.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Apply stage after the ones before it. Panics past 255 stages.
+    pub fn then<S: Stage + 'static>(mut self, stage: S) -> Self{
+        assert!(self.stages.len() < 255, "too many stages");
+        self.stages.push(Box::new(stage));
+        self
+    }
+    /// Encode x and put it through the stages, behind the header.
+    pub fn encode<T: Bufferable>(&self, x: &T) -> Buffer{
+        let bytes = self.stages.iter().fold(crate::encode(x), |bytes, stage| stage.apply(bytes));
+        let mut out = Vec::with_capacity(1 + 2 * self.stages.len() + bytes.len());
+        (self.stages.len() as u8).into_buffer(&mut out);
+        for stage in &self.stages{
+            stage.id().into_buffer(&mut out);
+        }
+        out.write_bytes(&bytes);
+        out
+    }
+    /// Undo the stages the header lists and decode a T. None if a stage is not known to this
+    /// pipeline, a stage finds the bytes broken or they do not hold exactly one T.
+    pub fn decode<T: Bufferable>(&self, bytes: &[u8]) -> Option<T>{
+        let ids = Self::stages_of(bytes)?;
+        let mut data = bytes[1 + 2 * ids.len()..].to_vec();
+        for id in ids.iter().rev(){
+            let stage = self.stages.iter().find(|s| s.id() == *id)?;
+            data = stage.undo(data)?;
+        }
+        crate::decode(&data)
+    }
+    /// The ids of the stages the header of bytes lists, in the order they were applied.
+    pub fn stages_of(bytes: &[u8]) -> Option<Vec<u16>>{
+        let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+        let count = u8::from_buffer(&mut buf)?;
+        (0..count).map(|_| u16::from_buffer(&mut buf)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::pipeline::*;
+
+    // stands in for a cipher
+    struct Xor(u8);
+    impl Stage for Xor{
+        fn id(&self) -> u16{ 0x100 }
+        fn apply(&self, bytes: Buffer) -> Buffer{ bytes.into_iter().map(|b| b ^ self.0).collect() }
+        fn undo(&self, bytes: Buffer) -> Option<Buffer>{ Some(self.apply(bytes)) }
+    }
+
+    #[test]
+    fn test_pipeline(){
+        let x = (vec![7u32; 20], String::from("secret"));
+        let writer = Pipeline::new().then(Xor(0x5a)).then(Crc32).then(Frame);
+        let bytes = writer.encode(&x);
+        assert_eq!(vec![0x100, CRC32, FRAME], Pipeline::stages_of(&bytes).unwrap());
+        assert_eq!(1 + 6 + 8 + encoded_size_of(&x) + 4, bytes.len());
+        assert_eq!(Some(x.clone()), writer.decode(&bytes));
+        // the reader knows more stages than are used
+        let reader = Pipeline::new().then(Frame).then(Crc32).then(Xor(0x5a));
+        assert_eq!(Some(x.clone()), reader.decode(&bytes));
+        assert_eq!(Some(x.clone()), reader.decode(&Pipeline::new().then(Crc32).encode(&x)));
+        // a stage it does not know, a damaged byte and a wrong key
+        assert_eq!(None, Pipeline::new().then(Crc32).then(Frame).decode::<(Vec<u32>, String)>(&bytes));
+        let mut broken = bytes.clone();
+        broken[20] ^= 1;
+        assert_eq!(None, reader.decode::<(Vec<u32>, String)>(&broken));
+        let wrong = Pipeline::new().then(Frame).then(Crc32).then(Xor(1));
+        assert_eq!(None, wrong.decode::<(Vec<u32>, String)>(&bytes));
+        assert_eq!(vec![0, 9], Pipeline::new().encode(&9u8));
+        assert_eq!(None, Pipeline::stages_of(&[2, 0, 1]));
+    }
+
+    #[cfg(feature = "entropy")]
+    #[test]
+    fn test_pipeline_huffman(){
+        let x = vec![0u8; 1000];
+        let pipeline = Pipeline::new().then(Huffman).then(Crc32);
+        let bytes = pipeline.encode(&x);
+        assert!(bytes.len() < 300);
+        assert_eq!(Some(x), pipeline.decode(&bytes));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_pipeline_lz4(){
+        let x = vec![String::from("stone"); 1000];
+        let pipeline = Pipeline::new().then(Lz4).then(Crc32);
+        let bytes = pipeline.encode(&x);
+        assert!(bytes.len() < 200);
+        assert_eq!(vec![LZ4, CRC32], Pipeline::stages_of(&bytes).unwrap());
+        assert_eq!(Some(x), pipeline.decode(&bytes));
+    }
+}
@@ -0,0 +1,338 @@
+//! A subset of the protobuf wire format, enabled with the `protobuf` feature.
+//! Enough for simple messages: varints, zigzag ints, fixed width numbers, strings, bytes,
+//! nested messages and packed repeated varints. Groups are not supported.
+//! Write a message with ProtoWriter and read its fields back with fields,
+//! or implement ProtoMessage and use to_proto and from_proto.
+//! # Example
+//! ```
+//! use bin_buffer::protobuf::*;
+//! let mut w = ProtoWriter::new();
+//! w.uint64(1, 150);
+//! w.string(2, "testing");
+//! let bytes = w.finish();
+//! assert_eq!(vec![0x08, 0x96, 0x01, 0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g'], bytes);
+//! let mut it = fields(&bytes);
+//! assert_eq!(Some(Some((1, WireValue::Varint(150)))), it.next());
+//! assert_eq!(Some("testing"), it.next().unwrap().unwrap().1.as_str());
+//! ```
+use std::convert::TryFrom;
+use crate::{ varint, Buffer };
+
+/// Writes the fields of a protobuf message.
+#[derive(Clone, Debug, Default)]
+pub struct ProtoWriter{
+    buffer: Buffer,
+}
+
+impl ProtoWriter{
+    /// Start an empty message.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    fn key(&mut self, field: u32, wire_type: u8){
+        varint::write_u64((u64::from(field) << 3) | u64::from(wire_type), &mut self.buffer);
+    }
+    /// Write a uint64, uint32, int64 with a positive value, or enum field.
+    pub fn uint64(&mut self, field: u32, x: u64) -> &mut Self{
+        self.key(field, 0);
+        varint::write_u64(x, &mut self.buffer);
+        self
+    }
+    /// Write an int64 or int32 field, negative numbers take ten bytes.
+    pub fn int64(&mut self, field: u32, x: i64) -> &mut Self{
+        self.uint64(field, x as u64)
+    }
+    /// Write a sint64 or sint32 field, zigzag encoded.
+    pub fn sint64(&mut self, field: u32, x: i64) -> &mut Self{
+        self.uint64(field, varint::zigzag(x))
+    }
+    /// Write a bool field.
+    pub fn bool(&mut self, field: u32, x: bool) -> &mut Self{
+        self.uint64(field, u64::from(x))
+    }
+    /// Write a fixed64 field.
+    pub fn fixed64(&mut self, field: u32, x: u64) -> &mut Self{
+        self.key(field, 1);
+        self.buffer.extend_from_slice(&x.to_le_bytes());
+        self
+    }
+    /// Write a fixed32 field.
+    pub fn fixed32(&mut self, field: u32, x: u32) -> &mut Self{
+        self.key(field, 5);
+        self.buffer.extend_from_slice(&x.to_le_bytes());
+        self
+    }
+    /// Write a double field.
+    pub fn double(&mut self, field: u32, x: f64) -> &mut Self{
+        self.fixed64(field, x.to_bits())
+    }
+    /// Write a float field.
+    pub fn float(&mut self, field: u32, x: f32) -> &mut Self{
+        self.fixed32(field, x.to_bits())
+    }
+    /// Write a bytes field.
+    pub fn bytes(&mut self, field: u32, x: &[u8]) -> &mut Self{
+        self.key(field, 2);
+        varint::write_u64(x.len() as u64, &mut self.buffer);
+        self.buffer.extend_from_slice(x);
+        self
+    }
+    /// Write a string field.
+    pub fn string(&mut self, field: u32, x: &str) -> &mut Self{
+        self.bytes(field, x.as_bytes())
+    }
+    /// Write a nested message field, filled in by f.
+    pub fn message<F: FnOnce(&mut ProtoWriter)>(&mut self, field: u32, f: F) -> &mut Self{
+        let mut inner = ProtoWriter::new();
+        f(&mut inner);
+        self.bytes(field, &inner.buffer)
+    }
+    /// Write a packed repeated uint64 field.
+    pub fn packed_uint64(&mut self, field: u32, xs: &[u64]) -> &mut Self{
+        let mut packed = Vec::new();
+        for x in xs{
+            varint::write_u64(*x, &mut packed);
+        }
+        self.bytes(field, &packed)
+    }
+    /// The encoded message.
+    pub fn finish(self) -> Buffer{
+        self.buffer
+    }
+}
+
+/// The value of a field as it is on the wire.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WireValue<'a>{
+    /// Wire type 0.
+    Varint(u64),
+    /// Wire type 1.
+    Fixed64(u64),
+    /// Wire type 2: strings, bytes, nested messages and packed fields.
+    Bytes(&'a [u8]),
+    /// Wire type 5.
+    Fixed32(u32),
+}
+
+impl<'a> WireValue<'a>{
+    /// A varint as is.
+    pub fn as_u64(&self) -> Option<u64>{
+        match self{
+            WireValue::Varint(x) => Option::Some(*x),
+            _ => Option::None,
+        }
+    }
+    /// A varint that fits a u32.
+    pub fn as_u32(&self) -> Option<u32>{
+        u32::try_from(self.as_u64()?).ok()
+    }
+    /// A int64 or int32 varint.
+    pub fn as_i64(&self) -> Option<i64>{
+        Option::Some(self.as_u64()? as i64)
+    }
+    /// A zigzag sint64 or sint32 varint.
+    pub fn as_sint64(&self) -> Option<i64>{
+        Option::Some(varint::unzigzag(self.as_u64()?))
+    }
+    /// A bool varint.
+    pub fn as_bool(&self) -> Option<bool>{
+        Option::Some(self.as_u64()? != 0)
+    }
+    /// A double.
+    pub fn as_f64(&self) -> Option<f64>{
+        match self{
+            WireValue::Fixed64(x) => Option::Some(f64::from_bits(*x)),
+            _ => Option::None,
+        }
+    }
+    /// A float.
+    pub fn as_f32(&self) -> Option<f32>{
+        match self{
+            WireValue::Fixed32(x) => Option::Some(f32::from_bits(*x)),
+            _ => Option::None,
+        }
+    }
+    /// Length delimited bytes.
+    pub fn as_bytes(&self) -> Option<&'a [u8]>{
+        match self{
+            WireValue::Bytes(x) => Option::Some(x),
+            _ => Option::None,
+        }
+    }
+    /// Length delimited UTF-8.
+    pub fn as_str(&self) -> Option<&'a str>{
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+    /// The fields of a nested message.
+    pub fn as_message(&self) -> Option<ProtoFields<'a>>{
+        Option::Some(fields(self.as_bytes()?))
+    }
+    /// The values of a packed repeated varint field, None if one is malformed.
+    pub fn as_packed_u64(&self) -> Option<Vec<u64>>{
+        let mut bytes = self.as_bytes()?;
+        let mut xs = Vec::new();
+        while !bytes.is_empty(){
+            let (x, len) = varint::decode_u64(bytes)?;
+            xs.push(x);
+            bytes = &bytes[len..];
+        }
+        Option::Some(xs)
+    }
+}
+
+/// Iterator over the fields of a message, made by fields.
+/// Items are the field number and its value, or None if the message is malformed,
+/// after which the iterator ends.
+pub struct ProtoFields<'a>{
+    bytes: &'a [u8],
+}
+/// Iterate over the fields of an encoded message.
+pub fn fields(bytes: &[u8]) -> ProtoFields<'_>{
+    ProtoFields{ bytes }
+}
+
+impl<'a> ProtoFields<'a>{
+    fn varint(&mut self) -> Option<u64>{
+        let (x, len) = varint::decode_u64(self.bytes)?;
+        self.bytes = &self.bytes[len..];
+        Option::Some(x)
+    }
+    fn take(&mut self, len: usize) -> Option<&'a [u8]>{
+        if len > self.bytes.len(){
+            return Option::None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Option::Some(taken)
+    }
+    fn field(&mut self) -> Option<(u32, WireValue<'a>)>{
+        let key = self.varint()?;
+        let field = u32::try_from(key >> 3).ok()?;
+        let value = match key & 7{
+            0 => WireValue::Varint(self.varint()?),
+            1 => WireValue::Fixed64(u64::from_le_bytes(<[u8; 8]>::try_from(self.take(8)?).ok()?)),
+            2 => {
+                let len = usize::try_from(self.varint()?).ok()?;
+                WireValue::Bytes(self.take(len)?)
+            },
+            5 => WireValue::Fixed32(u32::from_le_bytes(<[u8; 4]>::try_from(self.take(4)?).ok()?)),
+            _ => return Option::None,
+        };
+        if field == 0{
+            return Option::None;
+        }
+        Option::Some((field, value))
+    }
+}
+
+impl<'a> Iterator for ProtoFields<'a>{
+    type Item = Option<(u32, WireValue<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item>{
+        if self.bytes.is_empty(){
+            return Option::None;
+        }
+        let field = self.field();
+        if field.is_none(){
+            self.bytes = &[];
+        }
+        Option::Some(field)
+    }
+}
+
+impl<'a> std::iter::FusedIterator for ProtoFields<'a>{}
+
+/// A message that can be written to and read from the protobuf wire format.
+pub trait ProtoMessage: Default{
+    /// Write all fields.
+    fn write_proto(&self, w: &mut ProtoWriter);
+    /// Take in one field. Unknown fields should be ignored, None means the value was malformed.
+    fn read_field(&mut self, field: u32, value: WireValue) -> Option<()>;
+}
+/// Encode a message.
+pub fn to_proto<T: ProtoMessage>(x: &T) -> Buffer{
+    let mut w = ProtoWriter::new();
+    x.write_proto(&mut w);
+    w.finish()
+}
+/// Decode a message, starting from T::default() and reading every field into it.
+pub fn from_proto<T: ProtoMessage>(bytes: &[u8]) -> Option<T>{
+    let mut x = T::default();
+    for field in fields(bytes){
+        let (field, value) = field?;
+        x.read_field(field, value)?;
+    }
+    Option::Some(x)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::protobuf::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Inner{ a: u64 }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Outer{ name: String, inner: Inner, ids: Vec<u64>, delta: i64, score: f32 }
+
+    impl ProtoMessage for Inner{
+        fn write_proto(&self, w: &mut ProtoWriter){
+            w.uint64(1, self.a);
+        }
+        fn read_field(&mut self, field: u32, value: WireValue) -> Option<()>{
+            if field == 1 { self.a = value.as_u64()?; }
+            Option::Some(())
+        }
+    }
+
+    impl ProtoMessage for Outer{
+        fn write_proto(&self, w: &mut ProtoWriter){
+            w.string(2, &self.name)
+                .message(3, |w| self.inner.write_proto(w))
+                .packed_uint64(4, &self.ids)
+                .sint64(5, self.delta)
+                .float(6, self.score);
+        }
+        fn read_field(&mut self, field: u32, value: WireValue) -> Option<()>{
+            match field{
+                2 => self.name = value.as_str()?.to_owned(),
+                3 => self.inner = from_proto(value.as_bytes()?)?,
+                4 => self.ids = value.as_packed_u64()?,
+                5 => self.delta = value.as_sint64()?,
+                6 => self.score = value.as_f32()?,
+                _ => {},
+            }
+            Option::Some(())
+        }
+    }
+
+    #[test]
+    fn test_protobuf(){
+        // the examples from the protobuf encoding guide
+        let x = Outer{ name: String::from("testing"), inner: Inner{ a: 150 },
+            ids: vec![3, 270, 86942], delta: -2, score: 1.0 };
+        let bytes = to_proto(&x);
+        let expected = vec![0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g',
+            0x1a, 0x03, 0x08, 0x96, 0x01,
+            0x22, 0x06, 0x03, 0x8e, 0x02, 0x9e, 0xa7, 0x05,
+            0x28, 0x03,
+            0x35, 0x00, 0x00, 0x80, 0x3f];
+        assert_eq!(expected, bytes);
+        assert_eq!(Some(x), from_proto(&bytes));
+        // unknown fields are skipped, wire types are checked
+        let mut w = ProtoWriter::new();
+        w.fixed64(9, 1).uint64(7, 1).string(2, "x");
+        assert_eq!(Some(Outer{ name: String::from("x"), ..Outer::default() }), from_proto(&w.finish()));
+        let mut w = ProtoWriter::new();
+        w.uint64(2, 1);
+        assert_eq!(None, from_proto::<Outer>(&w.finish()));
+        // a length past the end
+        assert_eq!(vec![None], fields(&[0x12, 0x05, 0x00]).collect::<Vec<_>>());
+        // negative int64 takes ten bytes
+        let mut w = ProtoWriter::new();
+        w.int64(1, -1);
+        let bytes = w.finish();
+        assert_eq!(11, bytes.len());
+        assert_eq!(Some(-1), fields(&bytes).next().unwrap().unwrap().1.as_i64());
+    }
+}
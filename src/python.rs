@@ -0,0 +1,143 @@
+//! Python bindings, enabled with the `python` feature.
+//! Build them as an extension module named bin_buffer, for example with
+//! `maturin build --features python,pyo3/extension-module`.
+//! ```python
+//! import bin_buffer
+//! w = bin_buffer.Writer()
+//! w.push_u32(7)
+//! w.push_str("hello")
+//! r = bin_buffer.Reader(w.to_bytes())
+//! assert r.read_u32() == 7
+//! assert r.read_str() == "hello"
+//! ```
+//! Reading raises ValueError when the value is not there.
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyBytes;
+
+/// Buffer that Python writes values to.
+#[pyclass]
+#[derive(Default)]
+pub struct Writer{
+    buffer: Buffer,
+}
+
+#[pymethods]
+impl Writer{
+    #[new]
+    fn new() -> Self{
+        Self::default()
+    }
+    fn push_u8(&mut self, x: u8){
+        x.into_buffer(&mut self.buffer);
+    }
+    fn push_u16(&mut self, x: u16){
+        x.into_buffer(&mut self.buffer);
+    }
+    fn push_u32(&mut self, x: u32){
+        x.into_buffer(&mut self.buffer);
+    }
+    fn push_u64(&mut self, x: u64){
+        x.into_buffer(&mut self.buffer);
+    }
+    fn push_f32(&mut self, x: f32){
+        x.into_buffer(&mut self.buffer);
+    }
+    fn push_f64(&mut self, x: f64){
+        x.into_buffer(&mut self.buffer);
+    }
+    fn push_str(&mut self, x: &str){
+        x.to_owned().into_buffer(&mut self.buffer);
+    }
+    /// Write bytes as a ByteVec.
+    fn push_bytes(&mut self, x: &[u8]){
+        (x.len() as u64).into_buffer(&mut self.buffer);
+        self.buffer.extend_from_slice(x);
+    }
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes>{
+        PyBytes::new(py, &self.buffer)
+    }
+    fn __len__(&self) -> usize{
+        self.buffer.len()
+    }
+}
+
+/// Buffer that Python reads values from.
+#[pyclass]
+pub struct Reader{
+    buffer: ReadBuffer<'static>,
+}
+
+fn missing<T>(x: Option<T>, what: &str) -> PyResult<T>{
+    x.ok_or_else(|| PyValueError::new_err(format!("could not read a {}", what)))
+}
+
+#[pymethods]
+impl Reader{
+    #[new]
+    fn new(data: &[u8]) -> Self{
+        Self{ buffer: ReadBuffer::from_raw(data.to_vec()) }
+    }
+    fn read_u8(&mut self) -> PyResult<u8>{
+        missing(u8::from_buffer(&mut self.buffer), "u8")
+    }
+    fn read_u16(&mut self) -> PyResult<u16>{
+        missing(u16::from_buffer(&mut self.buffer), "u16")
+    }
+    fn read_u32(&mut self) -> PyResult<u32>{
+        missing(u32::from_buffer(&mut self.buffer), "u32")
+    }
+    fn read_u64(&mut self) -> PyResult<u64>{
+        missing(u64::from_buffer(&mut self.buffer), "u64")
+    }
+    fn read_f32(&mut self) -> PyResult<f32>{
+        missing(f32::from_buffer(&mut self.buffer), "f32")
+    }
+    fn read_f64(&mut self) -> PyResult<f64>{
+        missing(f64::from_buffer(&mut self.buffer), "f64")
+    }
+    fn read_str(&mut self) -> PyResult<String>{
+        missing(self.buffer.read_str().map(str::to_owned), "str")
+    }
+    /// Read a ByteVec as bytes.
+    fn read_bytes<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>>{
+        let blob = missing(self.buffer.read_blob(), "bytes")?;
+        Ok(PyBytes::new(py, blob))
+    }
+    fn remaining(&self) -> usize{
+        self.buffer.remaining()
+    }
+}
+
+/// The bin_buffer Python module.
+#[pymodule]
+fn bin_buffer(m: &Bound<'_, PyModule>) -> PyResult<()>{
+    m.add_class::<Writer>()?;
+    m.add_class::<Reader>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::python::*;
+
+    #[test]
+    fn test_python(){
+        let mut w = Writer::new();
+        w.push_u32(7);
+        w.push_f64(0.5);
+        w.push_str("hi");
+        w.push_bytes(&[1, 2]);
+        let mut expected = Vec::new();
+        (7u32, 0.5f64, String::from("hi"), ByteVec(vec![1, 2])).into_buffer(&mut expected);
+        assert_eq!(expected, w.buffer);
+        assert_eq!(expected.len(), w.__len__());
+        let mut r = Reader::new(&w.buffer);
+        assert_eq!(7, r.read_u32().unwrap());
+        assert_eq!(0.5, r.read_f64().unwrap());
+        assert_eq!("hi", r.read_str().unwrap());
+        assert_eq!(10, r.remaining());
+    }
+}
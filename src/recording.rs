@@ -0,0 +1,189 @@
+//! Recording values with the time they were sent, to play them back later, like network traffic
+//! of a game session that has to be gone through again while debugging.
+//! A recording starts with the magic "BREC", then every message is the time since the
+//! recording started in nanoseconds (u64, from a monotonic clock), the wall clock time in
+//! nanoseconds since the unix epoch (u64) and the value as a frame like net::send_over writes.
+//! A Player reads them back as fast as they can be read, at the speed they were recorded
+//! or faster or slower with with_speed.
+//! # Example
+//! ```
+//! use bin_buffer::recording::*;
+//! let mut recorder = Recorder::new(Vec::new()).unwrap();
+//! recorder.record(&(1u8, String::from("jump"))).unwrap();
+//! recorder.record(&(2u8, String::from("land"))).unwrap();
+//! let bytes = recorder.into_inner();
+//! let mut player = Player::new(&bytes[..]).unwrap().with_speed(100.0);
+//! let first = player.next_timed::<(u8, String)>().unwrap().unwrap();
+//! assert_eq!((1, String::from("jump")), first.value);
+//! let second = player.next_timed::<(u8, String)>().unwrap().unwrap();
+//! assert!(second.elapsed >= first.elapsed);
+//! assert!(player.next_timed::<(u8, String)>().unwrap().is_none());
+//! ```
+use std::io::{ self, Read, Write };
+use std::convert::TryFrom;
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+use crate::Bufferable;
+use crate::net::{ decode_frame, read_frame, write_frame, DEFAULT_MAX_FRAME_LEN };
+
+/// First bytes of every recording.
+pub const MAGIC: [u8; 4] = *b"BREC";
+
+fn nanos(d: Duration) -> u64{
+    u64::try_from(d.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Writes values with the time they were recorded.
+pub struct Recorder<W: Write>{
+    w: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W>{
+    /// Start a recording into w, the times of the messages count from now.
+    pub fn new(mut w: W) -> io::Result<Self>{
+        w.write_all(&MAGIC)?;
+        Ok(Self{ w, start: Instant::now() })
+    }
+    /// Record bytes that are already encoded.
+    pub fn record_bytes(&mut self, bytes: &[u8]) -> io::Result<()>{
+        let elapsed = nanos(self.start.elapsed());
+        let wall = nanos(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default());
+        let mut times = [0u8; 16];
+        times[..8].copy_from_slice(&elapsed.to_be_bytes());
+        times[8..].copy_from_slice(&wall.to_be_bytes());
+        self.w.write_all(&times)?;
+        write_frame(bytes, &mut self.w)
+    }
+    /// Record x.
+    pub fn record<T: Bufferable>(&mut self, x: &T) -> io::Result<()>{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.record_bytes(&bytes)
+    }
+    /// How long the recording has been going.
+    pub fn elapsed(&self) -> Duration{
+        self.start.elapsed()
+    }
+    /// The writer.
+    pub fn get_ref(&self) -> &W{
+        &self.w
+    }
+    /// Stop recording and give the writer back.
+    pub fn into_inner(self) -> W{
+        self.w
+    }
+}
+
+/// A recorded value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message<T>{
+    /// Time since the recording started.
+    pub elapsed: Duration,
+    /// Wall clock time it was recorded at.
+    pub wall: SystemTime,
+    /// The value.
+    pub value: T,
+}
+
+/// Reads a recording back.
+pub struct Player<R: Read>{
+    r: R,
+    speed: f64,
+    start: Option<Instant>,
+}
+
+impl<R: Read> Player<R>{
+    /// Play the recording in r. Fails with InvalidData if it is not a recording.
+    pub fn new(mut r: R) -> io::Result<Self>{
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC{
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a recording"));
+        }
+        Ok(Self{ r, speed: 1.0, start: Option::None })
+    }
+    /// Play speed times as fast as recorded for next_timed, 1.0 is the recorded speed.
+    /// 0.0, or anything that is not a positive number, means without waiting.
+    pub fn with_speed(mut self, speed: f64) -> Self{
+        self.speed = speed;
+        self
+    }
+    /// The next message as encoded bytes, right away. None at the end of the recording.
+    pub fn next_bytes(&mut self) -> io::Result<Option<Message<Vec<u8>>>>{
+        let mut times = [0u8; 16];
+        let mut got = 0;
+        while got < times.len(){
+            match self.r.read(&mut times[got..]){
+                Ok(0) if got == 0 => return Ok(Option::None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => got += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+        let mut elapsed = [0u8; 8];
+        let mut wall = [0u8; 8];
+        elapsed.copy_from_slice(&times[..8]);
+        wall.copy_from_slice(&times[8..]);
+        let value = read_frame(&mut self.r, DEFAULT_MAX_FRAME_LEN)?;
+        Ok(Option::Some(Message{
+            elapsed: Duration::from_nanos(u64::from_be_bytes(elapsed)),
+            wall: UNIX_EPOCH + Duration::from_nanos(u64::from_be_bytes(wall)),
+            value,
+        }))
+    }
+    /// The next message, right away. Fails with InvalidData if it is not exactly one T.
+    pub fn next_message<T: Bufferable>(&mut self) -> io::Result<Option<Message<T>>>{
+        match self.next_bytes()?{
+            Option::None => Ok(Option::None),
+            Option::Some(m) => Ok(Option::Some(Message{ elapsed: m.elapsed, wall: m.wall, value: decode_frame(m.value)? })),
+        }
+    }
+    /// The next message, after waiting until it is due: its elapsed time divided by the speed
+    /// after the first call to next_timed.
+    pub fn next_timed<T: Bufferable>(&mut self) -> io::Result<Option<Message<T>>>{
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let message = self.next_message::<T>()?;
+        if let Option::Some(m) = &message{
+            if self.speed > 0.0 && self.speed.is_finite(){
+                let due = start + m.elapsed.div_f64(self.speed);
+                let now = Instant::now();
+                if due > now{
+                    std::thread::sleep(due - now);
+                }
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::recording::*;
+
+    #[test]
+    fn test_recording(){
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        recorder.record(&1u32).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        recorder.record_bytes(&[0, 0, 0, 2]).unwrap();
+        recorder.record_bytes(&[3]).unwrap();
+        let bytes = recorder.into_inner();
+        assert_eq!(4 + 3 * 24 + 9, bytes.len());
+        let mut player = Player::new(&bytes[..]).unwrap();
+        let start = Instant::now();
+        let first = player.next_timed::<u32>().unwrap().unwrap();
+        let second = player.next_timed::<u32>().unwrap().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!((1, 2), (first.value, second.value));
+        assert!(second.elapsed - first.elapsed >= Duration::from_millis(20));
+        assert!(second.wall >= first.wall);
+        assert_eq!(io::ErrorKind::InvalidData, player.next_message::<u32>().unwrap_err().kind());
+        assert!(player.next_bytes().unwrap().is_none());
+        // as fast as it can, and a torn message
+        let mut player = Player::new(&bytes[..bytes.len() - 1]).unwrap().with_speed(0.0);
+        assert_eq!(2, (0..2).map(|_| player.next_timed::<u32>().unwrap().unwrap()).count());
+        assert_eq!(io::ErrorKind::UnexpectedEof, player.next_bytes().unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, Player::new(&b"BRE?"[..]).err().unwrap().kind());
+    }
+}
@@ -0,0 +1,267 @@
+//! Writing and reading trait objects such as Box<dyn Component>.
+//! Every concrete type gets a u32 id with Registered. encode_dyn writes that id,
+//! the length of the payload and then the value itself. A Registry knows how to turn
+//! each id back into a Box<dyn Trait>, so Registry::decode_dyn gives back the right type.
+//! Make DynBufferable a supertrait of your trait so trait objects can be encoded.
+//! Codecs is the same for types the host does not know when it is compiled: encode and decode
+//! functions for a type id are added at runtime, by plugins for example, and work on schema
+//! Values. Schema::Custom refers to them in a self-describing buffer, and
+//! EnvelopeRegistry::with_codecs hands envelopes of such types to a handler.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::registry::*;
+//! trait Shape: DynBufferable{ fn area(&self) -> f32; }
+//! #[derive(Clone)]
+//! struct Square(f32);
+//! impl Bufferable for Square{
+//!     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.0.into_buffer(vec); }
+//!     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ self.0.copy_into_buffer(vec); }
+//!     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{ Some(Square(f32::from_buffer(buf)?)) }
+//!     fn buffer_len(&self) -> usize{ 4 }
+//! }
+//! impl Registered for Square{ const TYPE_ID: u32 = 1; }
+//! impl Shape for Square{ fn area(&self) -> f32{ self.0 * self.0 } }
+//!
+//! let mut registry = Registry::<dyn Shape>::new();
+//! registry.register(|x: Square| Box::new(x));
+//! let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Square(2.0)), Box::new(Square(3.0))];
+//! let mut buffer = Vec::new();
+//! for shape in &shapes{
+//!     encode_dyn(shape.as_ref(), &mut buffer);
+//! }
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! assert_eq!(Some(4.0), registry.decode_dyn(&mut buffer).map(|s| s.area()));
+//! assert_eq!(Some(9.0), registry.decode_dyn(&mut buffer).map(|s| s.area()));
+//! ```
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use crate::{ Bufferable, ReadBuffer, Sink };
+use crate::schema::Value;
+
+/// Gives a concrete type its id in a Registry.
+pub trait Registered{
+    /// The id, unique among the types registered together.
+    const TYPE_ID: u32;
+}
+
+/// Object safe encoding, implemented for every Bufferable + Registered type.
+pub trait DynBufferable{
+    /// Registered::TYPE_ID of the concrete type.
+    fn dyn_type_id(&self) -> u32;
+    /// Write the value itself.
+    fn dyn_write(&self, vec: &mut dyn Sink);
+    /// Bufferable::buffer_len of the value.
+    fn dyn_buffer_len(&self) -> usize;
+}
+
+impl<T: Bufferable + Registered> DynBufferable for T{
+    fn dyn_type_id(&self) -> u32{
+        T::TYPE_ID
+    }
+    fn dyn_write(&self, vec: &mut dyn Sink){
+        self.copy_into_buffer(vec);
+    }
+    fn dyn_buffer_len(&self) -> usize{
+        self.buffer_len()
+    }
+}
+/// Write the type id, the payload length and the value.
+pub fn encode_dyn<D: DynBufferable + ?Sized, S: Sink + ?Sized>(x: &D, vec: &mut S){
+    let len = x.dyn_buffer_len();
+    vec.reserve(12 + len);
+    x.dyn_type_id().into_buffer(vec);
+    (len as u64).into_buffer(vec);
+    let mut sink: &mut S = vec;
+    x.dyn_write(&mut sink);
+}
+
+type Decoder<D> = Box<dyn Fn(&mut ReadBuffer) -> Option<Box<D>>>;
+
+/// Decoders for the concrete types behind a trait object type D, by id.
+pub struct Registry<D: ?Sized>{
+    decoders: HashMap<u32, Decoder<D>>,
+}
+
+impl<D: ?Sized> Default for Registry<D>{
+    fn default() -> Self{
+        Self{ decoders: HashMap::new() }
+    }
+}
+
+impl<D: ?Sized + 'static> Registry<D>{
+    /// An empty registry.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Register T under T::TYPE_ID, turning decoded values into a Box<D> with to_dyn.
+    /// Panics if that id is already taken.
+    pub fn register<T, F>(&mut self, to_dyn: F)
+        where T: Bufferable + Registered, F: Fn(T) -> Box<D> + 'static
+    {
+        let decoder: Decoder<D> = Box::new(move |buf| T::from_buffer(buf).map(&to_dyn));
+        assert!(self.decoders.insert(T::TYPE_ID, decoder).is_none(),
+            "type id {} is registered twice", T::TYPE_ID);
+    }
+    /// Whether a type is registered under id.
+    pub fn contains(&self, id: u32) -> bool{
+        self.decoders.contains_key(&id)
+    }
+    /// Read what encode_dyn wrote. None if the id is unknown,
+    /// or the value does not decode to exactly the payload length.
+    pub fn decode_dyn(&self, buf: &mut ReadBuffer) -> Option<Box<D>>{
+        let id = u32::from_buffer(buf)?;
+        let len = usize::try_from(u64::from_buffer(buf)?).ok()?;
+        if len > buf.remaining(){
+            return Option::None;
+        }
+        let decoder = self.decoders.get(&id)?;
+        let start = buf.remaining();
+        let x = decoder(buf)?;
+        if start - buf.remaining() != len{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+}
+
+type DecodeFn = Arc<dyn Fn(&mut ReadBuffer) -> Option<Value> + Send + Sync>;
+type EncodeFn = Arc<dyn Fn(&Value, &mut dyn Sink) -> bool + Send + Sync>;
+
+/// Encode and decode functions by type id, added at runtime.
+/// Cloning shares the functions.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::registry::Codecs;
+/// use bin_buffer::schema::{ Schema, Value };
+/// // a plugin type: a u8 count of u16 points
+/// let mut codecs = Codecs::new();
+/// codecs.register(40,
+///     |buf| {
+///         let n = u8::from_buffer(buf)?;
+///         (0..n).map(|_| u16::from_buffer(buf).map(|x| Value::UInt(x.into()))).collect::<Option<_>>().map(Value::Seq)
+///     },
+///     |value, vec| match value{
+///         Value::Seq(xs) if xs.len() < 256 => {
+///             (xs.len() as u8).into_buffer(vec);
+///             xs.iter().all(|x| Schema::U16.encode(x, vec))
+///         },
+///         _ => false,
+///     });
+/// let value = Value::Seq(vec![Value::UInt(3), Value::UInt(4)]);
+/// let mut buffer = Vec::new();
+/// assert!(codecs.encode(40, &value, &mut buffer));
+/// assert_eq!(vec![2, 0, 3, 0, 4], buffer);
+/// assert_eq!(Some(value), codecs.decode(40, &mut ReadBuffer::from_raw(buffer)));
+/// ```
+#[derive(Clone, Default)]
+pub struct Codecs{
+    codecs: HashMap<u32, (DecodeFn, EncodeFn)>,
+}
+
+impl Codecs{
+    /// No codecs.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Decode values of type id with decode and encode them with encode.
+    /// encode returns false if the value does not fit the type.
+    /// Panics if that id is already taken.
+    pub fn register<D, E>(&mut self, id: u32, decode: D, encode: E)
+        where D: Fn(&mut ReadBuffer) -> Option<Value> + Send + Sync + 'static,
+              E: Fn(&Value, &mut dyn Sink) -> bool + Send + Sync + 'static
+    {
+        assert!(self.codecs.insert(id, (Arc::new(decode), Arc::new(encode))).is_none(),
+            "type id {} is registered twice", id);
+    }
+    /// Whether there is a codec for id.
+    pub fn contains(&self, id: u32) -> bool{
+        self.codecs.contains_key(&id)
+    }
+    /// Read a value of type id. None if there is no codec for it or it does not decode.
+    pub fn decode(&self, id: u32, buf: &mut ReadBuffer) -> Option<Value>{
+        buf.nested(|buf| self.codecs.get(&id)?.0(buf))
+    }
+    /// Write value as type id. False if there is no codec for it or the value does not fit.
+    pub fn encode<S: Sink + ?Sized>(&self, id: u32, value: &Value, vec: &mut S) -> bool{
+        let mut sink: &mut S = vec;
+        self.codecs.get(&id).is_some_and(|codec| codec.1(value, &mut sink))
+    }
+}
+
+impl std::fmt::Debug for Codecs{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        let mut ids: Vec<_> = self.codecs.keys().collect();
+        ids.sort();
+        f.debug_struct("Codecs").field("ids", &ids).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::registry::*;
+
+    trait Component: DynBufferable + std::fmt::Debug{
+        fn name(&self) -> String;
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(f32, f32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Label(String);
+
+    impl Bufferable for Position{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ (self.0, self.1).into_buffer(vec); }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ (self.0, self.1).into_buffer(vec); }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+            let (x, y) = <(f32,f32)>::from_buffer(buf)?;
+            Some(Position(x, y))
+        }
+        fn buffer_len(&self) -> usize{ 8 }
+    }
+    impl Bufferable for Label{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.0.into_buffer(vec); }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ self.0.copy_into_buffer(vec); }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{ Some(Label(String::from_buffer(buf)?)) }
+        fn buffer_len(&self) -> usize{ self.0.buffer_len() }
+    }
+    impl Registered for Position{ const TYPE_ID: u32 = 10; }
+    impl Registered for Label{ const TYPE_ID: u32 = 11; }
+    impl Component for Position{ fn name(&self) -> String{ format!("{:?}", self) } }
+    impl Component for Label{ fn name(&self) -> String{ self.0.clone() } }
+
+    #[test]
+    fn test_registry(){
+        let mut registry = Registry::<dyn Component>::new();
+        registry.register(|x: Position| Box::new(x));
+        registry.register(|x: Label| Box::new(x));
+        assert!(registry.contains(10) && !registry.contains(12));
+        let xs: Vec<Box<dyn Component>> = vec![Box::new(Label(String::from("player"))), Box::new(Position(1.0, 2.0))];
+        let mut buffer = Vec::new();
+        for x in &xs{
+            encode_dyn(x.as_ref(), &mut buffer);
+        }
+        assert_eq!(&[0, 0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 14], &buffer[..12]);
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!("player", registry.decode_dyn(&mut read).unwrap().name());
+        assert_eq!("Position(1.0, 2.0)", registry.decode_dyn(&mut read).unwrap().name());
+        assert!(registry.decode_dyn(&mut read).is_none());
+        // unknown id, and a payload length that does not match
+        let mut other = Registry::<dyn Component>::new();
+        other.register(|x: Position| Box::new(x));
+        assert!(other.decode_dyn(&mut ReadBuffer::from_raw(buffer.clone())).is_none());
+        buffer[11] = 13;
+        assert!(registry.decode_dyn(&mut ReadBuffer::from_raw(buffer)).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_registry_twice(){
+        let mut registry = Registry::<dyn Component>::new();
+        registry.register(|x: Label| Box::new(x));
+        registry.register(|x: Label| Box::new(x));
+    }
+}
@@ -0,0 +1,220 @@
+//! Requests and responses over any stream, built on envelopes.
+//! A Call is a correlation id (u64) followed by an Envelope. The client gives every request
+//! a new id and keeps it as pending, the server answers with the same id and the response
+//! flag set, and the client matches the answer to its request by that id.
+//! How the calls travel is up to the caller, net::write_frame and net::read_frame do for
+//! TCP and Unix sockets, and so is giving up on an answer: pending tells how long every
+//! request has been waiting and cancel forgets one.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::rpc::*;
+//! use bin_buffer::registry::Registered;
+//! #[derive(Debug, PartialEq)]
+//! struct Add(u32, u32);
+//! impl Bufferable for Add{
+//!     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.copy_into_buffer(vec); }
+//!     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ (self.0, self.1).into_buffer(vec); }
+//!     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+//!         let (a, b) = <(u32, u32)>::from_buffer(buf)?;
+//!         Some(Add(a, b))
+//!     }
+//! }
+//! impl Registered for Add{ const TYPE_ID: u32 = 1; }
+//! impl Migrate for Add{ const VERSION: u32 = 1; }
+//! #[derive(Debug, PartialEq)]
+//! struct Sum(u32);
+//! impl Bufferable for Sum{
+//!     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){ self.0.into_buffer(vec); }
+//!     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){ self.0.into_buffer(vec); }
+//!     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{ Some(Sum(u32::from_buffer(buf)?)) }
+//! }
+//! impl Registered for Sum{ const TYPE_ID: u32 = 2; }
+//! impl Migrate for Sum{ const VERSION: u32 = 1; }
+//!
+//! let mut server = RpcServer::new();
+//! server.register(|add: Add| Sum(add.0 + add.1));
+//! let mut client = RpcClient::new();
+//! let (id, request) = client.request(&Add(2, 3));
+//! let response = server.handle(&request).unwrap();
+//! let (answered, envelope) = client.accept(&response).unwrap();
+//! assert_eq!(id, answered);
+//! assert_eq!(Some(Sum(5)), envelope.open::<Sum>());
+//! assert_eq!(0, client.pending().count());
+//! ```
+use std::collections::HashMap;
+use std::time::Instant;
+use crate::{ Buffer, Bufferable, Migrate, ReadBuffer, Sink };
+use crate::envelope::Envelope;
+use crate::registry::Registered;
+
+/// Set in the envelope flags of a response.
+pub const FLAG_RESPONSE: u16 = 0x8000;
+
+/// A request or a response: the correlation id and the message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Call{
+    /// Ties a response to its request.
+    pub id: u64,
+    /// The message.
+    pub envelope: Envelope,
+}
+
+impl Call{
+    /// Whether this is a response.
+    pub fn is_response(&self) -> bool{
+        self.envelope.has_flags(FLAG_RESPONSE)
+    }
+}
+
+impl Bufferable for Call{
+    const MIN_BUFFER_LEN: usize = 8 + crate::envelope::HEADER_LEN;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.id.into_buffer(vec);
+        self.envelope.copy_into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some(Self{ id: u64::from_buffer(buf)?, envelope: Envelope::from_buffer(buf)? })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.envelope.buffer_len()
+    }
+}
+
+/// The side that sends requests, keeping track of the ones without an answer yet.
+#[derive(Debug, Default)]
+pub struct RpcClient{
+    next_id: u64,
+    pending: HashMap<u64, Instant>,
+}
+
+impl RpcClient{
+    /// A client without requests.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// A request for x under a new id, encoded, and the id.
+    pub fn request<T: Migrate + Registered>(&mut self, x: &T) -> (u64, Buffer){
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending.insert(id, Instant::now());
+        let call = Call{ id, envelope: Envelope::wrap(x, 0) };
+        (id, crate::encode(&call))
+    }
+    /// Take in an encoded response. Gives its id and message if it answers a pending request,
+    /// which stops being pending. None for anything else, like an answer to a cancelled request.
+    pub fn accept(&mut self, bytes: &[u8]) -> Option<(u64, Envelope)>{
+        let call = crate::decode::<Call>(bytes)?;
+        if !call.is_response() || self.pending.remove(&call.id).is_none(){
+            return Option::None;
+        }
+        Option::Some((call.id, call.envelope))
+    }
+    /// Whether request id is waiting for an answer.
+    pub fn is_pending(&self, id: u64) -> bool{
+        self.pending.contains_key(&id)
+    }
+    /// The requests waiting for an answer, with when they were made.
+    pub fn pending(&self) -> impl Iterator<Item = (u64, Instant)> + '_{
+        self.pending.iter().map(|(id, at)| (*id, *at))
+    }
+    /// Stop waiting for request id, so a late answer to it is not accepted.
+    /// False if it was not pending.
+    pub fn cancel(&mut self, id: u64) -> bool{
+        self.pending.remove(&id).is_some()
+    }
+}
+
+type Handler = Box<dyn Fn(&Envelope) -> Option<Envelope>>;
+
+/// The side that answers requests, with a handler per request type.
+#[derive(Default)]
+pub struct RpcServer{
+    handlers: HashMap<u32, Handler>,
+}
+
+impl RpcServer{
+    /// A server without handlers.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Answer requests of type Req with handle.
+    /// Panics if Req::TYPE_ID already has a handler.
+    pub fn register<Req, Resp, F>(&mut self, handle: F)
+        where Req: Migrate + Registered, Resp: Migrate + Registered, F: Fn(Req) -> Resp + 'static
+    {
+        let handler: Handler = Box::new(move |envelope|{
+            Option::Some(Envelope::wrap(&handle(envelope.open::<Req>()?), FLAG_RESPONSE))
+        });
+        assert!(self.handlers.insert(Req::TYPE_ID, handler).is_none(),
+            "type id {} is registered twice", Req::TYPE_ID);
+    }
+    /// The response to a call, as a Call with the same id.
+    /// None if it is not a request, its type has no handler or it does not open.
+    pub fn answer(&self, call: &Call) -> Option<Call>{
+        if call.is_response(){
+            return Option::None;
+        }
+        let envelope = self.handlers.get(&call.envelope.type_id)?(&call.envelope)?;
+        Option::Some(Call{ id: call.id, envelope })
+    }
+    /// Decode a request, answer it and encode the response.
+    pub fn handle(&self, bytes: &[u8]) -> Option<Buffer>{
+        self.answer(&crate::decode::<Call>(bytes)?).map(|call| crate::encode(&call))
+    }
+}
+
+/// The response to request carrying x, for servers that answer without an RpcServer.
+pub fn respond<T: Migrate + Registered>(request: &Call, x: &T) -> Call{
+    Call{ id: request.id, envelope: Envelope::wrap(x, FLAG_RESPONSE) }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::rpc::*;
+    use crate::registry::Registered;
+
+    impl Registered for String{ const TYPE_ID: u32 = 100; }
+    impl Migrate for String{ const VERSION: u32 = 1; }
+    impl Registered for u64{ const TYPE_ID: u32 = 101; }
+    impl Migrate for u64{ const VERSION: u32 = 1; }
+
+    #[test]
+    fn test_rpc(){
+        let mut server = RpcServer::new();
+        server.register(|s: String| s.len() as u64);
+        let mut client = RpcClient::new();
+        let (a, first) = client.request(&String::from("abc"));
+        let (b, second) = client.request(&String::from("hello"));
+        let (c, unknown) = client.request(&7u64);
+        assert!(a != b && b != c);
+        assert_eq!(3, client.pending().count());
+        // answers come back in another order
+        let second = server.handle(&second).unwrap();
+        let first = server.handle(&first).unwrap();
+        assert_eq!(None, server.handle(&unknown));
+        let (id, envelope) = client.accept(&second).unwrap();
+        assert_eq!((b, Some(5u64)), (id, envelope.open()));
+        // a response is not a request, and is only accepted once
+        assert_eq!(None, server.handle(&second));
+        assert_eq!(None, client.accept(&second));
+        assert!(client.cancel(a));
+        assert!(!client.cancel(a));
+        assert_eq!(None, client.accept(&first));
+        assert!(client.is_pending(c) && !client.is_pending(a));
+        let request = decode::<Call>(&unknown).unwrap();
+        let manual = respond(&request, &String::from("no"));
+        assert!(manual.is_response());
+        let (id, envelope) = client.accept(&encode(&manual)).unwrap();
+        assert_eq!((c, Some(String::from("no"))), (id, envelope.open()));
+        assert_eq!(0, client.pending().count());
+    }
+}
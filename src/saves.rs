@@ -0,0 +1,273 @@
+//! Numbered save slots in a directory, for games and editors that keep a few saves around.
+//! A save is written to a temporary file that is synced and then renamed over the slot,
+//! so a crash leaves either the old or the new save, and the save it replaces is kept as a
+//! backup. Every save file is the CRC-32 of the rest (u32), the SlotInfo and the value.
+//! When a save does not check out or decode, load falls back to the backup.
+//! The SlotInfo of every slot is also kept in an index file, so listing the slots does not
+//! read every save, the index is made again from the saves if it is missing or broken.
+//! # Example
+//! ```
+//! use bin_buffer::saves::*;
+//! let dir = std::env::temp_dir().join("bin_buffer_saves_doc");
+//! let _ = std::fs::remove_dir_all(&dir);
+//! let slots = SaveSlots::new(&dir, 3).unwrap();
+//! slots.save(0, &(4u32, String::from("cave")), 1, "before the boss").unwrap();
+//! let loaded = slots.load::<(u32, String)>(0).unwrap().unwrap();
+//! assert_eq!((4, String::from("cave")), loaded.value);
+//! assert_eq!("before the boss", loaded.info.label);
+//! assert_eq!(vec![0], slots.list().iter().map(|(slot, _)| *slot).collect::<Vec<_>>());
+//! ```
+use std::fs::{ File, OpenOptions };
+use std::io::{ self, Write };
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use crate::{ Buffer, Bufferable, ReadBuffer, Sink };
+use crate::journal::crc32;
+
+/// What is known about a save without loading it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotInfo{
+    /// When it was saved, in seconds since the unix epoch.
+    pub saved_at: u64,
+    /// Version of the layout of the value, as given to save.
+    pub version: u32,
+    /// Label given by the user.
+    pub label: String,
+}
+
+impl Bufferable for SlotInfo{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        self.saved_at.into_buffer(vec);
+        self.version.into_buffer(vec);
+        self.label.copy_into_buffer(vec);
+    }
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some(Self{ saved_at: u64::from_buffer(buf)?, version: u32::from_buffer(buf)?, label: String::from_buffer(buf)? })
+    }
+    fn buffer_len(&self) -> usize{
+        12 + self.label.buffer_len()
+    }
+}
+
+/// A loaded save.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loaded<T>{
+    /// The value.
+    pub value: T,
+    /// Its info.
+    pub info: SlotInfo,
+    /// Whether the save was broken and this is the one before it.
+    pub from_backup: bool,
+}
+
+/// Name of the index file.
+pub const INDEX_FILE: &str = "slots.idx";
+
+/// A number of save slots in a directory.
+pub struct SaveSlots{
+    dir: PathBuf,
+    slots: usize,
+}
+
+// Write bytes to a temporary file next to path, sync it and rename it over path.
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()>{
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    // make the rename itself survive a crash where the platform allows syncing a directory
+    if let Option::Some(dir) = path.parent(){
+        if let Ok(dir) = File::open(dir){
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// The CRC-32 of bytes in front of them.
+fn checked(bytes: Buffer) -> Buffer{
+    let mut out = Vec::with_capacity(4 + bytes.len());
+    crc32(&bytes).into_buffer(&mut out);
+    out.extend_from_slice(&bytes);
+    out
+}
+
+// The bytes after the CRC, if it checks out.
+fn unchecked(bytes: &[u8]) -> Option<&[u8]>{
+    let (crc, rest) = (bytes.get(..4)?, &bytes[4..]);
+    if u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) != crc32(rest){
+        return Option::None;
+    }
+    Option::Some(rest)
+}
+
+fn read_save<T: Bufferable>(path: &Path) -> Option<(SlotInfo, T)>{
+    let bytes = std::fs::read(path).ok()?;
+    let mut buf = ReadBuffer::borrowed(unchecked(&bytes)?, Default::default());
+    let info = SlotInfo::from_buffer(&mut buf)?;
+    let value = T::from_buffer(&mut buf)?;
+    if buf.remaining() != 0{
+        return Option::None;
+    }
+    Option::Some((info, value))
+}
+
+// Reads a save only as far as its info, for making the index again.
+struct InfoOnly;
+
+impl Bufferable for InfoOnly{
+    fn into_buffer<S: Sink + ?Sized>(self, _: &mut S){}
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, _: &mut S){}
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.read_bytes(buf.remaining())?;
+        Option::Some(InfoOnly)
+    }
+}
+
+impl SaveSlots{
+    /// Slots 0 to slots - 1 in dir, which is created if needed.
+    pub fn new(dir: &Path, slots: usize) -> io::Result<Self>{
+        std::fs::create_dir_all(dir)?;
+        Ok(Self{ dir: dir.to_path_buf(), slots })
+    }
+    /// Number of slots.
+    pub fn slots(&self) -> usize{
+        self.slots
+    }
+    /// The file of slot.
+    pub fn path(&self, slot: usize) -> PathBuf{
+        self.dir.join(format!("slot{}.sav", slot))
+    }
+    /// The file of the save before the one in slot.
+    pub fn backup_path(&self, slot: usize) -> PathBuf{
+        self.dir.join(format!("slot{}.sav.bak", slot))
+    }
+    fn check(&self, slot: usize) -> io::Result<()>{
+        if slot >= self.slots{
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("there is no slot {}", slot)));
+        }
+        Ok(())
+    }
+    /// Save x in slot with version and label, keeping what was there as the backup.
+    pub fn save<T: Bufferable>(&self, slot: usize, x: &T, version: u32, label: &str) -> io::Result<SlotInfo>{
+        self.check(slot)?;
+        let saved_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let info = SlotInfo{ saved_at, version, label: String::from(label) };
+        let mut bytes = Vec::with_capacity(info.buffer_len() + x.buffer_len());
+        info.copy_into_buffer(&mut bytes);
+        x.copy_into_buffer(&mut bytes);
+        let path = self.path(slot);
+        if read_save::<InfoOnly>(&path).is_some(){
+            std::fs::rename(&path, self.backup_path(slot))?;
+        }
+        write_atomic(&path, &checked(bytes))?;
+        let mut index = self.list();
+        index.retain(|(s, _)| *s != slot);
+        index.push((slot, info.clone()));
+        index.sort_by_key(|(s, _)| *s);
+        self.write_index(&index)?;
+        Ok(info)
+    }
+    /// Load slot, None if it is empty. If the save is broken the backup is loaded instead,
+    /// and if both are broken this fails with InvalidData.
+    pub fn load<T: Bufferable>(&self, slot: usize) -> io::Result<Option<Loaded<T>>>{
+        self.check(slot)?;
+        if let Option::Some((info, value)) = read_save(&self.path(slot)){
+            return Ok(Option::Some(Loaded{ value, info, from_backup: false }));
+        }
+        if let Option::Some((info, value)) = read_save(&self.backup_path(slot)){
+            return Ok(Option::Some(Loaded{ value, info, from_backup: true }));
+        }
+        if self.path(slot).exists() || self.backup_path(slot).exists(){
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("slot {} and its backup are broken", slot)));
+        }
+        Ok(Option::None)
+    }
+    /// Remove the save in slot and its backup.
+    pub fn delete(&self, slot: usize) -> io::Result<()>{
+        self.check(slot)?;
+        for path in [self.path(slot), self.backup_path(slot)].iter(){
+            match std::fs::remove_file(path){
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {},
+            }
+        }
+        let mut index = self.list();
+        index.retain(|(s, _)| *s != slot);
+        self.write_index(&index)
+    }
+    /// The slots that have a save with their info, from the index.
+    pub fn list(&self) -> Vec<(usize, SlotInfo)>{
+        let read = std::fs::read(self.dir.join(INDEX_FILE)).ok().and_then(|bytes|{
+            let mut buf = ReadBuffer::borrowed(unchecked(&bytes)?, Default::default());
+            let index = Vec::<(u64, SlotInfo)>::from_buffer(&mut buf)?;
+            Option::Some(index.into_iter().map(|(slot, info)| (slot as usize, info)).collect())
+        });
+        read.unwrap_or_else(|| self.scan())
+    }
+    /// The info of slot, from the index.
+    pub fn info(&self, slot: usize) -> Option<SlotInfo>{
+        self.list().into_iter().find(|(s, _)| *s == slot).map(|(_, info)| info)
+    }
+    // The index made from the saves themselves.
+    fn scan(&self) -> Vec<(usize, SlotInfo)>{
+        (0..self.slots).filter_map(|slot|{
+            let info = read_save::<InfoOnly>(&self.path(slot)).or_else(|| read_save::<InfoOnly>(&self.backup_path(slot)))?.0;
+            Option::Some((slot, info))
+        }).collect()
+    }
+    fn write_index(&self, index: &[(usize, SlotInfo)]) -> io::Result<()>{
+        let index: Vec<(u64, SlotInfo)> = index.iter().map(|(slot, info)| (*slot as u64, info.clone())).collect();
+        let mut bytes = Vec::new();
+        index.copy_into_buffer(&mut bytes);
+        write_atomic(&self.dir.join(INDEX_FILE), &checked(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::saves::*;
+
+    #[test]
+    fn test_saves(){
+        let dir = std::env::temp_dir().join("bin_buffer_saves_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let slots = SaveSlots::new(&dir, 2).unwrap();
+        assert_eq!(None, slots.load::<u32>(1).unwrap());
+        assert_eq!(io::ErrorKind::InvalidInput, slots.save(2, &1u32, 1, "").unwrap_err().kind());
+        slots.save(1, &1u32, 1, "first").unwrap();
+        slots.save(1, &2u32, 2, "second").unwrap();
+        slots.save(0, &3u32, 2, "other").unwrap();
+        assert_eq!(vec![0, 1], slots.list().iter().map(|(s, _)| *s).collect::<Vec<_>>());
+        assert_eq!(Some(2), slots.info(1).map(|i| i.version));
+        let loaded = slots.load::<u32>(1).unwrap().unwrap();
+        assert_eq!((2, false), (loaded.value, loaded.from_backup));
+        // a broken save falls back to the one before it
+        let mut bytes = std::fs::read(slots.path(1)).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(slots.path(1), &bytes).unwrap();
+        let loaded = slots.load::<u32>(1).unwrap().unwrap();
+        assert_eq!((1, true, String::from("first")), (loaded.value, loaded.from_backup, loaded.info.label));
+        // saving again does not keep the broken save as the backup
+        slots.save(1, &4u32, 3, "third").unwrap();
+        std::fs::write(slots.path(1), b"broken").unwrap();
+        assert_eq!(Some(1), slots.load::<u32>(1).unwrap().map(|l| l.value));
+        std::fs::write(slots.backup_path(1), b"broken").unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, slots.load::<u32>(1).unwrap_err().kind());
+        // the index is made again when it is lost
+        std::fs::remove_file(dir.join(INDEX_FILE)).unwrap();
+        assert_eq!(vec![(0, String::from("other"))], slots.list().into_iter().map(|(s, i)| (s, i.label)).collect::<Vec<_>>());
+        slots.delete(0).unwrap();
+        assert!(slots.list().is_empty());
+        assert_eq!(None, slots.load::<u32>(0).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,378 @@
+//! Describing the layout of encoded data at runtime.
+//! A Schema says what is in a buffer, so it can be read into a Value without knowing the Rust type,
+//! for tools and for converting to other formats. Schemas are Bufferable themselves,
+//! SelfDescribing writes one in front of a value so the buffer can be read by anyone.
+//! Schema::Custom stands for a type whose layout only a registry::Codecs knows,
+//! read and written with decode_with and encode_with.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::schema::*;
+//! let mut buffer = Vec::new();
+//! (3u16, String::from("hi")).into_buffer(&mut buffer);
+//! let schema = <(u16,String)>::schema();
+//! let value = schema.decode(&mut ReadBuffer::from_raw(buffer)).unwrap();
+//! assert_eq!(Value::Seq(vec![Value::UInt(3), Value::Str(String::from("hi"))]), value);
+//! ```
+use std::collections::{ HashMap, HashSet };
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ByteVec, CanonicalF32, CanonicalF64, LossyString, ReadBuffer, Sink };
+use crate::registry::Codecs;
+
+/// Layout of an encoded value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Schema{
+    /// A u8.
+    U8,
+    /// A u16.
+    U16,
+    /// A u32.
+    U32,
+    /// A u64, or a usize.
+    U64,
+    /// A f32.
+    F32,
+    /// A f64.
+    F64,
+    /// A String.
+    Str,
+    /// A ByteVec.
+    Bytes,
+    /// A Vec of the inner schema.
+    Vec(Box<Schema>),
+    /// A tuple or struct, its fields one after the other.
+    Tuple(Vec<Schema>),
+    /// A map from the first schema to the second.
+    Map(Box<Schema>, Box<Schema>),
+    /// A type with this id in a Codecs.
+    Custom(u32),
+}
+
+/// A decoded value, without its Rust type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value{
+    /// Any of the unsigned integers.
+    UInt(u64),
+    /// Either float.
+    Float(f64),
+    /// A string.
+    Str(String),
+    /// Bytes.
+    Bytes(Buffer),
+    /// A Vec or a tuple.
+    Seq(Vec<Value>),
+    /// A map, in the order of its entries in the buffer.
+    Map(Vec<(Value, Value)>),
+}
+
+impl Schema{
+    /// Read a value with this layout. None for Custom types.
+    pub fn decode(&self, buf: &mut ReadBuffer) -> Option<Value>{
+        self.decode_with(buf, &Codecs::new())
+    }
+    /// Read a value with this layout, reading Custom types with codecs.
+    pub fn decode_with(&self, buf: &mut ReadBuffer, codecs: &Codecs) -> Option<Value>{
+        Option::Some(match self{
+            Schema::U8 => Value::UInt(u64::from(u8::from_buffer(buf)?)),
+            Schema::U16 => Value::UInt(u64::from(u16::from_buffer(buf)?)),
+            Schema::U32 => Value::UInt(u64::from(u32::from_buffer(buf)?)),
+            Schema::U64 => Value::UInt(u64::from_buffer(buf)?),
+            Schema::F32 => Value::Float(f64::from(f32::from_buffer(buf)?)),
+            Schema::F64 => Value::Float(f64::from_buffer(buf)?),
+            Schema::Str => Value::Str(String::from_buffer(buf)?),
+            Schema::Bytes => Value::Bytes(ByteVec::from_buffer(buf)?.0),
+            Schema::Vec(inner) => buf.nested(|buf|{
+                let len = buf.read_len()?;
+                buf.use_bytes(len.checked_mul(std::mem::size_of::<Value>())?)?;
+                let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+                for _ in 0..len{
+                    vec.push(inner.decode_with(buf, codecs)?);
+                }
+                Option::Some(Value::Seq(vec))
+            })?,
+            Schema::Tuple(fields) => buf.nested(|buf|{
+                fields.iter().map(|f| f.decode_with(buf, codecs)).collect::<Option<Vec<_>>>().map(Value::Seq)
+            })?,
+            Schema::Map(k, v) => buf.nested(|buf|{
+                let len = buf.read_len()?;
+                buf.use_bytes(len.checked_mul(std::mem::size_of::<(Value, Value)>())?)?;
+                let mut vec = Vec::with_capacity(len.min(buf.remaining()));
+                for _ in 0..len{
+                    vec.push((k.decode_with(buf, codecs)?, v.decode_with(buf, codecs)?));
+                }
+                Option::Some(Value::Map(vec))
+            })?,
+            Schema::Custom(id) => codecs.decode(*id, buf)?,
+        })
+    }
+    /// Write a value with this layout.
+    /// Returns false if the value does not fit, in which case part of it may have been written.
+    /// Always false for Custom types.
+    pub fn encode<S: Sink + ?Sized>(&self, value: &Value, vec: &mut S) -> bool{
+        self.encode_with(value, vec, &Codecs::new())
+    }
+    /// Write a value with this layout, writing Custom types with codecs.
+    pub fn encode_with<S: Sink + ?Sized>(&self, value: &Value, vec: &mut S, codecs: &Codecs) -> bool{
+        match (self, value){
+            (Schema::U8, Value::UInt(x)) => match u8::try_from(*x){
+                Ok(x) => x.into_buffer(vec),
+                Err(_) => return false,
+            },
+            (Schema::U16, Value::UInt(x)) => match u16::try_from(*x){
+                Ok(x) => x.into_buffer(vec),
+                Err(_) => return false,
+            },
+            (Schema::U32, Value::UInt(x)) => match u32::try_from(*x){
+                Ok(x) => x.into_buffer(vec),
+                Err(_) => return false,
+            },
+            (Schema::U64, Value::UInt(x)) => x.into_buffer(vec),
+            (Schema::F32, Value::Float(x)) => (*x as f32).into_buffer(vec),
+            (Schema::F64, Value::Float(x)) => x.into_buffer(vec),
+            (Schema::Str, Value::Str(x)) => x.copy_into_buffer(vec),
+            (Schema::Bytes, Value::Bytes(x)) => {
+                (x.len() as u64).into_buffer(vec);
+                vec.write_bytes(x);
+            },
+            (Schema::Vec(inner), Value::Seq(xs)) => {
+                (xs.len() as u64).into_buffer(vec);
+                return xs.iter().all(|x| inner.encode_with(x, vec, codecs));
+            },
+            (Schema::Tuple(fields), Value::Seq(xs)) => {
+                return fields.len() == xs.len()
+                    && fields.iter().zip(xs).all(|(f, x)| f.encode_with(x, vec, codecs));
+            },
+            (Schema::Map(k, v), Value::Map(xs)) => {
+                (xs.len() as u64).into_buffer(vec);
+                return xs.iter().all(|(x, y)| k.encode_with(x, vec, codecs) && v.encode_with(y, vec, codecs));
+            },
+            (Schema::Custom(id), value) => return codecs.encode(*id, value, vec),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Bufferable for Schema{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        match self{
+            Schema::U8 => 0u8.into_buffer(vec),
+            Schema::U16 => 1u8.into_buffer(vec),
+            Schema::U32 => 2u8.into_buffer(vec),
+            Schema::U64 => 3u8.into_buffer(vec),
+            Schema::F32 => 4u8.into_buffer(vec),
+            Schema::F64 => 5u8.into_buffer(vec),
+            Schema::Str => 6u8.into_buffer(vec),
+            Schema::Bytes => 7u8.into_buffer(vec),
+            Schema::Vec(inner) => {
+                8u8.into_buffer(vec);
+                inner.copy_into_buffer(vec);
+            },
+            Schema::Tuple(fields) => {
+                9u8.into_buffer(vec);
+                fields.copy_into_buffer(vec);
+            },
+            Schema::Map(k, v) => {
+                10u8.into_buffer(vec);
+                k.copy_into_buffer(vec);
+                v.copy_into_buffer(vec);
+            },
+            Schema::Custom(id) => {
+                11u8.into_buffer(vec);
+                id.into_buffer(vec);
+            },
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        Option::Some(match u8::from_buffer(buf)?{
+            0 => Schema::U8,
+            1 => Schema::U16,
+            2 => Schema::U32,
+            3 => Schema::U64,
+            4 => Schema::F32,
+            5 => Schema::F64,
+            6 => Schema::Str,
+            7 => Schema::Bytes,
+            8 => Schema::Vec(Box::new(buf.nested(Schema::from_buffer)?)),
+            9 => Schema::Tuple(Vec::from_buffer(buf)?),
+            10 => {
+                let (k, v) = buf.nested(<(Schema, Schema)>::from_buffer)?;
+                Schema::Map(Box::new(k), Box::new(v))
+            },
+            11 => Schema::Custom(u32::from_buffer(buf)?),
+            _ => return Option::None,
+        })
+    }
+}
+
+/// Bufferable type that knows its Schema.
+pub trait Described: Bufferable{
+    /// Layout of this type in a buffer.
+    fn schema() -> Schema;
+}
+
+impl Described for u8{ fn schema() -> Schema{ Schema::U8 } }
+impl Described for u16{ fn schema() -> Schema{ Schema::U16 } }
+impl Described for u32{ fn schema() -> Schema{ Schema::U32 } }
+impl Described for u64{ fn schema() -> Schema{ Schema::U64 } }
+impl Described for usize{ fn schema() -> Schema{ Schema::U64 } }
+impl Described for f32{ fn schema() -> Schema{ Schema::F32 } }
+impl Described for f64{ fn schema() -> Schema{ Schema::F64 } }
+impl Described for CanonicalF32{ fn schema() -> Schema{ Schema::F32 } }
+impl Described for CanonicalF64{ fn schema() -> Schema{ Schema::F64 } }
+impl Described for String{ fn schema() -> Schema{ Schema::Str } }
+impl Described for LossyString{ fn schema() -> Schema{ Schema::Str } }
+impl Described for ByteVec{ fn schema() -> Schema{ Schema::Bytes } }
+
+impl<T: Described> Described for Vec<T>{
+    fn schema() -> Schema{
+        Schema::Vec(Box::new(T::schema()))
+    }
+}
+
+impl<T: Described + Eq + std::hash::Hash> Described for HashSet<T>{
+    fn schema() -> Schema{
+        Schema::Vec(Box::new(T::schema()))
+    }
+}
+
+impl<K: Described + Eq + std::hash::Hash, V: Described> Described for HashMap<K,V>{
+    fn schema() -> Schema{
+        Schema::Map(Box::new(K::schema()), Box::new(V::schema()))
+    }
+}
+
+impl<U: Described, V: Described> Described for (U,V){
+    fn schema() -> Schema{
+        Schema::Tuple(vec![U::schema(), V::schema()])
+    }
+}
+
+impl<U: Described, V: Described, W: Described> Described for (U,V,W){
+    fn schema() -> Schema{
+        Schema::Tuple(vec![U::schema(), V::schema(), W::schema()])
+    }
+}
+
+impl<U: Described, V: Described, W: Described, X: Described> Described for (U,V,W,X){
+    fn schema() -> Schema{
+        Schema::Tuple(vec![U::schema(), V::schema(), W::schema(), X::schema()])
+    }
+}
+
+/// Writes the Schema of T in front of it, so the buffer describes itself.
+/// read_self_describing reads such a buffer without knowing T.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::schema::*;
+/// let mut buffer = Vec::new();
+/// SelfDescribing(vec![1u8,2]).into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let (schema, value) = read_self_describing(&mut buffer).unwrap();
+/// assert_eq!(Vec::<u8>::schema(), schema);
+/// assert_eq!(Value::Seq(vec![Value::UInt(1), Value::UInt(2)]), value);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelfDescribing<T>(pub T);
+
+impl<T: Described> Bufferable for SelfDescribing<T>{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        T::schema().into_buffer(vec);
+        self.0.into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        T::schema().into_buffer(vec);
+        self.0.copy_into_buffer(vec);
+    }
+
+    /// Fails if the schema in the buffer is not the one of T.
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        if Schema::from_buffer(buf)? != T::schema(){
+            return Option::None;
+        }
+        T::from_buffer(buf).map(Self)
+    }
+}
+/// Read a value written by SelfDescribing, together with its schema.
+pub fn read_self_describing(buf: &mut ReadBuffer) -> Option<(Schema, Value)>{
+    read_self_describing_with(buf, &Codecs::new())
+}
+
+/// Read a value written by SelfDescribing, reading Custom types with codecs.
+pub fn read_self_describing_with(buf: &mut ReadBuffer, codecs: &Codecs) -> Option<(Schema, Value)>{
+    let schema = Schema::from_buffer(buf)?;
+    let value = schema.decode_with(buf, codecs)?;
+    Option::Some((schema, value))
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::schema::*;
+
+    #[test]
+    fn test_schema(){
+        type T = (Vec<(u8,f32)>, HashMap<String,ByteVec>, u64, Vec<Vec<u32>>);
+        let mut map = HashMap::new();
+        map.insert(String::from("k"), ByteVec(vec![1]));
+        let x: T = (vec![(1, 0.5), (2, 1.5)], map, 9, vec![vec![], vec![3]]);
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        let value = T::schema().decode(&mut ReadBuffer::from_raw(buffer.clone())).unwrap();
+        let mut again = Vec::new();
+        assert!(T::schema().encode(&value, &mut again));
+        assert_eq!(buffer, again);
+        // the schema round trips too
+        let mut schema = Vec::new();
+        T::schema().into_buffer(&mut schema);
+        assert_eq!(Some(T::schema()), Schema::from_buffer(&mut ReadBuffer::from_raw(schema)));
+        // values that do not fit
+        assert!(!Schema::U8.encode(&Value::UInt(256), &mut Vec::new()));
+        assert!(!Schema::Str.encode(&Value::UInt(1), &mut Vec::new()));
+        assert!(!<(u8,u8)>::schema().encode(&Value::Seq(vec![Value::UInt(1)]), &mut Vec::new()));
+    }
+
+    #[test]
+    fn test_self_describing(){
+        let mut buffer = Vec::new();
+        SelfDescribing((1u16, String::from("a"))).into_buffer(&mut buffer);
+        let x = SelfDescribing::<(u16,String)>::from_buffer(&mut ReadBuffer::from_raw(buffer.clone()));
+        assert_eq!(Some(SelfDescribing((1, String::from("a")))), x);
+        assert_eq!(None, SelfDescribing::<(u32,String)>::from_buffer(&mut ReadBuffer::from_raw(buffer)));
+        // a deeply nested schema is stopped by the depth limit
+        let mut deep = vec![8u8; 100000];
+        deep.push(0);
+        assert_eq!(None, Schema::from_buffer(&mut ReadBuffer::from_raw(deep)));
+    }
+
+    #[test]
+    fn test_custom_schema(){
+        // a plugin type of a u32 written as its decimal digits
+        let mut codecs = Codecs::new();
+        codecs.register(5,
+            |buf| String::from_buffer(buf)?.parse().ok().map(Value::UInt),
+            |value, vec| match value{
+                Value::UInt(x) => { x.to_string().into_buffer(vec); true },
+                _ => false,
+            });
+        let schema = Schema::Tuple(vec![Schema::U8, Schema::Vec(Box::new(Schema::Custom(5)))]);
+        let value = Value::Seq(vec![Value::UInt(1), Value::Seq(vec![Value::UInt(42)])]);
+        let mut buffer = Vec::new();
+        schema.copy_into_buffer(&mut buffer);
+        assert!(schema.encode_with(&value, &mut buffer, &codecs));
+        assert_eq!(&[0, 0, 0, 0, 0, 0, 0, 2, b'4', b'2'], &buffer[buffer.len() - 10..]);
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        assert_eq!(Some((schema.clone(), value.clone())), read_self_describing_with(&mut read, &codecs));
+        // without the codec the type can not be read or written
+        assert_eq!(None, read_self_describing(&mut ReadBuffer::from_raw(buffer)));
+        assert!(!schema.encode(&value, &mut Vec::new()));
+        assert!(!Schema::Custom(6).encode_with(&Value::UInt(1), &mut Vec::new(), &codecs));
+    }
+}
@@ -0,0 +1,238 @@
+//! Bytes and strings for key material and tokens, enabled with the `zeroize` feature.
+//! SecretBuffer and SecretString overwrite their memory with zeros when they are dropped,
+//! with volatile writes the compiler can not leave out. They have the same layout as
+//! ByteVec and String. Decoding copies the bytes once, from the ReadBuffer straight into an
+//! allocation of the right size, so no grown and freed temporaries are left behind.
+//! The buffers they are written to and read from are not theirs to clear,
+//! zero_vec does that for a Buffer once it is no longer needed.
+//! They are not PartialEq, ct_eq compares them in a time that does not depend on where they differ.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::secret::*;
+//! let key = SecretBuffer::new(vec![1, 2, 3, 4]);
+//! let mut buffer = Vec::new();
+//! key.copy_into_buffer(&mut buffer);
+//! let mut read = ReadBuffer::from_raw(buffer);
+//! let again = SecretBuffer::from_buffer(&mut read).unwrap();
+//! assert_eq!(&[1, 2, 3, 4][..], again.expose());
+//! assert!(key.ct_eq(&again));
+//! zero_vec(read.into_raw());
+//! assert_eq!("SecretBuffer(4 bytes)", format!("{:?}", again));
+//! ```
+use std::sync::atomic::{ compiler_fence, Ordering };
+use crate::{ Buffer, Bufferable, BufferError, ReadBuffer, Sink };
+
+/// Overwrite bytes with zeros in a way that is not optimized away.
+pub fn zero_bytes(bytes: &mut [u8]){
+    for b in bytes.iter_mut(){
+        // SAFETY: b is a valid, aligned &mut u8.
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Zero all of the memory of vec, also the capacity past its length, and free it.
+pub fn zero_vec(mut vec: Buffer){
+    let cap = vec.capacity();
+    // SAFETY: the whole capacity is allocated and u8 has no invalid values.
+    unsafe { vec.set_len(cap) };
+    zero_bytes(&mut vec);
+}
+
+/// Whether a and b are equal, looking at every byte whatever the first difference is.
+/// Only the lengths are compared early, they are not secret.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool{
+    if a.len() != b.len(){
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b){
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+// Read a length prefixed block into an allocation of exactly its size.
+fn read_exact(buf: &mut ReadBuffer) -> Option<Buffer>{
+    let len = buf.read_len()?;
+    buf.use_bytes(len)?;
+    let bytes = buf.read_bytes(len)?;
+    let mut vec = Vec::with_capacity(bytes.len());
+    vec.extend_from_slice(bytes);
+    Option::Some(vec)
+}
+
+/// Bytes that are zeroed when dropped. Debug does not show them.
+#[derive(Clone, Default)]
+pub struct SecretBuffer(Buffer);
+
+impl SecretBuffer{
+    /// Take ownership of bytes.
+    pub fn new(bytes: Buffer) -> Self{
+        Self(bytes)
+    }
+    /// The bytes.
+    pub fn expose(&self) -> &[u8]{
+        &self.0
+    }
+    /// The bytes, to change them in place. The slice can not grow or shrink, so they stay
+    /// in the one allocation that is zeroed on drop.
+    pub fn expose_mut(&mut self) -> &mut [u8]{
+        &mut self.0
+    }
+    /// Compare in constant time, see ct_eq.
+    pub fn ct_eq(&self, other: &Self) -> bool{
+        ct_eq(&self.0, &other.0)
+    }
+    /// Number of bytes.
+    pub fn len(&self) -> usize{
+        self.0.len()
+    }
+    /// Whether there are no bytes.
+    pub fn is_empty(&self) -> bool{
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretBuffer{
+    fn drop(&mut self){
+        zero_vec(std::mem::take(&mut self.0));
+    }
+}
+
+impl std::fmt::Debug for SecretBuffer{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "SecretBuffer({} bytes)", self.0.len())
+    }
+}
+
+impl Bufferable for SecretBuffer{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (self.0.len() as u64).into_buffer(vec);
+        vec.write_bytes(&self.0);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        read_exact(buf).map(Self)
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        crate::ByteVec::validate(buf)
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.0.len()
+    }
+}
+
+/// A string that is zeroed when dropped. Debug does not show it.
+#[derive(Clone, Default)]
+pub struct SecretString(String);
+
+impl SecretString{
+    /// Take ownership of string.
+    pub fn new(string: String) -> Self{
+        Self(string)
+    }
+    /// The string.
+    pub fn expose(&self) -> &str{
+        &self.0
+    }
+    /// Compare in constant time, see ct_eq.
+    pub fn ct_eq(&self, other: &Self) -> bool{
+        ct_eq(self.0.as_bytes(), other.0.as_bytes())
+    }
+    /// Number of bytes.
+    pub fn len(&self) -> usize{
+        self.0.len()
+    }
+    /// Whether the string is empty.
+    pub fn is_empty(&self) -> bool{
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretString{
+    fn drop(&mut self){
+        zero_vec(std::mem::take(&mut self.0).into_bytes());
+    }
+}
+
+impl std::fmt::Debug for SecretString{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        write!(f, "SecretString({} bytes)", self.0.len())
+    }
+}
+
+impl Bufferable for SecretString{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (self.0.len() as u64).into_buffer(vec);
+        vec.write_bytes(self.0.as_bytes());
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let bytes = read_exact(buf)?;
+        if std::str::from_utf8(&bytes).is_err(){
+            zero_vec(bytes);
+            return Option::None;
+        }
+        // SAFETY: checked to be UTF-8 right above.
+        Option::Some(Self(unsafe { String::from_utf8_unchecked(bytes) }))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        String::validate(buf)
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.0.len()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::secret::*;
+
+    #[test]
+    fn test_secret(){
+        let token = SecretString::new(String::from("hunter2"));
+        let mut buffer = Vec::new();
+        token.copy_into_buffer(&mut buffer);
+        let mut plain = Vec::new();
+        String::from("hunter2").into_buffer(&mut plain);
+        assert_eq!(plain, buffer);
+        let read = SecretString::from_buffer(&mut ReadBuffer::from_raw(buffer)).unwrap();
+        assert_eq!(token.expose(), read.expose());
+        assert!(token.ct_eq(&read));
+        assert!(!token.ct_eq(&SecretString::new(String::from("hunter3"))));
+        assert!(!ct_eq(b"ab", b"abc"));
+        assert!(ct_eq(b"", b""));
+        assert_eq!(7, read.len());
+        assert_eq!("SecretString(7 bytes)", format!("{:?}", read));
+        let mut bad = Vec::new();
+        ByteVec(vec![0xff, 0xfe]).into_buffer(&mut bad);
+        assert!(SecretString::from_buffer(&mut ReadBuffer::from_raw(bad.clone())).is_none());
+        assert_eq!(Ok(()), validate::<SecretBuffer>(&mut ReadBuffer::from_raw(bad)));
+        let mut bytes = vec![1u8, 2, 3];
+        zero_bytes(&mut bytes);
+        assert_eq!(vec![0, 0, 0], bytes);
+        let mut key = SecretBuffer::new(vec![9; 5]);
+        key.expose_mut()[0] = 1;
+        assert_eq!(&[1, 9, 9, 9, 9][..], key.expose());
+        assert!(SecretBuffer::from_buffer(&mut ReadBuffer::from_raw(vec![0, 0, 0, 0, 0, 0, 0, 9, 1])).is_none());
+    }
+}
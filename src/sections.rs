@@ -0,0 +1,189 @@
+//! Files made of sections that can be read on their own, such as asset packs.
+//! A SectionWriter writes every section after the previous one and finish appends
+//! a table of contents: a Vec of (key, offset, length) and then the offset of that
+//! table as a u64. A SectionReader reads the table from the end, so it can jump
+//! straight to any section without reading the ones before it.
+//! Keys are any Bufferable, u32 for numbered sections or String for named ones.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::sections::*;
+//! let mut writer = SectionWriter::new();
+//! writer.add(String::from("names"), &vec![String::from("tree"), String::from("rock")]);
+//! writer.section(String::from("sizes"), |buf|{
+//!     1.5f32.into_buffer(buf);
+//!     0.5f32.into_buffer(buf);
+//! });
+//! let bytes = writer.finish();
+//! let reader = SectionReader::<String>::new(&bytes).unwrap();
+//! let mut sizes = reader.section(&String::from("sizes")).unwrap();
+//! assert_eq!(Some(1.5), f32::from_buffer(&mut sizes));
+//! assert_eq!(Some(vec![String::from("tree"), String::from("rock")]), reader.read(&String::from("names")));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, DecodeLimits, ReadBuffer };
+
+/// Writes sections one after another, followed by their table of contents.
+pub struct SectionWriter<K>{
+    buffer: Buffer,
+    table: Vec<(K, u64, u64)>,
+}
+
+impl<K: Bufferable + PartialEq> SectionWriter<K>{
+    /// A writer without sections.
+    pub fn new() -> Self{
+        Self{ buffer: Vec::new(), table: Vec::new() }
+    }
+    /// Write a section under key with f.
+    /// Panics if key is already used.
+    pub fn section<F: FnOnce(&mut Buffer)>(&mut self, key: K, f: F){
+        assert!(!self.contains(&key), "section key is used twice");
+        let start = self.buffer.len();
+        f(&mut self.buffer);
+        self.table.push((key, start as u64, (self.buffer.len() - start) as u64));
+    }
+    /// Write a section holding only x.
+    /// Panics if key is already used.
+    pub fn add<T: Bufferable>(&mut self, key: K, x: &T){
+        self.section(key, |buf| x.copy_into_buffer(buf));
+    }
+    /// Whether a section is written under key.
+    pub fn contains(&self, key: &K) -> bool{
+        self.table.iter().any(|(k, _, _)| k == key)
+    }
+    /// Number of sections.
+    pub fn len(&self) -> usize{
+        self.table.len()
+    }
+    /// Whether there are no sections.
+    pub fn is_empty(&self) -> bool{
+        self.table.is_empty()
+    }
+    /// Append the table of contents and give the bytes.
+    pub fn finish(self) -> Buffer{
+        let mut buffer = self.buffer;
+        let table_pos = buffer.len() as u64;
+        self.table.into_buffer(&mut buffer);
+        table_pos.into_buffer(&mut buffer);
+        buffer
+    }
+}
+
+impl<K: Bufferable + PartialEq> Default for SectionWriter<K>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+/// Reads the sections of what a SectionWriter wrote, in any order.
+pub struct SectionReader<'a, K>{
+    bytes: &'a [u8],
+    table: Vec<(K, usize, usize)>,
+    limits: DecodeLimits,
+}
+
+impl<'a, K: Bufferable + PartialEq> SectionReader<'a, K>{
+    /// Read the table of contents at the end of bytes.
+    /// None if it is missing or points outside the sections.
+    pub fn new(bytes: &'a [u8]) -> Option<Self>{
+        Self::with_limits(bytes, DecodeLimits::default())
+    }
+    /// Same as new, with limits for reading the table and the sections.
+    pub fn with_limits(bytes: &'a [u8], limits: DecodeLimits) -> Option<Self>{
+        let table_end = bytes.len().checked_sub(8)?;
+        let table_pos = u64::from_buffer(&mut ReadBuffer::borrowed(&bytes[table_end..], limits))?;
+        let table_pos = usize::try_from(table_pos).ok().filter(|&pos| pos <= table_end)?;
+        let mut buf = ReadBuffer::borrowed(&bytes[table_pos..table_end], limits);
+        let raw = Vec::<(K, u64, u64)>::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        let mut table = Vec::with_capacity(raw.len());
+        for (key, pos, len) in raw{
+            let pos = usize::try_from(pos).ok()?;
+            let len = usize::try_from(len).ok()?;
+            if pos.checked_add(len)? > table_pos{
+                return Option::None;
+            }
+            table.push((key, pos, len));
+        }
+        Option::Some(Self{ bytes, table, limits })
+    }
+    // Key, offset and length of every section.
+    pub(crate) fn table(&self) -> &[(K, usize, usize)]{
+        &self.table
+    }
+    /// Number of sections.
+    pub fn len(&self) -> usize{
+        self.table.len()
+    }
+    /// Whether there are no sections.
+    pub fn is_empty(&self) -> bool{
+        self.table.is_empty()
+    }
+    /// The keys, in the order the sections were written.
+    pub fn keys(&self) -> impl Iterator<Item = &K>{
+        self.table.iter().map(|(key, _, _)| key)
+    }
+    /// Whether there is a section under key.
+    pub fn contains(&self, key: &K) -> bool{
+        self.table.iter().any(|(k, _, _)| k == key)
+    }
+    /// A ReadBuffer over only the section under key, borrowing its bytes.
+    pub fn section(&self, key: &K) -> Option<ReadBuffer<'a>>{
+        let (_, pos, len) = self.table.iter().find(|(k, _, _)| k == key)?;
+        Option::Some(ReadBuffer::borrowed(&self.bytes[*pos..*pos + *len], self.limits))
+    }
+    /// Read the section under key as one T. None if it is missing,
+    /// does not decode or has bytes left over.
+    pub fn read<T: Bufferable>(&self, key: &K) -> Option<T>{
+        let mut buf = self.section(key)?;
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::sections::*;
+
+    #[test]
+    fn test_sections(){
+        let mut writer = SectionWriter::<u32>::new();
+        writer.add(7, &vec![1u16, 2, 3]);
+        writer.add(2, &String::from("two"));
+        writer.section(9, |_| {});
+        assert!(writer.contains(&2) && !writer.contains(&3));
+        assert_eq!(3, writer.len());
+        let bytes = writer.finish();
+        let table_pos = 14 + 11;
+        assert_eq!(&(table_pos as u64).to_be_bytes()[..], &bytes[bytes.len() - 8..]);
+        let reader = SectionReader::<u32>::new(&bytes).unwrap();
+        assert_eq!(vec![7, 2, 9], reader.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(Some(String::from("two")), reader.read(&2));
+        assert_eq!(Some(vec![1u16, 2, 3]), reader.read(&7));
+        assert_eq!(Some(0), reader.section(&9).map(|buf| buf.remaining()));
+        // missing, too short for the type, and bytes left over
+        assert!(reader.section(&1).is_none());
+        assert_eq!(None, reader.read::<u64>(&9));
+        assert_eq!(None, reader.read::<u64>(&7));
+        // a wrong table offset, and no table at all
+        let mut bad = bytes.clone();
+        let len = bad.len();
+        bad[len - 1] += 1;
+        assert!(SectionReader::<u32>::new(&bad).is_none());
+        assert!(SectionReader::<u32>::new(&bytes[..7]).is_none());
+        assert!(SectionReader::<u32>::new(&SectionWriter::<u32>::new().finish()).unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sections_twice(){
+        let mut writer = SectionWriter::new();
+        writer.add(String::from("a"), &1u8);
+        writer.add(String::from("a"), &2u8);
+    }
+}
@@ -0,0 +1,647 @@
+//! A serde Serializer and Deserializer for the format of this crate, enabled with the `serde` feature.
+//! Types that derive Serialize and Deserialize are written the way Bufferable writes the same
+//! shapes, so one side can use serde and the other Bufferable: numbers big endian, bool as a u8,
+//! char as a u32, strings, bytes, sequences and maps with a u64 length in front, Option with a
+//! u8 tag (0 for None), tuples and structs as their fields one after the other and enum variants
+//! as a u32 index and their fields, like #[derive(Bufferable)] without explicit tags.
+//! Maps are written in the order serde gives the entries, so a HashMap is only byte for byte
+//! the same as its Bufferable encoding when the entries happen to be in key order. Result is an
+//! enum to serde, so it gets a u32 tag where Bufferable uses a u8.
+//! The format does not say what type comes next, so deserialize_any and skipping unknown
+//! fields are not supported.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::serde_buffer::*;
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Player{ name: String, pos: (f32, f32), items: Vec<u16> }
+//! let player = Player{ name: String::from("ann"), pos: (1.0, 2.5), items: vec![3, 4] };
+//! let bytes = to_buffer(&player);
+//! // the same bytes as the Bufferable tuple of the fields
+//! assert_eq!(encode(&(String::from("ann"), (1.0f32, 2.5f32), vec![3u16, 4])), bytes);
+//! let mut buf = ReadBuffer::from_raw(bytes);
+//! assert_eq!(player, from_buffer_serde::<Player>(&mut buf).unwrap());
+//! ```
+use serde::{ ser, de, Serialize };
+use serde::de::{ DeserializeOwned, IntoDeserializer, Visitor };
+use crate::{ Buffer, Bufferable, BufferError, ByteVec, ReadBuffer };
+
+/// Why serializing or deserializing failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error{
+    /// The bytes do not hold the value, see BufferError.
+    Buffer(BufferError),
+    /// An error from a Serialize or Deserialize impl, or something the format does not support.
+    Message(String),
+}
+
+/// Result with the Error of this module.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl std::fmt::Display for Error{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        match self{
+            Error::Buffer(e) => e.fmt(f),
+            Error::Message(m) => f.write_str(m),
+        }
+    }
+}
+
+impl std::error::Error for Error{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>{
+        match self{
+            Error::Buffer(e) => Option::Some(e),
+            Error::Message(_) => Option::None,
+        }
+    }
+}
+
+impl From<BufferError> for Error{
+    fn from(e: BufferError) -> Self{
+        Error::Buffer(e)
+    }
+}
+
+impl ser::Error for Error{
+    fn custom<T: std::fmt::Display>(msg: T) -> Self{
+        Error::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error{
+    fn custom<T: std::fmt::Display>(msg: T) -> Self{
+        Error::Message(msg.to_string())
+    }
+}
+
+/// Encode x with its Serialize impl.
+pub fn to_buffer<T: Serialize + ?Sized>(x: &T) -> Buffer{
+    let mut vec = Vec::new();
+    x.serialize(&mut Serializer::new(&mut vec)).expect("serializing to a buffer can not fail");
+    vec
+}
+
+/// Encode x with its Serialize impl, failing with the error the impl gives.
+pub fn try_to_buffer<T: Serialize + ?Sized>(x: &T) -> Result<Buffer>{
+    let mut vec = Vec::new();
+    x.serialize(&mut Serializer::new(&mut vec))?;
+    Ok(vec)
+}
+
+/// Decode a T with its Deserialize impl. On failure the buffer is left where it was.
+pub fn from_buffer_serde<T: DeserializeOwned>(buf: &mut ReadBuffer) -> Result<T>{
+    let (iter, decoded) = (buf.iter, buf.decoded);
+    let res = T::deserialize(&mut Deserializer::new(buf));
+    if res.is_err(){
+        buf.iter = iter;
+        buf.decoded = decoded;
+    }
+    res
+}
+
+/// Writes serde values to a Buffer.
+pub struct Serializer<'v>{
+    vec: &'v mut Buffer,
+}
+
+impl<'v> Serializer<'v>{
+    /// Write to the end of vec.
+    pub fn new(vec: &'v mut Buffer) -> Self{
+        Self{ vec }
+    }
+
+    fn put<T: Bufferable>(&mut self, x: T) -> Result<()>{
+        x.into_buffer(self.vec);
+        Ok(())
+    }
+
+    // A length in front, or a placeholder filled in by end when serde does not know it.
+    fn prefixed(&mut self, len: Option<usize>) -> Compound<'_, 'v>{
+        let placeholder = match len{
+            Option::Some(len) => { (len as u64).into_buffer(self.vec); Option::None },
+            Option::None => { 0u64.into_buffer(self.vec); Option::Some(self.vec.len() - 8) },
+        };
+        Compound{ ser: self, placeholder, count: 0 }
+    }
+}
+
+/// Sequences and maps being written.
+pub struct Compound<'s, 'v>{
+    ser: &'s mut Serializer<'v>,
+    placeholder: Option<usize>,
+    count: u64,
+}
+
+impl Compound<'_, '_>{
+    fn end(self) -> Result<()>{
+        if let Option::Some(at) = self.placeholder{
+            self.ser.vec[at..at + 8].copy_from_slice(&self.count.to_be_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl<'s, 'v> ser::Serializer for &'s mut Serializer<'v>{
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'s, 'v>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Compound<'s, 'v>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()>{ self.put(v) }
+    fn serialize_i8(self, v: i8) -> Result<()>{ self.put(v) }
+    fn serialize_i16(self, v: i16) -> Result<()>{ self.put(v) }
+    fn serialize_i32(self, v: i32) -> Result<()>{ self.put(v) }
+    fn serialize_i64(self, v: i64) -> Result<()>{ self.put(v) }
+    fn serialize_i128(self, v: i128) -> Result<()>{ self.put(v) }
+    fn serialize_u8(self, v: u8) -> Result<()>{ self.put(v) }
+    fn serialize_u16(self, v: u16) -> Result<()>{ self.put(v) }
+    fn serialize_u32(self, v: u32) -> Result<()>{ self.put(v) }
+    fn serialize_u64(self, v: u64) -> Result<()>{ self.put(v) }
+    fn serialize_u128(self, v: u128) -> Result<()>{ self.put(v) }
+    fn serialize_f32(self, v: f32) -> Result<()>{ self.put(v) }
+    fn serialize_f64(self, v: f64) -> Result<()>{ self.put(v) }
+    fn serialize_char(self, v: char) -> Result<()>{ self.put(v) }
+
+    fn serialize_str(self, v: &str) -> Result<()>{
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()>{
+        (v.len() as u64).into_buffer(self.vec);
+        self.vec.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()>{
+        self.put(0u8)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()>{
+        self.put(1u8)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()>{
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()>{
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, index: u32, _variant: &'static str) -> Result<()>{
+        self.put(index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()>{
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, index: u32,
+        _variant: &'static str, value: &T) -> Result<()>
+    {
+        self.put(index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq>{
+        Ok(self.prefixed(len))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self>{
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self>{
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, index: u32, _variant: &'static str,
+        _len: usize) -> Result<Self>
+    {
+        self.put(index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap>{
+        Ok(self.prefixed(len))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self>{
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, index: u32, _variant: &'static str,
+        _len: usize) -> Result<Self>
+    {
+        self.put(index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool{
+        false
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_, '_>{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()>{
+        self.count += 1;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()>{
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_, '_>{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()>{
+        self.count += 1;
+        key.serialize(&mut *self.ser)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()>{
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()>{
+        Compound::end(self)
+    }
+}
+
+// Tuples, structs and the fields of variants are their fields one after the other.
+macro_rules! fields_impl{
+    ($trait:ident, $method:ident $(, $key:ident)?) => {
+        impl ser::$trait for &mut Serializer<'_>{
+            type Ok = ();
+            type Error = Error;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, $($key: &'static str,)? value: &T) -> Result<()>{
+                $(let _ = $key;)?
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<()>{
+                Ok(())
+            }
+        }
+    };
+}
+
+fields_impl!(SerializeTuple, serialize_element);
+fields_impl!(SerializeTupleStruct, serialize_field);
+fields_impl!(SerializeTupleVariant, serialize_field);
+fields_impl!(SerializeStruct, serialize_field, key);
+fields_impl!(SerializeStructVariant, serialize_field, key);
+
+/// Reads serde values from a ReadBuffer, with its limits.
+/// Strings and bytes are borrowed when the ReadBuffer borrows its bytes, see ReadBuffer::from_slice.
+pub struct Deserializer<'r, 'a>{
+    buf: &'r mut ReadBuffer<'a>,
+}
+
+impl<'r, 'a> Deserializer<'r, 'a>{
+    /// Read from buf, starting where it is.
+    pub fn new(buf: &'r mut ReadBuffer<'a>) -> Self{
+        Self{ buf }
+    }
+
+    /// Decode a T that may borrow from the bytes of the ReadBuffer, like a &str field.
+    pub fn deserialize<T: de::Deserialize<'a>>(&mut self) -> Result<T>{
+        T::deserialize(self)
+    }
+
+    fn read<T: Bufferable>(&mut self) -> Result<T>{
+        Ok(self.buf.read_checked()?)
+    }
+
+    // The error T::validate finds at the position at, moving back there.
+    fn failed<T: Bufferable>(&mut self, at: usize) -> Error{
+        self.buf.iter = at;
+        Error::Buffer(T::validate(&mut self.buf.fork()).err().unwrap_or(BufferError::Invalid))
+    }
+
+    fn nested<T, F: FnOnce(&mut Self) -> Result<T>>(&mut self, f: F) -> Result<T>{
+        if self.buf.depth >= self.buf.limits.max_depth{
+            return Err(Error::Buffer(BufferError::TooDeep));
+        }
+        self.buf.depth += 1;
+        let res = f(self);
+        self.buf.depth -= 1;
+        res
+    }
+
+    fn fields<V: Visitor<'a>>(&mut self, left: usize, visitor: V) -> Result<V::Value>{
+        visitor.visit_seq(Fields{ de: self, left })
+    }
+}
+
+impl<'a> de::Deserializer<'a> for &mut Deserializer<'_, 'a>{
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'a>>(self, _visitor: V) -> Result<V::Value>{
+        Err(Error::Message(String::from("the format does not say what type comes next")))
+    }
+
+    fn deserialize_bool<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_bool(self.read()?) }
+    fn deserialize_i8<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_i8(self.read()?) }
+    fn deserialize_i16<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_i16(self.read()?) }
+    fn deserialize_i32<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_i32(self.read()?) }
+    fn deserialize_i64<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_i64(self.read()?) }
+    fn deserialize_i128<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_i128(self.read()?) }
+    fn deserialize_u8<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_u8(self.read()?) }
+    fn deserialize_u16<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_u16(self.read()?) }
+    fn deserialize_u32<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_u32(self.read()?) }
+    fn deserialize_u64<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_u64(self.read()?) }
+    fn deserialize_u128<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_u128(self.read()?) }
+    fn deserialize_f32<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_f32(self.read()?) }
+    fn deserialize_f64<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_f64(self.read()?) }
+    fn deserialize_char<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{ visitor.visit_char(self.read()?) }
+
+    fn deserialize_str<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        if self.buf.borrowed_bytes().is_none(){
+            return visitor.visit_string(self.read::<String>()?);
+        }
+        let at = self.buf.iter;
+        match self.buf.borrow_str(){
+            Option::Some(s) => visitor.visit_borrowed_str(s),
+            Option::None => Err(self.failed::<String>(at)),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        if self.buf.borrowed_bytes().is_none(){
+            return visitor.visit_byte_buf(self.read::<ByteVec>()?.0);
+        }
+        let at = self.buf.iter;
+        match self.buf.borrow_blob(){
+            Option::Some(b) => visitor.visit_borrowed_bytes(b),
+            Option::None => Err(self.failed::<ByteVec>(at)),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        match self.read::<u8>()?{
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            _ => Err(Error::Buffer(BufferError::InvalidDiscriminant)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'a>>(self, _name: &'static str, visitor: V) -> Result<V::Value>{
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'a>>(self, _name: &'static str, visitor: V) -> Result<V::Value>{
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        self.nested(|de|{
+            let len = de.buf.check_prefix(de.buf.limits.max_elements)?;
+            de.fields(len, visitor)
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'a>>(self, len: usize, visitor: V) -> Result<V::Value>{
+        self.fields(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'a>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value>{
+        self.fields(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        self.nested(|de|{
+            let len = de.buf.check_prefix(de.buf.limits.max_elements)?;
+            visitor.visit_map(Fields{ de, left: len })
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'a>>(self, _name: &'static str, fields: &'static [&'static str],
+        visitor: V) -> Result<V::Value>
+    {
+        self.fields(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'a>>(self, _name: &'static str, _variants: &'static [&'static str],
+        visitor: V) -> Result<V::Value>
+    {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value>{
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'a>>(self, _visitor: V) -> Result<V::Value>{
+        Err(Error::Message(String::from("the format can not skip a value of unknown type")))
+    }
+
+    fn is_human_readable(&self) -> bool{
+        false
+    }
+}
+
+// The next left elements of a sequence, tuple or struct, or entries of a map.
+struct Fields<'d, 'r, 'a>{
+    de: &'d mut Deserializer<'r, 'a>,
+    left: usize,
+}
+
+impl<'a> de::SeqAccess<'a> for Fields<'_, '_, 'a>{
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'a>>(&mut self, seed: T) -> Result<Option<T::Value>>{
+        if self.left == 0{
+            return Ok(Option::None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Option::Some)
+    }
+
+    fn size_hint(&self) -> Option<usize>{
+        // a lying length should not make the visitor allocate much
+        Option::Some(self.left.min(self.de.buf.remaining()))
+    }
+}
+
+impl<'a> de::MapAccess<'a> for Fields<'_, '_, 'a>{
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'a>>(&mut self, seed: K) -> Result<Option<K::Value>>{
+        if self.left == 0{
+            return Ok(Option::None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Option::Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'a>>(&mut self, seed: V) -> Result<V::Value>{
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize>{
+        Option::Some(self.left.min(self.de.buf.remaining()))
+    }
+}
+
+impl<'a> de::EnumAccess<'a> for &mut Deserializer<'_, 'a>{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'a>>(self, seed: V) -> Result<(V::Value, Self)>{
+        let index = self.read::<u32>()?;
+        let variant = seed.deserialize(index.into_deserializer())
+            .map_err(|_: Error| Error::Buffer(BufferError::InvalidDiscriminant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'a> de::VariantAccess<'a> for &mut Deserializer<'_, 'a>{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()>{
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'a>>(self, seed: T) -> Result<T::Value>{
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'a>>(self, len: usize, visitor: V) -> Result<V::Value>{
+        self.fields(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'a>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>{
+        self.fields(fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::serde_buffer::*;
+    use std::collections::BTreeMap;
+    use serde::{ Serialize, Deserialize };
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    enum Shape{ Dot, Circle(f32), Rect{ w: u16, h: u16 }, Line(i8, i8) }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Unit;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Id(u64);
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Scene{
+        id: Id,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, i32>,
+        parent: Option<Box<Scene>>,
+        unit: Unit,
+        big: (i128, u128, char, bool),
+        #[serde(with = "serde_bytes_like")]
+        data: Vec<u8>,
+    }
+
+    // bytes as bytes instead of a sequence of u8
+    mod serde_bytes_like{
+        pub fn serialize<S: serde::Serializer>(x: &[u8], s: S) -> Result<S::Ok, S::Error>{
+            s.serialize_bytes(x)
+        }
+        pub fn deserialize<'a, D: serde::Deserializer<'a>>(d: D) -> Result<Vec<u8>, D::Error>{
+            struct V;
+            impl<'a> serde::de::Visitor<'a> for V{
+                type Value = Vec<u8>;
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{ f.write_str("bytes") }
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<u8>, E>{ Ok(v.to_vec()) }
+            }
+            d.deserialize_bytes(V)
+        }
+    }
+
+    #[test]
+    fn test_serde_buffer(){
+        let mut tags = BTreeMap::new();
+        tags.insert(String::from("b"), -2);
+        tags.insert(String::from("a"), 1);
+        let child = Scene{ id: Id(1), shapes: vec![Shape::Dot], tags: BTreeMap::new(), parent: None,
+            unit: Unit, big: (-1, 2, 'x', false), data: Vec::new() };
+        let scene = Scene{
+            id: Id(2),
+            shapes: vec![Shape::Circle(0.5), Shape::Rect{ w: 3, h: 4 }, Shape::Line(-1, 1)],
+            tags: tags.clone(),
+            parent: Some(Box::new(child)),
+            unit: Unit,
+            big: (i128::MIN, u128::MAX, 'é', true),
+            data: vec![9, 8, 7],
+        };
+        let bytes = to_buffer(&scene);
+        let mut buf = ReadBuffer::from_raw(bytes.clone());
+        assert_eq!(scene, from_buffer_serde::<Scene>(&mut buf).unwrap());
+        assert_eq!(0, buf.remaining());
+        // the same bytes as Bufferable writes
+        assert_eq!(encode(&tags), to_buffer(&tags));
+        assert_eq!(encode(&(Some(3u32), None::<u8>, vec![String::from("x")])),
+            to_buffer(&(Some(3u32), None::<u8>, vec![String::from("x")])));
+        assert_eq!(encode(&(2u32, 3u16, 4u16)), to_buffer(&Shape::Rect{ w: 3, h: 4 }));
+        assert_eq!(encode(&ByteVec(vec![9, 8, 7])), bytes[bytes.len() - 11..].to_vec());
+        let mut buf = ReadBuffer::from_raw(encode(&(7u64, vec![1i32, 2])));
+        assert_eq!((7u64, vec![1i32, 2]), from_buffer_serde(&mut buf).unwrap());
+        // an iterator without a known length
+        struct Odd;
+        impl Serialize for Odd{
+            fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error>{
+                s.collect_seq((0..10u8).filter(|x| x % 2 == 1))
+            }
+        }
+        assert_eq!(encode(&vec![1u8, 3, 5, 7, 9]), to_buffer(&Odd));
+        // borrowing from the bytes
+        let bytes = to_buffer(&(String::from("name"), 5u8));
+        let mut buf = ReadBuffer::from_slice(&bytes);
+        let (name, n): (&str, u8) = Deserializer::new(&mut buf).deserialize().unwrap();
+        assert_eq!(("name", 5), (name, n));
+        // broken input leaves the buffer where it was
+        let mut buf = ReadBuffer::from_raw(vec![0, 0, 0, 9]);
+        assert_eq!(Err(Error::Buffer(BufferError::InvalidDiscriminant)), from_buffer_serde::<Shape>(&mut buf));
+        assert_eq!(0, buf.consumed());
+        let mut buf = ReadBuffer::from_raw(vec![2]);
+        assert_eq!(Err(Error::Buffer(BufferError::InvalidDiscriminant)), from_buffer_serde::<Option<u8>>(&mut buf));
+        let mut buf = ReadBuffer::from_raw(vec![0, 0, 0, 0, 0, 0, 0, 2, 0xff, 0xfe]);
+        assert_eq!(Err(Error::Buffer(BufferError::InvalidUtf8)), from_buffer_serde::<String>(&mut buf));
+        let mut buf = ReadBuffer::from_slice(&[0, 0, 0, 0, 0, 0, 0, 2, 0xff, 0xfe]);
+        assert_eq!(Err(Error::Buffer(BufferError::InvalidUtf8)), Deserializer::new(&mut buf).deserialize::<&str>());
+        let mut buf = ReadBuffer::from_raw(vec![0, 0, 0, 1]);
+        assert_eq!(Err(Error::Buffer(BufferError::UnexpectedEof{ needed: 8, available: 4 })), from_buffer_serde::<u64>(&mut buf));
+        let limits = DecodeLimits{ max_depth: 1, ..DecodeLimits::default() };
+        let mut buf = ReadBuffer::from_raw(encode(&vec![vec![1u8]])).with_limits(limits);
+        assert_eq!(Err(Error::Buffer(BufferError::TooDeep)), from_buffer_serde::<Vec<Vec<u8>>>(&mut buf));
+        let mut buf = ReadBuffer::from_raw(encode(&1u8));
+        assert!(matches!(from_buffer_serde::<serde::de::IgnoredAny>(&mut buf), Err(Error::Message(_))));
+    }
+}
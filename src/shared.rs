@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use crate::{ Buffer, DecodeLimits, ReadBuffer };
+
+/// Bytes shared between threads, each reading them with its own ReadBuffer.
+/// Cloning only clones the Arc, so every thread can have its own handle
+/// without copying the data.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = Vec::new();
+/// vec![1u32, 2, 3].into_buffer(&mut buffer);
+/// String::from("second").into_buffer(&mut buffer);
+/// let shared = SharedReadBuffer::new(buffer);
+/// let handles: Vec<_> = (0..2).map(|_|{
+///     let shared = shared.clone();
+///     std::thread::spawn(move || Vec::<u32>::from_buffer(&mut shared.reader()))
+/// }).collect();
+/// for handle in handles{
+///     assert_eq!(Some(vec![1, 2, 3]), handle.join().unwrap());
+/// }
+/// let mut second = shared.reader_at(20).unwrap();
+/// assert_eq!(Some(String::from("second")), String::from_buffer(&mut second));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedReadBuffer{
+    bytes: Arc<[u8]>,
+    limits: DecodeLimits,
+}
+
+impl SharedReadBuffer{
+    /// Share the bytes of a Buffer.
+    pub fn new(vec: Buffer) -> Self{
+        Self::from_arc(vec.into())
+    }
+    /// Share bytes that already are in an Arc.
+    pub fn from_arc(bytes: Arc<[u8]>) -> Self{
+        Self{ bytes, limits: DecodeLimits::default() }
+    }
+    /// Set the limits every reader starts with.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self{
+        self.limits = limits;
+        self
+    }
+    /// The limits every reader starts with.
+    pub fn limits(&self) -> &DecodeLimits{
+        &self.limits
+    }
+    /// All the bytes.
+    pub fn as_slice(&self) -> &[u8]{
+        &self.bytes
+    }
+    /// Number of bytes.
+    pub fn len(&self) -> usize{
+        self.bytes.len()
+    }
+    /// Whether there are no bytes.
+    pub fn is_empty(&self) -> bool{
+        self.bytes.is_empty()
+    }
+    /// The Arc holding the bytes.
+    pub fn as_arc(&self) -> &Arc<[u8]>{
+        &self.bytes
+    }
+    /// A reader from the start. It borrows the bytes and has its own position.
+    pub fn reader(&self) -> ReadBuffer<'_>{
+        self.section(0, self.bytes.len()).unwrap()
+    }
+    /// A reader starting at pos, None if pos is past the end.
+    pub fn reader_at(&self, pos: usize) -> Option<ReadBuffer<'_>>{
+        self.section(pos, self.bytes.len().checked_sub(pos)?)
+    }
+    /// A reader over only the len bytes at pos, None if they are not all there.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let shared = SharedReadBuffer::new(vec![0, 1, 0, 2, 0, 3]);
+    /// let mut section = shared.section(2, 2).unwrap();
+    /// assert_eq!(Some(2), u16::from_buffer(&mut section));
+    /// assert_eq!(None, u16::from_buffer(&mut section));
+    /// assert!(shared.section(4, 3).is_none());
+    /// ```
+    pub fn section(&self, pos: usize, len: usize) -> Option<ReadBuffer<'_>>{
+        let bytes = self.bytes.get(pos..pos.checked_add(len)?)?;
+        Option::Some(ReadBuffer::borrowed(bytes, self.limits))
+    }
+}
+
+impl From<Buffer> for SharedReadBuffer{
+    fn from(vec: Buffer) -> Self{
+        Self::new(vec)
+    }
+}
+
+impl From<Arc<[u8]>> for SharedReadBuffer{
+    fn from(bytes: Arc<[u8]>) -> Self{
+        Self::from_arc(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_shared(){
+        let mut buffer = Vec::new();
+        let parts: Vec<Vec<u64>> = (0..4).map(|i| (0..100).map(|j| i * 1000 + j).collect()).collect();
+        let mut offsets = Vec::new();
+        for part in &parts{
+            offsets.push(buffer.len());
+            part.copy_into_buffer(&mut buffer);
+        }
+        let shared = SharedReadBuffer::new(buffer);
+        let handles: Vec<_> = offsets.iter().map(|&pos|{
+            let shared = shared.clone();
+            std::thread::spawn(move || Vec::<u64>::from_buffer(&mut shared.reader_at(pos).unwrap()))
+        }).collect();
+        for (handle, part) in handles.into_iter().zip(parts.iter()){
+            assert_eq!(Some(part.clone()), handle.join().unwrap());
+        }
+        // readers do not move each other
+        let mut a = shared.reader();
+        let mut b = shared.reader();
+        assert_eq!(Some(100), u64::from_buffer(&mut a));
+        assert_eq!(Some(100), u64::from_buffer(&mut b));
+        assert_eq!(0, shared.reader_at(shared.len()).unwrap().remaining());
+        assert!(shared.reader_at(shared.len() + 1).is_none());
+        assert!(shared.section(usize::MAX, 2).is_none());
+        // limits carry over to every reader
+        let limits = DecodeLimits{ max_elements: 10, ..DecodeLimits::default() };
+        let shared = SharedReadBuffer::from(Arc::clone(shared.as_arc())).with_limits(limits);
+        assert_eq!(None, Vec::<u64>::from_buffer(&mut shared.reader()));
+    }
+}
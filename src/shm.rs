@@ -0,0 +1,232 @@
+//! Exchanging state between processes on the same host through a named shared segment.
+//! One process writes the latest value into the segment, any number of others read it,
+//! without a connection between them. On Linux segments live in /dev/shm, which is memory,
+//! elsewhere in the temp directory. std has no shm_open or mmap, so a segment is a file
+//! read and written with plain file calls, which on /dev/shm never touch a disk.
+//! A segment starts with a header of HEADER_LEN bytes: the magic "BSHM", a CRC-32 of the value,
+//! a u64 sequence counter and the u64 length of the value, after which the value follows.
+//! The counter is odd while a write is going on, so readers that catch a write half done
+//! try again, and it goes up by 2 with every finished write.
+//! A segment can have one writer at a time.
+//! # Example
+//! ```
+//! use bin_buffer::shm::*;
+//! // a name of its own, so runs at the same time do not share the segment
+//! let name = format!("bin_buffer_doc_{}", std::process::id());
+//! let mut writer = SharedSegment::create(&name, 1024).unwrap();
+//! let mut reader = SharedSegment::open(&name).unwrap();
+//! assert_eq!(None, reader.read::<(u32, String)>().unwrap());
+//! let seq = writer.write(&(3u32, String::from("ready"))).unwrap();
+//! assert_eq!(Some((seq, (3u32, String::from("ready")))), reader.read().unwrap());
+//! assert_eq!(None, reader.read_newer::<(u32, String)>(seq).unwrap());
+//! SharedSegment::remove(&name).unwrap();
+//! ```
+use std::fs::{ File, OpenOptions };
+use std::io::{ self, Read, Seek, SeekFrom, Write };
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use crate::journal::crc32;
+
+/// First bytes of every segment.
+pub const MAGIC: [u8; 4] = *b"BSHM";
+/// Bytes in front of the value.
+pub const HEADER_LEN: usize = 24;
+/// How many times a read is tried while a write is going on before it fails with WouldBlock.
+pub const READ_TRIES: usize = 10_000;
+
+/// A named segment that holds one encoded value.
+pub struct SharedSegment{
+    file: File,
+    capacity: usize,
+}
+
+impl SharedSegment{
+    /// Where the segment called name lives. Fails with InvalidInput if the name is empty
+    /// or has anything but ASCII letters, digits, '_', '-' and '.'.
+    pub fn path(name: &str) -> io::Result<PathBuf>{
+        let ok = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.';
+        if name.is_empty() || name.starts_with('.') || !name.chars().all(ok){
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a segment name"));
+        }
+        let shm = std::path::Path::new("/dev/shm");
+        let dir = if cfg!(target_os = "linux") && shm.is_dir() { shm.to_path_buf() } else { std::env::temp_dir() };
+        Ok(dir.join(format!("{}.bshm", name)))
+    }
+    /// Make the segment called name with room for values of capacity bytes, empty.
+    /// A segment that was already there is overwritten.
+    /// Fails with InvalidInput if capacity is too large for a file.
+    pub fn create(name: &str, capacity: usize) -> io::Result<Self>{
+        let len = HEADER_LEN.checked_add(capacity).and_then(|len| u64::try_from(len).ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "capacity too large"))?;
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(Self::path(name)?)?;
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4..8].copy_from_slice(&crc32(&[]).to_be_bytes());
+        file.write_all(&header)?;
+        file.set_len(len)?;
+        Ok(Self{ file, capacity })
+    }
+    /// Open the segment called name that another process made.
+    /// Fails with InvalidData if it is not a segment.
+    pub fn open(name: &str) -> io::Result<Self>{
+        let mut file = OpenOptions::new().read(true).write(true).open(Self::path(name)?)?;
+        let len = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX);
+        let mut magic = [0u8; 4];
+        if len < HEADER_LEN || file.read_exact(&mut magic).is_err() || magic != MAGIC{
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a shared segment"));
+        }
+        Ok(Self{ file, capacity: len - HEADER_LEN })
+    }
+    /// Remove the segment called name. Processes that have it open can still use it.
+    pub fn remove(name: &str) -> io::Result<()>{
+        std::fs::remove_file(Self::path(name)?)
+    }
+    /// Largest value that fits, in bytes.
+    pub fn capacity(&self) -> usize{
+        self.capacity
+    }
+    /// The sequence counter, the number of the last write times 2, odd while a write is going on.
+    pub fn sequence(&mut self) -> io::Result<u64>{
+        Ok(self.header()?.1)
+    }
+    fn header(&mut self) -> io::Result<(u32, u64, usize)>{
+        let mut header = [0u8; HEADER_LEN];
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_exact(&mut header)?;
+        let mut crc = [0u8; 4];
+        let mut seq = [0u8; 8];
+        let mut len = [0u8; 8];
+        crc.copy_from_slice(&header[4..8]);
+        seq.copy_from_slice(&header[8..16]);
+        len.copy_from_slice(&header[16..24]);
+        Ok((u32::from_be_bytes(crc), u64::from_be_bytes(seq), usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX)))
+    }
+    fn write_at(&mut self, pos: u64, bytes: &[u8]) -> io::Result<()>{
+        self.file.seek(SeekFrom::Start(pos))?;
+        self.file.write_all(bytes)
+    }
+    /// Put bytes in the segment in place of what was there.
+    /// Returns the new sequence counter. Fails with InvalidInput if they do not fit.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<u64>{
+        if bytes.len() > self.capacity{
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "value does not fit in the segment"));
+        }
+        let seq = self.sequence()? | 1;
+        self.write_at(8, &seq.to_be_bytes())?;
+        self.write_at(HEADER_LEN as u64, bytes)?;
+        let mut header = [0u8; 20];
+        header[..4].copy_from_slice(&crc32(bytes).to_be_bytes());
+        header[4..12].copy_from_slice(&(seq + 1).to_be_bytes());
+        header[12..].copy_from_slice(&(bytes.len() as u64).to_be_bytes());
+        // the length and CRC go before the counter in the file, the counter is written last
+        self.write_at(16, &header[12..])?;
+        self.write_at(4, &header[..4])?;
+        self.write_at(8, &header[4..12])?;
+        self.file.flush()?;
+        Ok(seq + 1)
+    }
+    /// Encode x into the segment. Returns the new sequence counter.
+    pub fn write<T: Bufferable>(&mut self, x: &T) -> io::Result<u64>{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.write_bytes(&bytes)
+    }
+    /// The bytes in the segment with the sequence counter they were written with,
+    /// None if nothing was written yet. Fails with InvalidData if the CRC is wrong
+    /// and with WouldBlock if a write did not finish in READ_TRIES tries.
+    pub fn read_bytes(&mut self) -> io::Result<Option<(u64, Buffer)>>{
+        for _ in 0..READ_TRIES{
+            let (crc, seq, len) = self.header()?;
+            if seq == 0{
+                return Ok(Option::None);
+            }
+            if seq % 2 == 1 || len > self.capacity{
+                std::thread::yield_now();
+                continue;
+            }
+            let mut bytes = vec![0u8; len];
+            self.file.read_exact(&mut bytes)?;
+            if self.header()?.1 != seq{
+                continue;
+            }
+            if crc32(&bytes) != crc{
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "segment CRC does not match"));
+            }
+            return Ok(Option::Some((seq, bytes)));
+        }
+        Err(io::Error::new(io::ErrorKind::WouldBlock, "segment is being written"))
+    }
+    /// The value in the segment with its sequence counter, None if nothing was written yet.
+    /// Fails with InvalidData if it is not exactly one T.
+    pub fn read<T: Bufferable>(&mut self) -> io::Result<Option<(u64, T)>>{
+        let (seq, bytes) = match self.read_bytes()?{
+            Option::Some(x) => x,
+            Option::None => return Ok(Option::None),
+        };
+        let mut buf = ReadBuffer::from_raw(bytes);
+        match T::from_buffer(&mut buf){
+            Option::Some(x) if buf.remaining() == 0 => Ok(Option::Some((seq, x))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "segment does not hold one value")),
+        }
+    }
+    /// read, but None unless the value was written after the sequence counter seen.
+    pub fn read_newer<T: Bufferable>(&mut self, seen: u64) -> io::Result<Option<(u64, T)>>{
+        if self.sequence()? <= seen{
+            return Ok(Option::None);
+        }
+        Ok(self.read()?.filter(|(seq, _)| *seq > seen))
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::shm::*;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+
+    // A segment name no other test or test run uses.
+    fn unique_name() -> String{
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        format!("bin_buffer_test_{}_{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    #[test]
+    fn test_shm(){
+        assert_eq!(io::ErrorKind::InvalidInput, SharedSegment::path("../x").unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidInput, SharedSegment::path("").unwrap_err().kind());
+        let name = unique_name();
+        assert_eq!(io::ErrorKind::InvalidInput, SharedSegment::create(&name, usize::MAX).err().unwrap().kind());
+        assert!(!SharedSegment::path(&name).unwrap().exists());
+        let mut writer = SharedSegment::create(&name, 64).unwrap();
+        let mut reader = SharedSegment::open(&name).unwrap();
+        assert_eq!(64, reader.capacity());
+        assert_eq!(0, reader.sequence().unwrap());
+        assert_eq!(2, writer.write(&String::from("one")).unwrap());
+        assert_eq!(4, writer.write(&vec![1u8, 2]).unwrap());
+        assert_eq!(Some((4, vec![1u8, 2])), reader.read().unwrap());
+        assert_eq!(io::ErrorKind::InvalidData, reader.read::<u8>().unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidInput, writer.write_bytes(&[0; 65]).unwrap_err().kind());
+        let done = std::thread::spawn(move ||{
+            for i in 0..200u64{
+                writer.write(&vec![i; (i % 8) as usize]).unwrap();
+            }
+            writer
+        });
+        let mut seen = 4;
+        while seen < 404{
+            if let Some((seq, x)) = reader.read_newer::<Vec<u64>>(seen).unwrap(){
+                let i = (seq - 6) / 2;
+                assert_eq!(vec![i; (i % 8) as usize], x);
+                seen = seq;
+            }
+        }
+        let mut writer = done.join().unwrap();
+        writer.write_at(HEADER_LEN as u64 + 9, &[0xff]).unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, reader.read_bytes().unwrap_err().kind());
+        SharedSegment::remove(&name).unwrap();
+        let name = unique_name();
+        std::fs::write(SharedSegment::path(&name).unwrap(), b"not it").unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, SharedSegment::open(&name).err().unwrap().kind());
+        SharedSegment::remove(&name).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! Ed25519 signatures over encoded buffers, enabled with the `ed25519` feature.
+//! sign_buffer gives a detached signature of the bytes, verify_buffer checks one before
+//! anything is decoded, so bytes from someone else are only read once they are known to be
+//! theirs. Signed carries the signature in the same buffer as the payload:
+//! a flags byte, the payload like a ByteVec and, when the signed flag is set, the 64 byte signature.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::sign::*;
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let public = key.verifying_key();
+//! let bytes = encode(&String::from("mod v1.2"));
+//! let signature = sign_buffer(&key, &bytes);
+//! assert!(verify_buffer(&public, &bytes, &signature));
+//! assert_eq!(Some(String::from("mod v1.2")), decode_verified::<String>(&public, &bytes, &signature));
+//! let signed = encode(&Signed::sign(&key, bytes.clone()));
+//! let read = decode::<Signed>(&signed).unwrap();
+//! assert_eq!(Some(&bytes[..]), read.verified_payload(&public));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ByteVec, ReadBuffer, Sink };
+use ed25519_dalek::Signer;
+pub use ed25519_dalek::{ Signature, SigningKey, VerifyingKey };
+
+/// Bytes of a signature.
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Set in the flags of a Signed that carries a signature.
+pub const FLAG_SIGNED: u8 = 1;
+
+/// Sign the bytes of an encoded buffer.
+pub fn sign_buffer(key: &SigningKey, buf: &[u8]) -> Signature{
+    key.sign(buf)
+}
+
+/// Whether signature is a signature of buf by the owner of key.
+/// Uses the strict check, which also refuses signatures that were tampered into another valid form.
+pub fn verify_buffer(key: &VerifyingKey, buf: &[u8], signature: &Signature) -> bool{
+    key.verify_strict(buf, signature).is_ok()
+}
+
+/// Decode a T that takes up all of buf, only if signature checks out.
+pub fn decode_verified<T: Bufferable>(key: &VerifyingKey, buf: &[u8], signature: &Signature) -> Option<T>{
+    if !verify_buffer(key, buf, signature){
+        return Option::None;
+    }
+    crate::decode(buf)
+}
+
+/// A payload with the signature embedded, or without one if it was not signed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signed{
+    /// The signed bytes, usually an encoded value.
+    pub payload: Buffer,
+    /// The signature of the payload.
+    pub signature: Option<Signature>,
+}
+
+impl Signed{
+    /// A payload signed with key.
+    pub fn sign(key: &SigningKey, payload: Buffer) -> Self{
+        let signature = Option::Some(sign_buffer(key, &payload));
+        Self{ payload, signature }
+    }
+    /// A payload without a signature.
+    pub fn unsigned(payload: Buffer) -> Self{
+        Self{ payload, signature: Option::None }
+    }
+    /// Whether there is a signature and it is one by the owner of key.
+    pub fn verify(&self, key: &VerifyingKey) -> bool{
+        self.signature.as_ref().is_some_and(|s| verify_buffer(key, &self.payload, s))
+    }
+    /// The payload, only if verify says it is signed by key.
+    pub fn verified_payload(&self, key: &VerifyingKey) -> Option<&[u8]>{
+        if self.verify(key) { Option::Some(&self.payload) } else { Option::None }
+    }
+    /// The payload decoded as a T, only if verify says it is signed by key.
+    pub fn decode_verified<T: Bufferable>(&self, key: &VerifyingKey) -> Option<T>{
+        crate::decode(self.verified_payload(key)?)
+    }
+}
+
+impl Bufferable for Signed{
+    const MIN_BUFFER_LEN: usize = 9;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        let flags = if self.signature.is_some() { FLAG_SIGNED } else { 0 };
+        flags.into_buffer(vec);
+        (self.payload.len() as u64).into_buffer(vec);
+        vec.write_bytes(&self.payload);
+        if let Option::Some(signature) = &self.signature{
+            vec.write_bytes(&signature.to_bytes());
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let flags = u8::from_buffer(buf)?;
+        if flags & !FLAG_SIGNED != 0{
+            return Option::None;
+        }
+        let payload = ByteVec::from_buffer(buf)?.0;
+        let signature = if flags & FLAG_SIGNED != 0{
+            let bytes = <[u8; SIGNATURE_LEN]>::try_from(buf.read_bytes(SIGNATURE_LEN)?).ok()?;
+            Option::Some(Signature::from_bytes(&bytes))
+        } else {
+            Option::None
+        };
+        Option::Some(Self{ payload, signature })
+    }
+
+    fn buffer_len(&self) -> usize{
+        9 + self.payload.len() + if self.signature.is_some() { SIGNATURE_LEN } else { 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::sign::*;
+
+    #[test]
+    fn test_sign(){
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let other = SigningKey::from_bytes(&[2; 32]).verifying_key();
+        let bytes = encode(&(3u32, vec![1u16, 2]));
+        let signature = sign_buffer(&key, &bytes);
+        assert!(verify_buffer(&key.verifying_key(), &bytes, &signature));
+        assert!(!verify_buffer(&other, &bytes, &signature));
+        let mut tampered = bytes.clone();
+        tampered[3] ^= 1;
+        assert!(!verify_buffer(&key.verifying_key(), &tampered, &signature));
+        assert_eq!(None, decode_verified::<(u32, Vec<u16>)>(&key.verifying_key(), &tampered, &signature));
+        let signed = Signed::sign(&key, bytes.clone());
+        let encoded = encode(&signed);
+        assert_eq!(signed.buffer_len(), encoded.len());
+        let read = decode::<Signed>(&encoded).unwrap();
+        assert_eq!(Some((3u32, vec![1u16, 2])), read.decode_verified(&key.verifying_key()));
+        assert!(!read.verify(&other));
+        let unsigned = decode::<Signed>(&encode(&Signed::unsigned(bytes))).unwrap();
+        assert_eq!(None, unsigned.signature);
+        assert!(!unsigned.verify(&key.verifying_key()));
+        let mut bad_flags = encoded;
+        bad_flags[0] = 2;
+        assert_eq!(None, decode::<Signed>(&bad_flags));
+    }
+}
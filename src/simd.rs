@@ -0,0 +1,109 @@
+//! Byte swapping for the bulk number paths, enabled with the `simd` feature.
+//! Slices are copied as raw memory and then swapped to big endian in place,
+//! with AVX2 when the CPU has it and a plain loop the compiler can
+//! vectorize otherwise. On big endian targets nothing needs swapping.
+use crate::{ Buffer, ReadBuffer, Number };
+
+/// Write the numbers of a slice as big endian bytes.
+pub(crate) fn slice_into_buffer<T: Number, const N: usize>(slice: &[T], vec: &mut Buffer){
+    assert_eq!(std::mem::size_of::<T>(), N);
+    let start = vec.len();
+    // Safety: Number types are N bytes without padding, so the slice is
+    // slice.len() * N initialized bytes.
+    let bytes = unsafe{
+        std::slice::from_raw_parts(slice.as_ptr() as *const u8, slice.len() * N)
+    };
+    vec.extend_from_slice(bytes);
+    swap_words::<N>(&mut vec[start..]);
+}
+
+/// Read len big endian numbers into a new Vec.
+pub(crate) fn vec_from_buffer<T: Number, const N: usize>(len: usize, buf: &mut ReadBuffer) -> Option<Vec<T>>{
+    assert_eq!(std::mem::size_of::<T>(), N);
+    let bytes = buf.read_bytes(len.checked_mul(N)?)?;
+    let mut vec: Vec<T> = Vec::with_capacity(len);
+    // Safety: the Vec has room for len values, every bit pattern is a valid
+    // Number and the bytes are fully written before the length is set.
+    unsafe{
+        let dst = vec.as_mut_ptr() as *mut u8;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+        swap_words::<N>(std::slice::from_raw_parts_mut(dst, bytes.len()));
+        vec.set_len(len);
+    }
+    Option::Some(vec)
+}
+
+/// Convert every N byte word between native and big endian, in place.
+#[inline]
+fn swap_words<const N: usize>(bytes: &mut [u8]){
+    if cfg!(target_endian = "big") { return; }
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2"){
+            // Safety: the CPU supports AVX2.
+            unsafe{ swap_words_avx2::<N>(bytes); }
+            return;
+        }
+    }
+    swap_words_scalar::<N>(bytes);
+}
+
+#[inline]
+fn swap_words_scalar<const N: usize>(bytes: &mut [u8]){
+    for word in bytes.chunks_exact_mut(N){
+        word.reverse();
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn swap_words_avx2<const N: usize>(bytes: &mut [u8]){
+    use std::arch::x86_64::*;
+    // The shuffle works per 16 byte lane, N always divides 16.
+    let mut mask = [0u8; 32];
+    for (i, m) in mask.iter_mut().enumerate(){
+        let j = i % 16;
+        *m = ((j / N) * N + N - 1 - j % N) as u8;
+    }
+    let mask = _mm256_loadu_si256(mask.as_ptr() as *const __m256i);
+    let mut chunks = bytes.chunks_exact_mut(32);
+    for chunk in &mut chunks{
+        let ptr = chunk.as_mut_ptr() as *mut __m256i;
+        _mm256_storeu_si256(ptr, _mm256_shuffle_epi8(_mm256_loadu_si256(ptr), mask));
+    }
+    swap_words_scalar::<N>(chunks.into_remainder());
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    #[test]
+    fn test_simd_matches_scalar(){
+        for len in [0usize,1,3,4,7,8,15,16,17,33,100]{
+            let a: Vec<u16> = (0..len as u16).map(|x| x.wrapping_mul(40503)).collect();
+            let b: Vec<u32> = (0..len as u32).map(|x| x.wrapping_mul(2654435761)).collect();
+            let c: Vec<u64> = (0..len as u64).map(|x| x.wrapping_mul(11400714819323198485)).collect();
+            let d: Vec<f32> = (0..len).map(|x| x as f32 * -0.5).collect();
+            let mut buffer = Vec::new();
+            a.copy_into_buffer(&mut buffer);
+            b.copy_into_buffer(&mut buffer);
+            c.copy_into_buffer(&mut buffer);
+            d.copy_into_buffer(&mut buffer);
+            let mut scalar = Vec::new();
+            (len as u64).into_buffer(&mut scalar);
+            for x in &a { scalar.extend_from_slice(&x.to_be_bytes()); }
+            (len as u64).into_buffer(&mut scalar);
+            for x in &b { scalar.extend_from_slice(&x.to_be_bytes()); }
+            (len as u64).into_buffer(&mut scalar);
+            for x in &c { scalar.extend_from_slice(&x.to_be_bytes()); }
+            (len as u64).into_buffer(&mut scalar);
+            for x in &d { scalar.extend_from_slice(&x.to_be_bytes()); }
+            assert_eq!(scalar, buffer);
+            let mut buffer = ReadBuffer::from_raw(buffer);
+            assert_eq!(Some(a), Vec::<u16>::from_buffer(&mut buffer));
+            assert_eq!(Some(b), Vec::<u32>::from_buffer(&mut buffer));
+            assert_eq!(Some(c), Vec::<u64>::from_buffer(&mut buffer));
+            assert_eq!(Some(d), Vec::<f32>::from_buffer(&mut buffer));
+        }
+    }
+}
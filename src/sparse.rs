@@ -0,0 +1,167 @@
+use std::convert::TryFrom;
+use crate::{ BufferError, Bufferable, ReadBuffer, Sink };
+
+/// A Vec that is written as only its entries that are not T::default().
+/// The length of the whole Vec comes first, then the number of entries that are written,
+/// then every one of those as its index (u64) and its value, lowest index first.
+/// The length counts against DecodeLimits::max_elements and the memory of the Vec
+/// against max_total_bytes, set those when reading data you do not trust:
+/// a few bytes can ask for a huge Vec of defaults.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut voxels = vec![0u8; 4096];
+/// voxels[10] = 3;
+/// voxels[4000] = 1;
+/// let x = SparseVec(voxels);
+/// let mut buffer = Vec::new();
+/// x.copy_into_buffer(&mut buffer);
+/// assert_eq!(8 + 8 + 2 * 9, buffer.len());
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(x), SparseVec::<u8>::from_buffer(&mut buffer));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SparseVec<T>(pub Vec<T>);
+
+impl<T> std::ops::Deref for SparseVec<T>{
+    type Target = Vec<T>;
+    fn deref(&self) -> &Vec<T>{
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for SparseVec<T>{
+    fn deref_mut(&mut self) -> &mut Vec<T>{
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for SparseVec<T>{
+    fn from(vec: Vec<T>) -> Self{
+        Self(vec)
+    }
+}
+
+impl<T> From<SparseVec<T>> for Vec<T>{
+    fn from(vec: SparseVec<T>) -> Self{
+        vec.0
+    }
+}
+
+impl<T: Bufferable + Default + PartialEq> SparseVec<T>{
+    /// Number of entries that are not the default, the ones that get written.
+    pub fn entries(&self) -> usize{
+        let default = T::default();
+        self.0.iter().filter(|x| **x != default).count()
+    }
+}
+
+impl<T: Bufferable + Default + PartialEq> Bufferable for SparseVec<T>{
+    const MIN_BUFFER_LEN: usize = 16;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        let default = T::default();
+        (self.0.len() as u64).into_buffer(vec);
+        (self.entries() as u64).into_buffer(vec);
+        for (i, x) in self.0.iter().enumerate(){
+            if *x != default{
+                (i as u64).into_buffer(vec);
+                x.copy_into_buffer(vec);
+            }
+        }
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        buf.nested(|buf|{
+            let len = buf.read_len()?;
+            let entries = buf.read_len()?;
+            if entries > len{
+                return Option::None;
+            }
+            buf.use_bytes(len.checked_mul(std::mem::size_of::<T>())?)?;
+            // the length is not backed by bytes, so a Vec that can not be had is a failure
+            let mut dense = Vec::new();
+            dense.try_reserve_exact(len).ok()?;
+            dense.resize_with(len, T::default);
+            let mut next = 0;
+            for _ in 0..entries{
+                let i = usize::try_from(u64::from_buffer(buf)?).ok()?;
+                // indices go up, so every entry is set once
+                if i < next || i >= len{
+                    return Option::None;
+                }
+                dense[i] = T::from_buffer(buf)?;
+                next = i + 1;
+            }
+            Option::Some(Self(dense))
+        })
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        buf.check_nested(|buf|{
+            let len = buf.check_prefix(buf.limits.max_elements)?;
+            let entries = buf.check_prefix(buf.limits.max_elements)?;
+            if entries > len{
+                return Err(BufferError::Invalid);
+            }
+            buf.check_use_bytes(len.checked_mul(std::mem::size_of::<T>()).ok_or(BufferError::LengthOverflow)?)?;
+            let mut next = 0;
+            for _ in 0..entries{
+                let i = u64::from_buffer(buf).ok_or_else(|| buf.eof(8))?;
+                let i = usize::try_from(i).ok().filter(|i| *i >= next && *i < len).ok_or(BufferError::Invalid)?;
+                T::validate(buf)?;
+                next = i + 1;
+            }
+            Ok(())
+        })
+    }
+
+    fn buffer_len(&self) -> usize{
+        let default = T::default();
+        16 + self.0.iter().filter(|x| **x != default).map(|x| 8 + x.buffer_len()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    #[test]
+    fn test_sparse(){
+        let mut x = SparseVec(vec![String::new(); 1000]);
+        x[0] = String::from("first");
+        x[999] = String::from("last");
+        assert_eq!(2, x.entries());
+        let mut buffer = Vec::new();
+        x.copy_into_buffer(&mut buffer);
+        assert_eq!(x.buffer_len(), buffer.len());
+        assert_eq!(Some(x.clone()), decode(&buffer));
+        assert_eq!(Some(SparseVec::<u32>(Vec::new())), decode(&encode(&SparseVec::<u32>(Vec::new()))));
+        // indices out of order, past the end, and more entries than the length
+        for entries in [vec![(2u64, 1u8), (1, 1)], vec![(1, 1), (1, 2)], vec![(3, 1)]].iter(){
+            let mut bad = Vec::new();
+            3u64.into_buffer(&mut bad);
+            (entries.len() as u64).into_buffer(&mut bad);
+            for e in entries{
+                e.into_buffer(&mut bad);
+            }
+            assert_eq!(None, decode::<SparseVec<u8>>(&bad));
+        }
+        assert_eq!(None, decode::<SparseVec<u8>>(&[0,0,0,0,0,0,0,1, 0,0,0,0,0,0,0,2]));
+        // a huge length is stopped by the limits
+        let limits = DecodeLimits{ max_elements: 1 << 20, ..DecodeLimits::default() };
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(None, SparseVec::<u8>::from_buffer(&mut ReadBuffer::from_raw(huge.to_vec()).with_limits(limits)));
+        assert_eq!(Err(BufferError::LengthOverflow), validate::<SparseVec<u8>>(&mut ReadBuffer::from_raw(huge.to_vec()).with_limits(limits)));
+        // and without limits a length that can not be allocated fails instead of panicking
+        assert_eq!(None, decode::<SparseVec<u8>>(&huge));
+        assert_eq!(None, decode::<SparseVec<u64>>(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(Ok(()), validate::<SparseVec<String>>(&mut ReadBuffer::from_raw(buffer.clone())));
+        buffer.pop();
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 4, available: 3 }), validate::<SparseVec<String>>(&mut ReadBuffer::from_raw(buffer)));
+    }
+}
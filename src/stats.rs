@@ -0,0 +1,135 @@
+//! Counting how many bytes and how much time go into encoding and decoding.
+//! Route the values you care about through a Stats instead of calling
+//! into_buffer and from_buffer directly, then print it for a report.
+use crate::{ Buffer, Bufferable, ReadBuffer };
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{ Duration, Instant };
+
+/// Counters for one type or label.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters{
+    /// Number of values encoded.
+    pub encoded: u64,
+    /// Bytes written by those values.
+    pub bytes_written: u64,
+    /// Time spent encoding.
+    pub encode_time: Duration,
+    /// Number of values decoded, including failed attempts.
+    pub decoded: u64,
+    /// Bytes consumed by those values.
+    pub bytes_read: u64,
+    /// Time spent decoding.
+    pub decode_time: Duration,
+}
+
+/// Collects Counters per type or label during an encode or decode session.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::stats::Stats;
+/// let mut stats = Stats::new();
+/// let mut buffer = Vec::new();
+/// stats.encode(&7u32, &mut buffer);
+/// stats.encode_named("name", &String::from("hello"), &mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// assert_eq!(Some(7), stats.decode::<u32>(&mut buffer));
+/// assert_eq!(13, stats.get("name").unwrap().bytes_written);
+/// println!("{}", stats);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Stats{
+    counters: BTreeMap<&'static str, Counters>,
+}
+
+impl Stats{
+    /// Create empty Stats.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Copy the value into the buffer, counting it under its type name.
+    pub fn encode<T: Bufferable>(&mut self, x: &T, buf: &mut Buffer){
+        self.encode_named(std::any::type_name::<T>(), x, buf);
+    }
+    /// Copy the value into the buffer, counting it under the label.
+    pub fn encode_named<T: Bufferable>(&mut self, label: &'static str, x: &T, buf: &mut Buffer){
+        let start = buf.len();
+        let time = Instant::now();
+        x.copy_into_buffer(buf);
+        let elapsed = time.elapsed();
+        let c = self.counters.entry(label).or_default();
+        c.encoded += 1;
+        c.bytes_written += (buf.len() - start) as u64;
+        c.encode_time += elapsed;
+    }
+    /// Read a value from the buffer, counting it under its type name.
+    pub fn decode<T: Bufferable>(&mut self, buf: &mut ReadBuffer) -> Option<T>{
+        self.decode_named(std::any::type_name::<T>(), buf)
+    }
+    /// Read a value from the buffer, counting it under the label.
+    pub fn decode_named<T: Bufferable>(&mut self, label: &'static str, buf: &mut ReadBuffer) -> Option<T>{
+        let start = buf.remaining();
+        let time = Instant::now();
+        let res = T::from_buffer(buf);
+        let elapsed = time.elapsed();
+        let c = self.counters.entry(label).or_default();
+        c.decoded += 1;
+        c.bytes_read += (start - buf.remaining()) as u64;
+        c.decode_time += elapsed;
+        res
+    }
+    /// The counters for a type name or label, if anything was counted under it.
+    pub fn get(&self, label: &str) -> Option<&Counters>{
+        self.counters.get(label)
+    }
+    /// All labels with their counters, in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &Counters)>{
+        self.counters.iter().map(|(k, v)| (*k, v))
+    }
+    /// Forget everything counted so far.
+    pub fn clear(&mut self){
+        self.counters.clear();
+    }
+}
+
+/// A table with the labels that moved the most bytes first.
+impl fmt::Display for Stats{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        let mut rows: Vec<_> = self.iter().collect();
+        rows.sort_by_key(|(_, c)| std::cmp::Reverse(c.bytes_written + c.bytes_read));
+        writeln!(f, "{:<32} {:>10} {:>14} {:>12} {:>10} {:>14} {:>12}",
+            "label", "encoded", "bytes written", "encode time", "decoded", "bytes read", "decode time")?;
+        for (label, c) in rows{
+            writeln!(f, "{:<32} {:>10} {:>14} {:>12?} {:>10} {:>14} {:>12?}",
+                label, c.encoded, c.bytes_written, c.encode_time, c.decoded, c.bytes_read, c.decode_time)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::stats::*;
+    #[test]
+    fn test_stats(){
+        let mut stats = Stats::new();
+        let mut buffer = Vec::new();
+        stats.encode(&1u16, &mut buffer);
+        stats.encode(&2u16, &mut buffer);
+        stats.encode_named("points", &vec![1.0f32,2.0], &mut buffer);
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        stats.decode::<u16>(&mut buffer);
+        stats.decode::<u16>(&mut buffer);
+        stats.decode_named::<Vec<f32>>("points", &mut buffer);
+        assert_eq!(None, stats.decode::<u16>(&mut buffer));
+        let c = stats.get("u16").unwrap();
+        assert_eq!((2, 4, 3, 4), (c.encoded, c.bytes_written, c.decoded, c.bytes_read));
+        let c = stats.get("points").unwrap();
+        assert_eq!((1, 16, 1, 16), (c.encoded, c.bytes_written, c.decoded, c.bytes_read));
+        let report = stats.to_string();
+        assert!(report.find("points").unwrap() < report.find("u16").unwrap());
+        stats.clear();
+        assert_eq!(None, stats.get("u16"));
+    }
+}
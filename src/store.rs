@@ -0,0 +1,205 @@
+//! Places to keep buffers by key, so saving and loading is not tied to the file system.
+//! BufferStore has get, put, list and remove; implement it to store buffers in object storage,
+//! a database or anywhere else. MemoryStore keeps them in memory and DirStore, with the `fs`
+//! feature, keeps every key as a file in a directory.
+//! save and load write and read one value under a key on any store, and an EntryArchive
+//! can be kept in one with save_to and load_from.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::store::*;
+//! let mut store = MemoryStore::new();
+//! assert!(store.save("players/1", &(String::from("ann"), 12u32)));
+//! assert!(store.put("readme", b"hi"));
+//! assert_eq!(vec!["players/1", "readme"], store.list());
+//! assert_eq!(Some((String::from("ann"), 12u32)), store.load("players/1"));
+//! ```
+use std::collections::BTreeMap;
+use crate::{ Buffer, Bufferable, ReadBuffer };
+
+/// Buffers kept by key.
+pub trait BufferStore{
+    /// The bytes under key, None if there are none or they can not be read.
+    fn get(&self, key: &str) -> Option<Buffer>;
+    /// Keep bytes under key, replacing what was there. False if that failed.
+    fn put(&mut self, key: &str, bytes: &[u8]) -> bool;
+    /// All keys, sorted.
+    fn list(&self) -> Vec<String>;
+    /// Remove what is under key. False if there was nothing or it could not be removed.
+    fn remove(&mut self, key: &str) -> bool;
+    /// Whether there is something under key.
+    fn contains(&self, key: &str) -> bool{
+        self.get(key).is_some()
+    }
+    /// Keep x under key.
+    fn save<T: Bufferable>(&mut self, key: &str, x: &T) -> bool where Self: Sized{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.put(key, &bytes)
+    }
+    /// Read what is under key as one T. None if it is missing,
+    /// does not decode or has bytes left over.
+    fn load<T: Bufferable>(&self, key: &str) -> Option<T> where Self: Sized{
+        let bytes = self.get(key)?;
+        let mut buf = ReadBuffer::borrowed(&bytes, Default::default());
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+}
+
+/// A BufferStore in memory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStore{
+    entries: BTreeMap<String, Buffer>,
+}
+
+impl MemoryStore{
+    /// An empty store.
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Number of keys.
+    pub fn len(&self) -> usize{
+        self.entries.len()
+    }
+    /// Whether there are no keys.
+    pub fn is_empty(&self) -> bool{
+        self.entries.is_empty()
+    }
+}
+
+impl BufferStore for MemoryStore{
+    fn get(&self, key: &str) -> Option<Buffer>{
+        self.entries.get(key).cloned()
+    }
+    fn put(&mut self, key: &str, bytes: &[u8]) -> bool{
+        self.entries.insert(String::from(key), bytes.to_vec());
+        true
+    }
+    fn list(&self) -> Vec<String>{
+        self.entries.keys().cloned().collect()
+    }
+    fn remove(&mut self, key: &str) -> bool{
+        self.entries.remove(key).is_some()
+    }
+    fn contains(&self, key: &str) -> bool{
+        self.entries.contains_key(key)
+    }
+}
+
+/// A BufferStore that keeps every key as a file under a directory.
+/// Keys are paths relative to the directory with / between the parts, like "saves/slot1".
+/// Keys that are empty, start with / or have empty, . or .. parts are refused,
+/// so nothing outside the directory is touched.
+#[cfg(feature = "fs")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirStore{
+    root: std::path::PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl DirStore{
+    /// A store in the directory root, which is created when the first key is put.
+    pub fn new<P: Into<std::path::PathBuf>>(root: P) -> Self{
+        Self{ root: root.into() }
+    }
+    /// The directory.
+    pub fn root(&self) -> &std::path::Path{
+        &self.root
+    }
+    fn path(&self, key: &str) -> Option<std::path::PathBuf>{
+        let mut path = self.root.clone();
+        for part in key.split('/'){
+            if part.is_empty() || part == "." || part == ".." || part.contains('\\'){
+                return Option::None;
+            }
+            path.push(part);
+        }
+        Option::Some(path)
+    }
+    fn list_dir(dir: &std::path::Path, prefix: &str, keys: &mut Vec<String>){
+        let entries = if let Ok(e) = std::fs::read_dir(dir) { e } else { return; };
+        for entry in entries.flatten(){
+            let name = if let Ok(n) = entry.file_name().into_string() { n } else { continue; };
+            let key = format!("{}{}", prefix, name);
+            match entry.file_type(){
+                Ok(t) if t.is_dir() => Self::list_dir(&entry.path(), &format!("{}/", key), keys),
+                Ok(t) if t.is_file() => keys.push(key),
+                _ => {},
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl BufferStore for DirStore{
+    fn get(&self, key: &str) -> Option<Buffer>{
+        crate::buffer_read_file(&self.path(key)?)
+    }
+    fn put(&mut self, key: &str, bytes: &[u8]) -> bool{
+        let path = if let Some(p) = self.path(key) { p } else { return false; };
+        if let Some(dir) = path.parent(){
+            if std::fs::create_dir_all(dir).is_err(){
+                return false;
+            }
+        }
+        crate::buffer_write_file(&path, bytes)
+    }
+    fn list(&self) -> Vec<String>{
+        let mut keys = Vec::new();
+        Self::list_dir(&self.root, "", &mut keys);
+        keys.sort();
+        keys
+    }
+    fn remove(&mut self, key: &str) -> bool{
+        self.path(key).is_some_and(|path| std::fs::remove_file(path).is_ok())
+    }
+    fn contains(&self, key: &str) -> bool{
+        self.path(key).is_some_and(|path| path.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::store::*;
+
+    fn check_store<S: BufferStore>(store: &mut S){
+        assert!(store.list().is_empty());
+        assert!(store.save("b", &vec![1u16, 2]));
+        assert!(store.put("a/x", &[]));
+        assert!(store.put("a/y", &[7]));
+        assert_eq!(vec!["a/x", "a/y", "b"], store.list());
+        assert_eq!(Some(vec![1u16, 2]), store.load("b"));
+        assert_eq!(None, store.load::<u16>("b"));
+        assert_eq!(Some(Vec::new()), store.get("a/x"));
+        assert!(store.put("a/y", &[8]));
+        assert_eq!(Some(vec![8]), store.get("a/y"));
+        assert!(store.contains("a/y"));
+        assert!(store.remove("a/y"));
+        assert!(!store.remove("a/y"));
+        assert!(!store.contains("a/y"));
+        assert_eq!(None, store.get("missing"));
+    }
+
+    #[test]
+    fn test_store(){
+        let mut store = MemoryStore::new();
+        check_store(&mut store);
+        assert_eq!(2, store.len());
+        #[cfg(feature = "fs")]
+        {
+            let root = std::env::temp_dir().join("bin_buffer_store_test");
+            let _ = std::fs::remove_dir_all(&root);
+            let mut store = DirStore::new(&root);
+            check_store(&mut store);
+            assert!(root.join("a").join("x").is_file());
+            for key in ["", "/abs", "a/../b", "a//b", "./b"].iter(){
+                assert!(!store.put(key, &[1]), "{}", key);
+            }
+            let _ = std::fs::remove_dir_all(&root);
+        }
+    }
+}
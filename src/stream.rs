@@ -0,0 +1,259 @@
+use std::io::{ self, Read, Write };
+use crate::{ Buffer, Bufferable, BufferError, DecodeLimits, ReadBuffer, Sink };
+
+/// Bytes BufferWriter gathers before writing them and BufferReader asks for at least.
+const STREAM_BUFFER_LEN: usize = 64 << 10;
+
+/// Writes values straight to a file or socket, with the same layout as a Buffer,
+/// so data too big for memory never has to be one Buffer.
+/// Values are gathered in a buffer of 64 KiB by default, a value bigger than that
+/// goes to the writer while it is encoded. It is also a Sink, errors then come out of
+/// the next write or finish. After an error every write fails.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut writer = BufferWriter::new(Vec::new());
+/// writer.write(&1u32)?.write(&String::from("streamed"))?;
+/// for i in 0..1000u64{
+///     writer.write(&i)?;
+/// }
+/// let file = writer.finish()?;
+/// let mut reader = BufferReader::new(&file[..]);
+/// assert_eq!(1u32, reader.read::<u32>()?);
+/// assert_eq!(String::from("streamed"), reader.read::<String>()?);
+/// let mut sum = 0;
+/// while !reader.is_at_end()?{
+///     sum += reader.read::<u64>()?;
+/// }
+/// assert_eq!(499500, sum);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct BufferWriter<W: Write>{
+    writer: W,
+    buffer: Buffer,
+    capacity: usize,
+    written: u64,
+    error: Option<io::Error>,
+    failed: bool,
+}
+
+impl<W: Write> BufferWriter<W>{
+    /// Write to writer, gathering 64 KiB at a time.
+    pub fn new(writer: W) -> Self{
+        Self::with_capacity(STREAM_BUFFER_LEN, writer)
+    }
+    /// Write to writer, gathering capacity bytes at a time.
+    pub fn with_capacity(capacity: usize, writer: W) -> Self{
+        Self{ writer, buffer: Vec::with_capacity(capacity), capacity, written: 0, error: Option::None, failed: false }
+    }
+    /// Encode x to the writer.
+    /// Gives back the writer, so writes can be chained: `w.write(&x)?.write(&y)?`.
+    pub fn write<T: Bufferable>(&mut self, x: &T) -> io::Result<&mut Self>{
+        x.copy_into_buffer(self);
+        self.check()?;
+        Ok(self)
+    }
+    /// Bytes of the values written so far, including those not handed to the writer yet.
+    pub fn written(&self) -> u64{
+        self.written
+    }
+    /// The writer.
+    pub fn get_ref(&self) -> &W{
+        &self.writer
+    }
+    /// Write what is gathered and flush the writer.
+    pub fn flush(&mut self) -> io::Result<()>{
+        self.write_buffer();
+        self.check()?;
+        self.writer.flush()
+    }
+    /// Flush and give back the writer.
+    pub fn finish(mut self) -> io::Result<W>{
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_buffer(&mut self){
+        if !self.buffer.is_empty(){
+            let buffer = std::mem::take(&mut self.buffer);
+            self.write_through(&buffer);
+            self.buffer = buffer;
+            self.buffer.clear();
+        }
+    }
+
+    fn write_through(&mut self, bytes: &[u8]){
+        if self.failed{
+            return;
+        }
+        if let Err(e) = self.writer.write_all(bytes){
+            self.error = Option::Some(e);
+            self.failed = true;
+        }
+    }
+
+    fn check(&mut self) -> io::Result<()>{
+        match self.error.take(){
+            Option::Some(e) => Err(e),
+            Option::None if self.failed => Err(io::Error::other("an earlier write failed")),
+            Option::None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Sink for BufferWriter<W>{
+    fn write_bytes(&mut self, bytes: &[u8]){
+        self.written += bytes.len() as u64;
+        if self.buffer.len() + bytes.len() > self.capacity{
+            self.write_buffer();
+            if bytes.len() >= self.capacity{
+                self.write_through(bytes);
+                return;
+            }
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+}
+
+/// Reads values straight from a file or socket, as written by BufferWriter or found in a Buffer.
+/// It reads 64 KiB or more at a time and keeps what is left after a value for the next one,
+/// so only the value being decoded has to fit in memory. It never waits for more than one
+/// read, so it works on sockets where the other side waits for an answer.
+/// A value is decoded from what has been read so far, and if that is not all of it,
+/// more is read and it is tried again. Bytes that can never be a T fail as soon as validate
+/// says so, the rest once the reader ends. The limits count for every value on its own.
+/// See BufferWriter for an example.
+pub struct BufferReader<R: Read>{
+    reader: R,
+    buffer: Buffer,
+    pos: usize,
+    end: bool,
+    limits: DecodeLimits,
+}
+
+impl<R: Read> BufferReader<R>{
+    /// Read from reader.
+    pub fn new(reader: R) -> Self{
+        Self{ reader, buffer: Vec::new(), pos: 0, end: false, limits: DecodeLimits::default() }
+    }
+    /// Decode values with limits.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self{
+        self.limits = limits;
+        self
+    }
+    /// Read the next value. Fails with UnexpectedEof if the reader ends before it does
+    /// and with InvalidData if the bytes are not a T.
+    pub fn read<T: Bufferable>(&mut self) -> io::Result<T>{
+        loop{
+            let bytes = &self.buffer[self.pos..];
+            let mut buf = ReadBuffer::borrowed(bytes, self.limits);
+            if let Option::Some(x) = T::from_buffer(&mut buf){
+                self.pos += buf.consumed();
+                return Ok(x);
+            }
+            let error = match T::validate(&mut ReadBuffer::borrowed(bytes, self.limits)){
+                Ok(()) => BufferError::Invalid,
+                Err(e) => e,
+            };
+            match error{
+                BufferError::UnexpectedEof{ .. } | BufferError::Invalid if !self.end => self.fill()?,
+                BufferError::UnexpectedEof{ .. } => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, error)),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+            }
+        }
+    }
+    /// Whether every byte has been read, reading more to find out if needed.
+    pub fn is_at_end(&mut self) -> io::Result<bool>{
+        while self.pos == self.buffer.len() && !self.end{
+            self.fill()?;
+        }
+        Ok(self.pos == self.buffer.len())
+    }
+    /// The reader.
+    pub fn get_ref(&self) -> &R{
+        &self.reader
+    }
+
+    // One read, so a socket is not waited on for more than it has. Room is made for as much
+    // again as is kept, at least 64 KiB, so a big value from a file takes few tries.
+    fn fill(&mut self) -> io::Result<()>{
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        let len = self.buffer.len();
+        self.buffer.resize(len + len.max(STREAM_BUFFER_LEN), 0);
+        let read = loop{
+            match self.reader.read(&mut self.buffer[len..]){
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buffer.truncate(len);
+                    return Err(e);
+                },
+            }
+        };
+        self.buffer.truncate(len + read);
+        self.end = read == 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    // hands out at most 3 bytes per read
+    struct Trickle<'a>(&'a [u8]);
+    impl std::io::Read for Trickle<'_>{
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>{
+            let n = buf.len().min(3).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    struct Failing;
+    impl std::io::Write for Failing{
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize>{ Err(std::io::ErrorKind::BrokenPipe.into()) }
+        fn flush(&mut self) -> std::io::Result<()>{ Ok(()) }
+    }
+
+    #[test]
+    fn test_buffer_writer(){
+        let mut writer = BufferWriter::with_capacity(16, Vec::new());
+        writer.write(&vec![7u8; 40]).unwrap().write(&2u16).unwrap();
+        // the big value went through, the small one is still gathered
+        assert_eq!(48, writer.get_ref().len());
+        assert_eq!(50, writer.written());
+        let mut expected = encode(&vec![7u8; 40]);
+        expected.extend(encode(&2u16));
+        assert_eq!(expected, writer.finish().unwrap());
+        let mut failing = BufferWriter::with_capacity(4, Failing);
+        assert_eq!(std::io::ErrorKind::BrokenPipe, failing.write(&1u64).err().unwrap().kind());
+        assert!(failing.write(&1u8).is_err());
+        assert!(failing.finish().is_err());
+    }
+
+    #[test]
+    fn test_buffer_reader(){
+        let values = vec![(String::from("a"), vec![1u32; 1000]), (String::from("b"), Vec::new())];
+        let mut bytes = Vec::new();
+        for x in &values{
+            x.copy_into_buffer(&mut bytes);
+        }
+        let mut reader = BufferReader::new(Trickle(&bytes));
+        assert_eq!(values[0], reader.read::<(String, Vec<u32>)>().unwrap());
+        assert!(!reader.is_at_end().unwrap());
+        assert_eq!(values[1], reader.read::<(String, Vec<u32>)>().unwrap());
+        assert!(reader.is_at_end().unwrap());
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, reader.read::<u8>().unwrap_err().kind());
+        // cut off, and bytes that are not a T
+        let mut reader = BufferReader::new(&bytes[..100]);
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, reader.read::<(String, Vec<u32>)>().unwrap_err().kind());
+        let mut reader = BufferReader::new(&[0, 0, 0, 0, 0, 0, 0, 1, 0xff, 0][..]);
+        assert_eq!(std::io::ErrorKind::InvalidData, reader.read::<String>().unwrap_err().kind());
+        let limits = DecodeLimits{ max_elements: 100, ..DecodeLimits::default() };
+        let mut reader = BufferReader::new(&bytes[..]).with_limits(limits);
+        assert_eq!(std::io::ErrorKind::InvalidData, reader.read::<(String, Vec<u32>)>().unwrap_err().kind());
+    }
+}
@@ -0,0 +1,185 @@
+//! Tag-length-value encoding, for structs that have to stay readable as they change.
+//! Every field is written with a numeric tag and its length in bytes.
+//! Readers skip tags they do not know and get a default for tags that are missing,
+//! so fields can be added and removed without breaking old readers or old data.
+//! `#[derive(Bufferable)]` with `#[bufferable(tagged)]` writes a struct this way,
+//! every field with its `#[bufferable(tag = N)]` through field_or_skip and get_or_default.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::tagged::*;
+//! let mut writer = TaggedWriter::new();
+//! writer.field(1, &String::from("name"));
+//! writer.field(2, &7u32);
+//! writer.field(9, &1.5f64); // a field this reader does not know
+//! let mut buffer = Vec::new();
+//! writer.finish(&mut buffer);
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! let fields = TaggedFields::from_buffer(&mut buffer).unwrap();
+//! assert_eq!(Some(String::from("name")), fields.get_or_default(1));
+//! assert_eq!(Some(7u32), fields.get_or_default(2));
+//! assert_eq!(Some(0u64), fields.get_or_default(3));
+//! ```
+use std::convert::TryFrom;
+use crate::{ Buffer, Bufferable, ReadBuffer, Sink };
+
+/// Writes the fields of one tagged message.
+pub struct TaggedWriter{
+    body: Buffer,
+}
+
+impl TaggedWriter{
+    /// Start an empty message.
+    pub fn new() -> Self{
+        Self{ body: Vec::new() }
+    }
+    /// Write x as field tag.
+    pub fn field<T: Bufferable>(&mut self, tag: u32, x: &T) -> &mut Self{
+        tag.into_buffer(&mut self.body);
+        let len_at = self.body.len();
+        0u64.into_buffer(&mut self.body);
+        x.copy_into_buffer(&mut self.body);
+        let len = (self.body.len() - len_at - 8) as u64;
+        self.body[len_at..len_at + 8].copy_from_slice(&len.to_be_bytes());
+        self
+    }
+    /// Write x as field tag, unless it is the default value.
+    /// Readers using get_or_default get the same value back either way.
+    pub fn field_or_skip<T: Bufferable + Default + PartialEq>(&mut self, tag: u32, x: &T) -> &mut Self{
+        if *x != T::default(){
+            self.field(tag, x);
+        }
+        self
+    }
+    /// Bytes field_or_skip adds for x.
+    pub fn field_or_skip_len<T: Bufferable + Default + PartialEq>(x: &T) -> usize{
+        if *x != T::default() { 4 + 8 + x.buffer_len() } else { 0 }
+    }
+    /// Write the message, its length in bytes followed by the fields.
+    pub fn finish<S: Sink + ?Sized>(self, vec: &mut S){
+        (self.body.len() as u64).into_buffer(vec);
+        vec.write_bytes(&self.body);
+    }
+}
+
+impl Default for TaggedWriter{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+/// The fields of a tagged message that has been read.
+/// If a tag appears more than once the last one counts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaggedFields{
+    body: Buffer,
+    fields: Vec<(u32, usize, usize)>,
+}
+
+impl TaggedFields{
+    /// The bytes of field tag, None if it is not there.
+    pub fn raw(&self, tag: u32) -> Option<&[u8]>{
+        let (_, start, end) = self.fields.iter().rev().find(|(t, _, _)| *t == tag)?;
+        Option::Some(&self.body[*start..*end])
+    }
+    /// If field tag is there.
+    pub fn contains(&self, tag: u32) -> bool{
+        self.raw(tag).is_some()
+    }
+    /// The tags of the fields, in the order they were written.
+    pub fn tags(&self) -> impl Iterator<Item = u32> + '_{
+        self.fields.iter().map(|(t, _, _)| *t)
+    }
+    /// Read field tag. None if it is missing or does not decode as a T.
+    pub fn get<T: Bufferable>(&self, tag: u32) -> Option<T>{
+        let mut buf = ReadBuffer::from_raw(self.raw(tag)?.to_vec());
+        let x = T::from_buffer(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// Read field tag, or T::default() if it is missing.
+    /// None only if the field is there but does not decode as a T.
+    pub fn get_or_default<T: Bufferable + Default>(&self, tag: u32) -> Option<T>{
+        if self.contains(tag){
+            self.get(tag)
+        } else {
+            Option::Some(T::default())
+        }
+    }
+}
+
+impl Bufferable for TaggedFields{
+    const MIN_BUFFER_LEN: usize = 8;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        self.copy_into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        (self.body.len() as u64).into_buffer(vec);
+        vec.write_bytes(&self.body);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let body = buf.read_blob()?.to_vec();
+        buf.use_bytes(body.len())?;
+        let len = body.len();
+        let mut fields = Vec::new();
+        let mut body = ReadBuffer::from_raw(body);
+        while body.remaining() > 0{
+            let tag = u32::from_buffer(&mut body)?;
+            let field_len = usize::try_from(u64::from_buffer(&mut body)?).ok()?;
+            let start = len - body.remaining();
+            body.read_bytes(field_len)?;
+            fields.push((tag, start, start + field_len));
+        }
+        let body = body.into_raw();
+        Option::Some(Self{ body, fields })
+    }
+
+    fn buffer_len(&self) -> usize{
+        8 + self.body.len()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::tagged::*;
+
+    #[test]
+    fn test_tagged(){
+        let mut writer = TaggedWriter::new();
+        writer.field(1, &vec![1u16,2,3])
+            .field_or_skip(2, &0u8)
+            .field_or_skip(3, &String::from("kept"))
+            .field(1, &vec![4u16]);
+        assert_eq!(0, TaggedWriter::field_or_skip_len(&0u8));
+        assert_eq!(4 + 8 + 12, TaggedWriter::field_or_skip_len(&String::from("kept")));
+        let mut buffer = Vec::new();
+        writer.finish(&mut buffer);
+        5u8.into_buffer(&mut buffer);
+        let mut read = ReadBuffer::from_raw(buffer.clone());
+        let fields = TaggedFields::from_buffer(&mut read).unwrap();
+        assert_eq!(Some(5), u8::from_buffer(&mut read));
+        assert_eq!(vec![1, 3, 1], fields.tags().collect::<Vec<_>>());
+        assert_eq!(Some(vec![4u16]), fields.get(1));
+        assert!(!fields.contains(2));
+        assert_eq!(Some(0u8), fields.get_or_default(2));
+        assert_eq!(Some(String::from("kept")), fields.get(3));
+        // a field with the wrong type does not decode
+        assert_eq!(None, fields.get::<u64>(3));
+        // writing the fields back gives the same bytes
+        let mut again = Vec::new();
+        fields.copy_into_buffer(&mut again);
+        assert_eq!(&buffer[..buffer.len() - 1], &again[..]);
+        // a field length past the end of the message
+        let mut bad = Vec::new();
+        12u64.into_buffer(&mut bad);
+        1u32.into_buffer(&mut bad);
+        1u64.into_buffer(&mut bad);
+        assert_eq!(None, TaggedFields::from_buffer(&mut ReadBuffer::from_raw(bad)));
+    }
+}
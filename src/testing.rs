@@ -0,0 +1,589 @@
+//! Helpers for testing your own Bufferable impls.
+//! FaultyReadBuffer and check_faults feed a decoder truncated and corrupted input, which it has
+//! to survive without panicking or hanging. assert_roundtrip checks that an impl reads back
+//! what it writes, and with the `proptest` feature roundtrip_prop does so for generated values.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::testing::*;
+//! assert_faults(&(7u32, String::from("seven"), vec![7u8; 7]));
+//! ```
+//! ```
+//! # #[cfg(feature = "proptest")]{
+//! use bin_buffer::*;
+//! use bin_buffer::testing::*;
+//! roundtrip_prop::<(u32,String,Vec<f64>)>();
+//! # }
+//! ```
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::time::Duration;
+use crate::{ Buffer, Bufferable, DecodeLimits, ReadBuffer };
+use crate::schema::Value;
+#[cfg(feature = "fs")]
+use crate::schema::Schema;
+#[cfg(feature = "proptest")]
+use crate::ByteVec;
+#[cfg(feature = "proptest")]
+use proptest::prelude::*;
+#[cfg(feature = "proptest")]
+use proptest::test_runner::{ TestRunner, TestCaseError };
+
+/// A change made to an encoded value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault{
+    /// Only the first this many bytes are left.
+    Truncated(usize),
+    /// The byte at pos is xored with mask.
+    Flipped{
+        /// Position of the byte.
+        pos: usize,
+        /// Bits that are flipped.
+        mask: u8,
+    },
+    /// The byte at pos is replaced.
+    Replaced{
+        /// Position of the byte.
+        pos: usize,
+        /// The byte put there.
+        byte: u8,
+    },
+}
+
+impl std::fmt::Display for Fault{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result{
+        match self{
+            Fault::Truncated(len) => write!(f, "truncated to {} bytes", len),
+            Fault::Flipped{ pos, mask } => write!(f, "byte {} xored with {:#04x}", pos, mask),
+            Fault::Replaced{ pos, byte } => write!(f, "byte {} replaced with {:#04x}", pos, byte),
+        }
+    }
+}
+
+/// Limits FaultyReadBuffer reads with, so a corrupted length can not ask for all memory.
+pub const FAULT_LIMITS: DecodeLimits = DecodeLimits{
+    max_depth: crate::DEFAULT_MAX_DEPTH,
+    max_elements: 1 << 20,
+    max_string_len: 1 << 24,
+    max_total_bytes: 1 << 26,
+};
+
+/// The encoding of a value with every fault that can be made to it: cut off at every
+/// position, every bit of every byte flipped and every byte set to 0x00 and 0xff.
+pub struct FaultyReadBuffer{
+    bytes: Buffer,
+    limits: DecodeLimits,
+}
+
+impl FaultyReadBuffer{
+    /// Faults of the encoding of x.
+    pub fn new<T: Bufferable>(x: &T) -> Self{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        Self::from_bytes(bytes)
+    }
+    /// Faults of bytes.
+    pub fn from_bytes(bytes: Buffer) -> Self{
+        Self{ bytes, limits: FAULT_LIMITS }
+    }
+    /// Read with other limits than FAULT_LIMITS.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self{
+        self.limits = limits;
+        self
+    }
+    /// The bytes without faults.
+    pub fn bytes(&self) -> &[u8]{
+        &self.bytes
+    }
+    /// Every fault, the truncations first.
+    pub fn faults(&self) -> impl Iterator<Item = Fault>{
+        let len = self.bytes.len();
+        (0..len).map(Fault::Truncated)
+            .chain((0..len).flat_map(|pos| (0..8).map(move |bit| Fault::Flipped{ pos, mask: 1 << bit })))
+            .chain((0..len).flat_map(|pos| [0x00, 0xff].iter().map(move |&byte| Fault::Replaced{ pos, byte })))
+    }
+    /// The bytes with fault made to them.
+    pub fn faulty_bytes(&self, fault: Fault) -> Buffer{
+        let mut bytes = self.bytes.clone();
+        match fault{
+            Fault::Truncated(len) => bytes.truncate(len),
+            Fault::Flipped{ pos, mask } => bytes[pos] ^= mask,
+            Fault::Replaced{ pos, byte } => bytes[pos] = byte,
+        }
+        bytes
+    }
+    /// A reader over the bytes with fault made to them.
+    pub fn reader(&self, fault: Fault) -> ReadBuffer<'static>{
+        ReadBuffer::from_raw(self.faulty_bytes(fault)).with_limits(self.limits)
+    }
+}
+
+/// Longest check_faults waits for all faults to be decoded.
+pub const FAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Decode T from every fault of the encoding of x. Truncated input has to fail to decode,
+/// other faults may decode to something else, as long as nothing panics.
+/// Fails with what went wrong on the first fault that broke the decoder,
+/// or if decoding did not finish within FAULT_TIMEOUT, which points at an endless loop.
+pub fn check_faults<T: Bufferable + 'static>(x: &T) -> Result<(), String>{
+    check_faults_of::<T>(FaultyReadBuffer::new(x))
+}
+
+/// check_faults on the faults of any bytes.
+pub fn check_faults_of<T: Bufferable + 'static>(faulty: FaultyReadBuffer) -> Result<(), String>{
+    let at = Arc::new(AtomicUsize::new(0));
+    let progress = at.clone();
+    let faults: Vec<Fault> = faulty.faults().collect();
+    let (send, recv) = std::sync::mpsc::channel();
+    let checked = faults.clone();
+    std::thread::spawn(move ||{
+        for (i, fault) in checked.into_iter().enumerate(){
+            progress.store(i, Ordering::Relaxed);
+            let mut buf = faulty.reader(fault);
+            let decoded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| T::from_buffer(&mut buf).is_some()));
+            let error = match (fault, decoded){
+                (_, Err(_)) => Option::Some(format!("panicked with the input {}", fault)),
+                (Fault::Truncated(_), Ok(true)) => Option::Some(format!("decoded the input {}", fault)),
+                _ => Option::None,
+            };
+            if let Option::Some(error) = error{
+                let _ = send.send(Err(error));
+                return;
+            }
+        }
+        let _ = send.send(Ok(()));
+    });
+    match recv.recv_timeout(FAULT_TIMEOUT){
+        Ok(result) => result,
+        Err(_) => Err(format!("did not finish decoding the input {}", faults[at.load(Ordering::Relaxed)])),
+    }
+}
+
+/// check_faults, panicking with what went wrong.
+pub fn assert_faults<T: Bufferable + 'static>(x: &T){
+    if let Err(e) = check_faults(x){
+        panic!("{}", e);
+    }
+}
+
+/// Environment variable that makes check_golden write the golden files instead of comparing.
+pub const UPDATE_GOLDEN: &str = "BIN_BUFFER_UPDATE_GOLDEN";
+
+/// Hexdump of bytes, 16 per line, with the offset of every line in front.
+/// # Example
+/// ```
+/// use bin_buffer::testing::*;
+/// assert_eq!(format!("00000000  {:<47}  hi.\n", "68 69 0a"), hexdump(b"hi\n"));
+/// ```
+pub fn hexdump(bytes: &[u8]) -> String{
+    hexdump_at(bytes, 0)
+}
+
+fn hexdump_at(bytes: &[u8], start: usize) -> String{
+    let mut out = String::new();
+    for (i, row) in bytes.chunks(16).enumerate(){
+        let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+        let text: String = row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        out.push_str(&format!("{:08x}  {:<47}  {}\n", start + i * 16, hex.join(" "), text));
+    }
+    out
+}
+
+/// Where a and b first differ, their shorter length if one starts with the other,
+/// None if they are the same.
+pub fn first_difference(a: &[u8], b: &[u8]) -> Option<usize>{
+    if a == b{
+        return Option::None;
+    }
+    Option::Some(a.iter().zip(b).position(|(x, y)| x != y).unwrap_or_else(|| a.len().min(b.len())))
+}
+
+// The lines of bytes around pos.
+fn hexdump_near(bytes: &[u8], pos: usize) -> String{
+    let start = (pos / 16).saturating_sub(2) * 16;
+    hexdump_at(&bytes[start.min(bytes.len())..(start + 80).min(bytes.len())], start)
+}
+
+// The lines of a and b around pos.
+fn hexdump_around(a: &[u8], b: &[u8], pos: usize) -> String{
+    format!("expected:\n{}got:\n{}", hexdump_near(a, pos), hexdump_near(b, pos))
+}
+
+/// Describe how the bytes got differ from expected: their lengths,
+/// the first byte that differs and a hexdump of both around it. Empty if they are the same.
+pub fn byte_diff(expected: &[u8], got: &[u8]) -> String{
+    match first_difference(expected, got){
+        Option::None => String::new(),
+        Option::Some(pos) => format!("{} bytes expected, got {}, first difference at byte {} ({:#x})\n{}",
+            expected.len(), got.len(), pos, pos, hexdump_around(expected, got, pos)),
+    }
+}
+
+/// The places where two values differ, as paths like ".1[3]" with both sides.
+pub fn value_diff(expected: &Value, got: &Value) -> Vec<String>{
+    let mut diffs = Vec::new();
+    diff_into(expected, got, &mut String::new(), &mut diffs);
+    diffs
+}
+
+fn diff_into(a: &Value, b: &Value, path: &mut String, diffs: &mut Vec<String>){
+    match (a, b){
+        (Value::Seq(x), Value::Seq(y)) if x.len() == y.len() => {
+            for (i, (x, y)) in x.iter().zip(y).enumerate(){
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                diff_into(x, y, path, diffs);
+                path.truncate(len);
+            }
+        },
+        (Value::Map(x), Value::Map(y)) if x.len() == y.len() => {
+            for (i, ((xk, xv), (yk, yv))) in x.iter().zip(y).enumerate(){
+                let len = path.len();
+                path.push_str(&format!("{{{}}}", i));
+                diff_into(xk, yk, &mut format!("{}.key", path), diffs);
+                diff_into(xv, yv, path, diffs);
+                path.truncate(len);
+            }
+        },
+        _ if a != b => {
+            let path = if path.is_empty() { "value" } else { path.as_str() };
+            diffs.push(format!("{}: expected {:?}, got {:?}", path, a, b));
+        },
+        _ => {},
+    }
+}
+
+/// Compare the encoding of x with the golden file at path.
+/// A missing file is written, as is the file when the environment variable
+/// UPDATE_GOLDEN is set, so after a deliberate change the golden files can be made again.
+/// Fails with the byte_diff if the encoding changed, or if the file could not be read or written.
+/// # Example
+/// ```
+/// use bin_buffer::testing::*;
+/// let path = std::env::temp_dir().join("bin_buffer_golden_doc.bin");
+/// let _ = std::fs::remove_file(&path);
+/// assert_eq!(Ok(()), check_golden(&path, &(1u8, String::from("golden"))));
+/// assert_eq!(Ok(()), check_golden(&path, &(1u8, String::from("golden"))));
+/// assert!(check_golden(&path, &(1u8, String::from("changed"))).is_err());
+/// ```
+#[cfg(feature = "fs")]
+pub fn check_golden<T: Bufferable>(path: &std::path::Path, x: &T) -> Result<(), String>{
+    check_golden_with(path, x, Option::None)
+}
+
+/// check_golden that, when there is a schema, also lists the values that differ.
+#[cfg(feature = "fs")]
+pub fn check_golden_with_schema<T: Bufferable>(path: &std::path::Path, x: &T, schema: &Schema) -> Result<(), String>{
+    check_golden_with(path, x, Option::Some(schema))
+}
+
+#[cfg(feature = "fs")]
+fn check_golden_with<T: Bufferable>(path: &std::path::Path, x: &T, schema: Option<&Schema>) -> Result<(), String>{
+    let mut got = Vec::with_capacity(x.buffer_len());
+    x.copy_into_buffer(&mut got);
+    if std::env::var_os(UPDATE_GOLDEN).is_some() || !path.exists(){
+        if let Option::Some(dir) = path.parent(){
+            let _ = std::fs::create_dir_all(dir);
+        }
+        return std::fs::write(path, &got).map_err(|e| format!("could not write {}: {}", path.display(), e));
+    }
+    let expected = std::fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    if expected == got{
+        return Ok(());
+    }
+    let mut error = format!("encoding differs from {}, set {} to update it\n{}", path.display(), UPDATE_GOLDEN, byte_diff(&expected, &got));
+    if let Option::Some(schema) = schema{
+        let decode = |bytes: &[u8]| schema.decode(&mut ReadBuffer::from_raw(bytes.to_vec()));
+        match (decode(&expected), decode(&got)){
+            (Option::Some(a), Option::Some(b)) => for diff in value_diff(&a, &b){
+                error.push_str(&format!("{}\n", diff));
+            },
+            _ => error.push_str("the schema does not read both\n"),
+        }
+    }
+    Err(error)
+}
+
+/// check_golden, panicking with the difference.
+#[cfg(feature = "fs")]
+pub fn assert_golden<T: Bufferable>(path: &std::path::Path, x: &T){
+    if let Err(e) = check_golden(path, x){
+        panic!("{}", e);
+    }
+}
+
+/// Encode x, decode it again and say what went wrong if the same value does not come back
+/// or bytes are left over, with a hexdump of the encoding where it went wrong.
+/// When another value comes back, that is encoded too and the first byte where it differs is shown.
+/// None if x round trips.
+pub fn roundtrip_mismatch<T: Bufferable + PartialEq + std::fmt::Debug>(x: &T) -> Option<String>{
+    let bytes = crate::encode(x);
+    let mut buf = ReadBuffer::from_raw(bytes.clone());
+    let y = match T::from_buffer(&mut buf){
+        Option::Some(y) => y,
+        Option::None => {
+            let pos = bytes.len() - buf.remaining();
+            return Option::Some(format!("{:?} does not decode from its {} bytes, stopped at byte {} ({:#x})\n{}",
+                x, bytes.len(), pos, pos, hexdump_near(&bytes, pos)));
+        },
+    };
+    if &y != x{
+        let again = crate::encode(&y);
+        let diff = if again == bytes { String::from("which encodes the same\n") } else { byte_diff(&bytes, &again) };
+        return Option::Some(format!("{:?} decodes as {:?}, {}", x, y, diff));
+    }
+    if buf.remaining() != 0{
+        let pos = bytes.len() - buf.remaining();
+        return Option::Some(format!("{:?} leaves {} of its {} bytes after decoding, from byte {} ({:#x})\n{}",
+            x, buf.remaining(), bytes.len(), pos, pos, hexdump_near(&bytes, pos)));
+    }
+    Option::None
+}
+
+/// Panic with the roundtrip_mismatch if x does not round trip.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::testing::*;
+/// assert_roundtrip(&vec![(1u8, String::from("one")), (2, String::from("two"))]);
+/// assert_roundtrip(&ByteVec(vec![3, 4]));
+/// ```
+pub fn assert_roundtrip<T: Bufferable + PartialEq + std::fmt::Debug>(x: &T){
+    if let Option::Some(e) = roundtrip_mismatch(x){
+        panic!("{}", e);
+    }
+}
+
+#[cfg(feature = "proptest")]
+/// Encode x, decode it again and check that the same value comes back
+/// and the whole buffer was used.
+pub fn check_roundtrip<T: Bufferable + PartialEq + std::fmt::Debug>(x: &T) -> Result<(), TestCaseError>{
+    let mut buffer = Vec::new();
+    x.copy_into_buffer(&mut buffer);
+    let mut buffer = ReadBuffer::from_raw(buffer);
+    let y = T::from_buffer(&mut buffer);
+    prop_assert_eq!(Option::Some(x), y.as_ref());
+    prop_assert_eq!(0, buffer.remaining(), "bytes left after decoding");
+    Ok(())
+}
+
+#[cfg(feature = "proptest")]
+/// Run check_roundtrip on values generated by strategy.
+/// Panics with the smallest failing value if one is found.
+pub fn roundtrip_prop_with<T, S>(strategy: S)
+    where
+        T: Bufferable + PartialEq + std::fmt::Debug,
+        S: Strategy<Value = T>,
+{
+    let mut runner = TestRunner::default();
+    if let Err(e) = runner.run(&strategy, |x| check_roundtrip(&x)){
+        panic!("{}\n{}", e, runner);
+    }
+}
+
+#[cfg(feature = "proptest")]
+/// Run check_roundtrip on arbitrary values of T.
+pub fn roundtrip_prop<T>()
+    where T: Bufferable + Arbitrary + PartialEq + std::fmt::Debug,
+{
+    roundtrip_prop_with(any::<T>());
+}
+
+#[cfg(feature = "proptest")]
+impl Arbitrary for ByteVec{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy{
+        any::<Vec<u8>>().prop_map(ByteVec).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::testing::*;
+    use crate::schema::Schema;
+
+    #[test]
+    fn test_faults(){
+        assert_faults(&0u8);
+        assert_faults(&vec![String::from("a"), String::new()]);
+        assert_faults(&(1u64, vec![(2u16, 3.0f32)], ByteVec(vec![4, 5])));
+        assert_faults(&vec![vec![vec![1u32]]]);
+        let faulty = FaultyReadBuffer::new(&1u16);
+        assert_eq!(2 + 16 + 4, faulty.faults().count());
+        assert_eq!(vec![0, 3], faulty.faulty_bytes(Fault::Flipped{ pos: 1, mask: 2 }));
+        assert_eq!(None, u16::from_buffer(&mut faulty.reader(Fault::Truncated(1))));
+        // a decoder that does not notice it ran out of bytes
+        struct Lenient(u8);
+        impl Bufferable for Lenient{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                self.0.into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Lenient(u8::from_buffer(buf).unwrap_or(0)))
+            }
+        }
+        assert_eq!(Err(String::from("decoded the input truncated to 0 bytes")), check_faults(&Lenient(1)));
+        // and one that panics on a bad value
+        struct Picky(u8);
+        impl Bufferable for Picky{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                self.0.into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                let x = u8::from_buffer(buf)?;
+                assert!(x < 100);
+                Option::Some(Picky(x))
+            }
+        }
+        assert_eq!(Err(String::from("panicked with the input byte 0 xored with 0x80")), check_faults(&Picky(1)));
+    }
+
+    #[test]
+    fn test_golden(){
+        assert_eq!(None, first_difference(b"abc", b"abc"));
+        assert_eq!(Some(1), first_difference(b"abc", b"axc"));
+        assert_eq!(Some(2), first_difference(b"ab", b"abc"));
+        let a: Vec<u8> = (0..100).collect();
+        let mut b = a.clone();
+        b[70] = 0;
+        let diff = byte_diff(&a, &b);
+        assert!(diff.starts_with("100 bytes expected, got 100, first difference at byte 70 (0x46)\n"), "{}", diff);
+        assert!(diff.contains("00000040  40 41 42 43 44 45 46"), "{}", diff);
+        assert!(diff.contains("00000040  40 41 42 43 44 45 00"), "{}", diff);
+        let schema = Schema::Tuple(vec![Schema::U8, Schema::Vec(Box::new(Schema::Str))]);
+        let value = |bytes: Vec<u8>| schema.decode(&mut ReadBuffer::from_raw(bytes)).unwrap();
+        let x = value(encode(&(1u8, vec![String::from("a"), String::from("b")])));
+        let y = value(encode(&(2u8, vec![String::from("a"), String::from("c")])));
+        assert_eq!(vec!["[0]: expected UInt(1), got UInt(2)", "[1][1]: expected Str(\"b\"), got Str(\"c\")"], value_diff(&x, &y));
+        #[cfg(feature = "fs")]
+        {
+            let path = std::env::temp_dir().join("bin_buffer_golden_test").join("value.bin");
+            let _ = std::fs::remove_file(&path);
+            let x = (1u8, vec![String::from("a"), String::from("b")]);
+            assert_golden(&path, &x);
+            assert_golden(&path, &x);
+            let error = check_golden_with_schema(&path, &(1u8, vec![String::from("a")]), &schema).unwrap_err();
+            assert!(error.contains("first difference at byte 8"), "{}", error);
+            assert!(error.contains("[1]: expected Seq"), "{}", error);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip(){
+        assert_roundtrip(&0u64);
+        assert_roundtrip(&(String::from("a"), vec![1.5f32], ByteVec(vec![2])));
+        assert_eq!(None, roundtrip_mismatch(&vec![vec![1u16]]));
+        // drops the high byte
+        #[derive(Debug, PartialEq)]
+        struct Short(u16);
+        impl Bufferable for Short{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                self.0.into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Short(u16::from(u16::from_buffer(buf)? as u8)))
+            }
+        }
+        let e = roundtrip_mismatch(&Short(0x1234)).unwrap();
+        assert!(e.starts_with("Short(4660) decodes as Short(52), 2 bytes expected, got 2, first difference at byte 0 (0x0)\n"), "{}", e);
+        assert!(e.contains("00000000  12 34"), "{}", e);
+        assert_eq!(None, roundtrip_mismatch(&Short(0x34)));
+        // reads one byte too few or too many
+        #[derive(Debug, PartialEq)]
+        struct Off(u8, bool);
+        impl Bufferable for Off{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                (self.0, 0u8).into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                let x = u8::from_buffer(buf)?;
+                if x == 1{
+                    <(u8, u8)>::from_buffer(buf)?;
+                }
+                Option::Some(Off(x, false))
+            }
+        }
+        let e = roundtrip_mismatch(&Off(0, false)).unwrap();
+        assert!(e.starts_with("Off(0, false) leaves 1 of its 2 bytes after decoding, from byte 1 (0x1)\n"), "{}", e);
+        let e = roundtrip_mismatch(&Off(1, false)).unwrap();
+        assert!(e.starts_with("Off(1, false) does not decode from its 2 bytes, stopped at byte 2 (0x2)\n"), "{}", e);
+        let e = roundtrip_mismatch(&Off(2, true)).unwrap();
+        assert!(e.starts_with("Off(2, true) decodes as Off(2, false), which encodes the same\n"), "{}", e);
+    }
+
+    #[test]
+    #[should_panic(expected = "first difference at byte 0")]
+    fn test_assert_roundtrip_panics(){
+        #[derive(Debug, PartialEq)]
+        struct Inc(u8);
+        impl Bufferable for Inc{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                self.0.into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Inc(u8::from_buffer(buf)?.wrapping_add(1)))
+            }
+        }
+        assert_roundtrip(&Inc(1));
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn test_roundtrip_prop(){
+        roundtrip_prop::<u64>();
+        roundtrip_prop::<usize>();
+        roundtrip_prop::<u16>();
+        roundtrip_prop::<u8>();
+        roundtrip_prop::<String>();
+        roundtrip_prop::<ByteVec>();
+        roundtrip_prop::<Vec<(u32,String)>>();
+        roundtrip_prop::<(u8,u16,u32,u64)>();
+        // NaN != NaN, so keep to normal floats
+        roundtrip_prop_with(proptest::num::f64::NORMAL);
+        roundtrip_prop_with(proptest::collection::vec(proptest::num::f32::NORMAL, 0..100));
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    #[should_panic]
+    fn test_roundtrip_prop_catches(){
+        struct Bad(u32);
+        impl Bufferable for Bad{
+            fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                self.copy_into_buffer(vec);
+            }
+            fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                (self.0 as u16).into_buffer(vec);
+            }
+            fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                Option::Some(Bad(u32::from(u16::from_buffer(buf)?)))
+            }
+        }
+        impl PartialEq for Bad{
+            fn eq(&self, other: &Self) -> bool{ self.0 == other.0 }
+        }
+        impl std::fmt::Debug for Bad{
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{ write!(f, "Bad({})", self.0) }
+        }
+        roundtrip_prop_with(any::<u32>().prop_map(Bad));
+    }
+}
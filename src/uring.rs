@@ -0,0 +1,348 @@
+//! Sequential file IO through io_uring, enabled with the `io-uring` feature on Linux.
+//! UringWriter and UringReader are a Write and a Read over a file that keep several blocks
+//! in flight at once, so streaming a large encode or decode keeps the disk busy from a single
+//! thread instead of waiting on every write or read in turn.
+//! Both work with everything that takes a Write or Read, such as net::WriteBufferableExt
+//! and net::ReadBufferableExt, chunked::ChunkSink or io::copy.
+//! Creating the ring fails on kernels without io_uring or where it is turned off,
+//! callers that have to run there can fall back to a BufWriter or BufReader.
+//! # Example
+//! ```
+//! use bin_buffer::net::*;
+//! use bin_buffer::uring::*;
+//! let path = std::env::temp_dir().join("bin_buffer_uring_doc.bin");
+//! # let run = || -> std::io::Result<()>{
+//! let mut writer = UringWriter::create(&path)?;
+//! for i in 0..1000u32{
+//!     writer.write_bufferable(&(i, format!("row {}", i)))?;
+//! }
+//! writer.finish()?;
+//! let mut reader = UringReader::open(&path)?;
+//! for i in 0..1000u32{
+//!     assert_eq!((i, format!("row {}", i)), reader.read_bufferable::<(u32, String)>()?);
+//! }
+//! # Ok(()) };
+//! # if let Err(e) = run(){ assert_eq!(std::io::ErrorKind::Unsupported, e.kind()); }
+//! # let _ = std::fs::remove_file(&path);
+//! ```
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{ self, Read, Write };
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use io_uring::{ opcode, types, IoUring };
+use crate::Buffer;
+
+/// Bytes in a block, the size of every write and read but the last.
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+/// Blocks in flight at once.
+pub const DEFAULT_QUEUE_DEPTH: usize = 8;
+
+// io_uring missing or turned off becomes Unsupported, like http does for https.
+fn new_ring(depth: usize) -> io::Result<IoUring>{
+    assert!(depth > 0, "io_uring queue depth is 0");
+    IoUring::new(u32::try_from(depth.next_power_of_two()).unwrap_or(u32::MAX)).map_err(|e|{
+        match e.raw_os_error(){
+            Option::Some(38) | Option::Some(1) => io::Error::new(io::ErrorKind::Unsupported, e),
+            _ => e,
+        }
+    })
+}
+
+// Queue one entry, submitting what is queued first if the queue is full.
+fn queue(ring: &mut IoUring, entry: &io_uring::squeue::Entry) -> io::Result<()>{
+    // Safety: callers keep the buffer of the entry alive and in place until its completion.
+    unsafe{
+        if ring.submission().push(entry).is_err(){
+            ring.submit()?;
+            ring.submission().push(entry).map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+        }
+    }
+    Ok(())
+}
+
+/// A Write to a file that writes blocks through io_uring, several at a time.
+/// Call finish to wait for all of them and see any error, dropping it waits but ignores errors.
+pub struct UringWriter{
+    file: File,
+    ring: IoUring,
+    block: Buffer,
+    block_size: usize,
+    depth: usize,
+    offset: u64,
+    // Blocks being written, by their offset, held until the kernel is done with them.
+    in_flight: HashMap<u64, Buffer>,
+    error: Option<io::Error>,
+}
+
+impl UringWriter{
+    /// Create or truncate the file at path, with DEFAULT_BLOCK_SIZE and DEFAULT_QUEUE_DEPTH.
+    pub fn create(path: &Path) -> io::Result<Self>{
+        Self::new(File::create(path)?, DEFAULT_BLOCK_SIZE, DEFAULT_QUEUE_DEPTH)
+    }
+    /// Write file from its start in blocks of block_size, with at most depth blocks in flight.
+    /// Panics if block_size or depth is 0.
+    pub fn new(file: File, block_size: usize, depth: usize) -> io::Result<Self>{
+        assert!(block_size > 0, "io_uring block size is 0");
+        Ok(Self{
+            ring: new_ring(depth)?,
+            file,
+            block: Vec::with_capacity(block_size),
+            block_size,
+            depth,
+            offset: 0,
+            in_flight: HashMap::new(),
+            error: Option::None,
+        })
+    }
+    /// Bytes written so far, in flight or not.
+    pub fn position(&self) -> u64{
+        self.offset + self.block.len() as u64
+    }
+    // Hand the current block to the kernel.
+    fn submit_block(&mut self) -> io::Result<()>{
+        if self.block.is_empty(){
+            return Ok(());
+        }
+        while self.in_flight.len() >= self.depth{
+            self.complete(1)?;
+        }
+        let block = std::mem::replace(&mut self.block, Vec::with_capacity(self.block_size));
+        let entry = opcode::Write::new(types::Fd(self.file.as_raw_fd()), block.as_ptr(), block.len() as u32)
+            .offset(self.offset)
+            .build()
+            .user_data(self.offset);
+        let len = block.len() as u64;
+        queue(&mut self.ring, &entry)?;
+        self.in_flight.insert(self.offset, block);
+        self.offset += len;
+        self.ring.submit()?;
+        Ok(())
+    }
+    // Wait for at least wait writes to finish, writing the rest of any short ones directly.
+    // Only fails if waiting does, errors of the writes are kept for complete.
+    fn reap(&mut self, wait: usize) -> io::Result<()>{
+        self.ring.submit_and_wait(wait)?;
+        let done: Vec<(u64, i32)> = self.ring.completion().map(|c| (c.user_data(), c.result())).collect();
+        for (offset, res) in done{
+            let block = self.in_flight.remove(&offset).expect("completion of a write in flight");
+            if res < 0{
+                self.error.get_or_insert(io::Error::from_raw_os_error(-res));
+            } else if (res as usize) < block.len(){
+                if let Err(e) = self.file.write_all_at(&block[res as usize..], offset + res as u64){
+                    self.error.get_or_insert(e);
+                }
+            }
+        }
+        Ok(())
+    }
+    // reap, failing with the first error of a write.
+    fn complete(&mut self, wait: usize) -> io::Result<()>{
+        self.reap(wait)?;
+        match self.error.take(){
+            Option::Some(e) => Err(e),
+            Option::None => Ok(()),
+        }
+    }
+    /// Write out what is left, wait for every block and give back the file.
+    pub fn finish(mut self) -> io::Result<File>{
+        self.flush()?;
+        let file = self.file.try_clone()?;
+        Ok(file)
+    }
+}
+
+impl Write for UringWriter{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>{
+        let n = buf.len().min(self.block_size - self.block.len());
+        self.block.extend_from_slice(&buf[..n]);
+        if self.block.len() == self.block_size{
+            self.submit_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()>{
+        self.submit_block()?;
+        while !self.in_flight.is_empty(){
+            self.complete(1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for UringWriter{
+    fn drop(&mut self){
+        // the kernel may still be reading from blocks we own
+        let _ = self.flush();
+        while !self.in_flight.is_empty() && self.reap(1).is_ok(){}
+    }
+}
+
+/// A Read from a file that reads blocks ahead through io_uring, several at a time.
+pub struct UringReader{
+    file: File,
+    ring: IoUring,
+    len: u64,
+    block_size: usize,
+    depth: usize,
+    // Where the next read is submitted.
+    next: u64,
+    // The block being read from, how far, and where the block after it starts.
+    block: Buffer,
+    pos: usize,
+    block_end: u64,
+    // Blocks by their offset, being read or done and waiting for their turn.
+    in_flight: HashMap<u64, Buffer>,
+    done: HashMap<u64, io::Result<usize>>,
+}
+
+impl UringReader{
+    /// Open the file at path, with DEFAULT_BLOCK_SIZE and DEFAULT_QUEUE_DEPTH.
+    pub fn open(path: &Path) -> io::Result<Self>{
+        Self::new(File::open(path)?, DEFAULT_BLOCK_SIZE, DEFAULT_QUEUE_DEPTH)
+    }
+    /// Read file from its start to the length it has now, in blocks of block_size,
+    /// with at most depth blocks read ahead.
+    /// Panics if block_size or depth is 0.
+    pub fn new(file: File, block_size: usize, depth: usize) -> io::Result<Self>{
+        assert!(block_size > 0, "io_uring block size is 0");
+        let len = file.metadata()?.len();
+        Ok(Self{
+            ring: new_ring(depth)?,
+            file,
+            len,
+            block_size,
+            depth,
+            next: 0,
+            block: Vec::new(),
+            pos: 0,
+            block_end: 0,
+            in_flight: HashMap::new(),
+            done: HashMap::new(),
+        })
+    }
+    /// Bytes of the file it reads.
+    pub fn len(&self) -> u64{
+        self.len
+    }
+    /// Whether the file is empty.
+    pub fn is_empty(&self) -> bool{
+        self.len == 0
+    }
+    // Submit reads until depth blocks are ahead or the file is covered.
+    fn read_ahead(&mut self) -> io::Result<()>{
+        while self.in_flight.len() < self.depth && self.next < self.len{
+            let len = (self.len - self.next).min(self.block_size as u64) as usize;
+            let mut block = vec![0u8; len];
+            let entry = opcode::Read::new(types::Fd(self.file.as_raw_fd()), block.as_mut_ptr(), len as u32)
+                .offset(self.next)
+                .build()
+                .user_data(self.next);
+            queue(&mut self.ring, &entry)?;
+            self.in_flight.insert(self.next, block);
+            self.next += len as u64;
+            self.ring.submit()?;
+        }
+        Ok(())
+    }
+    // The block at offset, waiting for it if needed. None at the end.
+    fn next_block(&mut self, offset: u64) -> io::Result<Option<Buffer>>{
+        self.read_ahead()?;
+        if !self.in_flight.contains_key(&offset){
+            return Ok(Option::None);
+        }
+        while !self.done.contains_key(&offset){
+            self.ring.submit_and_wait(1)?;
+            let done: Vec<(u64, i32)> = self.ring.completion().map(|c| (c.user_data(), c.result())).collect();
+            for (at, res) in done{
+                let res = if res < 0 { Err(io::Error::from_raw_os_error(-res)) } else { Ok(res as usize) };
+                self.done.insert(at, res);
+            }
+        }
+        let mut block = self.in_flight.remove(&offset).expect("block in flight");
+        self.block_end = offset + block.len() as u64;
+        let read = self.done.remove(&offset).expect("completed block")?;
+        if read < block.len(){
+            // a short read, or the file shrank
+            let got = read + self.file.read_at(&mut block[read..], offset + read as u64)?;
+            block.truncate(got);
+        }
+        Ok(Option::Some(block))
+    }
+}
+
+impl Read for UringReader{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>{
+        if self.pos == self.block.len(){
+            match self.next_block(self.block_end)?{
+                Option::Some(block) => {
+                    self.block = block;
+                    self.pos = 0;
+                },
+                Option::None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.pos);
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Drop for UringReader{
+    fn drop(&mut self){
+        // the kernel may still be writing into blocks we own
+        while self.done.len() < self.in_flight.len(){
+            if self.ring.submit_and_wait(1).is_err(){
+                break;
+            }
+            let done: Vec<u64> = self.ring.completion().map(|c| c.user_data()).collect();
+            for at in done{
+                self.done.insert(at, Ok(0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::net::*;
+    use crate::uring::*;
+
+    #[test]
+    fn test_uring(){
+        let path = std::env::temp_dir().join("bin_buffer_uring_test.bin");
+        let mut writer = match UringWriter::new(File::create(&path).unwrap(), 100, 3){
+            Ok(writer) => writer,
+            // no io_uring where the tests run
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => return,
+            Err(e) => panic!("{}", e),
+        };
+        let mut expected = Vec::new();
+        for i in 0..500u32{
+            let x = (i, vec![i as u8; (i % 13) as usize]);
+            writer.write_bufferable(&x).unwrap();
+            expected.write_bufferable(&x).unwrap();
+        }
+        assert_eq!(expected.len() as u64, writer.position());
+        writer.finish().unwrap();
+        assert_eq!(expected, std::fs::read(&path).unwrap());
+        let mut reader = UringReader::new(File::open(&path).unwrap(), 64, 4).unwrap();
+        assert_eq!(expected.len() as u64, reader.len());
+        for i in 0..500u32{
+            assert_eq!((i, vec![i as u8; (i % 13) as usize]), reader.read_bufferable::<(u32, Vec<u8>)>().unwrap());
+        }
+        let mut rest = Vec::new();
+        assert_eq!(0, reader.read_to_end(&mut rest).unwrap());
+        // dropped with reads in flight
+        let mut reader = UringReader::new(File::open(&path).unwrap(), 16, 8).unwrap();
+        let mut first = [0u8; 4];
+        reader.read_exact(&mut first).unwrap();
+        assert_eq!(expected[..4], first);
+        drop(reader);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
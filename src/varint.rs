@@ -0,0 +1,254 @@
+//! Variable length integers, LEB128 style: 7 bits per byte, lowest bits first,
+//! the high bit set on every byte but the last. Small numbers take a single byte.
+//! Every number has one encoding only: reading refuses a last byte of 0 after the first,
+//! which would only pad the number with zero bits.
+//! VarInt and VarIntSigned are numbers written this way, signed ones zigzag encoded first,
+//! and VarLen puts a varint length in front of a String, ByteVec or Vec instead of a u64.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::varint;
+//! let mut buffer = Vec::new();
+//! varint::write_u64(300, &mut buffer);
+//! assert_eq!(vec![0xac, 0x02], buffer);
+//! let mut buffer = ReadBuffer::from_raw(buffer);
+//! assert_eq!(Some(300), varint::read_u64(&mut buffer));
+//! let x = (varint::VarInt(5), varint::VarIntSigned(-3), varint::VarLen(String::from("ab")));
+//! assert_eq!(vec![5, 5, 2, b'a', b'b'], encode(&x));
+//! ```
+use crate::{ BufferError, Bufferable, ByteVec, ReadBuffer, Sink };
+use crate::fields::{ FieldEncoding, Prefixed };
+
+/// Most bytes a u64 can take.
+pub const MAX_LEN: usize = 10;
+
+/// Write x as a varint.
+pub fn write_u64<S: Sink + ?Sized>(mut x: u64, vec: &mut S){
+    let mut bytes = [0u8; MAX_LEN];
+    let mut len = 0;
+    loop{
+        let byte = (x & 0x7f) as u8;
+        x >>= 7;
+        if x == 0{
+            bytes[len] = byte;
+            len += 1;
+            break;
+        }
+        bytes[len] = byte | 0x80;
+        len += 1;
+    }
+    vec.write_bytes(&bytes[..len]);
+}
+/// Read a varint. None if the buffer ends first, it does not fit a u64 or it is longer than needed.
+pub fn read_u64(buf: &mut ReadBuffer) -> Option<u64>{
+    let mut x = 0u64;
+    for i in 0..MAX_LEN{
+        let byte = buf.read_bytes(1)?[0];
+        let bits = u64::from(byte & 0x7f);
+        // the tenth byte only has room for a single bit
+        if i == MAX_LEN - 1 && bits > 1{
+            return Option::None;
+        }
+        x |= bits << (7 * i);
+        if byte & 0x80 == 0{
+            return Option::Some(x).filter(|_| i == 0 || byte != 0);
+        }
+    }
+    Option::None
+}
+/// Read a varint from the start of bytes, returning it and how many bytes it took.
+/// # Example
+/// ```
+/// use bin_buffer::varint;
+/// assert_eq!(Some((300, 2)), varint::decode_u64(&[0xac, 0x02, 0xff]));
+/// ```
+pub fn decode_u64(bytes: &[u8]) -> Option<(u64, usize)>{
+    let mut x = 0u64;
+    for (i, byte) in bytes.iter().take(MAX_LEN).enumerate(){
+        let bits = u64::from(byte & 0x7f);
+        if i == MAX_LEN - 1 && bits > 1{
+            return Option::None;
+        }
+        x |= bits << (7 * i);
+        if byte & 0x80 == 0{
+            return Option::Some((x, i + 1)).filter(|_| i == 0 || *byte != 0);
+        }
+    }
+    Option::None
+}
+/// How many bytes x takes as a varint.
+pub fn len_u64(x: u64) -> usize{
+    let bits = 64 - x.leading_zeros() as usize;
+    bits.max(1).div_ceil(7)
+}
+/// Map signed to unsigned so small negative numbers stay small: 0, -1, 1, -2 become 0, 1, 2, 3.
+pub fn zigzag(x: i64) -> u64{
+    ((x << 1) ^ (x >> 63)) as u64
+}
+/// Undo zigzag.
+pub fn unzigzag(x: u64) -> i64{
+    ((x >> 1) as i64) ^ -((x & 1) as i64)
+}
+
+/// A u64 written as a varint, so small values take a single byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarInt(pub u64);
+
+impl Bufferable for VarInt{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_u64(self.0, vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        write_u64(self.0, vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        read_u64(buf).map(VarInt)
+    }
+
+    fn buffer_len(&self) -> usize{
+        len_u64(self.0)
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        validate_u64(buf)
+    }
+}
+
+/// An i64 written zigzag encoded as a varint, so small negative values take a single byte too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarIntSigned(pub i64);
+
+impl Bufferable for VarIntSigned{
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        write_u64(zigzag(self.0), vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        write_u64(zigzag(self.0), vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        read_u64(buf).map(|x| VarIntSigned(unzigzag(x)))
+    }
+
+    fn buffer_len(&self) -> usize{
+        len_u64(zigzag(self.0))
+    }
+
+    fn validate(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+        validate_u64(buf)
+    }
+}
+
+// read_u64 telling a buffer that ends apart from a varint that is too big.
+fn validate_u64(buf: &mut ReadBuffer) -> Result<(), BufferError>{
+    let rest = &buf.as_slice()[buf.consumed()..];
+    match decode_u64(rest){
+        Option::Some((_, len)) => buf.skip(len),
+        Option::None if rest.len() < MAX_LEN && rest.iter().all(|b| b & 0x80 != 0) => Err(buf.eof(rest.len() + 1)),
+        Option::None => Err(BufferError::Invalid),
+    }
+}
+
+/// A String, ByteVec or Vec<T> with a varint length in front instead of a u64,
+/// the same as `as fields::Prefixed<VarInt>` in a struct.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// use bin_buffer::varint::*;
+/// let x = VarLen(vec![1u16, 2]);
+/// assert_eq!(vec![2, 0, 1, 0, 2], encode(&x));
+/// assert_eq!(Some(x), decode(&[2, 0, 1, 0, 2]));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VarLen<T>(pub T);
+
+macro_rules! var_len{
+    ($(impl$(<$p:ident>)? for $t:ty),*) => {
+        $(
+            impl$(<$p: Bufferable>)? Bufferable for VarLen<$t>{
+                fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+                    self.copy_into_buffer(vec);
+                }
+
+                fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+                    <Prefixed<VarInt> as FieldEncoding<$t>>::write(&self.0, vec);
+                }
+
+                fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+                    <Prefixed<VarInt> as FieldEncoding<$t>>::read(buf).map(VarLen)
+                }
+
+                fn buffer_len(&self) -> usize{
+                    <Prefixed<VarInt> as FieldEncoding<$t>>::len(&self.0)
+                }
+            }
+        )*
+    };
+}
+
+var_len!(impl for String, impl for ByteVec, impl<T> for Vec<T>);
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::varint::*;
+
+    #[test]
+    fn test_varint(){
+        let xs = [0, 1, 127, 128, 300, 16383, 16384, u32::MAX as u64, u64::MAX - 1, u64::MAX];
+        let mut buffer = Vec::new();
+        for x in xs.iter(){
+            let before = buffer.len();
+            write_u64(*x, &mut buffer);
+            assert_eq!(len_u64(*x), buffer.len() - before);
+        }
+        let mut buffer = ReadBuffer::from_raw(buffer);
+        for x in xs.iter(){
+            assert_eq!(Some(*x), read_u64(&mut buffer));
+        }
+        assert_eq!(None, read_u64(&mut buffer));
+        // too long and too big
+        assert_eq!(None, read_u64(&mut ReadBuffer::from_raw(vec![0x80; 11])));
+        assert_eq!(None, read_u64(&mut ReadBuffer::from_raw(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02])));
+        for x in [0i64, -1, 1, -2, 2, i64::MIN, i64::MAX].iter(){
+            assert_eq!(*x, unzigzag(zigzag(*x)));
+        }
+        assert_eq!(3, zigzag(-2));
+        assert_eq!(Some((u64::MAX, 10)), decode_u64(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]));
+        assert_eq!(None, decode_u64(&[0x80, 0x80]));
+        // overlong encodings of a number that fits in fewer bytes
+        assert_eq!(Some((0x45, 1)), decode_u64(&[0x45]));
+        assert_eq!(None, decode_u64(&[0xc5, 0x00]));
+        assert_eq!(None, read_u64(&mut ReadBuffer::from_raw(vec![0xc5, 0x00])));
+        assert_eq!(None, read_u64(&mut ReadBuffer::from_raw(vec![0x80, 0x80, 0x00])));
+        assert_eq!(Some(0), read_u64(&mut ReadBuffer::from_raw(vec![0x00])));
+    }
+
+    #[test]
+    fn test_varint_types(){
+        let x = ((VarInt(300), VarIntSigned(-65)), VarLen(String::from("hi")), VarLen(ByteVec(vec![7; 200])), VarLen(vec![VarInt(1), VarInt(128)]));
+        let bytes = encode(&x);
+        assert_eq!(vec![0xac, 0x02, 0x81, 0x01, 2, b'h', b'i', 0xc8, 0x01], bytes[..9].to_vec());
+        assert_eq!(bytes.len(), x.buffer_len());
+        assert_eq!(Some(x), decode(&bytes));
+        assert_eq!(1, encoded_size_of(&VarIntSigned(-64)));
+        assert_eq!(Some(VarIntSigned(i64::MIN)), decode(&encode(&VarIntSigned(i64::MIN))));
+        assert_eq!(Err(BufferError::UnexpectedEof{ needed: 3, available: 2 }), validate::<VarInt>(&mut ReadBuffer::from_raw(vec![0x80, 0x80])));
+        assert_eq!(Err(BufferError::Invalid), validate::<VarInt>(&mut ReadBuffer::from_raw(vec![0x80; 11])));
+        assert_eq!(Err(BufferError::Invalid), validate::<VarLen<String>>(&mut ReadBuffer::from_raw(vec![1, 0xff])));
+        // one value, one encoding
+        assert_eq!(Some(VarInt(0x45)), decode(&[0x45]));
+        assert_eq!(None, decode::<VarInt>(&[0xc5, 0x00]));
+        assert_eq!(Err(BufferError::Invalid), validate::<VarInt>(&mut ReadBuffer::from_raw(vec![0xc5, 0x00])));
+        assert_eq!(None, decode::<VarIntSigned>(&[0x81, 0x00]));
+        assert_eq!(Err(BufferError::Invalid), validate::<VarIntSigned>(&mut ReadBuffer::from_raw(vec![0x81, 0x00])));
+        assert_eq!(Some(VarLen(Vec::<u8>::new())), decode(&[0x00]));
+        assert_eq!(None, decode::<VarLen<Vec<u8>>>(&[0x80, 0x00]));
+        assert_eq!(None, decode::<VarLen<String>>(&[0x81, 0x00, b'a']));
+        let limits = DecodeLimits{ max_string_len: 1, ..DecodeLimits::default() };
+        assert_eq!(None, VarLen::<String>::from_buffer(&mut ReadBuffer::from_raw(encode(&VarLen(String::from("hi")))).with_limits(limits)));
+    }
+}
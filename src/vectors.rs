@@ -0,0 +1,132 @@
+//! Known values of every supported type with their exact encoding,
+//! for checking implementations of the format in other languages.
+//! write_dir puts them in a directory, the test_vectors binary does that from the command line:
+//! `cargo run --bin test_vectors -- <dir>`.
+//! # Example
+//! ```
+//! use bin_buffer::vectors::*;
+//! let v = vectors().into_iter().find(|v| v.name == "u32_258").unwrap();
+//! assert_eq!("u32", v.type_name);
+//! assert_eq!(vec![0, 0, 1, 2], v.bytes);
+//! ```
+use std::fmt::Debug;
+use std::collections::{ HashMap, HashSet };
+use crate::*;
+
+/// A value and its encoding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestVector{
+    /// Unique name, also used as file name.
+    pub name: &'static str,
+    /// The Rust type it was encoded as.
+    pub type_name: &'static str,
+    /// The value, Debug formatted.
+    pub value: String,
+    /// The encoded bytes.
+    pub bytes: Buffer,
+}
+
+fn vector<T: Bufferable + Debug>(name: &'static str, type_name: &'static str, x: T) -> TestVector{
+    let value = format!("{:?}", x);
+    let mut bytes = Vec::new();
+    x.into_buffer(&mut bytes);
+    TestVector{ name, type_name, value, bytes }
+}
+/// All test vectors.
+pub fn vectors() -> Vec<TestVector>{
+    let mut map = HashMap::new();
+    map.insert(String::from("b"), 2u8);
+    map.insert(String::from("a"), 1u8);
+    let set: HashSet<u16> = [300, 1, 2].iter().cloned().collect();
+    vec![
+        vector("u8_0", "u8", 0u8),
+        vector("u8_255", "u8", 255u8),
+        vector("u16_258", "u16", 258u16),
+        vector("u32_258", "u32", 258u32),
+        vector("u32_max", "u32", u32::MAX),
+        vector("u64_258", "u64", 258u64),
+        vector("u64_max", "u64", u64::MAX),
+        vector("usize_258", "usize", 258usize),
+        vector("u128_max", "u128", u128::MAX),
+        vector("i8_neg_1", "i8", -1i8),
+        vector("i16_min", "i16", i16::MIN),
+        vector("i32_neg_70000", "i32", -70000i32),
+        vector("i64_min", "i64", i64::MIN),
+        vector("i128_neg_2", "i128", -2i128),
+        vector("isize_neg_258", "isize", -258isize),
+        vector("bool_false", "bool", false),
+        vector("bool_true", "bool", true),
+        vector("char_ascii", "char", 'a'),
+        vector("char_emoji", "char", '😀'),
+        vector("f32_1_5", "f32", 1.5f32),
+        vector("f32_neg_zero", "f32", -0.0f32),
+        vector("f32_inf", "f32", f32::INFINITY),
+        vector("f64_0_1", "f64", 0.1f64),
+        vector("f64_neg_2", "f64", -2.0f64),
+        vector("canonical_f64_nan", "CanonicalF64", CanonicalF64(f64::NAN)),
+        vector("canonical_f32_neg_zero", "CanonicalF32", CanonicalF32(-0.0)),
+        vector("string_empty", "String", String::new()),
+        vector("string_ascii", "String", String::from("hello")),
+        vector("string_utf8", "String", String::from("héllo ✓")),
+        vector("lossy_string", "LossyString", LossyString(String::from("ok"))),
+        vector("bytes", "ByteVec", ByteVec(vec![0, 1, 254, 255])),
+        vector("vec_empty", "Vec<u32>", Vec::<u32>::new()),
+        vector("vec_u16", "Vec<u16>", vec![1u16, 2, 3]),
+        vector("vec_string", "Vec<String>", vec![String::from("a"), String::from("bc")]),
+        vector("vec_vec_u8", "Vec<Vec<u8>>", vec![vec![1u8], vec![], vec![2, 3]]),
+        // Debug of a hash map is not in a fixed order
+        TestVector{ value: String::from("{\"a\": 1, \"b\": 2}"), ..vector("map_string_u8", "HashMap<String,u8>", map) },
+        TestVector{ value: String::from("{1, 2, 300}"), ..vector("set_u16", "HashSet<u16>", set) },
+        vector("tuple_2", "(u8,String)", (7u8, String::from("x"))),
+        vector("tuple_3", "(u16,f32,u64)", (1u16, 0.5f32, 2u64)),
+        vector("tuple_4", "(u8,u8,ByteVec,Vec<u8>)", (1u8, 2u8, ByteVec(vec![3]), vec![4u8])),
+    ]
+}
+/// One line per vector: name, type, hex bytes and value, separated by tabs.
+pub fn to_text(vectors: &[TestVector]) -> String{
+    let mut text = String::new();
+    for v in vectors{
+        let hex: String = v.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        text.push_str(&format!("{}\t{}\t{}\t{}\n", v.name, v.type_name, hex, v.value));
+    }
+    text
+}
+/// Write every vector to dir as a <name>.bin file with the raw bytes,
+/// plus a vectors.tsv index as given by to_text. Creates dir if needed.
+#[cfg(feature = "fs")]
+pub fn write_dir(dir: &std::path::Path) -> std::io::Result<()>{
+    std::fs::create_dir_all(dir)?;
+    let vectors = vectors();
+    for v in &vectors{
+        std::fs::write(dir.join(format!("{}.bin", v.name)), &v.bytes)?;
+    }
+    std::fs::write(dir.join("vectors.tsv"), to_text(&vectors))
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::vectors::*;
+
+    #[test]
+    fn test_vectors(){
+        let vs = vectors();
+        let get = |name: &str| vs.iter().find(|v| v.name == name).unwrap().bytes.clone();
+        assert_eq!(vec![0x3f, 0xc0, 0, 0], get("f32_1_5"));
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o'], get("string_ascii"));
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 3, 0, 1, 0, 2, 0, 3], get("vec_u16"));
+        assert_eq!(vec![0x7f, 0xf8, 0, 0, 0, 0, 0, 0], get("canonical_f64_nan"));
+        assert_eq!(vec![0xff, 0xfe, 0xee, 0x90], get("i32_neg_70000"));
+        assert_eq!(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0xfe], get("isize_neg_258"));
+        assert_eq!(vec![1], get("bool_true"));
+        assert_eq!(vec![0, 1, 0xf6, 0x00], get("char_emoji"));
+        // maps are sorted by key, so the bytes do not depend on the hasher
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1, b'a', 1, 0, 0, 0, 0, 0, 0, 0, 1, b'b', 2], get("map_string_u8"));
+        // names are unique and every vector reads back
+        let names: HashSet<_> = vs.iter().map(|v| v.name).collect();
+        assert_eq!(vs.len(), names.len());
+        assert_eq!(vs.len(), to_text(&vs).lines().count());
+        let mut buffer = ReadBuffer::from_raw(get("vec_vec_u8"));
+        assert_eq!(Some(vec![vec![1u8], vec![], vec![2, 3]]), Vec::<Vec<u8>>::from_buffer(&mut buffer));
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{ Buffer, Bufferable, ReadBuffer, Sink };
+
+/// A type with a version number, that can upgrade older versions of itself.
+/// Bump VERSION when the layout changes and add an upgrade from the old layout to migrations.
+pub trait Migrate: Bufferable + 'static{
+    /// Version of the current layout.
+    const VERSION: u32;
+    /// Upgrades from older versions. Has none by default.
+    fn migrations() -> Migrations<Self>{
+        Migrations::new()
+    }
+}
+
+type Upgrade<T> = Box<dyn Fn(&mut ReadBuffer) -> Option<T>>;
+
+/// Set of functions that read older versions of T.
+pub struct Migrations<T>{
+    upgrades: Vec<(u32, Upgrade<T>)>,
+}
+
+impl<T: 'static> Migrations<T>{
+    /// Create Migrations without any upgrades.
+    pub fn new() -> Self{
+        Self{ upgrades: Vec::new() }
+    }
+    /// Read version from the buffer with read.
+    /// Replaces an earlier upgrade for the same version.
+    pub fn add<F: Fn(&mut ReadBuffer) -> Option<T> + 'static>(mut self, version: u32, read: F) -> Self{
+        self.upgrades.retain(|(v, _)| *v != version);
+        self.upgrades.push((version, Box::new(read)));
+        self
+    }
+    /// Read version as an Old and turn it into a T with upgrade.
+    /// Old can itself be a type that upgrades from something older.
+    pub fn upgrade_from<Old: Bufferable, F: Fn(Old) -> T + 'static>(self, version: u32, upgrade: F) -> Self{
+        self.add(version, move |buf| Option::Some(upgrade(Old::from_buffer(buf)?)))
+    }
+    /// If there is an upgrade for version.
+    pub fn supports(&self, version: u32) -> bool{
+        self.upgrades.iter().any(|(v, _)| *v == version)
+    }
+    /// Read a version of T with its upgrade, None if there is none.
+    pub fn read(&self, version: u32, buf: &mut ReadBuffer) -> Option<T>{
+        let (_, read) = self.upgrades.iter().find(|(v, _)| *v == version)?;
+        read(buf)
+    }
+}
+
+impl<T: 'static> Default for Migrations<T>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+/// Writes T with its version number in front.
+/// Reading a buffer written by an older version goes through T::migrations, so old files keep loading.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// // version 1 was only a name, version 2 added an age
+/// #[derive(Debug, PartialEq)]
+/// struct Person{ name: String, age: u8 }
+/// impl Bufferable for Person{
+///     fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+///         self.copy_into_buffer(vec);
+///     }
+///     fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+///         self.name.copy_into_buffer(vec);
+///         self.age.copy_into_buffer(vec);
+///     }
+///     fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+///         Some(Person{ name: String::from_buffer(buf)?, age: u8::from_buffer(buf)? })
+///     }
+/// }
+/// impl Migrate for Person{
+///     const VERSION: u32 = 2;
+///     fn migrations() -> Migrations<Self>{
+///         Migrations::new().upgrade_from(1, |name: String| Person{ name, age: 0 })
+///     }
+/// }
+/// let mut buffer = Vec::new();
+/// 1u32.into_buffer(&mut buffer);
+/// String::from("old").into_buffer(&mut buffer);
+/// let mut buffer = ReadBuffer::from_raw(buffer);
+/// let Versioned(person) = Versioned::<Person>::from_buffer(&mut buffer).unwrap();
+/// assert_eq!(Person{ name: String::from("old"), age: 0 }, person);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Versioned<T>(pub T);
+
+impl<T: Migrate> Bufferable for Versioned<T>{
+    const MIN_BUFFER_LEN: usize = 4;
+
+    fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+        T::VERSION.into_buffer(vec);
+        self.0.into_buffer(vec);
+    }
+
+    fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+        T::VERSION.into_buffer(vec);
+        self.0.copy_into_buffer(vec);
+    }
+
+    fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+        let version = u32::from_buffer(buf)?;
+        if version == T::VERSION{
+            T::from_buffer(buf).map(Self)
+        } else {
+            T::migrations().read(version, buf).map(Self)
+        }
+    }
+
+    fn buffer_len(&self) -> usize{
+        4 + self.0.buffer_len()
+    }
+}
+
+type Step = Box<dyn Fn(&mut ReadBuffer) -> Option<Buffer>>;
+
+/// A ladder of upgrades between the encodings of versions, working on bytes.
+/// Every step reads one value of a version and writes it as the next version,
+/// so loading data of any older version goes up the ladder and then decodes once.
+/// The data is written as with Versioned: the version as a u32 and then the value.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// // version 1 was a u8, version 2 a u16 and version 3 a (u16, String)
+/// let migrator = Migrator::new(3)
+///     .step(1, |buf| Some(encode(&u16::from(u8::from_buffer(buf)?))))
+///     .step(2, |buf| Some(encode(&(u16::from_buffer(buf)?, String::from("new")))));
+/// let mut old = Vec::new();
+/// 1u32.into_buffer(&mut old);
+/// 7u8.into_buffer(&mut old);
+/// assert_eq!(Some((7u16, String::from("new"))), migrator.load(&old));
+/// let current = migrator.save(&(8u16, String::from("x")));
+/// assert_eq!(Some((8u16, String::from("x"))), migrator.load(&current));
+/// ```
+pub struct Migrator{
+    current: u32,
+    steps: Vec<(u32, Step)>,
+}
+
+impl Migrator{
+    /// A ladder up to version current, without steps.
+    pub fn new(current: u32) -> Self{
+        Self{ current, steps: Vec::new() }
+    }
+    /// The version data is written as.
+    pub fn current(&self) -> u32{
+        self.current
+    }
+    /// Add the step from version from to from + 1: upgrade reads one value of version from
+    /// and returns the bytes of it as from + 1. Replaces an earlier step from the same version.
+    /// Panics if from is not below the current version.
+    pub fn step<F: Fn(&mut ReadBuffer) -> Option<Buffer> + 'static>(mut self, from: u32, upgrade: F) -> Self{
+        assert!(from < self.current, "step from version {} goes past the current version {}", from, self.current);
+        self.steps.retain(|(v, _)| *v != from);
+        self.steps.push((from, Box::new(upgrade)));
+        self
+    }
+    /// Whether data of version can be loaded: it is the current one
+    /// or there are steps from it all the way up.
+    pub fn supports(&self, version: u32) -> bool{
+        version <= self.current && (version..self.current).all(|v| self.steps.iter().any(|(s, _)| *s == v))
+    }
+    fn run(&self, from: u32, buf: &mut ReadBuffer) -> Option<Buffer>{
+        let (_, step) = self.steps.iter().find(|(v, _)| *v == from)?;
+        step(buf)
+    }
+    /// Read one value of version from buf and upgrade it to the bytes of the current version.
+    /// None if a step is missing or fails, or a step leaves bytes of the one before it unread.
+    pub fn upgrade(&self, version: u32, buf: &mut ReadBuffer) -> Option<Buffer>{
+        if !self.supports(version) || version == self.current{
+            return Option::None;
+        }
+        let mut bytes = self.run(version, buf)?;
+        for v in version + 1..self.current{
+            let mut next = ReadBuffer::borrowed(&bytes, Default::default());
+            let upgraded = self.run(v, &mut next)?;
+            if next.remaining() != 0{
+                return Option::None;
+            }
+            bytes = upgraded;
+        }
+        Option::Some(bytes)
+    }
+    /// Read a version number and a value of that version, upgrading it if it is older.
+    pub fn read<T: Bufferable>(&self, buf: &mut ReadBuffer) -> Option<T>{
+        let version = u32::from_buffer(buf)?;
+        if version == self.current{
+            return T::from_buffer(buf);
+        }
+        let bytes = self.upgrade(version, buf)?;
+        let mut upgraded = ReadBuffer::borrowed(&bytes, Default::default());
+        let x = T::from_buffer(&mut upgraded)?;
+        if upgraded.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// Read bytes holding exactly one versioned value, see read.
+    pub fn load<T: Bufferable>(&self, bytes: &[u8]) -> Option<T>{
+        let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+        let x = self.read(&mut buf)?;
+        if buf.remaining() != 0{
+            return Option::None;
+        }
+        Option::Some(x)
+    }
+    /// Write x as the current version.
+    pub fn save<T: Bufferable>(&self, x: &T) -> Buffer{
+        let mut bytes = Vec::with_capacity(4 + x.buffer_len());
+        self.current.into_buffer(&mut bytes);
+        x.copy_into_buffer(&mut bytes);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Point{ x: f32, y: f32, z: f32 }
+
+    impl Bufferable for Point{
+        fn into_buffer<S: Sink + ?Sized>(self, vec: &mut S){
+            self.copy_into_buffer(vec);
+        }
+        fn copy_into_buffer<S: Sink + ?Sized>(&self, vec: &mut S){
+            (self.x, self.y, self.z).into_buffer(vec);
+        }
+        fn from_buffer(buf: &mut ReadBuffer) -> Option<Self>{
+            let (x, y, z) = <(f32,f32,f32)>::from_buffer(buf)?;
+            Option::Some(Point{ x, y, z })
+        }
+        fn buffer_len(&self) -> usize{
+            12
+        }
+    }
+
+    impl Migrate for Point{
+        const VERSION: u32 = 3;
+        fn migrations() -> Migrations<Self>{
+            Migrations::new()
+                // version 1 stored doubles
+                .upgrade_from(1, |(x, y): (f64,f64)| Point{ x: x as f32, y: y as f32, z: 0.0 })
+                .upgrade_from(2, |(x, y): (f32,f32)| Point{ x, y, z: 0.0 })
+        }
+    }
+
+    fn read(version: u32, payload: impl Bufferable) -> Option<Point>{
+        let mut buffer = Vec::new();
+        version.into_buffer(&mut buffer);
+        payload.into_buffer(&mut buffer);
+        Versioned::<Point>::from_buffer(&mut ReadBuffer::from_raw(buffer)).map(|v| v.0)
+    }
+
+    #[test]
+    fn test_versioned(){
+        let p = Versioned(Point{ x: 1.0, y: 2.0, z: 3.0 });
+        let mut buffer = Vec::new();
+        p.copy_into_buffer(&mut buffer);
+        assert_eq!(p.buffer_len(), buffer.len());
+        assert_eq!(Some(p), Versioned::from_buffer(&mut ReadBuffer::from_raw(buffer)));
+        assert_eq!(Some(Point{ x: 1.0, y: 2.0, z: 0.0 }), read(1, (1.0f64, 2.0f64)));
+        assert_eq!(Some(Point{ x: 4.0, y: 5.0, z: 0.0 }), read(2, (4.0f32, 5.0f32)));
+        assert_eq!(None, read(4, (1.0f32, 2.0f32, 3.0f32)));
+        assert!(Point::migrations().supports(1));
+        assert!(!Point::migrations().supports(3));
+    }
+
+    #[test]
+    fn test_migrator(){
+        // version 1 stored doubles, version 2 two floats and version 3 a Point
+        let migrator = Migrator::new(3)
+            .step(2, |buf|{
+                let (x, y) = <(f32,f32)>::from_buffer(buf)?;
+                Option::Some(encode(&Point{ x, y, z: 0.0 }))
+            })
+            .step(1, |buf|{
+                let (x, y) = <(f64,f64)>::from_buffer(buf)?;
+                Option::Some(encode(&(x as f32, y as f32)))
+            });
+        let mut v1 = Vec::new();
+        1u32.into_buffer(&mut v1);
+        (1.0f64, 2.0f64).into_buffer(&mut v1);
+        assert_eq!(Some(Point{ x: 1.0, y: 2.0, z: 0.0 }), migrator.load(&v1));
+        // the same layout as Versioned
+        let p = Point{ x: 1.0, y: 2.0, z: 3.0 };
+        let bytes = migrator.save(&p);
+        assert_eq!(encode(&Versioned(Point{ x: 1.0, y: 2.0, z: 3.0 })), bytes);
+        assert_eq!(Some(p), migrator.load(&bytes));
+        assert!(migrator.supports(1) && migrator.supports(3));
+        assert!(!migrator.supports(0) && !migrator.supports(4));
+        assert_eq!(None, migrator.load::<Point>(&v1[..v1.len() - 1]));
+        assert_eq!(None, Migrator::new(3).step(2, |_| Option::Some(Vec::new())).load::<Point>(&v1));
+        // a step that leaves bytes of the version before it unread
+        let sloppy = Migrator::new(3)
+            .step(1, |_| Option::Some(encode(&(1.0f32, 2.0f32, 3.0f32))))
+            .step(2, |buf| Option::Some(encode(&<(f32,f32)>::from_buffer(buf)?)));
+        assert_eq!(None, sloppy.load::<(f32,f32)>(&v1));
+    }
+}
@@ -0,0 +1,228 @@
+//! A write ahead log: values appended in order, each with a sequence number,
+//! kept in a directory as segments that are journals, so every record has the CRC framing
+//! of journal and a torn tail after a crash is cut off when the log is opened.
+//! A segment is named after the sequence number of its first record, like
+//! 00000000000000000000.wal, and once it grows past the segment length a new one is started.
+//! Segments that are no longer needed, for example because a snapshot of the state was saved,
+//! are removed with remove_before.
+//! # Example
+//! ```
+//! use bin_buffer::wal::*;
+//! let dir = std::env::temp_dir().join("bin_buffer_wal_doc");
+//! let _ = std::fs::remove_dir_all(&dir);
+//! let mut wal = Wal::open(&dir).unwrap();
+//! assert_eq!(0, wal.append(&String::from("create")).unwrap());
+//! assert_eq!(1, wal.append(&String::from("update")).unwrap());
+//! wal.sync().unwrap();
+//! drop(wal);
+//! let wal = Wal::open(&dir).unwrap();
+//! let replayed: Vec<(SequenceNumber, String)> = wal.replay_from(1).unwrap().map(|r| r.unwrap()).collect();
+//! assert_eq!(vec![(1, String::from("update"))], replayed);
+//! ```
+use std::io;
+use std::marker::PhantomData;
+use std::path::{ Path, PathBuf };
+use crate::{ Bufferable, ReadBuffer };
+use crate::journal::{ Journal, RECORD_HEADER_LEN };
+
+/// Number of a record in the log, counting from 0.
+pub type SequenceNumber = u64;
+
+/// Bytes after which a new segment is started, 64 MiB.
+pub const DEFAULT_SEGMENT_LEN: u64 = 64 << 20;
+
+fn segment_name(first: SequenceNumber) -> String{
+    format!("{:020}.wal", first)
+}
+
+fn segment_first(name: &str) -> Option<SequenceNumber>{
+    let digits = name.strip_suffix(".wal")?;
+    if digits.len() != 20 || !digits.bytes().all(|b| b.is_ascii_digit()){
+        return Option::None;
+    }
+    digits.parse().ok()
+}
+
+/// An open write ahead log.
+pub struct Wal{
+    dir: PathBuf,
+    segment_len: u64,
+    // first sequence number of every segment, oldest first, the last one is current
+    segments: Vec<SequenceNumber>,
+    current: Journal,
+    current_len: u64,
+    next: SequenceNumber,
+}
+
+impl Wal{
+    /// Open the log in dir with segments of DEFAULT_SEGMENT_LEN, creating it if needed.
+    pub fn open(dir: &Path) -> io::Result<Self>{
+        Self::open_with_segment_len(dir, DEFAULT_SEGMENT_LEN)
+    }
+    /// Open the log in dir, starting a new segment when one grows past segment_len bytes.
+    pub fn open_with_segment_len(dir: &Path, segment_len: u64) -> io::Result<Self>{
+        std::fs::create_dir_all(dir)?;
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(dir)?{
+            let entry = entry?;
+            if let Option::Some(first) = entry.file_name().to_str().and_then(segment_first){
+                segments.push(first);
+            }
+        }
+        segments.sort_unstable();
+        if segments.is_empty(){
+            segments.push(0);
+        }
+        let first = segments[segments.len() - 1];
+        let path = dir.join(segment_name(first));
+        let current = Journal::open(&path)?;
+        let current_len = std::fs::metadata(&path)?.len();
+        let next = first + current.len() as u64;
+        Ok(Self{ dir: dir.to_path_buf(), segment_len, segments, current, current_len, next })
+    }
+    /// The directory of the log.
+    pub fn dir(&self) -> &Path{
+        &self.dir
+    }
+    /// The sequence number the next record will get.
+    pub fn next_sequence(&self) -> SequenceNumber{
+        self.next
+    }
+    /// The sequence number of the oldest record still in the log.
+    pub fn first_sequence(&self) -> SequenceNumber{
+        self.segments[0]
+    }
+    /// Number of segment files.
+    pub fn segments(&self) -> usize{
+        self.segments.len()
+    }
+    /// Append a record holding bytes and give its sequence number.
+    /// It is only sure to survive a crash after sync.
+    pub fn append_bytes(&mut self, bytes: &[u8]) -> io::Result<SequenceNumber>{
+        if self.current_len >= self.segment_len && !self.current.is_empty(){
+            self.rotate()?;
+        }
+        self.current.append_bytes(bytes)?;
+        self.current_len += (RECORD_HEADER_LEN + bytes.len()) as u64;
+        self.next += 1;
+        Ok(self.next - 1)
+    }
+    /// Append a record holding x and give its sequence number.
+    pub fn append<T: Bufferable>(&mut self, x: &T) -> io::Result<SequenceNumber>{
+        let mut bytes = Vec::with_capacity(x.buffer_len());
+        x.copy_into_buffer(&mut bytes);
+        self.append_bytes(&bytes)
+    }
+    fn rotate(&mut self) -> io::Result<()>{
+        self.current.sync()?;
+        self.current = Journal::open(&self.dir.join(segment_name(self.next)))?;
+        self.current_len = 0;
+        self.segments.push(self.next);
+        Ok(())
+    }
+    /// Wait until everything appended is on disk.
+    pub fn sync(&mut self) -> io::Result<()>{
+        self.current.sync()
+    }
+    /// Remove the segments that only hold records from before seq.
+    /// Records from before seq in the segment that has seq are kept.
+    /// Returns how many segments were removed.
+    pub fn remove_before(&mut self, seq: SequenceNumber) -> io::Result<usize>{
+        let mut removed = 0;
+        while self.segments.len() > 1 && self.segments[1] <= seq{
+            std::fs::remove_file(self.dir.join(segment_name(self.segments[0])))?;
+            self.segments.remove(0);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+    /// The records from sequence number from on, oldest first, read as T.
+    /// Fails with InvalidInput if from is older than the oldest record still in the log,
+    /// the items are InvalidData errors for records that are not exactly one T.
+    pub fn replay_from<T: Bufferable>(&self, from: SequenceNumber) -> io::Result<Replay<'_, T>>{
+        if from < self.first_sequence(){
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "records before the oldest segment are gone"));
+        }
+        let segment = self.segments.iter().rposition(|&first| first <= from).unwrap_or(0);
+        Ok(Replay{ wal: self, segment, journal: Option::None, seq: from, marker: PhantomData })
+    }
+}
+
+/// Iterator over the records of a Wal, see Wal::replay_from.
+pub struct Replay<'a, T>{
+    wal: &'a Wal,
+    segment: usize,
+    // the segment being read, None for the current one
+    journal: Option<Journal>,
+    seq: SequenceNumber,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: Bufferable> Iterator for Replay<'a, T>{
+    type Item = io::Result<(SequenceNumber, T)>;
+
+    fn next(&mut self) -> Option<Self::Item>{
+        while self.segment < self.wal.segments.len(){
+            let first = self.wal.segments[self.segment];
+            let last = self.segment + 1 == self.wal.segments.len();
+            if !last && self.journal.is_none(){
+                match Journal::open(&self.wal.dir.join(segment_name(first))){
+                    Ok(j) => self.journal = Option::Some(j),
+                    Err(e) => { self.segment = self.wal.segments.len(); return Option::Some(Err(e)); },
+                }
+            }
+            let journal = if last { &self.wal.current } else { self.journal.as_ref()? };
+            if let Option::Some(bytes) = journal.record((self.seq - first) as usize){
+                let seq = self.seq;
+                self.seq += 1;
+                let mut buf = ReadBuffer::borrowed(bytes, Default::default());
+                return Option::Some(match T::from_buffer(&mut buf){
+                    Option::Some(x) if buf.remaining() == 0 => Ok((seq, x)),
+                    _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("record {} is not one value", seq))),
+                });
+            }
+            if last{
+                break;
+            }
+            self.segment += 1;
+            self.journal = Option::None;
+            self.seq = self.seq.max(self.wal.segments[self.segment]);
+        }
+        Option::None
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::wal::*;
+
+    #[test]
+    fn test_wal(){
+        let dir = std::env::temp_dir().join("bin_buffer_wal_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut wal = Wal::open_with_segment_len(&dir, 100).unwrap();
+        for i in 0..20u64{
+            assert_eq!(i, wal.append(&i).unwrap());
+        }
+        // 20 bytes per record, so 5 records per segment
+        assert_eq!(4, wal.segments());
+        assert!(dir.join("00000000000000000015.wal").is_file());
+        let all: Vec<u64> = wal.replay_from::<u64>(0).unwrap().map(|r| r.unwrap().1).collect();
+        assert_eq!((0..20).collect::<Vec<_>>(), all);
+        assert_eq!(Some((7, 7)), wal.replay_from::<u64>(7).unwrap().next().map(|r| r.unwrap()));
+        assert_eq!(0, wal.replay_from::<u64>(20).unwrap().count());
+        assert_eq!(io::ErrorKind::InvalidData, wal.replay_from::<u8>(3).unwrap().next().unwrap().unwrap_err().kind());
+        wal.sync().unwrap();
+        drop(wal);
+        // a torn tail is cut off and the numbers go on where the log ended
+        crate::buffer_write_file_append(&dir.join("00000000000000000015.wal"), &[0, 0, 0]);
+        let mut wal = Wal::open_with_segment_len(&dir, 100).unwrap();
+        assert_eq!(20, wal.next_sequence());
+        assert_eq!(20, wal.append_bytes(&[]).unwrap());
+        assert_eq!(2, wal.remove_before(12).unwrap());
+        assert_eq!(10, wal.first_sequence());
+        assert_eq!(io::ErrorKind::InvalidInput, wal.replay_from::<u64>(9).err().unwrap().kind());
+        assert_eq!(vec![10, 11], wal.replay_from::<u64>(10).unwrap().take(2).map(|r| r.unwrap().0).collect::<Vec<_>>());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
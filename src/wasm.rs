@@ -0,0 +1,35 @@
+//! Moving buffers across the JavaScript boundary, enabled with the `wasm` feature.
+//! Values go out as a Uint8Array holding their bytes and come back in the same way,
+//! so a web build can share its save format with a native one.
+//! ```ignore
+//! use bin_buffer::wasm::*;
+//! #[wasm_bindgen]
+//! pub fn save(state: &State) -> Uint8Array{
+//!     to_uint8_array(state)
+//! }
+//! #[wasm_bindgen]
+//! pub fn load(bytes: &Uint8Array) -> Option<State>{
+//!     from_uint8_array(bytes)
+//! }
+//! ```
+use crate::{ Bufferable, ReadBuffer };
+pub use js_sys::Uint8Array;
+
+/// Encode x into a new Uint8Array.
+pub fn to_uint8_array<T: Bufferable>(x: &T) -> Uint8Array{
+    let mut buffer = Vec::with_capacity(x.buffer_len());
+    x.copy_into_buffer(&mut buffer);
+    Uint8Array::from(&buffer[..])
+}
+/// Copy encoded bytes into a new Uint8Array.
+pub fn buffer_to_uint8_array(buffer: &[u8]) -> Uint8Array{
+    Uint8Array::from(buffer)
+}
+/// Copy the bytes of a Uint8Array into a ReadBuffer, to read several values from it.
+pub fn read_buffer_from_uint8_array(array: &Uint8Array) -> ReadBuffer<'static>{
+    ReadBuffer::from_raw(array.to_vec())
+}
+/// Decode a T from the bytes of a Uint8Array.
+pub fn from_uint8_array<T: Bufferable>(array: &Uint8Array) -> Option<T>{
+    T::from_buffer(&mut read_buffer_from_uint8_array(array))
+}
@@ -0,0 +1,146 @@
+//! Hot reloading of buffer files, enabled with the `notify` feature.
+//! watch_buffer_file checks a file from a background thread and every time it changes on disk
+//! reads and decodes it again, handing the result to a callback.
+//! Changes are found by polling the modification time and the length of the file,
+//! so it works the same everywhere without dependencies.
+//! # Example
+//! ```
+//! use bin_buffer::*;
+//! use bin_buffer::watch::*;
+//! use std::time::Duration;
+//! let path = std::env::temp_dir().join("bin_buffer_watch_doc.bin");
+//! buffer_write_file(&path, &encode(&1u32));
+//! let (send, recv) = std::sync::mpsc::channel();
+//! let watcher = watch_buffer_file_every(&path, Duration::from_millis(10), move |config: std::io::Result<Vec<u32>>|{
+//!     let _ = send.send(config.ok());
+//! }).unwrap();
+//! buffer_write_file(&path, &encode(&vec![2u32, 3]));
+//! assert_eq!(Some(vec![2, 3]), recv.recv_timeout(Duration::from_secs(10)).unwrap());
+//! watcher.stop();
+//! ```
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread::JoinHandle;
+use std::time::{ Duration, SystemTime };
+use crate::{ Bufferable, ReadBuffer };
+
+/// How often watch_buffer_file checks the file.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Handle to a file being watched. The watching stops when this is dropped.
+pub struct FileWatcher{
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileWatcher{
+    /// Whether the watching thread is still running.
+    pub fn is_running(&self) -> bool{
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+    /// Stop watching, waiting until the callback is not running anymore.
+    pub fn stop(mut self){
+        self.shutdown();
+    }
+    fn shutdown(&mut self){
+        self.stop.store(true, Ordering::Relaxed);
+        if let Option::Some(thread) = self.thread.take(){
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for FileWatcher{
+    fn drop(&mut self){
+        self.shutdown();
+    }
+}
+
+// What is looked at to see if the file changed, None if it can not be read.
+fn stamp(path: &Path) -> Option<(SystemTime, u64)>{
+    let meta = std::fs::metadata(path).ok()?;
+    Option::Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Read the file at path as one T. Fails with the error of reading the file, or with
+/// InvalidData holding the BufferError if it does not hold exactly one T.
+pub fn read_buffer_file<T: Bufferable>(path: &Path) -> io::Result<T>{
+    let bytes = std::fs::read(path)?;
+    let mut buf = ReadBuffer::borrowed(&bytes, Default::default());
+    if let Option::Some(x) = T::from_buffer(&mut buf){
+        if buf.remaining() == 0{
+            return Ok(x);
+        }
+    }
+    let mut check = ReadBuffer::borrowed(&bytes, Default::default());
+    let error = match T::validate(&mut check){
+        Err(e) => e,
+        Ok(()) => crate::BufferError::Invalid,
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Watch the file at path using DEFAULT_POLL_INTERVAL, see watch_buffer_file_every.
+pub fn watch_buffer_file<T, F>(path: &Path, callback: F) -> io::Result<FileWatcher>
+    where T: Bufferable, F: FnMut(io::Result<T>) + Send + 'static{
+    watch_buffer_file_every(path, DEFAULT_POLL_INTERVAL, callback)
+}
+
+/// Watch the file at path, checking it every interval. Every time it changes it is read
+/// with read_buffer_file and callback gets the result, on the thread of the watcher.
+/// A file that disappears gives one error, it is read again when it comes back.
+/// The callback is not called for what is in the file when watching starts.
+/// Fails if the thread can not be started.
+pub fn watch_buffer_file_every<T, F>(path: &Path, interval: Duration, mut callback: F) -> io::Result<FileWatcher>
+    where T: Bufferable, F: FnMut(io::Result<T>) + Send + 'static{
+    let path: PathBuf = path.to_path_buf();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let mut last = stamp(&path);
+    let thread = std::thread::Builder::new().name(String::from("bin_buffer watch")).spawn(move ||{
+        while !stopped.load(Ordering::Relaxed){
+            std::thread::sleep(interval);
+            let now = stamp(&path);
+            if now == last{
+                continue;
+            }
+            last = now;
+            callback(read_buffer_file(&path));
+        }
+    })?;
+    Ok(FileWatcher{ stop, thread: Option::Some(thread) })
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    use crate::watch::*;
+
+    #[test]
+    fn test_watch(){
+        let path = std::env::temp_dir().join("bin_buffer_watch_test.bin");
+        buffer_write_file(&path, &encode(&String::from("first")));
+        assert_eq!(String::from("first"), read_buffer_file::<String>(&path).unwrap());
+        assert_eq!(io::ErrorKind::InvalidData, read_buffer_file::<u8>(&path).unwrap_err().kind());
+        let (send, recv) = std::sync::mpsc::channel();
+        let watcher = watch_buffer_file_every(&path, Duration::from_millis(5), move |x: io::Result<String>|{
+            let _ = send.send(x.map_err(|e| e.kind()));
+        }).unwrap();
+        assert!(watcher.is_running());
+        let wait = Duration::from_secs(10);
+        buffer_write_file(&path, &encode(&String::from("second")));
+        assert_eq!(Ok(String::from("second")), recv.recv_timeout(wait).unwrap());
+        buffer_write_file(&path, &[0, 0, 0, 0, 0, 0, 0, 9, b'x']);
+        assert_eq!(Err(io::ErrorKind::InvalidData), recv.recv_timeout(wait).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Err(io::ErrorKind::NotFound), recv.recv_timeout(wait).unwrap());
+        buffer_write_file(&path, &encode(&String::from("back")));
+        assert_eq!(Ok(String::from("back")), recv.recv_timeout(wait).unwrap());
+        drop(watcher);
+        // the callback is gone with the thread
+        assert!(recv.recv_timeout(wait).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
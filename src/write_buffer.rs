@@ -0,0 +1,355 @@
+use crate::{ Buffer, Bufferable, Sink };
+
+/// How many bytes a WriteBuffer holds before it moves to the heap.
+const INLINE_LEN: usize = 128;
+
+enum Storage{
+    Inline([u8; INLINE_LEN], usize),
+    Heap(Buffer),
+}
+
+/// Spot for a length that is filled in later, made by WriteBuffer::reserve_len_prefix.
+#[must_use = "the length is only written by WriteBuffer::finalize"]
+#[derive(Debug)]
+pub struct LenPrefix{
+    pos: usize,
+    cleared: u64,
+}
+
+/// Buffer to write to that keeps small messages on the stack.
+/// The first 128 bytes are stored inline, only bigger messages allocate.
+/// # Example
+/// ```
+/// use bin_buffer::*;
+/// let mut buffer = WriteBuffer::new();
+/// buffer.write(&1u32);
+/// buffer.write(&String::from("small"));
+/// assert!(buffer.is_inline());
+/// let mut buffer = ReadBuffer::from_raw(buffer.into_raw());
+/// assert_eq!(Some(1), u32::from_buffer(&mut buffer));
+/// ```
+pub struct WriteBuffer{
+    storage: Storage,
+    // times clear was called, so finalize can tell a placeholder was cleared away
+    cleared: u64,
+}
+
+impl WriteBuffer{
+    /// Create an empty WriteBuffer, without allocating.
+    pub fn new() -> Self{
+        Self{ storage: Storage::Inline([0; INLINE_LEN], 0), cleared: 0 }
+    }
+    /// Create an empty WriteBuffer with room for at least capacity bytes.
+    /// Only allocates if that is more than fits inline.
+    pub fn with_capacity(capacity: usize) -> Self{
+        if capacity <= INLINE_LEN { Self::new() }
+        else { Self{ storage: Storage::Heap(Vec::with_capacity(capacity)), cleared: 0 } }
+    }
+    /// Copy an object to the end of the buffer.
+    /// Gives back the buffer, so writes can be chained: `buffer.write(&x).write(&y)`.
+    pub fn write<T: Bufferable>(&mut self, x: &T) -> &mut Self{
+        x.copy_into_buffer(self);
+        self
+    }
+    /// Number of bytes written.
+    pub fn len(&self) -> usize{
+        self.as_slice().len()
+    }
+    /// If nothing has been written.
+    pub fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+    /// How many bytes fit before the next allocation.
+    pub fn capacity(&self) -> usize{
+        match &self.storage{
+            Storage::Inline(..) => INLINE_LEN,
+            Storage::Heap(vec) => vec.capacity(),
+        }
+    }
+    /// Remove everything written, keeping the memory for reuse.
+    /// Once a heap allocation exists it stays, so a WriteBuffer that is
+    /// cleared and reused for every message stops allocating as soon as it
+    /// has seen the largest message.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = WriteBuffer::new();
+    /// for i in 0..100u32{
+    ///     buffer.clear();
+    ///     buffer.write(&i);
+    ///     buffer.write(&vec![i; 50]);
+    ///     let message: &[u8] = buffer.as_slice();
+    ///     // send message ...
+    /// #   assert_eq!(212, message.len());
+    /// }
+    /// ```
+    pub fn clear(&mut self){
+        self.cleared = self.cleared.wrapping_add(1);
+        match &mut self.storage{
+            Storage::Inline(_, len) => *len = 0,
+            Storage::Heap(vec) => vec.clear(),
+        }
+    }
+    /// If the content still lives inline, without a heap allocation.
+    pub fn is_inline(&self) -> bool{
+        matches!(self.storage, Storage::Inline(..))
+    }
+    /// The bytes written so far.
+    pub fn as_slice(&self) -> &[u8]{
+        match &self.storage{
+            Storage::Inline(bytes, len) => &bytes[..*len],
+            Storage::Heap(vec) => vec,
+        }
+    }
+    /// Overwrite the bytes at pos with x, for filling in counts or offsets
+    /// that are only known after what follows is written.
+    /// Nothing changes and false is returned if x does not fit before the end.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = WriteBuffer::new();
+    /// buffer.write(&0u32);
+    /// buffer.write(&String::from("body"));
+    /// assert!(buffer.write_at(0, &(buffer.len() as u32)));
+    /// assert!(!buffer.write_at(14, &0u32));
+    /// let mut buffer = ReadBuffer::from_raw(buffer.into_raw());
+    /// assert_eq!(Some(16), u32::from_buffer(&mut buffer));
+    /// ```
+    pub fn write_at<T: Bufferable>(&mut self, pos: usize, x: &T) -> bool{
+        let mut bytes = WriteBuffer::new();
+        bytes.write(x);
+        let end = match pos.checked_add(bytes.len()){
+            Some(end) if end <= self.len() => end,
+            _ => return false,
+        };
+        self.as_mut_slice()[pos..end].copy_from_slice(bytes.as_slice());
+        true
+    }
+    /// Write a u64 placeholder for the length of what comes next.
+    /// Once that is written, finalize puts the number of bytes written since in its place,
+    /// which is the layout ByteVec and ReadBuffer::read_blob read.
+    /// # Example
+    /// ```
+    /// use bin_buffer::*;
+    /// let mut buffer = WriteBuffer::new();
+    /// let prefix = buffer.reserve_len_prefix();
+    /// buffer.write(&1u32);
+    /// buffer.write(&String::from("chunk"));
+    /// assert!(buffer.finalize(prefix));
+    /// let mut buffer = ReadBuffer::from_raw(buffer.into_raw());
+    /// assert_eq!(17, buffer.read_blob().unwrap().len());
+    /// ```
+    pub fn reserve_len_prefix(&mut self) -> LenPrefix{
+        let pos = self.len();
+        self.write(&0u64);
+        LenPrefix{ pos, cleared: self.cleared }
+    }
+    /// Fill in the length for prefix. Nothing is written and false is returned
+    /// if the buffer was cleared since prefix was reserved, even when it has grown past it again.
+    pub fn finalize(&mut self, prefix: LenPrefix) -> bool{
+        let start = prefix.pos + 8;
+        if prefix.cleared != self.cleared || start > self.len(){
+            return false;
+        }
+        let len = (self.len() - start) as u64;
+        self.write_at(prefix.pos, &len)
+    }
+    fn as_mut_slice(&mut self) -> &mut [u8]{
+        match &mut self.storage{
+            Storage::Inline(bytes, len) => &mut bytes[..*len],
+            Storage::Heap(vec) => vec,
+        }
+    }
+    /// Turn the WriteBuffer into a Buffer, allocating if it was inline.
+    pub fn into_raw(self) -> Buffer{
+        match self.storage{
+            Storage::Inline(bytes, len) => bytes[..len].to_vec(),
+            Storage::Heap(vec) => vec,
+        }
+    }
+    /// Move the content to the heap with room for extra more bytes.
+    fn spill(&mut self, extra: usize) -> &mut Buffer{
+        if let Storage::Inline(bytes, len) = &self.storage{
+            let mut vec = Vec::with_capacity((len + extra).max(2 * INLINE_LEN));
+            vec.extend_from_slice(&bytes[..*len]);
+            self.storage = Storage::Heap(vec);
+        }
+        match &mut self.storage{
+            Storage::Heap(vec) => vec,
+            Storage::Inline(..) => unreachable!(),
+        }
+    }
+}
+
+impl Default for WriteBuffer{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl Sink for WriteBuffer{
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]){
+        match &mut self.storage{
+            Storage::Inline(inline, len) if *len + bytes.len() <= INLINE_LEN => {
+                inline[*len..*len + bytes.len()].copy_from_slice(bytes);
+                *len += bytes.len();
+            },
+            Storage::Heap(vec) => vec.extend_from_slice(bytes),
+            Storage::Inline(..) => self.spill(bytes.len()).extend_from_slice(bytes),
+        }
+    }
+
+    fn reserve(&mut self, additional: usize){
+        match &mut self.storage{
+            Storage::Inline(_, len) if *len + additional <= INLINE_LEN => {},
+            Storage::Heap(vec) => vec.reserve(additional),
+            Storage::Inline(..) => { self.spill(additional); },
+        }
+    }
+
+    fn as_vec(&mut self) -> Option<&mut Buffer>{
+        match &mut self.storage{
+            Storage::Heap(vec) => Option::Some(vec),
+            Storage::Inline(..) => Option::None,
+        }
+    }
+}
+
+impl std::ops::Deref for WriteBuffer{
+    type Target = [u8];
+    fn deref(&self) -> &[u8]{
+        self.as_slice()
+    }
+}
+
+impl AsRef<[u8]> for WriteBuffer{
+    fn as_ref(&self) -> &[u8]{
+        self.as_slice()
+    }
+}
+
+impl std::fmt::Debug for WriteBuffer{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        f.debug_struct("WriteBuffer")
+            .field("inline", &self.is_inline())
+            .field("bytes", &self.as_slice())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::*;
+    #[test]
+    fn test_write_buffer_inline(){
+        let mut buffer = WriteBuffer::new();
+        for i in 0..16u64{
+            buffer.write(&i);
+        }
+        assert!(buffer.is_inline());
+        assert_eq!(128, buffer.len());
+        let mut expected = Vec::new();
+        for i in 0..16u64{
+            i.into_buffer(&mut expected);
+        }
+        assert_eq!(&expected[..], buffer.as_slice());
+        buffer.write(&0u8);
+        assert!(!buffer.is_inline());
+        0u8.into_buffer(&mut expected);
+        assert_eq!(expected, buffer.into_raw());
+        // chained writes give the same bytes as a Vec chained with put
+        let mut buffer = WriteBuffer::new();
+        buffer.write(&1u8).write(&String::from("a")).write(&(2u16, 3u32));
+        let mut vec = Vec::new();
+        vec.put(&1u8).put(&String::from("a")).put(&(2u16, 3u32));
+        assert_eq!(vec, buffer.into_raw());
+    }
+    #[test]
+    fn test_write_buffer_reuse(){
+        let mut buffer = WriteBuffer::new();
+        buffer.write(&vec![0u8; 1000]);
+        let capacity = buffer.capacity();
+        let ptr = buffer.as_slice().as_ptr();
+        for i in 0..1000usize{
+            buffer.clear();
+            assert!(buffer.is_empty());
+            buffer.write(&vec![i as u8; i % 900]);
+            buffer.write(&String::from("message"));
+            assert_eq!(capacity, buffer.capacity());
+            assert_eq!(ptr, buffer.as_slice().as_ptr());
+            let mut read = ReadBuffer::from_raw(buffer.as_slice().to_vec());
+            assert_eq!(Some(vec![i as u8; i % 900]), Vec::<u8>::from_buffer(&mut read));
+        }
+        let mut small = WriteBuffer::new();
+        small.write(&1u64);
+        small.clear();
+        assert!(small.is_inline() && small.is_empty());
+        assert_eq!(128, small.capacity());
+    }
+    #[test]
+    fn test_write_buffer_spill(){
+        let x = (vec![3u32; 100],String::from("spilled"));
+        let mut buffer = WriteBuffer::new();
+        buffer.write(&1u8);
+        buffer.write(&x);
+        assert!(!buffer.is_inline());
+        let mut buffer = ReadBuffer::from_raw(buffer.into_raw());
+        assert_eq!(Some(1), u8::from_buffer(&mut buffer));
+        assert_eq!(Some(x), <(Vec<u32>,String)>::from_buffer(&mut buffer));
+        assert!(WriteBuffer::with_capacity(64).is_inline());
+        assert!(!WriteBuffer::with_capacity(1000).is_inline());
+    }
+    #[test]
+    fn test_write_buffer_write_at(){
+        for size in [10usize, 1000].iter(){
+            let mut buffer = WriteBuffer::new();
+            buffer.write(&0u64);
+            buffer.write(&vec![7u8; *size]);
+            buffer.write(&0xffffu16);
+            let len = buffer.len();
+            assert!(buffer.write_at(0, &(len as u64)));
+            assert!(buffer.write_at(len - 2, &1u16));
+            assert!(!buffer.write_at(len - 1, &1u16));
+            assert!(!buffer.write_at(usize::MAX, &1u8));
+            assert_eq!(len, buffer.len());
+            let mut read = ReadBuffer::from_raw(buffer.into_raw());
+            assert_eq!(Some(len as u64), u64::from_buffer(&mut read));
+            assert_eq!(Some(vec![7u8; *size]), Vec::<u8>::from_buffer(&mut read));
+            assert_eq!(Some(1), u16::from_buffer(&mut read));
+        }
+    }
+    #[test]
+    fn test_write_buffer_len_prefix(){
+        // nested chunks, the outer one spilling to the heap
+        let mut buffer = WriteBuffer::new();
+        let outer = buffer.reserve_len_prefix();
+        buffer.write(&String::from("head"));
+        let inner = buffer.reserve_len_prefix();
+        buffer.write(&vec![1u16; 100]);
+        assert!(buffer.finalize(inner));
+        buffer.write(&9u8);
+        assert!(buffer.finalize(outer));
+        let mut read = ReadBuffer::from_raw(buffer.into_raw());
+        let chunk = read.read_blob().unwrap().to_vec();
+        assert_eq!(0, read.remaining());
+        let mut read = ReadBuffer::from_raw(chunk);
+        assert_eq!(Some("head"), read.read_str());
+        assert_eq!(8 + 200, read.read_blob().unwrap().len());
+        assert_eq!(Some(9), u8::from_buffer(&mut read));
+        // an empty chunk, and one that was cleared away
+        let mut buffer = WriteBuffer::new();
+        let prefix = buffer.reserve_len_prefix();
+        assert!(buffer.finalize(prefix));
+        assert_eq!(&[0; 8], buffer.as_slice());
+        let prefix = buffer.reserve_len_prefix();
+        buffer.clear();
+        assert!(!buffer.finalize(prefix));
+        // also when the buffer is longer again than where the placeholder was
+        let prefix = buffer.reserve_len_prefix();
+        buffer.clear();
+        buffer.write(&[1u8; 20]);
+        assert!(!buffer.finalize(prefix));
+        assert_eq!(&[1; 20], buffer.as_slice());
+    }
+}
@@ -0,0 +1,145 @@
+//! Values as binary WebSocket messages, enabled with the `tungstenite` feature.
+//! Every value goes in its own binary message, which already carries its length, so unlike
+//! net there is no frame around it. to_message and from_message convert single messages,
+//! WebSocketExt sends and receives values on a tungstenite WebSocket, skipping pings, pongs
+//! and the close message. A text message where a value is expected fails with InvalidData,
+//! like a binary message that does not hold exactly one value.
+//! With the `tokio-tungstenite` feature send_value_async and recv_value_async do the same
+//! on a tokio-tungstenite WebSocketStream, or either half of one that is split.
+//! # Example
+//! ```
+//! use bin_buffer::ws::*;
+//! use std::net::{ TcpListener, TcpStream };
+//! use tungstenite::WebSocket;
+//! use tungstenite::protocol::Role;
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+//! let (server, _) = listener.accept().unwrap();
+//! // after the http upgrade, which tungstenite::accept and tungstenite::connect do
+//! let mut client = WebSocket::from_raw_socket(client, Role::Client, None);
+//! let mut server = WebSocket::from_raw_socket(server, Role::Server, None);
+//! client.send_value(&(3u8, String::from("move"))).unwrap();
+//! assert_eq!((3u8, String::from("move")), server.recv_value::<(u8, String)>().unwrap());
+//! ```
+use std::io::{ self, Read, Write };
+use crate::Bufferable;
+use tungstenite::{ Error, WebSocket };
+pub use tungstenite::Message;
+
+fn invalid(text: &str) -> Error{
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, text))
+}
+
+/// A binary message holding x.
+pub fn to_message<T: Bufferable>(x: &T) -> Message{
+    Message::binary(crate::encode(x))
+}
+
+/// The value in a binary message, None for pings, pongs, close and raw frames.
+/// Fails with InvalidData for a text message or one that does not hold exactly one T.
+pub fn from_message<T: Bufferable>(msg: &Message) -> Result<Option<T>, Error>{
+    match msg{
+        Message::Binary(bytes) => crate::decode(bytes).map(Option::Some)
+            .ok_or_else(|| invalid("message does not hold the expected value")),
+        Message::Text(_) => Err(invalid("text message where a binary one was expected")),
+        _ => Ok(Option::None),
+    }
+}
+
+/// Sending and receiving values on a WebSocket.
+pub trait WebSocketExt{
+    /// Send x as one binary message.
+    fn send_value<T: Bufferable>(&mut self, x: &T) -> Result<(), Error>;
+    /// Receive the next binary message and decode it.
+    /// Fails with ConnectionClosed once the other side closed the connection.
+    fn recv_value<T: Bufferable>(&mut self) -> Result<T, Error>;
+}
+
+impl<S: Read + Write> WebSocketExt for WebSocket<S>{
+    fn send_value<T: Bufferable>(&mut self, x: &T) -> Result<(), Error>{
+        self.send(to_message(x))
+    }
+
+    fn recv_value<T: Bufferable>(&mut self) -> Result<T, Error>{
+        loop{
+            if let Option::Some(x) = from_message(&self.read()?)?{
+                return Ok(x);
+            }
+        }
+    }
+}
+
+/// Send x as one binary message on an async WebSocket, or the sending half of one.
+#[cfg(feature = "tokio-tungstenite")]
+pub async fn send_value_async<W, T>(ws: &mut W, x: &T) -> Result<(), Error>
+    where W: futures_util::Sink<Message, Error = Error> + Unpin, T: Bufferable
+{
+    use futures_util::SinkExt;
+    ws.send(to_message(x)).await
+}
+
+/// Receive the next binary message on an async WebSocket, or the receiving half of one,
+/// and decode it. None once the stream ends.
+#[cfg(feature = "tokio-tungstenite")]
+pub async fn recv_value_async<R, T>(ws: &mut R) -> Result<Option<T>, Error>
+    where R: futures_util::Stream<Item = Result<Message, Error>> + Unpin, T: Bufferable
+{
+    use futures_util::StreamExt;
+    while let Option::Some(msg) = ws.next().await{
+        if let Option::Some(x) = from_message(&msg?)?{
+            return Ok(Option::Some(x));
+        }
+    }
+    Ok(Option::None)
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ws::*;
+    use std::net::{ TcpListener, TcpStream };
+    use tungstenite::protocol::Role;
+
+    #[test]
+    fn test_ws(){
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let mut client = WebSocket::from_raw_socket(client, Role::Client, None);
+        let mut server = WebSocket::from_raw_socket(server, Role::Server, None);
+        client.send(Message::Ping(Default::default())).unwrap();
+        client.send_value(&vec![1u32, 2, 3]).unwrap();
+        client.send(Message::text("hi")).unwrap();
+        client.send_value(&5u16).unwrap();
+        client.close(None).unwrap();
+        assert_eq!(vec![1u32, 2, 3], server.recv_value::<Vec<u32>>().unwrap());
+        let err = server.recv_value::<u16>().unwrap_err();
+        assert!(matches!(err, Error::Io(ref e) if e.kind() == io::ErrorKind::InvalidData));
+        let err = server.recv_value::<u32>().unwrap_err();
+        assert!(matches!(err, Error::Io(ref e) if e.kind() == io::ErrorKind::InvalidData));
+        assert!(matches!(server.recv_value::<u16>(), Err(Error::ConnectionClosed)));
+        assert_eq!(Some(7u8), from_message(&to_message(&7u8)).unwrap());
+        assert_eq!(None, from_message::<u8>(&Message::Pong(Default::default())).unwrap());
+    }
+
+    #[cfg(feature = "tokio-tungstenite")]
+    #[test]
+    fn test_ws_async(){
+        use futures_util::FutureExt;
+        use std::cell::RefCell;
+        let sent = RefCell::new(Vec::new());
+        let mut sink = Box::pin(futures_util::sink::unfold((), |_, msg|{
+            sent.borrow_mut().push(msg);
+            async { Ok::<_, Error>(()) }
+        }));
+        send_value_async(&mut sink, &String::from("a")).now_or_never().unwrap().unwrap();
+        send_value_async(&mut sink, &String::from("b")).now_or_never().unwrap().unwrap();
+        drop(sink);
+        let mut sent = sent.into_inner();
+        sent.insert(1, Message::Pong(Default::default()));
+        let mut stream = futures_util::stream::iter(sent.into_iter().map(Ok));
+        let next = |s: &mut _| recv_value_async::<_, String>(s).now_or_never().unwrap().unwrap();
+        assert_eq!(Some(String::from("a")), next(&mut stream));
+        assert_eq!(Some(String::from("b")), next(&mut stream));
+        assert_eq!(None, next(&mut stream));
+    }
+}